@@ -1,18 +1,31 @@
 use crate::{
+    agents::{AgentMessage, AgentRole, AgentTeam},
     artifact::{ArtifactManager, ArtifactType},
+    checkpoint::{Checkpoint, CheckpointStore},
+    convergence::{artifact_fingerprint, ProgressTracker},
     config::Config,
-    context::ContextManager,
+    context::{ContextManager, ContextSource},
     event_bus::{Event, EventBus},
     executor::{Executor, StepResult},
+    git::{self, GitBranch},
+    github,
     interpreter::Interpreter,
     iteration_context::{FileInfo, IterationContext},
+    iteration_report::{next_attempt, IterationReport},
     llm_manager::LLMManager,
+    memory::{self, MemoryKind, MemoryStore, MEMORIES_RECALLED_EVENT, MEMORY_RECORDED_EVENT},
     planner::{Plan, Planner},
     reviewer::{IssueSeverity, ReviewResult, Reviewer},
+    run_control::{RunControl, APPROVAL_REQUESTED_EVENT, GUIDANCE_REQUESTED_EVENT},
+    run_record::{IterationRecord, StepRecord},
+    tool_loop::ToolLoop,
     CommandKind,
 };
 use anyhow::Result;
+use clap::ValueEnum;
 use log::{error, info, warn};
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
 /// Controls the iterative planning-action-review cycle
@@ -28,6 +41,32 @@ pub struct AgenticLoop {
     context_manager: Option<Arc<ContextManager>>,
     config: Option<Arc<Config>>,
     command: Option<CommandKind>,
+    /// Repository to branch and commit in, when git integration is enabled
+    git_root: Option<std::path::PathBuf>,
+    /// Plans and step outcomes of the iterations run so far
+    history: std::sync::Mutex<Vec<IterationRecord>>,
+    /// Pause and cancel requests, checked before each iteration and step
+    control: Option<Arc<RunControl>>,
+    /// Wait for each plan to be approved through `control` before executing it
+    require_approval: bool,
+    /// GitHub issue the pull request closes
+    closes_issue: Option<u64>,
+    /// URL of the pull request opened for the run's branch
+    pull_request: std::sync::Mutex<Option<String>>,
+    /// Saves the loop's state after every step
+    checkpoints: Option<Arc<CheckpointStore>>,
+    /// Checkpoint of an interrupted run to continue from
+    resume: std::sync::Mutex<Option<Checkpoint>>,
+    /// Why the loop stopped early, when it stopped making progress
+    stalled: std::sync::Mutex<Option<String>>,
+    /// Work each iteration with tools instead of planning it up front
+    tools_mode: bool,
+    /// Specialized agents planning, implementing, testing and reviewing, in the agents mode
+    agents: Option<Arc<AgentTeam>>,
+    /// Run directory to write a report of every iteration into
+    report_dir: Option<std::path::PathBuf>,
+    /// Decisions, conventions and corrections remembered across runs
+    memory: Option<Arc<MemoryStore>>,
 }
 
 impl AgenticLoop {
@@ -48,12 +87,26 @@ impl AgenticLoop {
             context_manager: None,
             config: None,
             command: None,
+            git_root: None,
+            history: std::sync::Mutex::new(Vec::new()),
+            control: None,
+            require_approval: false,
+            closes_issue: None,
+            pull_request: std::sync::Mutex::new(None),
+            checkpoints: None,
+            resume: std::sync::Mutex::new(None),
+            stalled: std::sync::Mutex::new(None),
+            tools_mode: false,
+            agents: None,
+            report_dir: None,
+            memory: None,
         }
     }
 
     #[allow(dead_code)]
     pub fn with_artifact_manager(mut self, manager: Arc<ArtifactManager>) -> Self {
         self.executor = self.executor.with_artifact_manager(manager.clone());
+        self.reviewer = self.reviewer.with_artifact_manager(manager.clone());
         self.artifact_manager = Some(manager);
         self
     }
@@ -70,28 +123,179 @@ impl AgenticLoop {
     }
 
     pub fn with_config(mut self, config: Arc<Config>) -> Self {
+        self.executor = self.executor.with_config(config.clone());
+        self.reviewer = self
+            .reviewer
+            .with_block_on(&config.review.block_on)
+            .with_rubrics(config.review.rubrics.clone());
+        if config.review.diff_only {
+            self.reviewer = self
+                .reviewer
+                .with_diff_only(config.review.diff_context_lines);
+        }
         self.config = Some(config);
         self
     }
 
+    pub fn with_transcript_dir(mut self, dir: std::path::PathBuf) -> Self {
+        self.executor = self.executor.with_transcript_dir(dir);
+        self
+    }
+
+    /// Write a report of each iteration to `iterations/<n>.md` in `run_dir`
+    pub fn with_iteration_reports(mut self, run_dir: std::path::PathBuf) -> Self {
+        self.report_dir = Some(run_dir);
+        self
+    }
+
+    /// Recall project memories for the planner, and record new ones in `store`
+    pub fn with_memory(mut self, store: Arc<MemoryStore>) -> Self {
+        self.memory = Some(store);
+        self
+    }
+
+    pub fn with_linters(
+        mut self,
+        root: std::path::PathBuf,
+        timeout: std::time::Duration,
+        sandbox: Option<crate::sandbox::Sandbox>,
+    ) -> Self {
+        self.reviewer = self.reviewer.with_linters(root, timeout, sandbox);
+        self
+    }
+
+    pub fn with_known_issues(mut self, issues: Vec<crate::reviewer::Issue>) -> Self {
+        self.reviewer = self.reviewer.with_known_issues(issues);
+        self
+    }
+
+    pub fn with_second_opinion(mut self, llm_manager: Arc<LLMManager>) -> Self {
+        self.reviewer = self.reviewer.with_second_opinion(llm_manager);
+        self
+    }
+
+    pub fn with_lsp(mut self, lsp: Arc<crate::lsp::LspDiagnostics>) -> Self {
+        self.reviewer = self.reviewer.with_lsp(lsp);
+        self
+    }
+
+    /// Retrieve relevant code per step from `index` instead of relying on scanned files in context
+    pub fn with_retrieval(mut self, index: Arc<crate::retrieval::RetrievalIndex>, top_k: usize) -> Self {
+        self.executor = self.executor.with_retrieval(index, top_k);
+        self
+    }
+
+    /// Pull definitions of symbols referenced by each step from `index`
+    pub fn with_code_index(mut self, index: Arc<crate::code_index::CodeIndex>) -> Self {
+        self.executor = self.executor.with_code_index(index);
+        self
+    }
+
+    pub fn with_mcp_tools(mut self, tools: Arc<crate::mcp_client::McpTools>) -> Self {
+        self.executor = self.executor.with_mcp_tools(tools);
+        self
+    }
+
+    /// Sample high-risk steps, and retries of failed ones, with `sampler`
+    pub fn with_sampler(mut self, sampler: Arc<crate::sampling::Sampler>) -> Self {
+        self.executor = self.executor.with_sampler(sampler);
+        self
+    }
+
+    /// Close GitHub issue `number` when the run's pull request is merged
+    pub fn with_closing_issue(mut self, number: u64) -> Self {
+        self.closes_issue = Some(number);
+        self
+    }
+
+    /// Work on a new branch in the repository at `root` and commit after each successful iteration
+    pub fn with_git(mut self, root: std::path::PathBuf) -> Self {
+        self.git_root = Some(root);
+        self
+    }
+
+    /// Let `control` pause, cancel and, with `require_approval`, approve each plan of the run
+    pub fn with_control(mut self, control: Arc<RunControl>, require_approval: bool) -> Self {
+        self.executor = self.executor.with_control(control.clone());
+        self.control = Some(control);
+        self.require_approval = require_approval;
+        self
+    }
+
+    /// Let the model work on each iteration with `tools` until it is done,
+    /// instead of planning the iteration
+    pub fn with_tools(mut self, tools: Arc<ToolLoop>) -> Self {
+        self.executor = self.executor.with_tools(tools);
+        self.tools_mode = true;
+        self
+    }
+
+    /// Plan, implement, test and review with the specialized agents of `team`
+    pub fn with_agents(mut self, team: Arc<AgentTeam>) -> Self {
+        self.executor = self.executor.with_agents(team.clone());
+        self.agents = Some(team);
+        self
+    }
+
+    /// Save the loop's state to `store` after every step and, given the
+    /// checkpoint of an interrupted run, continue where it stopped
+    pub fn with_checkpoints(mut self, store: Arc<CheckpointStore>, resume: Option<Checkpoint>) -> Self {
+        self.executor = self.executor.with_checkpoints(store.clone());
+        if let Some(checkpoint) = &resume {
+            self.executor = self.executor.with_first_iteration(checkpoint.iteration);
+        }
+        self.checkpoints = Some(store);
+        self.resume = std::sync::Mutex::new(resume);
+        self
+    }
+
     pub fn with_command(mut self, command: CommandKind) -> Self {
         self.executor = self.executor.with_command(command.clone());
+        if let Some(name) = command.to_possible_value() {
+            self.reviewer = self.reviewer.with_command(name.get_name());
+        }
         self.command = Some(command);
         self
     }
 
-    /// Run the agentic loop on the given input
-    pub async fn run(&self, input: &str, context_id: &str) -> Result<()> {
+    /// Plans and step outcomes of the iterations run so far
+    pub fn history(&self) -> Vec<IterationRecord> {
+        self.history.lock().unwrap().clone()
+    }
+
+    /// URL of the pull request opened at the end of the run, if any
+    pub fn pull_request(&self) -> Option<String> {
+        self.pull_request.lock().unwrap().clone()
+    }
+
+    /// The iterations allowed once `plan` is made, if they are more than
+    /// `current`, the limit so far
+    fn iteration_limit(&self, plan: &Plan, current: Option<usize>) -> Option<usize> {
+        // The tool session's plan does not rate the task
+        if self.tools_mode {
+            return None;
+        }
+        let budget = &self.config.as_ref()?.execution.iteration_budget;
+        budget.ratchet(current, &plan.estimated_complexity, self.max_iterations)
+    }
+
+    /// Why the loop gave up before `max_iterations`, if it stalled
+    pub fn stalled(&self) -> Option<String> {
+        self.stalled.lock().unwrap().clone()
+    }
+
+    /// Run the task to completion or until the iteration limit. Returns the last review.
+    pub async fn run(&self, input: &str, context_id: &str) -> Result<Option<ReviewResult>> {
         info!("Starting agentic loop for input: {}", input);
 
         // Interpret the task
         let task = self.interpreter.interpret(input)?;
         info!("Interpreted task: {}", task.description);
 
-        // Add initial task to context
+        // Add initial task to context, pinned so its constraints are never summarized away
         if let Some(ctx_mgr) = &self.context_manager {
             ctx_mgr
-                .add_message(context_id, "user".to_string(), input.to_string())
+                .add_pinned_message(context_id, "user".to_string(), input.to_string())
                 .await?;
             ctx_mgr
                 .add_message(
@@ -105,19 +309,54 @@ impl AgenticLoop {
                 .await?;
         }
 
+        let git_branch = self.start_git_branch(&task.description).await;
+
         let mut iteration = 0;
-        let mut _last_review: Option<ReviewResult> = None;
+        let mut last_review: Option<ReviewResult> = None;
         let mut iteration_context: Option<IterationContext> = None;
+        let mut progress = ProgressTracker::new(
+            self.config
+                .as_ref()
+                .map_or(0, |config| config.execution.stall_iterations),
+        );
+        // The plan an interrupted run was executing, with the steps it finished
+        let mut resumed_plan: Option<(Plan, Vec<StepResult>)> = None;
 
-        while iteration < self.max_iterations {
+        let resume = self.resume.lock().unwrap().take();
+        if let Some(checkpoint) = resume {
+            info!("Resuming interrupted run {} at {}", checkpoint.run_id, checkpoint.progress());
+            self.event_bus
+                .emit(Event::Custom {
+                    event_type: "checkpoint_restored".to_string(),
+                    data: serde_json::json!({
+                        "run_id": checkpoint.run_id,
+                        "iteration": checkpoint.iteration,
+                        "completed_steps": checkpoint.step_results.len(),
+                    }),
+                })
+                .await?;
+            iteration = checkpoint.iteration.saturating_sub(1);
+            iteration_context = checkpoint.iteration_context;
+            *self.history.lock().unwrap() = checkpoint.history;
+            resumed_plan = checkpoint.plan.map(|plan| (plan, checkpoint.step_results));
+        }
+
+        let memories = self.recall_memories(&task.description).await?;
+
+        // Lowered to the budget of the task's complexity once it is planned; the
+        // most complex plan so far sets it, so a small follow-up plan never shortens it
+        let mut budget: Option<usize> = None;
+        let mut max_iterations = self.max_iterations;
+        while iteration < max_iterations {
             iteration += 1;
-            info!("Starting iteration {}/{}", iteration, self.max_iterations);
+            info!("Starting iteration {}/{}", iteration, max_iterations);
 
             // Create or update iteration context
             let mut current_context = iteration_context
                 .take()
                 .unwrap_or_else(|| IterationContext::new(iteration));
             current_context.iteration = iteration;
+            current_context.memories = memories.clone();
 
             info!(
                 "Starting iteration {} with {} existing files",
@@ -134,44 +373,107 @@ impl AgenticLoop {
                     event_type: "iteration_started".to_string(),
                     data: serde_json::json!({
                         "iteration": iteration,
-                        "max_iterations": self.max_iterations,
+                        "max_iterations": max_iterations,
                         "has_existing_files": current_context.has_existing_files(),
                     }),
                 })
                 .await?;
 
-            // Plan the task
-            info!("Creating plan for task...");
-            let plan = match self
-                .planner
-                .plan(
-                    &task,
-                    &*self.llm_manager,
-                    self.config.as_deref(),
-                    Some(&current_context),
-                )
-                .await
-            {
-                Ok(p) => p,
-                Err(e) => {
-                    error!("Planning failed: {}", e);
-                    self.emit_task_failed("Planning failed", &e.to_string())
-                        .await?;
-                    return Err(e);
+            if let Some(control) = &self.control {
+                control.checkpoint().await?;
+            }
+
+            // Guidance steers the planning of this iteration and every later one
+            let asks_for_guidance = self
+                .config
+                .as_ref()
+                .is_some_and(|config| config.execution.ask_for_guidance);
+            if asks_for_guidance && iteration > 1 && resumed_plan.is_none() {
+                self.wait_for_guidance(iteration).await?;
+            }
+            self.apply_guidance(iteration, &mut current_context).await?;
+
+            if let Some(checkpoints) = &self.checkpoints {
+                checkpoints.start_iteration(iteration, &current_context, self.history());
+            }
+
+            // Hand edits made since the last iteration replace the generated content
+            self.reload_modified_artifacts(context_id).await;
+
+            // An interrupted run continues with its approved plan
+            let (plan, completed) = match resumed_plan.take() {
+                Some(resumed) => resumed,
+                // The tool session is the whole iteration
+                None if self.tools_mode => (ToolLoop::plan(&task, &current_context), Vec::new()),
+                None => {
+                    // Plan the task
+                    info!("Creating plan for task...");
+                    let plan = match self
+                        .planner
+                        .plan(
+                            &task,
+                            self.llm(AgentRole::Architect),
+                            self.config.as_deref(),
+                            Some(&current_context),
+                        )
+                        .await
+                    {
+                        Ok(p) => p,
+                        Err(e) => {
+                            error!("Planning failed: {}", e);
+                            self.emit_task_failed("Planning failed", &e.to_string())
+                                .await?;
+                            return Err(e);
+                        }
+                    };
+
+                    info!(
+                        "Plan created with {} steps, complexity: {:?}",
+                        plan.steps.len(),
+                        plan.estimated_complexity
+                    );
+
+                    if self.require_approval {
+                        self.wait_for_approval(iteration, &plan).await?;
+                    }
+                    // Steps the plan repeats from earlier iterations are not run again
+                    let carried = current_context.carried_forward(&plan);
+                    if !carried.is_empty() {
+                        info!("Carrying forward {} completed steps", carried.len());
+                    }
+                    (plan, carried)
                 }
             };
-
-            info!(
-                "Plan created with {} steps, complexity: {:?}",
-                plan.steps.len(),
-                plan.estimated_complexity
-            );
+            if let Some(checkpoints) = &self.checkpoints {
+                checkpoints.set_plan(&plan, &completed);
+            }
+            if let Some(limit) = self.iteration_limit(&plan, budget) {
+                info!(
+                    "Allowing {} iterations for a task of {:?} complexity",
+                    limit, plan.estimated_complexity
+                );
+                budget = Some(limit);
+                max_iterations = limit;
+                self.event_bus
+                    .emit(Event::Custom {
+                        event_type: "iteration_budget".to_string(),
+                        data: serde_json::json!({
+                            "complexity": format!("{:?}", plan.estimated_complexity),
+                            "max_iterations": max_iterations,
+                        }),
+                    })
+                    .await?;
+            }
+            self.hand_out_plan(context_id, iteration, &plan).await;
 
             // Execute the plan
             info!("Executing plan...");
-            let results = match self.executor.execute(&plan, context_id).await {
+            let results = match self.executor.execute(&plan, context_id, completed).await {
                 Ok(r) => r,
                 Err(e) => {
+                    self.record_iteration(iteration, &plan, &[]);
+                    let next = format!("Nothing: the execution failed: {:#}", e);
+                    self.report_iteration(iteration, &plan, &[], &HashMap::new(), None, next);
                     error!("Execution failed: {}", e);
                     self.emit_task_failed("Execution failed", &e.to_string())
                         .await?;
@@ -179,6 +481,8 @@ impl AgenticLoop {
                 }
             };
 
+            self.record_iteration(iteration, &plan, &results);
+
             // Count successful steps
             let successful_steps = results.iter().filter(|r| r.success).count();
             info!(
@@ -186,8 +490,32 @@ impl AgenticLoop {
                 successful_steps,
                 results.len()
             );
+            if self.agents.is_some() {
+                let mut content = format!("{}/{} steps succeeded", successful_steps, results.len());
+                for result in &results {
+                    let Some(step) = plan.steps.iter().find(|step| step.id == result.step_id) else {
+                        continue;
+                    };
+                    let outcome = match &result.error {
+                        Some(error) => format!("failed: {}", error),
+                        None if result.success => "done".to_string(),
+                        None => "failed".to_string(),
+                    };
+                    content.push_str(&format!("\n- {}: {}", step.description, outcome));
+                }
+                self.send_agent_message(
+                    context_id,
+                    AgentRole::Implementer,
+                    AgentRole::Reviewer,
+                    "results",
+                    iteration,
+                    content,
+                )
+                .await;
+            }
 
             // Update iteration context with created artifacts
+            let mut file_names = HashMap::new();
             if let Some(artifact_mgr) = &self.artifact_manager {
                 let artifacts = artifact_mgr.list_artifacts().await;
                 info!(
@@ -195,6 +523,7 @@ impl AgenticLoop {
                     artifacts.len()
                 );
                 for artifact in artifacts {
+                    file_names.insert(artifact.id.clone(), artifact.name.clone());
                     let path = artifact.name.clone();
                     if !current_context.existing_files.contains_key(&path) {
                         info!("Adding artifact to iteration context: {}", path);
@@ -227,16 +556,22 @@ impl AgenticLoop {
                     current_context.existing_files.len()
                 );
             }
+            // The tool session is redone whole each iteration
+            if !self.tools_mode {
+                current_context.record_steps(&plan, &results, &file_names);
+            }
 
             // Review the results
             info!("Reviewing execution results...");
-            let review = match self
+            let mut review = match self
                 .reviewer
-                .review(&plan, &results, &*self.llm_manager, context_id)
+                .review(&plan, &results, self.llm(AgentRole::Reviewer), context_id)
                 .await
             {
                 Ok(r) => r,
                 Err(e) => {
+                    let next = format!("Nothing: the review failed: {:#}", e);
+                    self.report_iteration(iteration, &plan, &results, &file_names, None, next);
                     error!("Review failed: {}", e);
                     self.emit_task_failed("Review failed", &e.to_string())
                         .await?;
@@ -245,21 +580,62 @@ impl AgenticLoop {
             };
 
             info!("Review complete: {}", review.summary);
+            if let Some(record) = self.history.lock().unwrap().last_mut() {
+                record.review = Some(review.summary.clone());
+            }
+
+            // Apply reviewer patches for trivial issues so they don't need a new iteration
+            let patched = self.apply_review_patches(&mut review).await;
+            if patched > 0 {
+                info!("Applied {} reviewer patches for trivial issues", patched);
+                if !review.ready_to_deploy && review.issues.is_empty() {
+                    info!("All remaining issues were fixed by reviewer patches");
+                    review.ready_to_deploy = true;
+                }
+            }
 
             // Log the actual issues found
             if !review.issues.is_empty() {
                 info!("Issues found during review:");
                 for issue in &review.issues {
-                    info!(
-                        "  - [{}] {:?}: {}",
-                        issue.severity, issue.category, issue.description
-                    );
+                    match &issue.location {
+                        Some(location) => info!(
+                            "  - [{}] {:?} {}: {}",
+                            issue.severity, issue.category, location, issue.description
+                        ),
+                        None => info!(
+                            "  - [{}] {:?}: {}",
+                            issue.severity, issue.category, issue.description
+                        ),
+                    }
                     if let Some(suggestion) = &issue.suggestion {
                         info!("    Suggestion: {}", suggestion);
                     }
                 }
             }
 
+            if self.agents.is_some() {
+                let mut content = review.summary.clone();
+                for issue in &review.issues {
+                    content.push_str(&format!("\n- [{}] {}", issue.severity, issue.description));
+                }
+                self.send_agent_message(
+                    context_id,
+                    AgentRole::Reviewer,
+                    AgentRole::Architect,
+                    "review",
+                    iteration,
+                    content,
+                )
+                .await;
+            }
+
+            if let Some(branch) = &git_branch
+                && successful_steps == results.len()
+            {
+                self.commit_iteration(branch, iteration, &plan, &review).await;
+            }
+
             // Update iteration context with review results
             current_context.update_from_review(review.clone());
             current_context.progress_summary = format!(
@@ -270,6 +646,9 @@ impl AgenticLoop {
             // Check if we're done
             if review.ready_to_deploy {
                 info!("Task completed successfully!");
+                let next = "Nothing: the review approved the work, so the run ends.".to_string();
+                self.report_iteration(iteration, &plan, &results, &file_names, Some(&review), next);
+                self.learn_from_run(&task.description, &plan, &review).await?;
 
                 // Post-process artifacts to clean up and organize
                 if let Some(artifact_mgr) = &self.artifact_manager {
@@ -278,13 +657,21 @@ impl AgenticLoop {
                     }
                 }
 
+                if let Some(branch) = &git_branch {
+                    self.open_pull_request(branch, &plan, &review).await;
+                }
+
                 self.emit_task_completed(&plan, &results, &review).await?;
-                return Ok(());
+                return Ok(Some(review));
             }
 
+            last_review = Some(review.clone());
+
             // Check if we should continue
-            if iteration >= self.max_iterations {
+            if iteration >= max_iterations {
                 warn!("Max iterations reached without completing task");
+                let next = format!("Nothing: the limit of {} iterations was reached.", iteration);
+                self.report_iteration(iteration, &plan, &results, &file_names, Some(&review), next);
                 self.emit_task_failed(
                     "Max iterations reached",
                     &format!("Failed to complete task after {} iterations", iteration),
@@ -293,6 +680,29 @@ impl AgenticLoop {
                 break;
             }
 
+            // Stop rather than spend the remaining iterations going in circles
+            if let Some(reason) = progress.observe(self.artifact_fingerprint().await, &review) {
+                warn!("Stopping early, the task stalled: {}", reason);
+                let next = format!("Nothing: the run stopped early, as {}.", reason);
+                self.report_iteration(iteration, &plan, &results, &file_names, Some(&review), next);
+                *self.stalled.lock().unwrap() = Some(reason.clone());
+                self.event_bus
+                    .emit(Event::Custom {
+                        event_type: "loop_stalled".to_string(),
+                        data: serde_json::json!({
+                            "iteration": iteration,
+                            "reason": reason,
+                        }),
+                    })
+                    .await?;
+                self.emit_task_failed(
+                    "Stalled",
+                    &format!("Stopped after {} iterations: {}", iteration, reason),
+                )
+                .await?;
+                return Ok(last_review);
+            }
+
             // Handle critical issues
             let critical_issues = review
                 .issues
@@ -307,6 +717,9 @@ impl AgenticLoop {
                 );
             }
 
+            let next = next_attempt(iteration + 1, &current_context);
+            self.report_iteration(iteration, &plan, &results, &file_names, Some(&review), next);
+
             // Store the context for the next iteration
             iteration_context = Some(current_context);
         }
@@ -318,9 +731,453 @@ impl AgenticLoop {
         )
         .await?;
 
+        Ok(last_review)
+    }
+
+    /// The model of `role`'s agent in the agents mode, otherwise the main one
+    fn llm(&self, role: AgentRole) -> &LLMManager {
+        match &self.agents {
+            Some(team) => team.llm(role),
+            None => &self.llm_manager,
+        }
+    }
+
+    /// Pass the architect's plan to the implementer, and its testing steps to the tester
+    async fn hand_out_plan(&self, context_id: &str, iteration: usize, plan: &Plan) {
+        if self.agents.is_none() {
+            return;
+        }
+        for to in [AgentRole::Implementer, AgentRole::Tester] {
+            let steps: Vec<String> = plan
+                .steps
+                .iter()
+                .filter(|step| AgentRole::for_step(&step.category) == to)
+                .map(|step| format!("{}: {}", step.id, step.description))
+                .collect();
+            if !steps.is_empty() {
+                let content = steps.join("\n");
+                self.send_agent_message(context_id, AgentRole::Architect, to, "plan", iteration, content)
+                    .await;
+            }
+        }
+    }
+
+    async fn send_agent_message(
+        &self,
+        context_id: &str,
+        from: AgentRole,
+        to: AgentRole,
+        kind: &str,
+        iteration: usize,
+        content: String,
+    ) {
+        if let Some(team) = &self.agents {
+            let message = AgentMessage {
+                from,
+                to,
+                kind: kind.to_string(),
+                iteration,
+                content,
+            };
+            team.send(context_id, message).await;
+        }
+    }
+
+    /// Announce the plan and wait until it is approved or the run is cancelled
+    async fn wait_for_approval(&self, iteration: usize, plan: &Plan) -> Result<()> {
+        let Some(control) = &self.control else {
+            return Ok(());
+        };
+        let request = serde_json::json!({
+            "iteration": iteration,
+            "plan_goal": plan.goal,
+            "steps": plan.steps.iter().map(|s| &s.description).collect::<Vec<_>>(),
+        });
+        self.event_bus
+            .emit(Event::Custom {
+                event_type: APPROVAL_REQUESTED_EVENT.to_string(),
+                data: request.clone(),
+            })
+            .await?;
+        info!("Waiting for the plan to be approved...");
+        control.wait_for_approval(request).await
+    }
+
+    /// Announce that the next plan waits for guidance, and wait for it.
+    /// Guidance already sent, e.g. over IPC, is used without asking.
+    async fn wait_for_guidance(&self, iteration: usize) -> Result<()> {
+        let Some(control) = &self.control else {
+            return Ok(());
+        };
+        if control.has_guidance() {
+            return Ok(());
+        }
+        self.event_bus
+            .emit(Event::Custom {
+                event_type: GUIDANCE_REQUESTED_EVENT.to_string(),
+                data: serde_json::json!({ "iteration": iteration }),
+            })
+            .await?;
+        info!("Waiting for guidance before planning iteration {}...", iteration);
+        control.wait_for_guidance().await
+    }
+
+    /// Add the guidance received since the last iteration to `context`
+    async fn apply_guidance(&self, iteration: usize, context: &mut IterationContext) -> Result<()> {
+        let Some(control) = &self.control else {
+            return Ok(());
+        };
+        let guidance = control.take_guidance();
+        if guidance.is_empty() {
+            return Ok(());
+        }
+        for text in &guidance {
+            info!("Guidance for iteration {}: {}", iteration, text);
+        }
+        self.event_bus
+            .emit(Event::Custom {
+                event_type: "guidance_applied".to_string(),
+                data: serde_json::json!({
+                    "iteration": iteration,
+                    "guidance": guidance,
+                }),
+            })
+            .await?;
+        context.user_guidance.extend(guidance);
+        Ok(())
+    }
+
+    /// Fingerprint of the artifacts as they are on disk
+    async fn artifact_fingerprint(&self) -> u64 {
+        let Some(artifact_mgr) = &self.artifact_manager else {
+            return 0;
+        };
+        let artifacts: Vec<(String, String)> = artifact_mgr
+            .list_artifacts()
+            .await
+            .into_iter()
+            .map(|artifact| {
+                let content = std::fs::read_to_string(&artifact.path)
+                    .ok()
+                    .or(artifact.content)
+                    .unwrap_or_default();
+                (artifact.name, content)
+            })
+            .collect();
+        artifact_fingerprint(
+            artifacts
+                .iter()
+                .map(|(name, content)| (name.as_str(), content.as_str())),
+        )
+    }
+
+    /// Write the report of an iteration, when reports are enabled
+    fn report_iteration(
+        &self,
+        iteration: usize,
+        plan: &Plan,
+        results: &[StepResult],
+        file_names: &HashMap<String, String>,
+        review: Option<&ReviewResult>,
+        next: String,
+    ) {
+        let Some(dir) = &self.report_dir else {
+            return;
+        };
+        let report = IterationReport {
+            iteration,
+            plan,
+            results,
+            file_names,
+            review,
+            next,
+        };
+        match report.write_to(dir) {
+            Ok(path) => info!("Saved iteration report to {}", path.display()),
+            Err(e) => warn!("Failed to save iteration report: {:#}", e),
+        }
+    }
+
+    /// The memories relevant to `task`, as listed to the planner
+    async fn recall_memories(&self, task: &str) -> Result<Vec<String>> {
+        let (Some(store), Some(config)) = (&self.memory, &self.config) else {
+            return Ok(Vec::new());
+        };
+        let memories: Vec<String> = store
+            .recall(task, config.memory.top_k)
+            .await
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if !memories.is_empty() {
+            info!("Recalled {} memories for the task", memories.len());
+            self.event_bus
+                .emit(Event::Custom {
+                    event_type: MEMORIES_RECALLED_EVENT.to_string(),
+                    data: serde_json::json!({ "memories": memories }),
+                })
+                .await?;
+        }
+        Ok(memories)
+    }
+
+    /// Record `content` for later runs, unless it is already remembered
+    async fn remember(&self, kind: MemoryKind, content: &str) -> Result<()> {
+        let Some(store) = &self.memory else {
+            return Ok(());
+        };
+        match store.record(kind, content).await {
+            Ok(Some(memory)) => {
+                self.event_bus
+                    .emit(Event::Custom {
+                        event_type: MEMORY_RECORDED_EVENT.to_string(),
+                        data: serde_json::to_value(&memory).unwrap_or_default(),
+                    })
+                    .await?;
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to record memory: {:#}", e),
+        }
         Ok(())
     }
 
+    /// Ask the model which decisions and conventions of the finished run later runs should
+    /// know. Only approved runs are learned from, so a failed approach is not remembered.
+    async fn learn_from_run(&self, task: &str, plan: &Plan, review: &ReviewResult) -> Result<()> {
+        let (Some(store), Some(config)) = (&self.memory, &self.config) else {
+            return Ok(());
+        };
+        if !config.memory.learn || !review.ready_to_deploy {
+            return Ok(());
+        }
+        let known = store.recall(task, config.memory.top_k).await;
+        let prompt = memory::learning_prompt(task, plan, Some(review), &known);
+        let reply = match self.llm_manager.send_prompt(&prompt).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                warn!("Failed to ask what to remember from the run: {:#}", e);
+                return Ok(());
+            }
+        };
+        for (kind, content) in memory::parse_learned(&reply) {
+            self.remember(kind, &content).await?;
+        }
+        Ok(())
+    }
+
+    /// Add an iteration's plan and the outcome of each of its steps to the history
+    fn record_iteration(&self, iteration: usize, plan: &Plan, results: &[StepResult]) {
+        let steps = plan
+            .steps
+            .iter()
+            .map(|step| {
+                let result = results.iter().find(|r| r.step_id == step.id);
+                StepRecord {
+                    description: step.description.clone(),
+                    category: format!("{:?}", step.category),
+                    success: result.map(|r| r.success),
+                    error: result.and_then(|r| r.error.clone()),
+                    artifacts: result.map(|r| r.artifacts_created.clone()).unwrap_or_default(),
+                }
+            })
+            .collect();
+        self.history.lock().unwrap().push(IterationRecord {
+            iteration,
+            goal: plan.goal.clone(),
+            steps,
+            review: None,
+        });
+    }
+
+    /// Re-read artifacts the user edited on disk into context, so the next plan
+    /// builds on the edits instead of regenerating from the stale copy
+    async fn reload_modified_artifacts(&self, context_id: &str) {
+        let Some(artifact_mgr) = &self.artifact_manager else {
+            return;
+        };
+        let modified = match artifact_mgr.refresh_modified().await {
+            Ok(modified) => modified,
+            Err(e) => {
+                warn!("Failed to check artifacts for external edits: {}", e);
+                return;
+            }
+        };
+        let Some(ctx_mgr) = &self.context_manager else {
+            return;
+        };
+        for artifact in modified {
+            info!("Reloading hand-edited artifact into context: {}", artifact.name);
+            let message = format!(
+                "{} was edited by hand since it was generated. Keep these edits and build on this content:\n```\n{}\n```",
+                artifact.name,
+                artifact.content.unwrap_or_default()
+            );
+            if let Err(e) = ctx_mgr
+                .add_message_from(
+                    context_id,
+                    "user".to_string(),
+                    message,
+                    ContextSource::Codebase,
+                )
+                .await
+            {
+                warn!("Failed to add {} to context: {}", artifact.name, e);
+            }
+        }
+    }
+
+    async fn start_git_branch(&self, task: &str) -> Option<GitBranch> {
+        let root = self.git_root.as_ref()?;
+        // A branch that could never get a commit would only strand the user on it
+        if let Some(artifact_mgr) = &self.artifact_manager
+            && !self.commits_artifacts()
+        {
+            warn!(
+                "Git integration disabled: artifacts are written to {}, not to the repository at {}; \
+                 set `execution.artifact_dir = \".\"` to commit them",
+                artifact_mgr.artifact_dir().display(),
+                root.display()
+            );
+            return None;
+        }
+        let config = self.config.as_deref();
+        let prefix = config.map_or("cli-engineer/", |c| c.git.branch_prefix.as_str());
+        let allow_init = config.is_none_or(|c| !c.execution.disable_auto_git);
+        match GitBranch::start(root, prefix, task, allow_init).await {
+            Ok(branch) => {
+                let _ = self
+                    .event_bus
+                    .emit(Event::Custom {
+                        event_type: "git_branch_created".to_string(),
+                        data: serde_json::json!({
+                            "branch": branch.name,
+                            "base": branch.base,
+                        }),
+                    })
+                    .await;
+                Some(branch)
+            }
+            Err(e) => {
+                warn!("Git integration disabled: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Whether artifacts are written to the git repository itself. An artifact
+    /// directory apart from the project is where output waits for `apply`, so
+    /// nothing in it is committed.
+    fn commits_artifacts(&self) -> bool {
+        let Some(artifact_mgr) = &self.artifact_manager else {
+            return false;
+        };
+        let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        self.git_root.as_deref().map(canonical) == Some(canonical(artifact_mgr.artifact_dir()))
+    }
+
+    /// Commit the files written, deleted and renamed so far to the run's branch
+    async fn commit_iteration(
+        &self,
+        branch: &GitBranch,
+        iteration: usize,
+        plan: &Plan,
+        review: &ReviewResult,
+    ) {
+        let Some(artifact_mgr) = &self.artifact_manager else {
+            return;
+        };
+        if !self.commits_artifacts() {
+            return;
+        }
+        let paths = artifact_mgr.touched_paths().await;
+        let artifacts = artifact_mgr.list_artifacts().await;
+        let names: Vec<String> = artifacts.iter().map(|a| a.name.clone()).collect();
+        let message = git::commit_message(&plan.goal, iteration, &review.summary, &names);
+
+        match branch.commit(&paths, &message).await {
+            Ok(Some(commit)) => {
+                info!("Committed iteration {} to {} ({})", iteration, branch.name, commit);
+                let _ = self
+                    .event_bus
+                    .emit(Event::Custom {
+                        event_type: "git_commit".to_string(),
+                        data: serde_json::json!({
+                            "branch": branch.name,
+                            "commit": commit,
+                            "iteration": iteration,
+                        }),
+                    })
+                    .await;
+            }
+            Ok(None) => info!("No changes to commit for iteration {}", iteration),
+            Err(e) => warn!("Failed to commit iteration {}: {}", iteration, e),
+        }
+    }
+
+    /// Push the run's branch and open a GitHub pull request for it, when
+    /// configured for this command
+    async fn open_pull_request(&self, branch: &GitBranch, plan: &Plan, review: &ReviewResult) {
+        let Some(config) = self.config.as_deref() else {
+            return;
+        };
+        if !config.git.pull_request
+            || !matches!(self.command, Some(CommandKind::Code | CommandKind::Refactor))
+        {
+            return;
+        }
+        let artifacts = match &self.artifact_manager {
+            Some(artifact_mgr) => artifact_mgr
+                .list_artifacts()
+                .await
+                .into_iter()
+                .map(|a| a.name)
+                .collect(),
+            None => Vec::new(),
+        };
+        let (title, mut body) =
+            github::pull_request_text(&plan.goal, &self.history(), review, &artifacts);
+        if let Some(number) = self.closes_issue {
+            body = format!("Closes #{}\n\n{}", number, body);
+        }
+
+        let opened = async {
+            let token = github::github_token(&config.git)?;
+            let repo = match &config.git.github_repo {
+                Some(repo) => repo.clone(),
+                None => branch.github_repo(&config.git.remote).await?,
+            };
+            branch.push(&config.git.remote).await?;
+            github::create_pull_request(
+                &token,
+                &repo,
+                &branch.name,
+                &branch.base,
+                &title,
+                &body,
+                config.git.draft,
+            )
+            .await
+        };
+        match opened.await {
+            Ok(url) => {
+                info!("Opened pull request {}", url);
+                *self.pull_request.lock().unwrap() = Some(url.clone());
+                let _ = self
+                    .event_bus
+                    .emit(Event::Custom {
+                        event_type: "pull_request_created".to_string(),
+                        data: serde_json::json!({
+                            "url": url,
+                            "branch": branch.name,
+                            "base": branch.base,
+                        }),
+                    })
+                    .await;
+            }
+            Err(e) => warn!("Failed to open a pull request: {:#}", e),
+        }
+    }
+
     async fn emit_task_completed(
         &self,
         plan: &Plan,
@@ -370,6 +1227,77 @@ impl AgenticLoop {
         Ok(())
     }
 
+    /// Apply reviewer-supplied patches to the matching artifacts. Issues whose
+    /// patch applied cleanly are removed from the review; the rest are kept for
+    /// the next iteration to address normally.
+    async fn apply_review_patches(&self, review: &mut ReviewResult) -> usize {
+        let Some(artifact_mgr) = &self.artifact_manager else {
+            return 0;
+        };
+        if review.issues.iter().all(|i| i.patch.is_none()) {
+            return 0;
+        }
+
+        let mut patched = 0;
+        let mut remaining = Vec::new();
+        for issue in review.issues.drain(..) {
+            let Some(patch) = &issue.patch else {
+                remaining.push(issue);
+                continue;
+            };
+
+            // The latest artifact for the file, patched as it is on disk now: the
+            // cached content may predate later iterations or edits made by hand
+            let file = patch.file.trim_start_matches("./");
+            let artifact = artifact_mgr
+                .list_artifacts()
+                .await
+                .into_iter()
+                .rev()
+                .find(|a| a.name == file || a.path.ends_with(file));
+            let Some(artifact) = artifact else {
+                warn!("Reviewer patch targets unknown artifact: {}", patch.file);
+                remaining.push(issue);
+                continue;
+            };
+
+            let content = match tokio::fs::read_to_string(&artifact.path).await {
+                Ok(content) => content,
+                Err(e) => {
+                    warn!("Cannot read {} to apply a reviewer patch: {}", artifact.name, e);
+                    remaining.push(issue);
+                    continue;
+                }
+            };
+            // The patched text builds on what was just read, so it is no conflict
+            artifact_mgr
+                .record_snapshot(&artifact.path, content.clone())
+                .await;
+            match patch.apply(&content) {
+                Some(updated) => match artifact_mgr.update_artifact(&artifact.id, updated).await {
+                    Ok(()) => {
+                        info!("Patched {}: {}", artifact.name, issue.description);
+                        patched += 1;
+                    }
+                    Err(e) => {
+                        warn!("Failed to apply reviewer patch to {}: {}", artifact.name, e);
+                        remaining.push(issue);
+                    }
+                },
+                None => {
+                    warn!(
+                        "Reviewer patch for {} did not match exactly once; leaving issue for the next iteration",
+                        artifact.name
+                    );
+                    remaining.push(issue);
+                }
+            }
+        }
+
+        review.issues = remaining;
+        patched
+    }
+
     /// Post-process artifacts to clean up duplicates and organize files
     async fn post_process_artifacts(&self, artifact_mgr: &Arc<ArtifactManager>) -> Result<()> {
         info!("Post-processing artifacts...");
@@ -418,3 +1346,210 @@ impl AgenticLoop {
 
 // Note: EventEmitter trait implementation removed as AgenticLoop
 // doesn't directly emit events, it uses the event_bus
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_manager::LLMProvider;
+    use crate::providers::mock::{CassetteMode, MockProvider};
+    use crate::reviewer::{FixPatch, Issue, IssueCategory, IssueSeverity, QualityLevel};
+
+    fn artifact(content: &str) -> String {
+        format!(
+            "<artifact filename=\"hello.py\" type=\"python\">\n<![CDATA[\n{}\n]]>\n</artifact>",
+            content
+        )
+    }
+
+    #[tokio::test]
+    async fn test_loop_with_mock_provider() {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_loop_{}", uuid::Uuid::new_v4()));
+        let mock = MockProvider::new()
+            .then_respond("1. Implement hello.py printing a greeting")
+            .then_respond(&artifact("print('Helo, world')"))
+            .then_respond(
+                "QUALITY: fair\nREADY_TO_DEPLOY: no\nSUMMARY: The greeting is misspelled.\n\
+                 ISSUES:\n- SEVERITY: major | CATEGORY: logic | \
+                 DESCRIPTION: The greeting says Helo | LOCATION: hello.py:1",
+            )
+            .then_respond("1. Update hello.py to fix the greeting")
+            .then_respond(&artifact("print('Hello, world')"))
+            .then_respond("QUALITY: good\nREADY_TO_DEPLOY: yes\nSUMMARY: Prints the greeting.");
+        let prompts = mock.prompts();
+        let bus = Arc::new(EventBus::new(1000));
+        let llm = Arc::new(LLMManager::new(
+            vec![Box::new(mock)],
+            bus.clone(),
+            Arc::new(Config::default()),
+        ));
+        let artifacts = Arc::new(ArtifactManager::new(dir.clone()).unwrap());
+        let agentic_loop =
+            AgenticLoop::new(llm, 3, bus).with_artifact_manager(artifacts.clone());
+
+        let review = agentic_loop
+            .run("Write a hello world script in Python", "ctx")
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(review.ready_to_deploy);
+        assert_eq!(review.summary, "Prints the greeting.");
+        let content = std::fs::read_to_string(dir.join("hello.py")).unwrap();
+        assert_eq!(content.trim(), "print('Hello, world')");
+
+        // The second plan was asked to fix the issue the first review found
+        let prompts = prompts.lock().unwrap();
+        assert_eq!(prompts.len(), 6);
+        assert!(prompts[3].contains("The greeting says Helo"));
+        assert!(prompts[4].contains("Update hello.py to fix the greeting"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_review_patches_apply_to_the_file_on_disk() {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_loop_{}", uuid::Uuid::new_v4()));
+        let bus = Arc::new(EventBus::new(1000));
+        let llm = Arc::new(LLMManager::new(
+            vec![Box::new(MockProvider::new())],
+            bus.clone(),
+            Arc::new(Config::default()),
+        ));
+        let artifacts = Arc::new(ArtifactManager::new(dir.clone()).unwrap());
+        let artifact = artifacts
+            .create_artifact(
+                "lib.rs".to_string(),
+                crate::artifact::ArtifactType::SourceCode,
+                "fn a() {}\n".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        // Changed after the artifact was created
+        std::fs::write(&artifact.path, "fn a() {}\nfn b() {}\n").unwrap();
+
+        let agentic_loop = AgenticLoop::new(llm, 1, bus).with_artifact_manager(artifacts);
+        let mut review = ReviewResult {
+            overall_quality: QualityLevel::Fair,
+            issues: vec![Issue {
+                severity: IssueSeverity::Minor,
+                category: IssueCategory::CodeStyle,
+                description: "Rename b".to_string(),
+                location: Some("lib.rs:2".to_string()),
+                suggestion: None,
+                patch: Some(FixPatch {
+                    file: "lib.rs".to_string(),
+                    search: "fn b()".to_string(),
+                    replace: "fn c()".to_string(),
+                }),
+            }],
+            suggestions: Vec::new(),
+            ready_to_deploy: false,
+            summary: String::new(),
+        };
+        assert_eq!(agentic_loop.apply_review_patches(&mut review).await, 1);
+        assert!(review.issues.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(&artifact.path).unwrap(),
+            "fn a() {}\nfn c() {}\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_no_branch_is_created_for_a_separate_artifact_dir() {
+        let root = std::env::temp_dir().join(format!("cli_engineer_git_{}", uuid::Uuid::new_v4()));
+        let bus = Arc::new(EventBus::new(1000));
+        let llm = Arc::new(LLMManager::new(
+            vec![Box::new(MockProvider::new())],
+            bus.clone(),
+            Arc::new(Config::default()),
+        ));
+        let artifacts = Arc::new(ArtifactManager::new(root.join("artifacts")).unwrap());
+        let agentic_loop = AgenticLoop::new(llm, 1, bus)
+            .with_artifact_manager(artifacts)
+            .with_git(root.clone());
+
+        assert!(agentic_loop.start_git_branch("Add a parser").await.is_none());
+        assert!(!root.join(".git").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    /// Write the hello world script with `main` as the model and `second` as the
+    /// second-opinion reviewer, returning the review and the script
+    async fn write_hello(
+        main: Box<dyn LLMProvider>,
+        second: Box<dyn LLMProvider>,
+    ) -> (ReviewResult, String) {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_loop_{}", uuid::Uuid::new_v4()));
+        let bus = Arc::new(EventBus::new(1000));
+        let manager = |provider| {
+            Arc::new(LLMManager::new(
+                vec![provider],
+                bus.clone(),
+                Arc::new(Config::default()),
+            ))
+        };
+        let artifacts = Arc::new(ArtifactManager::new(dir.clone()).unwrap());
+        let agentic_loop = AgenticLoop::new(manager(main), 1, bus.clone())
+            .with_artifact_manager(artifacts)
+            .with_second_opinion(manager(second));
+        let review = agentic_loop
+            .run("Write a hello world script in Python", "ctx")
+            .await
+            .unwrap()
+            .unwrap();
+        let content = std::fs::read_to_string(dir.join("hello.py")).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        (review, content)
+    }
+
+    #[tokio::test]
+    async fn test_replayed_run_matches_the_recording() {
+        let path = std::env::temp_dir()
+            .join(format!("cli_engineer_cassette_{}", uuid::Uuid::new_v4()))
+            .join("run.jsonl");
+        let mode = CassetteMode::record(&path).unwrap();
+        let main = mode
+            .provider("mock", "mock", || {
+                Ok(Box::new(
+                    MockProvider::new()
+                        .then_respond("1. Implement hello.py printing a greeting")
+                        .then_respond(&artifact("print('Hello, world')"))
+                        .then_respond("QUALITY: good\nREADY_TO_DEPLOY: yes\nSUMMARY: Prints it."),
+                ))
+            })
+            .unwrap();
+        let second = mode
+            .provider("judge", "large", || {
+                let judge = MockProvider::replaying(Arc::default(), "judge", "large");
+                Ok(Box::new(judge.then_respond(
+                    "QUALITY: good\nREADY_TO_DEPLOY: yes\nSUMMARY: Fine.\nISSUES:\n\
+                     - SEVERITY: minor | CATEGORY: documentation | DESCRIPTION: Add a docstring | \
+                     LOCATION: hello.py:1",
+                )))
+            })
+            .unwrap();
+        let (recorded, content) = write_hello(main, second).await;
+        assert_eq!(content.trim(), "print('Hello, world')");
+        let agreement = "Second opinion (large): 0 agreed";
+        assert!(recorded.summary.contains(agreement), "{}", recorded.summary);
+        assert_eq!(
+            recorded.issues[0].description,
+            "[second opinion only] Add a docstring"
+        );
+
+        // The executor's artifact and both reviews come back from the cassette
+        let mode = CassetteMode::replay(&path).unwrap();
+        let unbuilt = || -> Result<Box<dyn LLMProvider>> { anyhow::bail!("no API key") };
+        let main = mode.provider("mock", "mock", unbuilt).unwrap();
+        let second = mode.provider("Judge", "", unbuilt).unwrap();
+        let (replayed, replayed_content) = write_hello(main, second).await;
+        assert_eq!(replayed_content, content);
+        assert_eq!(replayed.summary, recorded.summary);
+        assert_eq!(replayed.ready_to_deploy, recorded.ready_to_deploy);
+        assert_eq!(
+            replayed.issues[0].description,
+            recorded.issues[0].description
+        );
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
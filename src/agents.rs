@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AgentConfig, AgentsConfig};
+use crate::context::ContextManager;
+use crate::event_bus::{Event, EventBus};
+use crate::llm_manager::LLMManager;
+use crate::planner::StepCategory;
+
+/// Event carrying a message from one agent to another
+pub const AGENT_MESSAGE_EVENT: &str = "agent_message";
+
+/// The specialty of an agent in the multi-agent mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentRole {
+    Architect,
+    Implementer,
+    Tester,
+    Reviewer,
+}
+
+impl AgentRole {
+    pub const ALL: [AgentRole; 4] = [
+        AgentRole::Architect,
+        AgentRole::Implementer,
+        AgentRole::Tester,
+        AgentRole::Reviewer,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AgentRole::Architect => "architect",
+            AgentRole::Implementer => "implementer",
+            AgentRole::Tester => "tester",
+            AgentRole::Reviewer => "reviewer",
+        }
+    }
+
+    /// This role's settings in `agents`
+    pub fn config(self, agents: &AgentsConfig) -> &AgentConfig {
+        match self {
+            AgentRole::Architect => &agents.architect,
+            AgentRole::Implementer => &agents.implementer,
+            AgentRole::Tester => &agents.tester,
+            AgentRole::Reviewer => &agents.reviewer,
+        }
+    }
+
+    /// The agent carrying out steps of `category`
+    pub fn for_step(category: &StepCategory) -> Self {
+        match category {
+            StepCategory::Testing => AgentRole::Tester,
+            _ => AgentRole::Implementer,
+        }
+    }
+
+    fn default_system_prompt(self) -> &'static str {
+        match self {
+            AgentRole::Architect => {
+                "You design the work: break the task into small, ordered steps with clear \
+                 boundaries between modules, and keep the design as simple as the task allows. \
+                 You do not write the code yourself."
+            }
+            AgentRole::Implementer => {
+                "You write production code that carries out the architect's steps exactly, \
+                 complete and ready to compile, following the conventions of the existing code."
+            }
+            AgentRole::Tester => {
+                "You write focused tests for the implementer's code: the main behaviour, edge \
+                 cases and error paths, in the project's test framework and layout."
+            }
+            AgentRole::Reviewer => {
+                "You review the team's work critically for correctness, missing requirements, \
+                 untested behaviour and maintainability, and report concrete issues."
+            }
+        }
+    }
+}
+
+impl fmt::Display for AgentRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A structured message one agent passes to another
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentMessage {
+    pub from: AgentRole,
+    pub to: AgentRole,
+    /// What the message carries: `plan`, `results` or `review`
+    pub kind: String,
+    pub iteration: usize,
+    pub content: String,
+}
+
+/// The agents of the multi-agent mode, each with its own system prompt and
+/// possibly its own model, and the channel they pass messages through
+pub struct AgentTeam {
+    agents: HashMap<AgentRole, Arc<LLMManager>>,
+    event_bus: Arc<EventBus>,
+    context_manager: Option<Arc<ContextManager>>,
+}
+
+impl AgentTeam {
+    /// A team using the models in `models`, by role, and `default` for the
+    /// other roles, with the system prompts of `config`
+    pub fn new(
+        default: Arc<LLMManager>,
+        models: HashMap<AgentRole, Arc<LLMManager>>,
+        config: &AgentsConfig,
+        event_bus: Arc<EventBus>,
+    ) -> Self {
+        let agents = AgentRole::ALL
+            .into_iter()
+            .map(|role| {
+                let model = models
+                    .get(&role)
+                    .cloned()
+                    .unwrap_or_else(|| default.clone());
+                let configured = role.config(config).system_prompt.as_deref();
+                let prompt = format!(
+                    "You are the {} agent of a software engineering team of architect, \
+                     implementer, tester and reviewer agents.\n{}",
+                    role,
+                    configured.unwrap_or(role.default_system_prompt())
+                );
+                (
+                    role,
+                    Arc::new(LLMManager::with_system_prompt(model, prompt)),
+                )
+            })
+            .collect();
+        Self {
+            agents,
+            event_bus,
+            context_manager: None,
+        }
+    }
+
+    /// Add the agents' messages to the conversation, so every later prompt sees them
+    pub fn with_context_manager(mut self, manager: Arc<ContextManager>) -> Self {
+        self.context_manager = Some(manager);
+        self
+    }
+
+    /// The model of `role`'s agent, with its system prompt
+    pub fn llm(&self, role: AgentRole) -> &Arc<LLMManager> {
+        &self.agents[&role]
+    }
+
+    /// Pass `message` on: announce it on the event bus and add it to the conversation
+    pub async fn send(&self, context_id: &str, message: AgentMessage) {
+        info!("{} -> {}: {}", message.from, message.to, message.kind);
+        if let Some(ctx_mgr) = &self.context_manager {
+            let content = format!(
+                "[{} -> {}: {}]\n{}",
+                message.from, message.to, message.kind, message.content
+            );
+            if let Err(e) = ctx_mgr
+                .add_message(context_id, "assistant".to_string(), content)
+                .await
+            {
+                warn!("Failed to add agent message to context: {}", e);
+            }
+        }
+        let _ = self
+            .event_bus
+            .emit(Event::Custom {
+                event_type: AGENT_MESSAGE_EVENT.to_string(),
+                data: serde_json::to_value(&message).unwrap_or_default(),
+            })
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::llm_manager::LocalProvider;
+
+    #[tokio::test]
+    async fn test_agent_team() {
+        let bus = Arc::new(EventBus::new(100));
+        let main = Arc::new(LLMManager::new(
+            vec![Box::new(LocalProvider)],
+            bus.clone(),
+            Arc::new(Config::default()),
+        ));
+        let mut config = AgentsConfig::default();
+        config.tester.system_prompt = Some("Write table-driven tests.".to_string());
+        let team = AgentTeam::new(main, HashMap::new(), &config, bus.clone());
+
+        // The local provider echoes the prompt it was sent
+        let tester = team.llm(AgentRole::for_step(&StepCategory::Testing));
+        let prompt = tester.send_prompt("Test add()").await.unwrap();
+        assert!(prompt.starts_with("You are the tester agent"));
+        assert!(prompt.ends_with("Write table-driven tests.\n\nTest add()"));
+        let implementer = team.llm(AgentRole::for_step(&StepCategory::CodeGeneration));
+        let prompt = implementer.send_prompt("Write add()").await.unwrap();
+        assert!(prompt.contains("You write production code"));
+
+        let mut events = bus.subscribe();
+        team.send(
+            "ctx",
+            AgentMessage {
+                from: AgentRole::Architect,
+                to: AgentRole::Implementer,
+                kind: "plan".to_string(),
+                iteration: 1,
+                content: "1. Write add()".to_string(),
+            },
+        )
+        .await;
+        match events.recv().await.unwrap() {
+            Event::Custom { event_type, data } => {
+                assert_eq!(event_type, AGENT_MESSAGE_EVENT);
+                assert_eq!(data["from"], "architect");
+                assert_eq!(data["to"], "implementer");
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use colored::*;
+
+use crate::artifact::{ArtifactManager, FileAction, MANIFEST_FILE};
+use crate::artifact_diff::{print_diff, unified_diff};
+use crate::redaction;
+
+/// A generated file and the project file it maps to
+pub struct PendingFile {
+    /// Path relative to both the artifact directory and the project root
+    pub relative: PathBuf,
+    pub target: PathBuf,
+    pub content: String,
+    /// Current content of the project file, if it exists
+    pub existing: Option<String>,
+}
+
+impl PendingFile {
+    pub fn is_unchanged(&self) -> bool {
+        self.existing.as_deref() == Some(self.content.as_str())
+    }
+
+    /// Unified diff from the project file to the artifact
+    pub fn diff(&self) -> String {
+        let name = self.relative.to_string_lossy();
+        unified_diff(
+            self.existing.as_deref(),
+            &self.content,
+            &format!("a/{}", name),
+            &format!("b/{}", name),
+        )
+    }
+}
+
+/// A delete or rename the model asked for, to carry over to the project.
+/// Paths are relative to both the artifact directory and the project root.
+#[derive(Debug, PartialEq)]
+pub enum PendingAction {
+    Delete { relative: PathBuf },
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+impl PendingAction {
+    fn describe(&self) -> String {
+        match self {
+            Self::Delete { relative } => format!("delete {}", relative.display()),
+            Self::Rename { from, to } => format!("rename {} to {}", from.display(), to.display()),
+        }
+    }
+}
+
+/// Deletes and renames recorded in the manifest whose file is still in the project
+pub async fn pending_actions(artifact_dir: &Path, root: &Path) -> Result<Vec<PendingAction>> {
+    let manager = ArtifactManager::new(artifact_dir.to_path_buf())?;
+    manager.init().await?;
+    let mut actions = Vec::new();
+    for operation in manager.list_operations().await {
+        let action = match &operation.action {
+            FileAction::Delete { filename } => {
+                relative_path(filename).map(|relative| PendingAction::Delete { relative })
+            }
+            FileAction::Rename { from, to } => relative_path(from)
+                .zip(relative_path(to))
+                .map(|(from, to)| PendingAction::Rename { from, to }),
+        };
+        let Some(action) = action else {
+            log::warn!("Skipping {:?}: path leaves the project", operation.action);
+            continue;
+        };
+        let source = match &action {
+            PendingAction::Delete { relative } => relative,
+            PendingAction::Rename { from, .. } => from,
+        };
+        if root.join(source).is_file() {
+            actions.push(action);
+        }
+    }
+    Ok(actions)
+}
+
+/// `name` as a path that stays under the directory it is relative to
+fn relative_path(name: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(name);
+    let normal = path.components().all(|c| matches!(c, Component::Normal(_)));
+    (normal && !name.is_empty()).then_some(path)
+}
+
+/// Carry a delete or rename over to the project. A deleted file is backed up
+/// under `backup_dir` first; a rename onto an existing file is refused.
+pub fn apply_action(action: &PendingAction, root: &Path, backup_dir: &Path) -> Result<()> {
+    match action {
+        PendingAction::Delete { relative } => {
+            let target = root.join(relative);
+            let backup = backup_dir.join(relative);
+            if let Some(parent) = backup.parent() {
+                fs::create_dir_all(parent).context("Failed to create backup directory")?;
+            }
+            fs::copy(&target, &backup)
+                .with_context(|| format!("Failed to back up {}", target.display()))?;
+            fs::remove_file(&target)
+                .with_context(|| format!("Failed to delete {}", target.display()))?;
+        }
+        PendingAction::Rename { from, to } => {
+            let (source, target) = (root.join(from), root.join(to));
+            if target.exists() {
+                anyhow::bail!("Cannot rename {}: {} exists", from.display(), to.display());
+            }
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).context("Failed to create parent directories")?;
+            }
+            fs::rename(&source, &target)
+                .with_context(|| format!("Failed to rename {}", source.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Artifacts of the last run, mapped to the same relative paths under `root`.
+/// The manifest provides the list; without one every file in the directory is used.
+/// Versions written next to edited files by the `keep` conflict strategy
+/// (`<name>.generated`) are left out.
+pub async fn pending_files(artifact_dir: &Path, root: &Path) -> Result<Vec<PendingFile>> {
+    if !artifact_dir.is_dir() {
+        anyhow::bail!("No artifacts found in {}", artifact_dir.display());
+    }
+
+    let manager = ArtifactManager::new(artifact_dir.to_path_buf())?;
+    manager.init().await?;
+    let mut sources: Vec<PathBuf> = manager
+        .list_artifacts()
+        .await
+        .into_iter()
+        .map(|a| a.path)
+        .collect();
+    if sources.is_empty() {
+        sources = ignore::WalkBuilder::new(artifact_dir)
+            .standard_filters(false)
+            .hidden(true)
+            .build()
+            .filter_map(|e| e.ok())
+            .map(|e| e.into_path())
+            .filter(|p| {
+                p.is_file()
+                    && !p
+                        .file_name()
+                        .is_some_and(|n| n.to_string_lossy().starts_with(MANIFEST_FILE))
+            })
+            .collect();
+    }
+    sources.sort();
+    sources.dedup();
+
+    let mut files = Vec::new();
+    for source in sources {
+        let Ok(relative) = source.strip_prefix(artifact_dir) else {
+            log::warn!(
+                "Skipping {}: not in the artifact directory",
+                source.display()
+            );
+            continue;
+        };
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            log::warn!("Skipping {}: path leaves the project", relative.display());
+            continue;
+        }
+        if relative.to_string_lossy().ends_with(".generated") {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&source) else {
+            continue;
+        };
+        let target = root.join(relative);
+        files.push(PendingFile {
+            relative: relative.to_path_buf(),
+            existing: fs::read_to_string(&target).ok(),
+            target,
+            content,
+        });
+    }
+    Ok(files)
+}
+
+/// Copy an artifact into the project, backing up the file it replaces under
+/// `backup_dir`. Returns the backup path, if there was a file to back up.
+pub fn apply_file(file: &PendingFile, backup_dir: &Path) -> Result<Option<PathBuf>> {
+    let backup = match &file.existing {
+        Some(existing) => {
+            let backup = backup_dir.join(&file.relative);
+            if let Some(parent) = backup.parent() {
+                fs::create_dir_all(parent).context("Failed to create backup directory")?;
+            }
+            fs::write(&backup, existing)
+                .with_context(|| format!("Failed to back up {}", file.target.display()))?;
+            Some(backup)
+        }
+        None => None,
+    };
+
+    if let Some(parent) = file.target.parent() {
+        fs::create_dir_all(parent).context("Failed to create parent directories")?;
+    }
+    fs::write(&file.target, &file.content)
+        .with_context(|| format!("Failed to write {}", file.target.display()))?;
+    Ok(backup)
+}
+
+/// The `apply` command: carry the deletes and renames of the last run over to
+/// the project, then show what each artifact changes and copy it in. With
+/// `interactive` every change is confirmed first; otherwise `yes` must be given.
+pub async fn run(artifact_dir: &Path, root: &Path, interactive: bool, yes: bool) -> Result<()> {
+    if !interactive && !yes {
+        anyhow::bail!(
+            "apply changes project files; pass --yes to apply every change, \
+             or --interactive to confirm each"
+        );
+    }
+    let actions = pending_actions(artifact_dir, root).await?;
+    let backup_dir = root
+        .join(".cli_engineer")
+        .join("backups")
+        .join(chrono::Local::now().format("%Y%m%d-%H%M%S").to_string());
+    let mut apply_rest = !interactive;
+    let mut applied = 0;
+    for action in &actions {
+        let description = action.describe();
+        if !apply_rest {
+            match ask(&format!(
+                "Apply: {}? [y]es/[n]o/[a]ll/[q]uit [n] ",
+                description
+            ))
+            .await
+            {
+                'y' => {}
+                'a' => apply_rest = true,
+                'q' => return Ok(()),
+                _ => continue,
+            }
+        }
+        match apply_action(action, root, &backup_dir) {
+            Ok(()) => {
+                applied += 1;
+                println!("{} {}", "Applied".green().bold(), description);
+            }
+            Err(e) => println!("{} {:#}", "Skipped".yellow().bold(), e),
+        }
+    }
+
+    // Read after the renames, so a renamed file that was also changed is backed up
+    let files = pending_files(artifact_dir, root).await?;
+    let changed: Vec<&PendingFile> = files.iter().filter(|f| !f.is_unchanged()).collect();
+    if changed.is_empty() && actions.is_empty() {
+        println!("Nothing to apply: the project already matches the artifacts");
+        return Ok(());
+    }
+
+    let total = changed.len() + actions.len();
+    for file in changed {
+        let name = file.relative.to_string_lossy();
+        let existing = file.existing.as_deref().unwrap_or_default();
+        if let Err(e) = redaction::check_placeholders(&name, &file.content, existing) {
+            println!("{} {}", "Skipped".yellow().bold(), e);
+            continue;
+        }
+        print_diff(&file.diff());
+        if !apply_rest {
+            match ask(&format!(
+                "Apply {}? [y]es/[n]o/[a]ll/[q]uit [n] ",
+                file.relative.display()
+            ))
+            .await
+            {
+                'y' => {}
+                'a' => apply_rest = true,
+                'q' => break,
+                _ => continue,
+            }
+        }
+        let backup = apply_file(file, &backup_dir)?;
+        applied += 1;
+        let status = if backup.is_some() {
+            "Updated"
+        } else {
+            "Created"
+        };
+        println!("{} {}", status.green().bold(), file.target.display());
+    }
+
+    println!("\nApplied {} of {} change(s)", applied, total);
+    if backup_dir.exists() {
+        println!("Replaced files were backed up to {}", backup_dir.display());
+    }
+    Ok(())
+}
+
+/// First letter of the answer to a question on the terminal, lowercased
+async fn ask(question: &str) -> char {
+    let question = question.to_string();
+    tokio::task::spawn_blocking(move || {
+        print!("{}", question);
+        let _ = std::io::stdout().flush();
+        let mut answer = String::new();
+        let _ = std::io::stdin().read_line(&mut answer);
+        answer.trim().to_lowercase().chars().next().unwrap_or('n')
+    })
+    .await
+    .unwrap_or('n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::ArtifactType;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_apply_artifacts() {
+        let root =
+            std::env::temp_dir().join(format!("cli_engineer_apply_{}", uuid::Uuid::new_v4()));
+        let artifact_dir = root.join("artifacts");
+        let manager = ArtifactManager::new(artifact_dir.clone()).unwrap();
+        for (name, content) in [("src/lib.rs", "fn new() {}\n"), ("README.md", "# Demo\n")] {
+            manager
+                .create_artifact(
+                    name.to_string(),
+                    ArtifactType::SourceCode,
+                    content.to_string(),
+                    HashMap::new(),
+                )
+                .await
+                .unwrap();
+        }
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/lib.rs"), "fn old() {}\n").unwrap();
+        fs::write(root.join("README.md"), "# Demo\n").unwrap();
+
+        let files = pending_files(&artifact_dir, &root).await.unwrap();
+        assert_eq!(files.len(), 2);
+        let readme = files
+            .iter()
+            .find(|f| f.relative == Path::new("README.md"))
+            .unwrap();
+        assert!(readme.is_unchanged());
+        let lib = files
+            .iter()
+            .find(|f| f.relative == Path::new("src/lib.rs"))
+            .unwrap();
+        assert!(lib.diff().contains("-fn old() {}\n+fn new() {}"));
+
+        let backup = apply_file(lib, &root.join("backup")).unwrap().unwrap();
+        assert_eq!(fs::read_to_string(backup).unwrap(), "fn old() {}\n");
+        assert_eq!(
+            fs::read_to_string(root.join("src/lib.rs")).unwrap(),
+            "fn new() {}\n"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_redacted_files_are_not_applied() {
+        let root =
+            std::env::temp_dir().join(format!("cli_engineer_apply_{}", uuid::Uuid::new_v4()));
+        let artifact_dir = root.join("artifacts");
+        fs::create_dir_all(&artifact_dir).unwrap();
+        let echoed = "TOKEN=[REDACTED:credential_assignment]\n";
+        fs::write(artifact_dir.join("config.toml"), echoed).unwrap();
+        fs::write(artifact_dir.join("notes.md"), "# Notes\n").unwrap();
+        fs::write(root.join("config.toml"), "TOKEN=abc123\n").unwrap();
+
+        // The file is offered for applying, and refused for its placeholder
+        let files = pending_files(&artifact_dir, &root).await.unwrap();
+        let config = files
+            .iter()
+            .find(|f| f.relative == Path::new("config.toml"))
+            .unwrap();
+        assert!(!config.is_unchanged());
+        assert!(
+            redaction::check_placeholders(
+                "config.toml",
+                &config.content,
+                config.existing.as_deref().unwrap_or_default()
+            )
+            .is_err()
+        );
+
+        let error = run(&artifact_dir, &root, false, false).await.unwrap_err();
+        assert!(error.to_string().contains("--yes"), "{}", error);
+        assert!(!root.join("notes.md").exists());
+
+        run(&artifact_dir, &root, false, true).await.unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("config.toml")).unwrap(),
+            "TOKEN=abc123\n"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("notes.md")).unwrap(),
+            "# Notes\n"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_deletes_and_renames_are_carried_over() {
+        let root =
+            std::env::temp_dir().join(format!("cli_engineer_apply_{}", uuid::Uuid::new_v4()));
+        let artifact_dir = root.join("artifacts");
+        let manager = ArtifactManager::new(artifact_dir.clone()).unwrap();
+        manager.init().await.unwrap();
+        manager.delete_artifact("old.rs").await.unwrap();
+        manager.rename_artifact("a.rs", "src/b.rs").await.unwrap();
+        manager.rename_artifact("c.rs", "taken.rs").await.unwrap();
+        for (name, content) in [
+            ("old.rs", "old"),
+            ("a.rs", "a"),
+            ("c.rs", "c"),
+            ("taken.rs", "t"),
+        ] {
+            fs::write(root.join(name), content).unwrap();
+        }
+        // Written next to an edited file by the keep conflict strategy
+        fs::write(artifact_dir.join("taken.rs.generated"), "generated").unwrap();
+
+        let actions = pending_actions(&artifact_dir, &root).await.unwrap();
+        assert_eq!(actions.len(), 3);
+        assert!(
+            pending_files(&artifact_dir, &root)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        run(&artifact_dir, &root, false, true).await.unwrap();
+        assert!(!root.join("old.rs").exists());
+        assert!(
+            fs::read_dir(root.join(".cli_engineer/backups"))
+                .unwrap()
+                .count()
+                == 1
+        );
+        assert_eq!(fs::read_to_string(root.join("src/b.rs")).unwrap(), "a");
+        assert!(!root.join("a.rs").exists());
+        // A rename onto an existing file is refused
+        assert_eq!(fs::read_to_string(root.join("c.rs")).unwrap(), "c");
+        assert_eq!(fs::read_to_string(root.join("taken.rs")).unwrap(), "t");
+        assert!(!root.join("taken.rs.generated").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
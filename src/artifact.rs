@@ -1,16 +1,32 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fs;
-use std::io::Write;
-use std::path::PathBuf;
+use std::io::{IsTerminal, Write};
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::sync::RwLock;
 
+use crate::artifact_diff::unified_diff;
+use crate::config::{ArtifactsConfig, ConflictStrategy};
 use crate::event_bus::{Event, EventBus, EventEmitter};
 use crate::impl_event_emitter;
+use crate::merge::three_way_merge;
+use crate::redaction;
+use crate::run_record::RunRecord;
+
+/// Manifest schema written by this version. 1.0 manifests lack artifact
+/// versions and operations, which load as empty.
+const MANIFEST_VERSION: &str = "2.0";
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// Metadata key holding an artifact's comma-separated tags
+pub const TAGS_KEY: &str = "tags";
+const BYTES_PER_MB: u64 = 1024 * 1024;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ArtifactType {
@@ -24,6 +40,23 @@ pub enum ArtifactType {
     Other(String),
 }
 
+impl ArtifactType {
+    /// Type of a generated file, from its extension
+    pub fn from_filename(filename: &str) -> Self {
+        match filename.split('.').next_back() {
+            Some("rs") => ArtifactType::SourceCode,
+            Some("toml") => ArtifactType::Configuration,
+            Some("json") => ArtifactType::Configuration,
+            Some("md") => ArtifactType::Documentation,
+            Some("txt") => ArtifactType::Documentation,
+            Some("sh") => ArtifactType::Script,
+            Some("py") => ArtifactType::SourceCode,
+            Some("js") => ArtifactType::SourceCode,
+            _ => ArtifactType::Other("unknown".to_string()),
+        }
+    }
+}
+
 impl fmt::Display for ArtifactType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -49,19 +82,123 @@ pub struct Artifact {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub metadata: HashMap<String, String>,
+    /// Every content the artifact has had, oldest first
+    #[serde(default)]
+    pub versions: Vec<ArtifactVersion>,
+}
+
+impl Artifact {
+    /// Tags from the metadata, e.g. `iteration:2` or `review`
+    pub fn tags(&self) -> Vec<&str> {
+        self.metadata
+            .get(TAGS_KEY)
+            .map(|tags| {
+                tags.split(',')
+                    .map(str::trim)
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().contains(&tag)
+    }
+
+    /// SHA-256 of the content last written, as recorded in the manifest
+    pub fn checksum(&self) -> Option<String> {
+        match self.versions.last() {
+            Some(version) => Some(version.sha256.clone()),
+            None => self.content.as_deref().map(content_sha256),
+        }
+    }
+}
+
+/// Add `tags` to artifact metadata, keeping existing tags and skipping duplicates
+pub fn add_tags(metadata: &mut HashMap<String, String>, tags: &[&str]) {
+    let mut all: Vec<String> = metadata
+        .get(TAGS_KEY)
+        .map(|t| t.split(',').map(|t| t.trim().to_string()).collect())
+        .unwrap_or_default();
+    for tag in tags {
+        let tag = tag.trim().replace(',', " ");
+        if !tag.is_empty() && !all.contains(&tag) {
+            all.push(tag);
+        }
+    }
+    all.retain(|t| !t.is_empty());
+    metadata.insert(TAGS_KEY.to_string(), all.join(","));
+}
+
+/// Lines of an artifact matching a search
+#[derive(Debug, Clone)]
+pub struct ArtifactMatch {
+    pub artifact: Artifact,
+    /// Line numbers (1-based) and text of the matching lines
+    pub lines: Vec<(usize, String)>,
+}
+
+/// One saved content of an artifact. The content itself is stored once per
+/// distinct hash under the versions directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactVersion {
+    /// 1 for the content the artifact was created with
+    pub version: usize,
+    pub sha256: String,
+    pub size: usize,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A file operation requested by the model other than creating content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FileAction {
+    Delete { filename: String },
+    Rename { from: String, to: String },
+}
+
+/// A recorded delete or rename, kept in the manifest so it can be undone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactOperation {
+    pub id: String,
+    pub action: FileAction,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Artifact record affected by the operation, if the file was an artifact
+    pub artifact: Option<Artifact>,
+    /// Copy of deleted content under the trash directory
+    pub backup_path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArtifactManifest {
+    /// Schema version, see `MANIFEST_VERSION`
     pub version: String,
     pub artifacts: Vec<Artifact>,
     pub metadata: HashMap<String, String>,
+    #[serde(default)]
+    pub operations: Vec<ArtifactOperation>,
 }
 
 /// Manages creation, storage, and retrieval of artifacts
 pub struct ArtifactManager {
     artifact_dir: PathBuf,
     artifacts: Arc<RwLock<Vec<Artifact>>>,
+    operations: Arc<RwLock<Vec<ArtifactOperation>>>,
+    /// Content of files as last loaded into context or written, with the file it was
+    /// read from, keyed by the path the file is written to (see `snapshot_key`)
+    snapshots: Arc<RwLock<HashMap<PathBuf, (PathBuf, String)>>>,
+    /// Content of files before this run first overwrote them, keyed by normalized path
+    originals: Arc<RwLock<HashMap<PathBuf, String>>>,
+    conflict_strategy: ConflictStrategy,
+    limits: ArtifactsConfig,
+    /// Set once the directory is reported over quota, so the warning is not repeated
+    quota_warned: AtomicBool,
+    /// Project the artifact directory belongs to; nothing is pruned when the
+    /// artifact directory is the project or holds it
+    project_root: Option<PathBuf>,
+    /// Set once pruning is refused for that reason, so the warning is not repeated
+    pruning_refused: AtomicBool,
+    /// Serializes manifest writes so a later state is never overwritten by an earlier one
+    manifest_lock: tokio::sync::Mutex<()>,
     event_bus: Option<Arc<EventBus>>,
 }
 
@@ -73,23 +210,247 @@ impl ArtifactManager {
         let manager = Self {
             artifact_dir,
             artifacts: Arc::new(RwLock::new(Vec::new())),
+            operations: Arc::new(RwLock::new(Vec::new())),
+            snapshots: Arc::new(RwLock::new(HashMap::new())),
+            originals: Arc::new(RwLock::new(HashMap::new())),
+            conflict_strategy: ConflictStrategy::default(),
+            limits: ArtifactsConfig::default(),
+            quota_warned: AtomicBool::new(false),
+            project_root: None,
+            pruning_refused: AtomicBool::new(false),
+            manifest_lock: tokio::sync::Mutex::new(()),
             event_bus: None,
         };
 
         Ok(manager)
     }
 
+    /// Set how overwrites of externally modified files are resolved
+    pub fn set_conflict_strategy(&mut self, strategy: ConflictStrategy) {
+        self.conflict_strategy = strategy;
+    }
+
+    /// Set the size limits and run retention enforced on the artifact directory
+    pub fn set_limits(&mut self, limits: ArtifactsConfig) {
+        self.limits = limits;
+    }
+
+    /// Set the project the artifact directory belongs to
+    pub fn set_project_root(&mut self, root: PathBuf) {
+        self.project_root = Some(root);
+    }
+
+    /// Remember the content of a file as it was loaded into context, so later
+    /// overwrites can detect edits made on disk in the meantime
+    pub async fn record_snapshot(&self, path: &Path, content: String) {
+        self.snapshots
+            .write()
+            .await
+            .insert(self.snapshot_key(path), (absolute_path(path), content));
+    }
+
+    /// Path a snapshot is kept under: where the file is written as an artifact, so
+    /// a project file loaded into context and the artifact replacing it share one
+    fn snapshot_key(&self, path: &Path) -> PathBuf {
+        let path = absolute_path(path);
+        let artifact_dir = absolute_path(&self.artifact_dir);
+        if !path.starts_with(&artifact_dir)
+            && let Some(root) = &self.project_root
+            && let Ok(relative) = path.strip_prefix(absolute_path(root))
+        {
+            return artifact_dir.join(relative);
+        }
+        path
+    }
+
+    /// Remember what `path` contained before this run first writes to it
+    async fn remember_original(&self, path: &Path) {
+        let key = normalize_path(path);
+        if self.originals.read().await.contains_key(&key) {
+            return;
+        }
+        if let Ok(content) = fs::read_to_string(path) {
+            self.originals.write().await.insert(key, content);
+        }
+    }
+
+    /// Content an artifact is compared against to show what this run changed: the
+    /// file as it was before the run overwrote it, otherwise the workspace file at
+    /// the same relative path, otherwise empty for files new in this run
+    pub async fn baseline_content(&self, artifact: &Artifact) -> String {
+        self.baseline_of(&artifact.path).await
+    }
+
+    async fn baseline_of(&self, path: &Path) -> String {
+        if let Some(original) = self.originals.read().await.get(&normalize_path(path)) {
+            return original.clone();
+        }
+
+        let relative = path.strip_prefix(&self.artifact_dir).unwrap_or(path);
+        if let Ok(workspace) = std::env::current_dir() {
+            let counterpart = workspace.join(relative);
+            if normalize_path(&counterpart) != normalize_path(path)
+                && let Ok(content) = fs::read_to_string(&counterpart)
+            {
+                return content;
+            }
+        }
+        String::new()
+    }
+
+    /// Check whether the file `path` replaces changed on disk since it was loaded or
+    /// last written and, if so, resolve the conflict. Returns the content to write
+    /// to `path`; when the on-disk version is kept that is the on-disk content, and
+    /// the generated version is written beside it as `<name>.generated`.
+    async fn resolve_conflict(&self, name: &str, path: &Path, content: String) -> Result<String> {
+        let (source, base) = match self.snapshots.read().await.get(&self.snapshot_key(path)) {
+            Some(snapshot) => snapshot.clone(),
+            None => return Ok(content),
+        };
+        let on_disk = match fs::read_to_string(&source) {
+            Ok(on_disk) => on_disk,
+            Err(_) => return Ok(content),
+        };
+        if on_disk == base || on_disk == content {
+            return Ok(content);
+        }
+
+        log::warn!(
+            "{} changed on disk since it was loaded; resolving with {:?} strategy",
+            source.display(),
+            self.conflict_strategy
+        );
+
+        let mut strategy = self.conflict_strategy;
+        if strategy == ConflictStrategy::Prompt {
+            strategy = prompt_conflict_resolution(&source).await;
+        }
+
+        let (write_path, write_content, resolution) = match strategy {
+            ConflictStrategy::Overwrite => (path.to_path_buf(), content, "overwritten".to_string()),
+            ConflictStrategy::Keep => {
+                let mut generated = path.as_os_str().to_owned();
+                generated.push(".generated");
+                let generated = PathBuf::from(generated);
+                self.check_artifact_size(name, &content)?;
+                self.check_placeholders(name, &generated, &content).await?;
+                if let Some(parent) = generated.parent() {
+                    fs::create_dir_all(parent).context("Failed to create parent directories")?;
+                }
+                fs::write(&generated, &content).context("Failed to write generated version")?;
+                (generated, on_disk, "kept on-disk version".to_string())
+            }
+            ConflictStrategy::Merge | ConflictStrategy::Prompt => {
+                let merged = three_way_merge(&base, &on_disk, &content, "on disk", "generated");
+                let resolution = if merged.conflicts == 0 {
+                    "merged".to_string()
+                } else {
+                    format!("merged with {} conflict(s)", merged.conflicts)
+                };
+                (path.to_path_buf(), merged.content, resolution)
+            }
+        };
+
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::ArtifactConflict {
+                    name: name.to_string(),
+                    path: write_path.to_string_lossy().to_string(),
+                    resolution,
+                })
+                .await;
+        }
+
+        Ok(write_content)
+    }
+
     /// Initialize the artifact manager by loading existing artifacts
-    #[allow(dead_code)]
     pub async fn init(&self) -> Result<()> {
         // Load existing manifest if present
-        if let Ok(manifest) = self.load_manifest() {
-            let mut artifacts = self.artifacts.write().await;
-            *artifacts = manifest.artifacts;
+        let manifest = self.load_manifest()?;
+        let mut artifacts = self.artifacts.write().await;
+        *artifacts = manifest.artifacts;
+        let mut operations = self.operations.write().await;
+        *operations = manifest.operations;
+        drop(artifacts);
+        drop(operations);
+
+        let modified = self.refresh_modified().await?;
+        if !modified.is_empty() {
+            log::info!(
+                "{} artifact(s) were edited outside cli_engineer since the last run",
+                modified.len()
+            );
         }
         Ok(())
     }
 
+    /// Take in edits made to artifact files outside cli_engineer. Each edited
+    /// file becomes a new version and the artifact's content, so later steps
+    /// build on the edit instead of the stale generated copy. Returns the
+    /// artifacts that changed. Only the latest artifact of each file is
+    /// compared; older entries for it predate the loop's own rewrites.
+    pub async fn refresh_modified(&self) -> Result<Vec<Artifact>> {
+        let mut modified = Vec::new();
+        {
+            let mut artifacts = self.artifacts.write().await;
+            let mut seen = HashSet::new();
+            for artifact in artifacts.iter_mut().rev() {
+                if !seen.insert(normalize_path(&artifact.path)) {
+                    continue;
+                }
+                if self.sync_from_disk(artifact)? {
+                    modified.push(artifact.clone());
+                }
+            }
+        }
+        for artifact in &modified {
+            let content = artifact.content.clone().unwrap_or_default();
+            self.record_snapshot(&artifact.path, content).await;
+            self.emit_modified_externally(artifact).await;
+        }
+        if !modified.is_empty() {
+            self.save_manifest().await?;
+        }
+        Ok(modified)
+    }
+
+    /// Record the file's content as a new version if it no longer matches the
+    /// artifact's checksum. Returns whether it did.
+    fn sync_from_disk(&self, artifact: &mut Artifact) -> Result<bool> {
+        let Some(checksum) = artifact.checksum() else {
+            return Ok(false);
+        };
+        let Ok(on_disk) = fs::read_to_string(&artifact.path) else {
+            return Ok(false);
+        };
+        if content_sha256(&on_disk) == checksum {
+            return Ok(false);
+        }
+
+        log::warn!(
+            "{} was edited outside cli_engineer; using the edited content",
+            artifact.name
+        );
+        let version = self.store_version(artifact.versions.len() + 1, &on_disk)?;
+        artifact.versions.push(version);
+        artifact.content = Some(on_disk);
+        artifact.updated_at = chrono::Utc::now();
+        Ok(true)
+    }
+
+    async fn emit_modified_externally(&self, artifact: &Artifact) {
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::ArtifactModifiedExternally {
+                    name: artifact.name.clone(),
+                    path: artifact.path.to_string_lossy().to_string(),
+                    version: artifact.versions.len(),
+                })
+                .await;
+        }
+    }
+
     /// Create a new artifact
     pub async fn create_artifact(
         &self,
@@ -134,17 +495,35 @@ impl ArtifactManager {
             format!("{}{}", name, extension)
         };
 
-        let path = self.artifact_dir.join(&filename);
+        let path = self.artifact_path(&filename)?;
+
+        // A file written again becomes the next version of its artifact
+        if let Some(id) = self.latest_at(&path).await.map(|a| a.id) {
+            self.update_artifact(&id, content).await?;
+            self.merge_metadata(&id, metadata).await?;
+            return self
+                .get_artifact(&id)
+                .await
+                .with_context(|| format!("Artifact not found: {}", id));
+        }
+
+        let content = self.resolve_conflict(&filename, &path, content).await?;
+        self.check_artifact_size(&filename, &content)?;
+        self.check_placeholders(&filename, &path, &content).await?;
 
         // Create parent directories if they don't exist
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).context("Failed to create parent directories")?;
         }
 
+        self.remember_original(&path).await;
+
         // Write content to file
         let mut file = fs::File::create(&path).context("Failed to create artifact file")?;
         file.write_all(content.as_bytes())
             .context("Failed to write artifact content")?;
+        self.record_snapshot(&path, content.clone()).await;
+        let version = self.store_version(1, &content)?;
 
         let artifact = Artifact {
             id: id.clone(),
@@ -155,6 +534,7 @@ impl ArtifactManager {
             created_at: now,
             updated_at: now,
             metadata,
+            versions: vec![version],
         };
 
         // Add to artifacts list
@@ -165,6 +545,7 @@ impl ArtifactManager {
 
         // Save manifest
         self.save_manifest().await?;
+        self.enforce_quota().await;
 
         // Emit event
         if let Some(bus) = &self.event_bus {
@@ -175,23 +556,93 @@ impl ArtifactManager {
                     path: path.to_string_lossy().to_string(),
                 })
                 .await;
+
+            let baseline = self.baseline_content(&artifact).await;
+            let content = artifact.content.as_deref().unwrap_or_default();
+            if baseline != content {
+                let _ = bus
+                    .emit(Event::ArtifactDiff {
+                        name: artifact.name.clone(),
+                        against: "workspace".to_string(),
+                        diff: unified_diff(
+                            (!baseline.is_empty()).then_some(baseline.as_str()),
+                            content,
+                            &format!("a/{}", artifact.name),
+                            &format!("b/{}", artifact.name),
+                        ),
+                    })
+                    .await;
+            }
         }
 
         Ok(artifact)
     }
 
-    /// Update an existing artifact
-    #[allow(dead_code)]
+    /// The most recent artifact written to `path`. Manifests from earlier
+    /// versions may hold several entries for one file; the last one is current.
+    async fn latest_at(&self, path: &Path) -> Option<Artifact> {
+        let path = normalize_path(path);
+        self.artifacts
+            .read()
+            .await
+            .iter()
+            .rev()
+            .find(|a| normalize_path(&a.path) == path)
+            .cloned()
+    }
+
+    /// Add `metadata` to an artifact's, merging tags with the existing ones
+    async fn merge_metadata(&self, id: &str, metadata: HashMap<String, String>) -> Result<()> {
+        if metadata.is_empty() {
+            return Ok(());
+        }
+        {
+            let mut artifacts = self.artifacts.write().await;
+            let artifact = artifacts
+                .iter_mut()
+                .find(|a| a.id == id)
+                .with_context(|| format!("Artifact not found: {}", id))?;
+            for (key, value) in metadata {
+                if key == TAGS_KEY {
+                    add_tags(&mut artifact.metadata, &value.split(',').collect::<Vec<_>>());
+                } else {
+                    artifact.metadata.insert(key, value);
+                }
+            }
+        }
+        self.save_manifest().await
+    }
+
+    /// Update an existing artifact. The new content becomes its next version
+    /// unless it matches the latest one.
     pub async fn update_artifact(&self, id: &str, content: String) -> Result<()> {
         let mut artifacts = self.artifacts.write().await;
 
         if let Some(artifact) = artifacts.iter_mut().find(|a| a.id == id) {
+            // Keep a hand edit in the history; the write below merges with it
+            // according to the conflict strategy
+            if self.sync_from_disk(artifact)? {
+                self.emit_modified_externally(artifact).await;
+            }
+            let path = artifact.path.clone();
+            let content = self.resolve_conflict(&artifact.name, &path, content).await?;
+            self.check_artifact_size(&artifact.name, &content)?;
+            self.check_placeholders(&artifact.name, &path, &content).await?;
+
+            self.remember_original(&path).await;
+
             // Write new content
-            let mut file =
-                fs::File::create(&artifact.path).context("Failed to open artifact file")?;
+            let mut file = fs::File::create(&path).context("Failed to open artifact file")?;
             file.write_all(content.as_bytes())
                 .context("Failed to write artifact content")?;
+            self.record_snapshot(&path, content.clone()).await;
 
+            let previous = artifact.content.take();
+            let sha256 = content_sha256(&content);
+            if artifact.versions.last().map(|v| &v.sha256) != Some(&sha256) {
+                let version = self.store_version(artifact.versions.len() + 1, &content)?;
+                artifact.versions.push(version);
+            }
             artifact.content = Some(content);
             artifact.updated_at = chrono::Utc::now();
 
@@ -201,12 +652,31 @@ impl ArtifactManager {
                     .emit(Event::ArtifactUpdated {
                         name: artifact.name.clone(),
                         path: artifact.path.to_string_lossy().to_string(),
+                        version: artifact.versions.len(),
                     })
                     .await;
+                let current = artifact.content.as_deref().unwrap_or_default();
+                if let Some(previous) = previous
+                    && previous != current
+                {
+                    let _ = bus
+                        .emit(Event::ArtifactDiff {
+                            name: artifact.name.clone(),
+                            against: format!("v{}", artifact.versions.len().saturating_sub(1)),
+                            diff: unified_diff(
+                                Some(&previous),
+                                current,
+                                &format!("a/{}", artifact.name),
+                                &format!("b/{}", artifact.name),
+                            ),
+                        })
+                        .await;
+                }
             }
 
             drop(artifacts);
             self.save_manifest().await?;
+            self.enforce_quota().await;
 
             Ok(())
         } else {
@@ -214,13 +684,50 @@ impl ArtifactManager {
         }
     }
 
+    /// Versions of an artifact, oldest first
+    pub async fn get_artifact_history(&self, id: &str) -> Result<Vec<ArtifactVersion>> {
+        let artifacts = self.artifacts.read().await;
+        artifacts
+            .iter()
+            .find(|a| a.id == id)
+            .map(|a| a.versions.clone())
+            .with_context(|| format!("Artifact not found: {}", id))
+    }
+
+    /// Content of an artifact at a version
+    pub async fn version_content(&self, id: &str, version: usize) -> Result<String> {
+        let history = self.get_artifact_history(id).await?;
+        let entry = history
+            .iter()
+            .find(|v| v.version == version)
+            .with_context(|| format!("Artifact {} has no version {}", id, version))?;
+        fs::read_to_string(self.versions_dir().join(&entry.sha256))
+            .with_context(|| format!("Failed to read version {} of artifact {}", version, id))
+    }
+
+    /// Restore the content an artifact had at `version`. The restored content is
+    /// recorded as a new version, so the revert itself can be undone.
+    pub async fn revert_artifact(&self, id: &str, version: usize) -> Result<()> {
+        let content = self.version_content(id, version).await?;
+        self.update_artifact(id, content).await
+    }
+
     /// Get an artifact by ID
-    #[allow(dead_code)]
     pub async fn get_artifact(&self, id: &str) -> Option<Artifact> {
         let artifacts = self.artifacts.read().await;
         artifacts.iter().find(|a| a.id == id).cloned()
     }
 
+    /// Directory artifacts are written to
+    pub fn artifact_dir(&self) -> &Path {
+        &self.artifact_dir
+    }
+
+    /// Write the manifest as it is now, e.g. after a run was interrupted mid-step
+    pub async fn flush(&self) -> Result<()> {
+        self.save_manifest().await
+    }
+
     /// List all artifacts
     pub async fn list_artifacts(&self) -> Vec<Artifact> {
         let artifacts = self.artifacts.read().await;
@@ -240,47 +747,694 @@ impl ArtifactManager {
             .collect()
     }
 
-    /// Save manifest to disk
-    async fn save_manifest(&self) -> Result<()> {
+    /// Add tags to an artifact
+    #[allow(dead_code)]
+    pub async fn tag_artifact(&self, id: &str, tags: &[&str]) -> Result<()> {
+        let mut artifacts = self.artifacts.write().await;
+        let artifact = artifacts
+            .iter_mut()
+            .find(|a| a.id == id)
+            .with_context(|| format!("Artifact not found: {}", id))?;
+        add_tags(&mut artifact.metadata, tags);
+        drop(artifacts);
+        self.save_manifest().await
+    }
+
+    /// Artifacts carrying `tag`, oldest first
+    pub async fn find_by_tag(&self, tag: &str) -> Vec<Artifact> {
+        let artifacts = self.artifacts.read().await;
+        artifacts
+            .iter()
+            .filter(|a| a.has_tag(tag))
+            .cloned()
+            .collect()
+    }
+
+    /// Artifacts whose name matches a glob such as `docs/**/*.md` or `*report*`
+    pub async fn find_by_glob(&self, pattern: &str) -> Result<Vec<Artifact>> {
+        let matcher = globset::Glob::new(pattern)
+            .with_context(|| format!("Invalid glob: {}", pattern))?
+            .compile_matcher();
+        let artifacts = self.artifacts.read().await;
+        Ok(artifacts
+            .iter()
+            .filter(|a| matcher.is_match(&a.name))
+            .cloned()
+            .collect())
+    }
+
+    /// Artifacts whose content matches a regular expression, with the matching lines
+    pub async fn search(&self, pattern: &str) -> Result<Vec<ArtifactMatch>> {
+        let regex = regex::Regex::new(pattern)
+            .with_context(|| format!("Invalid search pattern: {}", pattern))?;
         let artifacts = self.artifacts.read().await;
+        let mut matches = Vec::new();
+        for artifact in artifacts.iter() {
+            let content = match &artifact.content {
+                Some(content) => content.clone(),
+                None => fs::read_to_string(&artifact.path).unwrap_or_default(),
+            };
+            let lines: Vec<(usize, String)> = content
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| regex.is_match(line))
+                .map(|(i, line)| (i + 1, line.to_string()))
+                .collect();
+            if !lines.is_empty() {
+                matches.push(ArtifactMatch {
+                    artifact: artifact.clone(),
+                    lines,
+                });
+            }
+        }
+        Ok(matches)
+    }
+
+    /// Delete an artifact by name. The content is moved to the trash directory so the
+    /// operation can be undone. Names that are not artifacts are still recorded, so the
+    /// deletion can be carried over to the workspace.
+    pub async fn delete_artifact(&self, name: &str) -> Result<ArtifactOperation> {
+        let mut artifacts = self.artifacts.write().await;
+        let index = artifacts.iter().position(|a| a.name == name);
+        let path = match index {
+            Some(i) => artifacts[i].path.clone(),
+            None => self.artifact_path(name)?,
+        };
+
+        let backup_path = if path.is_file() {
+            let backup = self
+                .trash_dir()
+                .join(format!("{}_{}", uuid::Uuid::new_v4(), file_name_of(&path)));
+            fs::create_dir_all(self.trash_dir()).context("Failed to create trash directory")?;
+            fs::rename(&path, &backup).context("Failed to move artifact to trash")?;
+            Some(backup)
+        } else {
+            None
+        };
+
+        let operation = ArtifactOperation {
+            id: uuid::Uuid::new_v4().to_string(),
+            action: FileAction::Delete {
+                filename: name.to_string(),
+            },
+            timestamp: chrono::Utc::now(),
+            artifact: index.map(|i| artifacts.remove(i)),
+            backup_path,
+        };
+        drop(artifacts);
+
+        self.operations.write().await.push(operation.clone());
+        self.save_manifest().await?;
+
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::ArtifactDeleted {
+                    name: name.to_string(),
+                    path: path.to_string_lossy().to_string(),
+                })
+                .await;
+        }
+
+        Ok(operation)
+    }
+
+    /// Rename (move) an artifact, or another file in the artifact directory. Names that
+    /// are not there are still recorded, so the rename can be carried over to the
+    /// workspace. Fails if `to` exists, rather than overwrite it.
+    pub async fn rename_artifact(&self, from: &str, to: &str) -> Result<ArtifactOperation> {
+        let mut artifacts = self.artifacts.write().await;
+        let old_path = match artifacts.iter().find(|a| a.name == from) {
+            Some(artifact) => artifact.path.clone(),
+            None => self.artifact_path(from)?,
+        };
+        let new_path = self.artifact_path(to)?;
+        if new_path.exists() || artifacts.iter().any(|a| a.name == to) {
+            anyhow::bail!("Cannot rename {} to {}: {} already exists", from, to, to);
+        }
+        move_file(&old_path, &new_path).context("Failed to rename artifact")?;
+
+        let mut previous = None;
+        if let Some(artifact) = artifacts.iter_mut().find(|a| a.name == from) {
+            previous = Some(artifact.clone());
+            artifact.name = to.to_string();
+            artifact.path = new_path.clone();
+            artifact.updated_at = chrono::Utc::now();
+        }
+        drop(artifacts);
+
+        let operation = ArtifactOperation {
+            id: uuid::Uuid::new_v4().to_string(),
+            action: FileAction::Rename {
+                from: from.to_string(),
+                to: to.to_string(),
+            },
+            timestamp: chrono::Utc::now(),
+            artifact: previous,
+            backup_path: None,
+        };
+
+        self.operations.write().await.push(operation.clone());
+        self.save_manifest().await?;
+
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::ArtifactRenamed {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    path: new_path.to_string_lossy().to_string(),
+                })
+                .await;
+        }
+
+        Ok(operation)
+    }
+
+    /// Apply a file action requested by the model
+    pub async fn apply_file_action(&self, action: &FileAction) -> Result<ArtifactOperation> {
+        match action {
+            FileAction::Delete { filename } => self.delete_artifact(filename).await,
+            FileAction::Rename { from, to } => self.rename_artifact(from, to).await,
+        }
+    }
+
+    /// Files written, deleted or renamed through the manager, leaving out its own
+    /// manifest, versions and trash
+    pub async fn touched_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self
+            .artifacts
+            .read()
+            .await
+            .iter()
+            .map(|a| a.path.clone())
+            .collect();
+        for operation in self.operations.read().await.iter() {
+            let names = match &operation.action {
+                FileAction::Delete { filename } => vec![filename],
+                FileAction::Rename { from, to } => vec![from, to],
+            };
+            paths.extend(names.into_iter().filter_map(|name| self.artifact_path(name).ok()));
+        }
+        paths.retain(|path| !self.is_bookkeeping(path));
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+
+    /// Whether `path` is the manifest, its backup or a file in the versions or trash directory
+    fn is_bookkeeping(&self, path: &Path) -> bool {
+        let path = normalize_path(path);
+        if path.starts_with(normalize_path(&self.versions_dir()))
+            || path.starts_with(normalize_path(&self.trash_dir()))
+        {
+            return true;
+        }
+        path.parent() == Some(normalize_path(&self.artifact_dir).as_path())
+            && file_name_of(&path).starts_with(MANIFEST_FILE)
+    }
+
+    /// List recorded delete/rename operations, oldest first
+    pub async fn list_operations(&self) -> Vec<ArtifactOperation> {
+        self.operations.read().await.clone()
+    }
+
+    /// Undo the most recent delete or rename operation
+    pub async fn undo_last_operation(&self) -> Result<Option<ArtifactOperation>> {
+        let last_id = self.operations.read().await.last().map(|op| op.id.clone());
+        match last_id {
+            Some(id) => self.undo_operation(&id).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Undo a recorded delete or rename operation by ID
+    pub async fn undo_operation(&self, id: &str) -> Result<ArtifactOperation> {
+        let mut operations = self.operations.write().await;
+        let index = operations
+            .iter()
+            .position(|op| op.id == id)
+            .with_context(|| format!("Operation not found: {}", id))?;
+        let operation = operations[index].clone();
+
+        let mut artifacts = self.artifacts.write().await;
+        match &operation.action {
+            FileAction::Delete { filename } => {
+                let target = match &operation.artifact {
+                    Some(artifact) => artifact.path.clone(),
+                    None => self.artifact_path(filename)?,
+                };
+                if let Some(backup) = &operation.backup_path {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent).context("Failed to create parent directories")?;
+                    }
+                    fs::rename(backup, &target).context("Failed to restore deleted artifact")?;
+                }
+                if let Some(artifact) = &operation.artifact {
+                    artifacts.push(artifact.clone());
+                }
+            }
+            FileAction::Rename { from, to } => {
+                let current = artifacts.iter().position(|a| a.name == *to);
+                let renamed = match current {
+                    Some(i) => artifacts[i].path.clone(),
+                    None => self.artifact_path(to)?,
+                };
+                let original = match &operation.artifact {
+                    Some(artifact) => artifact.path.clone(),
+                    None => self.artifact_path(from)?,
+                };
+                if original.exists() || artifacts.iter().any(|a| a.name == *from) {
+                    anyhow::bail!("Cannot undo the rename: {} exists again", from);
+                }
+                move_file(&renamed, &original).context("Failed to revert artifact rename")?;
+                if let Some(original) = &operation.artifact
+                    && let Some(i) = current
+                {
+                    let current = &mut artifacts[i];
+                    current.name = original.name.clone();
+                    current.path = original.path.clone();
+                    current.updated_at = chrono::Utc::now();
+                }
+            }
+        }
+
+        operations.remove(index);
+        drop(artifacts);
+        drop(operations);
+        self.save_manifest().await?;
+
+        Ok(operation)
+    }
+
+    /// Path of the artifact `name` under the artifact directory. Names may contain
+    /// subdirectories but must not be absolute or lead out of the directory,
+    /// including through a symlinked subdirectory, nor name the manager's own
+    /// manifest, versions or trash.
+    fn artifact_path(&self, name: &str) -> Result<PathBuf> {
+        let mut relative = PathBuf::new();
+        for component in Path::new(name).components() {
+            match component {
+                Component::Normal(part) => relative.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !relative.pop() {
+                        anyhow::bail!("Artifact path {} leaves the artifact directory", name);
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => {
+                    anyhow::bail!("Artifact path {} must be relative", name);
+                }
+            }
+        }
+        if relative.as_os_str().is_empty() {
+            anyhow::bail!("Invalid artifact name: {:?}", name);
+        }
+
+        let path = self.artifact_dir.join(&relative);
+        if self.is_bookkeeping(&path) {
+            anyhow::bail!("Artifact path {} is reserved for the manifest, versions and trash", name);
+        }
+        let existing = path
+            .ancestors()
+            .find(|p| p.exists())
+            .unwrap_or(&self.artifact_dir);
+        if let (Ok(root), Ok(resolved)) =
+            (self.artifact_dir.canonicalize(), existing.canonicalize())
+            && !resolved.starts_with(&root)
+        {
+            anyhow::bail!(
+                "Artifact path {} resolves outside the artifact directory",
+                name
+            );
+        }
+        Ok(path)
+    }
+
+    fn trash_dir(&self) -> PathBuf {
+        self.artifact_dir.join(".trash")
+    }
+
+    fn versions_dir(&self) -> PathBuf {
+        self.artifact_dir.join(".versions")
+    }
+
+    /// Store `content` under its hash, once per distinct content
+    fn store_version(&self, version: usize, content: &str) -> Result<ArtifactVersion> {
+        let sha256 = content_sha256(content);
+        let blob = self.versions_dir().join(&sha256);
+        if !blob.exists() {
+            fs::create_dir_all(self.versions_dir())
+                .context("Failed to create versions directory")?;
+            fs::write(&blob, content).context("Failed to store artifact version")?;
+        }
+        Ok(ArtifactVersion {
+            version,
+            sha256,
+            size: content.len(),
+            created_at: chrono::Utc::now(),
+        })
+    }
+
+    /// Save manifest to disk. It is written to a temporary file and renamed over
+    /// the old one, so a crash mid-write never leaves a truncated manifest; the
+    /// previous manifest is kept as `manifest.json.bak`.
+    async fn save_manifest(&self) -> Result<()> {
+        let _guard = self.manifest_lock.lock().await;
         let manifest = ArtifactManifest {
-            version: "1.0".to_string(),
-            artifacts: artifacts.clone(),
+            version: MANIFEST_VERSION.to_string(),
+            artifacts: self.artifacts.read().await.clone(),
             metadata: HashMap::new(),
+            operations: self.operations.read().await.clone(),
         };
-
-        let manifest_path = self.artifact_dir.join("manifest.json");
         let json =
             serde_json::to_string_pretty(&manifest).context("Failed to serialize manifest")?;
 
-        fs::write(manifest_path, json).context("Failed to write manifest")?;
+        let manifest_path = self.artifact_dir.join(MANIFEST_FILE);
+        let temp_path =
+            self.artifact_dir
+                .join(format!(".{}.{}.tmp", MANIFEST_FILE, uuid::Uuid::new_v4()));
+        let written = fs::File::create(&temp_path).and_then(|mut file| {
+            file.write_all(json.as_bytes())?;
+            file.sync_all()
+        });
+        if let Err(e) = written {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e).context("Failed to write manifest");
+        }
+
+        if manifest_path.exists() {
+            let _ = fs::copy(&manifest_path, self.manifest_backup_path());
+        }
+        if let Err(e) = fs::rename(&temp_path, &manifest_path) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(e).context("Failed to replace manifest");
+        }
 
         Ok(())
     }
 
-    /// Load manifest from disk
-    #[allow(dead_code)]
+    fn manifest_backup_path(&self) -> PathBuf {
+        self.artifact_dir.join(format!("{}.bak", MANIFEST_FILE))
+    }
+
+    /// Load manifest from disk. A corrupt manifest is set aside and replaced by
+    /// the backup of the previous one, or by whatever entries can be salvaged.
     fn load_manifest(&self) -> Result<ArtifactManifest> {
-        let manifest_path = self.artifact_dir.join("manifest.json");
+        let manifest_path = self.artifact_dir.join(MANIFEST_FILE);
 
+        let empty = ArtifactManifest {
+            version: MANIFEST_VERSION.to_string(),
+            artifacts: Vec::new(),
+            metadata: HashMap::new(),
+            operations: Vec::new(),
+        };
         if !manifest_path.exists() {
-            return Ok(ArtifactManifest {
-                version: "1.0".to_string(),
-                artifacts: Vec::new(),
-                metadata: HashMap::new(),
-            });
+            return Ok(empty);
         }
 
-        let json = fs::read_to_string(manifest_path).context("Failed to read manifest")?;
+        let json = fs::read_to_string(&manifest_path).context("Failed to read manifest")?;
+        let error = match serde_json::from_str::<ArtifactManifest>(&json) {
+            Ok(manifest) => return check_manifest_version(manifest),
+            Err(e) => e,
+        };
 
-        let manifest: ArtifactManifest =
-            serde_json::from_str(&json).context("Failed to parse manifest")?;
+        let corrupt_path = self.artifact_dir.join(format!(
+            "{}.corrupt-{}",
+            MANIFEST_FILE,
+            chrono::Utc::now().format("%Y%m%d%H%M%S")
+        ));
+        log::warn!(
+            "Artifact manifest is corrupt ({}); moved it to {}",
+            error,
+            corrupt_path.display()
+        );
+        fs::rename(&manifest_path, &corrupt_path)
+            .context("Failed to set aside corrupt manifest")?;
+
+        if let Ok(backup) = fs::read_to_string(self.manifest_backup_path())
+            && let Ok(manifest) = serde_json::from_str::<ArtifactManifest>(&backup)
+        {
+            log::warn!("Restored the artifact manifest from its backup");
+            return check_manifest_version(manifest);
+        }
 
+        let manifest = salvage_manifest(&json).unwrap_or(empty);
+        log::warn!(
+            "Recovered {} artifact(s) and {} operation(s) from the corrupt manifest",
+            manifest.artifacts.len(),
+            manifest.operations.len()
+        );
         Ok(manifest)
     }
 
+    /// Reject content larger than `max_artifact_mb`
+    fn check_artifact_size(&self, name: &str, content: &str) -> Result<()> {
+        let max = self.limits.max_artifact_mb * BYTES_PER_MB;
+        if max > 0 && content.len() as u64 > max {
+            anyhow::bail!(
+                "Artifact {} is {:.1} MB, over the {} MB limit (artifacts.max_artifact_mb)",
+                name,
+                content.len() as f64 / BYTES_PER_MB as f64,
+                self.limits.max_artifact_mb
+            );
+        }
+        Ok(())
+    }
+
+    /// Refuse content carrying redaction placeholders the file at `path`, or else the
+    /// project file it stands for, did not have
+    async fn check_placeholders(&self, name: &str, path: &Path, content: &str) -> Result<()> {
+        let previous = match fs::read_to_string(path) {
+            Ok(previous) => previous,
+            Err(_) => self.baseline_of(path).await,
+        };
+        redaction::check_placeholders(name, content, &previous)
+    }
+
+    /// Keep the artifact directory under `max_total_mb` by pruning versions
+    /// and trashed files the manifest no longer needs, oldest first. Other
+    /// files are never pruned; if they exceed the quota a warning is logged once.
+    async fn enforce_quota(&self) {
+        let max = self.limits.max_total_mb * BYTES_PER_MB;
+        if max == 0 || !self.pruning_allowed() {
+            return;
+        }
+        let mut total = dir_size(&self.artifact_dir);
+        if total <= max {
+            return;
+        }
+
+        let mut pruned = 0;
+        for (path, size, _) in self.prunable_files().await {
+            if total <= max {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total = total.saturating_sub(size);
+                pruned += 1;
+            }
+        }
+        if pruned > 0 {
+            log::warn!(
+                "Artifact directory exceeded {} MB; pruned {} unused file(s) of artifacts",
+                self.limits.max_total_mb,
+                pruned
+            );
+            self.emit_pruned(pruned, "quota").await;
+        }
+        if total > max && !self.quota_warned.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "Artifact directory {} uses {:.1} MB, over the {} MB quota (artifacts.max_total_mb)",
+                self.artifact_dir.display(),
+                total as f64 / BYTES_PER_MB as f64,
+                self.limits.max_total_mb
+            );
+        }
+    }
+
+    /// Keep the history of the last `keep_runs` runs under `state_dir` (run
+    /// summaries, transcripts and redaction reports). Versions and undo records
+    /// older than the oldest kept run are dropped from the manifest, and the
+    /// files only they needed pruned. Returns the number of entries removed.
+    pub async fn enforce_retention(&self, state_dir: &Path) -> Result<usize> {
+        let keep = self.limits.keep_runs;
+        if keep == 0 {
+            return Ok(0);
+        }
+        let runs_dir = RunRecord::runs_dir(state_dir);
+        let mut run_ids: Vec<String> = fs::read_dir(&runs_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if run_ids.len() <= keep {
+            return Ok(0);
+        }
+        // Run IDs start with their timestamp, so they sort oldest first
+        run_ids.sort();
+        let oldest_kept = run_ids[run_ids.len() - keep].clone();
+        let cutoff: SystemTime = RunRecord::load(state_dir, Some(&oldest_kept))
+            .map(|record| record.started_at.into())
+            .or_else(|_| fs::metadata(runs_dir.join(&oldest_kept))?.modified())
+            .context("Failed to determine the start of the oldest kept run")?;
+
+        let mut removed = 0;
+        for dir in ["runs", "transcripts", "redaction_reports"] {
+            let Ok(entries) = fs::read_dir(state_dir.join(dir)) else {
+                continue;
+            };
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                let run_id = path.file_stem().unwrap_or_default().to_string_lossy();
+                if *run_id >= *oldest_kept {
+                    continue;
+                }
+                let result = if path.is_dir() {
+                    fs::remove_dir_all(&path)
+                } else {
+                    fs::remove_file(&path)
+                };
+                if result.is_ok() {
+                    removed += 1;
+                }
+            }
+        }
+
+        let mut pruned = 0;
+        if self.pruning_allowed() {
+            self.forget_history_before(cutoff.into()).await?;
+            for (path, _, _) in self.prunable_files().await {
+                if fs::remove_file(&path).is_ok() {
+                    pruned += 1;
+                }
+            }
+        }
+        if pruned > 0 {
+            self.emit_pruned(pruned, "retention").await;
+        }
+        if removed + pruned > 0 {
+            log::info!(
+                "Kept the last {} runs: removed {} run record(s) and {} old file(s) of artifacts",
+                keep,
+                removed,
+                pruned
+            );
+        }
+        Ok(removed + pruned)
+    }
+
+    /// Drop the undo records and all but the latest version of each artifact
+    /// from before `cutoff`
+    async fn forget_history_before(&self, cutoff: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        let mut changed = false;
+        {
+            let mut operations = self.operations.write().await;
+            let before = operations.len();
+            operations.retain(|operation| operation.timestamp >= cutoff);
+            changed |= operations.len() != before;
+            let mut artifacts = self.artifacts.write().await;
+            for artifact in artifacts.iter_mut() {
+                let latest = artifact.versions.len().saturating_sub(1);
+                let before = artifact.versions.len();
+                let mut index = 0;
+                artifact.versions.retain(|version| {
+                    index += 1;
+                    index > latest || version.created_at >= cutoff
+                });
+                changed |= artifact.versions.len() != before;
+            }
+        }
+        if changed {
+            self.save_manifest().await?;
+        }
+        Ok(())
+    }
+
+    /// Whether pruning may delete files: not when the artifact directory is
+    /// the project itself or holds it, where a bug could delete the user's work
+    fn pruning_allowed(&self) -> bool {
+        let Some(root) = &self.project_root else {
+            return true;
+        };
+        let canonical =
+            |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| normalize_path(path));
+        if canonical(root).starts_with(canonical(&self.artifact_dir)) {
+            if !self.pruning_refused.swap(true, Ordering::Relaxed) {
+                log::warn!(
+                    "Not pruning {}: the artifact directory holds the project",
+                    self.artifact_dir.display()
+                );
+            }
+            return false;
+        }
+        true
+    }
+
+    /// Files the manifest recorded but no longer needs: versions no artifact
+    /// refers to and trashed files no undoable operation refers to. Files
+    /// outside the version store and the trash are never listed. Sorted
+    /// oldest first as (path, size, modified).
+    async fn prunable_files(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let artifacts = self.artifacts.read().await;
+        let operations = self.operations.read().await;
+        let records = artifacts
+            .iter()
+            .chain(operations.iter().filter_map(|op| op.artifact.as_ref()));
+        let blobs: HashSet<String> = records
+            .flat_map(|artifact| artifact.versions.iter().map(|v| v.sha256.clone()))
+            .collect();
+        let backups: HashSet<PathBuf> = operations
+            .iter()
+            .filter_map(|op| op.backup_path.as_deref())
+            .map(normalize_path)
+            .collect();
+        drop(artifacts);
+        drop(operations);
+
+        let walk = |dir: PathBuf| {
+            ignore::WalkBuilder::new(dir)
+                .standard_filters(false)
+                .hidden(false)
+                .build()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        };
+        let unused_versions = walk(self.versions_dir()).filter(|e| {
+            let name = e.file_name().to_string_lossy();
+            // Version blobs are named by their hash; anything else is not ours
+            is_sha256(&name) && !blobs.contains(name.as_ref())
+        });
+        let unused_backups =
+            walk(self.trash_dir()).filter(|e| !backups.contains(&normalize_path(e.path())));
+        let mut files: Vec<_> = unused_versions
+            .chain(unused_backups)
+            .filter_map(|e| {
+                let metadata = e.metadata().ok()?;
+                Some((e.into_path(), metadata.len(), metadata.modified().ok()?))
+            })
+            .collect();
+        files.sort_by_key(|(_, _, modified)| *modified);
+        files
+    }
+
+    async fn emit_pruned(&self, files: usize, reason: &str) {
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::Custom {
+                    event_type: "artifacts_pruned".to_string(),
+                    data: serde_json::json!({ "files": files, "reason": reason }),
+                })
+                .await;
+        }
+    }
+
     /// Clean up orphaned files
     pub async fn cleanup(&self) -> Result<()> {
+        if !self.pruning_allowed() {
+            return Ok(());
+        }
         let artifacts = self.artifacts.read().await;
         let artifact_paths: Vec<_> = artifacts.iter().map(|a| a.path.clone()).collect();
 
@@ -292,8 +1446,9 @@ impl ArtifactManager {
             let entry = entry?;
             let path = entry.path();
 
-            // Skip manifest and directories
-            if path.is_dir() || path.file_name() == Some("manifest.json".as_ref()) {
+            // Skip the manifest, its backups and directories
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if path.is_dir() || name.starts_with(MANIFEST_FILE) {
                 continue;
             }
 
@@ -307,5 +1462,711 @@ impl ArtifactManager {
     }
 }
 
+fn is_sha256(name: &str) -> bool {
+    name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Total size of the files under `dir`
+fn dir_size(dir: &Path) -> u64 {
+    ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .hidden(false)
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|e| e.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// Ask on the terminal how to resolve a conflict; merges when not interactive
+async fn prompt_conflict_resolution(path: &Path) -> ConflictStrategy {
+    if !std::io::stdin().is_terminal() {
+        return ConflictStrategy::Merge;
+    }
+
+    let question = format!(
+        "\n{} was modified on disk during this run. [m]erge, [o]verwrite or [k]eep? [m] ",
+        path.display()
+    );
+    tokio::task::spawn_blocking(move || {
+        eprint!("{}", question);
+        let _ = std::io::stderr().flush();
+        let mut answer = String::new();
+        let _ = std::io::stdin().read_line(&mut answer);
+        match answer.trim().to_lowercase().as_str() {
+            "o" | "overwrite" => ConflictStrategy::Overwrite,
+            "k" | "keep" => ConflictStrategy::Keep,
+            _ => ConflictStrategy::Merge,
+        }
+    })
+    .await
+    .unwrap_or(ConflictStrategy::Merge)
+}
+
+/// Absolute, lexically normalized form of `path`
+fn absolute_path(path: &Path) -> PathBuf {
+    normalize_path(&std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+/// Lexically normalize a path so `./a/../b` and `b` compare equal
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Refuse manifests written by a newer schema rather than silently dropping fields
+fn check_manifest_version(manifest: ArtifactManifest) -> Result<ArtifactManifest> {
+    let major = |v: &str| v.split('.').next().and_then(|m| m.parse::<u32>().ok());
+    if let (Some(found), Some(supported)) = (major(&manifest.version), major(MANIFEST_VERSION))
+        && found > supported
+    {
+        anyhow::bail!(
+            "Artifact manifest version {} is newer than the supported {}",
+            manifest.version,
+            MANIFEST_VERSION
+        );
+    }
+    Ok(manifest)
+}
+
+/// The artifacts and operations of a manifest that still parse individually
+fn salvage_manifest(json: &str) -> Option<ArtifactManifest> {
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    let entries = |key: &str| {
+        value
+            .get(key)
+            .and_then(|v| v.as_array())
+            .cloned()
+            .unwrap_or_default()
+    };
+    Some(ArtifactManifest {
+        version: MANIFEST_VERSION.to_string(),
+        artifacts: entries("artifacts")
+            .into_iter()
+            .filter_map(|a| serde_json::from_value(a).ok())
+            .collect(),
+        metadata: HashMap::new(),
+        operations: entries("operations")
+            .into_iter()
+            .filter_map(|o| serde_json::from_value(o).ok())
+            .collect(),
+    })
+}
+
+fn content_sha256(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Move the file at `from`, if there is one, to `to`, creating its directory
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if !from.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).context("Failed to create parent directories")?;
+    }
+    fs::rename(from, to)?;
+    Ok(())
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "artifact".to_string())
+}
+
 // Implement EventEmitter trait
 impl_event_emitter!(ArtifactManager);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_history_and_revert() {
+        let dir =
+            std::env::temp_dir().join(format!("cli_engineer_artifacts_{}", uuid::Uuid::new_v4()));
+        let manager = ArtifactManager::new(dir.clone()).unwrap();
+        let artifact = manager
+            .create_artifact(
+                "notes.md".to_string(),
+                ArtifactType::Documentation,
+                "first".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        manager
+            .update_artifact(&artifact.id, "second".to_string())
+            .await
+            .unwrap();
+        manager
+            .update_artifact(&artifact.id, "second".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            manager
+                .get_artifact_history(&artifact.id)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        manager.revert_artifact(&artifact.id, 1).await.unwrap();
+        let history = manager.get_artifact_history(&artifact.id).await.unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].sha256, history[2].sha256);
+        assert_eq!(fs::read_to_string(dir.join("notes.md")).unwrap(), "first");
+        assert!(manager.revert_artifact(&artifact.id, 9).await.is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rewrites_are_versions_of_one_artifact() {
+        let (root, manager) = limited_manager("rewrite");
+        let write = |content: &str, iteration: usize| {
+            let mut metadata = HashMap::new();
+            add_tags(&mut metadata, &[&format!("iteration:{}", iteration)]);
+            manager.create_artifact(
+                "src/lib.rs".to_string(),
+                ArtifactType::SourceCode,
+                content.to_string(),
+                metadata,
+            )
+        };
+        let first = write("fn a() {}\n", 1).await.unwrap();
+        let second = write("fn b() {}\n", 2).await.unwrap();
+
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.versions.len(), 2);
+        assert_eq!(second.tags(), ["iteration:1", "iteration:2"]);
+        assert_eq!(manager.list_artifacts().await.len(), 1);
+
+        manager.revert_artifact(&first.id, 1).await.unwrap();
+        assert_eq!(fs::read_to_string(&first.path).unwrap(), "fn a() {}\n");
+        assert_eq!(
+            manager.get_artifact_history(&first.id).await.unwrap().len(),
+            3
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tags_and_queries() {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_tags_{}", uuid::Uuid::new_v4()));
+        let manager = ArtifactManager::new(dir.clone()).unwrap();
+        for (iteration, name, content) in [
+            (1, "docs/report.md", "# Report\nAll good\n"),
+            (2, "docs/report_2.md", "# Report\nTODO: fix login\n"),
+            (2, "src/main.rs", "fn main() {}\n"),
+        ] {
+            let mut metadata = HashMap::new();
+            add_tags(&mut metadata, &[&format!("iteration:{}", iteration)]);
+            manager
+                .create_artifact(
+                    name.to_string(),
+                    ArtifactType::Documentation,
+                    content.to_string(),
+                    metadata,
+                )
+                .await
+                .unwrap();
+        }
+
+        let names =
+            |artifacts: Vec<Artifact>| artifacts.into_iter().map(|a| a.name).collect::<Vec<_>>();
+        assert_eq!(
+            names(manager.find_by_tag("iteration:2").await),
+            ["docs/report_2.md", "src/main.rs"]
+        );
+        assert_eq!(
+            names(manager.find_by_glob("docs/*report*").await.unwrap()),
+            ["docs/report.md", "docs/report_2.md"]
+        );
+
+        let main = manager.find_by_glob("*.rs").await.unwrap().remove(0);
+        manager
+            .tag_artifact(&main.id, &["entry-point", "iteration:2"])
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.get_artifact(&main.id).await.unwrap().tags(),
+            ["iteration:2", "entry-point"]
+        );
+
+        let found = manager.search("(?i)todo").await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].lines, [(2, "TODO: fix login".to_string())]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_nested_and_escaping_paths() {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_paths_{}", uuid::Uuid::new_v4()));
+        let manager = ArtifactManager::new(dir.clone()).unwrap();
+        let create = |name: &str| {
+            manager.create_artifact(
+                name.to_string(),
+                ArtifactType::Documentation,
+                String::new(),
+                HashMap::new(),
+            )
+        };
+
+        create("docs/guide/usage.md").await.unwrap();
+        assert!(dir.join("docs/guide/usage.md").is_file());
+        create("docs/../notes.md").await.unwrap();
+        assert!(dir.join("notes.md").is_file());
+
+        for name in [
+            "../../etc/thing",
+            "docs/../../x.md",
+            "/etc/thing",
+            "manifest.json",
+            "./manifest.json.bak",
+            "docs/../manifest.json",
+            ".versions/0123abcd",
+            ".trash/notes.md",
+        ] {
+            assert!(create(name).await.is_err(), "{} was accepted", name);
+        }
+        assert!(
+            manager
+                .rename_artifact("notes.md", "../notes.md")
+                .await
+                .is_err()
+        );
+        assert!(
+            manager
+                .rename_artifact("notes.md", "manifest.json")
+                .await
+                .is_err()
+        );
+        assert!(manager.delete_artifact("../outside.md").await.is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(std::env::temp_dir(), dir.join("link")).unwrap();
+            assert!(create("link/escaped.md").await.is_err());
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_manifest_recovery() {
+        let dir =
+            std::env::temp_dir().join(format!("cli_engineer_manifest_{}", uuid::Uuid::new_v4()));
+        let manager = ArtifactManager::new(dir.clone()).unwrap();
+        for name in ["a.md", "b.md"] {
+            manager
+                .create_artifact(
+                    name.to_string(),
+                    ArtifactType::Documentation,
+                    name.to_string(),
+                    HashMap::new(),
+                )
+                .await
+                .unwrap();
+        }
+
+        // A truncated write falls back to the previous manifest
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let json = fs::read_to_string(&manifest_path).unwrap();
+        fs::write(&manifest_path, &json[..json.len() / 2]).unwrap();
+        let reloaded = ArtifactManager::new(dir.clone()).unwrap();
+        reloaded.init().await.unwrap();
+        assert_eq!(reloaded.list_artifacts().await.len(), 1);
+
+        // Without a backup, entries that still parse are kept
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["artifacts"][1]["path"] = serde_json::json!(42);
+        fs::write(&manifest_path, value.to_string()).unwrap();
+        fs::remove_file(dir.join("manifest.json.bak")).unwrap();
+        let reloaded = ArtifactManager::new(dir.clone()).unwrap();
+        reloaded.init().await.unwrap();
+        assert_eq!(reloaded.list_artifacts().await.len(), 1);
+        assert_eq!(reloaded.list_artifacts().await[0].name, "a.md");
+
+        fs::write(
+            &manifest_path,
+            r#"{"version": "9.0", "artifacts": [], "metadata": {}}"#,
+        )
+        .unwrap();
+        assert!(
+            ArtifactManager::new(dir.clone())
+                .unwrap()
+                .init()
+                .await
+                .is_err()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A manager with a 1 MB quota keeping one run, in a fresh directory
+    fn limited_manager(name: &str) -> (PathBuf, ArtifactManager) {
+        let root = std::env::temp_dir()
+            .join(format!("cli_engineer_{}_{}", name, uuid::Uuid::new_v4()));
+        let mut manager = ArtifactManager::new(root.join("artifacts")).unwrap();
+        manager.set_limits(ArtifactsConfig {
+            max_artifact_mb: 1,
+            max_total_mb: 1,
+            keep_runs: 1,
+        });
+        (root, manager)
+    }
+
+    fn kb(n: usize) -> String {
+        "x".repeat(n * 1024)
+    }
+
+    fn save_run(state_dir: &Path, run_id: &str, started_at: chrono::DateTime<chrono::Utc>) {
+        RunRecord {
+            run_id: run_id.to_string(),
+            command: "review".to_string(),
+            prompt: String::new(),
+            started_at,
+            finished_at: started_at,
+            success: true,
+            error: None,
+            artifacts: Vec::new(),
+            review: None,
+            pull_request: None,
+            stalled: None,
+            iterations: Vec::new(),
+            api_calls: 0,
+            tokens: 0,
+            cost: 0.0,
+            outcome: None,
+        }
+        .save(state_dir)
+        .unwrap();
+        fs::create_dir_all(state_dir.join("transcripts").join(run_id)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_artifacts_over_the_size_limit_are_refused() {
+        let (root, manager) = limited_manager("size_limit");
+        let too_big = manager
+            .create_artifact("big.txt".to_string(), ArtifactType::Data, kb(1100), HashMap::new())
+            .await;
+        assert!(too_big.is_err());
+        assert!(!root.join("artifacts/big.txt").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_quota_prunes_only_unused_versions() {
+        let (root, manager) = limited_manager("quota");
+        let dir = root.join("artifacts");
+        // A file the manifest never recorded is left alone, however large
+        fs::write(dir.join("stale.txt"), kb(300)).unwrap();
+        fs::create_dir_all(dir.join(".versions")).unwrap();
+        let unused = dir.join(".versions").join(content_sha256("gone"));
+        fs::write(&unused, kb(300)).unwrap();
+        for name in ["report.txt", "notes.txt"] {
+            manager
+                .create_artifact(name.to_string(), ArtifactType::Data, kb(200), HashMap::new())
+                .await
+                .unwrap();
+        }
+        assert!(!unused.exists());
+        assert!(dir.join("stale.txt").exists());
+        assert!(dir.join("report.txt").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_retention_forgets_history_of_old_runs() {
+        let (root, manager) = limited_manager("retention");
+        let dir = root.join("artifacts");
+        let state_dir = root.join(".cli_engineer");
+        let artifact = manager
+            .create_artifact(
+                "notes.txt".to_string(),
+                ArtifactType::Data,
+                "v1".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        manager.update_artifact(&artifact.id, "v2".to_string()).await.unwrap();
+        fs::write(dir.join("old.txt"), "old").unwrap();
+
+        let later = chrono::Utc::now() + chrono::Duration::seconds(5);
+        save_run(&state_dir, "20250101_090000_aaaa", later);
+        save_run(&state_dir, "20250102_090000_bbbb", later);
+        // Two run records and a transcript, and the first version of notes.txt
+        assert_eq!(manager.enforce_retention(&state_dir).await.unwrap(), 3);
+        assert!(!state_dir.join("runs/20250101_090000_aaaa").exists());
+        assert!(!state_dir.join("transcripts/20250101_090000_aaaa").exists());
+        assert!(state_dir.join("runs/20250102_090000_bbbb").exists());
+        assert!(!dir.join(".versions").join(content_sha256("v1")).exists());
+        assert!(dir.join(".versions").join(content_sha256("v2")).exists());
+        assert_eq!(manager.list_artifacts().await[0].versions.len(), 1);
+        assert!(dir.join("old.txt").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_nothing_is_pruned_when_the_artifact_dir_holds_the_project() {
+        let (root, mut manager) = limited_manager("project_root");
+        let dir = root.join("artifacts");
+        manager.set_project_root(dir.clone());
+        fs::create_dir_all(dir.join(".versions")).unwrap();
+        let unused = dir.join(".versions").join(content_sha256("gone"));
+        fs::write(&unused, kb(1100)).unwrap();
+        manager
+            .create_artifact("a.txt".to_string(), ArtifactType::Data, kb(10), HashMap::new())
+            .await
+            .unwrap();
+        assert!(unused.exists());
+        manager.cleanup().await.unwrap();
+        assert!(unused.exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_touched_paths_cover_deletes_and_renames() {
+        let (root, manager) = limited_manager("touched");
+        let dir = root.join("artifacts");
+        for name in ["a.txt", "b.txt"] {
+            manager
+                .create_artifact(
+                    name.to_string(),
+                    ArtifactType::Data,
+                    "x".to_string(),
+                    HashMap::new(),
+                )
+                .await
+                .unwrap();
+        }
+        manager.delete_artifact("a.txt").await.unwrap();
+        manager.rename_artifact("b.txt", "sub/c.txt").await.unwrap();
+        assert_eq!(
+            manager.touched_paths().await,
+            vec![dir.join("a.txt"), dir.join("b.txt"), dir.join("sub/c.txt")]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rename_refuses_to_overwrite() {
+        let (root, manager) = limited_manager("rename_over");
+        let dir = root.join("artifacts");
+        manager
+            .create_artifact(
+                "a.txt".to_string(),
+                ArtifactType::Data,
+                "a".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        let error = manager.rename_artifact("a.txt", "b.txt").await.unwrap_err();
+        assert!(error.to_string().contains("already exists"), "{}", error);
+        assert_eq!(fs::read_to_string(dir.join("b.txt")).unwrap(), "b");
+        assert!(dir.join("a.txt").exists());
+        assert!(manager.list_operations().await.is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_renames_are_undone() {
+        let (root, manager) = limited_manager("rename_undo");
+        let dir = root.join("artifacts");
+        manager
+            .create_artifact(
+                "a.txt".to_string(),
+                ArtifactType::Data,
+                "a".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        // A file in the artifact directory that is not an artifact
+        fs::write(dir.join("plain.txt"), "plain").unwrap();
+        manager.rename_artifact("a.txt", "sub/b.txt").await.unwrap();
+        manager.rename_artifact("plain.txt", "moved.txt").await.unwrap();
+        assert!(!dir.join("plain.txt").exists());
+        assert_eq!(manager.list_operations().await.len(), 2);
+
+        manager.undo_last_operation().await.unwrap().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("plain.txt")).unwrap(), "plain");
+        assert!(!dir.join("moved.txt").exists());
+
+        manager.undo_last_operation().await.unwrap().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("a.txt")).unwrap(), "a");
+        assert!(!dir.join("sub/b.txt").exists());
+        let artifacts = manager.list_artifacts().await;
+        assert_eq!(artifacts.len(), 1);
+        assert_eq!(artifacts[0].name, "a.txt");
+        assert!(manager.undo_last_operation().await.unwrap().is_none());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_redaction_placeholders_are_not_written() {
+        let (root, manager) = limited_manager("placeholders");
+        let dir = root.join("artifacts");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("settings.toml"), "token = \"abc123\"\n").unwrap();
+        let echoed = "token = \"[REDACTED:credential_assignment]\"\n".to_string();
+        let result = manager
+            .create_artifact(
+                "settings.toml".to_string(),
+                ArtifactType::Configuration,
+                echoed,
+                HashMap::new(),
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join("settings.toml")).unwrap(),
+            "token = \"abc123\"\n"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_edits_to_project_files_in_context_are_merged() {
+        let (root, mut manager) = limited_manager("merge_project");
+        manager.set_project_root(root.clone());
+        fs::write(root.join("notes.md"), "a\nb\nc\n").unwrap();
+        manager
+            .record_snapshot(&root.join("./notes.md"), "a\nb\nc\n".to_string())
+            .await;
+        fs::write(root.join("notes.md"), "a, edited\nb\nc\n").unwrap();
+
+        let artifact = manager
+            .create_artifact(
+                "notes.md".to_string(),
+                ArtifactType::Documentation,
+                "a\nb\nc, generated\n".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(artifact.path, root.join("artifacts/notes.md"));
+        assert_eq!(
+            fs::read_to_string(&artifact.path).unwrap(),
+            "a, edited\nb\nc, generated\n"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keep_leaves_the_edited_file_in_place() {
+        let (root, mut manager) = limited_manager("keep");
+        manager.set_conflict_strategy(ConflictStrategy::Keep);
+        let artifact = manager
+            .create_artifact(
+                "notes.md".to_string(),
+                ArtifactType::Documentation,
+                "one\n".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        fs::write(&artifact.path, "one, edited\n").unwrap();
+        manager
+            .update_artifact(&artifact.id, "two\n".to_string())
+            .await
+            .unwrap();
+
+        assert_eq!(fs::read_to_string(&artifact.path).unwrap(), "one, edited\n");
+        let generated = root.join("artifacts/notes.md.generated");
+        assert_eq!(fs::read_to_string(generated).unwrap(), "two\n");
+        let kept = manager.get_artifact(&artifact.id).await.unwrap();
+        assert_eq!(kept.path, artifact.path);
+        assert_eq!(kept.content.as_deref(), Some("one, edited\n"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rewrites_are_not_external_modifications() {
+        let (root, manager) = limited_manager("rewrite_refresh");
+        for content in ["fn a() {}\n", "fn b() {}\n"] {
+            manager
+                .create_artifact(
+                    "lib.rs".to_string(),
+                    ArtifactType::SourceCode,
+                    content.to_string(),
+                    HashMap::new(),
+                )
+                .await
+                .unwrap();
+        }
+        assert!(manager.refresh_modified().await.unwrap().is_empty());
+
+        // An older entry for the file, as manifests written before rewrites
+        // were versioned hold, is not compared
+        let mut stale = manager.list_artifacts().await.remove(0);
+        stale.id = uuid::Uuid::new_v4().to_string();
+        stale.versions.truncate(1);
+        manager.artifacts.write().await.insert(0, stale);
+        assert!(manager.refresh_modified().await.unwrap().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_external_modification() {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_dirty_{}", uuid::Uuid::new_v4()));
+        let manager = ArtifactManager::new(dir.clone()).unwrap();
+        let artifact = manager
+            .create_artifact(
+                "notes.md".to_string(),
+                ArtifactType::Documentation,
+                "one\n".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        assert!(manager.refresh_modified().await.unwrap().is_empty());
+
+        fs::write(&artifact.path, "one, edited\n").unwrap();
+        let modified = manager.refresh_modified().await.unwrap();
+        assert_eq!(modified.len(), 1);
+        assert_eq!(modified[0].content.as_deref(), Some("one, edited\n"));
+        assert_eq!(modified[0].versions.len(), 2);
+        assert!(manager.refresh_modified().await.unwrap().is_empty());
+
+        // An edit right before an update is kept as its own version
+        fs::write(&artifact.path, "two, edited\n").unwrap();
+        manager
+            .update_artifact(&artifact.id, "two, edited\n".to_string())
+            .await
+            .unwrap();
+        let history = manager.get_artifact_history(&artifact.id).await.unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(
+            manager.version_content(&artifact.id, 3).await.unwrap(),
+            "two, edited\n"
+        );
+
+        // Edits made between runs are picked up when the manifest is loaded
+        fs::write(&artifact.path, "three\n").unwrap();
+        let reloaded = ArtifactManager::new(dir.clone()).unwrap();
+        reloaded.init().await.unwrap();
+        let artifact = reloaded.get_artifact(&artifact.id).await.unwrap();
+        assert_eq!(artifact.content.as_deref(), Some("three\n"));
+        assert_eq!(artifact.versions.len(), 4);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
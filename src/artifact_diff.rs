@@ -0,0 +1,143 @@
+use std::path::Path;
+
+use anyhow::Result;
+use colored::*;
+
+use crate::artifact::{Artifact, ArtifactManager};
+
+/// Unified diff between two contents; a missing `old` is shown as a new file
+pub fn unified_diff(old: Option<&str>, new: &str, old_name: &str, new_name: &str) -> String {
+    let old_header = if old.is_some() {
+        old_name.to_string()
+    } else {
+        "/dev/null".to_string()
+    };
+    similar::TextDiff::from_lines(old.unwrap_or_default(), new)
+        .unified_diff()
+        .header(&old_header, new_name)
+        .to_string()
+}
+
+/// Print a unified diff with added and removed lines colored
+pub fn print_diff(diff: &str) {
+    for line in diff.lines() {
+        if line.starts_with("+++") || line.starts_with("---") {
+            println!("{}", line.bold());
+        } else if line.starts_with('+') {
+            println!("{}", line.green());
+        } else if line.starts_with('-') {
+            println!("{}", line.red());
+        } else if line.starts_with("@@") {
+            println!("{}", line.cyan());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Diff of an artifact against its previous version, if it has one
+pub async fn previous_version_diff(
+    manager: &ArtifactManager,
+    artifact: &Artifact,
+) -> Result<Option<String>> {
+    let Some(current) = artifact.versions.last() else {
+        return Ok(None);
+    };
+    if current.version < 2 {
+        return Ok(None);
+    }
+    let old = manager
+        .version_content(&artifact.id, current.version - 1)
+        .await?;
+    let new = manager
+        .version_content(&artifact.id, current.version)
+        .await?;
+    Ok(Some(unified_diff(
+        Some(&old),
+        &new,
+        &format!("{}@v{}", artifact.name, current.version - 1),
+        &format!("{}@v{}", artifact.name, current.version),
+    )))
+}
+
+/// Diff from the workspace file at the artifact's relative path to the artifact,
+/// or None if they are identical
+pub fn workspace_diff(
+    manager: &ArtifactManager,
+    artifact: &Artifact,
+    root: &Path,
+) -> Option<String> {
+    let relative = artifact
+        .path
+        .strip_prefix(manager.artifact_dir())
+        .unwrap_or(&artifact.path);
+    let content = artifact
+        .content
+        .clone()
+        .or_else(|| std::fs::read_to_string(&artifact.path).ok())?;
+    let workspace = std::fs::read_to_string(root.join(relative)).ok();
+    if workspace.as_deref() == Some(content.as_str()) {
+        return None;
+    }
+    let name = relative.to_string_lossy();
+    Some(unified_diff(
+        workspace.as_deref(),
+        &content,
+        &format!("a/{}", name),
+        &format!("b/{}", name),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifact::ArtifactType;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_artifact_diffs() {
+        let root = std::env::temp_dir().join(format!("cli_engineer_diff_{}", uuid::Uuid::new_v4()));
+        let manager = ArtifactManager::new(root.join("artifacts")).unwrap();
+        let artifact = manager
+            .create_artifact(
+                "src/lib.rs".to_string(),
+                ArtifactType::SourceCode,
+                "fn a() {}\n".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        assert!(
+            previous_version_diff(&manager, &artifact)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let diff = workspace_diff(&manager, &artifact, &root).unwrap();
+        assert!(diff.starts_with("--- /dev/null\n+++ b/src/lib.rs\n"));
+
+        // Written again, as the executor and tool loop do on a later iteration
+        let artifact = manager
+            .create_artifact(
+                "src/lib.rs".to_string(),
+                ArtifactType::SourceCode,
+                "fn b() {}\n".to_string(),
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(manager.list_artifacts().await.len(), 1);
+        let diff = previous_version_diff(&manager, &artifact)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(diff.contains("--- src/lib.rs@v1\n+++ src/lib.rs@v2\n"));
+        assert!(diff.contains("-fn a() {}\n+fn b() {}\n"));
+
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn b() {}\n").unwrap();
+        assert!(workspace_diff(&manager, &artifact, &root).is_none());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::event_bus::Event;
+
+/// Whether an artifact has been copied into the project by `apply`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyStatus {
+    /// The project has no file at the artifact's path yet
+    New,
+    /// The project file differs from the artifact
+    Pending,
+    /// The project file matches the artifact
+    Applied,
+}
+
+impl ApplyStatus {
+    /// Status of the artifact at `path` in `artifact_dir` against the project
+    /// at `root`, or `None` when it does not map to a project file
+    fn of(path: &Path, artifact_dir: &Path, root: &Path) -> Option<Self> {
+        let relative = path.strip_prefix(artifact_dir).ok()?;
+        let content = fs::read(path).ok()?;
+        Some(match fs::read(root.join(relative)) {
+            Ok(existing) if existing == content => ApplyStatus::Applied,
+            Ok(_) => ApplyStatus::Pending,
+            Err(_) => ApplyStatus::New,
+        })
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ApplyStatus::New => "new",
+            ApplyStatus::Pending => "pending apply",
+            ApplyStatus::Applied => "applied",
+        }
+    }
+}
+
+/// An artifact written during the run
+#[derive(Debug, Clone)]
+pub struct ArtifactEntry {
+    pub name: String,
+    pub path: PathBuf,
+    /// `ArtifactType` name, empty for artifacts of earlier runs
+    pub artifact_type: String,
+    pub size: Option<u64>,
+    /// Latest version number; 1 until the artifact is updated
+    pub version: usize,
+    pub status: Option<ApplyStatus>,
+}
+
+impl ArtifactEntry {
+    /// "created" or "updated v3"
+    pub fn change(&self) -> String {
+        if self.version > 1 {
+            format!("updated v{}", self.version)
+        } else {
+            "created".to_string()
+        }
+    }
+}
+
+/// Artifacts created or updated during the run, kept up to date from artifact
+/// events
+#[derive(Debug, Default)]
+pub struct ArtifactList {
+    pub entries: Vec<ArtifactEntry>,
+    /// Artifact directory and project root, to tell what `apply` would change
+    workspace: Option<(PathBuf, PathBuf)>,
+}
+
+impl ArtifactList {
+    pub fn with_workspace(artifact_dir: PathBuf, root: PathBuf) -> Self {
+        Self {
+            entries: Vec::new(),
+            workspace: Some((artifact_dir, root)),
+        }
+    }
+
+    /// Update the list from `event`, returning whether it changed
+    pub fn apply(&mut self, event: &Event) -> bool {
+        match event {
+            Event::ArtifactCreated {
+                name,
+                path,
+                artifact_type,
+            } => {
+                self.entries.retain(|entry| &entry.name != name);
+                self.entries.push(ArtifactEntry {
+                    name: name.clone(),
+                    path: PathBuf::from(path),
+                    artifact_type: artifact_type.clone(),
+                    size: None,
+                    version: 1,
+                    status: None,
+                });
+                self.refresh(name);
+            }
+            Event::ArtifactUpdated {
+                name,
+                path,
+                version,
+            }
+            | Event::ArtifactModifiedExternally {
+                name,
+                path,
+                version,
+            } => {
+                match self.entries.iter_mut().find(|entry| &entry.name == name) {
+                    Some(entry) => {
+                        entry.path = PathBuf::from(path);
+                        entry.version = *version;
+                    }
+                    None => self.entries.push(ArtifactEntry {
+                        name: name.clone(),
+                        path: PathBuf::from(path),
+                        artifact_type: String::new(),
+                        size: None,
+                        version: *version,
+                        status: None,
+                    }),
+                }
+                self.refresh(name);
+            }
+            Event::ArtifactRenamed { from, to, path } => {
+                let Some(entry) = self.entries.iter_mut().find(|entry| &entry.name == from) else {
+                    return false;
+                };
+                entry.name = to.clone();
+                entry.path = PathBuf::from(path);
+                self.refresh(to);
+            }
+            Event::ArtifactDeleted { name, .. } => {
+                let count = self.entries.len();
+                self.entries.retain(|entry| &entry.name != name);
+                return self.entries.len() != count;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Read the size and apply status of `name` from disk
+    fn refresh(&mut self, name: &str) {
+        let workspace = self.workspace.clone();
+        let Some(entry) = self.entries.iter_mut().find(|entry| entry.name == name) else {
+            return;
+        };
+        entry.size = fs::metadata(&entry.path)
+            .ok()
+            .map(|metadata| metadata.len());
+        entry.status = workspace
+            .and_then(|(artifact_dir, root)| ApplyStatus::of(&entry.path, &artifact_dir, &root));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// File size as `812 B`, `4.2 KB` or `1.3 MB`
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_artifact_list_from_events() {
+        let root = std::env::temp_dir().join(format!("cli_engineer_list_{}", uuid::Uuid::new_v4()));
+        let artifact_dir = root.join("artifacts");
+        fs::create_dir_all(artifact_dir.join("src")).unwrap();
+        let mut list = ArtifactList::with_workspace(artifact_dir.clone(), root.clone());
+        let write = |name: &str, content: &str| {
+            let path = artifact_dir.join(name);
+            fs::write(&path, content).unwrap();
+            path.to_string_lossy().to_string()
+        };
+
+        let created = |name: &str, path: String| Event::ArtifactCreated {
+            name: name.to_string(),
+            path,
+            artifact_type: "SourceCode".to_string(),
+        };
+        list.apply(&created("main.rs", write("src/main.rs", "fn main() {}")));
+        list.apply(&created("README.md", write("README.md", "# Hi")));
+        fs::write(root.join("README.md"), "# Hi").unwrap();
+        list.apply(&Event::ArtifactUpdated {
+            name: "README.md".to_string(),
+            path: write("README.md", "# Hello"),
+            version: 2,
+        });
+        assert_eq!(list.len(), 2);
+        let main = &list.entries[0];
+        assert_eq!((main.size, main.status), (Some(12), Some(ApplyStatus::New)));
+        assert_eq!(main.change(), "created");
+        let readme = &list.entries[1];
+        assert_eq!(readme.status, Some(ApplyStatus::Pending));
+        assert_eq!(readme.change(), "updated v2");
+
+        fs::write(root.join("README.md"), "# Hello").unwrap();
+        list.apply(&Event::ArtifactRenamed {
+            from: "README.md".to_string(),
+            to: "INTRO.md".to_string(),
+            path: artifact_dir.join("README.md").to_string_lossy().to_string(),
+        });
+        assert_eq!(list.entries[1].name, "INTRO.md");
+        assert_eq!(list.entries[1].status, Some(ApplyStatus::Applied));
+        assert!(list.apply(&Event::ArtifactDeleted {
+            name: "main.rs".to_string(),
+            path: String::new(),
+        }));
+        assert_eq!(list.len(), 1);
+
+        assert_eq!(format_size(812), "812 B");
+        assert_eq!(format_size(4300), "4.2 KB");
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
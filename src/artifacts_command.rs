@@ -0,0 +1,221 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use colored::*;
+
+use crate::artifact::{Artifact, ArtifactManager, ArtifactOperation, FileAction};
+use crate::artifact_diff::{previous_version_diff, print_diff, workspace_diff};
+use crate::bundle::export_run;
+use crate::run_record::RunRecord;
+
+const USAGE: &str = "Usage: cli_engineer artifacts -- <diff [NAME] | history NAME | \
+revert NAME VERSION | find GLOB|tag:TAG | search PATTERN | operations | undo [ID] | \
+export FILE [RUN_ID]>";
+
+/// The `artifacts` command: inspect the artifacts of the last run
+pub async fn run(artifact_dir: &Path, root: &Path, args: &[String]) -> Result<()> {
+    let manager = ArtifactManager::new(artifact_dir.to_path_buf())?;
+    manager.init().await?;
+
+    match args {
+        [] => diff(&manager, root, None).await,
+        [action, rest @ ..] => match (action.as_str(), rest) {
+            ("diff", []) => diff(&manager, root, None).await,
+            ("diff", [name]) => diff(&manager, root, Some(name)).await,
+            ("history", [name]) => history(&manager, name).await,
+            ("revert", [name, version]) => revert(&manager, name, version).await,
+            ("find", [query]) => find(&manager, query).await,
+            ("search", [pattern]) => search(&manager, pattern).await,
+            ("operations", []) => operations(&manager).await,
+            ("undo", []) => undo(&manager, None).await,
+            ("undo", [id]) => undo(&manager, Some(id)).await,
+            ("export", [output]) => export(artifact_dir, root, output, None),
+            ("export", [output, run_id]) => export(artifact_dir, root, output, Some(run_id)),
+            _ => anyhow::bail!(USAGE),
+        },
+    }
+}
+
+/// For every artifact, or the one named, show what changed since its previous
+/// version and how it differs from the workspace
+async fn diff(manager: &ArtifactManager, root: &Path, filter: Option<&str>) -> Result<()> {
+    let artifacts: Vec<Artifact> = manager
+        .list_artifacts()
+        .await
+        .into_iter()
+        .filter(|a| {
+            filter.is_none_or(|name| a.name == name || a.name.ends_with(&format!("/{}", name)))
+        })
+        .collect();
+    if artifacts.is_empty() {
+        match filter {
+            Some(name) => anyhow::bail!("No artifact named {}", name),
+            None => println!("No artifacts in {}", manager.artifact_dir().display()),
+        }
+        return Ok(());
+    }
+
+    for artifact in &artifacts {
+        println!(
+            "{} {} (v{})",
+            "==".bright_blue(),
+            artifact.name.bright_white().bold(),
+            artifact.versions.len().max(1)
+        );
+        match previous_version_diff(manager, artifact).await? {
+            Some(diff) => {
+                println!("{}", "Changes since the previous version:".dimmed());
+                print_diff(&diff);
+            }
+            None => println!("{}", "No previous version".dimmed()),
+        }
+        match workspace_diff(manager, artifact, root) {
+            Some(diff) => {
+                println!("{}", "Changes against the workspace:".dimmed());
+                print_diff(&diff);
+            }
+            None => println!("{}", "Matches the workspace".dimmed()),
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// The artifact named `name`, or whose path ends with it; the latest one when
+/// the manifest holds several for a file
+async fn named(manager: &ArtifactManager, name: &str) -> Result<Artifact> {
+    manager
+        .list_artifacts()
+        .await
+        .into_iter()
+        .rev()
+        .find(|a| a.name == name || a.name.ends_with(&format!("/{}", name)))
+        .with_context(|| format!("No artifact named {}", name))
+}
+
+/// List the versions of an artifact, oldest first
+async fn history(manager: &ArtifactManager, name: &str) -> Result<()> {
+    let artifact = named(manager, name).await?;
+    for version in manager.get_artifact_history(&artifact.id).await? {
+        println!(
+            "v{} {} {} bytes {}",
+            version.version,
+            version.created_at.format("%Y-%m-%d %H:%M:%S"),
+            version.size,
+            version.sha256[..12].dimmed()
+        );
+    }
+    Ok(())
+}
+
+/// Restore an artifact to one of its versions, recorded as its next version
+async fn revert(manager: &ArtifactManager, name: &str, version: &str) -> Result<()> {
+    let artifact = named(manager, name).await?;
+    let version: usize = version
+        .trim_start_matches('v')
+        .parse()
+        .with_context(|| format!("Invalid version: {}", version))?;
+    manager.revert_artifact(&artifact.id, version).await?;
+    println!(
+        "{} {} to v{}",
+        "Reverted".green(),
+        artifact.name,
+        version
+    );
+    Ok(())
+}
+
+/// List artifacts whose name matches a glob, or that carry `tag:TAG`
+async fn find(manager: &ArtifactManager, query: &str) -> Result<()> {
+    let artifacts = match query.strip_prefix("tag:") {
+        Some(tag) => manager.find_by_tag(tag).await,
+        None => manager.find_by_glob(query).await?,
+    };
+    for artifact in &artifacts {
+        let tags = artifact.tags();
+        if tags.is_empty() {
+            println!("{}", artifact.name);
+        } else {
+            println!(
+                "{} {}",
+                artifact.name,
+                format!("[{}]", tags.join(", ")).dimmed()
+            );
+        }
+    }
+    if artifacts.is_empty() {
+        println!("No matching artifacts");
+    }
+    Ok(())
+}
+
+/// Print the artifact lines matching a regular expression, grep style
+async fn search(manager: &ArtifactManager, pattern: &str) -> Result<()> {
+    let matches = manager.search(pattern).await?;
+    for found in &matches {
+        for (line_number, line) in &found.lines {
+            println!(
+                "{}:{}: {}",
+                found.artifact.name.bright_white(),
+                line_number.to_string().cyan(),
+                line
+            );
+        }
+    }
+    if matches.is_empty() {
+        println!("No matches");
+    }
+    Ok(())
+}
+
+/// List the deletes and renames that can be undone, oldest first
+async fn operations(manager: &ArtifactManager) -> Result<()> {
+    let operations = manager.list_operations().await;
+    for operation in &operations {
+        println!(
+            "{} {} {}",
+            operation.id.dimmed(),
+            operation.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            describe(operation)
+        );
+    }
+    if operations.is_empty() {
+        println!("No deletes or renames to undo");
+    }
+    Ok(())
+}
+
+/// Undo the delete or rename `id`, or the most recent one
+async fn undo(manager: &ArtifactManager, id: Option<&str>) -> Result<()> {
+    let operation = match id {
+        Some(id) => manager.undo_operation(id).await?,
+        None => match manager.undo_last_operation().await? {
+            Some(operation) => operation,
+            None => {
+                println!("No deletes or renames to undo");
+                return Ok(());
+            }
+        },
+    };
+    println!("{} {}", "Undid".green(), describe(&operation));
+    Ok(())
+}
+
+fn describe(operation: &ArtifactOperation) -> String {
+    match &operation.action {
+        FileAction::Delete { filename } => format!("delete {}", filename),
+        FileAction::Rename { from, to } => format!("rename {} -> {}", from, to),
+    }
+}
+
+/// Bundle the most recent run, or `run_id`, into an archive
+fn export(artifact_dir: &Path, root: &Path, output: &str, run_id: Option<&str>) -> Result<()> {
+    let state_dir = root.join(".cli_engineer");
+    let record = RunRecord::load(&state_dir, run_id)?;
+    let files = export_run(Path::new(output), artifact_dir, &state_dir, &record)?;
+    println!(
+        "Exported run {} ({} files) to {}",
+        record.run_id, files, output
+    );
+    Ok(())
+}
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use colored::*;
+use log::warn;
+
+use crate::artifact::ArtifactManager;
+use crate::config::{Config, SecondOpinionConfig};
+use crate::create_second_opinion_manager;
+use crate::event_bus::{Event, EventBus};
+use crate::executor::Executor;
+use crate::llm_manager::LLMManager;
+use crate::planner::{ComplexityLevel, Plan, Step, StepCategory};
+use crate::reviewer::Reviewer;
+
+const USAGE: &str =
+    "Usage: cli_engineer bench-providers [--judge MODEL] [-- PROVIDER|PROVIDER/MODEL|ALIAS ...]";
+
+/// A small coding task and the file its answer must hold
+struct BenchTask {
+    name: &'static str,
+    description: &'static str,
+    file: &'static str,
+}
+
+impl BenchTask {
+    /// A one-step plan, as the planner would make for the task
+    fn plan(&self) -> Plan {
+        Plan {
+            goal: self.description.to_string(),
+            steps: vec![Step {
+                id: "step_1".to_string(),
+                description: self.description.to_string(),
+                category: StepCategory::CodeGeneration,
+                inputs: Vec::new(),
+                expected_outputs: vec![self.file.to_string()],
+                success_criteria: Vec::new(),
+                estimated_tokens: 1000,
+                high_risk: false,
+            }],
+            dependencies: HashMap::new(),
+            estimated_complexity: ComplexityLevel::Simple,
+        }
+    }
+}
+
+/// Tasks small enough to run against every model, in several languages
+const SUITE: [BenchTask; 3] = [
+    BenchTask {
+        name: "fizzbuzz",
+        description: "Create fizzbuzz.py with a function fizzbuzz(n) returning the FizzBuzz \
+                      strings for 1 to n, and a main block printing them for n = 15",
+        file: "fizzbuzz.py",
+    },
+    BenchTask {
+        name: "slugify",
+        description: "Create slugify.js exporting a function slugify(text) that lowercases the \
+                      text, replaces runs of other characters than letters and digits with one \
+                      dash and trims dashes from both ends",
+        file: "slugify.js",
+    },
+    BenchTask {
+        name: "stack",
+        description: "Create src/stack.rs with a generic Stack<T> backed by a Vec, with push, \
+                      pop, peek, len and is_empty, and unit tests",
+        file: "src/stack.rs",
+    },
+];
+
+/// How one model did on the suite
+#[derive(Debug, Default)]
+struct BenchResult {
+    tasks: usize,
+    latency: Duration,
+    tokens: usize,
+    cost: f32,
+    /// Tasks answered with the expected file in the artifact format
+    compliant: usize,
+    /// Tasks whose result the judge found ready to deploy
+    passed: usize,
+    /// Tasks whose step failed, like on an API error
+    failed: usize,
+}
+
+/// The `bench-providers` command: run the suite against each named model, or
+/// each enabled provider's, and compare latency, tokens, cost, artifact format
+/// compliance and review pass rate. The results are reviewed by `judge`, or
+/// else the `review.second_opinion` model, which is not benchmarked itself.
+pub async fn run(config: Arc<Config>, judge: Option<&str>, args: &[String]) -> Result<()> {
+    let targets = if args.is_empty() {
+        config.ai_providers.enabled_models()
+    } else {
+        args.iter()
+            .map(|name| config.model_choice(name))
+            .collect::<Result<Vec<_>>>()?
+    };
+    if targets.is_empty() {
+        anyhow::bail!("No provider is enabled\n{}", USAGE);
+    }
+    // The same judge reviews every model's work, so the pass rates compare
+    let judge = judge_choice(&config, judge)?;
+    let judge =
+        create_second_opinion_manager(&judge, &config, Arc::new(EventBus::new(1000)), None)?;
+    let judge_name = label(&judge);
+    println!(
+        "Running {} tasks against each model, reviewed by {}\n",
+        SUITE.len(),
+        judge_name.bright_white()
+    );
+
+    let mut rows = Vec::new();
+    for target in &targets {
+        let bus = Arc::new(EventBus::new(1000));
+        match create_second_opinion_manager(target, &config, bus.clone(), None) {
+            // A model reviewing its own work would favor itself
+            Ok(llm) if label(&llm) == judge_name => {
+                println!("  Skipping {}, the judge", judge_name);
+            }
+            Ok(llm) => {
+                let name = label(&llm);
+                println!("  Benchmarking {}...", name);
+                rows.push((name, bench(llm, &bus, &judge, &SUITE).await));
+            }
+            Err(e) => rows.push((describe(target), Err(e))),
+        }
+    }
+    if rows.is_empty() {
+        anyhow::bail!(
+            "No model to benchmark besides the judge {}\n{}",
+            judge_name,
+            USAGE
+        );
+    }
+
+    let width = rows
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(5);
+    println!();
+    println!(
+        "  {:<width$}  {:>8}  {:>8}  {:>9}  {:>6}  {:>6}",
+        "Model", "Latency", "Tokens", "Cost", "Format", "Review"
+    );
+    for (name, outcome) in rows {
+        match outcome {
+            Ok(result) => {
+                let mut line = format!(
+                    "  {:<width$}  {:>7.1}s  {:>8}  {:>9}  {:>6}  {:>6}",
+                    name,
+                    result.latency.as_secs_f64() / result.tasks.max(1) as f64,
+                    result.tokens,
+                    format!("${:.4}", result.cost),
+                    percent(result.compliant, result.tasks),
+                    percent(result.passed, result.tasks)
+                );
+                if result.failed > 0 {
+                    line.push_str(&format!("  {}", format!("{} failed", result.failed).red()));
+                }
+                println!("{}", line);
+            }
+            Err(e) => println!("  {:<width$}  {}", name, format!("{:#}", e).red()),
+        }
+    }
+    println!(
+        "\n{}",
+        "Latency is per task; Format is the share of answers with the expected file as an \
+         artifact, Review the share the judge found ready to deploy"
+            .dimmed()
+    );
+    Ok(())
+}
+
+/// Run `tasks` against `llm`, counting the calls reported on `bus` and
+/// having `judge` review each result
+async fn bench(
+    llm: Arc<LLMManager>,
+    bus: &EventBus,
+    judge: &LLMManager,
+    tasks: &[BenchTask],
+) -> Result<BenchResult> {
+    let dir = std::env::temp_dir().join(format!("cli_engineer_bench_{}", uuid::Uuid::new_v4()));
+    let mut events = bus.subscribe();
+    let mut result = BenchResult::default();
+    for task in tasks {
+        // Each task gets its own artifacts, so its review sees only its files
+        let task_dir = dir.join(task.name);
+        let artifacts = Arc::new(ArtifactManager::new(task_dir.clone())?);
+        let executor = Executor::new(llm.clone()).with_artifact_manager(artifacts.clone());
+        let plan = task.plan();
+        let started = Instant::now();
+        let results = executor.execute(&plan, "bench", Vec::new()).await;
+        result.latency += started.elapsed();
+        result.tasks += 1;
+        let results = match results {
+            Ok(results) if results.iter().all(|r| r.success) => results,
+            Ok(_) | Err(_) => {
+                result.failed += 1;
+                continue;
+            }
+        };
+        if task_dir.join(task.file).is_file() {
+            result.compliant += 1;
+        }
+        let reviewer = Reviewer::new().with_artifact_manager(artifacts);
+        match reviewer.review(&plan, &results, judge, "bench").await {
+            Ok(review) if review.ready_to_deploy => result.passed += 1,
+            Ok(_) => {}
+            Err(e) => warn!("Review of the {} task failed: {:#}", task.name, e),
+        }
+    }
+    while let Ok(event) = events.try_recv() {
+        if let Event::APICallCompleted { tokens, cost, .. } = event {
+            result.tokens += tokens;
+            result.cost += cost;
+        }
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+    Ok(result)
+}
+
+/// The model that reviews the results: the one named with `--judge`, or else
+/// the configured second opinion
+fn judge_choice(config: &Config, judge: Option<&str>) -> Result<SecondOpinionConfig> {
+    if let Some(name) = judge {
+        return config.model_choice(name);
+    }
+    match &config.review.second_opinion {
+        Some(second_opinion) => Ok(second_opinion.clone()),
+        None => anyhow::bail!(
+            "bench-providers needs a judge that is not benchmarked: pass --judge \
+             PROVIDER|PROVIDER/MODEL|ALIAS or set [review.second_opinion]"
+        ),
+    }
+}
+
+fn label(llm: &LLMManager) -> String {
+    format!("{}/{}", llm.provider().name(), llm.provider().model_name())
+}
+
+fn describe(target: &SecondOpinionConfig) -> String {
+    match &target.model {
+        Some(model) => format!("{}/{}", target.provider, model),
+        None => target.provider.clone(),
+    }
+}
+
+fn percent(count: usize, total: usize) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+    format!("{}%", count * 100 / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::MockProvider;
+
+    fn manager(provider: MockProvider) -> Arc<LLMManager> {
+        Arc::new(LLMManager::new(
+            vec![Box::new(provider)],
+            Arc::new(EventBus::new(1000)),
+            Arc::new(Config::default()),
+        ))
+    }
+
+    #[test]
+    fn test_the_judge_flag_wins_over_the_second_opinion() {
+        let mut config = Config::default();
+        config.review.second_opinion = Some(SecondOpinionConfig {
+            provider: "openai".to_string(),
+            model: Some("o3".to_string()),
+        });
+        let judge = judge_choice(&config, Some("anthropic/claude-opus-4-1")).unwrap();
+        assert_eq!(judge.provider, "anthropic");
+        assert_eq!(judge.model.as_deref(), Some("claude-opus-4-1"));
+        let judge = judge_choice(&config, None).unwrap();
+        assert_eq!(judge.provider, "openai");
+    }
+
+    #[test]
+    fn test_a_judge_is_required() {
+        let error = judge_choice(&Config::default(), None).unwrap_err();
+        assert!(error.to_string().contains("--judge"), "{}", error);
+        assert!(judge_choice(&Config::default(), Some("nobody")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_bench_counts_compliance_and_reviews() {
+        let tasks = [
+            BenchTask {
+                name: "hello",
+                description: "Create hello.py printing a greeting",
+                file: "hello.py",
+            },
+            BenchTask {
+                name: "add",
+                description: "Create add.py with a function add(a, b)",
+                file: "add.py",
+            },
+            BenchTask {
+                name: "sub",
+                description: "Create sub.py with a function sub(a, b)",
+                file: "sub.py",
+            },
+        ];
+        let bus = Arc::new(EventBus::new(1000));
+        let llm = Arc::new(LLMManager::new(
+            vec![Box::new(
+                MockProvider::new()
+                    .then_respond(
+                        "<artifact filename=\"hello.py\" type=\"python\">\n<![CDATA[\n\
+                         print('Hello')\n]]>\n</artifact>",
+                    )
+                    .then_respond("The function would add a and b.")
+                    .then_fail("rate limited"),
+            )],
+            bus.clone(),
+            Arc::new(Config::default()),
+        ));
+        let judge = manager(
+            MockProvider::new()
+                .respond_to(
+                    "hello.py",
+                    "QUALITY: good\nREADY_TO_DEPLOY: yes\nSUMMARY: Done.",
+                )
+                .respond_to(
+                    "add(a, b)",
+                    "QUALITY: poor\nREADY_TO_DEPLOY: no\nSUMMARY: No file.",
+                ),
+        );
+
+        let result = bench(llm, &bus, &judge, &tasks).await.unwrap();
+        assert_eq!(result.tasks, 3);
+        assert_eq!(result.compliant, 1);
+        assert_eq!(result.passed, 1);
+        assert_eq!(result.failed, 1);
+        assert!(result.tokens > 0);
+        assert_eq!(percent(result.compliant, result.tasks), "33%");
+    }
+}
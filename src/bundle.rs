@@ -0,0 +1,239 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::artifact::MANIFEST_FILE;
+use crate::event_log::EVENTS_FILE;
+use crate::run_record::RunRecord;
+
+/// Archive formats a run can be exported to, chosen by file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BundleFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl BundleFormat {
+    fn from_path(path: &Path) -> Result<Self> {
+        let name = path.to_string_lossy().to_lowercase();
+        if name.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Ok(Self::Tar)
+        } else {
+            anyhow::bail!(
+                "Unsupported bundle format for {}; use .zip, .tar, .tar.gz or .tgz",
+                path.display()
+            )
+        }
+    }
+}
+
+/// Bundle a run for attaching to a ticket or pull request: its artifacts and
+/// manifest, step transcripts, event log, final review and a summary.
+/// Everything is placed under a `cli_engineer_<run_id>/` directory. Returns
+/// the number of files.
+pub fn export_run(
+    output: &Path,
+    artifact_dir: &Path,
+    state_dir: &Path,
+    record: &RunRecord,
+) -> Result<usize> {
+    let format = BundleFormat::from_path(output)?;
+    let root = format!("cli_engineer_{}", record.run_id);
+
+    let mut entries: Vec<(String, Vec<u8>)> = vec![
+        (
+            format!("{}/summary.md", root),
+            record.to_markdown().into_bytes(),
+        ),
+        (
+            format!("{}/summary.json", root),
+            serde_json::to_vec_pretty(record)?,
+        ),
+    ];
+    if let Some(review) = &record.review {
+        entries.push((
+            format!("{}/review.json", root),
+            serde_json::to_vec_pretty(review)?,
+        ));
+    }
+    let events_path = RunRecord::runs_dir(state_dir)
+        .join(&record.run_id)
+        .join(EVENTS_FILE);
+    if let Ok(events) = fs::read(&events_path) {
+        entries.push((format!("{}/{}", root, EVENTS_FILE), events));
+    }
+
+    // Manifest backups and the version store are internal to the artifact manager
+    let artifact_files = files_under(artifact_dir).into_iter().filter(|(name, _)| {
+        name == MANIFEST_FILE || !name.starts_with(&format!("{}.", MANIFEST_FILE))
+    });
+    let transcript_files = files_under(&state_dir.join("transcripts").join(&record.run_id));
+    let report_files = files_under(
+        &RunRecord::runs_dir(state_dir)
+            .join(&record.run_id)
+            .join("iterations"),
+    );
+    for (prefix, (name, path)) in artifact_files
+        .map(|f| ("artifacts", f))
+        .chain(transcript_files.into_iter().map(|f| ("transcripts", f)))
+        .chain(report_files.into_iter().map(|f| ("iterations", f)))
+    {
+        let content =
+            fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        entries.push((format!("{}/{}/{}", root, prefix, name), content));
+    }
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).context("Failed to create output directory")?;
+    }
+    let file =
+        File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+    match format {
+        BundleFormat::Zip => {
+            let mut zip = zip::ZipWriter::new(file);
+            let options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Deflated);
+            for (name, content) in &entries {
+                zip.start_file(name.as_str(), options)?;
+                zip.write_all(content)?;
+            }
+            zip.finish()?;
+        }
+        BundleFormat::Tar => write_tar(file, &entries)?.flush()?,
+        BundleFormat::TarGz => {
+            let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            write_tar(encoder, &entries)?.finish()?;
+        }
+    }
+    Ok(entries.len())
+}
+
+fn write_tar<W: Write>(writer: W, entries: &[(String, Vec<u8>)]) -> Result<W> {
+    let mut tar = tar::Builder::new(writer);
+    let mtime = chrono::Utc::now().timestamp().max(0) as u64;
+    for (name, content) in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        tar.append_data(&mut header, name, content.as_slice())?;
+    }
+    Ok(tar.into_inner()?)
+}
+
+/// Files under `dir` as (relative path with `/` separators, path), skipping hidden entries
+fn files_under(dir: &Path) -> Vec<(String, std::path::PathBuf)> {
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+    let mut files: Vec<_> = ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .hidden(true)
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(dir).ok()?;
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            Some((name, e.into_path()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run_record::ArtifactRecord;
+    use std::io::Read;
+
+    #[test]
+    fn test_export_run() {
+        let dir =
+            std::env::temp_dir().join(format!("cli_engineer_bundle_{}", uuid::Uuid::new_v4()));
+        let artifact_dir = dir.join("artifacts");
+        let state_dir = dir.join(".cli_engineer");
+        let now = chrono::Utc::now();
+        let record = RunRecord {
+            run_id: "20250101_090000_abcd".to_string(),
+            command: "docs".to_string(),
+            prompt: "Document the API".to_string(),
+            started_at: now,
+            finished_at: now,
+            success: true,
+            error: None,
+            artifacts: vec![ArtifactRecord {
+                name: "docs/api.md".to_string(),
+                path: "artifacts/docs/api.md".to_string(),
+            }],
+            review: None,
+            pull_request: None,
+            stalled: None,
+            iterations: Vec::new(),
+            api_calls: 0,
+            tokens: 0,
+            cost: 0.0,
+            outcome: None,
+        };
+        fs::create_dir_all(artifact_dir.join("docs")).unwrap();
+        fs::create_dir_all(artifact_dir.join(".versions")).unwrap();
+        fs::write(artifact_dir.join("docs/api.md"), "# API\n").unwrap();
+        fs::write(artifact_dir.join(MANIFEST_FILE), "{}").unwrap();
+        fs::write(artifact_dir.join("manifest.json.bak"), "{}").unwrap();
+        fs::write(artifact_dir.join(".versions/abc"), "# API\n").unwrap();
+        let transcripts = state_dir.join("transcripts").join(&record.run_id);
+        fs::create_dir_all(&transcripts).unwrap();
+        fs::write(transcripts.join("iter1_step1.md"), "# Step 1/1\n").unwrap();
+
+        let expected = [
+            "cli_engineer_20250101_090000_abcd/summary.md",
+            "cli_engineer_20250101_090000_abcd/summary.json",
+            "cli_engineer_20250101_090000_abcd/artifacts/docs/api.md",
+            "cli_engineer_20250101_090000_abcd/artifacts/manifest.json",
+            "cli_engineer_20250101_090000_abcd/transcripts/iter1_step1.md",
+        ];
+
+        let zip_path = dir.join("run.zip");
+        assert_eq!(
+            export_run(&zip_path, &artifact_dir, &state_dir, &record).unwrap(),
+            5
+        );
+        let mut archive = zip::ZipArchive::new(File::open(&zip_path).unwrap()).unwrap();
+        let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        assert_eq!(names, expected);
+        let mut content = String::new();
+        archive
+            .by_name("cli_engineer_20250101_090000_abcd/artifacts/docs/api.md")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "# API\n");
+
+        let tar_path = dir.join("run.tar.gz");
+        export_run(&tar_path, &artifact_dir, &state_dir, &record).unwrap();
+        let mut archive =
+            tar::Archive::new(flate2::read::GzDecoder::new(File::open(&tar_path).unwrap()));
+        let names: Vec<String> = archive
+            .entries()
+            .unwrap()
+            .map(|e| e.unwrap().path().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, expected);
+
+        assert!(export_run(&dir.join("run.rar"), &artifact_dir, &state_dir, &record).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
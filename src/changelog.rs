@@ -0,0 +1,523 @@
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use colored::*;
+use log::warn;
+
+use crate::config::Config;
+use crate::event_bus::EventBus;
+use crate::git::{self, CommitInfo};
+use crate::llm_manager::LLMManager;
+use crate::setup_managers;
+
+const CHANGELOG_FILE: &str = "CHANGELOG.md";
+const USAGE: &str = "Usage: cli_engineer changelog [--since TAG|COMMIT] [-- VERSION]";
+
+const HEADER: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),
+and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+";
+
+/// Sections of a release in Keep a Changelog, in the order they are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Added,
+    Changed,
+    Deprecated,
+    Removed,
+    Fixed,
+    Security,
+}
+
+impl Section {
+    const ALL: [Section; 6] = [
+        Section::Added,
+        Section::Changed,
+        Section::Deprecated,
+        Section::Removed,
+        Section::Fixed,
+        Section::Security,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Section::Added => "Added",
+            Section::Changed => "Changed",
+            Section::Deprecated => "Deprecated",
+            Section::Removed => "Removed",
+            Section::Fixed => "Fixed",
+            Section::Security => "Security",
+        }
+    }
+
+    fn from_title(title: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|section| section.title().eq_ignore_ascii_case(title.trim()))
+    }
+
+    /// Section of a change with the conventional commit type `kind`, if its
+    /// message has one, and `description`. None for changes that don't matter
+    /// to users, such as CI fixes and version bumps.
+    fn of(kind: Option<&str>, description: &str) -> Option<Self> {
+        let lower = description.to_lowercase();
+        match kind {
+            Some("chore" | "ci" | "build" | "test" | "tests" | "style" | "release") => return None,
+            _ if ["security", "vulnerab", "cve-"]
+                .iter()
+                .any(|word| lower.contains(word)) =>
+            {
+                return Some(Section::Security);
+            }
+            Some("feat" | "feature") => return Some(Section::Added),
+            Some("fix" | "bugfix" | "hotfix") => return Some(Section::Fixed),
+            Some("perf" | "refactor" | "docs" | "revert") => return Some(Section::Changed),
+            _ => {}
+        }
+        let first_word = lower.split_whitespace().next().unwrap_or_default();
+        match first_word {
+            "add" | "adds" | "added" | "create" | "introduce" | "implement" | "support" => {
+                Some(Section::Added)
+            }
+            "fix" | "fixes" | "fixed" | "correct" | "resolve" | "repair" => Some(Section::Fixed),
+            "remove" | "removes" | "removed" | "drop" | "delete" => Some(Section::Removed),
+            "deprecate" | "deprecates" | "deprecated" => Some(Section::Deprecated),
+            "bump" | "release" | "merge" | "wip" => None,
+            _ => Some(Section::Changed),
+        }
+    }
+}
+
+/// A change to the project: a commit, or a merged pull request
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    section: Section,
+    text: String,
+}
+
+impl Change {
+    /// The change `commit` makes, sorted by its message; None for the
+    /// changes users don't need to hear about
+    fn of(commit: &CommitInfo) -> Option<Self> {
+        let (subject, reference) = match pull_request(commit) {
+            Some((title, reference)) => (title, Some(reference)),
+            // Merges of other branches bring in nothing new of their own
+            None if commit.subject.starts_with("Merge ") => return None,
+            None => (commit.subject.as_str(), None),
+        };
+        let (kind, description) = conventional(subject);
+        let section = Section::of(kind.as_deref(), description)?;
+        let mut chars = description.chars();
+        let mut text: String = chars.next()?.to_uppercase().chain(chars).collect();
+        if let Some(reference) = reference
+            && !text.contains(&reference)
+        {
+            text = format!("{} ({})", text, reference);
+        }
+        Some(Self { section, text })
+    }
+}
+
+/// Title and reference, `#12` or `!12`, of the pull or merge request `commit` merged
+fn pull_request(commit: &CommitInfo) -> Option<(&str, String)> {
+    let title = commit
+        .body
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty());
+    if let Some(rest) = commit.subject.strip_prefix("Merge pull request #") {
+        let number: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        return Some((title?, format!("#{}", number)));
+    }
+    // GitLab: `Merge branch 'login' into 'main'`, then the title and
+    // `See merge request group/project!12`
+    if commit.subject.starts_with("Merge branch ") {
+        let reference = commit
+            .body
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("See merge request "))?;
+        let (_, number) = reference.rsplit_once('!')?;
+        return Some((title?, format!("!{}", number)));
+    }
+    None
+}
+
+/// Type of a conventional commit message such as `feat(ui)!: Add themes`,
+/// lowercased, and its description
+fn conventional(subject: &str) -> (Option<String>, &str) {
+    if let Some((head, description)) = subject.split_once(": ") {
+        let head = head.trim_end_matches('!');
+        let kind = head.split('(').next().unwrap_or(head);
+        if !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic()) {
+            return (Some(kind.to_ascii_lowercase()), description.trim());
+        }
+    }
+    (None, subject.trim())
+}
+
+/// The `changelog` command: summarize the commits and merged pull requests
+/// after `since`, the latest tag by default, into a release of CHANGELOG.md
+pub async fn run(config: &Config, root: &Path, since: Option<&str>, args: &[String]) -> Result<()> {
+    if args.len() > 1 {
+        anyhow::bail!("Expected at most a version\n{}", USAGE);
+    }
+    let since = match since {
+        Some(since) => Some(since.to_string()),
+        None => git::latest_tag(root).await,
+    };
+    let commits = git::first_parent_log(root, since.as_deref())
+        .await
+        .context("Failed to read the git history")?;
+    let from = since.as_deref().unwrap_or("the first commit");
+    let changes: Vec<Change> = commits.iter().filter_map(Change::of).collect();
+    if changes.is_empty() {
+        anyhow::bail!("No changes to write since {}", from);
+    }
+    // The release being made, or the changes waiting for one
+    let heading = match args.first() {
+        Some(version) => format!(
+            "[{}] - {}",
+            version.trim_start_matches('v'),
+            chrono::Utc::now().format("%Y-%m-%d")
+        ),
+        None => "[Unreleased]".to_string(),
+    };
+
+    println!(
+        "Summarizing {} from {} since {}...",
+        count(changes.len(), "change"),
+        count(commits.len(), "commit"),
+        from
+    );
+    let (llm, _, _) = setup_managers(config, Arc::new(EventBus::new(1000)), None).await?;
+    let sections = summarize(&llm, &changes).await;
+
+    let path = root.join(CHANGELOG_FILE);
+    let existing = match std::fs::read_to_string(&path) {
+        Ok(existing) => existing,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    std::fs::write(&path, update(&existing, &heading, &sections))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    let entries: usize = sections.iter().map(|(_, entries)| entries.len()).sum();
+    println!(
+        "{} Wrote {} under {} to {}",
+        "✓".green(),
+        count(entries, "entry"),
+        heading.bright_white(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// `1 change`, `2 changes`, `3 entries`
+fn count(n: usize, noun: &str) -> String {
+    match (n, noun.strip_suffix('y')) {
+        (1, _) => format!("1 {}", noun),
+        (_, Some(stem)) => format!("{} {}ies", n, stem),
+        (_, None) => format!("{} {}s", n, noun),
+    }
+}
+
+/// Have the model write `changes` up as entries for the project's users, or
+/// list them as they are if it fails to
+async fn summarize(llm: &LLMManager, changes: &[Change]) -> Vec<(Section, Vec<String>)> {
+    match llm.send_prompt(&prompt(changes)).await {
+        Ok(response) => {
+            let sections = parse_sections(&response);
+            if !sections.is_empty() {
+                return sections;
+            }
+            warn!("The model's changelog had no sections; listing the changes as they are");
+        }
+        Err(e) => warn!(
+            "Failed to summarize the changes: {:#}; listing them as they are",
+            e
+        ),
+    }
+    Section::ALL
+        .into_iter()
+        .map(|section| {
+            let entries = changes
+                .iter()
+                .filter(|change| change.section == section)
+                .map(|change| change.text.clone())
+                .collect();
+            (section, entries)
+        })
+        .filter(|(_, entries): &(Section, Vec<String>)| !entries.is_empty())
+        .collect()
+}
+
+fn prompt(changes: &[Change]) -> String {
+    let list: Vec<String> = changes
+        .iter()
+        .map(|change| format!("- [{}] {}", change.section.title(), change.text))
+        .collect();
+    format!(
+        "Write the entries of a changelog release in the Keep a Changelog format from these \
+         commits and merged pull requests of a project, newest first. Each is labeled with the \
+         section its message suggests; move it if its description says otherwise.\n\n{}\n\n\
+         Rules:\n\
+         - Group the entries under `### Added`, `### Changed`, `### Deprecated`, `### Removed`, \
+         `### Fixed` and `### Security`, in that order, leaving out empty sections\n\
+         - Write one line per change, starting with `- `, for the users of the project rather \
+         than its developers; merge related commits into one entry\n\
+         - Keep pull request references such as (#123)\n\
+         - Leave out changes that don't matter to users, such as refactorings, tests and CI\n\
+         - Reply with the sections alone, without a version heading or other text",
+        list.join("\n")
+    )
+}
+
+/// Entries of each known `### Section` of a release, in the order of `Section::ALL`
+fn parse_sections(text: &str) -> Vec<(Section, Vec<String>)> {
+    let mut sections: Vec<(Section, Vec<String>)> = Vec::new();
+    let mut current = None;
+    for line in text.lines().map(str::trim) {
+        if let Some(title) = line.strip_prefix("### ") {
+            current = Section::from_title(title);
+        } else if let Some(section) = current
+            && let Some(entry) = line.strip_prefix("- ").or(line.strip_prefix("* "))
+        {
+            match sections.iter_mut().find(|(s, _)| *s == section) {
+                Some((_, entries)) => entries.push(entry.trim().to_string()),
+                None => sections.push((section, vec![entry.trim().to_string()])),
+            }
+        }
+    }
+    sections.sort_by_key(|(section, _)| Section::ALL.iter().position(|s| s == section));
+    sections
+}
+
+fn render(sections: &[(Section, Vec<String>)]) -> String {
+    sections
+        .iter()
+        .map(|(section, entries)| {
+            let entries: Vec<String> = entries.iter().map(|entry| format!("- {}", entry)).collect();
+            format!("### {}\n\n{}\n", section.title(), entries.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Add the entries of `new` missing from `sections`, keeping the order of `Section::ALL`
+fn merge(sections: &mut Vec<(Section, Vec<String>)>, new: &[(Section, Vec<String>)]) {
+    for (section, entries) in new {
+        let index = match sections.iter().position(|(s, _)| s == section) {
+            Some(index) => index,
+            None => {
+                sections.push((*section, Vec::new()));
+                sections.len() - 1
+            }
+        };
+        let existing = &mut sections[index].1;
+        for entry in entries {
+            if !existing
+                .iter()
+                .any(|other| other.eq_ignore_ascii_case(entry))
+            {
+                existing.push(entry.clone());
+            }
+        }
+    }
+    sections.sort_by_key(|(section, _)| Section::ALL.iter().position(|s| s == section));
+}
+
+/// `changelog` with a `## heading` release of `sections`. The entries under
+/// `[Unreleased]`, whether written by hand or by an earlier run, are kept:
+/// new changes are merged into them, and a release takes them over. An earlier
+/// write of the same release is replaced. A new release goes above the latest one.
+fn update(changelog: &str, heading: &str, sections: &[(Section, Vec<String>)]) -> String {
+    if changelog.trim().is_empty() {
+        return format!("{}\n## {}\n\n{}", HEADER, heading, render(sections));
+    }
+    let lines: Vec<&str> = changelog.lines().collect();
+    let key = |heading: &str| {
+        heading
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_lowercase()
+    };
+    let is_release = |line: &str| line.starts_with("## ");
+    // Link definitions such as `[1.0.0]: https://...` close the file
+    let is_end = |line: &str| is_release(line) || (line.starts_with('[') && line.contains("]: "));
+    // Line range of the release headed `key`, if the changelog has one
+    let find = |wanted: &str| {
+        let start = lines.iter().position(|line| {
+            line.strip_prefix("## ")
+                .is_some_and(|other| key(other) == wanted)
+        })?;
+        let end = lines[start + 1..]
+            .iter()
+            .position(|line| is_end(line))
+            .map_or(lines.len(), |offset| start + 1 + offset);
+        Some(start..end)
+    };
+    let unreleased = find("[unreleased]");
+    let replaced = Some(key(heading))
+        .filter(|key| key != "[unreleased]")
+        .and_then(|key| find(&key));
+
+    let mut merged = unreleased
+        .clone()
+        .map(|range| parse_sections(&lines[range.start + 1..range.end].join("\n")))
+        .unwrap_or_default();
+    merge(&mut merged, sections);
+    let section = format!("## {}\n\n{}", heading, render(&merged));
+
+    let removed = [unreleased, replaced];
+    let start = removed
+        .iter()
+        .flatten()
+        .map(|range| range.start)
+        .min()
+        .or_else(|| lines.iter().position(|line| is_end(line)))
+        .unwrap_or(lines.len());
+    let rest: Vec<&str> = (start..lines.len())
+        .filter(|i| !removed.iter().flatten().any(|range| range.contains(i)))
+        .map(|i| lines[i])
+        .collect();
+
+    let mut updated = lines[..start].join("\n").trim_end().to_string();
+    updated.push_str("\n\n");
+    updated.push_str(&section);
+    if !rest.is_empty() {
+        updated.push('\n');
+        updated.push_str(&rest.join("\n"));
+        updated.push('\n');
+    }
+    updated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(subject: &str, body: &str) -> CommitInfo {
+        CommitInfo {
+            subject: subject.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_commits_are_sorted_into_sections() {
+        let commits = [
+            commit("feat(ui)!: themes for the dashboard", ""),
+            commit(
+                "Merge pull request #42 from ana/login",
+                "Fix the login redirect",
+            ),
+            commit(
+                "Merge branch 'sbom' into 'main'",
+                "Add an SBOM command\n\nSee merge request group/project!7",
+            ),
+            commit("Merge branch 'main' into feature", ""),
+            commit("chore: bump version", ""),
+            commit("Bump serde to 1.0.200", ""),
+            commit("Drop Python 3.7 support (#40)", ""),
+            commit("fix: escape HTML in reports to prevent XSS (security)", ""),
+            commit("Speed up the scan", ""),
+        ];
+        let changes: Vec<Change> = commits.iter().filter_map(Change::of).collect();
+        let sorted: Vec<(Section, &str)> = changes
+            .iter()
+            .map(|change| (change.section, change.text.as_str()))
+            .collect();
+        assert_eq!(
+            sorted,
+            [
+                (Section::Added, "Themes for the dashboard"),
+                (Section::Fixed, "Fix the login redirect (#42)"),
+                (Section::Added, "Add an SBOM command (!7)"),
+                (Section::Removed, "Drop Python 3.7 support (#40)"),
+                (
+                    Section::Security,
+                    "Escape HTML in reports to prevent XSS (security)"
+                ),
+                (Section::Changed, "Speed up the scan"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_the_model_reply_is_parsed_into_sections() {
+        let response = "Here is the changelog:\n\n### Fixed\n\n- Login redirects work again \
+                        (#42)\n\n### Added\n\n* Dashboard themes\n- SBOM command (!7)\n\n\
+                        ### Notes\n\n- Ignored";
+        let sections = parse_sections(response);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].0, Section::Added);
+        assert_eq!(sections[1].1, ["Login redirects work again (#42)"]);
+        assert!(render(&sections).starts_with("### Added\n\n- Dashboard themes\n- SBOM"));
+    }
+
+    fn entries(section: Section, entries: &[&str]) -> Vec<(Section, Vec<String>)> {
+        vec![(section, entries.iter().map(|e| e.to_string()).collect())]
+    }
+
+    #[test]
+    fn test_a_new_changelog_gets_the_header() {
+        let changelog = update("", "[Unreleased]", &entries(Section::Added, &["Themes"]));
+        assert!(changelog.starts_with("# Changelog\n\nAll notable changes"));
+        assert!(changelog.ends_with("## [Unreleased]\n\n### Added\n\n- Themes\n"));
+    }
+
+    #[test]
+    fn test_unreleased_changes_are_merged() {
+        let existing = format!(
+            "{}\n## [Unreleased]\n\n### Fixed\n\n- Hand-written fix\n\n\
+             ## [1.2.0] - 2025-01-01\n\n### Fixed\n\n- Old fix\n",
+            HEADER
+        );
+        let mut new = entries(Section::Added, &["Themes"]);
+        new.extend(entries(Section::Fixed, &["hand-written fix", "New fix"]));
+        let updated = update(&existing, "[Unreleased]", &new);
+        assert_eq!(updated.matches("## [Unreleased]").count(), 1);
+        assert!(updated.contains(
+            "## [Unreleased]\n\n### Added\n\n- Themes\n\n\
+             ### Fixed\n\n- Hand-written fix\n- New fix\n\n## [1.2.0]"
+        ));
+        assert!(updated.ends_with("- Old fix\n"));
+    }
+
+    #[test]
+    fn test_a_release_takes_over_the_unreleased_changes() {
+        let existing = format!(
+            "{}\n## [Unreleased]\n\n### Added\n\n- Themes\n\n\
+             ## [1.2.0] - 2025-01-01\n\n### Fixed\n\n- Old fix\n\n\
+             [1.2.0]: https://example.com/v1.2.0\n",
+            HEADER
+        );
+        let released = update(
+            &existing,
+            "[1.3.0] - 2025-02-01",
+            &entries(Section::Fixed, &["New fix"]),
+        );
+        assert!(!released.contains("[Unreleased]"));
+        assert!(released.contains(
+            "## [1.3.0] - 2025-02-01\n\n### Added\n\n- Themes\n\n### Fixed\n\n- New fix\n"
+        ));
+        let new = released.find("## [1.3.0]").unwrap();
+        assert!(new < released.find("## [1.2.0]").unwrap());
+        assert!(released.ends_with("- Old fix\n\n[1.2.0]: https://example.com/v1.2.0\n"));
+
+        // Writing the release again replaces it
+        let again = update(
+            &released,
+            "[1.3.0] - 2025-02-02",
+            &entries(Section::Fixed, &["Newer fix"]),
+        );
+        assert_eq!(again.matches("## [1.3.0]").count(), 1);
+        assert!(again.contains("- Newer fix") && !again.contains("- New fix"));
+        assert!(again.contains("- Old fix"));
+    }
+}
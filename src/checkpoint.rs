@@ -0,0 +1,328 @@
+use std::fs;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::executor::StepResult;
+use crate::iteration_context::IterationContext;
+use crate::planner::Plan;
+use crate::run_record::{IterationRecord, RunRecord};
+
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// State of the agentic loop after its last step, from which an interrupted
+/// run continues instead of starting over
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub run_id: String,
+    pub command: String,
+    /// Task given to the loop
+    pub prompt: String,
+    pub saved_at: DateTime<Utc>,
+    /// Iteration in progress
+    pub iteration: usize,
+    /// Context the iteration started with
+    pub iteration_context: Option<IterationContext>,
+    /// The iteration's plan, once made and approved
+    pub plan: Option<Plan>,
+    /// Results of the plan's steps run so far
+    pub step_results: Vec<StepResult>,
+    /// Plans and step outcomes of the earlier iterations
+    pub history: Vec<IterationRecord>,
+}
+
+impl Checkpoint {
+    pub fn new(run_id: &str, command: &str, prompt: &str) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            command: command.to_string(),
+            prompt: prompt.to_string(),
+            saved_at: Utc::now(),
+            iteration: 1,
+            iteration_context: None,
+            plan: None,
+            step_results: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+
+    /// The checkpoint of the most recently interrupted `command` run in
+    /// `state_dir`, if any
+    pub fn load(state_dir: &Path, command: &str) -> Option<Self> {
+        fs::read_dir(RunRecord::runs_dir(state_dir))
+            .ok()?
+            .flatten()
+            .filter_map(|entry| Self::read(&entry.path().join(CHECKPOINT_FILE)))
+            .filter(|checkpoint| checkpoint.command == command)
+            .max_by_key(|checkpoint| checkpoint.saved_at)
+    }
+
+    fn read(path: &Path) -> Option<Self> {
+        let json = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&json) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(e) => {
+                warn!("Ignoring unreadable checkpoint {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Where the checkpoint of run `run_id` is kept, next to its summary, so
+    /// runs of one project never overwrite each other's
+    fn path(state_dir: &Path, run_id: &str) -> PathBuf {
+        RunRecord::runs_dir(state_dir)
+            .join(run_id)
+            .join(CHECKPOINT_FILE)
+    }
+
+    /// Where the run stopped, e.g. `iteration 2, 3 of 5 steps done`
+    pub fn progress(&self) -> String {
+        match &self.plan {
+            Some(plan) => format!(
+                "iteration {}, {} of {} steps done",
+                self.iteration,
+                self.step_results.len(),
+                plan.steps.len()
+            ),
+            None => format!("iteration {}, before planning", self.iteration),
+        }
+    }
+}
+
+/// Writes the checkpoint of a run after every change, replacing the file
+/// atomically so a crash mid-write leaves the previous one intact
+pub struct CheckpointStore {
+    path: PathBuf,
+    checkpoint: Mutex<Checkpoint>,
+    /// Checkpoint of the run this one continues, removed once this one has
+    /// saved its own
+    replaced: Mutex<Option<PathBuf>>,
+}
+
+impl CheckpointStore {
+    pub fn new(state_dir: &Path, checkpoint: Checkpoint) -> Self {
+        Self {
+            path: Checkpoint::path(state_dir, &checkpoint.run_id),
+            checkpoint: Mutex::new(checkpoint),
+            replaced: Mutex::new(None),
+        }
+    }
+
+    /// Continue the run that saved `previous`, whose checkpoint this one's replaces
+    pub fn replacing(self, state_dir: &Path, previous: &Checkpoint) -> Self {
+        *self.replaced.lock().unwrap() = Some(Checkpoint::path(state_dir, &previous.run_id));
+        self
+    }
+
+    /// Record the start of `iteration`, before it has a plan
+    pub fn start_iteration(
+        &self,
+        iteration: usize,
+        context: &IterationContext,
+        history: Vec<IterationRecord>,
+    ) {
+        self.update(|checkpoint| {
+            checkpoint.iteration = iteration;
+            checkpoint.iteration_context = Some(context.clone());
+            checkpoint.plan = None;
+            checkpoint.step_results.clear();
+            checkpoint.history = history;
+        });
+    }
+
+    /// Record the plan about to be executed and the steps of it already done
+    pub fn set_plan(&self, plan: &Plan, completed: &[StepResult]) {
+        self.update(|checkpoint| {
+            checkpoint.plan = Some(plan.clone());
+            checkpoint.step_results = completed.to_vec();
+        });
+    }
+
+    pub fn record_step(&self, result: &StepResult) {
+        self.update(|checkpoint| checkpoint.step_results.push(result.clone()));
+    }
+
+    /// Remove the checkpoint once the run has ended
+    pub fn clear(&self) {
+        if let Err(e) = fs::remove_file(&self.path)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!("Failed to remove checkpoint {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Checkpoint)) {
+        let mut checkpoint = self.checkpoint.lock().unwrap();
+        change(&mut checkpoint);
+        checkpoint.saved_at = Utc::now();
+        if let Err(e) = self.write(&checkpoint) {
+            warn!("Failed to save checkpoint: {:#}", e);
+            return;
+        }
+        if let Some(replaced) = self.replaced.lock().unwrap().take()
+            && let Err(e) = fs::remove_file(&replaced)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            warn!("Failed to remove checkpoint {}: {}", replaced.display(), e);
+        }
+    }
+
+    fn write(&self, checkpoint: &Checkpoint) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).context("Failed to create state directory")?;
+        }
+        let json = serde_json::to_string(checkpoint).context("Failed to serialize checkpoint")?;
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(json.as_bytes())?;
+                file.sync_all()
+            })
+            .context("Failed to write checkpoint")?;
+        fs::rename(&temp_path, &self.path).context("Failed to replace checkpoint")
+    }
+}
+
+/// The checkpoint of an interrupted `command` run to continue: always with
+/// `resume`, otherwise if the user agrees when asked on the terminal
+pub fn offer_resume(
+    state_dir: &Path,
+    command: &str,
+    resume: bool,
+    interactive: bool,
+) -> Option<Checkpoint> {
+    let Some(checkpoint) = Checkpoint::load(state_dir, command) else {
+        if resume {
+            warn!(
+                "No interrupted {} run to resume; starting a new one",
+                command
+            );
+        }
+        return None;
+    };
+    if resume {
+        return Some(checkpoint);
+    }
+    if !interactive || !std::io::stdin().is_terminal() {
+        info!(
+            "Found interrupted run {} ({}); pass --resume to continue it",
+            checkpoint.run_id,
+            checkpoint.progress()
+        );
+        return None;
+    }
+
+    let task = checkpoint.prompt.lines().next().unwrap_or_default();
+    // Continuing is opt-in, so a stray Enter starts the new task as asked
+    eprint!(
+        "An interrupted {} run was found ({}):\n  {}\nContinue it instead? [y/N] ",
+        command,
+        checkpoint.progress(),
+        task
+    );
+    let _ = std::io::stderr().flush();
+    let mut answer = String::new();
+    let _ = std::io::stdin().read_line(&mut answer);
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes").then_some(checkpoint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::ComplexityLevel;
+
+    fn state_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("cli_engineer_checkpoint_{}", uuid::Uuid::new_v4()))
+    }
+
+    fn plan() -> Plan {
+        Plan {
+            goal: "Add a CLI".to_string(),
+            steps: Vec::new(),
+            dependencies: Default::default(),
+            estimated_complexity: ComplexityLevel::Simple,
+        }
+    }
+
+    #[test]
+    fn test_checkpoint_store() {
+        let dir = state_dir();
+        let store = CheckpointStore::new(&dir, Checkpoint::new("run", "code", "Add a CLI"));
+        assert!(Checkpoint::load(&dir, "code").is_none());
+
+        store.start_iteration(2, &IterationContext::new(2), Vec::new());
+        store.set_plan(&plan(), &[]);
+        store.record_step(&StepResult {
+            step_id: "step_1".to_string(),
+            success: true,
+            output: "done".to_string(),
+            artifacts_created: vec!["main.rs".to_string()],
+            tokens_used: 10,
+            error: None,
+        });
+
+        let checkpoint = Checkpoint::load(&dir, "code").unwrap();
+        assert_eq!(checkpoint.iteration, 2);
+        assert_eq!(checkpoint.plan.unwrap().goal, "Add a CLI");
+        assert_eq!(checkpoint.step_results[0].artifacts_created, ["main.rs"]);
+        assert!(offer_resume(&dir, "review", true, false).is_none());
+        assert!(offer_resume(&dir, "code", true, false).is_some());
+        // Without --resume, a headless run starts over
+        assert!(offer_resume(&dir, "code", false, false).is_none());
+
+        store.clear();
+        assert!(Checkpoint::load(&dir, "code").is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_each_run_has_its_own_checkpoint() {
+        let dir = state_dir();
+        let code = CheckpointStore::new(&dir, Checkpoint::new("run1", "code", "Add a CLI"));
+        code.set_plan(&plan(), &[]);
+        let docs = CheckpointStore::new(&dir, Checkpoint::new("run2", "docs", "Document it"));
+        docs.start_iteration(3, &IterationContext::new(3), Vec::new());
+        let later = CheckpointStore::new(&dir, Checkpoint::new("run3", "code", "Add a GUI"));
+        later.start_iteration(1, &IterationContext::new(1), Vec::new());
+
+        assert_eq!(Checkpoint::load(&dir, "docs").unwrap().iteration, 3);
+        // The most recent run of the command is the one continued
+        assert_eq!(Checkpoint::load(&dir, "code").unwrap().run_id, "run3");
+        later.clear();
+        assert_eq!(Checkpoint::load(&dir, "code").unwrap().run_id, "run1");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_a_continued_run_replaces_the_checkpoint() {
+        let dir = state_dir();
+        let interrupted = CheckpointStore::new(&dir, Checkpoint::new("run1", "code", "Add a CLI"));
+        interrupted.set_plan(&plan(), &[]);
+        let previous = Checkpoint::load(&dir, "code").unwrap();
+
+        let resumed = CheckpointStore::new(&dir, Checkpoint::new("run2", "code", "Add a CLI"))
+            .replacing(&dir, &previous);
+        assert_eq!(Checkpoint::load(&dir, "code").unwrap().run_id, "run1");
+        resumed.set_plan(&plan(), &[]);
+        assert_eq!(Checkpoint::load(&dir, "code").unwrap().run_id, "run2");
+        resumed.clear();
+        assert!(Checkpoint::load(&dir, "code").is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_unreadable_checkpoints_are_ignored() {
+        let dir = state_dir();
+        let run_dir = RunRecord::runs_dir(&dir).join("run1");
+        fs::create_dir_all(&run_dir).unwrap();
+        fs::write(run_dir.join(CHECKPOINT_FILE), "{ not json").unwrap();
+        assert!(Checkpoint::load(&dir, "code").is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
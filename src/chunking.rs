@@ -0,0 +1,171 @@
+use crate::code_index::{CodeIndex, SymbolKind};
+use crate::retrieval::{chunk_file, dot, local_embedding};
+
+/// Bytes of section text kept from an elided file
+const ELIDED_FILE_BUDGET: usize = 40_000;
+/// Lines from the top of the file (imports, module docs) always kept
+const HEADER_LINES: usize = 40;
+/// Symbols longer than this are replaced by their nested symbols
+const MAX_SECTION_LINES: usize = 200;
+
+/// A 1-based inclusive line range of a file
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Section {
+    start_line: usize,
+    end_line: usize,
+}
+
+/// A large file reduced to fit in context
+pub struct ElidedFile {
+    pub content: String,
+    pub sections_total: usize,
+    pub sections_included: usize,
+}
+
+/// Split a file into sections: top-level symbols for supported languages,
+/// headings for markdown, and overlapping line windows otherwise
+fn split_sections(path: &str, content: &str) -> Vec<Section> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    if path.ends_with(".md") {
+        let mut starts: Vec<usize> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.starts_with('#'))
+            .map(|(i, _)| i + 1)
+            .collect();
+        if !starts.is_empty() {
+            starts.push(lines.len() + 1);
+            return starts
+                .windows(2)
+                .map(|w| Section {
+                    start_line: w[0],
+                    end_line: w[1] - 1,
+                })
+                .collect();
+        }
+    }
+
+    let mut index = CodeIndex::default();
+    index.add_file(path, content);
+    let mut symbols: Vec<Section> = index
+        .symbols_in(path)
+        .filter(|s| s.kind != SymbolKind::Import)
+        .map(|s| Section {
+            start_line: s.start_line,
+            end_line: s.end_line,
+        })
+        .collect();
+    if symbols.is_empty() {
+        return chunk_file(path, content)
+            .into_iter()
+            .map(|c| Section {
+                start_line: c.start_line,
+                end_line: c.end_line,
+            })
+            .collect();
+    }
+
+    // Outermost symbols that fit; oversized ones give way to their nested symbols
+    symbols.sort_by_key(|s| (s.start_line, std::cmp::Reverse(s.end_line)));
+    let mut sections: Vec<Section> = Vec::new();
+    for symbol in symbols {
+        let overlaps = sections
+            .last()
+            .is_some_and(|last| symbol.start_line <= last.end_line);
+        if !overlaps && symbol.end_line + 1 - symbol.start_line <= MAX_SECTION_LINES {
+            sections.push(symbol);
+        }
+    }
+    sections
+}
+
+/// Reduce a large file to its header and the sections most relevant to `query`,
+/// marking the omitted line ranges so the model knows the file is partial
+pub fn elide_large_file(path: &str, content: &str, query: &str) -> ElidedFile {
+    let lines: Vec<&str> = content.lines().collect();
+    let sections = split_sections(path, content);
+    let text = |s: &Section| lines[s.start_line - 1..s.end_line.min(lines.len())].join("\n");
+
+    let query_vector = local_embedding(query);
+    let mut ranked: Vec<(f32, Section)> = sections
+        .iter()
+        .filter(|s| s.start_line > HEADER_LINES)
+        .map(|s| (dot(&query_vector, &local_embedding(&text(s))), *s))
+        .collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let sections_total = ranked.len();
+
+    let header_end = HEADER_LINES.min(lines.len());
+    let mut budget = ELIDED_FILE_BUDGET.saturating_sub(lines[..header_end].join("\n").len());
+    let mut selected: Vec<Section> = Vec::new();
+    for (_, section) in ranked {
+        let size = text(&section).len();
+        if size <= budget {
+            budget -= size;
+            selected.push(section);
+        }
+    }
+    selected.sort_by_key(|s| s.start_line);
+
+    let mut out = lines[..header_end].join("\n");
+    let mut next_line = header_end + 1;
+    for section in &selected {
+        if section.start_line > next_line {
+            out.push_str(&format!(
+                "\n... (lines {}-{} omitted) ...",
+                next_line,
+                section.start_line - 1
+            ));
+        }
+        out.push('\n');
+        out.push_str(&text(section));
+        next_line = section.end_line + 1;
+    }
+    if next_line <= lines.len() {
+        out.push_str(&format!(
+            "\n... (lines {}-{} omitted) ...",
+            next_line,
+            lines.len()
+        ));
+    }
+
+    ElidedFile {
+        content: out,
+        sections_total,
+        sections_included: selected.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elide_large_file_keeps_relevant_sections() {
+        let mut content = String::from("use std::collections::HashMap;\n");
+        content.push_str(&"\n".repeat(HEADER_LINES));
+        for i in 0..400 {
+            content.push_str(&format!(
+                "fn helper_{}() -> usize {{\n    let padding = \"{}\";\n    padding.len()\n}}\n\n",
+                i,
+                "p".repeat(200)
+            ));
+        }
+        content.push_str(
+            "fn compress_context(messages: &[String]) -> String {\n    messages.join(\" \")\n}\n",
+        );
+        assert!(content.len() > 100_000);
+
+        let elided = elide_large_file(
+            "src/big.rs",
+            &content,
+            "fix compress_context joining messages",
+        );
+        assert!(elided.content.starts_with("use std::collections::HashMap;"));
+        assert!(elided.content.contains("fn compress_context"));
+        assert!(elided.content.contains("omitted) ..."));
+        assert!(elided.content.len() <= ELIDED_FILE_BUDGET + 10_000);
+        assert!(elided.sections_included < elided.sections_total);
+    }
+}
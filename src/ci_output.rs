@@ -0,0 +1,264 @@
+use clap::ValueEnum;
+
+use crate::reviewer::{Issue, IssueSeverity};
+use crate::run_record::RunRecord;
+
+/// Machine-readable report of a run's review issues for CI systems
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// GitHub Actions workflow commands (`::warning file=...,line=...::...`)
+    GithubAnnotations,
+    /// Checkstyle XML, read by most CI and code review tools
+    Checkstyle,
+    /// JUnit XML with a failing test case per issue
+    Junit,
+}
+
+/// The issues of the run's final review in `format`
+pub fn render(format: OutputFormat, record: &RunRecord) -> String {
+    let issues = record
+        .review
+        .as_ref()
+        .map(|review| review.issues.as_slice())
+        .unwrap_or_default();
+    match format {
+        OutputFormat::GithubAnnotations => github_annotations(record, issues),
+        OutputFormat::Checkstyle => checkstyle(issues),
+        OutputFormat::Junit => junit(record, issues),
+    }
+}
+
+fn github_annotations(record: &RunRecord, issues: &[Issue]) -> String {
+    let mut output = String::new();
+    for issue in issues {
+        let level = match issue.severity {
+            IssueSeverity::Critical | IssueSeverity::Major => "error",
+            IssueSeverity::Minor => "warning",
+            IssueSeverity::Info => "notice",
+        };
+        let mut properties = Vec::new();
+        if let Some(path) = issue.location_path() {
+            properties.push(format!("file={}", escape_property(path)));
+        }
+        if let Some((start, end)) = issue.location_lines() {
+            properties.push(format!("line={}", start));
+            properties.push(format!("endLine={}", end));
+        }
+        properties.push(format!("title={}", escape_property(&title(issue))));
+        output.push_str(&format!(
+            "::{} {}::{}\n",
+            level,
+            properties.join(","),
+            escape_data(&message(issue))
+        ));
+    }
+    if let Some(error) = &record.error {
+        output.push_str(&format!(
+            "::error title=cli_engineer failed::{}\n",
+            escape_data(error)
+        ));
+    }
+    output.push_str(&record.summary_line());
+    output
+}
+
+fn checkstyle(issues: &[Issue]) -> String {
+    // Issues grouped by file in order of first appearance; those without one go under ""
+    let mut files: Vec<(&str, Vec<&Issue>)> = Vec::new();
+    for issue in issues {
+        let path = issue.location_path().unwrap_or("");
+        match files.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, grouped)) => grouped.push(issue),
+            None => files.push((path, vec![issue])),
+        }
+    }
+
+    let mut output =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n");
+    for (path, grouped) in files {
+        output.push_str(&format!("  <file name=\"{}\">\n", escape_xml(path)));
+        for issue in grouped {
+            let severity = match issue.severity {
+                IssueSeverity::Critical | IssueSeverity::Major => "error",
+                IssueSeverity::Minor => "warning",
+                IssueSeverity::Info => "info",
+            };
+            let line = issue.location_lines().map_or(0, |(start, _)| start);
+            output.push_str(&format!(
+                "    <error line=\"{}\" severity=\"{}\" message=\"{}\" source=\"cli_engineer.{:?}\"/>\n",
+                line,
+                severity,
+                escape_xml(&message(issue)),
+                issue.category
+            ));
+        }
+        output.push_str("  </file>\n");
+    }
+    output.push_str("</checkstyle>");
+    output
+}
+
+fn junit(record: &RunRecord, issues: &[Issue]) -> String {
+    let mut cases = Vec::new();
+    let mut failures = 0;
+    for issue in issues {
+        let name = match &issue.location {
+            Some(location) => format!("{} ({})", issue.description, location),
+            None => issue.description.clone(),
+        };
+        let classname = issue.location_path().unwrap_or(&record.command);
+        let mut case = format!(
+            "    <testcase classname=\"{}\" name=\"{}\"",
+            escape_xml(classname),
+            escape_xml(&name)
+        );
+        // Informational issues are listed but do not fail the suite
+        if issue.severity == IssueSeverity::Info {
+            case.push_str("/>\n");
+        } else {
+            failures += 1;
+            case.push_str(&format!(
+                ">\n      <failure message=\"{}\" type=\"{}\">{}</failure>\n    </testcase>\n",
+                escape_xml(&title(issue)),
+                issue.severity,
+                escape_xml(&message(issue))
+            ));
+        }
+        cases.push(case);
+    }
+    let mut errors = 0;
+    if let Some(error) = &record.error {
+        errors += 1;
+        cases.push(format!(
+            "    <testcase classname=\"{}\" name=\"run\">\n      <error message=\"{}\"/>\n    </testcase>\n",
+            escape_xml(&record.command),
+            escape_xml(error)
+        ));
+    } else if cases.is_empty() {
+        // An empty suite reads as "no tests ran" to some CI systems
+        cases.push(format!(
+            "    <testcase classname=\"{}\" name=\"review\"/>\n",
+            escape_xml(&record.command)
+        ));
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites>\n  \
+<testsuite name=\"cli_engineer {}\" tests=\"{}\" failures=\"{}\" errors=\"{}\">\n{}  \
+</testsuite>\n</testsuites>",
+        escape_xml(&record.command),
+        cases.len(),
+        failures,
+        errors,
+        cases.concat()
+    )
+}
+
+fn title(issue: &Issue) -> String {
+    format!("{} {:?} issue", issue.severity, issue.category)
+}
+
+fn message(issue: &Issue) -> String {
+    match &issue.suggestion {
+        Some(suggestion) if !suggestion.trim().is_empty() => {
+            format!("{}\nSuggestion: {}", issue.description, suggestion.trim())
+        }
+        _ => issue.description.clone(),
+    }
+}
+
+/// Escape the message of a workflow command
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value of a workflow command
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reviewer::{IssueCategory, QualityLevel, ReviewResult};
+
+    #[test]
+    fn test_ci_output() {
+        let issue = |severity, location: Option<&str>, description: &str| Issue {
+            severity,
+            category: IssueCategory::Security,
+            description: description.to_string(),
+            location: location.map(str::to_string),
+            suggestion: Some("Use a parameterized query".to_string()),
+            patch: None,
+        };
+        let review = ReviewResult {
+            overall_quality: QualityLevel::Poor,
+            issues: vec![
+                issue(
+                    IssueSeverity::Critical,
+                    Some("src/db.rs:10-12"),
+                    "SQL built with format!, 100% injectable",
+                ),
+                issue(IssueSeverity::Info, None, "Consider <sqlx>"),
+            ],
+            suggestions: Vec::new(),
+            ready_to_deploy: false,
+            summary: String::new(),
+        };
+        let record = RunRecord {
+            run_id: "run-1".to_string(),
+            command: "review".to_string(),
+            prompt: String::new(),
+            started_at: chrono::Utc::now(),
+            finished_at: chrono::Utc::now(),
+            success: false,
+            error: None,
+            artifacts: Vec::new(),
+            review: Some(review),
+            pull_request: None,
+            stalled: None,
+            iterations: Vec::new(),
+            api_calls: 0,
+            tokens: 0,
+            cost: 0.0,
+            outcome: None,
+        };
+
+        let annotations = render(OutputFormat::GithubAnnotations, &record);
+        assert!(annotations.starts_with(
+            "::error file=src/db.rs,line=10,endLine=12,title=Critical Security issue::\
+SQL built with format!, 100%25 injectable%0ASuggestion: Use a parameterized query\n\
+::notice title=Info Security issue::Consider <sqlx>"
+        ));
+
+        let checkstyle = render(OutputFormat::Checkstyle, &record);
+        assert!(
+            checkstyle
+                .contains("<file name=\"src/db.rs\">\n    <error line=\"10\" severity=\"error\"")
+        );
+        assert!(checkstyle.contains("<file name=\"\">\n    <error line=\"0\" severity=\"info\" message=\"Consider &lt;sqlx&gt;"));
+
+        let junit = render(OutputFormat::Junit, &record);
+        assert!(junit.contains("tests=\"2\" failures=\"1\" errors=\"0\""));
+        assert!(junit.contains("<testcase classname=\"review\" name=\"Consider &lt;sqlx&gt;\"/>"));
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use log::{debug, info};
+use tree_sitter::{Language, Node, Parser};
+
+/// Longest definition rendered into a prompt before it is truncated
+const MAX_DEFINITION_LINES: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Class,
+    Interface,
+    Type,
+    Module,
+    Import,
+}
+
+impl fmt::Display for SymbolKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SymbolKind::Function => "function",
+            SymbolKind::Method => "method",
+            SymbolKind::Struct => "struct",
+            SymbolKind::Enum => "enum",
+            SymbolKind::Trait => "trait",
+            SymbolKind::Class => "class",
+            SymbolKind::Interface => "interface",
+            SymbolKind::Type => "type",
+            SymbolKind::Module => "module",
+            SymbolKind::Import => "import",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A definition or import extracted from a source file
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    /// Name including its enclosing type, e.g. `ContextManager::compress_context`
+    pub qualified_name: String,
+    pub kind: SymbolKind,
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// First line of the definition
+    pub signature: String,
+}
+
+/// Symbol table of the scanned codebase, built with tree-sitter
+#[derive(Default)]
+pub struct CodeIndex {
+    symbols: Vec<Symbol>,
+    sources: HashMap<String, String>,
+}
+
+impl CodeIndex {
+    /// Index `files` (path, content); files in unsupported languages are skipped
+    pub fn build(files: &[(String, String)]) -> Self {
+        let mut index = Self::default();
+        for (path, content) in files {
+            index.add_file(path, content);
+        }
+        info!(
+            "Indexed {} symbols from {} files",
+            index.symbols.len(),
+            index.sources.len()
+        );
+        index
+    }
+
+    /// Parse `content` and replace any symbols previously indexed for `path`
+    pub fn add_file(&mut self, path: &str, content: &str) {
+        let Some(language) = language_for(path) else {
+            return;
+        };
+        let mut parser = Parser::new();
+        if parser.set_language(&language).is_err() {
+            return;
+        }
+        let Some(tree) = parser.parse(content, None) else {
+            debug!("Failed to parse {} for the symbol index", path);
+            return;
+        };
+
+        self.symbols.retain(|s| s.path != path);
+        let mut collector = Collector {
+            path,
+            source: content.as_bytes(),
+            symbols: &mut self.symbols,
+        };
+        collector.visit(tree.root_node(), None);
+        self.sources.insert(path.to_string(), content.to_string());
+    }
+
+    /// Paths of the indexed files, in no particular order
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.sources.keys().map(String::as_str)
+    }
+
+    /// Definitions and imports of `path`, in source order
+    pub fn symbols_in<'a>(&'a self, path: &'a str) -> impl Iterator<Item = &'a Symbol> + 'a {
+        self.symbols.iter().filter(move |s| s.path == path)
+    }
+
+    /// Definitions matching `query`, which may be a bare name or qualified with
+    /// `::` or `.` (`ContextManager::compress_context`, `Reviewer.review`)
+    pub fn find(&self, query: &str) -> Vec<&Symbol> {
+        let query = query.replace('.', "::");
+        let suffix = format!("::{}", query);
+        self.symbols
+            .iter()
+            .filter(|s| s.kind != SymbolKind::Import)
+            .filter(|s| s.qualified_name == query || s.qualified_name.ends_with(&suffix))
+            .collect()
+    }
+
+    /// Compact outline of every indexed file: imports and definition signatures
+    pub fn outline(&self) -> String {
+        let mut paths: Vec<&String> = self.sources.keys().collect();
+        paths.sort();
+
+        let mut out = String::from("Codebase outline (symbol index):\n");
+        for path in paths {
+            out.push_str(&format!("\n{}\n", path));
+            let imports: Vec<&str> = self
+                .symbols_in(path)
+                .filter(|s| s.kind == SymbolKind::Import)
+                .map(|s| s.name.as_str())
+                .collect();
+            if !imports.is_empty() {
+                out.push_str(&format!("  imports: {}\n", imports.join(" ")));
+            }
+            for symbol in self
+                .symbols_in(path)
+                .filter(|s| s.kind != SymbolKind::Import)
+            {
+                out.push_str(&format!(
+                    "  {} {} (line {}): {}\n",
+                    symbol.kind, symbol.qualified_name, symbol.start_line, symbol.signature
+                ));
+            }
+        }
+        out
+    }
+
+    /// Definitions of the symbols referenced in `text`, at most `max` of them.
+    /// Only identifiers that look like code (qualified, snake_case or CamelCase)
+    /// are considered so ordinary words don't pull in unrelated definitions.
+    pub fn definitions_for(&self, text: &str, max: usize) -> String {
+        let mut found: Vec<&Symbol> = Vec::new();
+        for candidate in identifier_references(text) {
+            if found.len() >= max {
+                break;
+            }
+            for symbol in self.find(&candidate) {
+                if found.len() < max
+                    && !found
+                        .iter()
+                        .any(|s| s.path == symbol.path && s.start_line == symbol.start_line)
+                {
+                    found.push(symbol);
+                }
+            }
+        }
+
+        if found.is_empty() {
+            return String::new();
+        }
+        let mut out = String::from("Definitions referenced by this step:\n\n");
+        for symbol in found {
+            out.push_str(&self.render(symbol));
+        }
+        out
+    }
+
+    fn render(&self, symbol: &Symbol) -> String {
+        let source = self
+            .sources
+            .get(&symbol.path)
+            .map(String::as_str)
+            .unwrap_or("");
+        let lines: Vec<&str> = source
+            .lines()
+            .skip(symbol.start_line - 1)
+            .take(symbol.end_line + 1 - symbol.start_line)
+            .collect();
+        let mut body = lines
+            .iter()
+            .take(MAX_DEFINITION_LINES)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+        if lines.len() > MAX_DEFINITION_LINES {
+            body.push_str("\n... (truncated)");
+        }
+        let extension = symbol.path.rsplit('.').next().unwrap_or("");
+        format!(
+            "{} `{}` ({}:{}-{})\n```{}\n{}\n```\n\n",
+            symbol.kind,
+            symbol.qualified_name,
+            symbol.path,
+            symbol.start_line,
+            symbol.end_line,
+            extension,
+            body
+        )
+    }
+}
+
+fn language_for(path: &str) -> Option<Language> {
+    let extension = path.rsplit('.').next()?.to_lowercase();
+    let language = match extension.as_str() {
+        "rs" => tree_sitter_rust::LANGUAGE,
+        "py" => tree_sitter_python::LANGUAGE,
+        "js" | "jsx" | "mjs" | "cjs" => tree_sitter_javascript::LANGUAGE,
+        "ts" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+        "tsx" => tree_sitter_typescript::LANGUAGE_TSX,
+        "go" => tree_sitter_go::LANGUAGE,
+        _ => return None,
+    };
+    Some(language.into())
+}
+
+/// Walks a syntax tree and records symbols. Node kinds are shared across the
+/// supported grammars closely enough that one walker handles all of them.
+struct Collector<'a> {
+    path: &'a str,
+    source: &'a [u8],
+    symbols: &'a mut Vec<Symbol>,
+}
+
+impl Collector<'_> {
+    fn visit(&mut self, node: Node, container: Option<&str>) {
+        match node.kind() {
+            "use_declaration"
+            | "import_statement"
+            | "import_from_statement"
+            | "import_declaration" => {
+                let text = self.text(node);
+                self.push(node, &text, SymbolKind::Import, None);
+            }
+            "function_item"
+            | "function_signature_item"
+            | "function_definition"
+            | "function_declaration"
+            | "method_definition"
+            | "method_declaration" => {
+                let receiver = (node.kind() == "method_declaration")
+                    .then(|| self.go_receiver(node))
+                    .flatten();
+                let container = receiver.as_deref().or(container);
+                let kind = if container.is_some() {
+                    SymbolKind::Method
+                } else {
+                    SymbolKind::Function
+                };
+                self.push_named(node, kind, container);
+            }
+            "impl_item" => {
+                // Methods in `impl Type` are qualified by the implementing type
+                let type_name = node
+                    .child_by_field_name("type")
+                    .map(|t| base_type_name(&self.text(t)));
+                self.visit_children(node, type_name.as_deref());
+            }
+            "struct_item" => {
+                self.push_named(node, SymbolKind::Struct, container);
+            }
+            "enum_item" | "enum_declaration" => {
+                self.push_named(node, SymbolKind::Enum, container);
+            }
+            "type_item" | "type_alias_declaration" => {
+                self.push_named(node, SymbolKind::Type, container);
+            }
+            "mod_item" => {
+                self.push_named(node, SymbolKind::Module, container);
+                self.visit_children(node, container);
+            }
+            "trait_item"
+            | "class_definition"
+            | "class_declaration"
+            | "abstract_class_declaration"
+            | "interface_declaration" => {
+                let kind = match node.kind() {
+                    "trait_item" => SymbolKind::Trait,
+                    "interface_declaration" => SymbolKind::Interface,
+                    _ => SymbolKind::Class,
+                };
+                if let Some(name) = self.push_named(node, kind, container) {
+                    self.visit_children(node, Some(&name));
+                }
+            }
+            "type_spec" => {
+                let kind = match node.child_by_field_name("type").map(|t| t.kind()) {
+                    Some("struct_type") => SymbolKind::Struct,
+                    Some("interface_type") => SymbolKind::Interface,
+                    _ => SymbolKind::Type,
+                };
+                self.push_named(node, kind, container);
+            }
+            _ => self.visit_children(node, container),
+        }
+    }
+
+    fn visit_children(&mut self, node: Node, container: Option<&str>) {
+        let mut cursor = node.walk();
+        for child in node.named_children(&mut cursor) {
+            self.visit(child, container);
+        }
+    }
+
+    /// Record a symbol named by the node's `name` field; returns the name
+    fn push_named(
+        &mut self,
+        node: Node,
+        kind: SymbolKind,
+        container: Option<&str>,
+    ) -> Option<String> {
+        let name = self.text(node.child_by_field_name("name")?);
+        self.push(node, &name, kind, container);
+        Some(name)
+    }
+
+    fn push(&mut self, node: Node, name: &str, kind: SymbolKind, container: Option<&str>) {
+        let qualified_name = match container {
+            Some(container) if kind != SymbolKind::Import => format!("{}::{}", container, name),
+            _ => name.to_string(),
+        };
+        let signature = self
+            .text(node)
+            .lines()
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_string();
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            qualified_name,
+            kind,
+            path: self.path.to_string(),
+            start_line: node.start_position().row + 1,
+            end_line: node.end_position().row + 1,
+            signature,
+        });
+    }
+
+    /// Receiver type of a Go method: `func (m *Manager) Run()` -> `Manager`
+    fn go_receiver(&self, node: Node) -> Option<String> {
+        let receiver = node.child_by_field_name("receiver")?;
+        let mut cursor = receiver.walk();
+        let parameter = receiver.named_children(&mut cursor).next()?;
+        let type_node = parameter.child_by_field_name("type")?;
+        Some(base_type_name(&self.text(type_node)))
+    }
+
+    fn text(&self, node: Node) -> String {
+        node.utf8_text(self.source).unwrap_or_default().to_string()
+    }
+}
+
+/// Strip references, pointers and generic arguments from a type: `&mut Foo<T>` -> `Foo`
+fn base_type_name(type_text: &str) -> String {
+    let name = type_text
+        .trim_start_matches(['&', '*'])
+        .trim_start_matches("mut ")
+        .trim();
+    name.split(['<', '['])
+        .next()
+        .unwrap_or(name)
+        .trim()
+        .to_string()
+}
+
+/// Identifiers in free text that look like references to code
+fn identifier_references(text: &str) -> Vec<String> {
+    let mut references: Vec<String> = Vec::new();
+    let words = text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':' || c == '.'));
+    for word in words {
+        let word = word.trim_matches(|c: char| c == ':' || c == '.');
+        let looks_like_code = word.contains("::")
+            || word.contains('.')
+            || word.contains('_')
+            || word.chars().skip(1).any(|c| c.is_uppercase());
+        if word.len() >= 3 && looks_like_code && !references.iter().any(|r| r == word) {
+            references.push(word.to_string());
+        }
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_rust_and_python() {
+        let rust = "use std::sync::Arc;\n\
+                    \n\
+                    pub struct ContextManager {\n    \
+                        limit: usize,\n\
+                    }\n\
+                    \n\
+                    impl ContextManager {\n    \
+                        pub fn compress_context(&self) -> usize {\n        \
+                            self.limit / 2\n    \
+                        }\n\
+                    }\n";
+        let python =
+            "import os\n\nclass Reviewer:\n    def review(self):\n        return os.getcwd()\n";
+        let index = CodeIndex::build(&[
+            ("src/context.rs".to_string(), rust.to_string()),
+            ("tools/review.py".to_string(), python.to_string()),
+        ]);
+
+        let method = index.find("ContextManager::compress_context");
+        assert_eq!(method.len(), 1);
+        assert_eq!(method[0].kind, SymbolKind::Method);
+        assert_eq!((method[0].start_line, method[0].end_line), (8, 10));
+        assert_eq!(index.find("Reviewer.review")[0].path, "tools/review.py");
+
+        let imports: Vec<&str> = index
+            .symbols_in("src/context.rs")
+            .filter(|s| s.kind == SymbolKind::Import)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(imports, vec!["use std::sync::Arc;"]);
+        assert!(
+            index
+                .outline()
+                .contains("method ContextManager::compress_context (line 8)")
+        );
+
+        let definitions =
+            index.definitions_for("Update `ContextManager::compress_context` to halve less", 5);
+        assert!(definitions.contains("self.limit / 2"));
+        assert!(!definitions.contains("pub struct ContextManager"));
+    }
+}
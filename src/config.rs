@@ -1,7 +1,12 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::config_validation::{self, ConfigErrors, ConfigOrigin};
+use crate::context::ContextSource;
+use crate::planner::ComplexityLevel;
 
 /// Main configuration structure for cli_engineer
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -17,6 +22,108 @@ pub struct Config {
 
     /// Context management configuration
     pub context: ContextConfig,
+
+    /// Formatting of generated artifacts
+    #[serde(default)]
+    pub formatting: FormattingConfig,
+
+    /// Review behaviour
+    #[serde(default)]
+    pub review: ReviewConfig,
+
+    /// Security scanner integrations for the `security` command
+    #[serde(default)]
+    pub security: SecurityConfig,
+
+    /// Which files are scanned into context
+    #[serde(default)]
+    pub scan: ScanConfig,
+
+    /// Secret redaction applied before content is sent to providers
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+
+    /// Work on a dedicated branch and commit after each iteration
+    #[serde(default)]
+    pub git: GitConfig,
+
+    /// Jira and Linear access for `--task`
+    #[serde(default)]
+    pub tracker: TrackerConfig,
+
+    /// Size limits and retention for the artifact directory and run history
+    #[serde(default)]
+    pub artifacts: ArtifactsConfig,
+
+    /// Webhooks and desktop notifications sent when a run finishes or reaches a budget threshold
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+
+    /// OpenTelemetry export of traces and metrics
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+
+    /// Format of the log file and levels of single modules
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
+    /// Cost and token budgets of a run and when to alert about them
+    #[serde(default)]
+    pub budget: BudgetConfig,
+
+    /// External commands run when events are emitted
+    #[serde(default)]
+    pub hooks: HooksConfig,
+
+    /// Local socket streaming events and accepting control messages
+    #[serde(default)]
+    pub ipc: IpcConfig,
+
+    /// Job queue of `serve --http`
+    #[serde(default)]
+    pub serve: ServeConfig,
+
+    /// HTML report written when a run ends
+    #[serde(default)]
+    pub report: ReportConfig,
+
+    /// Output of the `docs` command
+    #[serde(default)]
+    pub docs: DocsConfig,
+
+    /// Model Context Protocol servers whose tools the model can call
+    #[serde(default)]
+    pub mcp: McpConfig,
+
+    /// Language servers whose diagnostics on changed files are added to reviews
+    #[serde(default)]
+    pub lsp: LspConfig,
+
+    /// Agents of the "agents" execution mode
+    #[serde(default)]
+    pub agents: AgentsConfig,
+
+    /// Decisions, conventions and corrections remembered across runs
+    #[serde(default)]
+    pub memory: MemoryConfig,
+
+    /// Names usable wherever a model is set, for a model of any provider
+    /// (`fast = "claude-haiku-4"`) or of one (`strong = "openai/o3"`)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub models: HashMap<String, String>,
+
+    /// Settings of single commands, keyed by command name
+    #[serde(default)]
+    pub command: HashMap<String, CommandConfig>,
+
+    /// Named sets of settings applied on top of the rest with `--profile`,
+    /// e.g. `[profiles.cheap.budget]`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, toml::Value>,
+
+    /// File and settings the configuration was loaded from
+    #[serde(skip)]
+    pub origin: ConfigOrigin,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +144,90 @@ pub struct AIProvidersConfig {
     pub ollama: Option<OllamaConfig>,
 }
 
+impl AIProvidersConfig {
+    /// Model setting of each configured provider, by provider name
+    pub fn models_mut(&mut self) -> Vec<(&'static str, &mut String)> {
+        let sections = [
+            ("openai", &mut self.openai),
+            ("anthropic", &mut self.anthropic),
+            ("openrouter", &mut self.openrouter),
+            ("gemini", &mut self.gemini),
+        ];
+        let mut models: Vec<(&'static str, &mut String)> = sections
+            .into_iter()
+            .filter_map(|(name, section)| Some((name, &mut section.as_mut()?.model)))
+            .collect();
+        if let Some(ollama) = &mut self.ollama {
+            models.push(("ollama", &mut ollama.model));
+        }
+        models
+    }
+
+    /// Provider and model of each enabled provider, in the order they are tried
+    pub fn enabled_models(&self) -> Vec<SecondOpinionConfig> {
+        let sections = [
+            ("openrouter", &self.openrouter),
+            ("gemini", &self.gemini),
+            ("openai", &self.openai),
+            ("anthropic", &self.anthropic),
+        ];
+        let ollama = self.ollama.iter().filter(|ollama| ollama.enabled);
+        sections
+            .into_iter()
+            .filter_map(|(name, section)| {
+                let section = section.as_ref().filter(|section| section.enabled)?;
+                Some((name, section.model.clone()))
+            })
+            .chain(ollama.map(|ollama| ("ollama", ollama.model.clone())))
+            .map(|(name, model)| SecondOpinionConfig {
+                provider: name.to_string(),
+                model: Some(model),
+            })
+            .collect()
+    }
+
+    /// Use only `provider`, with `model`
+    fn route_to(&mut self, provider: &str, model: String) {
+        let sections = [
+            ("openai", &mut self.openai),
+            ("anthropic", &mut self.anthropic),
+            ("openrouter", &mut self.openrouter),
+            ("gemini", &mut self.gemini),
+        ];
+        for (name, section) in sections {
+            if name == provider {
+                let section = section.get_or_insert_with(|| ProviderConfig {
+                    enabled: true,
+                    model: String::new(),
+                    temperature: None,
+                    cost_per_1m_input_tokens: None,
+                    cost_per_1m_output_tokens: None,
+                    max_tokens: None,
+                    api_key: None,
+                    api_key_file: None,
+                });
+                section.enabled = true;
+                section.model = model.clone();
+            } else if let Some(section) = section {
+                section.enabled = false;
+            }
+        }
+        if provider == "ollama" {
+            let ollama = self.ollama.get_or_insert_with(|| OllamaConfig {
+                enabled: true,
+                model: String::new(),
+                temperature: None,
+                base_url: None,
+                max_tokens: None,
+            });
+            ollama.enabled = true;
+            ollama.model = model;
+        } else if let Some(ollama) = &mut self.ollama {
+            ollama.enabled = false;
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
     /// Whether this provider is enabled
@@ -54,93 +245,1202 @@ pub struct ProviderConfig {
     /// Cost per 1M output tokens (in USD)
     pub cost_per_1m_output_tokens: Option<f32>,
 
-    /// Maximum context size in tokens
-    pub max_tokens: Option<usize>,
+    /// Maximum context size in tokens
+    pub max_tokens: Option<usize>,
+
+    /// API key, used when the provider's environment variable is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// File holding the API key, such as a mounted secret; used when neither
+    /// the environment variable nor `api_key` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaConfig {
+    /// Whether this provider is enabled
+    pub enabled: bool,
+
+    /// Model to use
+    pub model: String,
+
+    /// Temperature setting
+    pub temperature: Option<f32>,
+
+    /// Base URL for Ollama server
+    pub base_url: Option<String>,
+
+    /// Maximum context size in tokens
+    pub max_tokens: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionConfig {
+    /// Maximum iterations for the agentic loop
+    #[serde(default = "default_max_iterations")]
+    pub max_iterations: usize,
+
+    /// Fewer iterations for tasks the planner rates as simpler
+    #[serde(default)]
+    pub iteration_budget: IterationBudgetConfig,
+
+    /// Plan each iteration up front, or let the model work with tools until done
+    #[serde(default)]
+    pub mode: ExecutionMode,
+
+    /// Tools offered to the model in the "tools" mode
+    #[serde(default)]
+    pub tools: ToolsConfig,
+
+    /// Several candidate responses for high-risk steps and retries of failed ones
+    #[serde(default)]
+    pub sampling: SamplingConfig,
+
+    /// Stop early after this many consecutive iterations without progress:
+    /// no material change to the artifacts, or the same review issues again (0 disables)
+    #[serde(default = "default_stall_iterations")]
+    pub stall_iterations: usize,
+
+    /// Stop the run after this many seconds, keeping its checkpoint for --resume
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+
+    /// Wait for guidance from the user before planning each iteration after the first
+    #[serde(default)]
+    pub ask_for_guidance: bool,
+
+    /// Enable parallel task execution
+    #[serde(default = "default_parallel_enabled")]
+    pub parallel_enabled: bool,
+
+    /// Working directory for artifacts
+    #[serde(default = "default_artifact_dir")]
+    pub artifact_dir: String,
+
+    /// Run linters and security scanners, which can execute project code, in a container
+    #[serde(default = "default_isolated_execution")]
+    pub isolated_execution: bool,
+
+    /// Container used when `isolated_execution` is on
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+
+    /// Clean up artifacts on exit
+    #[serde(default = "default_cleanup_on_exit")]
+    pub cleanup_on_exit: bool,
+
+    /// Disable automatic git repository initialization unless explicitly requested
+    #[serde(default = "default_disable_auto_git")]
+    pub disable_auto_git: bool,
+
+    /// Check generated code for syntax errors before saving it
+    #[serde(default = "default_validate_syntax")]
+    pub validate_syntax: bool,
+
+    /// Maximum attempts at asking the model to fix syntax errors within a step
+    #[serde(default = "default_max_syntax_fix_attempts")]
+    pub max_syntax_fix_attempts: usize,
+
+    /// Save each step's prompt, response, and artifacts under .cli_engineer/transcripts/
+    #[serde(default = "default_save_transcripts")]
+    pub save_transcripts: bool,
+
+    /// Write each iteration's plan, step outcomes, review and next attempt to
+    /// .cli_engineer/runs/<run>/iterations/<n>.md
+    #[serde(default = "default_save_iteration_reports")]
+    pub save_iteration_reports: bool,
+
+    /// Append every reasoning trace to .cli_engineer/reasoning_<run>.md
+    #[serde(default = "default_export_reasoning")]
+    pub export_reasoning: bool,
+
+    /// How to handle files that changed on disk since they were loaded ("merge", "prompt", "keep", "overwrite")
+    #[serde(default)]
+    pub conflict_strategy: ConflictStrategy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationBudgetConfig {
+    /// Scale the iterations allowed with the complexity of the task's plans
+    #[serde(default = "default_adaptive_iterations")]
+    pub adaptive: bool,
+
+    /// Most iterations for a simple task
+    #[serde(default = "default_simple_iterations")]
+    pub simple: usize,
+
+    /// Most iterations for a task of medium complexity
+    #[serde(default = "default_medium_iterations")]
+    pub medium: usize,
+
+    /// Most iterations for a complex task; `max_iterations` when unset
+    #[serde(default)]
+    pub complex: Option<usize>,
+}
+
+impl Default for IterationBudgetConfig {
+    fn default() -> Self {
+        Self {
+            adaptive: default_adaptive_iterations(),
+            simple: default_simple_iterations(),
+            medium: default_medium_iterations(),
+            complex: None,
+        }
+    }
+}
+
+impl IterationBudgetConfig {
+    /// Iterations allowed for a task of `complexity`, never more than `max_iterations`
+    pub fn limit(&self, complexity: &ComplexityLevel, max_iterations: usize) -> usize {
+        if !self.adaptive {
+            return max_iterations;
+        }
+        let limit = match complexity {
+            ComplexityLevel::Simple => Some(self.simple),
+            ComplexityLevel::Medium => Some(self.medium),
+            ComplexityLevel::Complex => self.complex,
+        };
+        limit.map_or(max_iterations, |limit| limit.min(max_iterations))
+    }
+
+    /// The limit once a plan of `complexity` is made, if it is above `current`,
+    /// the limit so far: the most complex plan of a run sets its limit, and a
+    /// simpler plan later never lowers it
+    pub fn ratchet(
+        &self,
+        current: Option<usize>,
+        complexity: &ComplexityLevel,
+        max_iterations: usize,
+    ) -> Option<usize> {
+        let limit = self.limit(complexity, max_iterations);
+        current.is_none_or(|current| limit > current).then_some(limit)
+    }
+}
+
+/// How each iteration of the agentic loop does its work
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    /// Plan the iteration, then execute the plan's steps
+    #[default]
+    Plan,
+    /// Let the model read, search, write and run commands until it is done
+    Tools,
+    /// Plan, implement, test and review with specialized agents (experimental)
+    Agents,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolsConfig {
+    /// Most responses the model gets per iteration before its work is reviewed
+    #[serde(default = "default_tool_max_turns")]
+    pub max_turns: usize,
+
+    /// Offer the run_command tool, which runs shell commands where the
+    /// artifacts are written, in the sandbox with `isolated_execution`
+    #[serde(default = "default_tool_run_commands")]
+    pub run_commands: bool,
+
+    /// Seconds a command may run before it is killed
+    #[serde(default = "default_tool_command_timeout_secs")]
+    pub command_timeout_secs: u64,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            max_turns: default_tool_max_turns(),
+            run_commands: default_tool_run_commands(),
+            command_timeout_secs: default_tool_command_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SamplingConfig {
+    /// Candidate responses generated for a high-risk step or a retry; 1 disables sampling
+    #[serde(default = "default_sampling_candidates")]
+    pub candidates: usize,
+
+    /// Further providers taking turns with the main one at generating candidates
+    #[serde(default)]
+    pub providers: Vec<SecondOpinionConfig>,
+
+    /// How the response used is chosen among the candidates
+    #[serde(default)]
+    pub judge: SamplingJudge,
+}
+
+impl Default for SamplingConfig {
+    fn default() -> Self {
+        Self {
+            candidates: default_sampling_candidates(),
+            providers: Vec::new(),
+            judge: SamplingJudge::default(),
+        }
+    }
+}
+
+/// Who picks the best of a step's candidate responses
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplingJudge {
+    /// The main model picks one, or merges them
+    #[default]
+    Model,
+    /// The candidate agreeing most with the others, without another request
+    Heuristic,
+}
+
+/// How to handle an artifact whose target file changed on disk since it was loaded
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// Three-way merge the on-disk edits with the generated content
+    #[default]
+    Merge,
+    /// Ask on the terminal; falls back to merge when not interactive
+    Prompt,
+    /// Keep the on-disk file and write the generated content alongside it
+    Keep,
+    /// Replace the on-disk file with the generated content
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxConfig {
+    /// Container runtime, e.g. "docker" or "podman"
+    #[serde(default = "default_sandbox_runtime")]
+    pub runtime: String,
+
+    /// Image with the project's toolchain; required for isolated execution
+    #[serde(default)]
+    pub image: String,
+
+    /// How the project gets into the container
+    #[serde(default)]
+    pub mount: SandboxMount,
+
+    /// Allow network access from the container
+    #[serde(default)]
+    pub network: bool,
+
+    /// Further options passed to `<runtime> create`, e.g. ["--memory", "2g"]
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            runtime: default_sandbox_runtime(),
+            image: String::new(),
+            mount: SandboxMount::default(),
+            network: false,
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+/// How the project is made available inside the sandbox container
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxMount {
+    /// Copy the project in, so nothing run in the container can change it
+    #[default]
+    Copy,
+    /// Bind-mount the project read-write
+    Mount,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UIConfig {
+    /// Enable colorful output
+    #[serde(default = "default_colorful")]
+    pub colorful: bool,
+
+    /// Show progress bars
+    #[serde(default = "default_progress_bars")]
+    pub progress_bars: bool,
+
+    /// Show real-time metrics
+    #[serde(default = "default_metrics")]
+    pub metrics: bool,
+
+    /// Output format ("terminal", "json", "plain")
+    #[serde(default = "default_output_format")]
+    pub output_format: String,
+
+    /// Colors of the dashboard and text UI
+    #[serde(default)]
+    pub theme: ThemeConfig,
+}
+
+/// Color scheme: a named preset with optional per-element colors on top
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThemeConfig {
+    /// "dark", "light" for light terminal backgrounds, or "mono" for no colors
+    #[serde(default = "default_theme_preset")]
+    pub preset: String,
+
+    /// Colors by element (border, title, text, muted, accent, highlight,
+    /// success, warning, error): names such as "blue", "#rrggbb" or 0-255
+    #[serde(default)]
+    pub colors: HashMap<String, String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            preset: default_theme_preset(),
+            colors: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextConfig {
+    /// Fallback maximum tokens for context (only used if LLM manager unavailable)
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: usize,
+
+    /// Compression threshold (0.0 to 1.0)
+    #[serde(default = "default_compression_threshold")]
+    pub compression_threshold: f32,
+
+    /// Enable context caching
+    #[serde(default = "default_cache_enabled")]
+    pub cache_enabled: bool,
+
+    /// Index scanned files and retrieve only the chunks relevant to each step
+    /// instead of loading every file into context
+    #[serde(default)]
+    pub retrieval: bool,
+
+    /// Number of chunks retrieved per step
+    #[serde(default = "default_retrieval_top_k")]
+    pub retrieval_top_k: usize,
+
+    /// Embedding model; defaults to the provider's embedding model
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+
+    /// Files (relative to the project root) that are always in context and never compressed
+    #[serde(default)]
+    pub pinned: Vec<String>,
+
+    /// Build a symbol index of scanned files so steps can pull in referenced definitions
+    #[serde(default = "default_symbol_index")]
+    pub symbol_index: bool,
+    /// Share of the context window allotted to each kind of message
+    #[serde(default)]
+    pub budgets: ContextBudgets,
+}
+
+/// Fractions (0.0 to 1.0) of the context window reserved for each message source
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ContextBudgets {
+    /// Scanned files and tool findings; files beyond this budget are not loaded
+    #[serde(default = "default_codebase_budget")]
+    pub codebase: f32,
+
+    /// Task input, step prompts and responses; summarized when over budget
+    #[serde(default = "default_conversation_budget")]
+    pub conversation: f32,
+
+    /// Review prompts and verdicts; the oldest are dropped when over budget
+    #[serde(default = "default_plan_review_budget")]
+    pub plan_review: f32,
+}
+
+impl Default for ContextBudgets {
+    fn default() -> Self {
+        Self {
+            codebase: default_codebase_budget(),
+            conversation: default_conversation_budget(),
+            plan_review: default_plan_review_budget(),
+        }
+    }
+}
+
+impl ContextBudgets {
+    pub fn share(&self, source: ContextSource) -> f32 {
+        match source {
+            ContextSource::Codebase => self.codebase,
+            ContextSource::Conversation => self.conversation,
+            ContextSource::PlanReview => self.plan_review,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormattingConfig {
+    /// Run generated artifacts through the language formatter before saving
+    #[serde(default = "default_formatting_enabled")]
+    pub enabled: bool,
+
+    /// Formatter command overrides keyed by file extension (e.g. `py = "ruff format -"`).
+    /// Commands read from stdin and write to stdout; `{filename}` is substituted.
+    /// An empty string disables formatting for that extension.
+    #[serde(default)]
+    pub formatters: HashMap<String, String>,
+
+    /// Maximum time to wait for a formatter, in seconds
+    #[serde(default = "default_formatter_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for FormattingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_formatting_enabled(),
+            formatters: HashMap::new(),
+            timeout_secs: default_formatter_timeout_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewConfig {
+    /// Run project linters (cargo clippy, ruff, eslint) during `review` and `refactor`
+    #[serde(default = "default_run_linters")]
+    pub run_linters: bool,
+
+    /// Maximum time to wait for a linter, in seconds
+    #[serde(default = "default_linter_timeout_secs")]
+    pub linter_timeout_secs: u64,
+
+    /// Issue severities that prevent `ready_to_deploy` (critical, major, minor, info)
+    #[serde(default = "default_block_on")]
+    pub block_on: Vec<String>,
+
+    /// Review the diff of files changed during the run instead of step outputs
+    #[serde(default)]
+    pub diff_only: bool,
+
+    /// Lines of context around each change in diff mode
+    #[serde(default = "default_diff_context_lines")]
+    pub diff_context_lines: usize,
+
+    /// Rubric files appended to the review prompt, keyed by command
+    /// (e.g. `security`) or detected language (e.g. `rust`)
+    #[serde(default)]
+    pub rubrics: HashMap<String, String>,
+
+    /// Optional second reviewer on a different provider/model
+    #[serde(default)]
+    pub second_opinion: Option<SecondOpinionConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecondOpinionConfig {
+    /// Provider name: openai, anthropic, openrouter, gemini or ollama
+    pub provider: String,
+
+    /// Model to use; defaults to the model configured for the provider
+    pub model: Option<String>,
+}
+
+impl Default for ReviewConfig {
+    fn default() -> Self {
+        Self {
+            run_linters: default_run_linters(),
+            linter_timeout_secs: default_linter_timeout_secs(),
+            block_on: default_block_on(),
+            diff_only: false,
+            diff_context_lines: default_diff_context_lines(),
+            rubrics: HashMap::new(),
+            second_opinion: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityConfig {
+    /// Scanners to run: `cargo-audit`, `npm-audit`, `osv`, `semgrep`. `osv` sends
+    /// the project's dependencies to osv.dev, so it only runs when listed.
+    #[serde(default = "default_security_scanners")]
+    pub scanners: Vec<String>,
+
+    /// Ruleset passed to `semgrep --config`
+    #[serde(default = "default_semgrep_config")]
+    pub semgrep_config: String,
+
+    /// Maximum time to wait for a scanner, in seconds
+    #[serde(default = "default_scanner_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Format of the SBOM written by the `sbom` command
+    #[serde(default)]
+    pub sbom_format: SbomFormat,
+}
+
+/// Software bill of materials formats, both written as JSON
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SbomFormat {
+    /// CycloneDX 1.5
+    #[default]
+    Cyclonedx,
+    /// SPDX 2.3
+    Spdx,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            scanners: default_security_scanners(),
+            semgrep_config: default_semgrep_config(),
+            timeout_secs: default_scanner_timeout_secs(),
+            sbom_format: SbomFormat::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanConfig {
+    /// File extensions scanned into context
+    #[serde(default = "default_scan_extensions")]
+    pub extensions: Vec<String>,
+
+    /// File names scanned regardless of extension (manifests, Dockerfiles)
+    #[serde(default = "default_scan_file_names")]
+    pub file_names: Vec<String>,
+
+    /// Maximum directory depth below the project root
+    #[serde(default = "default_scan_max_depth")]
+    pub max_depth: usize,
+
+    /// Files larger than this (bytes) are elided to their most relevant sections
+    #[serde(default = "default_large_file_bytes")]
+    pub large_file_bytes: usize,
+
+    /// Files larger than this (bytes) are skipped
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+
+    /// Per-command adjustments, keyed by command name
+    #[serde(default = "default_scan_commands")]
+    pub commands: HashMap<String, ScanOverride>,
+}
+
+/// Additions and limits applied on top of `[scan]` for one command
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanOverride {
+    #[serde(default)]
+    pub extra_extensions: Vec<String>,
+
+    #[serde(default)]
+    pub extra_file_names: Vec<String>,
+
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+
+    #[serde(default)]
+    pub large_file_bytes: Option<usize>,
+
+    #[serde(default)]
+    pub max_file_bytes: Option<u64>,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            extensions: default_scan_extensions(),
+            file_names: default_scan_file_names(),
+            max_depth: default_scan_max_depth(),
+            large_file_bytes: default_large_file_bytes(),
+            max_file_bytes: default_max_file_bytes(),
+            commands: default_scan_commands(),
+        }
+    }
+}
+
+impl ScanConfig {
+    /// Scan settings for `command`, with its override applied
+    pub fn for_command(&self, command: &str) -> ScanConfig {
+        let mut settings = self.clone();
+        settings.commands.clear();
+        if let Some(command) = self.commands.get(command) {
+            settings.extensions.extend(command.extra_extensions.iter().cloned());
+            settings.file_names.extend(command.extra_file_names.iter().cloned());
+            settings.max_depth = command.max_depth.unwrap_or(self.max_depth);
+            settings.large_file_bytes = command.large_file_bytes.unwrap_or(self.large_file_bytes);
+            settings.max_file_bytes = command.max_file_bytes.unwrap_or(self.max_file_bytes);
+        }
+        settings
+    }
+}
+
+/// Settings replacing the general ones when running one command, from
+/// `[command.<name>]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CommandConfig {
+    /// Model of the enabled provider
+    #[serde(default)]
+    pub model: Option<String>,
+
+    #[serde(default)]
+    pub max_iterations: Option<usize>,
+
+    #[serde(default)]
+    pub artifact_dir: Option<String>,
+
+    /// Scan additions and limits, on top of `[scan.commands.<name>]`
+    #[serde(default)]
+    pub scan: Option<ScanOverride>,
+
+    /// Task given to the agent instead of the built-in one; `{prompt}` is
+    /// replaced by the prompt from the command line
+    #[serde(default)]
+    pub prompt: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactionConfig {
+    /// Redact secrets from scanned and pinned files before they enter context
+    #[serde(default = "default_redaction_enabled")]
+    pub enabled: bool,
+
+    /// Also redact random-looking tokens that match no known secret format
+    #[serde(default = "default_entropy_detection")]
+    pub entropy_detection: bool,
+
+    /// Additional regular expressions whose matches are redacted
+    #[serde(default)]
+    pub extra_patterns: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_redaction_enabled(),
+            entropy_detection: default_entropy_detection(),
+            extra_patterns: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitConfig {
+    /// Create a branch at the start of a run and commit applied changes after each successful iteration
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Prefix of the branch created for each task, followed by a slug of the task
+    #[serde(default = "default_git_branch_prefix")]
+    pub branch_prefix: String,
+
+    /// Push the branch and open a GitHub pull request when a code or refactor run succeeds
+    #[serde(default)]
+    pub pull_request: bool,
+
+    /// Open pull requests as drafts
+    #[serde(default)]
+    pub draft: bool,
+
+    /// Remote the branch is pushed to
+    #[serde(default = "default_git_remote")]
+    pub remote: String,
+
+    /// `owner/name` of the GitHub repository; detected from the remote's URL when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_repo: Option<String>,
+
+    /// Token used when the `GITHUB_TOKEN` environment variable is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>,
+
+    /// Token used when the `GITLAB_TOKEN` environment variable is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gitlab_token: Option<String>,
+
+    /// Hosts of self-managed GitLab instances, besides gitlab.com
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gitlab_hosts: Vec<String>,
+
+    /// Comment on the `--issue` when the run ends with its outcome and pull request
+    #[serde(default = "default_issue_comment")]
+    pub issue_comment: bool,
+}
+
+impl Default for GitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            branch_prefix: default_git_branch_prefix(),
+            pull_request: false,
+            draft: false,
+            remote: default_git_remote(),
+            github_repo: None,
+            github_token: None,
+            gitlab_token: None,
+            gitlab_hosts: Vec::new(),
+            issue_comment: default_issue_comment(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrackerConfig {
+    /// Base URL of the Jira site, e.g. `https://example.atlassian.net`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jira_url: Option<String>,
+
+    /// Account email for Jira Cloud; without it the token is sent as a
+    /// personal access token, as Jira Server and Data Center expect
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jira_email: Option<String>,
+
+    /// Token used when the `JIRA_API_TOKEN` environment variable is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jira_token: Option<String>,
+
+    /// Custom field holding the acceptance criteria, e.g. `customfield_10034`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jira_acceptance_criteria_field: Option<String>,
+
+    /// API key used when the `LINEAR_API_KEY` environment variable is not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linear_token: Option<String>,
+
+    /// Keys of the Linear teams, e.g. `["LIN"]`; tickets of other teams are looked up in Jira
+    #[serde(default)]
+    pub linear_teams: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactsConfig {
+    /// Largest single artifact in MB; bigger artifacts are rejected (0 = no limit)
+    #[serde(default = "default_max_artifact_mb")]
+    pub max_artifact_mb: u64,
+
+    /// Size of the artifact directory in MB above which stale files are pruned (0 = no limit)
+    #[serde(default = "default_max_total_mb")]
+    pub max_total_mb: u64,
+
+    /// Number of most recent runs whose history and stale artifacts are kept (0 = keep all)
+    #[serde(default = "default_keep_runs")]
+    pub keep_runs: usize,
+}
+
+impl Default for ArtifactsConfig {
+    fn default() -> Self {
+        Self {
+            max_artifact_mb: default_max_artifact_mb(),
+            max_total_mb: default_max_total_mb(),
+            keep_runs: default_keep_runs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotificationsConfig {
+    /// Endpoints that receive a message for each notification
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// Show a desktop notification when a run completes or fails
+    #[serde(default)]
+    pub desktop: bool,
+}
+
+impl NotificationsConfig {
+    /// Whether any notification would be sent
+    pub fn enabled(&self) -> bool {
+        self.desktop || !self.webhooks.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+
+    /// Payload shape expected by the endpoint
+    #[serde(default)]
+    pub format: WebhookFormat,
+
+    /// Notifications sent to this webhook; all of them when empty
+    #[serde(default)]
+    pub events: Vec<NotificationKind>,
+}
+
+/// Payload shape of a webhook
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// Slack incoming webhook: `{"text": ...}`
+    Slack,
+    /// Discord webhook: `{"content": ...}`
+    Discord,
+    /// The notification's fields as a JSON object
+    #[default]
+    Json,
+}
+
+/// What a notification reports
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    TaskCompleted,
+    TaskFailed,
+    BudgetThreshold,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// Export spans and metrics over OTLP/HTTP
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Collector base URL, e.g. `http://localhost:4318`. When unset the standard
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT` variable or the OTLP default is used.
+    #[serde(default)]
+    pub endpoint: Option<String>,
+
+    /// `service.name` resource attribute of the exported data
+    #[serde(default = "default_telemetry_service_name")]
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: None,
+            service_name: default_telemetry_service_name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggingConfig {
+    /// Format of the log file
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Levels of single modules, overriding the level `--verbose` sets:
+    /// `providers = "debug"`, `ui = "warn"`. A key applies to the modules whose
+    /// path starts with it; the longest matching key wins.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub levels: HashMap<String, String>,
+
+    /// Size in MB past which the log is continued in a new file (0 = no limit)
+    #[serde(default = "default_log_max_file_mb")]
+    pub max_file_mb: u64,
+
+    /// Age in days past which log files are deleted (0 = keep all)
+    #[serde(default = "default_log_max_age_days")]
+    pub max_age_days: u64,
+
+    /// Number of most recent log files kept (0 = keep all)
+    #[serde(default = "default_log_max_files")]
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            format: LogFormat::default(),
+            levels: HashMap::new(),
+            max_file_mb: default_log_max_file_mb(),
+            max_age_days: default_log_max_age_days(),
+            max_files: default_log_max_files(),
+        }
+    }
+}
+
+/// Log file formats
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One line of text per record
+    #[default]
+    Text,
+    /// One JSON object per record, with the timestamp, level, module, message
+    /// and the step and provider it was logged for
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetConfig {
+    /// Cost budget of a run in USD
+    #[serde(default)]
+    pub max_cost: Option<f32>,
+
+    /// Token budget of a run
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+
+    /// Percentages of a budget at which a `BudgetThreshold` event is emitted
+    #[serde(default = "default_alert_thresholds")]
+    pub alert_thresholds: Vec<u8>,
+}
+
+impl Default for BudgetConfig {
+    fn default() -> Self {
+        Self {
+            max_cost: None,
+            max_tokens: None,
+            alert_thresholds: default_alert_thresholds(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Maximum time a hook may run, in seconds
+    #[serde(default = "default_hook_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Emit each hook's exit status and output as a `hook_output` event
+    #[serde(default)]
+    pub emit_output: bool,
+
+    /// Commands keyed by `on_<event>`, e.g. `on_artifact_created = "./lint.sh {path}"`.
+    /// Fields of the event are substituted for `{field}` placeholders.
+    #[serde(flatten)]
+    pub commands: HashMap<String, String>,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_hook_timeout_secs(),
+            emit_output: false,
+            commands: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IpcConfig {
+    /// Stream events and accept pause, resume, cancel and approve messages
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Unix socket path or Windows pipe name. Defaults to
+    /// `.cli_engineer/cli_engineer.sock`, or `\\.\pipe\cli_engineer` on Windows.
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Wait for an `approve` message before executing each plan
+    #[serde(default)]
+    pub require_approval: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServeConfig {
+    /// Bearer token required by `serve --http` when the `CLI_ENGINEER_API_TOKEN`
+    /// environment variable is not set; without either, one is generated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_token: Option<String>,
+
+    /// Most jobs waiting to run; further submissions are refused until one starts
+    #[serde(default = "default_max_queued_jobs")]
+    pub max_queued_jobs: usize,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self {
+            api_token: None,
+            max_queued_jobs: default_max_queued_jobs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportConfig {
+    /// Write `.cli_engineer/runs/<run_id>/report.html` when a run ends
+    #[serde(default)]
+    pub html: bool,
+
+    /// Open the report in the default browser; implies `html`
+    #[serde(default)]
+    pub open: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DocsConfig {
+    /// Write Mermaid diagrams of the modules and their imports to `docs/architecture.md`
+    #[serde(default)]
+    pub diagrams: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpConfig {
+    /// Tool call rounds allowed after each step's response
+    #[serde(default = "default_max_tool_rounds")]
+    pub max_tool_rounds: usize,
+
+    /// Servers keyed by the name the model calls them by
+    #[serde(default)]
+    pub servers: HashMap<String, McpServerConfig>,
+}
+
+impl Default for McpConfig {
+    fn default() -> Self {
+        Self {
+            max_tool_rounds: default_max_tool_rounds(),
+            servers: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Program started to serve the protocol over its stdin and stdout
+    pub command: String,
+
+    /// Arguments passed to the program
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Environment variables set for the program
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+
+    /// Start the server; disabled servers are skipped
+    #[serde(default = "default_mcp_server_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LspConfig {
+    /// Ask language servers for diagnostics on the files each iteration changed
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// How long to wait for a server's diagnostics, including its startup
+    #[serde(default = "default_lsp_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Servers keyed by name; rust-analyzer and typescript-language-server when unset
+    #[serde(default = "default_lsp_servers")]
+    pub servers: HashMap<String, LspServerConfig>,
+}
+
+impl Default for LspConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_secs: default_lsp_timeout_secs(),
+            servers: default_lsp_servers(),
+        }
+    }
 }
 
+/// Project memory, kept in .cli_engineer/memory.jsonl with an embedding index
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OllamaConfig {
-    /// Whether this provider is enabled
+pub struct MemoryConfig {
+    /// Recall the memories relevant to each task for the planner
+    #[serde(default = "default_memory_enabled")]
     pub enabled: bool,
 
-    /// Model to use
-    pub model: String,
-
-    /// Temperature setting
-    pub temperature: Option<f32>,
+    /// Most memories recalled for a task
+    #[serde(default = "default_memory_top_k")]
+    pub top_k: usize,
 
-    /// Base URL for Ollama server
-    pub base_url: Option<String>,
+    /// Ask the model at the end of each approved run which decisions and conventions to remember
+    #[serde(default = "default_memory_learn")]
+    pub learn: bool,
+}
 
-    /// Maximum context size in tokens
-    pub max_tokens: Option<usize>,
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_memory_enabled(),
+            top_k: default_memory_top_k(),
+            learn: default_memory_learn(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExecutionConfig {
-    /// Maximum iterations for the agentic loop
-    #[serde(default = "default_max_iterations")]
-    pub max_iterations: usize,
+/// The agents of the "agents" execution mode, by role
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentsConfig {
+    /// Plans each iteration
+    #[serde(default)]
+    pub architect: AgentConfig,
 
-    /// Enable parallel task execution
-    #[serde(default = "default_parallel_enabled")]
-    pub parallel_enabled: bool,
+    /// Carries out the plan's steps, except the testing ones
+    #[serde(default)]
+    pub implementer: AgentConfig,
 
-    /// Working directory for artifacts
-    #[serde(default = "default_artifact_dir")]
-    pub artifact_dir: String,
+    /// Carries out the plan's testing steps
+    #[serde(default)]
+    pub tester: AgentConfig,
 
-    /// Enable isolated execution environments
-    #[serde(default = "default_isolated_execution")]
-    pub isolated_execution: bool,
+    /// Reviews each iteration's work
+    #[serde(default)]
+    pub reviewer: AgentConfig,
+}
 
-    /// Clean up artifacts on exit
-    #[serde(default = "default_cleanup_on_exit")]
-    pub cleanup_on_exit: bool,
+impl AgentsConfig {
+    /// Settings of each agent, by role name
+    pub fn roles(&self) -> [(&'static str, &AgentConfig); 4] {
+        [
+            ("architect", &self.architect),
+            ("implementer", &self.implementer),
+            ("tester", &self.tester),
+            ("reviewer", &self.reviewer),
+        ]
+    }
 
-    /// Disable automatic git repository initialization unless explicitly requested
-    #[serde(default = "default_disable_auto_git")]
-    pub disable_auto_git: bool,
+    pub fn roles_mut(&mut self) -> [(&'static str, &mut AgentConfig); 4] {
+        [
+            ("architect", &mut self.architect),
+            ("implementer", &mut self.implementer),
+            ("tester", &mut self.tester),
+            ("reviewer", &mut self.reviewer),
+        ]
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UIConfig {
-    /// Enable colorful output
-    #[serde(default = "default_colorful")]
-    pub colorful: bool,
-
-    /// Show progress bars
-    #[serde(default = "default_progress_bars")]
-    pub progress_bars: bool,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentConfig {
+    /// Provider of the agent's model: openai, anthropic, openrouter, gemini or ollama;
+    /// the main provider when unset
+    #[serde(default)]
+    pub provider: Option<String>,
 
-    /// Show real-time metrics
-    #[serde(default = "default_metrics")]
-    pub metrics: bool,
+    /// Model of the agent; the provider's configured model when unset
+    #[serde(default)]
+    pub model: Option<String>,
 
-    /// Output format ("terminal", "json", "plain")
-    #[serde(default = "default_output_format")]
-    pub output_format: String,
+    /// Replaces the built-in system prompt of the agent's role
+    #[serde(default)]
+    pub system_prompt: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContextConfig {
-    /// Fallback maximum tokens for context (only used if LLM manager unavailable)
-    #[serde(default = "default_max_tokens")]
-    pub max_tokens: usize,
+pub struct LspServerConfig {
+    /// Program started to serve the protocol over its stdin and stdout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
 
-    /// Compression threshold (0.0 to 1.0)
-    #[serde(default = "default_compression_threshold")]
-    pub compression_threshold: f32,
+    /// Arguments passed to the program
+    #[serde(default)]
+    pub args: Vec<String>,
 
-    /// Enable context caching
-    #[serde(default = "default_cache_enabled")]
-    pub cache_enabled: bool,
+    /// `host:port` of a running server to connect to instead of starting one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+
+    /// Extensions of the files the server checks, e.g. ["rs"]
+    pub extensions: Vec<String>,
 }
 
 // Default value functions
 fn default_max_iterations() -> usize {
     10
 }
+fn default_stall_iterations() -> usize {
+    2
+}
 fn default_parallel_enabled() -> bool {
     false
 }
@@ -150,6 +1450,21 @@ fn default_artifact_dir() -> String {
 fn default_isolated_execution() -> bool {
     false
 }
+fn default_tool_max_turns() -> usize {
+    30
+}
+fn default_tool_run_commands() -> bool {
+    false
+}
+fn default_tool_command_timeout_secs() -> u64 {
+    120
+}
+fn default_sampling_candidates() -> usize {
+    1
+}
+fn default_sandbox_runtime() -> String {
+    "docker".to_string()
+}
 fn default_cleanup_on_exit() -> bool {
     false
 }
@@ -165,6 +1480,9 @@ fn default_metrics() -> bool {
 fn default_output_format() -> String {
     "terminal".to_string()
 }
+fn default_theme_preset() -> String {
+    "dark".to_string()
+}
 fn default_max_tokens() -> usize {
     100_000
 }
@@ -177,6 +1495,205 @@ fn default_cache_enabled() -> bool {
 fn default_disable_auto_git() -> bool {
     false
 }
+fn default_git_branch_prefix() -> String {
+    "cli-engineer/".to_string()
+}
+fn default_git_remote() -> String {
+    "origin".to_string()
+}
+fn default_issue_comment() -> bool {
+    false
+}
+fn default_max_queued_jobs() -> usize {
+    100
+}
+fn default_max_artifact_mb() -> u64 {
+    10
+}
+fn default_max_total_mb() -> u64 {
+    500
+}
+fn default_keep_runs() -> usize {
+    20
+}
+fn default_lsp_timeout_secs() -> u64 {
+    60
+}
+fn default_lsp_servers() -> HashMap<String, LspServerConfig> {
+    let server = |command: &str, args: &[&str], extensions: &[&str]| LspServerConfig {
+        command: Some(command.to_string()),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        address: None,
+        extensions: extensions.iter().map(|e| e.to_string()).collect(),
+    };
+    HashMap::from([
+        ("rust".to_string(), server("rust-analyzer", &[], &["rs"])),
+        (
+            "typescript".to_string(),
+            server(
+                "typescript-language-server",
+                &["--stdio"],
+                &["ts", "tsx", "js", "jsx"],
+            ),
+        ),
+    ])
+}
+fn default_max_tool_rounds() -> usize {
+    5
+}
+fn default_mcp_server_enabled() -> bool {
+    true
+}
+fn default_telemetry_service_name() -> String {
+    "cli_engineer".to_string()
+}
+fn default_log_max_file_mb() -> u64 {
+    10
+}
+fn default_log_max_age_days() -> u64 {
+    14
+}
+fn default_log_max_files() -> usize {
+    20
+}
+fn default_alert_thresholds() -> Vec<u8> {
+    vec![50, 80, 100]
+}
+fn default_hook_timeout_secs() -> u64 {
+    60
+}
+fn default_formatting_enabled() -> bool {
+    true
+}
+fn default_formatter_timeout_secs() -> u64 {
+    30
+}
+fn default_retrieval_top_k() -> usize {
+    8
+}
+fn default_codebase_budget() -> f32 {
+    0.6
+}
+fn default_conversation_budget() -> f32 {
+    0.25
+}
+fn default_plan_review_budget() -> f32 {
+    0.15
+}
+fn default_symbol_index() -> bool {
+    true
+}
+fn default_run_linters() -> bool {
+    true
+}
+fn default_linter_timeout_secs() -> u64 {
+    300
+}
+fn default_block_on() -> Vec<String> {
+    vec!["critical".to_string(), "major".to_string()]
+}
+fn default_diff_context_lines() -> usize {
+    3
+}
+fn default_security_scanners() -> Vec<String> {
+    vec![
+        "cargo-audit".to_string(),
+        "npm-audit".to_string(),
+        "semgrep".to_string(),
+    ]
+}
+fn default_semgrep_config() -> String {
+    "auto".to_string()
+}
+fn default_scanner_timeout_secs() -> u64 {
+    600
+}
+fn default_scan_extensions() -> Vec<String> {
+    [
+        "rs", "py", "js", "ts", "java", "c", "cpp", "h", "hpp", "go", "rb", "php", "swift", "kt",
+        "scala", "sh", "bash", "yaml", "yml", "json", "toml", "xml", "html", "css", "jsx", "tsx",
+        "vue", "svelte",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+fn default_scan_file_names() -> Vec<String> {
+    [
+        "Cargo.toml", "package.json", "pom.xml", "build.gradle", "requirements.txt", "setup.py",
+        "Gemfile", "composer.json", "Makefile", "Dockerfile", ".gitignore", "README.md", "README",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+fn default_scan_max_depth() -> usize {
+    5
+}
+fn default_large_file_bytes() -> usize {
+    100_000
+}
+fn default_max_file_bytes() -> u64 {
+    2_000_000
+}
+fn default_scan_commands() -> HashMap<String, ScanOverride> {
+    // Security reviews need dependency lockfiles and infrastructure-as-code
+    let security = ScanOverride {
+        extra_extensions: ["tf", "tfvars", "hcl", "bicep", "conf", "ini", "properties"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        extra_file_names: [
+            "Cargo.lock", "package-lock.json", "yarn.lock", "pnpm-lock.yaml", "poetry.lock",
+            "Pipfile", "Pipfile.lock", "go.mod", "go.sum", "Gemfile.lock", "composer.lock",
+            "docker-compose.yml", "docker-compose.yaml", "Containerfile", "Jenkinsfile",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect(),
+        ..Default::default()
+    };
+    HashMap::from([("security".to_string(), security)])
+}
+fn default_redaction_enabled() -> bool {
+    true
+}
+fn default_entropy_detection() -> bool {
+    true
+}
+fn default_adaptive_iterations() -> bool {
+    false
+}
+fn default_simple_iterations() -> usize {
+    2
+}
+fn default_medium_iterations() -> usize {
+    5
+}
+fn default_memory_enabled() -> bool {
+    true
+}
+fn default_memory_top_k() -> usize {
+    5
+}
+fn default_memory_learn() -> bool {
+    false
+}
+fn default_save_iteration_reports() -> bool {
+    true
+}
+fn default_save_transcripts() -> bool {
+    true
+}
+fn default_export_reasoning() -> bool {
+    false
+}
+fn default_validate_syntax() -> bool {
+    true
+}
+fn default_max_syntax_fix_attempts() -> usize {
+    2
+}
 
 impl Default for Config {
     fn default() -> Self {
@@ -189,6 +1706,8 @@ impl Default for Config {
                     cost_per_1m_input_tokens: None,
                     cost_per_1m_output_tokens: None,
                     max_tokens: None,
+                    api_key: None,
+                    api_key_file: None,
                 }),
                 anthropic: Some(ProviderConfig {
                     enabled: false,
@@ -197,6 +1716,8 @@ impl Default for Config {
                     cost_per_1m_input_tokens: None,
                     cost_per_1m_output_tokens: None,
                     max_tokens: None,
+                    api_key: None,
+                    api_key_file: None,
                 }),
                 openrouter: Some(ProviderConfig {
                     enabled: false,
@@ -205,6 +1726,8 @@ impl Default for Config {
                     cost_per_1m_input_tokens: None,
                     cost_per_1m_output_tokens: None,
                     max_tokens: None,
+                    api_key: None,
+                    api_key_file: None,
                 }),
                 gemini: Some(ProviderConfig {
                     enabled: false,
@@ -213,6 +1736,8 @@ impl Default for Config {
                     cost_per_1m_input_tokens: None,
                     cost_per_1m_output_tokens: None,
                     max_tokens: None,
+                    api_key: None,
+                    api_key_file: None,
                 }),
                 ollama: Some(OllamaConfig {
                     enabled: false,
@@ -224,62 +1749,286 @@ impl Default for Config {
             },
             execution: ExecutionConfig {
                 max_iterations: default_max_iterations(),
+                iteration_budget: IterationBudgetConfig::default(),
+                mode: ExecutionMode::default(),
+                tools: ToolsConfig::default(),
+                sampling: SamplingConfig::default(),
+                stall_iterations: default_stall_iterations(),
+                timeout_secs: None,
+                ask_for_guidance: false,
                 parallel_enabled: default_parallel_enabled(),
                 artifact_dir: default_artifact_dir(),
                 isolated_execution: default_isolated_execution(),
+                sandbox: SandboxConfig::default(),
                 cleanup_on_exit: default_cleanup_on_exit(),
                 disable_auto_git: default_disable_auto_git(),
+                validate_syntax: default_validate_syntax(),
+                max_syntax_fix_attempts: default_max_syntax_fix_attempts(),
+                save_transcripts: default_save_transcripts(),
+                save_iteration_reports: default_save_iteration_reports(),
+                export_reasoning: default_export_reasoning(),
+                conflict_strategy: ConflictStrategy::default(),
             },
             ui: UIConfig {
                 colorful: default_colorful(),
                 progress_bars: default_progress_bars(),
                 metrics: default_metrics(),
                 output_format: default_output_format(),
+                theme: ThemeConfig::default(),
             },
             context: ContextConfig {
                 max_tokens: default_max_tokens(),
                 compression_threshold: default_compression_threshold(),
                 cache_enabled: default_cache_enabled(),
+                retrieval: false,
+                retrieval_top_k: default_retrieval_top_k(),
+                embedding_model: None,
+                pinned: Vec::new(),
+                symbol_index: default_symbol_index(),
+                budgets: ContextBudgets::default(),
             },
+            formatting: FormattingConfig::default(),
+            review: ReviewConfig::default(),
+            security: SecurityConfig::default(),
+            scan: ScanConfig::default(),
+            redaction: RedactionConfig::default(),
+            git: GitConfig::default(),
+            tracker: TrackerConfig::default(),
+            artifacts: ArtifactsConfig::default(),
+            notifications: NotificationsConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            logging: LoggingConfig::default(),
+            budget: BudgetConfig::default(),
+            hooks: HooksConfig::default(),
+            ipc: IpcConfig::default(),
+            serve: ServeConfig::default(),
+            report: ReportConfig::default(),
+            docs: DocsConfig::default(),
+            mcp: McpConfig::default(),
+            lsp: LspConfig::default(),
+            agents: AgentsConfig::default(),
+            memory: MemoryConfig::default(),
+            models: HashMap::new(),
+            command: HashMap::new(),
+            profiles: HashMap::new(),
+            origin: ConfigOrigin::default(),
         }
     }
 }
 
 impl Config {
-    /// Load configuration from a TOML file
-    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let contents = fs::read_to_string(path.as_ref())
-            .with_context(|| format!("Failed to read config file: {}", path.as_ref().display()))?;
+    /// Load configuration from a TOML file, with the settings of `profile`
+    /// and then the `CLI_ENGINEER__*` environment variables applied on top
+    pub fn from_file<P: AsRef<Path>>(path: P, profile: Option<&str>) -> Result<Self> {
+        Self::from_files(&[path.as_ref().to_path_buf()], profile)
+    }
 
-        toml::from_str(&contents)
-            .with_context(|| format!("Failed to parse config file: {}", path.as_ref().display()))
+    /// Load configuration from TOML files merged in order, each overriding
+    /// the sections and fields of those before it
+    fn from_files(paths: &[PathBuf], profile: Option<&str>) -> Result<Self> {
+        let mut table = toml::Table::new();
+        let mut origin = ConfigOrigin::default();
+        for path in paths {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+            let file_table: toml::Table = toml::from_str(&contents)
+                .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+            merge_tables(&mut table, file_table);
+            origin.files.push((path.clone(), contents));
+        }
+        let names: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+        Self::from_table(table, profile, origin)
+            .with_context(|| format!("Failed to load config file: {}", names.join(" and ")))
+    }
+
+    /// Configuration from the parsed contents of a file, with `profile` and
+    /// the environment overrides applied
+    fn from_table(
+        mut table: toml::Table,
+        profile: Option<&str>,
+        mut origin: ConfigOrigin,
+    ) -> Result<Self> {
+        if let Some(profile) = profile {
+            apply_profile(&mut table, profile)?;
+        }
+        let overrides = apply_env_overrides(&mut table, std::env::vars())?;
+        origin.table = table.clone();
+        origin.profile = profile.map(str::to_string);
+        origin.overrides = overrides.clone();
+        let config: Result<Self, _> = toml::Value::Table(table).try_into();
+        let mut config = match config {
+            Ok(config) => config,
+            // Misspelled keys are the likely cause of a missing or mistyped field
+            Err(e) => {
+                let mut problems = config_validation::check_keys(&origin);
+                problems.push(config_validation::Problem {
+                    location: None,
+                    message: e.message().trim_end().to_string(),
+                });
+                let error = anyhow::Error::new(ConfigErrors(problems));
+                return Err(if overrides.is_empty() {
+                    error
+                } else {
+                    error.context(format!("With overrides from {}", overrides.join(", ")))
+                });
+            }
+        };
+        config.origin = origin;
+        config.resolve_model_aliases();
+        Ok(config)
+    }
+
+    /// Replace the aliases among the provider, second-opinion, sampling and
+    /// agent models with the models they stand for. An alias for another provider's model is
+    /// left for validation to report.
+    fn resolve_model_aliases(&mut self) {
+        for (provider, model) in self.ai_providers.models_mut() {
+            match resolve_model(&self.models, model) {
+                (Some(target), _) if target != provider => {}
+                (_, resolved) => *model = resolved,
+            }
+        }
+        let sampling = self.execution.sampling.providers.iter_mut();
+        for second_opinion in self.review.second_opinion.iter_mut().chain(sampling) {
+            let Some(model) = &second_opinion.model else {
+                continue;
+            };
+            let (provider, model) = resolve_model(&self.models, model);
+            if let Some(provider) = provider {
+                second_opinion.provider = provider;
+            }
+            second_opinion.model = Some(model);
+        }
+        for (_, agent) in self.agents.roles_mut() {
+            let Some(model) = &agent.model else {
+                continue;
+            };
+            let (provider, model) = resolve_model(&self.models, model);
+            if provider.is_some() {
+                agent.provider = provider;
+            }
+            agent.model = Some(model);
+        }
+    }
+
+    /// Check the configuration, reporting every problem at once: unknown keys,
+    /// values out of range, conflicting options and, with `require_api_keys`,
+    /// enabled providers whose API key is not set
+    pub fn validate(&self, require_api_keys: bool) -> Result<()> {
+        let mut problems = config_validation::check_keys(&self.origin);
+        problems.extend(config_validation::check_settings(self, require_api_keys));
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(problems).into())
+        }
     }
 
     /// Load configuration from command line argument or default locations
-    pub fn load(config_path: &Option<String>) -> Result<Self> {
+    pub fn load(config_path: &Option<String>, profile: Option<&str>) -> Result<Self> {
         if let Some(path) = config_path {
-            return Self::from_file(path);
+            return Self::from_file(path, profile);
         }
 
-        // Try loading from default locations
-        let default_paths = vec![
-            "cli_engineer.toml",
-            ".cli_engineer.toml",
-            "~/.config/cli_engineer/config.toml",
-        ];
+        // The user's defaults, such as providers, with the project's file over them
+        let global = PathBuf::from(shellexpand::tilde(GLOBAL_CONFIG_PATH).as_ref());
+        let project = PROJECT_CONFIG_PATHS.iter().map(PathBuf::from).find(|path| path.exists());
+        let paths: Vec<PathBuf> = [Some(global), project]
+            .into_iter()
+            .flatten()
+            .filter(|path| path.exists())
+            .collect();
+        if !paths.is_empty() {
+            return Self::from_files(&paths, profile);
+        }
+
+        // The defaults have no profiles
+        if let Some(profile) = profile {
+            anyhow::bail!("Profile '{}' requested but no config file was loaded", profile);
+        }
+
+        // Use the default config if no file found, still open to overrides
+        let defaults = toml::Value::try_from(Self::default())
+            .context("Failed to serialize configuration")?;
+        match defaults {
+            toml::Value::Table(table) => Self::from_table(table, None, ConfigOrigin::default()),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// The provider and model `name` stands for: a provider, with its
+    /// configured model, `provider/model` or an alias for one provider's model
+    pub fn model_choice(&self, name: &str) -> Result<SecondOpinionConfig> {
+        if PROVIDER_NAMES.contains(&name) {
+            return Ok(SecondOpinionConfig {
+                provider: name.to_string(),
+                model: None,
+            });
+        }
+        let (provider, model) = match name.split_once('/') {
+            Some((provider, model)) if PROVIDER_NAMES.contains(&provider) => {
+                (Some(provider.to_string()), model.to_string())
+            }
+            _ => resolve_model(&self.models, name),
+        };
+        let Some(provider) = provider else {
+            anyhow::bail!(
+                "'{}' names no provider; use PROVIDER, PROVIDER/MODEL or an alias for PROVIDER/MODEL",
+                name
+            );
+        };
+        Ok(SecondOpinionConfig {
+            provider,
+            model: Some(model),
+        })
+    }
 
-        for path in default_paths {
-            let expanded_path = shellexpand::tilde(path);
-            if Path::new(expanded_path.as_ref()).exists() {
-                match Self::from_file(expanded_path.as_ref()) {
-                    Ok(config) => return Ok(config),
-                    Err(e) => eprintln!("Warning: Failed to load config from {}: {}", path, e),
+    /// Configuration for running `command`, with its `[command.<name>]`
+    /// settings applied
+    pub fn for_command(mut self, command: &str) -> Self {
+        let Some(settings) = self.command.get(command).cloned() else {
+            return self;
+        };
+        if let Some(model) = settings.model {
+            match resolve_model(&self.models, &model) {
+                // An alias for one provider's model runs the command on that provider
+                (Some(provider), model) => self.ai_providers.route_to(&provider, model),
+                (None, model) => {
+                    let providers = &mut self.ai_providers;
+                    let sections = [
+                        &mut providers.openai,
+                        &mut providers.anthropic,
+                        &mut providers.openrouter,
+                        &mut providers.gemini,
+                    ];
+                    for provider in sections.into_iter().flatten() {
+                        if provider.enabled {
+                            provider.model = model.clone();
+                        }
+                    }
+                    if let Some(ollama) = &mut providers.ollama
+                        && ollama.enabled
+                    {
+                        ollama.model = model;
+                    }
                 }
             }
         }
-
-        // Return default config if no file found
-        Ok(Self::default())
+        if let Some(max_iterations) = settings.max_iterations {
+            self.execution.max_iterations = max_iterations;
+        }
+        if let Some(artifact_dir) = settings.artifact_dir {
+            self.execution.artifact_dir = artifact_dir;
+        }
+        if let Some(scan) = settings.scan {
+            let existing = self.scan.commands.entry(command.to_string()).or_default();
+            existing.extra_extensions.extend(scan.extra_extensions);
+            existing.extra_file_names.extend(scan.extra_file_names);
+            existing.max_depth = scan.max_depth.or(existing.max_depth);
+            existing.large_file_bytes = scan.large_file_bytes.or(existing.large_file_bytes);
+            existing.max_file_bytes = scan.max_file_bytes.or(existing.max_file_bytes);
+        }
+        self
     }
 
     /// Save configuration to a file
@@ -303,3 +2052,448 @@ impl Config {
         }
     }
 }
+
+/// API key of a provider from `env_var`, or else from the `api_key` or
+/// `api_key_file` of its section
+pub fn resolve_api_key(env_var: &str, section: Option<&ProviderConfig>) -> Result<String> {
+    if let Ok(key) = std::env::var(env_var)
+        && !key.is_empty()
+    {
+        return Ok(key);
+    }
+    if let Some(key) = section.and_then(|section| section.api_key.clone()) {
+        return Ok(key);
+    }
+    let Some(path) = section.and_then(|section| section.api_key_file.as_deref()) else {
+        anyhow::bail!(
+            "{} environment variable not set, and no api_key or api_key_file is configured",
+            env_var
+        );
+    };
+    let path = shellexpand::tilde(path);
+    let key = fs::read_to_string(path.as_ref())
+        .with_context(|| format!("Failed to read API key file: {}", path))?;
+    let key = key.trim();
+    if key.is_empty() {
+        anyhow::bail!("API key file is empty: {}", path);
+    }
+    Ok(key.to_string())
+}
+
+/// Providers that can prefix the model of an alias, as in `openai/o3`
+pub const PROVIDER_NAMES: [&str; 5] = ["openai", "anthropic", "openrouter", "gemini", "ollama"];
+
+/// The provider, if the alias names one, and model that `name` stands for
+/// in `aliases`, or just `name` when it is not an alias
+fn resolve_model(aliases: &HashMap<String, String>, name: &str) -> (Option<String>, String) {
+    let Some(target) = aliases.get(name) else {
+        return (None, name.to_string());
+    };
+    match target.split_once('/') {
+        Some((provider, model)) if PROVIDER_NAMES.contains(&provider) => {
+            (Some(provider.to_string()), model.to_string())
+        }
+        // Other prefixes are part of the model, like OpenRouter's `meta-llama/...`
+        _ => (None, target.clone()),
+    }
+}
+
+/// User-wide config file, merged under the project's
+const GLOBAL_CONFIG_PATH: &str = "~/.config/cli_engineer/config.toml";
+
+/// Project config files in the current directory; the first found is used
+const PROJECT_CONFIG_PATHS: [&str; 2] = ["cli_engineer.toml", ".cli_engineer.toml"];
+
+/// Prefix of the environment variables overriding config fields, which name
+/// the field with `__` between keys: `CLI_ENGINEER__EXECUTION__MAX_ITERATIONS=3`
+pub const ENV_PREFIX: &str = "CLI_ENGINEER__";
+
+/// Set the fields named by the `CLI_ENGINEER__*` variables among `vars`,
+/// returning the names of the variables applied
+fn apply_env_overrides(
+    table: &mut toml::Table,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> Result<Vec<String>> {
+    let mut applied = Vec::new();
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let keys: Vec<String> = path.split("__").map(str::to_lowercase).collect();
+        if keys.iter().any(String::is_empty) {
+            anyhow::bail!("Invalid config override {}: empty key", name);
+        }
+        let (field, sections) = keys.split_last().expect("split yields a key");
+        let mut section = &mut *table;
+        for key in sections {
+            section = section
+                .entry(key.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                .as_table_mut()
+                .with_context(|| {
+                    format!("Invalid config override {}: `{}` is not a section", name, key)
+                })?;
+        }
+        // Text fields keep numbers and booleans as written, like a numeric token
+        let value = match parse_env_value(&raw) {
+            toml::Value::String(text) => toml::Value::String(text),
+            _ if config_validation::is_string_field(&keys) => toml::Value::String(raw),
+            value => value,
+        };
+        section.insert(field.clone(), value);
+        applied.push(name);
+    }
+    applied.sort();
+    Ok(applied)
+}
+
+/// A TOML value such as `3`, `true` or `["rs", "py"]`, or else the text as a
+/// string
+fn parse_env_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// Merge the `[profiles.<name>]` section of a config file into the rest of it
+fn apply_profile(table: &mut toml::Table, name: &str) -> Result<()> {
+    let profiles = table.get("profiles").and_then(toml::Value::as_table);
+    let Some(profile) = profiles
+        .and_then(|profiles| profiles.get(name))
+        .and_then(toml::Value::as_table)
+        .cloned()
+    else {
+        let available: Vec<&str> = profiles
+            .map(|profiles| profiles.keys().map(String::as_str).collect())
+            .unwrap_or_default();
+        anyhow::bail!(
+            "Unknown profile '{}' (available: {})",
+            name,
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        );
+    };
+    merge_tables(table, profile);
+    Ok(())
+}
+
+/// Merge `overlay` into `base`: tables key by key, anything else replaced
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overlay)) => {
+                merge_tables(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let path = std::env::temp_dir().join(format!("cli_engineer_{}.toml", uuid::Uuid::new_v4()));
+        fs::write(
+            &path,
+            r#"
+[ai_providers.anthropic]
+enabled = true
+model = "claude-sonnet-4-0"
+
+[ai_providers.ollama]
+enabled = false
+model = "qwen3:8b"
+
+[execution]
+max_iterations = 5
+
+[ui]
+[context]
+
+[profiles.cheap.ai_providers.anthropic]
+enabled = false
+
+[profiles.cheap.ai_providers.ollama]
+enabled = true
+
+[profiles.cheap.budget]
+max_cost = 0.5
+"#,
+        )
+        .unwrap();
+
+        let config = Config::from_file(&path, None).unwrap();
+        assert!(config.ai_providers.anthropic.unwrap().enabled);
+        assert_eq!(config.budget.max_cost, None);
+        assert!(config.profiles.contains_key("cheap"));
+
+        let cheap = Config::from_file(&path, Some("cheap")).unwrap();
+        let anthropic = cheap.ai_providers.anthropic.unwrap();
+        // Settings the profile leaves alone are kept
+        assert!(!anthropic.enabled && anthropic.model == "claude-sonnet-4-0");
+        assert!(cheap.ai_providers.ollama.unwrap().enabled);
+        assert_eq!(cheap.budget.max_cost, Some(0.5));
+        assert_eq!(cheap.execution.max_iterations, 5);
+
+        let error = Config::from_file(&path, Some("quality")).unwrap_err();
+        assert!(format!("{:#}", error).contains("Unknown profile 'quality' (available: cheap)"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_global_and_project_files() {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let global = dir.join("config.toml");
+        let project = dir.join("cli_engineer.toml");
+        fs::write(
+            &global,
+            r#"
+[ai_providers.anthropic]
+enabled = true
+model = "claude-sonnet-4-0"
+
+[execution]
+max_iterations = 5
+disable_auto_git = true
+
+[ui]
+[context]
+"#,
+        )
+        .unwrap();
+        fs::write(
+            &project,
+            r#"
+[execution]
+max_iterations = 3
+
+[scan]
+extensions = ["rs"]
+
+[budget]
+max_cost = 2.0
+"#,
+        )
+        .unwrap();
+
+        let config = Config::from_files(&[global.clone(), project.clone()], None).unwrap();
+        // Providers come from the global file, project settings override it
+        assert!(config.ai_providers.anthropic.as_ref().unwrap().enabled);
+        assert_eq!(config.execution.max_iterations, 3);
+        assert!(config.execution.disable_auto_git);
+        assert_eq!(config.scan.extensions, ["rs"]);
+        assert_eq!(config.budget.max_cost, Some(2.0));
+        assert_eq!(config.origin.files.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_api_key_sources() {
+        let env_var = "CLI_ENGINEER_TEST_UNSET_API_KEY";
+        let mut section = Config::default().ai_providers.openai.unwrap();
+        let error = resolve_api_key(env_var, Some(&section)).unwrap_err();
+        assert!(error.to_string().contains("no api_key or api_key_file"));
+
+        let path = std::env::temp_dir().join(format!("cli_engineer_key_{}", uuid::Uuid::new_v4()));
+        fs::write(&path, "sk-from-file\n").unwrap();
+        section.api_key_file = Some(path.to_string_lossy().to_string());
+        assert_eq!(resolve_api_key(env_var, Some(&section)).unwrap(), "sk-from-file");
+        section.api_key = Some("sk-inline".to_string());
+        assert_eq!(resolve_api_key(env_var, Some(&section)).unwrap(), "sk-inline");
+
+        fs::remove_file(&path).unwrap();
+        section.api_key = None;
+        let error = resolve_api_key(env_var, Some(&section)).unwrap_err();
+        assert!(error.to_string().starts_with("Failed to read API key file"));
+    }
+
+    #[test]
+    fn test_osv_is_opt_in() {
+        let security: SecurityConfig = toml::from_str("").unwrap();
+        assert!(!security.scanners.contains(&"osv".to_string()));
+        let security: SecurityConfig = toml::from_str("scanners = [\"osv\"]").unwrap();
+        assert_eq!(security.scanners, ["osv"]);
+    }
+
+    #[test]
+    fn test_iteration_budget() {
+        let mut budget = IterationBudgetConfig::default();
+        assert_eq!(budget.limit(&ComplexityLevel::Simple, 10), 10);
+        budget.adaptive = true;
+        assert_eq!(budget.limit(&ComplexityLevel::Simple, 10), 2);
+        assert_eq!(budget.limit(&ComplexityLevel::Medium, 10), 5);
+        assert_eq!(budget.limit(&ComplexityLevel::Medium, 3), 3);
+        assert_eq!(budget.limit(&ComplexityLevel::Complex, 10), 10);
+        budget.complex = Some(8);
+        assert_eq!(budget.limit(&ComplexityLevel::Complex, 10), 8);
+        budget.adaptive = false;
+        assert_eq!(budget.limit(&ComplexityLevel::Simple, 10), 10);
+    }
+
+    #[test]
+    fn test_iteration_budget_only_rises() {
+        let budget = IterationBudgetConfig {
+            adaptive: true,
+            ..IterationBudgetConfig::default()
+        };
+        let limit = budget.ratchet(None, &ComplexityLevel::Simple, 10);
+        assert_eq!(limit, Some(2));
+        let limit = budget.ratchet(limit, &ComplexityLevel::Medium, 10);
+        assert_eq!(limit, Some(5));
+        // A simpler plan, or one as complex, leaves the limit alone
+        assert_eq!(budget.ratchet(limit, &ComplexityLevel::Simple, 10), None);
+        assert_eq!(budget.ratchet(limit, &ComplexityLevel::Medium, 10), None);
+        assert_eq!(budget.ratchet(limit, &ComplexityLevel::Complex, 10), Some(10));
+    }
+
+    #[test]
+    fn test_iteration_budget_is_capped_by_max_iterations() {
+        let budget = IterationBudgetConfig {
+            adaptive: true,
+            complex: Some(20),
+            ..IterationBudgetConfig::default()
+        };
+        assert_eq!(budget.ratchet(None, &ComplexityLevel::Complex, 4), Some(4));
+        assert_eq!(budget.ratchet(Some(4), &ComplexityLevel::Complex, 4), None);
+        // Without scaling, the first plan sets max_iterations and nothing changes it
+        let fixed = IterationBudgetConfig::default();
+        assert_eq!(fixed.ratchet(None, &ComplexityLevel::Simple, 7), Some(7));
+        assert_eq!(fixed.ratchet(Some(7), &ComplexityLevel::Complex, 7), None);
+    }
+
+    #[test]
+    fn test_command_settings() {
+        let mut config = Config::default();
+        config.command.insert(
+            "security".to_string(),
+            CommandConfig {
+                model: Some("o3".to_string()),
+                max_iterations: Some(2),
+                scan: Some(ScanOverride {
+                    extra_extensions: vec!["nix".to_string()],
+                    max_depth: Some(4),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let docs = config.clone().for_command("docs");
+        assert_eq!(docs.execution.max_iterations, default_max_iterations());
+        let security = config.for_command("security");
+        assert_eq!(security.execution.max_iterations, 2);
+        assert_eq!(security.ai_providers.openai.unwrap().model, "o3");
+        // Disabled providers keep their model
+        assert_eq!(security.ai_providers.anthropic.unwrap().model, "claude-sonnet-4-0");
+        let scan = security.scan.for_command("security");
+        assert_eq!(scan.max_depth, 4);
+        assert!(["nix", "tf"].iter().all(|ext| scan.extensions.iter().any(|e| e == ext)));
+    }
+
+    #[test]
+    fn test_model_aliases() {
+        let mut table = toml::Value::try_from(Config::default()).unwrap();
+        let overlay: toml::Table = toml::from_str(
+            r#"
+[models]
+fast = "claude-haiku-4"
+strong = "openai/o3"
+cheap = "anthropic/claude-haiku-4"
+
+[ai_providers.anthropic]
+enabled = true
+model = "fast"
+
+[ai_providers.openai]
+model = "cheap"
+
+[review.second_opinion]
+provider = "anthropic"
+model = "strong"
+
+[command.review]
+model = "cheap"
+
+[profiles.quick.ai_providers.anthropic]
+model = "cheap"
+"#,
+        )
+        .unwrap();
+        merge_tables(table.as_table_mut().unwrap(), overlay);
+        let table = table.as_table().unwrap();
+
+        let config = Config::from_table(table.clone(), None, ConfigOrigin::default()).unwrap();
+        assert_eq!(config.ai_providers.anthropic.as_ref().unwrap().model, "claude-haiku-4");
+        // An alias for another provider's model is left for validation
+        assert_eq!(config.ai_providers.openai.as_ref().unwrap().model, "cheap");
+        assert!(format!("{:#}", config.validate(false).unwrap_err()).contains(
+            "`ai_providers.openai.model` is the alias `cheap` for `anthropic/claude-haiku-4`"
+        ));
+        let second_opinion = config.review.second_opinion.clone().unwrap();
+        assert_eq!(second_opinion.provider, "openai");
+        assert_eq!(second_opinion.model.as_deref(), Some("o3"));
+        let strong = config.model_choice("strong").unwrap();
+        assert_eq!((strong.provider.as_str(), strong.model.as_deref()), ("openai", Some("o3")));
+        assert_eq!(config.model_choice("gemini").unwrap().model, None);
+        assert!(config.model_choice("fast").is_err());
+
+        // An alias naming a provider runs the command on it alone
+        let review = config.for_command("review");
+        assert!(!review.ai_providers.openai.unwrap().enabled);
+        let anthropic = review.ai_providers.anthropic.unwrap();
+        assert!(anthropic.enabled && anthropic.model == "claude-haiku-4");
+
+        let quick = Config::from_table(table.clone(), Some("quick"), ConfigOrigin::default());
+        assert_eq!(quick.unwrap().ai_providers.anthropic.unwrap().model, "claude-haiku-4");
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let mut table = match toml::Value::try_from(Config::default()).unwrap() {
+            toml::Value::Table(table) => table,
+            _ => unreachable!(),
+        };
+        let vars = [
+            ("CLI_ENGINEER__EXECUTION__MAX_ITERATIONS", "3"),
+            ("CLI_ENGINEER__AI_PROVIDERS__OLLAMA__ENABLED", "true"),
+            ("CLI_ENGINEER__AI_PROVIDERS__OLLAMA__MODEL", "llama3.1:8b"),
+            ("CLI_ENGINEER__SCAN__EXTENSIONS", r#"["rs", "toml"]"#),
+            ("CLI_ENGINEER__BUDGET__MAX_COST", "2.5"),
+            ("CLI_ENGINEERING", "ignored"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let applied = apply_env_overrides(&mut table, vars).unwrap();
+        assert_eq!(applied.len(), 5);
+
+        let config: Config = toml::Value::Table(table.clone()).try_into().unwrap();
+        assert_eq!(config.execution.max_iterations, 3);
+        let ollama = config.ai_providers.ollama.unwrap();
+        assert!(ollama.enabled && ollama.model == "llama3.1:8b");
+        assert_eq!(config.scan.extensions, ["rs", "toml"]);
+        assert_eq!(config.budget.max_cost, Some(2.5));
+
+        let tokens = [
+            ("CLI_ENGINEER__SERVE__API_TOKEN", "12345678"),
+            ("CLI_ENGINEER__AI_PROVIDERS__OLLAMA__MODEL", "true"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        apply_env_overrides(&mut table, tokens).unwrap();
+        let config: Config = toml::Value::Table(table.clone()).try_into().unwrap();
+        assert_eq!(config.serve.api_token.as_deref(), Some("12345678"));
+        assert_eq!(config.ai_providers.ollama.unwrap().model, "true");
+
+        let invalid = ("CLI_ENGINEER__EXECUTION__MAX_ITERATIONS__X", "1");
+        let invalid = [(invalid.0.to_string(), invalid.1.to_string())];
+        assert!(apply_env_overrides(&mut table, invalid).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::*;
+
+use crate::CommandKind;
+use crate::config::{Config, ProviderConfig, resolve_api_key};
+use crate::config_validation::{self, ConfigOrigin};
+
+const USAGE: &str = "Usage: cli_engineer config -- doctor";
+
+/// How long to wait for a provider to answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Settings that are read but change nothing
+const IGNORED_SETTINGS: [&str; 6] = [
+    "execution.parallel_enabled",
+    "ui.output_format",
+    "ui.metrics",
+    "ai_providers.openai.max_tokens",
+    "ai_providers.anthropic.max_tokens",
+    "ai_providers.gemini.max_tokens",
+];
+
+/// Where Ollama is reached, whatever `ai_providers.ollama.base_url` says
+const OLLAMA_URL: &str = "http://localhost:11434";
+
+/// The `config` command: `doctor` shows the effective configuration and what
+/// may be wrong with it
+pub async fn run(config: &Config, args: &[String]) -> Result<()> {
+    match args {
+        [action] if action == "doctor" => doctor(config).await,
+        _ => anyhow::bail!(USAGE),
+    }
+}
+
+async fn doctor(config: &Config) -> Result<()> {
+    let origin = &config.origin;
+    heading("Sources");
+    if origin.files.is_empty() {
+        println!("  No config file; the defaults are used");
+    }
+    for (path, _) in &origin.files {
+        println!("  {}", path.display());
+    }
+    if let Some(profile) = &origin.profile {
+        println!("  Profile {}", profile.bright_white());
+    }
+    for name in &origin.overrides {
+        println!("  {}", name);
+    }
+
+    heading("Effective configuration");
+    let mut settings = Vec::new();
+    if let toml::Value::Table(table) = toml::Value::try_from(config)? {
+        for (key, value) in table {
+            // Profiles are shown applied, under their sections
+            if key != "profiles" {
+                flatten(value, &mut vec![key], &mut settings);
+            }
+        }
+    }
+    for (path, value) in &settings {
+        let source = origin.locate(path);
+        let source = source.as_deref().unwrap_or("default");
+        println!("  {} = {}  {}", path.join("."), value, source.dimmed());
+    }
+
+    heading("Providers");
+    let client = reqwest::Client::builder().timeout(PROBE_TIMEOUT).build()?;
+    let providers = &config.ai_providers;
+    let sections = [
+        ("openai", providers.openai.as_ref(), "OPENAI_API_KEY"),
+        (
+            "anthropic",
+            providers.anthropic.as_ref(),
+            "ANTHROPIC_API_KEY",
+        ),
+        (
+            "openrouter",
+            providers.openrouter.as_ref(),
+            "OPENROUTER_API_KEY",
+        ),
+        ("gemini", providers.gemini.as_ref(), "GEMINI_API_KEY"),
+    ];
+    if sections.iter().all(|(_, section, _)| section.is_none()) && providers.ollama.is_none() {
+        println!("  None configured");
+    }
+    let mut enabled = Vec::new();
+    for (name, section, env_var) in sections {
+        let Some(section) = section else {
+            continue;
+        };
+        if !section.enabled {
+            println!("  {:<11} {}", name, "disabled".dimmed());
+            continue;
+        }
+        let (found, status) = match resolve_api_key(env_var, Some(section)) {
+            Ok(key) => (true, probe(&client, name, &key).await),
+            // The problems below say why
+            Err(_) => (false, "not checked".dimmed()),
+        };
+        let source = key_source(env_var, section, found);
+        println!(
+            "  {:<11} {}  {}  {}",
+            name,
+            section.model.bright_white(),
+            source,
+            status
+        );
+        if found {
+            enabled.push(name);
+        }
+    }
+    if let Some(ollama) = &providers.ollama {
+        if ollama.enabled {
+            let status = probe(&client, "ollama", "").await;
+            println!(
+                "  {:<11} {}  {}",
+                "ollama",
+                ollama.model.bright_white(),
+                status
+            );
+            enabled.push("ollama");
+        } else {
+            println!("  {:<11} {}", "ollama", "disabled".dimmed());
+        }
+    }
+
+    heading("Warnings");
+    // Where each warning comes from, if known, and what it is
+    let mut warnings: Vec<(Option<String>, String)> = Vec::new();
+    match enabled.as_slice() {
+        [] => warnings.push((
+            None,
+            "No provider can be used; runs fall back to a local stand-in".to_string(),
+        )),
+        [_] => {}
+        [first, ..] => warnings.push((
+            None,
+            format!(
+                "Several providers are enabled; only {} is used (openrouter, gemini, openai, anthropic, then ollama)",
+                first
+            ),
+        )),
+    }
+    for setting in IGNORED_SETTINGS {
+        if let Some(source) = explicit_source(origin, setting) {
+            warnings.push((source, format!("`{}` has no effect", setting)));
+        }
+    }
+    let ollama_url = providers
+        .ollama
+        .as_ref()
+        .and_then(|ollama| ollama.base_url.as_deref());
+    if let Some(source) = explicit_source(origin, "ai_providers.ollama.base_url")
+        && ollama_url.is_some_and(|url| url.trim_end_matches('/') != OLLAMA_URL)
+    {
+        warnings.push((
+            source,
+            format!(
+                "`ai_providers.ollama.base_url` has no effect; Ollama is reached at {}",
+                OLLAMA_URL
+            ),
+        ));
+    }
+    let commands = config.command.keys().map(|name| ("command", name));
+    let scans = config
+        .scan
+        .commands
+        .keys()
+        .map(|name| ("scan.commands", name));
+    let configurable = configurable_commands();
+    for (section, name) in commands.chain(scans) {
+        if !configurable.contains(name) {
+            let path = format!("{}.{}", section, name);
+            warnings.push((
+                explicit_source(origin, &path).flatten(),
+                format!(
+                    "`[{}]` is not a command; expected one of {}",
+                    path,
+                    configurable.join(", ")
+                ),
+            ));
+        }
+    }
+    if warnings.is_empty() {
+        println!("  {}", "None".green());
+    }
+    for (source, warning) in &warnings {
+        match source {
+            Some(source) => println!("  {} {}: {}", "!".yellow(), source, warning),
+            None => println!("  {} {}", "!".yellow(), warning),
+        }
+    }
+
+    heading("Problems");
+    let mut problems = config_validation::check_keys(origin);
+    problems.extend(config_validation::check_settings(config, true));
+    if problems.is_empty() {
+        println!("  {}", "None".green());
+        return Ok(());
+    }
+    for problem in &problems {
+        match &problem.location {
+            Some(location) => println!("  {} {}: {}", "✗".red(), location, problem.message),
+            None => println!("  {} {}", "✗".red(), problem.message),
+        }
+    }
+    anyhow::bail!(
+        "The configuration has {} problem{}",
+        problems.len(),
+        if problems.len() == 1 { "" } else { "s" }
+    )
+}
+
+fn heading(title: &str) {
+    println!("\n{}", title.bright_white().bold());
+}
+
+/// Leaf settings of `value` with their key paths; API keys and tokens are masked
+fn flatten(value: toml::Value, path: &mut Vec<String>, settings: &mut Vec<(Vec<String>, String)>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                path.push(key);
+                flatten(value, path, settings);
+                path.pop();
+            }
+        }
+        _ if path
+            .last()
+            .is_some_and(|key| key == "api_key" || key.ends_with("_token")) =>
+        {
+            settings.push((path.clone(), "\"********\"".to_string()))
+        }
+        // Settings are f32, which would show as 0.20000000298023224
+        toml::Value::Float(float) if float.fract() != 0.0 => {
+            settings.push((path.clone(), (float as f32).to_string()))
+        }
+        value => settings.push((path.clone(), value.to_string())),
+    }
+}
+
+/// Whether `path` was set rather than left at its default, and if so where,
+/// when that is known
+fn explicit_source(origin: &ConfigOrigin, path: &str) -> Option<Option<String>> {
+    let keys: Vec<String> = path.split('.').map(str::to_string).collect();
+    let mut value = origin.table.get(&keys[0])?;
+    for key in &keys[1..] {
+        value = value.get(key)?;
+    }
+    Some(origin.locate(&keys))
+}
+
+/// Where the API key of an enabled provider comes from
+fn key_source(env_var: &str, section: &ProviderConfig, found: bool) -> ColoredString {
+    if !found {
+        "no key".red()
+    } else if std::env::var(env_var).is_ok_and(|key| !key.is_empty()) {
+        format!("key from {}", env_var).normal()
+    } else if section.api_key.is_some() {
+        "key from api_key".normal()
+    } else {
+        "key from api_key_file".normal()
+    }
+}
+
+/// Whether the provider's API answers and accepts `key`
+async fn probe(client: &reqwest::Client, provider: &str, key: &str) -> ColoredString {
+    let request = match provider {
+        "openai" => client
+            .get("https://api.openai.com/v1/models")
+            .bearer_auth(key),
+        "anthropic" => client
+            .get("https://api.anthropic.com/v1/models")
+            .header("x-api-key", key)
+            .header("anthropic-version", "2023-06-01"),
+        "openrouter" => client
+            .get("https://openrouter.ai/api/v1/key")
+            .bearer_auth(key),
+        "gemini" => client.get(format!(
+            "https://generativelanguage.googleapis.com/v1beta/models?key={}",
+            key
+        )),
+        _ => client.get(format!("{}/api/tags", OLLAMA_URL)),
+    };
+    match request.send().await {
+        Ok(response) if response.status().is_success() => "reachable".green(),
+        Ok(response) if matches!(response.status().as_u16(), 401 | 403) => {
+            format!("key rejected (HTTP {})", response.status().as_u16()).red()
+        }
+        Ok(response) => format!("HTTP {}", response.status().as_u16()).yellow(),
+        Err(e) if e.is_timeout() => "no answer".red(),
+        Err(_) => "unreachable".red(),
+    }
+}
+
+/// Commands that `[command.<name>]` and `[scan.commands.<name>]` can configure
+fn configurable_commands() -> Vec<String> {
+    CommandKind::value_variants()
+        .iter()
+        .filter(|command| command.calls_model())
+        .map(CommandKind::name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doctor_settings() {
+        let mut config = Config::default();
+        config.ai_providers.openai.as_mut().unwrap().api_key = Some("sk-secret".to_string());
+        let mut settings = Vec::new();
+        flatten(
+            toml::Value::try_from(&config).unwrap(),
+            &mut Vec::new(),
+            &mut settings,
+        );
+        let setting = |path: &str| {
+            settings
+                .iter()
+                .find(|(keys, _)| keys.join(".") == path)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(setting("execution.max_iterations"), Some("10"));
+        assert_eq!(setting("ai_providers.openai.api_key"), Some("\"********\""));
+        assert!(
+            !settings
+                .iter()
+                .any(|(_, value)| value.contains("sk-secret"))
+        );
+
+        let table: toml::Table = toml::from_str("[execution]\nparallel_enabled = true\n").unwrap();
+        config.origin = ConfigOrigin {
+            table,
+            ..Default::default()
+        };
+        assert_eq!(
+            explicit_source(&config.origin, "execution.parallel_enabled"),
+            Some(None)
+        );
+        assert_eq!(explicit_source(&config.origin, "ui.metrics"), None);
+    }
+
+    #[test]
+    fn test_commands_that_call_a_model_are_configurable() {
+        let configurable = configurable_commands();
+        for name in [
+            "code",
+            "sbom",
+            "changelog",
+            "coverage-gaps",
+            "bench-providers",
+        ] {
+            assert!(configurable.iter().any(|c| c == name), "{}", name);
+        }
+        for name in ["apply", "config", "replay"] {
+            assert!(!configurable.iter().any(|c| c == name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,852 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+use serde::Deserialize;
+use serde::de::value::{Error, StrDeserializer};
+use serde::de::{
+    self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+};
+
+use crate::config::{Config, ENV_PREFIX, PROVIDER_NAMES, resolve_api_key};
+use crate::theme::Theme;
+
+/// Where the configuration came from, to point problems at the line or
+/// variable that caused them
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOrigin {
+    /// Config files loaded and their contents, each overriding those before it
+    pub files: Vec<(PathBuf, String)>,
+    /// Keys as merged from the file, profile and environment
+    pub table: toml::Table,
+    pub profile: Option<String>,
+    /// `CLI_ENGINEER__*` variables applied
+    pub overrides: Vec<String>,
+}
+
+/// Something wrong with the configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// Line or environment variable the problem comes from, if known
+    pub location: Option<String>,
+    pub message: String,
+}
+
+/// Every problem found in a configuration, reported together
+#[derive(Debug)]
+pub struct ConfigErrors(pub Vec<Problem>);
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.0.len();
+        write!(
+            f,
+            "Invalid configuration ({} problem{}):",
+            count,
+            if count == 1 { "" } else { "s" }
+        )?;
+        for problem in &self.0 {
+            match &problem.location {
+                Some(location) => write!(f, "\n  {}: {}", location, problem.message)?,
+                None => write!(f, "\n  {}", problem.message)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// Problems of the keys in `origin.table` alone: keys no setting has, with the
+/// closest known key as a suggestion
+pub fn check_keys(origin: &ConfigOrigin) -> Vec<Problem> {
+    let schema = Schema::of::<Config>();
+    let mut unknown = Vec::new();
+    let table = toml::Value::Table(origin.table.clone());
+    schema.unknown_keys(&table, &mut Vec::new(), &mut unknown);
+    // Profiles are applied over the whole configuration, so they take its keys
+    if let Some(profiles) = origin.table.get("profiles").and_then(toml::Value::as_table) {
+        for (name, profile) in profiles {
+            let mut path = vec!["profiles".to_string(), name.clone()];
+            schema.unknown_keys(profile, &mut path, &mut unknown);
+        }
+    }
+
+    let mut problems: Vec<Problem> = unknown
+        .into_iter()
+        .map(|(path, suggestion)| {
+            let mut message = format!("unknown key `{}`", path.join("."));
+            if let Some(suggestion) = suggestion {
+                message.push_str(&format!("; did you mean `{}`?", suggestion));
+            }
+            Problem {
+                location: origin.locate(&path),
+                message,
+            }
+        })
+        .collect();
+    // In the order of the files
+    problems.sort_by_key(|problem| {
+        let (file, line) = problem.location.as_deref()?.rsplit_once(':')?;
+        Some((file.to_string(), line.parse::<usize>().ok()?))
+    });
+    problems
+}
+
+/// Whether the config field at `path`, such as `["serve", "api_token"]`, holds
+/// a string
+pub fn is_string_field(path: &[String]) -> bool {
+    Schema::of::<Config>().is_string(path)
+}
+
+/// Problems of the settings: values out of range, options that contradict
+/// each other and, with `require_api_keys`, enabled providers without a key
+pub fn check_settings(config: &Config, require_api_keys: bool) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut problem = |path: &str, message: String| {
+        let keys: Vec<String> = path.split('.').map(str::to_string).collect();
+        problems.push(Problem {
+            location: config.origin.locate(&keys),
+            message,
+        });
+    };
+
+    let providers = &config.ai_providers;
+    let sections = [
+        (
+            "openai",
+            providers.openai.as_ref(),
+            Some("OPENAI_API_KEY"),
+            2.0,
+        ),
+        (
+            "anthropic",
+            providers.anthropic.as_ref(),
+            Some("ANTHROPIC_API_KEY"),
+            1.0,
+        ),
+        (
+            "openrouter",
+            providers.openrouter.as_ref(),
+            Some("OPENROUTER_API_KEY"),
+            2.0,
+        ),
+        (
+            "gemini",
+            providers.gemini.as_ref(),
+            Some("GEMINI_API_KEY"),
+            2.0,
+        ),
+    ];
+    let ollama = providers
+        .ollama
+        .as_ref()
+        .map(|ollama| (ollama.enabled, ollama.temperature, &ollama.model));
+    let enabled = sections
+        .iter()
+        .map(|(name, section, key, max)| {
+            (
+                *name,
+                section.map(|s| (s.enabled, s.temperature, &s.model)),
+                key.map(|env_var| {
+                    let from_file = section.is_some_and(|s| s.api_key_file.is_some());
+                    (from_file, resolve_api_key(env_var, *section))
+                }),
+                *max,
+            )
+        })
+        .chain([("ollama", ollama, None, 2.0)]);
+    for (name, section, api_key, max_temperature) in enabled {
+        let Some((enabled, temperature, model)) = section else {
+            continue;
+        };
+        // Aliases left unresolved are those for another provider's model
+        if let Some(target) = config.models.get(model) {
+            problem(
+                &format!("ai_providers.{}.model", name),
+                format!(
+                    "`ai_providers.{}.model` is the alias `{}` for `{}`, a model of another provider",
+                    name, model, target
+                ),
+            );
+        }
+        if let Some(temperature) = temperature
+            && !(0.0..=max_temperature).contains(&temperature)
+        {
+            problem(
+                &format!("ai_providers.{}.temperature", name),
+                format!(
+                    "`ai_providers.{}.temperature` is {}, outside 0.0 to {:.1}",
+                    name, temperature, max_temperature
+                ),
+            );
+        }
+        if let Some((from_file, Err(e))) = api_key
+            && enabled
+            && require_api_keys
+        {
+            let key = if from_file { "api_key_file" } else { "enabled" };
+            problem(
+                &format!("ai_providers.{}.{}", name, key),
+                format!(
+                    "`ai_providers.{}` is enabled but has no API key: {:#}",
+                    name, e
+                ),
+            );
+        }
+    }
+    let sampling = config.execution.sampling.providers.iter().enumerate();
+    let extra_providers = config
+        .review
+        .second_opinion
+        .iter()
+        .map(|second_opinion| ("review.second_opinion".to_string(), second_opinion))
+        .chain(sampling.map(|(index, provider)| {
+            (format!("execution.sampling.providers.{}", index), provider)
+        }));
+    for (section, second_opinion) in extra_providers {
+        let known = PROVIDER_NAMES;
+        let provider = second_opinion.provider.to_lowercase();
+        if !known.contains(&provider.as_str()) {
+            problem(
+                &format!("{}.provider", section),
+                format!(
+                    "unknown provider `{}` in `{}`; expected one of {}",
+                    second_opinion.provider,
+                    section,
+                    known.join(", ")
+                ),
+            );
+        }
+    }
+
+    if config.execution.max_iterations == 0 {
+        problem(
+            "execution.max_iterations",
+            "`execution.max_iterations` must be at least 1".to_string(),
+        );
+    }
+    for (role, agent) in config.agents.roles() {
+        let Some(provider) = &agent.provider else {
+            continue;
+        };
+        if !PROVIDER_NAMES.contains(&provider.to_lowercase().as_str()) {
+            problem(
+                &format!("agents.{}.provider", role),
+                format!(
+                    "unknown provider `{}` in `agents.{}`; expected one of {}",
+                    provider,
+                    role,
+                    PROVIDER_NAMES.join(", ")
+                ),
+            );
+        }
+    }
+
+    for (module, level) in &config.logging.levels {
+        if level.parse::<log::LevelFilter>().is_err() {
+            problem(
+                &format!("logging.levels.{}", module),
+                format!(
+                    "`logging.levels.{}` must be off, error, warn, info, debug or trace, not `{}`",
+                    module, level
+                ),
+            );
+        }
+    }
+
+    let budget = &config.execution.iteration_budget;
+    let budgets = [
+        ("simple", Some(budget.simple)),
+        ("medium", Some(budget.medium)),
+        ("complex", budget.complex),
+    ];
+    for (level, limit) in budgets {
+        if limit == Some(0) {
+            problem(
+                &format!("execution.iteration_budget.{}", level),
+                format!("`execution.iteration_budget.{}` must be at least 1", level),
+            );
+        }
+    }
+    if config.execution.timeout_secs == Some(0) {
+        problem(
+            "execution.timeout_secs",
+            "`execution.timeout_secs` must be at least 1; leave it out for no timeout".to_string(),
+        );
+    }
+    if config.execution.sampling.candidates == 0 {
+        problem(
+            "execution.sampling.candidates",
+            "`execution.sampling.candidates` must be at least 1".to_string(),
+        );
+    }
+    if config.execution.tools.max_turns == 0 {
+        problem(
+            "execution.tools.max_turns",
+            "`execution.tools.max_turns` must be at least 1".to_string(),
+        );
+    }
+    let context = &config.context;
+    if !(context.compression_threshold > 0.0 && context.compression_threshold <= 1.0) {
+        problem(
+            "context.compression_threshold",
+            format!(
+                "`context.compression_threshold` is {}, outside 0.0 to 1.0",
+                context.compression_threshold
+            ),
+        );
+    }
+    let budgets = [
+        ("codebase", context.budgets.codebase),
+        ("conversation", context.budgets.conversation),
+        ("plan_review", context.budgets.plan_review),
+    ];
+    for (name, share) in budgets {
+        if !(0.0..=1.0).contains(&share) {
+            problem(
+                &format!("context.budgets.{}", name),
+                format!(
+                    "`context.budgets.{}` is {}, outside 0.0 to 1.0",
+                    name, share
+                ),
+            );
+        }
+    }
+    let total: f32 = budgets.iter().map(|(_, share)| share).sum();
+    // Leave room for rounding of the shares
+    if total > 1.001 {
+        problem(
+            "context.budgets",
+            format!(
+                "`context.budgets` add up to {:.2}, more than the whole context window",
+                total
+            ),
+        );
+    }
+
+    for severity in &config.review.block_on {
+        if !["critical", "major", "minor", "info"].contains(&severity.to_lowercase().as_str()) {
+            problem(
+                "review.block_on",
+                format!(
+                    "unknown severity `{}` in `review.block_on`; expected critical, major, minor or info",
+                    severity
+                ),
+            );
+        }
+    }
+    for scanner in &config.security.scanners {
+        if !["cargo-audit", "npm-audit", "osv", "semgrep"].contains(&scanner.as_str()) {
+            problem(
+                "security.scanners",
+                format!(
+                    "unknown scanner `{}` in `security.scanners`; expected cargo-audit, npm-audit, osv or semgrep",
+                    scanner
+                ),
+            );
+        }
+    }
+    if config.budget.max_cost.is_some_and(|cost| cost <= 0.0) {
+        problem(
+            "budget.max_cost",
+            "`budget.max_cost` must be more than 0".to_string(),
+        );
+    }
+    for threshold in &config.budget.alert_thresholds {
+        if !(1..=100).contains(threshold) {
+            problem(
+                "budget.alert_thresholds",
+                format!(
+                    "`budget.alert_thresholds` has {}; thresholds are percentages from 1 to 100",
+                    threshold
+                ),
+            );
+        }
+    }
+    if config.ipc.require_approval && !config.ipc.enabled {
+        problem(
+            "ipc.require_approval",
+            "`ipc.require_approval` needs `ipc.enabled`, or plans could never be approved"
+                .to_string(),
+        );
+    }
+    if config.serve.max_queued_jobs == 0 {
+        problem(
+            "serve.max_queued_jobs",
+            "`serve.max_queued_jobs` must be at least 1, or no job could be submitted".to_string(),
+        );
+    }
+    if config.git.pull_request && !config.git.enabled {
+        problem(
+            "git.pull_request",
+            "`git.pull_request` needs `git.enabled`, or there is no branch to open it from"
+                .to_string(),
+        );
+    }
+    if config.git.pull_request && !writes_to_project(&config.execution.artifact_dir) {
+        problem(
+            "git.pull_request",
+            format!(
+                "`git.pull_request` needs `execution.artifact_dir = \".\"`; artifacts written to \
+`{}` are never committed, so the pull request would have no changes",
+                config.execution.artifact_dir
+            ),
+        );
+    }
+    for (name, server) in &config.lsp.servers {
+        if server.command.is_some() == server.address.is_some() {
+            problem(
+                &format!("lsp.servers.{}", name),
+                format!(
+                    "language server `{}` needs either a `command` or an `address`",
+                    name
+                ),
+            );
+        }
+    }
+    if config.execution.isolated_execution && config.execution.sandbox.image.trim().is_empty() {
+        problem(
+            "execution.sandbox.image",
+            "`execution.isolated_execution` needs an `execution.sandbox.image` with the \
+project's tools"
+                .to_string(),
+        );
+    }
+    if let Err(e) = Theme::from_config(&config.ui) {
+        problem("ui.theme", e.to_string());
+    }
+    problems
+}
+
+impl ConfigOrigin {
+    /// `file:line` of the key at `path`, or the variable that set it
+    pub fn locate(&self, path: &[String]) -> Option<String> {
+        let mut candidates = vec![path.to_vec()];
+        if let Some(profile) = &self.profile {
+            let mut in_profile = vec!["profiles".to_string(), profile.clone()];
+            in_profile.extend(path.iter().cloned());
+            candidates.insert(0, in_profile);
+        }
+        let variable = format!("{}{}", ENV_PREFIX, path.join("__").to_uppercase());
+        if std::env::var_os(&variable).is_some() {
+            return Some(variable);
+        }
+
+        let documents: Vec<_> = self
+            .files
+            .iter()
+            .rev()
+            .filter_map(|(file, contents)| {
+                let document = toml_edit::ImDocument::parse(contents.as_str()).ok()?;
+                Some((file, contents, document))
+            })
+            .collect();
+        candidates.iter().find_map(|keys| {
+            documents.iter().find_map(|(file, contents, document)| {
+                let offset = key_offset(document.as_table(), keys)?;
+                let line = contents[..offset].matches('\n').count() + 1;
+                Some(format!("{}:{}", file.display(), line))
+            })
+        })
+    }
+}
+
+/// Byte offset in the parsed document of the key at `path`, or of the array
+/// holding the element when `path` ends at an index
+fn key_offset(table: &dyn toml_edit::TableLike, path: &[String]) -> Option<usize> {
+    let (key, rest) = path.split_first()?;
+    let (found, item) = table.get_key_value(key)?;
+    let offset = found.span().map(|span| span.start);
+    let Some((next, after)) = rest.split_first() else {
+        return offset;
+    };
+    let Ok(index) = next.parse::<usize>() else {
+        return key_offset(item.as_table_like()?, rest);
+    };
+    let element: &dyn toml_edit::TableLike = match item {
+        toml_edit::Item::ArrayOfTables(tables) => tables.get(index)?,
+        toml_edit::Item::Value(toml_edit::Value::Array(array)) => {
+            array.get(index)?.as_inline_table()?
+        }
+        _ => return None,
+    };
+    if after.is_empty() {
+        offset
+    } else {
+        key_offset(element, after)
+    }
+}
+
+/// Whether artifacts written to `artifact_dir` land in the project itself
+fn writes_to_project(artifact_dir: &str) -> bool {
+    let path = Path::new(artifact_dir);
+    if path.is_absolute() {
+        let canonical = |path: &Path| path.canonicalize().ok();
+        return std::env::current_dir().ok().as_deref().and_then(canonical) == canonical(path);
+    }
+    path.components().all(|c| c == Component::CurDir)
+}
+
+/// Keys a type accepts, learned by deserializing it from a `Probe`
+#[derive(Debug, Clone, Default)]
+enum Schema {
+    /// A value without keys, or one taking any keys
+    #[default]
+    Any,
+    /// A string
+    Str,
+    /// A struct and the schema of each of its fields
+    Struct(BTreeMap<&'static str, Schema>),
+    /// A map with arbitrary keys and values of one schema
+    Map(Box<Schema>),
+    /// An array with elements of one schema
+    Seq(Box<Schema>),
+}
+
+impl Schema {
+    fn of<'de, T: Deserialize<'de>>() -> Schema {
+        let slot = Slot::default();
+        // Probing fails on types it cannot satisfy; what was learned until then stands
+        let _ = T::deserialize(Probe(slot.clone()));
+        slot.take()
+    }
+
+    /// Whether the value at `path` below this schema is a string
+    fn is_string(&self, path: &[String]) -> bool {
+        match (self, path.split_first()) {
+            (Schema::Str, None) => true,
+            (Schema::Struct(fields), Some((key, rest))) => {
+                fields.get(key.as_str()).is_some_and(|field| field.is_string(rest))
+            }
+            (Schema::Map(values), Some((_, rest))) => values.is_string(rest),
+            _ => false,
+        }
+    }
+
+    /// Collect the keys of `value` this schema does not have, with the known
+    /// key closest to each
+    fn unknown_keys(
+        &self,
+        value: &toml::Value,
+        path: &mut Vec<String>,
+        found: &mut Vec<(Vec<String>, Option<&'static str>)>,
+    ) {
+        match (self, value) {
+            (Schema::Struct(fields), toml::Value::Table(table)) => {
+                for (key, value) in table {
+                    path.push(key.clone());
+                    match fields.get(key.as_str()) {
+                        Some(field) => field.unknown_keys(value, path, found),
+                        None => found.push((path.clone(), closest(key, fields.keys().copied()))),
+                    }
+                    path.pop();
+                }
+            }
+            (Schema::Map(values), toml::Value::Table(table)) => {
+                for (key, value) in table {
+                    path.push(key.clone());
+                    values.unknown_keys(value, path, found);
+                    path.pop();
+                }
+            }
+            (Schema::Seq(element), toml::Value::Array(items)) => {
+                for (index, item) in items.iter().enumerate() {
+                    path.push(index.to_string());
+                    element.unknown_keys(item, path, found);
+                    path.pop();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The known key most like `key`, if any is close enough to be a typo of it
+fn closest(key: &str, known: impl Iterator<Item = &'static str>) -> Option<&'static str> {
+    known
+        .map(|candidate| {
+            (
+                strsim::normalized_damerau_levenshtein(key, candidate),
+                candidate,
+            )
+        })
+        .filter(|(similarity, _)| *similarity >= 0.6)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, candidate)| candidate)
+}
+
+type Slot = Rc<RefCell<Schema>>;
+
+/// Deserializer that hands every type a placeholder value of the shape it
+/// asks for, recording the fields of structs along the way
+struct Probe(Slot);
+
+macro_rules! probe_leaves {
+    ($($method:ident => $visit:ident($($value:expr)?)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                visitor.$visit($($value)?)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for Probe {
+    type Error = Error;
+
+    probe_leaves! {
+        deserialize_any => visit_str(""),
+        deserialize_bool => visit_bool(false),
+        deserialize_i8 => visit_i8(0),
+        deserialize_i16 => visit_i16(0),
+        deserialize_i32 => visit_i32(0),
+        deserialize_i64 => visit_i64(0),
+        deserialize_u8 => visit_u8(0),
+        deserialize_u16 => visit_u16(0),
+        deserialize_u32 => visit_u32(0),
+        deserialize_u64 => visit_u64(0),
+        deserialize_f32 => visit_f32(0.0),
+        deserialize_f64 => visit_f64(0.0),
+        deserialize_char => visit_char(' '),
+        deserialize_bytes => visit_bytes(&[]),
+        deserialize_byte_buf => visit_byte_buf(Vec::new()),
+        deserialize_unit => visit_unit(),
+        deserialize_identifier => visit_str(""),
+        deserialize_ignored_any => visit_unit(),
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.0.borrow_mut() = Schema::Str;
+        visitor.visit_str("")
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        *self.0.borrow_mut() = Schema::Str;
+        visitor.visit_string(String::new())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let element = Slot::default();
+        let result = visitor.visit_seq(ProbeSeq(Some(element.clone())));
+        *self.0.borrow_mut() = Schema::Seq(Box::new(element.take()));
+        result
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = Slot::default();
+        let result = visitor.visit_map(ProbeMap {
+            entries: vec![("", value.clone())].into_iter(),
+            pending: None,
+        });
+        *self.0.borrow_mut() = Schema::Map(Box::new(value.take()));
+        result
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let slots: Vec<(&'static str, Slot)> = fields
+            .iter()
+            .map(|field| (*field, Slot::default()))
+            .collect();
+        let result = visitor.visit_map(ProbeMap {
+            entries: slots.clone().into_iter(),
+            pending: None,
+        });
+        let fields = slots
+            .into_iter()
+            .map(|(field, slot)| (field, slot.take()))
+            .collect();
+        *self.0.borrow_mut() = Schema::Struct(fields);
+        result
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        let variant: StrDeserializer<'_, Error> =
+            variants.first().copied().unwrap_or("").into_deserializer();
+        visitor.visit_enum(variant)
+    }
+}
+
+/// Entries of a probed struct or map: each key with a probe for its value
+struct ProbeMap {
+    entries: std::vec::IntoIter<(&'static str, Slot)>,
+    pending: Option<Slot>,
+}
+
+impl<'de> MapAccess<'de> for ProbeMap {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let Some((key, slot)) = self.entries.next() else {
+            return Ok(None);
+        };
+        self.pending = Some(slot);
+        let key: StrDeserializer<'_, Error> = key.into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let slot = self
+            .pending
+            .take()
+            .ok_or_else(|| de::Error::custom("value without a key"))?;
+        seed.deserialize(Probe(slot))
+    }
+}
+
+/// A probed sequence of one element
+struct ProbeSeq(Option<Slot>);
+
+impl<'de> SeqAccess<'de> for ProbeSeq {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.0.take() {
+            Some(slot) => seed.deserialize(Probe(slot)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_problems() {
+        let contents = r#"
+[ai_providers.anthropic]
+enabled = true
+model = "claude-sonnet-4-0"
+temprature = 0.5
+
+[execution]
+max_iteratons = 3
+
+[ui]
+[context]
+compression_threshold = 1.5
+
+[[notifications.webhooks]]
+url = "https://example.com/hook"
+formt = "slack"
+
+[profiles.cheap.budget]
+max_cots = 1.0
+"#;
+        let table: toml::Table = toml::from_str(contents).unwrap();
+        let origin = ConfigOrigin {
+            files: vec![(PathBuf::from("cli_engineer.toml"), contents.to_string())],
+            table: table.clone(),
+            ..Default::default()
+        };
+        let problems = check_keys(&origin);
+        let messages: Vec<String> = problems
+            .iter()
+            .map(|p| format!("{}: {}", p.location.as_deref().unwrap_or("-"), p.message))
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "cli_engineer.toml:5: unknown key `ai_providers.anthropic.temprature`; \
+                 did you mean `temperature`?",
+                "cli_engineer.toml:8: unknown key `execution.max_iteratons`; \
+                 did you mean `max_iterations`?",
+                "cli_engineer.toml:16: unknown key `notifications.webhooks.0.formt`; \
+                 did you mean `format`?",
+                "cli_engineer.toml:19: unknown key `profiles.cheap.budget.max_cots`; \
+                 did you mean `max_cost`?",
+            ]
+        );
+
+        let mut config: Config = toml::Value::Table(table).try_into().unwrap();
+        config.origin = origin;
+        config.ai_providers.anthropic.as_mut().unwrap().temperature = Some(1.5);
+        config.review.block_on.push("blocker".to_string());
+        let problems = check_settings(&config, false);
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert!(
+            problems[0]
+                .message
+                .contains("temperature` is 1.5, outside 0.0 to 1.0")
+        );
+        assert_eq!(
+            problems[1].location.as_deref(),
+            Some("cli_engineer.toml:12")
+        );
+        assert!(problems[2].message.contains("unknown severity `blocker`"));
+        let report = ConfigErrors(problems).to_string();
+        assert!(report.starts_with("Invalid configuration (3 problems):\n  "));
+    }
+
+    #[test]
+    fn test_pull_requests_need_artifacts_in_the_project() {
+        let mut config = Config::default();
+        config.git.enabled = true;
+        config.git.pull_request = true;
+        assert_eq!(config.execution.artifact_dir, "./artifacts");
+        let problems = check_settings(&config, false);
+        assert_eq!(problems.len(), 1, "{:?}", problems);
+        assert!(problems[0].message.contains("execution.artifact_dir = \".\""));
+
+        for dir in [".", "./"] {
+            config.execution.artifact_dir = dir.to_string();
+            assert!(check_settings(&config, false).is_empty(), "{}", dir);
+        }
+    }
+}
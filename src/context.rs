@@ -1,12 +1,15 @@
 use std::collections::{HashMap, VecDeque};
-use std::path::PathBuf;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use anyhow::{Context as AnyhowContext, Result};
+use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
 
-use crate::event_bus::{Event, EventBus, EventEmitter};
+use crate::config::ContextBudgets;
+use crate::event_bus::{ContextBreakdown, Event, EventBus, EventEmitter};
 use crate::impl_event_emitter;
 use crate::llm_manager::LLMManager;
 
@@ -16,6 +19,37 @@ pub struct Message {
     pub content: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub token_count: Option<usize>,
+    /// Pinned messages are never summarized and survive every compression
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub source: ContextSource,
+    /// Hash of the whitespace-normalized content, used to detect repeats
+    #[serde(skip)]
+    pub content_hash: u64,
+}
+
+/// Hash of `text` ignoring differences in whitespace, so reformatted or
+/// re-indented copies of the same content are treated as identical
+pub fn content_hash(text: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for word in text.split_whitespace() {
+        word.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Where a message came from; each source has its own share of the context window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextSource {
+    /// Scanned files and tool findings
+    Codebase,
+    /// Task input, step prompts and model responses
+    #[default]
+    Conversation,
+    /// Review prompts and verdicts
+    PlanReview,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +71,24 @@ pub struct CompressedContext {
     pub compressed_token_count: usize,
 }
 
+/// Version of the file format written by `ContextManager::export`
+const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// A context written to disk to be shared or handed to another run
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportedContext {
+    format_version: u32,
+    exported_at: chrono::DateTime<chrono::Utc>,
+    context: ConversationContext,
+}
+
+/// Conversation messages always kept verbatim by compression
+const MIN_RECENT_MESSAGES: usize = 5;
+/// Shorter messages (step prompts, acknowledgements) are always added as-is
+const MIN_DEDUP_TOKENS: usize = 50;
+/// Earlier messages at least this long are replaced by a reference when quoted verbatim
+const MIN_QUOTED_CHARS: usize = 500;
+
 /// Configuration for context management
 #[derive(Debug, Clone)]
 pub struct ContextConfig {
@@ -44,6 +96,7 @@ pub struct ContextConfig {
     pub compression_threshold: f32, // 0.0 to 1.0
     pub cache_enabled: bool,
     pub cache_dir: PathBuf,
+    pub budgets: ContextBudgets,
 }
 
 impl Default for ContextConfig {
@@ -53,6 +106,7 @@ impl Default for ContextConfig {
             compression_threshold: 0.8,
             cache_enabled: true,
             cache_dir: PathBuf::from("./cache"),
+            budgets: ContextBudgets::default(),
         }
     }
 }
@@ -102,7 +156,16 @@ impl ContextManager {
 
     /// Create a new conversation context
     pub async fn create_context(&self, metadata: HashMap<String, String>) -> String {
-        let id = uuid::Uuid::new_v4().to_string();
+        self.create_context_with_id(uuid::Uuid::new_v4().to_string(), metadata)
+            .await
+    }
+
+    /// Create a conversation context with a caller-chosen ID, replacing any existing one
+    pub async fn create_context_with_id(
+        &self,
+        id: String,
+        metadata: HashMap<String, String>,
+    ) -> String {
         let now = chrono::Utc::now();
 
         let context = ConversationContext {
@@ -127,53 +190,268 @@ impl ContextManager {
 
     /// Add a message to context
     pub async fn add_message(&self, context_id: &str, role: String, content: String) -> Result<()> {
+        self.push_message(
+            context_id,
+            role,
+            content,
+            false,
+            ContextSource::Conversation,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Add a message counted against the budget of `source`. Returns false if a
+    /// codebase message was dropped because the codebase budget is exhausted.
+    pub async fn add_message_from(
+        &self,
+        context_id: &str,
+        role: String,
+        content: String,
+        source: ContextSource,
+    ) -> Result<bool> {
+        self.push_message(context_id, role, content, false, source)
+            .await
+    }
+
+    /// Add a message that is never compressed and is always re-included after compression
+    pub async fn add_pinned_message(
+        &self,
+        context_id: &str,
+        role: String,
+        content: String,
+    ) -> Result<()> {
+        self.push_message(context_id, role, content, true, ContextSource::Conversation)
+            .await?;
+        Ok(())
+    }
+
+    /// Pin the existing messages for which `matches` returns true; returns how many were pinned
+    pub async fn pin_messages(
+        &self,
+        context_id: &str,
+        matches: impl Fn(&Message) -> bool,
+    ) -> Result<usize> {
+        let mut contexts = self.contexts.write().await;
+
+        if let Some(context) = contexts.get_mut(context_id) {
+            let mut count = 0;
+            for message in context.messages.iter_mut().filter(|m| !m.pinned) {
+                if matches(message) {
+                    message.pinned = true;
+                    count += 1;
+                }
+            }
+            Ok(count)
+        } else {
+            anyhow::bail!("Context not found: {}", context_id)
+        }
+    }
+
+    async fn push_message(
+        &self,
+        context_id: &str,
+        role: String,
+        content: String,
+        pinned: bool,
+        source: ContextSource,
+    ) -> Result<bool> {
         let mut contexts = self.contexts.write().await;
 
         if let Some(context) = contexts.get_mut(context_id) {
             // Estimate token count (improved estimation)
-            let token_count = self.estimate_tokens(&content);
+            let mut token_count = self.estimate_tokens(&content);
+            let mut content = content;
+            let hash = content_hash(&content);
+
+            if token_count >= MIN_DEDUP_TOKENS {
+                if let Some(existing) = context
+                    .messages
+                    .iter_mut()
+                    .find(|m| m.content_hash == hash && m.role == role)
+                {
+                    // Already in context: pin the earlier copy if asked to, otherwise
+                    // repeat nothing for system content and a short reference for turns
+                    if pinned || role == "system" {
+                        existing.pinned |= pinned;
+                        self.emit_deduplicated(context_id, source, token_count)
+                            .await;
+                        return Ok(true);
+                    }
+                    let reference = format!(
+                        "[Same content as the earlier {} message from {}; not repeated]",
+                        existing.role,
+                        existing.timestamp.format("%H:%M:%S")
+                    );
+                    let saved = token_count;
+                    token_count = self.estimate_tokens(&reference);
+                    content = reference;
+                    self.emit_deduplicated(context_id, source, saved - token_count)
+                        .await;
+                } else if let Some(replaced) = replace_quoted(&context.messages, &content) {
+                    let saved = token_count;
+                    token_count = self.estimate_tokens(&replaced);
+                    content = replaced;
+                    self.emit_deduplicated(context_id, source, saved.saturating_sub(token_count))
+                        .await;
+                }
+            }
+
+            let max_tokens = self.max_tokens();
+
+            // Enforce the source's share of the window; pinned messages are exempt
+            let allowance = (max_tokens as f32 * self.config.budgets.share(source)) as usize;
+            let source_tokens = |context: &ConversationContext| -> usize {
+                context
+                    .messages
+                    .iter()
+                    .filter(|m| m.source == source && !m.pinned)
+                    .map(|m| m.token_count.unwrap_or(0))
+                    .sum()
+            };
+            let mut over_budget = !pinned && source_tokens(context) + token_count > allowance;
+            if over_budget {
+                match source {
+                    ContextSource::Codebase => {
+                        // Keep what's already loaded rather than crowding out the conversation
+                        self.emit_budget_exceeded(context_id, source, allowance)
+                            .await;
+                        return Ok(false);
+                    }
+                    ContextSource::PlanReview => {
+                        // Older reviews are superseded by newer ones
+                        while source_tokens(context) + token_count > allowance {
+                            let Some(index) = context
+                                .messages
+                                .iter()
+                                .position(|m| m.source == source && !m.pinned)
+                            else {
+                                break;
+                            };
+                            if let Some(evicted) = context.messages.remove(index) {
+                                context.total_tokens = context
+                                    .total_tokens
+                                    .saturating_sub(evicted.token_count.unwrap_or(0));
+                            }
+                        }
+                        over_budget = false;
+                    }
+                    // Conversation history is summarized below
+                    ContextSource::Conversation => {}
+                }
+            }
 
             let message = Message {
                 role,
                 content,
                 timestamp: chrono::Utc::now(),
                 token_count: Some(token_count),
+                pinned,
+                source,
+                content_hash: hash,
             };
 
             context.messages.push_back(message);
             context.total_tokens += token_count;
             context.updated_at = chrono::Utc::now();
 
-            // Check if we need compression
-            let max_tokens = if let Some(llm_manager) = &self.llm_manager {
-                llm_manager.get_context_size()
-            } else {
-                self.config.max_tokens // Fallback to config if no LLM manager
-            };
-
+            // Check if we need compression; an over-budget conversation is only
+            // compressed while there is more than the minimum recent window to summarize
+            let compressible = context
+                .messages
+                .iter()
+                .filter(|m| m.role != "system" && !m.pinned)
+                .count();
+            over_budget = over_budget && compressible > MIN_RECENT_MESSAGES;
             let usage_ratio = context.total_tokens as f32 / max_tokens as f32;
-            if usage_ratio > self.config.compression_threshold {
+            self.emit_usage(context_id, context).await;
+            if usage_ratio > self.config.compression_threshold || over_budget {
+                info!(
+                    "Compressing context at {:.0}% usage ({})",
+                    usage_ratio * 100.0,
+                    breakdown(context)
+                );
                 drop(contexts);
                 self.compress_context(context_id).await?;
-            } else {
-                // Emit usage event
-                if let Some(bus) = &self.event_bus {
-                    let _ = bus
-                        .emit(Event::ContextUsageChanged {
-                            id: context_id.to_string(),
-                            usage_percentage: usage_ratio * 100.0,
-                            total_tokens: context.total_tokens,
-                        })
-                        .await;
-                }
             }
 
-            Ok(())
+            Ok(true)
         } else {
             anyhow::bail!("Context not found: {}", context_id)
         }
     }
 
+    /// Emit the context's usage with its breakdown by source
+    async fn emit_usage(&self, context_id: &str, context: &ConversationContext) {
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::ContextUsageChanged {
+                    id: context_id.to_string(),
+                    usage_percentage: context.total_tokens as f32 / self.max_tokens() as f32
+                        * 100.0,
+                    total_tokens: context.total_tokens,
+                    breakdown: breakdown(context),
+                })
+                .await;
+        }
+    }
+
+    async fn emit_deduplicated(
+        &self,
+        context_id: &str,
+        source: ContextSource,
+        tokens_saved: usize,
+    ) {
+        debug!(
+            "Skipped {} tokens of {:?} content already in context",
+            tokens_saved, source
+        );
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::Custom {
+                    event_type: "context_deduplicated".to_string(),
+                    data: serde_json::json!({
+                        "context_id": context_id,
+                        "source": source,
+                        "tokens_saved": tokens_saved,
+                    }),
+                })
+                .await;
+        }
+    }
+
+    /// Context window size of the active model, or the configured fallback
+    fn max_tokens(&self) -> usize {
+        match &self.llm_manager {
+            Some(llm_manager) => llm_manager.get_context_size(),
+            None => self.config.max_tokens,
+        }
+    }
+
+    async fn emit_budget_exceeded(
+        &self,
+        context_id: &str,
+        source: ContextSource,
+        allowance: usize,
+    ) {
+        info!(
+            "Context budget for {:?} ({} tokens) exhausted; message not added",
+            source, allowance
+        );
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::Custom {
+                    event_type: "context_budget_exceeded".to_string(),
+                    data: serde_json::json!({
+                        "context_id": context_id,
+                        "source": source,
+                        "allowance_tokens": allowance,
+                    }),
+                })
+                .await;
+        }
+    }
+
     /// Get messages from context with optional token limit
     #[allow(dead_code)]
     pub async fn get_messages(
@@ -185,20 +463,35 @@ impl ContextManager {
 
         if let Some(context) = contexts.get(context_id) {
             if let Some(max) = max_tokens {
-                // Return most recent messages that fit within token limit
-                let mut messages = Vec::new();
-                let mut token_count = 0;
-
-                for message in context.messages.iter().rev() {
+                // Pinned messages are always returned; fill the rest of the limit
+                // with the most recent messages
+                let mut token_count: usize = context
+                    .messages
+                    .iter()
+                    .filter(|m| m.pinned)
+                    .map(|m| m.token_count.unwrap_or(0))
+                    .sum();
+                let mut included: Vec<bool> = context.messages.iter().map(|m| m.pinned).collect();
+
+                for (index, message) in context.messages.iter().enumerate().rev() {
+                    if message.pinned {
+                        continue;
+                    }
                     let msg_tokens = message.token_count.unwrap_or(0);
                     if token_count + msg_tokens > max {
                         break;
                     }
-                    messages.push(message.clone());
+                    included[index] = true;
                     token_count += msg_tokens;
                 }
 
-                messages.reverse();
+                let messages = context
+                    .messages
+                    .iter()
+                    .zip(included)
+                    .filter(|(_, included)| *included)
+                    .map(|(m, _)| m.clone())
+                    .collect();
                 Ok(messages)
             } else {
                 Ok(context.messages.iter().cloned().collect())
@@ -213,19 +506,19 @@ impl ContextManager {
         let mut contexts = self.contexts.write().await;
 
         if let Some(context) = contexts.get_mut(context_id) {
-            // Keep system messages separate
+            // Keep system and pinned messages separate; they are never summarized
             let system_messages: Vec<_> = context
                 .messages
                 .iter()
-                .filter(|m| m.role == "system")
+                .filter(|m| m.role == "system" || m.pinned)
                 .cloned()
                 .collect();
 
-            // Get non-system messages
+            // Get the remaining conversation messages
             let conversation_messages: Vec<_> = context
                 .messages
                 .iter()
-                .filter(|m| m.role != "system")
+                .filter(|m| m.role != "system" && !m.pinned)
                 .cloned()
                 .collect();
 
@@ -233,32 +526,26 @@ impl ContextManager {
                 return Ok(());
             }
 
-            // Calculate token budget (30% of max for recent messages)
-            let token_budget = (self.config.max_tokens as f32 * 0.3) as usize;
+            // Recent messages may use half of the conversation budget, leaving
+            // room to grow before the next compression
+            let token_budget =
+                (self.max_tokens() as f32 * self.config.budgets.conversation * 0.5) as usize;
 
             // Try different window sizes to find what fits in budget
-            let window_sizes = [30, 25, 20, 15, 10, 5];
+            let window_sizes = [30, 25, 20, 15, 10, MIN_RECENT_MESSAGES];
             let mut recent_messages = Vec::new();
 
             let mut messages_to_summarize = Vec::new();
 
             for window_size in window_sizes.iter() {
-                recent_messages.clear();
-
                 // Take the last N messages
                 let start_idx = conversation_messages.len().saturating_sub(*window_size);
-
-                for msg in conversation_messages[start_idx..].iter() {
-                    let msg_tokens = msg.token_count.unwrap_or(0);
-                    if msg_tokens <= token_budget {
-                        recent_messages.push(msg.clone());
-                    } else {
-                        break;
-                    }
-                }
+                let window = &conversation_messages[start_idx..];
+                let window_tokens: usize = window.iter().map(|m| m.token_count.unwrap_or(0)).sum();
 
                 // If we found a good window, use it
-                if !recent_messages.is_empty() {
+                if window_tokens <= token_budget {
+                    recent_messages = window.to_vec();
                     messages_to_summarize = conversation_messages[..start_idx].to_vec();
                     break;
                 }
@@ -266,7 +553,7 @@ impl ContextManager {
 
             // If no recent messages fit, just keep the last 5
             if recent_messages.is_empty() {
-                let keep_count = conversation_messages.len().min(5);
+                let keep_count = conversation_messages.len().min(MIN_RECENT_MESSAGES);
                 recent_messages =
                     conversation_messages[conversation_messages.len() - keep_count..].to_vec();
                 messages_to_summarize =
@@ -342,7 +629,7 @@ impl ContextManager {
             // Rebuild context with compressed version
             context.messages.clear();
 
-            // Re-add system messages
+            // Re-add system and pinned messages
             for msg in system_messages {
                 context.messages.push_back(msg);
             }
@@ -357,6 +644,9 @@ impl ContextManager {
                     ),
                     timestamp: chrono::Utc::now(),
                     token_count: Some(self.estimate_tokens(&summary_content) + 10),
+                    pinned: false,
+                    source: ContextSource::Conversation,
+                    content_hash: content_hash(&summary_content),
                 });
             }
 
@@ -382,6 +672,7 @@ impl ContextManager {
                     })
                     .await;
             }
+            self.emit_usage(context_id, context).await;
 
             Ok(())
         } else {
@@ -429,8 +720,29 @@ impl ContextManager {
         }
     }
 
+    /// Keep only the messages for which `keep` returns true
+    pub async fn retain_messages(
+        &self,
+        context_id: &str,
+        keep: impl Fn(&Message) -> bool,
+    ) -> Result<()> {
+        let mut contexts = self.contexts.write().await;
+
+        if let Some(context) = contexts.get_mut(context_id) {
+            context.messages.retain(|m| keep(m));
+            context.total_tokens = context
+                .messages
+                .iter()
+                .map(|m| m.token_count.unwrap_or(0))
+                .sum();
+            context.updated_at = chrono::Utc::now();
+            Ok(())
+        } else {
+            anyhow::bail!("Context not found: {}", context_id)
+        }
+    }
+
     /// Save context to cache
-    #[allow(dead_code)]
     pub async fn save_to_cache(&self, context_id: &str) -> Result<()> {
         if !self.config.cache_enabled {
             return Ok(());
@@ -454,7 +766,6 @@ impl ContextManager {
     }
 
     /// Load context from cache
-    #[allow(dead_code)]
     pub async fn load_from_cache(&self, context_id: &str) -> Result<()> {
         if !self.config.cache_enabled {
             anyhow::bail!("Cache is disabled");
@@ -470,15 +781,329 @@ impl ContextManager {
             .await
             .context("Failed to read context from cache")?;
 
-        let context: ConversationContext =
+        let mut context: ConversationContext =
             serde_json::from_str(&json).context("Failed to deserialize context")?;
+        for message in context.messages.iter_mut() {
+            message.content_hash = content_hash(&message.content);
+        }
 
         let mut contexts = self.contexts.write().await;
         contexts.insert(context_id.to_string(), context);
 
         Ok(())
     }
+
+    /// Write a context to `dir/<context_id>.json` so it can be shared, e.g. in a bug
+    /// report, or passed to another run with `--context-file`. Returns the file path.
+    pub async fn export(&self, context_id: &str, dir: &Path) -> Result<PathBuf> {
+        let contexts = self.contexts.read().await;
+        let context = contexts
+            .get(context_id)
+            .with_context(|| format!("Context not found: {}", context_id))?;
+
+        let exported = ExportedContext {
+            format_version: EXPORT_FORMAT_VERSION,
+            exported_at: chrono::Utc::now(),
+            context: context.clone(),
+        };
+        let json =
+            serde_json::to_string_pretty(&exported).context("Failed to serialize context")?;
+        tokio::fs::create_dir_all(dir)
+            .await
+            .context("Failed to create context export directory")?;
+        let path = dir.join(format!("{}.json", context_id));
+        tokio::fs::write(&path, json)
+            .await
+            .with_context(|| format!("Failed to write context to {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    /// Load a context written by `export`, replacing any context with the same ID.
+    /// Returns the ID of the imported context.
+    pub async fn import(&self, path: &Path) -> Result<String> {
+        let json = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read context file {}", path.display()))?;
+        let exported: ExportedContext = serde_json::from_str(&json)
+            .with_context(|| format!("{} is not an exported context", path.display()))?;
+        if exported.format_version > EXPORT_FORMAT_VERSION {
+            anyhow::bail!(
+                "{} uses context format version {}, but this version of cli_engineer reads up to {}",
+                path.display(),
+                exported.format_version,
+                EXPORT_FORMAT_VERSION
+            );
+        }
+
+        let mut context = exported.context;
+        for message in context.messages.iter_mut() {
+            message.content_hash = content_hash(&message.content);
+        }
+        let id = context.id.clone();
+        self.contexts.write().await.insert(id.clone(), context);
+
+        if let Some(bus) = &self.event_bus {
+            let _ = bus.emit(Event::ContextLoaded { id: id.clone() }).await;
+        }
+        Ok(id)
+    }
+}
+
+/// Tokens held by each kind of message in `context`
+fn breakdown(context: &ConversationContext) -> ContextBreakdown {
+    let mut breakdown = ContextBreakdown::default();
+    for message in &context.messages {
+        let tokens = message.token_count.unwrap_or(0);
+        let bucket = if message.content.starts_with("=== Context Summary") {
+            &mut breakdown.summaries
+        } else {
+            match message.source {
+                ContextSource::Codebase => &mut breakdown.codebase,
+                ContextSource::PlanReview => &mut breakdown.plan_review,
+                ContextSource::Conversation if message.role == "system" => &mut breakdown.system,
+                ContextSource::Conversation => &mut breakdown.conversation,
+            }
+        };
+        *bucket += tokens;
+    }
+    breakdown
+}
+
+/// Replace verbatim copies of earlier long messages inside `content` (e.g. a review
+/// prompt echoing step outputs) with a reference; None if nothing was quoted
+fn replace_quoted(messages: &VecDeque<Message>, content: &str) -> Option<String> {
+    let mut out = content.to_string();
+    for message in messages {
+        let quoted = message.content.trim();
+        if quoted.len() < MIN_QUOTED_CHARS || quoted.len() >= out.len() || !out.contains(quoted) {
+            continue;
+        }
+        let first_line: String = quoted
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .take(60)
+            .collect();
+        out = out.replace(
+            quoted,
+            &format!(
+                "[Repeated content omitted: see the earlier {} message starting \"{}\"]",
+                message.role, first_line
+            ),
+        );
+    }
+    (out.len() < content.len()).then_some(out)
 }
 
 // Implement EventEmitter trait
 impl_event_emitter!(ContextManager);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pinned_messages_survive_compression() {
+        let manager = ContextManager::new(ContextConfig {
+            max_tokens: 1_000,
+            compression_threshold: 0.5,
+            cache_enabled: false,
+            cache_dir: PathBuf::new(),
+            budgets: ContextBudgets::default(),
+        })
+        .unwrap();
+        let id = manager.create_context(HashMap::new()).await;
+
+        manager
+            .add_pinned_message(
+                &id,
+                "user".to_string(),
+                "Never change the public API".to_string(),
+            )
+            .await
+            .unwrap();
+        for i in 0..40 {
+            manager
+                .add_message(
+                    &id,
+                    "assistant".to_string(),
+                    format!("step {} {}", i, "x ".repeat(20)),
+                )
+                .await
+                .unwrap();
+        }
+
+        let messages = manager.get_messages(&id, None).await.unwrap();
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.content.starts_with("=== Context Summary"))
+        );
+        assert!(
+            messages
+                .iter()
+                .any(|m| m.pinned && m.content == "Never change the public API")
+        );
+
+        let limited = manager.get_messages(&id, Some(50)).await.unwrap();
+        assert!(limited[0].pinned);
+    }
+
+    #[tokio::test]
+    async fn test_codebase_budget_limits_scanned_files() {
+        let manager = ContextManager::new(ContextConfig {
+            max_tokens: 1_000,
+            compression_threshold: 0.9,
+            cache_enabled: false,
+            cache_dir: PathBuf::new(),
+            budgets: ContextBudgets::default(),
+        })
+        .unwrap();
+        let id = manager.create_context(HashMap::new()).await;
+
+        let file = "fn main() {} ".repeat(60);
+        let mut loaded = 0;
+        for i in 0..10 {
+            if manager
+                .add_message_from(
+                    &id,
+                    "system".to_string(),
+                    format!("// file {}\n{}", i, file),
+                    ContextSource::Codebase,
+                )
+                .await
+                .unwrap()
+            {
+                loaded += 1;
+            }
+        }
+        // 60% of 1,000 tokens holds two files of ~214 tokens
+        assert_eq!(loaded, 2);
+
+        manager
+            .add_message(&id, "user".to_string(), "Add a CLI flag".to_string())
+            .await
+            .unwrap();
+        let messages = manager.get_messages(&id, None).await.unwrap();
+        assert_eq!(messages.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_content_is_not_added_twice() {
+        let manager = ContextManager::new(ContextConfig {
+            max_tokens: 100_000,
+            compression_threshold: 0.9,
+            cache_enabled: false,
+            cache_dir: PathBuf::new(),
+            budgets: ContextBudgets::default(),
+        })
+        .unwrap();
+        let id = manager.create_context(HashMap::new()).await;
+
+        let file = "File: src/lib.rs\n".to_string()
+            + &"pub fn add(a: i32, b: i32) -> i32 { a + b }\n".repeat(20);
+        for content in [file.clone(), file.replace('\n', "\n  ")] {
+            manager
+                .add_message_from(&id, "system".to_string(), content, ContextSource::Codebase)
+                .await
+                .unwrap();
+        }
+        let response = "Implemented the change. ".repeat(40);
+        manager
+            .add_message(&id, "assistant".to_string(), response.clone())
+            .await
+            .unwrap();
+        manager
+            .add_message(
+                &id,
+                "user".to_string(),
+                format!("Review these results:\n{}\nIs it correct?", response.trim()),
+            )
+            .await
+            .unwrap();
+
+        let messages = manager.get_messages(&id, None).await.unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[2].content.contains("Repeated content omitted"));
+        assert!(messages[2].content.len() < 300);
+    }
+
+    #[tokio::test]
+    async fn test_usage_breakdown_by_source() {
+        let manager = ContextManager::new(ContextConfig {
+            max_tokens: 100_000,
+            compression_threshold: 0.9,
+            cache_enabled: false,
+            cache_dir: PathBuf::new(),
+            budgets: ContextBudgets::default(),
+        })
+        .unwrap();
+        let id = manager.create_context(HashMap::new()).await;
+
+        let add = |role: &str, content: &str, source| {
+            manager.add_message_from(&id, role.to_string(), content.to_string(), source)
+        };
+        add(
+            "system",
+            "File: src/main.rs\nfn main() {}",
+            ContextSource::Codebase,
+        )
+        .await
+        .unwrap();
+        add(
+            "system",
+            "Task interpreted as: add a flag",
+            ContextSource::Conversation,
+        )
+        .await
+        .unwrap();
+        add("user", "Step 1: add the flag", ContextSource::Conversation)
+            .await
+            .unwrap();
+        add("user", "Review the results", ContextSource::PlanReview)
+            .await
+            .unwrap();
+
+        let contexts = manager.contexts.read().await;
+        let breakdown = breakdown(&contexts[&id]);
+        assert!(breakdown.codebase > 0 && breakdown.system > 0);
+        assert!(breakdown.conversation > 0 && breakdown.plan_review > 0);
+        assert_eq!(breakdown.summaries, 0);
+        assert_eq!(
+            breakdown.codebase + breakdown.system + breakdown.conversation + breakdown.plan_review,
+            contexts[&id].total_tokens
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let config = ContextConfig {
+            max_tokens: 100_000,
+            compression_threshold: 0.9,
+            cache_enabled: false,
+            cache_dir: PathBuf::new(),
+            budgets: ContextBudgets::default(),
+        };
+        let manager = ContextManager::new(config.clone()).unwrap();
+        let id = manager.create_context(HashMap::new()).await;
+        manager
+            .add_pinned_message(&id, "user".to_string(), "Fix the login bug".to_string())
+            .await
+            .unwrap();
+
+        let dir =
+            std::env::temp_dir().join(format!("cli_engineer_export_{}", uuid::Uuid::new_v4()));
+        let path = manager.export(&id, &dir).await.unwrap();
+
+        let other = ContextManager::new(config).unwrap();
+        assert_eq!(other.import(&path).await.unwrap(), id);
+        let messages = other.get_messages(&id, None).await.unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].pinned);
+        assert_eq!(messages[0].content_hash, content_hash("Fix the login bug"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::BTreeSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::reviewer::ReviewResult;
+
+/// Notices when the loop stops making progress: iterations that leave the
+/// artifacts materially unchanged, or whose reviews raise the same issues again
+pub struct ProgressTracker {
+    /// Consecutive iterations without progress that stop the loop; 0 never stops it
+    limit: usize,
+    artifacts: Option<u64>,
+    issues: Option<BTreeSet<String>>,
+    unchanged_iterations: usize,
+    repeated_issue_iterations: usize,
+}
+
+impl ProgressTracker {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            artifacts: None,
+            issues: None,
+            unchanged_iterations: 0,
+            repeated_issue_iterations: 0,
+        }
+    }
+
+    /// Record an iteration's artifacts, as an [`artifact_fingerprint`], and its
+    /// review. Returns why the loop stalled once `limit` iterations in a row
+    /// made no progress.
+    pub fn observe(&mut self, artifacts: u64, review: &ReviewResult) -> Option<String> {
+        let issues = issue_set(review);
+        if self.artifacts == Some(artifacts) {
+            self.unchanged_iterations += 1;
+        } else {
+            self.unchanged_iterations = 0;
+        }
+        if !issues.is_empty() && self.issues.as_ref() == Some(&issues) {
+            self.repeated_issue_iterations += 1;
+        } else {
+            self.repeated_issue_iterations = 0;
+        }
+        let count = issues.len();
+        self.artifacts = Some(artifacts);
+        self.issues = Some(issues);
+
+        if self.limit == 0 {
+            None
+        } else if self.unchanged_iterations >= self.limit {
+            Some(format!(
+                "the last {} iterations made no material change to the artifacts",
+                self.unchanged_iterations
+            ))
+        } else if self.repeated_issue_iterations >= self.limit {
+            Some(format!(
+                "the review raised the same {} issue{} in the last {} iterations",
+                count,
+                if count == 1 { "" } else { "s" },
+                self.repeated_issue_iterations + 1
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+/// Hash of the artifacts' names and content, ignoring whitespace so
+/// reformatting alone does not count as a change
+pub fn artifact_fingerprint<'a>(artifacts: impl IntoIterator<Item = (&'a str, &'a str)>) -> u64 {
+    let mut artifacts: Vec<_> = artifacts.into_iter().collect();
+    artifacts.sort();
+    let mut hasher = DefaultHasher::new();
+    for (name, content) in artifacts {
+        name.hash(&mut hasher);
+        for c in content.chars().filter(|c| !c.is_whitespace()) {
+            c.hash(&mut hasher);
+        }
+        // Separate the artifacts so content cannot shift between them
+        0xffu8.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// The review's issues, compared by severity, location and wording
+fn issue_set(review: &ReviewResult) -> BTreeSet<String> {
+    review
+        .issues
+        .iter()
+        .map(|issue| {
+            let description = issue
+                .description
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase();
+            format!(
+                "{}|{}|{}",
+                issue.severity,
+                issue.location.as_deref().unwrap_or(""),
+                description
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reviewer::{Issue, IssueCategory, IssueSeverity, QualityLevel};
+
+    fn review(issues: &[&str]) -> ReviewResult {
+        ReviewResult {
+            overall_quality: QualityLevel::Fair,
+            issues: issues
+                .iter()
+                .map(|description| Issue {
+                    severity: IssueSeverity::Major,
+                    category: IssueCategory::Logic,
+                    description: description.to_string(),
+                    location: Some("src/lib.rs".to_string()),
+                    suggestion: None,
+                    patch: None,
+                })
+                .collect(),
+            suggestions: Vec::new(),
+            ready_to_deploy: false,
+            summary: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_progress_tracker() {
+        let first = artifact_fingerprint([("a.rs", "fn main() {}")]);
+        let reformatted = artifact_fingerprint([("a.rs", "fn main() {\n}\n")]);
+        let changed = artifact_fingerprint([("a.rs", "fn main() { run() }")]);
+        assert_eq!(first, reformatted);
+        assert_ne!(first, changed);
+
+        let mut tracker = ProgressTracker::new(2);
+        assert_eq!(tracker.observe(first, &review(&["Bug"])), None);
+        assert_eq!(tracker.observe(reformatted, &review(&["Other bug"])), None);
+        let stalled = tracker.observe(first, &review(&["Third bug"]));
+        assert!(stalled.unwrap().contains("no material change"));
+
+        // The same issues, however the artifacts change
+        let mut tracker = ProgressTracker::new(2);
+        assert_eq!(tracker.observe(first, &review(&["Off by one"])), None);
+        assert_eq!(tracker.observe(changed, &review(&["off  by one"])), None);
+        assert_eq!(
+            tracker.observe(first, &review(&["Off by one"])),
+            Some("the review raised the same 1 issue in the last 3 iterations".to_string())
+        );
+
+        let mut never = ProgressTracker::new(0);
+        for _ in 0..5 {
+            assert_eq!(never.observe(first, &review(&["Bug"])), None);
+        }
+    }
+}
@@ -0,0 +1,593 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use colored::*;
+use serde_json::Value;
+
+use crate::artifact::{ArtifactManager, ArtifactType};
+use crate::code_index::{CodeIndex, Symbol, SymbolKind};
+use crate::config::Config;
+
+pub const REPORT_NAME: &str = "coverage_gaps.md";
+
+/// Where coverage tools write their data, looked at when `--coverage` is not given
+const COVERAGE_FILES: &[&str] = &[
+    "lcov.info",
+    "coverage/lcov.info",
+    "coverage.lcov",
+    "target/lcov.info",
+    "coverage.json",
+    "coverage/coverage.json",
+];
+/// Gaps given to the test-writing run, highest priority first
+const MAX_TEST_TARGETS: usize = 10;
+/// Lines of a function's source shown to the test-writing run
+const MAX_SOURCE_LINES: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoverageFormat {
+    /// `lcov.info`, written by `cargo llvm-cov --lcov`, `coverage lcov`, nyc and c8
+    Lcov,
+    /// `cargo llvm-cov --json` or `llvm-cov export`
+    LlvmCov,
+    /// `coverage json`
+    CoveragePy,
+}
+
+impl fmt::Display for CoverageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CoverageFormat::Lcov => "lcov",
+            CoverageFormat::LlvmCov => "llvm-cov JSON",
+            CoverageFormat::CoveragePy => "coverage.py JSON",
+        })
+    }
+}
+
+/// Execution counts of the instrumented lines of each file, keyed by the
+/// path the coverage tool wrote
+#[derive(Debug)]
+pub struct Coverage {
+    pub format: CoverageFormat,
+    files: BTreeMap<String, BTreeMap<usize, u64>>,
+}
+
+impl Coverage {
+    /// Parse coverage data, telling the format from its content
+    pub fn parse(content: &str) -> Result<Self> {
+        if !content.trim_start().starts_with('{') {
+            return Ok(Self::parse_lcov(content));
+        }
+        let json: Value = serde_json::from_str(content).context("Invalid coverage JSON")?;
+        if let Some(data) = json.get("data").and_then(Value::as_array) {
+            Ok(Self::parse_llvm_cov(data))
+        } else if let Some(files) = json.get("files").and_then(Value::as_object) {
+            Ok(Self::parse_coverage_py(files))
+        } else {
+            anyhow::bail!("Unknown coverage JSON: expected llvm-cov or coverage.py output")
+        }
+    }
+
+    fn parse_lcov(content: &str) -> Self {
+        let mut files: BTreeMap<String, BTreeMap<usize, u64>> = BTreeMap::new();
+        let mut current = None;
+        for line in content.lines() {
+            if let Some(path) = line.strip_prefix("SF:") {
+                current = Some(path.trim().to_string());
+            } else if line == "end_of_record" {
+                current = None;
+            } else if let (Some(path), Some(record)) = (&current, line.strip_prefix("DA:")) {
+                let mut fields = record.split(',');
+                let line = fields.next().and_then(|n| n.trim().parse().ok());
+                let hits = fields.next().and_then(|n| n.trim().parse::<u64>().ok());
+                if let (Some(line), Some(hits)) = (line, hits) {
+                    let lines = files.entry(path.clone()).or_default();
+                    *lines.entry(line).or_default() += hits;
+                }
+            }
+        }
+        Self {
+            format: CoverageFormat::Lcov,
+            files,
+        }
+    }
+
+    /// Segments are `[line, column, count, has_count, is_region_entry, is_gap_region]`
+    fn parse_llvm_cov(data: &[Value]) -> Self {
+        let mut files: BTreeMap<String, BTreeMap<usize, u64>> = BTreeMap::new();
+        let exports = data.iter().filter_map(|export| export["files"].as_array());
+        for file in exports.flatten() {
+            let Some(path) = file["filename"].as_str() else {
+                continue;
+            };
+            let lines = files.entry(path.to_string()).or_default();
+            for segment in file["segments"].as_array().into_iter().flatten() {
+                let has_count = segment[3].as_bool().unwrap_or(false);
+                let is_gap = segment[5].as_bool().unwrap_or(false);
+                if let (Some(line), Some(count), true, false) =
+                    (segment[0].as_u64(), segment[2].as_u64(), has_count, is_gap)
+                {
+                    let hits = lines.entry(line as usize).or_default();
+                    *hits = (*hits).max(count);
+                }
+            }
+        }
+        Self {
+            format: CoverageFormat::LlvmCov,
+            files,
+        }
+    }
+
+    fn parse_coverage_py(report: &serde_json::Map<String, Value>) -> Self {
+        let line_numbers = |value: &Value| {
+            value
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_u64)
+                .map(|line| line as usize)
+                .collect::<Vec<_>>()
+        };
+        let files = report
+            .iter()
+            .map(|(path, file)| {
+                let executed = line_numbers(&file["executed_lines"]).into_iter();
+                let missing = line_numbers(&file["missing_lines"]).into_iter();
+                let lines = executed
+                    .map(|line| (line, 1))
+                    .chain(missing.map(|line| (line, 0)))
+                    .collect();
+                (path.clone(), lines)
+            })
+            .collect();
+        Self {
+            format: CoverageFormat::CoveragePy,
+            files,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Priority::High => "High",
+            Priority::Medium => "Medium",
+            Priority::Low => "Low",
+        })
+    }
+}
+
+/// A public function none of whose lines ran during the tests
+#[derive(Debug, Clone)]
+pub struct Gap {
+    pub name: String,
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Other files that mention the function, a rough count of its callers
+    pub callers: usize,
+    pub priority: Priority,
+    source: String,
+}
+
+impl Gap {
+    fn lines(&self) -> usize {
+        self.end_line + 1 - self.start_line
+    }
+
+    /// Long functions used from many places are tested first
+    fn score(&self) -> usize {
+        self.lines() * (1 + self.callers)
+    }
+}
+
+/// Coverage of one source file
+#[derive(Debug)]
+struct FileCoverage {
+    path: String,
+    covered_lines: usize,
+    lines: usize,
+    public_functions: usize,
+    gaps: usize,
+}
+
+/// Report untested public functions from the coverage data at `coverage`, or
+/// found in the usual places under `root`. Returns the gaps, highest priority first.
+pub async fn run(config: &Config, root: &Path, coverage: Option<&Path>) -> Result<Vec<Gap>> {
+    let path = match coverage {
+        Some(path) => path.to_path_buf(),
+        None => COVERAGE_FILES
+            .iter()
+            .map(|file| root.join(file))
+            .find(|path| path.is_file())
+            .with_context(|| {
+                format!(
+                    "No coverage data found; write lcov, llvm-cov JSON or coverage.py JSON \
+                     (e.g. `cargo llvm-cov --lcov --output-path lcov.info`) or pass --coverage \
+                     PATH. Looked for {}",
+                    COVERAGE_FILES.join(", ")
+                )
+            })?,
+    };
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let coverage =
+        Coverage::parse(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let (gaps, files) = find_gaps(root, &coverage);
+    let report = render(&path, &coverage, &gaps, &files);
+    let artifact_dir = root.join(&config.execution.artifact_dir);
+    write_report(&artifact_dir, report).await?;
+
+    let functions: usize = files.iter().map(|file| file.public_functions).sum();
+    println!(
+        "{} {} of {} public functions have no coverage; report written to {}",
+        "✓".green(),
+        gaps.len(),
+        functions,
+        artifact_dir.join(REPORT_NAME).display()
+    );
+    for gap in gaps.iter().take(5) {
+        println!(
+            "  {:<6} {} ({}:{})",
+            gap.priority.to_string(),
+            gap.name.bright_white(),
+            gap.path,
+            gap.start_line
+        );
+    }
+    Ok(gaps)
+}
+
+/// Task of a code run writing tests for the highest priority `gaps`, with the
+/// user's `instructions` added
+pub fn tests_prompt(gaps: &[Gap], instructions: &str) -> String {
+    let mut prompt = String::from(
+        "Write unit tests for these public functions, which the test suite never runs. \
+         Follow the project's existing test layout, framework and naming, and cover each \
+         function's main path, edge cases and errors. Do not change the functions themselves.",
+    );
+    if !instructions.is_empty() {
+        prompt.push_str(&format!(" {}", instructions));
+    }
+    for gap in gaps.iter().take(MAX_TEST_TARGETS) {
+        prompt.push_str(&format!(
+            "\n\n{} ({}:{}-{}):\n```\n{}\n```",
+            gap.name, gap.path, gap.start_line, gap.end_line, gap.source
+        ));
+    }
+    prompt
+}
+
+/// Index the files with coverage data and find their public functions
+/// without a single executed line
+fn find_gaps(root: &Path, coverage: &Coverage) -> (Vec<Gap>, Vec<FileCoverage>) {
+    // Files outside the project, such as dependencies, are left out
+    let files: Vec<(String, &BTreeMap<usize, u64>)> = coverage
+        .files
+        .iter()
+        .filter_map(|(path, lines)| Some((project_path(root, path)?, lines)))
+        .filter(|(path, _)| !is_test_file(path))
+        .collect();
+    let sources: HashMap<&str, String> = files
+        .iter()
+        .filter_map(|(path, _)| {
+            let content = std::fs::read_to_string(root.join(path)).ok()?;
+            Some((path.as_str(), content))
+        })
+        .collect();
+    let mut index = CodeIndex::default();
+    for (path, content) in &sources {
+        index.add_file(path, content);
+    }
+
+    let mut gaps = Vec::new();
+    let mut report = Vec::new();
+    for (path, lines) in &files {
+        let Some(source) = sources.get(path.as_str()) else {
+            continue;
+        };
+        let source_lines: Vec<&str> = source.lines().collect();
+        let symbols: Vec<&Symbol> = index.symbols_in(path).collect();
+        // Rust unit tests live in `mod tests` next to the code
+        let test_modules: Vec<(usize, usize)> = symbols
+            .iter()
+            .filter(|s| s.kind == SymbolKind::Module && s.name.starts_with("test"))
+            .map(|s| (s.start_line, s.end_line))
+            .collect();
+        let mut file = FileCoverage {
+            path: path.clone(),
+            covered_lines: lines.values().filter(|hits| **hits > 0).count(),
+            lines: lines.len(),
+            public_functions: 0,
+            gaps: 0,
+        };
+        for symbol in symbols {
+            let in_tests = test_modules
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&symbol.start_line));
+            let line = source_lines
+                .get(symbol.start_line - 1)
+                .copied()
+                .unwrap_or("");
+            if in_tests || !is_public(symbol, line) {
+                continue;
+            }
+            let mut hits = lines
+                .range(symbol.start_line..=symbol.end_line)
+                .map(|(_, h)| h);
+            let instrumented = hits.clone().next().is_some();
+            // Declarations without a body have no instrumented lines
+            if !instrumented {
+                continue;
+            }
+            file.public_functions += 1;
+            if hits.any(|hits| *hits > 0) {
+                continue;
+            }
+            file.gaps += 1;
+            let end_line = symbol
+                .end_line
+                .min(symbol.start_line + MAX_SOURCE_LINES - 1);
+            gaps.push(Gap {
+                name: symbol.qualified_name.clone(),
+                path: path.clone(),
+                start_line: symbol.start_line,
+                end_line: symbol.end_line,
+                callers: callers(symbol, path, &sources),
+                priority: Priority::Low,
+                source: source_lines[symbol.start_line - 1..end_line.min(source_lines.len())]
+                    .join("\n"),
+            });
+        }
+        report.push(file);
+    }
+
+    for gap in &mut gaps {
+        gap.priority = if gap.callers >= 2 || gap.lines() >= 30 {
+            Priority::High
+        } else if gap.callers == 1 || gap.lines() >= 10 {
+            Priority::Medium
+        } else {
+            Priority::Low
+        };
+    }
+    gaps.sort_by(|a, b| {
+        (a.priority, b.score(), &a.path, a.start_line).cmp(&(
+            b.priority,
+            a.score(),
+            &b.path,
+            b.start_line,
+        ))
+    });
+    (gaps, report)
+}
+
+/// `path` relative to `root`, if it is a file of the project. Coverage
+/// written elsewhere, as on CI, is matched by the longest suffix that exists.
+fn project_path(root: &Path, path: &str) -> Option<String> {
+    let path = PathBuf::from(path);
+    let relative = match path.strip_prefix(root) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) if path.is_relative() => path,
+        Err(_) => {
+            let components: Vec<_> = path.components().collect();
+            (1..components.len())
+                .map(|skip| components[skip..].iter().collect::<PathBuf>())
+                .find(|relative| root.join(relative).is_file())?
+        }
+    };
+    root.join(&relative)
+        .is_file()
+        .then(|| relative.to_string_lossy().replace('\\', "/"))
+}
+
+fn is_test_file(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    path.split('/')
+        .any(|part| matches!(part, "tests" | "test" | "__tests__" | "spec"))
+        || name.starts_with("test_")
+        || name.ends_with("_test.go")
+        || name.ends_with("_test.py")
+        || name.contains(".test.")
+        || name.contains(".spec.")
+}
+
+/// Whether the function or method `symbol`, whose first line is `line`, can
+/// be called from other modules
+fn is_public(symbol: &Symbol, line: &str) -> bool {
+    if !matches!(symbol.kind, SymbolKind::Function | SymbolKind::Method) {
+        return false;
+    }
+    let line = line.trim_start();
+    let name = &symbol.name;
+    match symbol.path.rsplit('.').next().unwrap_or("") {
+        "rs" => line.starts_with("pub ") || line.starts_with("pub("),
+        "py" => !symbol
+            .qualified_name
+            .split("::")
+            .any(|part| part.starts_with('_')),
+        "go" => name.starts_with(|c: char| c.is_uppercase()),
+        _ => match symbol.kind {
+            SymbolKind::Method => !name.starts_with(['_', '#']) && !line.starts_with("private"),
+            _ => line.starts_with("export"),
+        },
+    }
+}
+
+/// Other files mentioning a call of `symbol`
+fn callers(symbol: &Symbol, path: &str, sources: &HashMap<&str, String>) -> usize {
+    let call = format!("{}(", symbol.name);
+    let owner = symbol
+        .qualified_name
+        .rsplit_once("::")
+        .map(|(owner, _)| owner);
+    sources
+        .iter()
+        .filter(|(other, content)| {
+            **other != path
+                && content.contains(&call)
+                && owner.is_none_or(|owner| content.contains(owner))
+        })
+        .count()
+}
+
+fn render(
+    coverage_path: &Path,
+    coverage: &Coverage,
+    gaps: &[Gap],
+    files: &[FileCoverage],
+) -> String {
+    let functions: usize = files.iter().map(|file| file.public_functions).sum();
+    let mut out = format!(
+        "# Coverage Gaps\n\nFrom `{}` ({}): {} of {} public functions have no coverage.\n\n",
+        coverage_path.display(),
+        coverage.format,
+        gaps.len(),
+        functions
+    );
+    out.push_str(
+        "Functions are ordered by priority: high for functions of 30 lines or more or \
+         called from two or more other files, medium for 10 lines or more or one calling \
+         file, low otherwise.\n\n",
+    );
+    if gaps.is_empty() {
+        out.push_str("Every public function runs during the tests.\n");
+    } else {
+        out.push_str("| Priority | Function | Location | Lines | Calling files |\n");
+        out.push_str("|---|---|---|---|---|\n");
+        for gap in gaps {
+            out.push_str(&format!(
+                "| {} | `{}` | `{}:{}` | {} | {} |\n",
+                gap.priority,
+                gap.name,
+                gap.path,
+                gap.start_line,
+                gap.lines(),
+                gap.callers
+            ));
+        }
+    }
+
+    out.push_str("\n## Files\n\n| File | Line coverage | Untested public functions |\n");
+    out.push_str("|---|---|---|\n");
+    let mut files: Vec<&FileCoverage> = files.iter().collect();
+    files.sort_by(|a, b| b.gaps.cmp(&a.gaps).then_with(|| a.path.cmp(&b.path)));
+    for file in files {
+        let percent = match file.lines {
+            0 => 100.0,
+            lines => file.covered_lines as f64 * 100.0 / lines as f64,
+        };
+        out.push_str(&format!(
+            "| `{}` | {:.1}% | {} of {} |\n",
+            file.path, percent, file.gaps, file.public_functions
+        ));
+    }
+    out
+}
+
+/// Write the report to the artifact directory, replacing an earlier one
+async fn write_report(artifact_dir: &Path, report: String) -> Result<()> {
+    let artifact_manager = ArtifactManager::new(artifact_dir.to_path_buf())?;
+    artifact_manager.init().await?;
+    let existing = artifact_manager
+        .list_artifacts()
+        .await
+        .into_iter()
+        .find(|artifact| {
+            artifact
+                .path
+                .file_name()
+                .is_some_and(|name| name == REPORT_NAME)
+        });
+    match existing {
+        Some(artifact) => artifact_manager.update_artifact(&artifact.id, report).await,
+        None => artifact_manager
+            .create_artifact(
+                REPORT_NAME.to_string(),
+                ArtifactType::Documentation,
+                report,
+                HashMap::new(),
+            )
+            .await
+            .map(|_| ()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untested_public_functions() {
+        let root =
+            std::env::temp_dir().join(format!("cli_engineer_coverage_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(
+            root.join("src/lib.rs"),
+            "pub fn parse(input: &str) -> usize {\n    input.len()\n}\n\n\
+             pub fn render(value: usize) -> String {\n    value.to_string()\n}\n\n\
+             fn helper() {\n    render(1);\n}\n\n\
+             #[cfg(test)]\nmod tests {\n    pub fn fixture() {\n        super::parse(\"\");\n    }\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("src/main.rs"),
+            "fn main() {\n    lib::render(2);\n}\n",
+        )
+        .unwrap();
+
+        // Absolute paths, as cargo llvm-cov writes them; files of dependencies are left out
+        let lcov = format!(
+            "SF:{lib}\nDA:1,3\nDA:2,3\nDA:5,0\nDA:6,0\nDA:9,0\nDA:10,0\nDA:15,0\nDA:16,0\n\
+             end_of_record\nSF:{main}\nDA:1,1\nDA:2,1\nend_of_record\n\
+             SF:/home/me/.cargo/registry/src/serde/lib.rs\nDA:1,0\nend_of_record\n",
+            lib = root.join("src/lib.rs").display(),
+            main = root.join("src/main.rs").display(),
+        );
+        let coverage = Coverage::parse(&lcov).unwrap();
+        assert_eq!(coverage.format, CoverageFormat::Lcov);
+        let (gaps, files) = find_gaps(&root, &coverage);
+        // `helper` is private and `fixture` is test code
+        let names: Vec<&str> = gaps.iter().map(|gap| gap.name.as_str()).collect();
+        assert_eq!(names, ["render"]);
+        assert_eq!(
+            (gaps[0].path.as_str(), gaps[0].start_line),
+            ("src/lib.rs", 5)
+        );
+        assert_eq!(gaps[0].callers, 1);
+        assert_eq!(gaps[0].priority, Priority::Medium);
+
+        let report = render(Path::new("lcov.info"), &coverage, &gaps, &files);
+        assert!(report.contains("1 of 2 public functions have no coverage"));
+        assert!(report.contains("| Medium | `render` | `src/lib.rs:5` | 3 | 1 |"));
+        assert!(report.contains("| `src/lib.rs` | 25.0% | 1 of 2 |"));
+        let prompt = tests_prompt(&gaps, "Use rstest.");
+        assert!(prompt.contains("Use rstest."));
+        assert!(prompt.contains("render (src/lib.rs:5-7):\n```\npub fn render"));
+
+        let python =
+            r#"{"files": {"app/views.py": {"executed_lines": [1, 2], "missing_lines": [5]}}}"#;
+        let coverage = Coverage::parse(python).unwrap();
+        assert_eq!(coverage.format, CoverageFormat::CoveragePy);
+        assert_eq!(coverage.files["app/views.py"].get(&5), Some(&0));
+        let llvm = r#"{"type": "llvm.coverage.json.export", "data": [{"files": [
+            {"filename": "src/lib.rs", "segments": [[1, 1, 3, true, true, false], [5, 1, 0, true, true, false],
+             [6, 1, 9, true, false, true]]}]}]}"#;
+        let coverage = Coverage::parse(llvm).unwrap();
+        assert_eq!(coverage.format, CoverageFormat::LlvmCov);
+        let lines = &coverage.files["src/lib.rs"];
+        assert_eq!((lines[&1], lines[&5], lines.get(&6)), (3, 0, None));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
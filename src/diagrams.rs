@@ -0,0 +1,633 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::artifact::{ArtifactManager, ArtifactType};
+use crate::code_index::{CodeIndex, SymbolKind};
+
+/// Document the diagrams are written to
+pub const DIAGRAMS_NAME: &str = "docs/architecture.md";
+const START_MARKER: &str = "<!-- cli_engineer:diagrams:start -->";
+const END_MARKER: &str = "<!-- cli_engineer:diagrams:end -->";
+/// Above this many modules the graph shows directories instead
+const MAX_MODULES: usize = 60;
+/// Types named per module in the module table
+const MAX_TYPES: usize = 8;
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs"];
+
+/// Modules of the indexed codebase and the imports between them. A module is
+/// a source file, or a directory for Go, whose packages span several files.
+#[derive(Debug, Default)]
+pub struct ModuleGraph {
+    /// Types and function count of each module
+    modules: BTreeMap<String, Definitions>,
+    edges: BTreeSet<(String, String)>,
+}
+
+#[derive(Debug, Default)]
+struct Definitions {
+    types: Vec<String>,
+    functions: usize,
+}
+
+impl ModuleGraph {
+    /// Resolve the imports recorded in `index` to the indexed files they name.
+    /// Imports of external packages resolve to nothing and are left out.
+    pub fn build(index: &CodeIndex) -> Self {
+        let mut paths: Vec<&str> = index.paths().collect();
+        paths.sort();
+        let resolver = Resolver::new(&paths);
+
+        let mut graph = Self::default();
+        for path in paths {
+            let module = module_of(path);
+            let definitions = graph.modules.entry(module.clone()).or_default();
+            for symbol in index.symbols_in(path) {
+                match symbol.kind {
+                    SymbolKind::Struct
+                    | SymbolKind::Enum
+                    | SymbolKind::Trait
+                    | SymbolKind::Class
+                    | SymbolKind::Interface
+                    | SymbolKind::Type => definitions.types.push(symbol.qualified_name.clone()),
+                    SymbolKind::Function | SymbolKind::Method => definitions.functions += 1,
+                    SymbolKind::Module => {}
+                    SymbolKind::Import => {
+                        for target in resolver.resolve(path, &symbol.name) {
+                            if target != module {
+                                graph.edges.insert((module.clone(), target));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    /// Markdown section with a Mermaid graph of the dependencies between
+    /// modules and a table of what each module defines
+    pub fn render(&self) -> String {
+        let mut out = String::from("## Module dependencies\n\n");
+        out.push_str(
+            "Generated from the import statements of the source files; \
+             an arrow points from a module to a module it imports.\n\n",
+        );
+        let directories: BTreeSet<String> = self
+            .modules
+            .keys()
+            .map(|module| parent_dir(module))
+            .collect();
+        // A single directory is drawn module by module however large it is
+        if self.modules.len() > MAX_MODULES && directories.len() > 1 {
+            out.push_str(&format!(
+                "The {} modules are grouped by directory.\n\n",
+                self.modules.len()
+            ));
+            let edges = self
+                .edges
+                .iter()
+                .map(|(from, to)| (parent_dir(from), parent_dir(to)))
+                .filter(|(from, to)| from != to)
+                .collect();
+            out.push_str(&mermaid(&directories, &edges, false));
+        } else {
+            let modules = self.modules.keys().cloned().collect();
+            out.push_str(&mermaid(&modules, &self.edges, true));
+        }
+
+        out.push_str("\n## Modules\n\n| Module | Types | Functions |\n|---|---|---|\n");
+        for (module, definitions) in &self.modules {
+            let mut types: Vec<String> = definitions
+                .types
+                .iter()
+                .take(MAX_TYPES)
+                .map(|name| format!("`{}`", name))
+                .collect();
+            if definitions.types.len() > MAX_TYPES {
+                types.push(format!("+{} more", definitions.types.len() - MAX_TYPES));
+            }
+            out.push_str(&format!(
+                "| `{}` | {} | {} |\n",
+                module,
+                types.join(", "),
+                definitions.functions
+            ));
+        }
+        out
+    }
+}
+
+/// Context message telling the model the diagrams exist, so the
+/// documentation it writes follows them instead of imagining the architecture
+pub fn diagrams_context(section: &str) -> String {
+    format!(
+        "The module structure below was generated from the code and will be written to {} \
+         when the run ends, keeping whatever else you write there. Describe the architecture \
+         consistently with it and link to {} instead of drawing your own diagrams.\n\n{}",
+        DIAGRAMS_NAME, DIAGRAMS_NAME, section
+    )
+}
+
+/// Write `section` into `docs/architecture.md`, replacing the diagrams of an
+/// earlier run and keeping the documentation the model wrote around them
+pub async fn write_diagrams(artifact_manager: &ArtifactManager, section: &str) -> Result<()> {
+    let existing = artifact_manager
+        .list_artifacts()
+        .await
+        .into_iter()
+        .find(|artifact| artifact.path.ends_with(DIAGRAMS_NAME));
+    let current = std::fs::read_to_string(artifact_manager.artifact_dir().join(DIAGRAMS_NAME))
+        .unwrap_or_default();
+    let content = embed(&current, section);
+    match existing {
+        Some(artifact) => {
+            artifact_manager
+                .update_artifact(&artifact.id, content)
+                .await
+        }
+        None => artifact_manager
+            .create_artifact(
+                DIAGRAMS_NAME.to_string(),
+                ArtifactType::Documentation,
+                content,
+                HashMap::new(),
+            )
+            .await
+            .map(|_| ()),
+    }
+}
+
+/// `document` with `section` between the diagram markers: in place of the
+/// marked block, after the title, or as a new document
+fn embed(document: &str, section: &str) -> String {
+    let block = format!("{}\n{}{}", START_MARKER, section, END_MARKER);
+    if let Some((before, rest)) = document.split_once(START_MARKER)
+        && let Some((_, after)) = rest.split_once(END_MARKER)
+    {
+        return format!("{}{}{}", before, block, after);
+    }
+    if document.trim().is_empty() {
+        return format!("# Architecture\n\n{}\n", block);
+    }
+    match document.split_once('\n') {
+        Some((title, rest)) if title.starts_with("# ") => {
+            format!("{}\n\n{}\n\n{}\n", title, block, rest.trim())
+        }
+        _ => format!("{}\n\n{}\n", block, document.trim_end()),
+    }
+}
+
+/// Mermaid flowchart of `nodes` and `edges`, with nodes in a subgraph per
+/// directory when `grouped`
+fn mermaid(nodes: &BTreeSet<String>, edges: &BTreeSet<(String, String)>, grouped: bool) -> String {
+    let ids: HashMap<&str, String> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.as_str(), format!("m{}", i)))
+        .collect();
+    let mut out = String::from("```mermaid\ngraph LR\n");
+    if grouped {
+        let mut directories: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+        for node in nodes {
+            directories.entry(parent_dir(node)).or_default().push(node);
+        }
+        for (i, (directory, members)) in directories.iter().enumerate() {
+            let indent = if directory.is_empty() { "  " } else { "    " };
+            if !directory.is_empty() {
+                out.push_str(&format!("  subgraph d{}[\"{}\"]\n", i, label(directory)));
+            }
+            for node in members {
+                let name = node.rsplit('/').next().unwrap_or(node);
+                out.push_str(&format!(
+                    "{}{}[\"{}\"]\n",
+                    indent,
+                    ids[node.as_str()],
+                    label(name)
+                ));
+            }
+            if !directory.is_empty() {
+                out.push_str("  end\n");
+            }
+        }
+    } else {
+        for node in nodes {
+            let name = if node.is_empty() { "." } else { node };
+            out.push_str(&format!("  {}[\"{}\"]\n", ids[node.as_str()], label(name)));
+        }
+    }
+    for (from, to) in edges {
+        if let (Some(from), Some(to)) = (ids.get(from.as_str()), ids.get(to.as_str())) {
+            out.push_str(&format!("  {} --> {}\n", from, to));
+        }
+    }
+    out.push_str("```\n");
+    out
+}
+
+fn label(text: &str) -> String {
+    text.replace('"', "#quot;")
+}
+
+fn parent_dir(path: &str) -> String {
+    path.rsplit_once('/')
+        .map_or(String::new(), |(dir, _)| dir.to_string())
+}
+
+fn module_of(path: &str) -> String {
+    if path.ends_with(".go") {
+        let dir = parent_dir(path);
+        if dir.is_empty() { ".".to_string() } else { dir }
+    } else {
+        path.to_string()
+    }
+}
+
+fn extension(path: &str) -> &str {
+    Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+}
+
+/// Maps import statements to the indexed files they name
+struct Resolver<'a> {
+    paths: BTreeSet<&'a str>,
+    /// Rust modules keyed by crate source directory and module path
+    rust: HashMap<(String, String), &'a str>,
+    /// Python files keyed by dotted module name
+    python: HashMap<String, &'a str>,
+    go_packages: BTreeSet<String>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(paths: &[&'a str]) -> Self {
+        let mut resolver = Self {
+            paths: paths.iter().copied().collect(),
+            rust: HashMap::new(),
+            python: HashMap::new(),
+            go_packages: BTreeSet::new(),
+        };
+        for &path in paths {
+            match extension(path) {
+                "rs" => {
+                    let (root, segments) = rust_module(path);
+                    resolver.rust.insert((root, segments.join("::")), path);
+                }
+                "py" => {
+                    resolver.python.insert(python_module(path), path);
+                }
+                "go" => {
+                    resolver.go_packages.insert(module_of(path));
+                }
+                _ => {}
+            }
+        }
+        resolver
+    }
+
+    /// Modules imported by `import`, a statement of the file `path`
+    fn resolve(&self, path: &str, import: &str) -> Vec<String> {
+        match extension(path) {
+            "rs" => self.resolve_rust(path, import),
+            "py" => self.resolve_python(path, import),
+            "go" => self.resolve_go(import),
+            extension if JS_EXTENSIONS.contains(&extension) => self.resolve_js(path, import),
+            _ => Vec::new(),
+        }
+    }
+
+    fn resolve_rust(&self, path: &str, import: &str) -> Vec<String> {
+        let (root, module) = rust_module(path);
+        let current: Vec<&str> = module.iter().map(String::as_str).collect();
+        let statement = import.split_whitespace().collect::<Vec<_>>().join(" ");
+        let Some((_, tree)) = statement.split_once("use ") else {
+            return Vec::new();
+        };
+        // Aliases are marked with `@` so the tree can be read without spaces
+        let tree = tree.replace(" as ", "@").replace(' ', "");
+        let mut targets = Vec::new();
+        for used in expand_use_tree(tree.trim_end_matches(';')) {
+            let segments: Vec<&str> = used.split("::").filter(|s| !s.is_empty()).collect();
+            let candidates = match segments.first() {
+                Some(&"crate") => vec![segments[1..].to_vec()],
+                Some(&"self") => vec![[current.as_slice(), &segments[1..]].concat()],
+                Some(&"super") => {
+                    let supers = segments.iter().take_while(|s| **s == "super").count();
+                    let base = &current[..current.len().saturating_sub(supers)];
+                    vec![[base, &segments[supers..]].concat()]
+                }
+                // A name of the current module, or of the crate root in main.rs
+                _ => vec![[current.as_slice(), &segments].concat(), segments.clone()],
+            };
+            let found = candidates.iter().find_map(|segments| {
+                (1..=segments.len()).rev().find_map(|len| {
+                    self.rust
+                        .get(&(root.clone(), segments[..len].join("::")))
+                        .copied()
+                })
+            });
+            targets.extend(found.map(str::to_string));
+        }
+        targets
+    }
+
+    fn resolve_python(&self, path: &str, import: &str) -> Vec<String> {
+        let import = import.split_whitespace().collect::<Vec<_>>().join(" ");
+        let names: Vec<Vec<String>> = if let Some(rest) = import.strip_prefix("from ") {
+            let Some((from, imported)) = rest.split_once(" import ") else {
+                return Vec::new();
+            };
+            let from = if from.starts_with('.') {
+                let dots = from.chars().take_while(|c| *c == '.').count();
+                let mut package: Vec<&str> = python_package(path);
+                package.truncate(package.len().saturating_sub(dots - 1));
+                let rest = &from[dots..];
+                package.extend(rest.split('.').filter(|s| !s.is_empty()));
+                package.join(".")
+            } else {
+                from.to_string()
+            };
+            // Imported names are submodules or names defined in the module
+            imported
+                .trim_matches(|c| c == '(' || c == ')' || c == ' ')
+                .split(',')
+                .filter_map(|name| name.split_whitespace().next())
+                .map(|name| vec![format!("{}.{}", from, name), from.clone()])
+                .collect()
+        } else if let Some(rest) = import.strip_prefix("import ") {
+            // `import a.b` also imports `a`, but depends on `a.b`
+            rest.split(',')
+                .filter_map(|name| name.split_whitespace().next())
+                .map(|name| {
+                    let mut candidates = vec![name.to_string()];
+                    let mut name = name;
+                    while let Some((parent, _)) = name.rsplit_once('.') {
+                        candidates.push(parent.to_string());
+                        name = parent;
+                    }
+                    candidates
+                })
+                .collect()
+        } else {
+            return Vec::new();
+        };
+        let mut targets: Vec<String> = Vec::new();
+        for candidates in names {
+            if let Some(found) = candidates.iter().find_map(|name| self.python_file(name))
+                && !targets.contains(&found)
+            {
+                targets.push(found);
+            }
+        }
+        targets
+    }
+
+    /// The file of module `name`, also found under a source directory such as `src/`
+    fn python_file(&self, name: &str) -> Option<String> {
+        if name.is_empty() {
+            return None;
+        }
+        if let Some(path) = self.python.get(name) {
+            return Some(path.to_string());
+        }
+        let suffix = format!(".{}", name);
+        let mut matches = self
+            .python
+            .iter()
+            .filter(|(module, _)| module.ends_with(&suffix));
+        let (_, path) = matches.next()?;
+        // An ambiguous name is left unresolved
+        matches.next().is_none().then(|| path.to_string())
+    }
+
+    fn resolve_js(&self, path: &str, import: &str) -> Vec<String> {
+        let Some(specifier) = quoted(import).pop() else {
+            return Vec::new();
+        };
+        if !specifier.starts_with('.') {
+            return Vec::new();
+        }
+        let Some(base) = normalize(
+            Path::new(path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(&specifier),
+        ) else {
+            return Vec::new();
+        };
+        let mut candidates = vec![base.clone()];
+        for extension in JS_EXTENSIONS {
+            candidates.push(format!("{}.{}", base, extension));
+        }
+        for extension in JS_EXTENSIONS {
+            candidates.push(format!("{}/index.{}", base, extension));
+        }
+        candidates
+            .into_iter()
+            .find(|candidate| self.paths.contains(candidate.as_str()))
+            .into_iter()
+            .collect()
+    }
+
+    fn resolve_go(&self, import: &str) -> Vec<String> {
+        quoted(import)
+            .into_iter()
+            .filter_map(|imported| {
+                self.go_packages
+                    .iter()
+                    .filter(|package| {
+                        imported == **package || imported.ends_with(&format!("/{}", package))
+                    })
+                    .max_by_key(|package| package.len())
+                    .cloned()
+            })
+            .collect()
+    }
+}
+
+/// Source directory of the crate `path` belongs to, and its module path
+fn rust_module(path: &str) -> (String, Vec<String>) {
+    let without_extension = path.trim_end_matches(".rs");
+    let parts: Vec<&str> = without_extension.split('/').collect();
+    let (root, mut segments): (String, Vec<String>) =
+        match parts.iter().position(|part| *part == "src") {
+            Some(src) => (
+                parts[..=src].join("/"),
+                parts[src + 1..].iter().map(|s| s.to_string()).collect(),
+            ),
+            None => (String::new(), parts.iter().map(|s| s.to_string()).collect()),
+        };
+    if segments.last().is_some_and(|last| last == "mod") {
+        segments.pop();
+    }
+    if segments.len() == 1 && (segments[0] == "main" || segments[0] == "lib") {
+        segments.clear();
+    }
+    (root, segments)
+}
+
+fn python_module(path: &str) -> String {
+    let module = path.trim_end_matches(".py").replace('/', ".");
+    module
+        .strip_suffix(".__init__")
+        .map_or(module.clone(), str::to_string)
+}
+
+/// Package a relative import in `path` starts from
+fn python_package(path: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = path.split('/').collect();
+    parts.pop();
+    parts
+}
+
+/// Paths named by a Rust use tree: `a::{b, c::{d, e}}` gives `a::b`, `a::c::d`, `a::c::e`
+fn expand_use_tree(tree: &str) -> Vec<String> {
+    let Some(open) = tree.find('{') else {
+        let path = tree.split('@').next().unwrap_or(tree);
+        return vec![path.trim_end_matches("::*").to_string()];
+    };
+    let prefix = &tree[..open];
+    let inner = tree[open + 1..]
+        .strip_suffix('}')
+        .unwrap_or(&tree[open + 1..]);
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&inner[start..]);
+    items
+        .into_iter()
+        .filter(|item| !item.is_empty())
+        .flat_map(|item| {
+            let item = if item == "self" { "" } else { item };
+            expand_use_tree(&format!("{}{}", prefix, item))
+        })
+        .map(|path| path.trim_end_matches("::").to_string())
+        .collect()
+}
+
+/// Strings quoted with `'` or `"` in `text`
+fn quoted(text: &str) -> Vec<String> {
+    let mut strings = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['"', '\'']) {
+        let quote = rest[start..].chars().next().unwrap_or('"');
+        let after = &rest[start + 1..];
+        let Some(end) = after.find(quote) else {
+            break;
+        };
+        strings.push(after[..end].to_string());
+        rest = &after[end + 1..];
+    }
+    strings
+}
+
+/// `path` with `.` and `..` resolved, or None when it leaves the project
+fn normalize(path: PathBuf) -> Option<String> {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop()?;
+            }
+            _ => {}
+        }
+    }
+    Some(parts.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_graph_from_imports() {
+        let files: Vec<(String, String)> = [
+            (
+                "src/main.rs",
+                "mod config;\nuse config::Config;\nuse std::sync::Arc;\nfn main() {}\n",
+            ),
+            (
+                "src/config.rs",
+                "use crate::{llm::{Client, Model as M}, util::*};\npub struct Config;\n",
+            ),
+            (
+                "src/llm/mod.rs",
+                "use super::config::Config;\npub trait Client {}\n",
+            ),
+            ("src/util.rs", "use serde::Deserialize;\npub fn slug() {}\n"),
+            ("app/models.py", "class User:\n    pass\n"),
+            (
+                "app/views.py",
+                "from . import models\nfrom .util import slug\nimport os\n",
+            ),
+            ("app/util.py", "def slug(text):\n    return text\n"),
+            (
+                "web/src/app.ts",
+                "import { api } from './api';\nimport React from 'react';\n",
+            ),
+            ("web/src/api/index.ts", "export const api = 1;\n"),
+            (
+                "cmd/main.go",
+                "package main\n\nimport (\n\t\"fmt\"\n\t\"example.com/shop/store\"\n)\n",
+            ),
+            ("store/store.go", "package store\n\ntype Store struct{}\n"),
+        ]
+        .iter()
+        .map(|(path, content)| (path.to_string(), content.to_string()))
+        .collect();
+        let graph = ModuleGraph::build(&CodeIndex::build(&files));
+
+        let edge = |from: &str, to: &str| (from.to_string(), to.to_string());
+        let expected: BTreeSet<_> = [
+            edge("src/main.rs", "src/config.rs"),
+            edge("src/config.rs", "src/llm/mod.rs"),
+            edge("src/config.rs", "src/util.rs"),
+            edge("src/llm/mod.rs", "src/config.rs"),
+            edge("app/views.py", "app/models.py"),
+            edge("app/views.py", "app/util.py"),
+            edge("web/src/app.ts", "web/src/api/index.ts"),
+            edge("cmd", "store"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(graph.edges, expected);
+
+        let section = graph.render();
+        assert!(section.starts_with("## Module dependencies"));
+        assert!(section.contains("```mermaid\ngraph LR\n"));
+        assert!(section.contains("subgraph d"));
+        assert!(section.contains("| `src/config.rs` | `Config` | 0 |"));
+
+        // The model's documentation is kept and the diagrams of a rerun replace the old ones
+        let document = embed(
+            "# Architecture\n\nThe CLI is built around an agentic loop.\n",
+            "old\n",
+        );
+        assert!(
+            document.starts_with("# Architecture\n\n<!-- cli_engineer:diagrams:start -->\nold\n")
+        );
+        let document = embed(&document, "new\n");
+        assert!(document.contains("new\n<!-- cli_engineer:diagrams:end -->"));
+        assert!(!document.contains("old"));
+        assert!(document.ends_with("agentic loop.\n"));
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::{Component, Path, PathBuf};
+
+use crate::reviewer::{Issue, IssueCategory, IssueSeverity};
+
+/// A relative link found in a markdown document
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarkdownLink {
+    pub line: usize,
+    pub target: String,
+}
+
+/// Extract relative file links from markdown: inline `[text](target)` links,
+/// images, and `[ref]: target` definitions. External URLs, pure anchors and
+/// links inside fenced code blocks are skipped.
+pub fn extract_relative_links(content: &str) -> Vec<MarkdownLink> {
+    let mut links = Vec::new();
+    let mut in_fence = false;
+
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        let mut targets = Vec::new();
+
+        // Reference definitions: [id]: target "title"
+        if trimmed.starts_with('[')
+            && let Some(end) = trimmed.find("]:")
+        {
+            if let Some(target) = trimmed[end + 2..].split_whitespace().next() {
+                targets.push(target.to_string());
+            }
+        } else {
+            let mut rest = line;
+            while let Some(start) = rest.find("](") {
+                let after = &rest[start + 2..];
+                let Some(end) = after.find(')') else {
+                    break;
+                };
+                // Drop an optional title: [text](target "title")
+                if let Some(target) = after[..end].split_whitespace().next() {
+                    targets.push(target.to_string());
+                }
+                rest = &after[end + 1..];
+            }
+        }
+
+        for target in targets {
+            if let Some(target) = normalize_target(&target) {
+                links.push(MarkdownLink {
+                    line: index + 1,
+                    target,
+                });
+            }
+        }
+    }
+
+    links
+}
+
+/// Strip angle brackets, anchors and queries; return None for non-file links
+fn normalize_target(target: &str) -> Option<String> {
+    let target = target.trim_start_matches('<').trim_end_matches('>');
+    if target.is_empty() || target.starts_with('#') || target.contains("://") {
+        return None;
+    }
+    if let Some((scheme, _)) = target.split_once(':')
+        && !scheme.contains('/')
+        && !scheme.contains('.')
+    {
+        // mailto:, tel:, data: and similar
+        return None;
+    }
+
+    let path = target.split(['#', '?']).next().unwrap_or_default();
+    if path.is_empty() {
+        None
+    } else {
+        Some(path.to_string())
+    }
+}
+
+/// Lexically resolve `..` and `.` components without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Check the relative links of a markdown artifact. A link is valid if its target
+/// exists either among the artifacts or at the same relative position in the
+/// workspace, so generated docs may link to source files of the project.
+pub fn check_markdown_links(
+    artifact_path: &Path,
+    content: &str,
+    artifact_root: &Path,
+    workspace_root: &Path,
+) -> Vec<Issue> {
+    let relative = artifact_path
+        .strip_prefix(artifact_root)
+        .unwrap_or(artifact_path);
+    let relative_dir = relative.parent().unwrap_or(Path::new(""));
+    let display_name = relative.to_string_lossy();
+
+    extract_relative_links(content)
+        .into_iter()
+        .filter(|link| {
+            let in_artifacts = normalize(&artifact_root.join(relative_dir).join(&link.target));
+            let in_workspace = normalize(&workspace_root.join(relative_dir).join(&link.target));
+            !in_artifacts.exists() && !in_workspace.exists()
+        })
+        .map(|link| Issue {
+            severity: IssueSeverity::Major,
+            category: IssueCategory::Documentation,
+            description: format!(
+                "Broken link: `{}` links to `{}`, which does not exist",
+                display_name, link.target
+            ),
+            location: Some(format!("{}:{}", display_name, link.line)),
+            suggestion: Some(format!(
+                "Create `{}` or correct the link",
+                normalize(&relative_dir.join(&link.target)).display()
+            )),
+            patch: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_relative_links() {
+        let content = "# Docs\n\
+                       See [setup](setup.md#install) and ![diagram](<img/arch.png> \"Arch\").\n\
+                       External [site](https://example.com), [anchor](#top), [mail](mailto:a@b.c).\n\
+                       ```\n[not a link](code.md)\n```\n\
+                       [ref]: ../src/main.rs\n";
+
+        let links = extract_relative_links(content);
+        let targets: Vec<(usize, &str)> =
+            links.iter().map(|l| (l.line, l.target.as_str())).collect();
+        assert_eq!(
+            targets,
+            vec![(2, "setup.md"), (2, "img/arch.png"), (7, "../src/main.rs")]
+        );
+    }
+}
@@ -1,9 +1,12 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::fmt;
 use std::sync::Arc;
 use tokio::sync::{RwLock, broadcast};
 
+use crate::config::BudgetConfig;
+
 /// Events that can be emitted by components
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
@@ -30,6 +33,22 @@ pub enum Event {
         error: String,
     },
 
+    // Plan step events
+    /// The executor began step `number` of `total` in the current plan
+    StepStarted {
+        step_id: String,
+        number: usize,
+        total: usize,
+        description: String,
+    },
+    /// A plan step ended, after `duration_ms`
+    StepFinished {
+        step_id: String,
+        status: StepStatus,
+        duration_ms: u64,
+        error: Option<String>,
+    },
+
     // Artifact events
     ArtifactCreated {
         name: String,
@@ -39,6 +58,36 @@ pub enum Event {
     ArtifactUpdated {
         name: String,
         path: String,
+        /// Version number of the new content
+        version: usize,
+    },
+    ArtifactDeleted {
+        name: String,
+        path: String,
+    },
+    ArtifactRenamed {
+        from: String,
+        to: String,
+        path: String,
+    },
+    ArtifactConflict {
+        name: String,
+        path: String,
+        resolution: String,
+    },
+    /// An artifact's file was edited outside cli_engineer; the edit was
+    /// recorded as a new version
+    ArtifactModifiedExternally {
+        name: String,
+        path: String,
+        version: usize,
+    },
+    /// What an artifact write changed, as a unified diff
+    ArtifactDiff {
+        name: String,
+        /// What the artifact was compared with, e.g. "workspace" or "v2"
+        against: String,
+        diff: String,
     },
 
     // Execution events
@@ -73,6 +122,7 @@ pub enum Event {
         id: String,
         usage_percentage: f32,
         total_tokens: usize,
+        breakdown: ContextBreakdown,
     },
     ContextCompressed {
         id: String,
@@ -97,15 +147,37 @@ pub enum Event {
         provider: String,
         model: String,
     },
+    /// Output streamed so far by the call in progress, sent at most a few
+    /// times a second
+    APICallProgress {
+        provider: String,
+        output_tokens: usize,
+        elapsed_ms: u64,
+    },
     APICallCompleted {
         provider: String,
         tokens: usize,
         cost: f32,
+        /// Time from sending the request to the end of the response
+        #[serde(default)]
+        duration_ms: u64,
+        #[serde(default)]
+        output_tokens: usize,
+        /// Time to the first streamed output, for streaming providers
+        #[serde(default)]
+        first_token_ms: Option<u64>,
     },
     APIError {
         provider: String,
         error: String,
     },
+    /// The run's cost or token usage reached `pct` percent of its budget
+    BudgetThreshold {
+        pct: u8,
+        kind: BudgetKind,
+        used: f64,
+        limit: f64,
+    },
 
     // System events
     ConfigLoaded {
@@ -126,10 +198,108 @@ pub enum Event {
     },
 }
 
+/// Tokens in a context window by kind of content
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextBreakdown {
+    /// Scanned files, outlines and tool findings
+    pub codebase: usize,
+    /// Compression summaries of earlier conversation
+    pub summaries: usize,
+    /// Task input, step prompts and model responses
+    pub conversation: usize,
+    /// Review prompts and verdicts
+    pub plan_review: usize,
+    /// Other system messages, such as the interpreted task
+    pub system: usize,
+}
+
+impl fmt::Display for ContextBreakdown {
+    /// Non-empty parts, largest first: `codebase 41.2k, conversation 8.0k`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = [
+            ("codebase", self.codebase),
+            ("conversation", self.conversation),
+            ("review", self.plan_review),
+            ("summaries", self.summaries),
+            ("system", self.system),
+        ];
+        parts.sort_by_key(|(_, tokens)| std::cmp::Reverse(*tokens));
+        let parts: Vec<String> = parts
+            .iter()
+            .filter(|(_, tokens)| *tokens > 0)
+            .map(|(name, tokens)| format!("{} {:.1}k", name, *tokens as f32 / 1000.0))
+            .collect();
+        if parts.is_empty() {
+            write!(f, "empty")
+        } else {
+            write!(f, "{}", parts.join(", "))
+        }
+    }
+}
+
+/// Budget a `BudgetThreshold` event refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BudgetKind {
+    Cost,
+    Tokens,
+}
+
+impl BudgetKind {
+    /// An amount of this budget: `$4.00` or `8000 tokens`
+    pub fn format_amount(&self, amount: f64) -> String {
+        match self {
+            BudgetKind::Cost => format!("${:.2}", amount),
+            BudgetKind::Tokens => format!("{:.0} tokens", amount),
+        }
+    }
+}
+
+impl fmt::Display for BudgetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BudgetKind::Cost => write!(f, "cost"),
+            BudgetKind::Tokens => write!(f, "token"),
+        }
+    }
+}
+
+/// How a plan step ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StepStatus {
+    Done,
+    Failed,
+    Skipped,
+}
+
+impl fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepStatus::Done => write!(f, "done"),
+            StepStatus::Failed => write!(f, "failed"),
+            StepStatus::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// Receives every event as it is emitted. Unlike broadcast subscribers, sinks
+/// are called in order before `emit` returns and never miss events.
+pub trait EventSink: Send + Sync {
+    fn handle(&self, event: &Event);
+}
+
 /// Event bus for component communication
 pub struct EventBus {
     sender: broadcast::Sender<Event>,
     metrics: Arc<RwLock<Metrics>>,
+    sinks: std::sync::RwLock<Vec<Arc<dyn EventSink>>>,
+    budget: std::sync::Mutex<BudgetState>,
+}
+
+/// Budgets of the run and the thresholds already alerted on
+#[derive(Default)]
+struct BudgetState {
+    config: BudgetConfig,
+    alerted: Vec<(BudgetKind, u8)>,
 }
 
 /// Accumulated metrics from events
@@ -142,6 +312,9 @@ pub struct Metrics {
     pub tasks_completed: usize,
     pub tasks_failed: usize,
     pub current_context_usage: f32,
+    pub context_breakdown: ContextBreakdown,
+    /// Error of the last API call, if it failed
+    pub last_api_error: Option<String>,
 }
 
 impl EventBus {
@@ -151,27 +324,50 @@ impl EventBus {
         Self {
             sender,
             metrics: Arc::new(RwLock::new(Metrics::default())),
+            sinks: std::sync::RwLock::new(Vec::new()),
+            budget: std::sync::Mutex::new(BudgetState::default()),
         }
     }
 
+    /// Set the budgets checked after each API call
+    pub fn set_budget(&self, mut config: BudgetConfig) {
+        config.alert_thresholds.sort_unstable();
+        config.alert_thresholds.dedup();
+        *self.budget.lock().unwrap() = BudgetState {
+            config,
+            alerted: Vec::new(),
+        };
+    }
+
+    /// Register a sink that receives every event emitted from now on
+    pub fn add_sink(&self, sink: Arc<dyn EventSink>) {
+        self.sinks.write().unwrap().push(sink);
+    }
+
     /// Subscribe to events
     pub fn subscribe(&self) -> broadcast::Receiver<Event> {
         self.sender.subscribe()
     }
 
-    /// Emit an event to all subscribers
+    /// Emit an event to all subscribers, followed by any budget alerts it triggers
     pub async fn emit(&self, event: Event) -> Result<()> {
         // Update metrics based on event
-        self.update_metrics(&event).await;
-
-        // Send event to subscribers
-        match self.sender.send(event) {
-            Ok(_) => Ok(()),
-            Err(_) => {
-                // No receivers, but that's okay
-                Ok(())
-            }
+        let alerts = self.update_metrics(&event).await;
+
+        self.dispatch(event);
+        for alert in alerts {
+            self.dispatch(alert);
         }
+        Ok(())
+    }
+
+    fn dispatch(&self, event: Event) {
+        for sink in self.sinks.read().unwrap().iter() {
+            sink.handle(&event);
+        }
+
+        // Send event to subscribers; having no receivers is okay
+        let _ = self.sender.send(event);
     }
 
     /// Get current metrics
@@ -179,8 +375,8 @@ impl EventBus {
         self.metrics.read().await.clone()
     }
 
-    /// Update metrics based on event
-    async fn update_metrics(&self, event: &Event) {
+    /// Update metrics based on event, returning the budget alerts it triggers
+    async fn update_metrics(&self, event: &Event) -> Vec<Event> {
         let mut metrics = self.metrics.write().await;
 
         match event {
@@ -188,6 +384,10 @@ impl EventBus {
                 metrics.total_api_calls += 1;
                 metrics.total_tokens += tokens;
                 metrics.total_cost += cost;
+                metrics.last_api_error = None;
+            }
+            Event::APIError { error, .. } => {
+                metrics.last_api_error = Some(error.clone());
             }
             Event::ArtifactCreated { .. } => {
                 metrics.artifacts_created += 1;
@@ -201,8 +401,59 @@ impl EventBus {
             Event::ContextUsage { percentage, .. } => {
                 metrics.current_context_usage = *percentage;
             }
+            Event::ContextUsageChanged {
+                usage_percentage,
+                breakdown,
+                ..
+            } => {
+                metrics.current_context_usage = *usage_percentage;
+                metrics.context_breakdown = breakdown.clone();
+            }
             _ => {}
         }
+
+        if matches!(event, Event::APICallCompleted { .. }) {
+            self.budget_alerts(&metrics)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// `BudgetThreshold` events for thresholds newly reached by `metrics`
+    fn budget_alerts(&self, metrics: &Metrics) -> Vec<Event> {
+        let mut budget = self.budget.lock().unwrap();
+        let BudgetState { config, alerted } = &mut *budget;
+        let usage = [
+            (
+                BudgetKind::Cost,
+                metrics.total_cost as f64,
+                config.max_cost.map(f64::from),
+            ),
+            (
+                BudgetKind::Tokens,
+                metrics.total_tokens as f64,
+                config.max_tokens.map(|t| t as f64),
+            ),
+        ];
+
+        let mut alerts = Vec::new();
+        for (kind, used, limit) in usage {
+            let Some(limit) = limit.filter(|l| *l > 0.0) else {
+                continue;
+            };
+            for &pct in &config.alert_thresholds {
+                if used * 100.0 >= limit * pct as f64 && !alerted.contains(&(kind, pct)) {
+                    alerted.push((kind, pct));
+                    alerts.push(Event::BudgetThreshold {
+                        pct,
+                        kind,
+                        used,
+                        limit,
+                    });
+                }
+            }
+        }
+        alerts
     }
 }
 
@@ -269,6 +520,9 @@ mod tests {
             provider: "openai".to_string(),
             tokens: 100,
             cost: 0.01,
+            duration_ms: 0,
+            output_tokens: 0,
+            first_token_ms: None,
         })
         .await
         .unwrap();
@@ -278,4 +532,44 @@ mod tests {
         assert_eq!(metrics.total_tokens, 100);
         assert_eq!(metrics.total_cost, 0.01);
     }
+
+    #[tokio::test]
+    async fn test_budget_thresholds() {
+        let bus = EventBus::new(100);
+        bus.set_budget(BudgetConfig {
+            max_cost: Some(1.0),
+            max_tokens: Some(1000),
+            alert_thresholds: vec![100, 50, 80],
+        });
+        let mut receiver = bus.subscribe();
+        let api_call = |tokens, cost| Event::APICallCompleted {
+            provider: "openai".to_string(),
+            tokens,
+            cost,
+            duration_ms: 0,
+            output_tokens: 0,
+            first_token_ms: None,
+        };
+
+        let mut alerts = Vec::new();
+        for event in [api_call(600, 0.3), api_call(100, 0.3), api_call(400, 0.5)] {
+            bus.emit(event).await.unwrap();
+        }
+        while let Ok(event) = receiver.try_recv() {
+            if let Event::BudgetThreshold { pct, kind, .. } = event {
+                alerts.push((kind, pct));
+            }
+        }
+        assert_eq!(
+            alerts,
+            vec![
+                (BudgetKind::Tokens, 50),
+                (BudgetKind::Cost, 50),
+                (BudgetKind::Cost, 80),
+                (BudgetKind::Cost, 100),
+                (BudgetKind::Tokens, 80),
+                (BudgetKind::Tokens, 100),
+            ]
+        );
+    }
 }
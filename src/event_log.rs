@@ -0,0 +1,133 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::event_bus::{Event, EventSink};
+
+/// Name of the event log in a run's directory
+pub const EVENTS_FILE: &str = "events.jsonl";
+
+/// One line of the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub event: Event,
+}
+
+/// Event sink appending every event as a JSON line to
+/// `.cli_engineer/runs/<run_id>/events.jsonl`
+pub struct JsonlEventLog {
+    path: PathBuf,
+    writer: Mutex<BufWriter<File>>,
+    /// Set after the first write error, which is reported once
+    failed: AtomicBool,
+}
+
+impl JsonlEventLog {
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create run directory")?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open event log {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(BufWriter::new(file)),
+            failed: AtomicBool::new(false),
+        })
+    }
+}
+
+/// Read an event log. A line cut short by a crash is skipped with a warning.
+pub fn read_events(path: &Path) -> Result<Vec<EventRecord>> {
+    let content =
+        fs::read_to_string(path).with_context(|| format!("No event log at {}", path.display()))?;
+    let mut records = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(e) => log::warn!("Skipping line {} of {}: {}", index + 1, path.display(), e),
+        }
+    }
+    Ok(records)
+}
+
+impl EventSink for JsonlEventLog {
+    fn handle(&self, event: &Event) {
+        let record = EventRecord {
+            timestamp: chrono::Utc::now(),
+            event: event.clone(),
+        };
+        let Ok(line) = serde_json::to_string(&record) else {
+            return;
+        };
+        let mut writer = self.writer.lock().unwrap();
+        // Flushed per line so the log is complete up to a crash
+        if let Err(e) = writeln!(writer, "{}", line).and_then(|_| writer.flush())
+            && !self.failed.swap(true, Ordering::Relaxed)
+        {
+            // Logging would emit another event; report on stderr instead
+            eprintln!("Failed to write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::EventBus;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_event_log() {
+        let dir =
+            std::env::temp_dir().join(format!("cli_engineer_events_{}", uuid::Uuid::new_v4()));
+        let path = dir.join("run").join(EVENTS_FILE);
+        let bus = EventBus::new(10);
+        bus.add_sink(Arc::new(JsonlEventLog::create(&path).unwrap()));
+
+        bus.emit(Event::TaskStarted {
+            task_id: "t1".to_string(),
+            description: "Review the code".to_string(),
+        })
+        .await
+        .unwrap();
+        bus.emit(Event::APICallCompleted {
+            provider: "openai".to_string(),
+            tokens: 42,
+            cost: 0.5,
+            duration_ms: 0,
+            output_tokens: 0,
+            first_token_ms: None,
+        })
+        .await
+        .unwrap();
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"{\"timestamp\":")
+            .unwrap();
+        let records = read_events(&path).unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(matches!(&records[0].event, Event::TaskStarted { task_id, .. } if task_id == "t1"));
+        assert!(matches!(
+            records[1].event,
+            Event::APICallCompleted { tokens: 42, .. }
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,17 +1,39 @@
 use anyhow::{Context as AnyhowContext, Result};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
 
-use crate::artifact::{ArtifactManager, ArtifactType};
+use crate::agents::{AgentRole, AgentTeam};
+use crate::artifact::{ArtifactManager, ArtifactType, FileAction, add_tags};
+use crate::checkpoint::CheckpointStore;
+use crate::code_index::CodeIndex;
+use crate::config::Config;
 use crate::context::ContextManager;
-use crate::event_bus::{Event, EventBus};
+use crate::event_bus::{Event, EventBus, StepStatus};
+use crate::formatter::format_content;
 use crate::llm_manager::LLMManager;
+use crate::logger;
+use crate::mcp_client::McpTools;
+use crate::plan_progress::PLAN_EXECUTION_STARTED_EVENT;
 use crate::planner::{Plan, Step, StepCategory};
+use crate::retrieval::RetrievalIndex;
+use crate::run_control::RunControl;
+use crate::sampling::{STEP_SAMPLED_EVENT, Sample, Sampler};
+use crate::syntax_check::check_syntax;
+use crate::tool_loop::ToolLoop;
+use crate::transcript::StepTranscript;
 use log::{info, warn};
+use serde::{Deserialize, Serialize};
 use crate::CommandKind;
+use clap::ValueEnum;
+
+/// Most symbol definitions pulled into a single step prompt
+const MAX_STEP_DEFINITIONS: usize = 5;
 
 /// Result of executing a single step
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StepResult {
     pub step_id: String,
     pub success: bool,
@@ -29,6 +51,21 @@ pub struct Executor {
     event_bus: Option<Arc<EventBus>>,
     llm_manager: Arc<LLMManager>,
     command: Option<CommandKind>,
+    config: Option<Arc<Config>>,
+    transcript_dir: Option<PathBuf>,
+    retrieval: Option<(Arc<RetrievalIndex>, usize)>,
+    code_index: Option<Arc<CodeIndex>>,
+    mcp_tools: Option<Arc<McpTools>>,
+    /// Works each step with tools instead of a single prompt, in the tools mode
+    tools: Option<Arc<ToolLoop>>,
+    /// Generates several candidate responses for high-risk steps and retries
+    sampler: Option<Arc<Sampler>>,
+    /// Implementer and tester agents carrying out the steps, in the agents mode
+    agents: Option<Arc<AgentTeam>>,
+    control: Option<Arc<RunControl>>,
+    /// Saves each step's result so an interrupted run can continue after it
+    checkpoints: Option<Arc<CheckpointStore>>,
+    iteration: AtomicUsize,
 }
 
 impl Executor {
@@ -39,9 +76,85 @@ impl Executor {
             event_bus: None,
             llm_manager,
             command: None,
+            config: None,
+            transcript_dir: None,
+            retrieval: None,
+            code_index: None,
+            mcp_tools: None,
+            tools: None,
+            sampler: None,
+            agents: None,
+            control: None,
+            checkpoints: None,
+            iteration: AtomicUsize::new(0),
         }
     }
 
+    /// Write a transcript of every executed step into `dir`
+    pub fn with_transcript_dir(mut self, dir: PathBuf) -> Self {
+        self.transcript_dir = Some(dir);
+        self
+    }
+
+    /// Prepend the `top_k` most relevant code chunks from `index` to each step prompt
+    pub fn with_retrieval(mut self, index: Arc<RetrievalIndex>, top_k: usize) -> Self {
+        self.retrieval = Some((index, top_k));
+        self
+    }
+
+    /// Prepend definitions of symbols the step description refers to
+    pub fn with_code_index(mut self, index: Arc<CodeIndex>) -> Self {
+        self.code_index = Some(index);
+        self
+    }
+
+    /// Offer the tools of MCP servers to the model during each step
+    pub fn with_mcp_tools(mut self, tools: Arc<McpTools>) -> Self {
+        self.mcp_tools = Some(tools);
+        self
+    }
+
+    /// Let the model work on each step with the tools of `tools`
+    pub fn with_tools(mut self, tools: Arc<ToolLoop>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    /// Sample high-risk steps with `sampler`, and retry failed steps with it once
+    pub fn with_sampler(mut self, sampler: Arc<Sampler>) -> Self {
+        self.sampler = Some(sampler);
+        self
+    }
+
+    /// Have the implementer and tester agents of `team` carry out the steps
+    pub fn with_agents(mut self, team: Arc<AgentTeam>) -> Self {
+        self.agents = Some(team);
+        self
+    }
+
+    pub fn with_config(mut self, config: Arc<Config>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Wait while the run is paused before each step, and stop once it is cancelled
+    pub fn with_control(mut self, control: Arc<RunControl>) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    /// Record each step's result in `store`
+    pub fn with_checkpoints(mut self, store: Arc<CheckpointStore>) -> Self {
+        self.checkpoints = Some(store);
+        self
+    }
+
+    /// Number the next executed plan as `iteration`, for a run continued from a checkpoint
+    pub fn with_first_iteration(self, iteration: usize) -> Self {
+        self.iteration.store(iteration.saturating_sub(1), Ordering::SeqCst);
+        self
+    }
+
     #[allow(dead_code)]
     pub fn with_artifact_manager(mut self, manager: Arc<ArtifactManager>) -> Self {
         self.artifact_manager = Some(manager);
@@ -64,43 +177,138 @@ impl Executor {
         self
     }
 
-    /// Execute the entire plan and return results for each step
-    pub async fn execute(&self, plan: &Plan, context_id: &str) -> Result<Vec<StepResult>> {
-        let mut results = Vec::new();
+    /// Execute the entire plan and return results for each step. Steps with a
+    /// result in `completed`, restored from a checkpoint, are not run again.
+    pub async fn execute(
+        &self,
+        plan: &Plan,
+        context_id: &str,
+        completed: Vec<StepResult>,
+    ) -> Result<Vec<StepResult>> {
+        let mut results = completed;
+        let iteration = self.iteration.fetch_add(1, Ordering::SeqCst) + 1;
 
         // Emit plan execution started event
         if let Some(bus) = &self.event_bus {
             let _ = bus
                 .emit(Event::Custom {
-                    event_type: "plan_execution_started".to_string(),
+                    event_type: PLAN_EXECUTION_STARTED_EVENT.to_string(),
                     data: serde_json::json!({
+                        "iteration": iteration,
                         "plan_goal": plan.goal,
                         "total_steps": plan.steps.len(),
-                        "complexity": format!("{:?}", plan.estimated_complexity)
+                        "complexity": format!("{:?}", plan.estimated_complexity),
+                        "steps": plan.steps.iter().map(|step| serde_json::json!({
+                            "id": step.id,
+                            "description": step.description,
+                            "category": format!("{:?}", step.category),
+                        })).collect::<Vec<_>>(),
                     }),
                 })
                 .await;
         }
 
         for (index, step) in plan.steps.iter().enumerate() {
+            if let Some(done) = results.iter().find(|r| r.step_id == step.id) {
+                let status = if done.success { StepStatus::Done } else { StepStatus::Failed };
+                self.emit_step_finished(&step.id, status, Instant::now(), &done.error)
+                    .await;
+                continue;
+            }
+
+            if let Some(control) = &self.control {
+                control.checkpoint().await?;
+            }
+
             // Check dependencies (if implemented)
             if !self.dependencies_met(&step.id, &plan.dependencies, &results) {
-                results.push(StepResult {
+                let result = StepResult {
                     step_id: step.id.clone(),
                     success: false,
                     output: String::new(),
                     artifacts_created: Vec::new(),
                     tokens_used: 0,
                     error: Some("Dependencies not met".to_string()),
-                });
+                };
+                self.emit_step_finished(
+                    &step.id,
+                    StepStatus::Failed,
+                    Instant::now(),
+                    &result.error,
+                )
+                .await;
+                self.save_checkpoint(&result);
+                results.push(result);
                 continue;
             }
 
-            // Execute the step
-            let result = self
-                .execute_step(step, context_id, index + 1, plan.steps.len())
-                .await
-                .context(format!("Failed to execute step: {}", step.description))?;
+            let started = Instant::now();
+            if let Some(bus) = &self.event_bus {
+                let _ = bus
+                    .emit(Event::StepStarted {
+                        step_id: step.id.clone(),
+                        number: index + 1,
+                        total: plan.steps.len(),
+                        description: step.description.clone(),
+                    })
+                    .await;
+            }
+
+            // Execute the step, unless it is skipped or the run cancelled meanwhile
+            let execution = logger::in_step(&step.id, async {
+                let number = index + 1;
+                let total = plan.steps.len();
+                match self.execute_step(step, context_id, number, total, iteration, false).await {
+                    // Provider errors are not retried: more requests would fail the same way
+                    Ok(result) if !result.success && self.sampler.is_some() => {
+                        warn!(
+                            "Step {} failed, retrying with several candidates: {}",
+                            number,
+                            result.error.as_deref().unwrap_or("no error given")
+                        );
+                        self.execute_step(step, context_id, number, total, iteration, true)
+                            .await
+                    }
+                    result => result,
+                }
+                .context(format!("Failed to execute step: {}", step.description))
+            });
+            let outcome = match &self.control {
+                Some(control) => tokio::select! {
+                    result = execution => result.map(|result| (result, None)),
+                    interrupted = control.interrupted() => interrupted.map(|()| {
+                        warn!("Skipped step {}: {}", index + 1, step.description);
+                        let result = StepResult {
+                            step_id: step.id.clone(),
+                            success: false,
+                            output: String::new(),
+                            artifacts_created: Vec::new(),
+                            tokens_used: 0,
+                            error: Some("Skipped by the user".to_string()),
+                        };
+                        (result, Some(StepStatus::Skipped))
+                    }),
+                },
+                None => execution.await.map(|result| (result, None)),
+            };
+            let result = match outcome {
+                Ok((result, status)) => {
+                    let status = status.unwrap_or(if result.success {
+                        StepStatus::Done
+                    } else {
+                        StepStatus::Failed
+                    });
+                    self.emit_step_finished(&step.id, status, started, &result.error)
+                        .await;
+                    result
+                }
+                Err(e) => {
+                    let error = Some(format!("{:#}", e));
+                    self.emit_step_finished(&step.id, StepStatus::Failed, started, &error)
+                        .await;
+                    return Err(e);
+                }
+            };
 
             // Emit step completed event
             if let Some(bus) = &self.event_bus {
@@ -118,27 +326,92 @@ impl Executor {
                     .await;
             }
 
+            self.save_checkpoint(&result);
             results.push(result);
         }
 
         Ok(results)
     }
 
-    /// Execute a single step based on its category
+    fn save_checkpoint(&self, result: &StepResult) {
+        if let Some(store) = &self.checkpoints {
+            store.record_step(result);
+        }
+    }
+
+    /// Announce how a step ended, for the plan checklists of the UIs
+    async fn emit_step_finished(
+        &self,
+        step_id: &str,
+        status: StepStatus,
+        started: Instant,
+        error: &Option<String>,
+    ) {
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::StepFinished {
+                    step_id: step_id.to_string(),
+                    status,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    error: error.clone(),
+                })
+                .await;
+        }
+    }
+
+    /// Execute a single step based on its category. High-risk steps, and
+    /// `retry`s of failed ones, get the best of several candidate responses
+    #[tracing::instrument(
+        name = "step",
+        skip_all,
+        fields(
+            step.id = %step.id,
+            step.category = ?step.category,
+            step.number = step_num,
+            iteration,
+        )
+    )]
     async fn execute_step(
         &self,
         step: &Step,
         context_id: &str,
         step_num: usize,
         total_steps: usize,
+        iteration: usize,
+        retry: bool,
     ) -> Result<StepResult> {
         info!(
             "Executing step {}/{}: {}",
             step_num, total_steps, step.description
         );
+        let started = Instant::now();
+        let mut transcript = StepTranscript::new(
+            iteration,
+            &step.id,
+            step_num,
+            total_steps,
+            &step.description,
+            format!("{:?}", step.category),
+        );
+        if let Some(tools) = &self.tools {
+            return self
+                .execute_with_tools(tools, step, context_id, iteration, transcript, started)
+                .await;
+        }
 
         // Build the appropriate prompt based on step category
-        let base_prompt = self.build_step_prompt(step, step_num, total_steps);
+        let mut base_prompt = self.build_step_prompt(step, step_num, total_steps);
+        if let Some((index, top_k)) = &self.retrieval {
+            let retrieved = index.context_for(&step.description, *top_k).await;
+            base_prompt = format!("{}{}", retrieved, base_prompt);
+        }
+        if let Some(index) = &self.code_index {
+            let definitions = index.definitions_for(&step.description, MAX_STEP_DEFINITIONS);
+            base_prompt = format!("{}{}", definitions, base_prompt);
+        }
+        if let Some(tools) = &self.mcp_tools {
+            base_prompt.push_str(&tools.prompt_section());
+        }
 
         // Get all context messages if available
         let full_prompt = if let Some(ctx_mgr) = &self.context_manager {
@@ -177,7 +450,38 @@ impl Executor {
         };
 
         // Send to LLM
-        let response = self.llm_manager.send_prompt(&full_prompt).await?;
+        transcript.prompt = full_prompt.clone();
+        let response = match &self.sampler {
+            Some(sampler) if step.high_risk || retry => {
+                match sampler.sample(&full_prompt, &step.description).await {
+                    Ok(sample) => Ok(self.record_sample(step, sample, &mut transcript).await),
+                    Err(e) => Err(e),
+                }
+            }
+            _ => self.llm_for(step).send_prompt(&full_prompt).await,
+        };
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                transcript.error = Some(e.to_string());
+                transcript.duration = started.elapsed();
+                self.save_transcript(&transcript);
+                return Err(e);
+            }
+        };
+        transcript.response = response.clone();
+        let response = match self
+            .run_tool_calls(step, &full_prompt, response, &mut transcript)
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                transcript.error = Some(e.to_string());
+                transcript.duration = started.elapsed();
+                self.save_transcript(&transcript);
+                return Err(e);
+            }
+        };
 
         info!("Received response from LLM for step {}", step_num);
 
@@ -220,7 +524,18 @@ impl Executor {
                     let artifacts = self
                         .extract_code_artifacts(&response, &step.description, &step.category)
                         .await?;
+                    let artifacts = self
+                        .repair_syntax_errors(artifacts, step, &mut transcript)
+                        .await?;
                     for (filename, content) in artifacts {
+                        let content = match &self.config {
+                            Some(config) => {
+                                format_content(&filename, &content, &config.formatting).await
+                            }
+                            None => content,
+                        };
+                        transcript.artifacts.push((filename.clone(), content.len()));
+
                         // Safety check: For Docs command, only allow files in docs/ directory
                         if matches!(self.command, Some(CommandKind::Docs)) {
                             if !filename.starts_with("docs/") {
@@ -232,21 +547,18 @@ impl Executor {
                             }
                         }
                         
-                        let extension = filename.split('.').last();
-                        let artifact_type = match extension {
-                            Some("rs") => ArtifactType::SourceCode,
-                            Some("toml") => ArtifactType::Configuration,
-                            Some("json") => ArtifactType::Configuration,
-                            Some("md") => ArtifactType::Documentation,
-                            Some("txt") => ArtifactType::Documentation,
-                            Some("sh") => ArtifactType::Script,
-                            Some("py") => ArtifactType::SourceCode,
-                            Some("js") => ArtifactType::SourceCode,
-                            _ => ArtifactType::Other("unknown".to_string()),
-                        };
+                        let artifact_type = ArtifactType::from_filename(&filename);
                         let mut metadata = HashMap::new();
                         metadata.insert("step_id".to_string(), step.id.clone());
                         metadata.insert("category".to_string(), format!("{:?}", step.category));
+                        let mut tags = vec![
+                            format!("iteration:{}", iteration),
+                            format!("{:?}", step.category).to_lowercase(),
+                        ];
+                        if let Some(command) = self.command.as_ref().and_then(|c| c.to_possible_value()) {
+                            tags.push(command.get_name().to_string());
+                        }
+                        add_tags(&mut metadata, &tags.iter().map(String::as_str).collect::<Vec<_>>());
 
                         match artifact_mgr
                             .create_artifact(
@@ -265,6 +577,29 @@ impl Executor {
                             }
                         }
                     }
+
+                    for action in Self::extract_file_actions(&response) {
+                        if matches!(self.command, Some(CommandKind::Docs)) {
+                            let touches_outside_docs = match &action {
+                                FileAction::Delete { filename } => !filename.starts_with("docs/"),
+                                FileAction::Rename { from, to } => {
+                                    !from.starts_with("docs/") || !to.starts_with("docs/")
+                                }
+                            };
+                            if touches_outside_docs {
+                                warn!(
+                                    "Refusing {:?} during Docs command - only files in docs/ directory are allowed",
+                                    action
+                                );
+                                continue;
+                            }
+                        }
+
+                        match artifact_mgr.apply_file_action(&action).await {
+                            Ok(_) => info!("Applied file action: {:?}", action),
+                            Err(e) => warn!("Failed to apply file action {:?}: {}", action, e),
+                        }
+                    }
                 }
             }
             _ => {
@@ -272,9 +607,288 @@ impl Executor {
             }
         }
 
+        transcript.duration = started.elapsed();
+        self.save_transcript(&transcript);
+
         Ok(result)
     }
 
+    /// Work on `step` in a tool session, recording each turn in the transcript
+    async fn execute_with_tools(
+        &self,
+        tools: &ToolLoop,
+        step: &Step,
+        context_id: &str,
+        iteration: usize,
+        mut transcript: StepTranscript,
+        started: Instant,
+    ) -> Result<StepResult> {
+        let outcome = match tools.run(step, iteration, self.control.as_deref()).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                transcript.error = Some(e.to_string());
+                transcript.duration = started.elapsed();
+                self.save_transcript(&transcript);
+                return Err(e);
+            }
+        };
+        let turns = outcome.turns.len();
+        let mut rounds = outcome.turns.into_iter();
+        if let Some((prompt, response)) = rounds.next() {
+            transcript.prompt = prompt;
+            transcript.response = response;
+        }
+        transcript.follow_ups = rounds.collect();
+        transcript.duration = started.elapsed();
+        self.save_transcript(&transcript);
+
+        if let Some(ctx_mgr) = &self.context_manager {
+            ctx_mgr
+                .add_message(context_id, "assistant".to_string(), outcome.summary.clone())
+                .await?;
+        }
+        Ok(StepResult {
+            step_id: step.id.clone(),
+            success: outcome.finished,
+            output: outcome.summary,
+            artifacts_created: outcome.artifacts,
+            tokens_used: 0,
+            error: (!outcome.finished)
+                .then(|| format!("Stopped after {} turns without finishing", turns)),
+        })
+    }
+
+    /// Keep the candidates of a sampled step in its transcript and announce
+    /// them, returning the response chosen
+    async fn record_sample(
+        &self,
+        step: &Step,
+        sample: Sample,
+        transcript: &mut StepTranscript,
+    ) -> String {
+        if let Some(bus) = &self.event_bus {
+            let providers: Vec<_> = sample.candidates.iter().map(|c| &c.provider).collect();
+            let _ = bus
+                .emit(Event::Custom {
+                    event_type: STEP_SAMPLED_EVENT.to_string(),
+                    data: serde_json::json!({
+                        "step_id": step.id,
+                        "candidates": sample.candidates.len(),
+                        "providers": providers,
+                        "selection": sample.selection,
+                    }),
+                })
+                .await;
+        }
+        transcript.candidates = sample
+            .candidates
+            .into_iter()
+            .map(|candidate| (candidate.provider, candidate.response))
+            .collect();
+        transcript.selection = Some(sample.selection);
+        sample.response
+    }
+
+    /// The model carrying out `step`: its agent's in the agents mode
+    fn llm_for(&self, step: &Step) -> &LLMManager {
+        match &self.agents {
+            Some(team) => team.llm(AgentRole::for_step(&step.category)),
+            None => &self.llm_manager,
+        }
+    }
+
+    fn save_transcript(&self, transcript: &StepTranscript) {
+        if let Some(dir) = &self.transcript_dir {
+            match transcript.write_to(dir) {
+                Ok(path) => info!("Saved step transcript to {}", path.display()),
+                Err(e) => warn!("Failed to save step transcript: {}", e),
+            }
+        }
+    }
+
+    /// Run the MCP tool calls a response ends with and send the results back,
+    /// until the model answers without calls or the rounds run out
+    async fn run_tool_calls(
+        &self,
+        step: &Step,
+        prompt: &str,
+        mut response: String,
+        transcript: &mut StepTranscript,
+    ) -> Result<String> {
+        let Some(tools) = &self.mcp_tools else {
+            return Ok(response);
+        };
+        let max_rounds = self
+            .config
+            .as_ref()
+            .map_or(5, |config| config.mcp.max_tool_rounds);
+
+        for round in 0..=max_rounds {
+            let calls = Self::extract_tool_calls(&response);
+            if calls.is_empty() {
+                break;
+            }
+            if round == max_rounds {
+                warn!("Ignoring tool calls after {} rounds", max_rounds);
+                break;
+            }
+
+            let mut results = String::new();
+            for (server, tool, arguments) in calls {
+                let started = Instant::now();
+                let arguments = if arguments.trim().is_empty() {
+                    "{}"
+                } else {
+                    arguments.trim()
+                };
+                let outcome = match serde_json::from_str(arguments) {
+                    Ok(arguments) => tools.call(&server, &tool, arguments).await,
+                    Err(e) => Err(anyhow::anyhow!("Arguments are not valid JSON: {}", e)),
+                };
+                let (status, output) = match outcome {
+                    Ok(output) => ("ok", output),
+                    Err(e) => {
+                        warn!("Tool call {}.{} failed: {:#}", server, tool, e);
+                        ("error", format!("{:#}", e))
+                    }
+                };
+                if let Some(bus) = &self.event_bus {
+                    let _ = bus
+                        .emit(Event::Custom {
+                            event_type: "mcp_tool_called".to_string(),
+                            data: serde_json::json!({
+                                "server": server,
+                                "tool": tool,
+                                "status": status,
+                                "duration_ms": started.elapsed().as_millis() as u64,
+                            }),
+                        })
+                        .await;
+                }
+                results.push_str(&format!(
+                    "<tool_result server=\"{}\" tool=\"{}\" status=\"{}\">\n{}\n</tool_result>\n\n",
+                    server, tool, status, output
+                ));
+            }
+
+            let follow_up = format!(
+                "{}\n\nYOUR RESPONSE SO FAR:\n{}\n\nTOOL RESULTS:\n{}\
+Continue the step using these results. Repeat anything from your response so far that the step \
+still needs, such as artifacts, since only your next response is used.",
+                prompt, response, results
+            );
+            let next = self.llm_for(step).send_prompt(&follow_up).await?;
+            transcript.follow_ups.push((follow_up, next.clone()));
+            response = next;
+        }
+
+        Ok(response)
+    }
+
+    /// The MCP tool calls of a response, `<tool_call server="..." tool="...">`
+    /// tags, as server, tool and arguments
+    fn extract_tool_calls(response: &str) -> Vec<(String, String, String)> {
+        extract_tool_call_tags(response)
+            .into_iter()
+            .filter_map(|(attributes, arguments)| {
+                match (attributes.get("server"), attributes.get("tool")) {
+                    (Some(server), Some(tool)) => Some((server.clone(), tool.clone(), arguments)),
+                    _ => {
+                        warn!("Ignoring malformed tool call: {:?}", attributes);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Validate extracted artifacts and ask the model to fix any syntax errors
+    /// before they are saved, so broken files don't wait for a full review cycle
+    async fn repair_syntax_errors(
+        &self,
+        mut artifacts: Vec<(String, String)>,
+        step: &Step,
+        transcript: &mut StepTranscript,
+    ) -> Result<Vec<(String, String)>> {
+        let (enabled, max_attempts) = match &self.config {
+            Some(config) => (
+                config.execution.validate_syntax,
+                config.execution.max_syntax_fix_attempts,
+            ),
+            None => (true, 2),
+        };
+        if !enabled {
+            return Ok(artifacts);
+        }
+
+        for attempt in 1..=max_attempts {
+            let mut errors = Vec::new();
+            for (filename, content) in &artifacts {
+                if let Some(error) = check_syntax(filename, content).await {
+                    errors.push((error, content.clone()));
+                }
+            }
+
+            if errors.is_empty() {
+                return Ok(artifacts);
+            }
+
+            for (error, _) in &errors {
+                warn!("Syntax error in generated file {}", error);
+            }
+            info!(
+                "Requesting syntax fixes for {} file(s) (attempt {}/{})",
+                errors.len(),
+                attempt,
+                max_attempts
+            );
+
+            let mut fix_prompt = format!(
+                "While executing the step \"{}\" you generated files that do not parse.\n\n",
+                step.description
+            );
+            for (error, content) in &errors {
+                fix_prompt.push_str(&format!(
+                    "Syntax error: {}\nCurrent content of {}:\n<![CDATA[\n{}\n]]>\n\n",
+                    error, error.filename, content
+                ));
+            }
+            fix_prompt.push_str(
+                "Fix ONLY the syntax errors and return the COMPLETE corrected content of each listed file \
+using the XML artifact format:\n\
+<artifact filename=\"filename.ext\" type=\"language\">\n<![CDATA[\nentire file content here\n]]>\n</artifact>",
+            );
+
+            let fix_response = self.llm_for(step).send_prompt(&fix_prompt).await?;
+            transcript
+                .follow_ups
+                .push((fix_prompt.clone(), fix_response.clone()));
+            let fixed = self
+                .extract_code_artifacts(&fix_response, &step.description, &step.category)
+                .await?;
+
+            if fixed.is_empty() {
+                warn!("Syntax fix response contained no artifacts");
+                break;
+            }
+
+            for (filename, content) in fixed {
+                if let Some(existing) = artifacts.iter_mut().find(|(name, _)| *name == filename) {
+                    existing.1 = content;
+                }
+            }
+        }
+
+        // Save whatever we have; the reviewer will still see any remaining problems
+        for (filename, content) in &artifacts {
+            if let Some(error) = check_syntax(filename, content).await {
+                warn!("Saving {} with unresolved syntax error: {}", filename, error);
+            }
+        }
+
+        Ok(artifacts)
+    }
+
     fn build_step_prompt(&self, step: &Step, step_num: usize, total_steps: usize) -> String {
         let category_context = match step.category {
             StepCategory::Analysis => {
@@ -401,7 +1015,10 @@ WHAT YOU MUST DO:
 4. NEVER use generic names like 'file_1.py' or 'script.py'
 5. Use descriptive filenames that match the functionality
 6. If implementing tests, use test_<feature>.py format
-7. The CDATA section allows any content including markdown with code blocks"
+7. The CDATA section allows any content including markdown with code blocks
+8. To delete or rename a file, use a self-closing action tag instead of an artifact:
+   <action type=\"delete\" filename=\"old_file.ext\" />
+   <action type=\"rename\" from=\"old_name.ext\" to=\"new_name.ext\" />"
             }
             StepCategory::Documentation => {
                 "\n\nCRITICAL DOCUMENTATION RULES:
@@ -460,6 +1077,45 @@ WHAT YOU MUST DO:
         true
     }
 
+    /// Extract `<action type="delete|rename" ... />` tags from a response
+    fn extract_file_actions(response: &str) -> Vec<FileAction> {
+        let mut actions = Vec::new();
+
+        for line in response.lines() {
+            let line = line.trim();
+            if !line.starts_with("<action") {
+                continue;
+            }
+
+            let attributes = parse_tag_attributes(line);
+            let action = match attributes.get("type").map(String::as_str) {
+                Some("delete") => attributes
+                    .get("filename")
+                    .filter(|f| !f.is_empty())
+                    .map(|filename| FileAction::Delete {
+                        filename: filename.clone(),
+                    }),
+                Some("rename") => match (attributes.get("from"), attributes.get("to")) {
+                    (Some(from), Some(to)) if !from.is_empty() && !to.is_empty() => {
+                        Some(FileAction::Rename {
+                            from: from.clone(),
+                            to: to.clone(),
+                        })
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            match action {
+                Some(action) => actions.push(action),
+                None => warn!("Ignoring malformed action tag: {}", line),
+            }
+        }
+
+        actions
+    }
+
     async fn extract_code_artifacts(
         &self,
         response: &str,
@@ -587,3 +1243,99 @@ WHAT YOU MUST DO:
         Ok(artifacts)
     }
 }
+
+/// Extract `<tool_call ...>{arguments}</tool_call>` tags from a response as
+/// the attributes of each tag and the arguments it holds
+pub(crate) fn extract_tool_call_tags(response: &str) -> Vec<(HashMap<String, String>, String)> {
+    let mut calls = Vec::new();
+    let mut rest = response;
+
+    while let Some(start) = rest.find("<tool_call") {
+        let after = &rest[start..];
+        let Some(tag_end) = after.find('>') else {
+            break;
+        };
+        let Some(close) = after.find("</tool_call>") else {
+            break;
+        };
+        if close > tag_end {
+            calls.push((
+                parse_tag_attributes(&after[..tag_end]),
+                after[tag_end + 1..close].to_string(),
+            ));
+        }
+        rest = &after[close + "</tool_call>".len()..];
+    }
+
+    calls
+}
+
+/// Parse `key="value"` attributes from a single-line XML-style tag
+pub(crate) fn parse_tag_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = tag;
+
+    while let Some(eq) = rest.find("=\"") {
+        let key = rest[..eq]
+            .rsplit(|c: char| c.is_whitespace() || c == '<')
+            .next()
+            .unwrap_or("")
+            .to_string();
+        let after = &rest[eq + 2..];
+        let Some(end) = after.find('"') else {
+            break;
+        };
+        if !key.is_empty() {
+            attributes.insert(key, after[..end].to_string());
+        }
+        rest = &after[end + 1..];
+    }
+
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_file_actions() {
+        let response = "Removing the old module.\n\
+<action type=\"delete\" filename=\"src/old.rs\" />\n\
+<action type=\"rename\" from=\"src/a b.rs\" to=\"src/c.rs\"/>\n\
+<action type=\"rename\" from=\"src/x.rs\" />";
+
+        let actions = Executor::extract_file_actions(response);
+        assert_eq!(
+            actions,
+            vec![
+                FileAction::Delete {
+                    filename: "src/old.rs".to_string()
+                },
+                FileAction::Rename {
+                    from: "src/a b.rs".to_string(),
+                    to: "src/c.rs".to_string()
+                },
+            ]
+        );
+    }
+    #[test]
+    fn test_extract_tool_calls() {
+        let response = "Checking the schema first.\n\
+<tool_call server=\"db\" tool=\"query\">{\"sql\": \"select 1\"}</tool_call>\n\
+<tool_call tool=\"list\"></tool_call>\n\
+<tool_call server=\"fs\" tool=\"list\">\n</tool_call>";
+
+        assert_eq!(
+            Executor::extract_tool_calls(response),
+            vec![
+                (
+                    "db".to_string(),
+                    "query".to_string(),
+                    "{\"sql\": \"select 1\"}".to_string()
+                ),
+                ("fs".to_string(), "list".to_string(), "\n".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,145 @@
+use crate::code_index::{CodeIndex, SymbolKind};
+
+/// Key symbols listed per file in the repository overview
+const MAX_KEY_SYMBOLS: usize = 6;
+/// Longest purpose text kept from a file's leading documentation
+const MAX_PURPOSE_CHARS: usize = 120;
+
+/// One-line summary of a file that is not loaded into context:
+/// `path: purpose [symbols: a, b, c]`
+pub fn summarize_file(path: &str, content: &str) -> String {
+    let mut line = path.to_string();
+    if let Some(purpose) = purpose(path, content) {
+        line.push_str(&format!(": {}", purpose));
+    }
+
+    let mut index = CodeIndex::default();
+    index.add_file(path, content);
+    let symbols: Vec<String> = index
+        .symbols_in(path)
+        .filter(|s| !matches!(s.kind, SymbolKind::Import | SymbolKind::Method))
+        .filter(|s| s.qualified_name == s.name)
+        .map(|s| s.name.clone())
+        .collect();
+    if !symbols.is_empty() {
+        let shown = symbols.len().min(MAX_KEY_SYMBOLS);
+        line.push_str(&format!(" [symbols: {}", symbols[..shown].join(", ")));
+        if symbols.len() > shown {
+            line.push_str(&format!(", +{} more", symbols.len() - shown));
+        }
+        line.push(']');
+    }
+    line
+}
+
+/// Compact block listing files the model knows exist but has not seen
+pub fn repository_overview(summaries: &[String]) -> String {
+    format!(
+        "Repository overview ({} files not loaded into context):\n{}",
+        summaries.len(),
+        summaries
+            .iter()
+            .map(|s| format!("- {}", s))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+/// What a file is for, taken from its leading documentation: module doc
+/// comments, a Python docstring, a markdown title or a manifest description
+fn purpose(path: &str, content: &str) -> Option<String> {
+    let text = if path.ends_with(".md") {
+        content
+            .lines()
+            .find_map(|l| l.strip_prefix("# "))
+            .map(str::to_string)
+    } else if path.ends_with(".py") {
+        let rest = content.trim_start();
+        let quote = ["\"\"\"", "'''"]
+            .into_iter()
+            .find(|q| rest.starts_with(q))?;
+        rest[3..].split(quote).next().map(str::to_string)
+    } else if path.ends_with(".toml") || path.ends_with(".json") {
+        content.lines().find_map(|l| {
+            let l = l.trim().trim_start_matches('"');
+            let value = l.strip_prefix("description")?;
+            let value = value.trim_start_matches('"').trim_start();
+            let value = value
+                .strip_prefix('=')
+                .or_else(|| value.strip_prefix(':'))?;
+            Some(
+                value
+                    .trim()
+                    .trim_end_matches(',')
+                    .trim_matches('"')
+                    .to_string(),
+            )
+        })
+    } else {
+        // Leading comment block: `//!` and `///` in Rust, `//`, `/** */` or `#` elsewhere
+        let comment: Vec<&str> = content
+            .lines()
+            .map(str::trim)
+            .skip_while(|l| l.is_empty() || l.starts_with("#!"))
+            .take_while(|l| {
+                l.starts_with("//")
+                    || l.starts_with("/*")
+                    || l.starts_with('*')
+                    || l.starts_with('#')
+            })
+            .map(|l| {
+                l.trim_start_matches(['/', '!', '*', '#'])
+                    .trim_end_matches("*/")
+                    .trim()
+            })
+            .filter(|l| !l.is_empty() && !l.starts_with('['))
+            .collect();
+        (!comment.is_empty()).then(|| comment.join(" "))
+    }?;
+
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    // Keep the first sentence
+    let sentence = text
+        .split(". ")
+        .next()
+        .unwrap_or(&text)
+        .trim_end_matches('.');
+    if sentence.is_empty() {
+        return None;
+    }
+    Some(if sentence.chars().count() > MAX_PURPOSE_CHARS {
+        let truncated: String = sentence.chars().take(MAX_PURPOSE_CHARS).collect();
+        format!("{}...", truncated)
+    } else {
+        sentence.to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize_file() {
+        let rust = "//! Parses configuration files. Supports TOML.\n\nuse std::fs;\n\n\
+                    pub struct Config {}\n\nimpl Config {\n    pub fn load() {}\n}\n\n\
+                    pub fn parse() {}\n";
+        assert_eq!(
+            summarize_file("src/config.rs", rust),
+            "src/config.rs: Parses configuration files [symbols: Config, parse]"
+        );
+
+        let python = "\"\"\"Command line entry point.\"\"\"\n\ndef main():\n    pass\n";
+        assert_eq!(
+            summarize_file("app/cli.py", python),
+            "app/cli.py: Command line entry point [symbols: main]"
+        );
+
+        let manifest = "[package]\nname = \"demo\"\ndescription = \"A demo crate\"\n";
+        assert_eq!(
+            summarize_file("Cargo.toml", manifest),
+            "Cargo.toml: A demo crate"
+        );
+        assert_eq!(summarize_file("data.yaml", "a: 1\n"), "data.yaml");
+    }
+}
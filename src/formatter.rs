@@ -0,0 +1,236 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::FormattingConfig;
+
+/// Built-in formatter commands keyed by file extension. Each command reads the
+/// file from stdin and writes the formatted result to stdout.
+const DEFAULT_FORMATTERS: &[(&str, &[&str])] = &[
+    ("rs", &["rustfmt", "--emit", "stdout"]),
+    ("py", &["black", "--quiet", "-"]),
+    ("js", &["prettier", "--stdin-filepath", "{filename}"]),
+    ("jsx", &["prettier", "--stdin-filepath", "{filename}"]),
+    ("ts", &["prettier", "--stdin-filepath", "{filename}"]),
+    ("tsx", &["prettier", "--stdin-filepath", "{filename}"]),
+    ("css", &["prettier", "--stdin-filepath", "{filename}"]),
+    ("go", &["gofmt"]),
+];
+
+/// Resolve the formatter command for a file in the project at `root`, as the
+/// program and its arguments, preferring configured overrides. An empty
+/// override disables formatting for that extension.
+fn formatter_for(filename: &str, config: &FormattingConfig, root: &Path) -> Option<Vec<String>> {
+    let extension = filename.rsplit('.').next()?.to_lowercase();
+
+    // The command is split before `{filename}` is substituted, so a name with
+    // spaces stays one argument
+    let mut command: Vec<String> = match config.formatters.get(&extension) {
+        Some(command) => command.split_whitespace().map(str::to_string).collect(),
+        None => {
+            let (_, command) = DEFAULT_FORMATTERS
+                .iter()
+                .find(|(ext, _)| *ext == extension)?;
+            let mut command: Vec<String> = command.iter().map(|arg| arg.to_string()).collect();
+            if extension == "rs"
+                && let Some(edition) = rustfmt_edition(root)
+            {
+                command.extend(["--edition".to_string(), edition]);
+            }
+            command
+        }
+    };
+    for arg in &mut command {
+        *arg = arg.replace("{filename}", filename);
+    }
+    (!command.is_empty()).then_some(command)
+}
+
+/// The edition to pass rustfmt for the project at `root`: the one in its
+/// Cargo.toml, unless a rustfmt.toml is there for rustfmt to find itself.
+/// Without either, rustfmt's default of 2015 is also Cargo's.
+fn rustfmt_edition(root: &Path) -> Option<String> {
+    if root.join("rustfmt.toml").exists() || root.join(".rustfmt.toml").exists() {
+        return None;
+    }
+    let manifest: toml::Table = std::fs::read_to_string(root.join("Cargo.toml"))
+        .ok()?
+        .parse()
+        .ok()?;
+    let edition = |table: Option<&toml::Value>| table?.get("edition")?.as_str().map(str::to_string);
+    // `edition.workspace = true` is not a string, so the workspace's is used
+    edition(manifest.get("package")).or_else(|| {
+        edition(
+            manifest
+                .get("workspace")
+                .and_then(|workspace| workspace.get("package")),
+        )
+    })
+}
+
+/// Format generated file content with the project's formatter for its language.
+/// Returns the original content unchanged if no formatter is configured, the
+/// formatter is not installed, or it fails.
+pub async fn format_content(filename: &str, content: &str, config: &FormattingConfig) -> String {
+    if !config.enabled {
+        return content.to_string();
+    }
+
+    // Artifacts are written relative to the working directory, the project root
+    let root = std::env::current_dir().unwrap_or_default();
+    let Some(command) = formatter_for(filename, config, &root) else {
+        return content.to_string();
+    };
+    let program = &command[0];
+
+    let mut child = match Command::new(program)
+        .args(&command[1..])
+        .current_dir(&root)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("Formatter '{}' unavailable for {}: {}", program, filename, e);
+            return content.to_string();
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = stdin.write_all(content.as_bytes()).await;
+    }
+
+    let timeout = Duration::from_secs(config.timeout_secs);
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) if output.status.success() => {
+            let formatted = String::from_utf8_lossy(&output.stdout).to_string();
+            if formatted.trim().is_empty() {
+                return content.to_string();
+            }
+            if formatted != content {
+                info!("Formatted {} with {}", filename, program);
+            }
+            formatted
+        }
+        Ok(Ok(output)) => {
+            warn!(
+                "Formatter {} failed for {}: {}",
+                program,
+                filename,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            content.to_string()
+        }
+        Ok(Err(e)) => {
+            warn!("Formatter {} failed for {}: {}", program, filename, e);
+            content.to_string()
+        }
+        Err(_) => {
+            warn!("Formatter {} timed out for {}", program, filename);
+            content.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "[package]\nname = \"app\"\nedition = \"2024\"\n";
+
+    fn project(files: &[(&str, &str)]) -> std::path::PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("cli_engineer_formatter_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        for (name, content) in files {
+            std::fs::write(root.join(name), content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn test_rustfmt_gets_the_edition_of_the_manifest() {
+        let root = project(&[("Cargo.toml", MANIFEST)]);
+        let command = formatter_for("src/main.rs", &FormattingConfig::default(), &root).unwrap();
+        assert_eq!(command[..3], ["rustfmt", "--emit", "stdout"]);
+        assert_eq!(command[3..], ["--edition", "2024"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_rustfmt_gets_the_edition_of_the_workspace() {
+        let manifest = "[package]\nname = \"app\"\nedition.workspace = true\n\n\
+                        [workspace.package]\nedition = \"2021\"\n";
+        let root = project(&[("Cargo.toml", manifest)]);
+        let command = formatter_for("lib.rs", &FormattingConfig::default(), &root).unwrap();
+        assert_eq!(command[3..], ["--edition", "2021"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_rustfmt_finds_its_own_config() {
+        let root = project(&[
+            ("Cargo.toml", MANIFEST),
+            ("rustfmt.toml", "edition = \"2024\"\n"),
+        ]);
+        let command = formatter_for("lib.rs", &FormattingConfig::default(), &root).unwrap();
+        assert_eq!(command, ["rustfmt", "--emit", "stdout"]);
+        // Nor is an edition guessed without a manifest
+        let _ = std::fs::remove_file(root.join("rustfmt.toml"));
+        let _ = std::fs::remove_file(root.join("Cargo.toml"));
+        let command = formatter_for("lib.rs", &FormattingConfig::default(), &root).unwrap();
+        assert_eq!(command, ["rustfmt", "--emit", "stdout"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_a_filename_with_spaces_is_one_argument() {
+        let root = project(&[]);
+        let command =
+            formatter_for("src/my component.tsx", &FormattingConfig::default(), &root).unwrap();
+        assert_eq!(
+            command,
+            ["prettier", "--stdin-filepath", "src/my component.tsx"]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_configured_formatters_override_the_defaults() {
+        let root = project(&[]);
+        let mut config = FormattingConfig::default();
+        config.formatters.insert(
+            "py".to_string(),
+            "ruff format --stdin-filename {filename} -".to_string(),
+        );
+        config.formatters.insert("go".to_string(), " ".to_string());
+        let command = formatter_for("app/main.py", &config, &root).unwrap();
+        assert_eq!(
+            command,
+            ["ruff", "format", "--stdin-filename", "app/main.py", "-"]
+        );
+        assert!(formatter_for("main.go", &config, &root).is_none());
+        assert!(formatter_for("notes.txt", &config, &root).is_none());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_a_missing_formatter_leaves_the_content_alone() {
+        let mut config = FormattingConfig::default();
+        config.formatters.insert(
+            "py".to_string(),
+            "cli_engineer_no_such_formatter -".to_string(),
+        );
+        let content = "print( 'hi' )\n";
+        assert_eq!(format_content("hello.py", content, &config).await, content);
+        config.enabled = false;
+        assert_eq!(format_content("hello.py", content, &config).await, content);
+    }
+}
@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+use crate::config::GitConfig;
+use crate::git::subject_line;
+use crate::reviewer::ReviewResult;
+use crate::run_record::IterationRecord;
+
+pub const API_URL: &str = "https://api.github.com";
+/// Longest pull request title
+const MAX_TITLE_LEN: usize = 100;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Token for the GitHub API, from `GITHUB_TOKEN` or else `git.github_token`
+pub fn github_token(config: &GitConfig) -> Result<String> {
+    if let Ok(token) = std::env::var("GITHUB_TOKEN")
+        && !token.is_empty()
+    {
+        return Ok(token);
+    }
+    config
+        .github_token
+        .clone()
+        .context("GITHUB_TOKEN environment variable not set, and no git.github_token is configured")
+}
+
+/// Title and body of the pull request for a successful run
+pub fn pull_request_text(
+    goal: &str,
+    history: &[IterationRecord],
+    review: &ReviewResult,
+    artifacts: &[String],
+) -> (String, String) {
+    let mut body = review.summary.trim().to_string();
+
+    body.push_str("\n\n## Plan\n");
+    for record in history {
+        if history.len() > 1 {
+            body.push_str(&format!(
+                "\n**Iteration {}:** {}\n",
+                record.iteration, record.goal
+            ));
+        }
+        for step in &record.steps {
+            let mark = if step.success == Some(true) { "x" } else { " " };
+            body.push_str(&format!("- [{}] {}\n", mark, step.description));
+        }
+    }
+
+    body.push_str(&format!(
+        "\n## Review\nQuality: {:?}\n",
+        review.overall_quality
+    ));
+    for issue in &review.issues {
+        match &issue.location {
+            Some(location) => body.push_str(&format!(
+                "- **{}** {} (`{}`)\n",
+                issue.severity, issue.description, location
+            )),
+            None => body.push_str(&format!("- **{}** {}\n", issue.severity, issue.description)),
+        }
+    }
+
+    if !artifacts.is_empty() {
+        body.push_str("\n## Files\n");
+        for name in artifacts {
+            body.push_str(&format!("- `{}`\n", name));
+        }
+    }
+    body.push_str("\n---\nOpened by cli_engineer.\n");
+
+    (subject_line(goal, MAX_TITLE_LEN), body)
+}
+
+/// Open a pull request from `head` into `base` of `repo` (`owner/name`) and
+/// return its URL
+pub async fn create_pull_request(
+    token: &str,
+    repo: &str,
+    head: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+    draft: bool,
+) -> Result<String> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()?;
+    let response = client
+        .post(format!("{}/repos/{}/pulls", API_URL, repo))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("User-Agent", "cli_engineer")
+        .json(&json!({
+            "title": title,
+            "body": body,
+            "head": head,
+            "base": base,
+            "draft": draft,
+        }))
+        .send()
+        .await
+        .context("Failed to reach the GitHub API")?;
+    let status = response.status();
+    let reply: Value = response.json().await.unwrap_or(Value::Null);
+    if !status.is_success() {
+        let message = reply
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("no details");
+        // Validation failures, such as an existing pull request, are explained here
+        let details: Vec<&str> = reply
+            .get("errors")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.get("message").and_then(Value::as_str))
+            .collect();
+        anyhow::bail!(
+            "GitHub refused the pull request (HTTP {}): {}{}",
+            status.as_u16(),
+            message,
+            if details.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", details.join("; "))
+            }
+        );
+    }
+    reply
+        .get("html_url")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .context("GitHub did not return the pull request's URL")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reviewer::{Issue, IssueCategory, IssueSeverity, QualityLevel};
+    use crate::run_record::StepRecord;
+
+    #[test]
+    fn test_pull_request_text() {
+        let step = |description: &str, success| StepRecord {
+            description: description.to_string(),
+            category: "CodeGeneration".to_string(),
+            success,
+            error: None,
+            artifacts: Vec::new(),
+        };
+        let history = [IterationRecord {
+            iteration: 1,
+            goal: "Add a login form".to_string(),
+            steps: vec![step("Create login.rs", Some(true)), step("Add tests", None)],
+            review: None,
+        }];
+        let review = ReviewResult {
+            overall_quality: QualityLevel::Good,
+            issues: vec![Issue {
+                severity: IssueSeverity::Minor,
+                category: IssueCategory::CodeStyle,
+                description: "Long function".to_string(),
+                location: Some("login.rs:3".to_string()),
+                suggestion: None,
+                patch: None,
+            }],
+            suggestions: Vec::new(),
+            ready_to_deploy: true,
+            summary: "The form works.".to_string(),
+        };
+
+        let (title, body) = pull_request_text(
+            "Add a login form\nwith OAuth",
+            &history,
+            &review,
+            &["login.rs".to_string()],
+        );
+        assert_eq!(title, "Add a login form");
+        assert!(
+            body.starts_with(
+                "The form works.\n\n## Plan\n- [x] Create login.rs\n- [ ] Add tests\n"
+            )
+        );
+        assert!(body.contains("- **Minor** Long function (`login.rs:3`)"));
+        assert!(body.contains("## Files\n- `login.rs`"));
+    }
+}
@@ -0,0 +1,332 @@
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+
+use crate::config::HooksConfig;
+use crate::event_bus::{Event, EventBus, EventSink};
+
+/// Custom event carrying a hook's output. It never triggers hooks itself.
+const HOOK_OUTPUT_EVENT: &str = "hook_output";
+/// Most characters of each output stream kept in a `hook_output` event
+const MAX_OUTPUT_CHARS: usize = 4000;
+/// How much longer than a hook's timeout `flush` waits, for emitting its output
+const FLUSH_GRACE: Duration = Duration::from_secs(5);
+
+/// A hook ready to run: its command with placeholders filled in, the
+/// environment describing the event and the event as JSON for stdin
+#[derive(Debug)]
+struct Hook {
+    name: String,
+    program: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    input: String,
+}
+
+/// Event sink running the commands configured in `[hooks]`. Hooks run in the
+/// background; `flush` waits for them before the process exits.
+pub struct HookRunner {
+    config: HooksConfig,
+    bus: Weak<EventBus>,
+    pending: Mutex<Vec<JoinHandle<()>>>,
+    /// Whether the run's completion or failure has been seen
+    finished: AtomicBool,
+}
+
+impl HookRunner {
+    pub fn new(config: HooksConfig, bus: &Arc<EventBus>) -> Self {
+        for name in config.commands.keys().filter(|k| !k.starts_with("on_")) {
+            warn!(
+                "Ignoring hook '{}': hooks are named after events, e.g. on_artifact_created",
+                name
+            );
+        }
+        Self {
+            config,
+            bus: Arc::downgrade(bus),
+            pending: Mutex::new(Vec::new()),
+            finished: AtomicBool::new(false),
+        }
+    }
+
+    /// The hook configured for `event`, if any
+    fn hook_for(&self, event: &Event) -> Option<Hook> {
+        // The end of a run can be reported more than once; only the first counts
+        if matches!(
+            event,
+            Event::TaskCompleted { .. } | Event::TaskFailed { .. }
+        ) && self.finished.swap(true, Ordering::Relaxed)
+        {
+            return None;
+        }
+        let (event_name, data) = event_name_and_data(event);
+        if event_name == HOOK_OUTPUT_EVENT {
+            return None;
+        }
+        let name = format!("on_{}", event_name);
+        let command_line = self.config.commands.get(&name)?;
+
+        let mut fields = vec![("event".to_string(), event_name.clone())];
+        if let Value::Object(map) = &data {
+            fields.extend(map.iter().filter_map(|(key, value)| match value {
+                Value::String(s) => Some((key.clone(), s.clone())),
+                Value::Number(_) | Value::Bool(_) => Some((key.clone(), value.to_string())),
+                _ => None,
+            }));
+        }
+
+        // Placeholders are filled per argument, so values are never split or
+        // interpreted by a shell
+        let mut parts = command_line.split_whitespace().map(|part| {
+            fields.iter().fold(part.to_string(), |part, (key, value)| {
+                part.replace(&format!("{{{}}}", key), value)
+            })
+        });
+        let program = parts.next()?;
+        Some(Hook {
+            program,
+            args: parts.collect(),
+            env: fields
+                .iter()
+                .map(|(key, value)| {
+                    (
+                        format!("CLI_ENGINEER_{}", key.to_uppercase()),
+                        value.clone(),
+                    )
+                })
+                .collect(),
+            input: serde_json::json!({ "event": event_name, "data": data }).to_string(),
+            name,
+        })
+    }
+
+    /// Wait for hooks still running, at most a little longer than their
+    /// timeout, then abandon the rest
+    pub async fn flush(&self) {
+        let pending: Vec<_> = self.pending.lock().unwrap().drain(..).collect();
+        let running: Vec<_> = pending.iter().map(JoinHandle::abort_handle).collect();
+        let limit = Duration::from_secs(self.config.timeout_secs) + FLUSH_GRACE;
+        if tokio::time::timeout(limit, futures::future::join_all(pending))
+            .await
+            .is_err()
+        {
+            warn!("Stopped waiting for hooks after {}s", limit.as_secs());
+            for hook in running {
+                hook.abort();
+            }
+        }
+    }
+}
+
+impl EventSink for HookRunner {
+    fn handle(&self, event: &Event) {
+        let Some(hook) = self.hook_for(event) else {
+            return;
+        };
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let bus = self.config.emit_output.then(|| self.bus.clone());
+        let handle = tokio::spawn(run_hook(hook, timeout, bus));
+        self.pending.lock().unwrap().push(handle);
+    }
+}
+
+/// Run `hook`, logging failures, and emit its output to `bus` if given
+async fn run_hook(hook: Hook, timeout: Duration, bus: Option<Weak<EventBus>>) {
+    let mut child = match Command::new(&hook.program)
+        .args(&hook.args)
+        .envs(hook.env.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Hook {} could not run {}: {}", hook.name, hook.program, e);
+            return;
+        }
+    };
+
+    // Writing the event counts against the timeout too: a hook that does not
+    // read stdin would otherwise block a large event forever
+    let stdin = child.stdin.take();
+    let input = hook.input.as_bytes();
+    let write = async move {
+        if let Some(mut stdin) = stdin {
+            // Hooks that do not read the event close stdin early; that is fine
+            let _ = stdin.write_all(input).await;
+        }
+    };
+    let run = async { tokio::join!(write, child.wait_with_output()).1 };
+    let output = match tokio::time::timeout(timeout, run).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            warn!("Hook {} failed: {}", hook.name, e);
+            return;
+        }
+        Err(_) => {
+            warn!("Hook {} timed out after {}s", hook.name, timeout.as_secs());
+            return;
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if output.status.success() {
+        info!("Hook {} ran {}", hook.name, hook.program);
+    } else {
+        warn!(
+            "Hook {} exited with {}: {}",
+            hook.name,
+            output.status,
+            stderr.trim()
+        );
+    }
+
+    if let Some(bus) = bus.and_then(|bus| bus.upgrade()) {
+        let _ = bus
+            .emit(Event::Custom {
+                event_type: HOOK_OUTPUT_EVENT.to_string(),
+                data: serde_json::json!({
+                    "hook": hook.name,
+                    "command": hook.program,
+                    "success": output.status.success(),
+                    "exit_code": output.status.code(),
+                    "stdout": stdout.chars().take(MAX_OUTPUT_CHARS).collect::<String>(),
+                    "stderr": stderr.chars().take(MAX_OUTPUT_CHARS).collect::<String>(),
+                }),
+            })
+            .await;
+    }
+}
+
+/// Snake-case name of an event, as used in hook names, and its fields.
+/// Custom events are named by their `event_type`.
+fn event_name_and_data(event: &Event) -> (String, Value) {
+    if let Event::Custom { event_type, data } = event {
+        return (event_type.clone(), data.clone());
+    }
+    match serde_json::to_value(event) {
+        Ok(Value::Object(map)) => map
+            .into_iter()
+            .next()
+            .map(|(variant, data)| (snake_case(&variant), data))
+            .unwrap_or_default(),
+        Ok(Value::String(variant)) => (snake_case(&variant), Value::Null),
+        _ => Default::default(),
+    }
+}
+
+/// `APICallCompleted` -> `api_call_completed`
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let after_lower = chars[i - 1].is_lowercase();
+            let ends_acronym =
+                chars[i - 1].is_uppercase() && chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if after_lower || ends_acronym {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hooks() {
+        assert_eq!(snake_case("APICallCompleted"), "api_call_completed");
+        assert_eq!(snake_case("ArtifactCreated"), "artifact_created");
+        assert_eq!(snake_case("SystemReady"), "system_ready");
+
+        let bus = Arc::new(EventBus::new(100));
+        let mut config = HooksConfig {
+            emit_output: true,
+            ..HooksConfig::default()
+        };
+        config
+            .commands
+            .insert("on_artifact_created".to_string(), "cat".to_string());
+        config.commands.insert(
+            "on_iteration_started".to_string(),
+            "./check.sh --iteration={iteration} {event}".to_string(),
+        );
+        let runner = Arc::new(HookRunner::new(config, &bus));
+
+        let hook = runner
+            .hook_for(&Event::Custom {
+                event_type: "iteration_started".to_string(),
+                data: serde_json::json!({ "iteration": 2, "has_existing_files": true }),
+            })
+            .unwrap();
+        assert_eq!(hook.program, "./check.sh");
+        assert_eq!(hook.args, ["--iteration=2", "iteration_started"]);
+        assert!(
+            hook.env
+                .contains(&("CLI_ENGINEER_ITERATION".to_string(), "2".to_string()))
+        );
+        assert!(runner.hook_for(&Event::SystemReady).is_none());
+
+        // The event reaches the hook on stdin and its output comes back as an event
+        bus.add_sink(runner.clone());
+        let mut receiver = bus.subscribe();
+        bus.emit(Event::ArtifactCreated {
+            name: "my notes.md".to_string(),
+            path: "artifacts/my notes.md".to_string(),
+            artifact_type: "Documentation".to_string(),
+        })
+        .await
+        .unwrap();
+        runner.flush().await;
+        let output = loop {
+            if let Event::Custom { event_type, data } = receiver.recv().await.unwrap()
+                && event_type == HOOK_OUTPUT_EVENT
+            {
+                break data;
+            }
+        };
+        assert_eq!(output["hook"], "on_artifact_created");
+        assert_eq!(output["success"], true);
+        let stdin: Value = serde_json::from_str(output["stdout"].as_str().unwrap()).unwrap();
+        assert_eq!(stdin["event"], "artifact_created");
+        assert_eq!(stdin["data"]["path"], "artifacts/my notes.md");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_a_hook_ignoring_stdin_is_bounded_by_the_timeout() {
+        let bus = Arc::new(EventBus::new(100));
+        let mut config = HooksConfig {
+            timeout_secs: 1,
+            ..HooksConfig::default()
+        };
+        config
+            .commands
+            .insert("on_large".to_string(), "sleep 30".to_string());
+        let runner = HookRunner::new(config, &bus);
+        // More than a pipe holds, so writing it blocks until the hook reads
+        runner.handle(&Event::Custom {
+            event_type: "large".to_string(),
+            // An array, which is left out of the environment
+            data: serde_json::json!({ "lines": vec!["x".repeat(1000); 1000] }),
+        });
+        let started = std::time::Instant::now();
+        runner.flush().await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_secs(1) && elapsed < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,596 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::event_bus::{Event, StepStatus};
+use crate::event_log::{EVENTS_FILE, EventRecord, read_events};
+use crate::reviewer::IssueSeverity;
+use crate::run_record::RunRecord;
+
+/// Name of the report in a run's directory
+pub const REPORT_FILE: &str = "report.html";
+
+const STYLE: &str = include_str!("html_report.css");
+
+/// A step's span on the timeline
+struct TimelineEntry {
+    label: String,
+    start_ms: i64,
+    end_ms: i64,
+    status: Option<StepStatus>,
+}
+
+/// Cost and tokens of the API calls made in one part of the run
+struct CostEntry {
+    label: String,
+    cost: f64,
+    tokens: usize,
+}
+
+/// Write `record` and its event log as a self-contained HTML page to
+/// `.cli_engineer/runs/<run_id>/report.html`
+pub fn write(record: &RunRecord, state_dir: &Path) -> Result<PathBuf> {
+    let dir = RunRecord::runs_dir(state_dir).join(&record.run_id);
+    // Without an event log the report lacks the timeline, costs and diffs
+    let events = read_events(&dir.join(EVENTS_FILE)).unwrap_or_default();
+    fs::create_dir_all(&dir).context("Failed to create run directory")?;
+    let path = dir.join(REPORT_FILE);
+    fs::write(&path, render(record, &events)).context("Failed to write HTML report")?;
+    Ok(path)
+}
+
+/// Open `path` in the default browser
+pub fn open_in_browser(path: &Path) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command
+        .arg(path)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .context("Failed to start the browser")?;
+    Ok(())
+}
+
+/// The report page of `record`, with the timeline, cost breakdown and diffs
+/// taken from `events`
+pub fn render(record: &RunRecord, events: &[EventRecord]) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{} run {}</title>\n<style>\n{}</style>\n</head>\n<body>\n",
+        escape(&record.command),
+        escape(&record.run_id),
+        STYLE
+    );
+    render_header(&mut html, record);
+    html.push_str("<main>\n");
+    let _ = write!(
+        html,
+        "<section>\n<h2>Prompt</h2>\n<pre class=\"prompt\">{}</pre>\n</section>\n",
+        escape(&record.prompt)
+    );
+    render_plan(&mut html, record);
+    render_timeline(&mut html, record, events);
+    render_review(&mut html, record);
+    render_costs(&mut html, record, events);
+    render_artifacts(&mut html, record, events);
+    html.push_str("</main>\n</body>\n</html>\n");
+    html
+}
+
+fn render_header(html: &mut String, record: &RunRecord) {
+    let (class, result) = match &record.error {
+        Some(_) => ("failed", "Failed"),
+        None if record.success => ("completed", "Completed"),
+        None if record.stalled.is_some() => ("", "Stalled"),
+        None => ("", "Not completed"),
+    };
+    let _ = write!(
+        html,
+        "<header>\n<h1>cli_engineer {} <span class=\"badge {}\">{}</span></h1>\n\
+         <div class=\"meta\"><span>Run <b>{}</b></span><span>Started <b>{}</b></span>\
+         <span>Duration <b>{}s</b></span><span>Iterations <b>{}</b></span>\
+         <span>API calls <b>{}</b></span><span>Tokens <b>{}</b></span>\
+         <span>Cost <b>${:.4}</b></span></div>\n",
+        escape(&record.command),
+        class,
+        result,
+        escape(&record.run_id),
+        record.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+        (record.finished_at - record.started_at).num_seconds(),
+        record.iterations.len(),
+        record.api_calls,
+        record.tokens,
+        record.cost
+    );
+    if let Some(reason) = record.error.as_ref().or(record.stalled.as_ref()) {
+        let _ = writeln!(html, "<p class=\"error\">{}</p>", escape(reason));
+    }
+    html.push_str("</header>\n");
+}
+
+fn render_plan(html: &mut String, record: &RunRecord) {
+    if record.iterations.is_empty() {
+        return;
+    }
+    html.push_str("<section>\n<h2>Plan</h2>\n");
+    for iteration in &record.iterations {
+        let _ = write!(
+            html,
+            "<h3>Iteration {}: {}</h3>\n<ol class=\"steps\">\n",
+            iteration.iteration,
+            escape(&iteration.goal)
+        );
+        for step in &iteration.steps {
+            let (class, outcome) = match step.success {
+                Some(true) => ("done", "done"),
+                Some(false) => ("failed", "failed"),
+                None => ("", "not run"),
+            };
+            let _ = write!(
+                html,
+                "<li><span class=\"badge {}\">{}</span> {} <span class=\"dim\">{}</span>",
+                class,
+                outcome,
+                escape(&step.description),
+                escape(&step.category)
+            );
+            if let Some(error) = &step.error {
+                let _ = write!(html, "<div class=\"error\">{}</div>", escape(error));
+            }
+            if !step.artifacts.is_empty() {
+                let _ = write!(
+                    html,
+                    "<div class=\"dim\">Artifacts: {}</div>",
+                    escape(&step.artifacts.join(", "))
+                );
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ol>\n");
+        if let Some(review) = &iteration.review {
+            let _ = writeln!(html, "<p><b>Review:</b> {}</p>", escape(review));
+        }
+    }
+    html.push_str("</section>\n");
+}
+
+/// When each step ran, from its start and finish events
+fn timeline(record: &RunRecord, events: &[EventRecord]) -> Vec<TimelineEntry> {
+    let offset =
+        |time: chrono::DateTime<chrono::Utc>| (time - record.started_at).num_milliseconds();
+    let end_ms = offset(record.finished_at);
+    let mut iteration = 0;
+    let mut entries: Vec<(String, TimelineEntry)> = Vec::new();
+    for logged in events {
+        match &logged.event {
+            Event::Custom { event_type, data } if event_type == "iteration_started" => {
+                iteration = data
+                    .get("iteration")
+                    .and_then(|iteration| iteration.as_u64())
+                    .unwrap_or(iteration as u64 + 1) as usize;
+            }
+            Event::StepStarted {
+                step_id,
+                number,
+                total,
+                description,
+            } => entries.push((
+                format!("{}/{}", iteration, step_id),
+                TimelineEntry {
+                    label: format!("{}.{}/{} {}", iteration, number, total, description),
+                    start_ms: offset(logged.timestamp),
+                    end_ms,
+                    status: None,
+                },
+            )),
+            Event::StepFinished {
+                step_id, status, ..
+            } => {
+                let key = format!("{}/{}", iteration, step_id);
+                if let Some((_, entry)) = entries.iter_mut().rev().find(|(k, _)| *k == key) {
+                    entry.end_ms = offset(logged.timestamp);
+                    entry.status = Some(*status);
+                }
+            }
+            _ => {}
+        }
+    }
+    entries.into_iter().map(|(_, entry)| entry).collect()
+}
+
+fn render_timeline(html: &mut String, record: &RunRecord, events: &[EventRecord]) {
+    let entries = timeline(record, events);
+    if entries.is_empty() {
+        return;
+    }
+    let span = (record.finished_at - record.started_at)
+        .num_milliseconds()
+        .max(1) as f64;
+    html.push_str("<section class=\"timeline\">\n<h2>Step timeline</h2>\n");
+    for entry in &entries {
+        let left = (entry.start_ms as f64 / span * 100.0).clamp(0.0, 100.0);
+        let width =
+            ((entry.end_ms - entry.start_ms) as f64 / span * 100.0).clamp(0.0, 100.0 - left);
+        let status = entry
+            .status
+            .map_or("running".to_string(), |status| status.to_string());
+        let _ = writeln!(
+            html,
+            "<div class=\"row\"><span class=\"label\" title=\"{label}\">{label}</span>\
+             <span class=\"track\"><span class=\"bar {class}\" style=\"left:{left:.2}%;width:{width:.2}%\"></span></span>\
+             <span class=\"value\">{secs:.1}s {status}</span></div>",
+            label = escape(&entry.label),
+            class = status.to_lowercase(),
+            left = left,
+            width = width,
+            secs = (entry.end_ms - entry.start_ms) as f64 / 1000.0,
+            status = escape(&status.to_lowercase()),
+        );
+    }
+    html.push_str("</section>\n");
+}
+
+fn severity_class(severity: &IssueSeverity) -> &'static str {
+    match severity {
+        IssueSeverity::Critical => "critical",
+        IssueSeverity::Major => "major",
+        IssueSeverity::Minor => "minor",
+        IssueSeverity::Info => "info",
+    }
+}
+
+fn render_review(html: &mut String, record: &RunRecord) {
+    let Some(review) = &record.review else {
+        return;
+    };
+    let _ = write!(
+        html,
+        "<section>\n<h2>Review findings</h2>\n<p>Quality: <b>{:?}</b> · Ready to deploy: <b>{}</b></p>\n<p>{}</p>\n",
+        review.overall_quality,
+        if review.ready_to_deploy { "yes" } else { "no" },
+        escape(&review.summary)
+    );
+    if review.issues.is_empty() {
+        html.push_str("<p class=\"dim\">No issues found.</p>\n");
+    } else {
+        let mut issues: Vec<_> = review.issues.iter().collect();
+        // Most severe first, in the order the enum lists them
+        issues.sort_by_key(|issue| issue.severity.clone() as u8);
+        html.push_str(
+            "<table>\n<tr><th>Severity</th><th>Category</th><th>Location</th><th>Finding</th></tr>\n",
+        );
+        for issue in issues {
+            let _ = write!(
+                html,
+                "<tr><td><span class=\"badge {}\">{}</span></td><td>{:?}</td><td><code>{}</code></td><td>{}",
+                severity_class(&issue.severity),
+                issue.severity,
+                issue.category,
+                escape(issue.location.as_deref().unwrap_or("")),
+                escape(&issue.description)
+            );
+            if let Some(suggestion) = &issue.suggestion {
+                let _ = write!(html, "<div class=\"dim\">{}</div>", escape(suggestion));
+            }
+            html.push_str("</td></tr>\n");
+        }
+        html.push_str("</table>\n");
+    }
+    if !review.suggestions.is_empty() {
+        html.push_str("<h3>Suggestions</h3>\n<ul>\n");
+        for suggestion in &review.suggestions {
+            let _ = writeln!(
+                html,
+                "<li><b>{}</b> <span class=\"dim\">({:?})</span>: {}</li>",
+                escape(&suggestion.title),
+                suggestion.priority,
+                escape(&suggestion.description)
+            );
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</section>\n");
+}
+
+/// API call costs by the part of the run they were made in: planning, a step
+/// or review of each iteration
+fn cost_breakdown(events: &[EventRecord]) -> Vec<CostEntry> {
+    let mut iteration = 0;
+    let mut phase = "Setup".to_string();
+    let mut entries: Vec<CostEntry> = Vec::new();
+    for record in events {
+        match &record.event {
+            Event::Custom { event_type, data } => match event_type.as_str() {
+                "iteration_started" => {
+                    iteration = data
+                        .get("iteration")
+                        .and_then(|iteration| iteration.as_u64())
+                        .unwrap_or(iteration as u64 + 1) as usize;
+                    phase = format!("{}. Planning", iteration);
+                }
+                "review_started" => phase = format!("{}. Review", iteration),
+                _ => {}
+            },
+            Event::StepStarted {
+                number,
+                description,
+                ..
+            } => phase = format!("{}.{} {}", iteration, number, description),
+            Event::APICallCompleted { tokens, cost, .. } => {
+                match entries.iter_mut().find(|entry| entry.label == phase) {
+                    Some(entry) => {
+                        entry.cost += *cost as f64;
+                        entry.tokens += tokens;
+                    }
+                    None => entries.push(CostEntry {
+                        label: phase.clone(),
+                        cost: *cost as f64,
+                        tokens: *tokens,
+                    }),
+                }
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+fn render_costs(html: &mut String, record: &RunRecord, events: &[EventRecord]) {
+    let entries = cost_breakdown(events);
+    if entries.is_empty() {
+        return;
+    }
+    // Free local models have tokens but no cost to chart
+    let by_cost = entries.iter().any(|entry| entry.cost > 0.0);
+    let value = |entry: &CostEntry| {
+        if by_cost {
+            entry.cost
+        } else {
+            entry.tokens as f64
+        }
+    };
+    let max = entries
+        .iter()
+        .map(value)
+        .fold(0.0, f64::max)
+        .max(f64::EPSILON);
+    let _ = writeln!(
+        html,
+        "<section class=\"chart\">\n<h2>Cost breakdown</h2>\n<p class=\"dim\">{} API calls, {} tokens, ${:.4} in total</p>",
+        record.api_calls, record.tokens, record.cost
+    );
+    for entry in &entries {
+        let _ = writeln!(
+            html,
+            "<div class=\"row\"><span class=\"label\" title=\"{label}\">{label}</span>\
+             <span class=\"track\"><span class=\"bar\" style=\"left:0;width:{width:.2}%\"></span></span>\
+             <span class=\"value\">${cost:.4} · {tokens} tok</span></div>",
+            label = escape(&entry.label),
+            width = value(entry) / max * 100.0,
+            cost = entry.cost,
+            tokens = entry.tokens,
+        );
+    }
+    html.push_str("</section>\n");
+}
+
+fn render_artifacts(html: &mut String, record: &RunRecord, events: &[EventRecord]) {
+    let diffs: Vec<_> = events
+        .iter()
+        .filter_map(|logged| match &logged.event {
+            Event::ArtifactDiff {
+                name,
+                against,
+                diff,
+            } => Some((name, against, diff)),
+            _ => None,
+        })
+        .collect();
+    if record.artifacts.is_empty() && diffs.is_empty() {
+        return;
+    }
+    html.push_str("<section>\n<h2>Artifacts</h2>\n<ul>\n");
+    for artifact in &record.artifacts {
+        let _ = writeln!(
+            html,
+            "<li>{} <code class=\"dim\">{}</code></li>",
+            escape(&artifact.name),
+            escape(&artifact.path)
+        );
+    }
+    html.push_str("</ul>\n");
+    if !diffs.is_empty() {
+        html.push_str("<h3>Diffs</h3>\n");
+    }
+    for (name, against, diff) in diffs {
+        let _ = write!(
+            html,
+            "<details><summary>{} <span class=\"dim\">against {}</span></summary><pre>",
+            escape(name),
+            escape(against)
+        );
+        for line in diff.lines() {
+            let class = if line.starts_with("+++") || line.starts_with("---") {
+                "dim"
+            } else if line.starts_with('+') {
+                "add"
+            } else if line.starts_with('-') {
+                "del"
+            } else if line.starts_with("@@") {
+                "hunk"
+            } else {
+                ""
+            };
+            let _ = writeln!(html, "<span class=\"{}\">{}</span>", class, escape(line));
+        }
+        html.push_str("</pre></details>\n");
+    }
+    html.push_str("</section>\n");
+}
+
+/// `text` with the characters HTML gives meaning escaped
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reviewer::{Issue, IssueCategory, QualityLevel, ReviewResult};
+    use crate::run_record::{IterationRecord, StepRecord};
+
+    #[test]
+    fn test_html_report() {
+        let started = chrono::Utc::now();
+        let at = |secs: i64| started + chrono::Duration::seconds(secs);
+        let issue = |severity, description: &str| Issue {
+            severity,
+            category: IssueCategory::Security,
+            description: description.to_string(),
+            location: Some("src/main.rs:10".to_string()),
+            suggestion: None,
+            patch: None,
+        };
+        let record = RunRecord {
+            run_id: "20250101_090000_aaaa".to_string(),
+            command: "security".to_string(),
+            prompt: "Check <input> handling".to_string(),
+            started_at: started,
+            finished_at: at(10),
+            success: false,
+            error: None,
+            artifacts: Vec::new(),
+            review: Some(ReviewResult {
+                overall_quality: QualityLevel::Fair,
+                issues: vec![
+                    issue(IssueSeverity::Minor, "Verbose errors"),
+                    issue(IssueSeverity::Critical, "SQL injection"),
+                ],
+                suggestions: Vec::new(),
+                ready_to_deploy: false,
+                summary: "Two findings".to_string(),
+            }),
+            pull_request: None,
+            stalled: None,
+            iterations: vec![IterationRecord {
+                iteration: 1,
+                goal: "Audit".to_string(),
+                steps: vec![StepRecord {
+                    description: "Scan inputs".to_string(),
+                    category: "Analysis".to_string(),
+                    success: Some(true),
+                    error: None,
+                    artifacts: Vec::new(),
+                }],
+                review: None,
+            }],
+            api_calls: 2,
+            tokens: 300,
+            cost: 0.03,
+            outcome: None,
+        };
+        let event = |secs, event| EventRecord {
+            timestamp: at(secs),
+            event,
+        };
+        let events = vec![
+            event(
+                0,
+                Event::Custom {
+                    event_type: "iteration_started".to_string(),
+                    data: serde_json::json!({"iteration": 1}),
+                },
+            ),
+            event(
+                1,
+                Event::APICallCompleted {
+                    provider: "openai".to_string(),
+                    tokens: 100,
+                    cost: 0.01,
+                    duration_ms: 0,
+                    output_tokens: 0,
+                    first_token_ms: None,
+                },
+            ),
+            event(
+                2,
+                Event::StepStarted {
+                    step_id: "step_1".to_string(),
+                    number: 1,
+                    total: 1,
+                    description: "Scan inputs".to_string(),
+                },
+            ),
+            event(
+                3,
+                Event::APICallCompleted {
+                    provider: "openai".to_string(),
+                    tokens: 200,
+                    cost: 0.02,
+                    duration_ms: 0,
+                    output_tokens: 0,
+                    first_token_ms: None,
+                },
+            ),
+            event(
+                7,
+                Event::StepFinished {
+                    step_id: "step_1".to_string(),
+                    status: StepStatus::Done,
+                    duration_ms: 5000,
+                    error: None,
+                },
+            ),
+            event(
+                8,
+                Event::ArtifactDiff {
+                    name: "security_report.md".to_string(),
+                    against: "workspace".to_string(),
+                    diff: "@@ -1 +1 @@\n-old\n+new".to_string(),
+                },
+            ),
+        ];
+
+        let entries = timeline(&record, &events);
+        assert_eq!((entries[0].start_ms, entries[0].end_ms), (2000, 7000));
+        let costs = cost_breakdown(&events);
+        assert_eq!(costs[0].label, "1. Planning");
+        assert_eq!(
+            (costs[1].label.as_str(), costs[1].tokens),
+            ("1.1 Scan inputs", 200)
+        );
+
+        let html = render(&record, &events);
+        assert!(html.contains("Check &lt;input&gt; handling"));
+        assert!(html.contains("left:20.00%;width:50.00%"));
+        // Critical findings come first
+        let critical = html.find("SQL injection").unwrap();
+        assert!(critical < html.find("Verbose errors").unwrap());
+        assert!(html.contains("<span class=\"badge critical\">Critical</span>"));
+        assert!(html.contains("<span class=\"del\">-old</span>"));
+    }
+}
@@ -0,0 +1,757 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use chrono::{DateTime, Utc};
+use log::info;
+use serde_json::{Value, json};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, Notify, broadcast, watch};
+
+use crate::CommandKind;
+use crate::config::Config;
+use crate::event_bus::EventBus;
+use crate::mcp_server::{run_task, task_command};
+use crate::run_control::{ControlMessage, RunControl};
+use crate::run_record::RunRecord;
+use crate::serve::{self, bearer_token, error_object, same_origin, token_matches};
+use crate::web_dashboard::EventFeed;
+
+/// Finished jobs kept for polling and downloads; older ones are forgotten
+const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn name(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    fn is_finished(self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+/// A task submitted over the API, run once the jobs before it are done
+struct Job {
+    id: String,
+    command: CommandKind,
+    command_name: String,
+    prompt: String,
+    require_approval: bool,
+    status: JobStatus,
+    submitted_at: DateTime<Utc>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    error: Option<String>,
+    cancel_requested: bool,
+    /// Record of the run, once it ended
+    run: Option<RunRecord>,
+    bus: Arc<EventBus>,
+    control: Arc<RunControl>,
+    feed: Arc<EventFeed>,
+    /// Set when the job finishes, ending its event streams
+    done: watch::Sender<bool>,
+}
+
+impl Job {
+    fn finish(&mut self, status: JobStatus) {
+        self.status = status;
+        self.finished_at = Some(Utc::now());
+        // Unlike `send`, this also records it while no stream is open
+        self.done.send_replace(true);
+    }
+
+    /// The job as JSON; `position` is its place in the queue
+    fn to_json(&self, position: Option<usize>, with_run: bool) -> Value {
+        let mut job = json!({
+            "id": self.id,
+            "command": self.command_name,
+            "prompt": self.prompt,
+            "status": self.status.name(),
+            "submittedAt": self.submitted_at,
+            "startedAt": self.started_at,
+            "finishedAt": self.finished_at,
+            "error": self.error,
+        });
+        if let Some(position) = position {
+            job["position"] = json!(position);
+        }
+        if self.status == JobStatus::Running {
+            job["paused"] = json!(self.control.is_paused());
+            job["approvalRequest"] = json!(self.control.approval_request());
+        }
+        if with_run && let Some(run) = &self.run {
+            job["run"] = json!(run);
+        }
+        job
+    }
+}
+
+struct HttpServer {
+    config: Arc<Config>,
+    /// Address the server listens on, which requests must be sent to
+    address: SocketAddr,
+    /// Bearer token clients must send
+    token: String,
+    /// Every job in submission order
+    jobs: Mutex<Vec<Job>>,
+    /// Woken when a job is queued
+    queued: Notify,
+    next_id: AtomicU64,
+}
+
+/// Serve the REST API on `address` until Ctrl-C: clients queue tasks, poll
+/// them, stream their events and download their artifacts. Jobs run one at a
+/// time in the working directory.
+pub async fn serve(config: Arc<Config>, address: SocketAddr) -> Result<()> {
+    let configured = serve::configured_token(&config.serve);
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to listen on {}", address))?;
+    let address = listener.local_addr()?;
+    // Without a token any page open in a browser could run tasks
+    let token = match configured {
+        Some(token) => token,
+        None => {
+            let token = serve::generate_token();
+            println!("API token for this session: {}", token);
+            token
+        }
+    };
+    let server = Arc::new(HttpServer {
+        config,
+        address,
+        token,
+        jobs: Mutex::new(Vec::new()),
+        queued: Notify::new(),
+        next_id: AtomicU64::new(1),
+    });
+    tokio::spawn(run_jobs(server.clone()));
+
+    println!("Serving the REST API on http://{}", address);
+    let shutdown = {
+        let server = server.clone();
+        async move {
+            let _ = tokio::signal::ctrl_c().await;
+            server.cancel_all().await;
+        }
+    };
+    axum::serve(listener, router(server.clone()))
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    Ok(())
+}
+
+fn router(server: Arc<HttpServer>) -> Router {
+    Router::new()
+        .route("/jobs", get(list_jobs).post(submit))
+        .route("/jobs/{id}", get(job))
+        .route("/jobs/{id}/events", get(events))
+        .route("/jobs/{id}/artifacts", get(artifacts))
+        .route("/jobs/{id}/artifacts/{*name}", get(download))
+        .route("/jobs/{id}/{action}", post(control))
+        .with_state(server)
+}
+
+/// Run queued jobs one after the other
+async fn run_jobs(server: Arc<HttpServer>) {
+    loop {
+        let next = {
+            let mut jobs = server.jobs.lock().await;
+            jobs.iter_mut()
+                .find(|job| job.status == JobStatus::Queued)
+                .map(|job| {
+                    job.status = JobStatus::Running;
+                    job.started_at = Some(Utc::now());
+                    (
+                        job.id.clone(),
+                        job.command.clone(),
+                        job.command_name.clone(),
+                        job.prompt.clone(),
+                        job.bus.clone(),
+                        job.control.clone(),
+                        job.require_approval,
+                    )
+                })
+        };
+        let Some((id, command, command_name, prompt, bus, control, require_approval)) = next else {
+            server.queued.notified().await;
+            continue;
+        };
+
+        info!("Running job {}: {} {}", id, command_name, prompt);
+        let outcome = run_task(
+            &server.config,
+            command,
+            &command_name,
+            &prompt,
+            bus,
+            control,
+            require_approval,
+        )
+        .await;
+
+        let mut jobs = server.jobs.lock().await;
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            let status = match outcome {
+                Ok(record) => {
+                    job.error = record.error.clone();
+                    let success = record.success;
+                    job.run = Some(record);
+                    if success {
+                        JobStatus::Succeeded
+                    } else if job.cancel_requested {
+                        JobStatus::Cancelled
+                    } else {
+                        JobStatus::Failed
+                    }
+                }
+                Err(e) => {
+                    job.error = Some(format!("{:#}", e));
+                    JobStatus::Failed
+                }
+            };
+            job.finish(status);
+        }
+        let finished = jobs.iter().filter(|job| job.status.is_finished()).count();
+        for _ in MAX_FINISHED_JOBS..finished {
+            if let Some(oldest) = jobs.iter().position(|job| job.status.is_finished()) {
+                jobs.remove(oldest);
+            }
+        }
+    }
+}
+
+impl HttpServer {
+    /// The response refusing a request that was not sent to this server or
+    /// lacks the token, if it should be refused
+    fn check(&self, headers: &HeaderMap) -> Option<Response> {
+        if !same_origin(self.address, headers) {
+            return Some(error_response(
+                StatusCode::FORBIDDEN,
+                "Requests must be sent to the server's own address",
+            ));
+        }
+        let authorized =
+            bearer_token(headers).is_some_and(|given| token_matches(given, &self.token));
+        (!authorized)
+            .then(|| error_response(StatusCode::UNAUTHORIZED, "Missing or wrong bearer token"))
+    }
+
+    /// As `check`, for a request that changes state, which must be JSON so a
+    /// page of another site cannot send it without a preflight
+    fn check_json(&self, headers: &HeaderMap) -> Option<Response> {
+        let json = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"));
+        if !json {
+            return Some(error_response(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "Requests must be sent as application/json",
+            ));
+        }
+        self.check(headers)
+    }
+
+    /// Drop the queue and cancel the running job, so its record is still saved
+    async fn cancel_all(&self) {
+        let mut jobs = self.jobs.lock().await;
+        for job in jobs.iter_mut() {
+            match job.status {
+                JobStatus::Queued => job.finish(JobStatus::Cancelled),
+                JobStatus::Running => {
+                    job.cancel_requested = true;
+                    let _ = job
+                        .control
+                        .send(ControlMessage::Cancel, &job.bus, "http")
+                        .await;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/json")],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    let error = error_object(i64::from(status.as_u16()), message);
+    json_response(status, json!({ "error": error }))
+}
+
+fn not_found(id: &str) -> Response {
+    error_response(StatusCode::NOT_FOUND, &format!("No job {}", id))
+}
+
+/// Place of each queued job in the queue, starting at 1
+fn positions(jobs: &[Job]) -> impl Iterator<Item = Option<usize>> + '_ {
+    let mut position = 0;
+    jobs.iter().map(move |job| {
+        (job.status == JobStatus::Queued).then(|| {
+            position += 1;
+            position
+        })
+    })
+}
+
+/// `POST /jobs` with `{"command", "prompt", "requireApproval"}`
+async fn submit(
+    State(server): State<Arc<HttpServer>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Some(refused) = server.check_json(&headers) {
+        return refused;
+    }
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &format!("Invalid JSON: {}", e)),
+    };
+    let argument = |key: &str| request.get(key).and_then(Value::as_str).unwrap_or("");
+    let command_name = argument("command").to_string();
+    let prompt = argument("prompt").to_string();
+    let command = match task_command(&command_name, &prompt) {
+        Ok(command) => command,
+        Err(e) => return error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    };
+
+    let mut jobs = server.jobs.lock().await;
+    let waiting = jobs
+        .iter()
+        .filter(|job| job.status == JobStatus::Queued)
+        .count();
+    if waiting >= server.config.serve.max_queued_jobs {
+        return error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            &format!("The queue is full with {} jobs", waiting),
+        );
+    }
+    let bus = Arc::new(EventBus::new(1000));
+    let feed = Arc::new(EventFeed::new());
+    bus.add_sink(feed.clone());
+    let job = Job {
+        id: server.next_id.fetch_add(1, Ordering::Relaxed).to_string(),
+        command,
+        command_name,
+        prompt,
+        require_approval: request
+            .get("requireApproval")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        status: JobStatus::Queued,
+        submitted_at: Utc::now(),
+        started_at: None,
+        finished_at: None,
+        error: None,
+        cancel_requested: false,
+        run: None,
+        bus,
+        control: Arc::new(RunControl::new()),
+        feed,
+        done: watch::channel(false).0,
+    };
+    info!("Queued job {}: {} {}", job.id, job.command_name, job.prompt);
+    let response = job.to_json(Some(waiting + 1), false);
+    jobs.push(job);
+    server.queued.notify_one();
+    json_response(StatusCode::ACCEPTED, response)
+}
+
+/// `GET /jobs`: every job, without run records
+async fn list_jobs(State(server): State<Arc<HttpServer>>, headers: HeaderMap) -> Response {
+    if let Some(refused) = server.check(&headers) {
+        return refused;
+    }
+    let jobs = server.jobs.lock().await;
+    let list: Vec<Value> = jobs
+        .iter()
+        .zip(positions(&jobs))
+        .map(|(job, position)| job.to_json(position, false))
+        .collect();
+    json_response(StatusCode::OK, json!({ "jobs": list }))
+}
+
+/// `GET /jobs/{id}`: the job, with its run record once it ended
+async fn job(
+    State(server): State<Arc<HttpServer>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    if let Some(refused) = server.check(&headers) {
+        return refused;
+    }
+    let jobs = server.jobs.lock().await;
+    match jobs
+        .iter()
+        .zip(positions(&jobs))
+        .find(|(job, _)| job.id == id)
+    {
+        Some((job, position)) => json_response(StatusCode::OK, job.to_json(position, true)),
+        None => not_found(&id),
+    }
+}
+
+/// `POST /jobs/{id}/{action}`: pause, resume, skip, approve or cancel a job
+async fn control(
+    State(server): State<Arc<HttpServer>>,
+    headers: HeaderMap,
+    Path((id, action)): Path<(String, String)>,
+) -> Response {
+    if let Some(refused) = server.check_json(&headers) {
+        return refused;
+    }
+    let message = match action.as_str() {
+        "pause" => ControlMessage::Pause,
+        "resume" => ControlMessage::Resume,
+        "skip" => ControlMessage::Skip,
+        "approve" => ControlMessage::Approve,
+        "cancel" => ControlMessage::Cancel,
+        _ => return error_response(StatusCode::NOT_FOUND, &format!("Unknown action {}", action)),
+    };
+    let mut jobs = server.jobs.lock().await;
+    let Some(job) = jobs.iter_mut().find(|job| job.id == id) else {
+        return not_found(&id);
+    };
+    match job.status {
+        JobStatus::Queued if message == ControlMessage::Cancel => job.finish(JobStatus::Cancelled),
+        JobStatus::Running => {
+            let cancel = message == ControlMessage::Cancel;
+            if let Err(e) = job.control.send(message, &job.bus, "http").await {
+                return error_response(StatusCode::CONFLICT, &e.to_string());
+            }
+            job.cancel_requested |= cancel;
+        }
+        status => {
+            return error_response(
+                StatusCode::CONFLICT,
+                &format!("Job {} is {}", id, status.name()),
+            );
+        }
+    }
+    json_response(StatusCode::OK, job.to_json(None, false))
+}
+
+/// `GET /jobs/{id}/artifacts`: the files the job's run wrote
+async fn artifacts(
+    State(server): State<Arc<HttpServer>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    if let Some(refused) = server.check(&headers) {
+        return refused;
+    }
+    let jobs = server.jobs.lock().await;
+    let Some(job) = jobs.iter().find(|job| job.id == id) else {
+        return not_found(&id);
+    };
+    match &job.run {
+        Some(run) => json_response(StatusCode::OK, json!({ "artifacts": run.artifacts })),
+        None => error_response(
+            StatusCode::CONFLICT,
+            &format!("Job {} is {}", id, job.status.name()),
+        ),
+    }
+}
+
+/// `GET /jobs/{id}/artifacts/{name}`: the current content of an artifact of the job
+async fn download(
+    State(server): State<Arc<HttpServer>>,
+    headers: HeaderMap,
+    Path((id, name)): Path<(String, String)>,
+) -> Response {
+    if let Some(refused) = server.check(&headers) {
+        return refused;
+    }
+    let path = {
+        let jobs = server.jobs.lock().await;
+        let Some(job) = jobs.iter().find(|job| job.id == id) else {
+            return not_found(&id);
+        };
+        // Only files the run recorded can be downloaded
+        job.run.as_ref().and_then(|run| {
+            run.artifacts
+                .iter()
+                .find(|artifact| artifact.name == name)
+                .map(|artifact| artifact.path.clone())
+        })
+    };
+    let Some(path) = path else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            &format!("Job {} has no artifact {}", id, name),
+        );
+    };
+    match tokio::fs::read(&path).await {
+        Ok(content) => {
+            let file_name = name.rsplit('/').next().unwrap_or(&name).replace('"', "");
+            (
+                [
+                    (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        format!("attachment; filename=\"{}\"", file_name),
+                    ),
+                ],
+                content,
+            )
+                .into_response()
+        }
+        Err(e) => error_response(StatusCode::GONE, &format!("Cannot read {}: {}", path, e)),
+    }
+}
+
+/// `GET /jobs/{id}/events`: server-sent events of the job so far, then as they
+/// happen, ending with a `finished` event holding the job
+async fn events(
+    State(server): State<Arc<HttpServer>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Response {
+    if let Some(refused) = server.check(&headers) {
+        return refused;
+    }
+    let stream = {
+        let jobs = server.jobs.lock().await;
+        let Some(job) = jobs.iter().find(|job| job.id == id) else {
+            return not_found(&id);
+        };
+        let (history, live) = job.feed.subscribe();
+        EventStream {
+            pending: history.into_iter().map(event).collect(),
+            live,
+            done: job.done.subscribe(),
+            server: server.clone(),
+            id,
+            ended: false,
+        }
+    };
+    let stream = futures::stream::unfold(stream, |mut stream| async move {
+        let event = stream.next().await?;
+        Some((Ok::<_, Infallible>(event), stream))
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn event(json: String) -> SseEvent {
+    SseEvent::default().event("event").data(json)
+}
+
+/// Events of one job for one client
+struct EventStream {
+    pending: VecDeque<SseEvent>,
+    live: broadcast::Receiver<String>,
+    done: watch::Receiver<bool>,
+    server: Arc<HttpServer>,
+    id: String,
+    ended: bool,
+}
+
+impl EventStream {
+    async fn next(&mut self) -> Option<SseEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            if self.ended {
+                return None;
+            }
+            tokio::select! {
+                biased;
+                received = self.live.recv() => match received {
+                    Ok(json) => return Some(event(json)),
+                    Err(RecvError::Lagged(missed)) => {
+                        let error = json!({
+                            "error": format!("{} events were dropped because the client fell behind", missed)
+                        });
+                        return Some(SseEvent::default().event("error").data(error.to_string()));
+                    }
+                    Err(RecvError::Closed) => {}
+                },
+                _ = self.done.wait_for(|done| *done) => {}
+            }
+            self.end().await;
+        }
+    }
+
+    /// Queue the events emitted as the job ended, then the `finished` event
+    async fn end(&mut self) {
+        while let Ok(json) = self.live.try_recv() {
+            self.pending.push_back(event(json));
+        }
+        let jobs = self.server.jobs.lock().await;
+        if let Some(job) = jobs.iter().find(|job| job.id == self.id) {
+            self.pending.push_back(
+                SseEvent::default()
+                    .event("finished")
+                    .data(job.to_json(None, true).to_string()),
+            );
+        }
+        self.ended = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// A server without a worker, so submitted jobs stay queued
+    async fn start() -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = Arc::new(HttpServer {
+            config: Arc::new(Config::default()),
+            address,
+            token: "secret".to_string(),
+            jobs: Mutex::new(Vec::new()),
+            queued: Notify::new(),
+            next_id: AtomicU64::new(1),
+        });
+        tokio::spawn(axum::serve(listener, router(server)).into_future());
+        address
+    }
+
+    /// Send a raw request; `headers` are added to Host and Connection
+    async fn send(address: SocketAddr, request_line: &str, headers: &str, body: &str) -> String {
+        let request = format!(
+            "{}\r\nHost: {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            request_line,
+            address,
+            headers,
+            body.len(),
+            body
+        );
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    /// An authorized JSON request
+    async fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> String {
+        let headers = "Authorization: Bearer secret\r\nContent-Type: application/json\r\n";
+        send(
+            address,
+            &format!("{} {} HTTP/1.1", method, path),
+            headers,
+            body,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_queued_and_controlled() {
+        let address = start().await;
+        let invalid = request(address, "POST", "/jobs", r#"{"command":"apply"}"#).await;
+        assert!(invalid.starts_with("HTTP/1.1 400") && invalid.contains("Unknown command"));
+        assert!(invalid.contains(r#""error":{"code":400,"message":"Unknown command"#));
+        let queued = request(address, "POST", "/jobs", r#"{"command":"review"}"#).await;
+        assert!(queued.starts_with("HTTP/1.1 202"));
+        assert!(queued.contains(r#""id":"1""#) && queued.contains(r#""position":1"#));
+        let cancelled = request(address, "POST", "/jobs/1/cancel", "").await;
+        assert!(cancelled.contains(r#""status":"cancelled""#));
+        let paused = request(address, "POST", "/jobs/1/pause", "").await;
+        assert!(paused.starts_with("HTTP/1.1 409"));
+
+        // The stream of a finished job ends with its final state
+        let events = request(address, "GET", "/jobs/1/events", "").await;
+        assert!(events.contains("event: finished\ndata: {"));
+        let missing = request(address, "GET", "/jobs/2", "").await;
+        assert!(missing.starts_with("HTTP/1.1 404"));
+    }
+
+    #[tokio::test]
+    async fn test_requests_need_the_token() {
+        let address = start().await;
+        let anonymous = send(address, "GET /jobs HTTP/1.1", "", "").await;
+        assert!(anonymous.starts_with("HTTP/1.1 401"));
+        let wrong = send(
+            address,
+            "GET /jobs HTTP/1.1",
+            "Authorization: Bearer secreT\r\n",
+            "",
+        )
+        .await;
+        assert!(wrong.starts_with("HTTP/1.1 401"));
+    }
+
+    #[tokio::test]
+    async fn test_submissions_must_be_json() {
+        let address = start().await;
+        // What a form or `fetch` of another site can send without a preflight
+        let headers = "Authorization: Bearer secret\r\nContent-Type: text/plain\r\n";
+        let body = r#"{"command":"code","prompt":"rm -rf"}"#;
+        let plain = send(address, "POST /jobs HTTP/1.1", headers, body).await;
+        assert!(plain.starts_with("HTTP/1.1 415"));
+        let untyped = send(
+            address,
+            "POST /jobs/1/cancel HTTP/1.1",
+            "Authorization: Bearer secret\r\n",
+            "",
+        )
+        .await;
+        assert!(untyped.starts_with("HTTP/1.1 415"));
+        let jobs = request(address, "GET", "/jobs", "").await;
+        assert!(jobs.contains(r#"{"jobs":[]}"#));
+    }
+
+    #[tokio::test]
+    async fn test_requests_from_other_origins_are_refused() {
+        let address = start().await;
+        let headers = "Authorization: Bearer secret\r\nContent-Type: application/json\r\n\
+                       Origin: https://evil.example\r\n";
+        let foreign = send(
+            address,
+            "POST /jobs HTTP/1.1",
+            headers,
+            r#"{"command":"review"}"#,
+        )
+        .await;
+        assert!(foreign.starts_with("HTTP/1.1 403"));
+
+        // A rebound DNS name reaches the port with the attacker's host name
+        let request = "GET /jobs HTTP/1.1\r\nHost: evil.example\r\nAuthorization: Bearer secret\r\n\
+                       Connection: close\r\n\r\n";
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut rebound = String::new();
+        stream.read_to_string(&mut rebound).await.unwrap();
+        assert!(rebound.starts_with("HTTP/1.1 403"));
+    }
+}
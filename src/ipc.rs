@@ -0,0 +1,233 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::event_bus::{Event, EventBus};
+use crate::event_log::EventRecord;
+use crate::run_control::{APPROVAL_REQUESTED_EVENT, ControlMessage, RunControl};
+
+/// Endpoint used when `[ipc] path` is not set: a socket in the state directory,
+/// or a named pipe on Windows
+pub fn default_endpoint(state_dir: &Path) -> String {
+    if cfg!(windows) {
+        r"\\.\pipe\cli_engineer".to_string()
+    } else {
+        state_dir.join("cli_engineer.sock").display().to_string()
+    }
+}
+
+/// Local endpoint streaming the run's events as JSON lines and accepting
+/// control messages, one JSON object per line. Stops serving when dropped.
+pub struct IpcBridge {
+    endpoint: String,
+    task: JoinHandle<()>,
+}
+
+impl Drop for IpcBridge {
+    fn drop(&mut self) {
+        self.task.abort();
+        #[cfg(unix)]
+        let _ = std::fs::remove_file(&self.endpoint);
+    }
+}
+
+/// Start serving on a Unix socket at `endpoint`
+#[cfg(unix)]
+pub fn start(endpoint: &str, bus: Arc<EventBus>, control: Arc<RunControl>) -> Result<IpcBridge> {
+    let path = Path::new(endpoint);
+    if path.exists() {
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            anyhow::bail!("Another run is already serving on {}", endpoint);
+        }
+        // Left behind by a run that did not exit cleanly
+        std::fs::remove_file(path).context("Failed to remove stale socket")?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create socket directory")?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", endpoint))?;
+
+    let task = tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve_client(stream, bus.clone(), control.clone()));
+                }
+                Err(e) => {
+                    warn!("IPC bridge stopped accepting connections: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+    Ok(IpcBridge {
+        endpoint: endpoint.to_string(),
+        task,
+    })
+}
+
+/// Start serving on the named pipe `endpoint`
+#[cfg(windows)]
+pub fn start(endpoint: &str, bus: Arc<EventBus>, control: Arc<RunControl>) -> Result<IpcBridge> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(endpoint)
+        .with_context(|| format!("Failed to create pipe {}", endpoint))?;
+    let pipe_name = endpoint.to_string();
+    let task = tokio::spawn(async move {
+        loop {
+            if let Err(e) = server.connect().await {
+                warn!("IPC bridge stopped accepting connections: {}", e);
+                break;
+            }
+            // Each client gets its own pipe instance; create the next one to wait on
+            let next = match ServerOptions::new().create(&pipe_name) {
+                Ok(next) => next,
+                Err(e) => {
+                    warn!("IPC bridge stopped accepting connections: {}", e);
+                    tokio::spawn(serve_client(server, bus.clone(), control.clone()));
+                    break;
+                }
+            };
+            let client = std::mem::replace(&mut server, next);
+            tokio::spawn(serve_client(client, bus.clone(), control.clone()));
+        }
+    });
+    Ok(IpcBridge {
+        endpoint: endpoint.to_string(),
+        task,
+    })
+}
+
+/// Stream events to one client and apply the control messages it sends.
+/// Malformed or rejected messages are answered with `{"error": ...}`.
+async fn serve_client<S>(stream: S, bus: Arc<EventBus>, control: Arc<RunControl>)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    info!("IPC client connected");
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+    let mut events = bus.subscribe();
+
+    // A client attaching while a plan waits for approval would otherwise never see it
+    if let Some(request) = control.approval_request() {
+        let record = EventRecord {
+            timestamp: chrono::Utc::now(),
+            event: Event::Custom {
+                event_type: APPROVAL_REQUESTED_EVENT.to_string(),
+                data: request,
+            },
+        };
+        let line = format!("{}\n", serde_json::to_string(&record).unwrap_or_default());
+        if writer.write_all(line.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        let reply = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let record = EventRecord {
+                        timestamp: chrono::Utc::now(),
+                        event,
+                    };
+                    serde_json::to_string(&record).unwrap_or_default()
+                }
+                Err(RecvError::Lagged(missed)) => serde_json::json!({
+                    "error": format!("{} events were dropped because the client fell behind", missed)
+                })
+                .to_string(),
+                Err(RecvError::Closed) => break,
+            },
+            line = lines.next_line() => match line {
+                Ok(Some(line)) if line.trim().is_empty() => continue,
+                Ok(Some(line)) => match handle_message(&line, &bus, &control).await {
+                    Ok(()) => continue,
+                    Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+                },
+                _ => break,
+            },
+        };
+        if writer
+            .write_all(format!("{}\n", reply).as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+    info!("IPC client disconnected");
+}
+
+/// Apply a control message and announce it to every subscriber
+async fn handle_message(line: &str, bus: &EventBus, control: &RunControl) -> Result<()> {
+    let message: ControlMessage = serde_json::from_str(line).with_context(|| {
+        format!(
+            "Invalid control message {}; expected e.g. {{\"command\": \"pause\"}}",
+            line.trim()
+        )
+    })?;
+    control.send(message.clone(), bus, "ipc").await?;
+    info!("IPC client sent {:?}", message);
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ipc_bridge() {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_ipc_{}", uuid::Uuid::new_v4()));
+        let endpoint = default_endpoint(&dir);
+        let bus = Arc::new(EventBus::new(100));
+        let control = Arc::new(RunControl::new());
+        let bridge = start(&endpoint, bus.clone(), control.clone()).unwrap();
+        assert!(start(&endpoint, bus.clone(), control.clone()).is_err());
+
+        let stream = tokio::net::UnixStream::connect(&endpoint).await.unwrap();
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut lines = BufReader::new(reader).lines();
+        // Seeing the announcement of a control message shows the client is subscribed
+        writer
+            .write_all(b"{\"command\": \"pause\"}\n")
+            .await
+            .unwrap();
+        let paused: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(paused["event"]["Custom"]["event_type"], "run_paused");
+
+        bus.emit(Event::SystemReady).await.unwrap();
+        let ready: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(ready["event"], "SystemReady");
+
+        writer
+            .write_all(b"{\"command\": \"approve\"}\n")
+            .await
+            .unwrap();
+        let error: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(error["error"], "No plan is awaiting approval");
+
+        writer
+            .write_all(b"{\"command\": \"cancel\"}\n")
+            .await
+            .unwrap();
+        lines.next_line().await.unwrap();
+        assert!(control.checkpoint().await.is_err());
+
+        drop(bridge);
+        assert!(!Path::new(&endpoint).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,3 +1,5 @@
+use crate::executor::StepResult;
+use crate::planner::Plan;
 use crate::reviewer::{Issue, ReviewResult};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -20,6 +22,33 @@ pub struct IterationContext {
 
     /// Summary of what has been accomplished so far
     pub progress_summary: String,
+
+    /// Guidance the user gave between iterations, oldest first
+    #[serde(default)]
+    pub user_guidance: Vec<String>,
+
+    /// Steps that succeeded in earlier iterations, carried forward so they are not redone
+    #[serde(default)]
+    pub completed_steps: Vec<StepOutcome>,
+
+    /// Steps of the last iteration that failed or were not run, to be redone
+    #[serde(default)]
+    pub failed_steps: Vec<StepOutcome>,
+
+    /// Decisions, conventions and corrections recalled from earlier runs
+    #[serde(default)]
+    pub memories: Vec<String>,
+}
+
+/// An executed step, kept for the plans of later iterations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepOutcome {
+    pub description: String,
+    /// Iteration the step ran in
+    pub iteration: usize,
+    /// Names of the files the step produced
+    pub files: Vec<String>,
+    pub result: StepResult,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +77,10 @@ impl IterationContext {
             last_review: None,
             pending_issues: Vec::new(),
             progress_summary: String::new(),
+            user_guidance: Vec::new(),
+            completed_steps: Vec::new(),
+            failed_steps: Vec::new(),
+            memories: Vec::new(),
         }
     }
 
@@ -59,13 +92,34 @@ impl IterationContext {
         // Extract issues that need fixing
         self.pending_issues = review.issues.clone();
 
-        // Mark files with issues
+        // Only the latest review's issues are pending
+        for file_info in self.existing_files.values_mut() {
+            file_info.has_issues = false;
+            file_info.issues.clear();
+        }
+
+        // Mark files with issues. Locations may carry a line range and may be
+        // relative to the workspace rather than the artifact directory.
         for issue in &review.issues {
-            if let Some(file) = issue.location.as_ref() {
-                if let Some(file_info) = self.existing_files.get_mut(file) {
-                    file_info.has_issues = true;
-                    file_info.issues.push(issue.description.clone());
-                }
+            let Some(path) = issue.location_path() else {
+                continue;
+            };
+            let path = path.trim_start_matches("./");
+            let matching = self.existing_files.iter_mut().find(|(name, _)| {
+                let name = name.trim_start_matches("./");
+                name == path
+                    || name.ends_with(&format!("/{}", path))
+                    || path.ends_with(&format!("/{}", name))
+            });
+            if let Some((_, file_info)) = matching {
+                file_info.has_issues = true;
+                file_info.issues.push(match issue.location_lines() {
+                    Some((start, end)) if start != end => {
+                        format!("lines {}-{}: {}", start, end, issue.description)
+                    }
+                    Some((line, _)) => format!("line {}: {}", line, issue.description),
+                    None => issue.description.clone(),
+                });
             }
         }
 
@@ -75,6 +129,80 @@ impl IterationContext {
     pub fn has_existing_files(&self) -> bool {
         !self.existing_files.is_empty()
     }
+
+    /// Carry forward the steps of `plan` that succeeded and keep those that did
+    /// not for the next plan to redo. `file_names` maps artifact ids to names.
+    pub fn record_steps(
+        &mut self,
+        plan: &Plan,
+        results: &[StepResult],
+        file_names: &HashMap<String, String>,
+    ) {
+        self.failed_steps.clear();
+        for step in &plan.steps {
+            let result = results.iter().find(|r| r.step_id == step.id);
+            let outcome = StepOutcome {
+                description: step.description.clone(),
+                iteration: self.iteration,
+                files: result
+                    .iter()
+                    .flat_map(|r| &r.artifacts_created)
+                    .filter_map(|id| file_names.get(id).cloned())
+                    .collect(),
+                result: result.cloned().unwrap_or_else(|| StepResult {
+                    step_id: step.id.clone(),
+                    success: false,
+                    output: String::new(),
+                    artifacts_created: Vec::new(),
+                    tokens_used: 0,
+                    error: Some("Not run".to_string()),
+                }),
+            };
+            let key = step_key(&step.description);
+            self.completed_steps.retain(|done| step_key(&done.description) != key);
+            if outcome.result.success {
+                self.completed_steps.push(outcome);
+            } else {
+                self.failed_steps.push(outcome);
+            }
+        }
+    }
+
+    /// Whether a completed step produced a file the last review found issues in
+    pub fn is_affected(&self, step: &StepOutcome) -> bool {
+        step.files.iter().any(|name| {
+            self.existing_files
+                .get(name)
+                .is_some_and(|file_info| file_info.has_issues)
+        })
+    }
+
+    /// Results of the steps of `plan` that repeat a completed step unaffected
+    /// by the review, to reuse instead of running them again
+    pub fn carried_forward(&self, plan: &Plan) -> Vec<StepResult> {
+        plan.steps
+            .iter()
+            .filter_map(|step| {
+                let key = step_key(&step.description);
+                let done = self.completed_steps.iter().find(|done| {
+                    step_key(&done.description) == key && !self.is_affected(done)
+                })?;
+                Some(StepResult {
+                    step_id: step.id.clone(),
+                    ..done.result.clone()
+                })
+            })
+            .collect()
+    }
+}
+
+/// A step description compared regardless of case and spacing
+fn step_key(description: &str) -> String {
+    description
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
 }
 
 impl fmt::Display for IterationContext {
@@ -84,6 +212,14 @@ impl fmt::Display for IterationContext {
         // Basic info
         output.push_str(&format!("Iteration #{}\n", self.iteration));
 
+        // What earlier runs learned about the project
+        if !self.memories.is_empty() {
+            output.push_str("\nProject memory from earlier runs:\n");
+            for memory in &self.memories {
+                output.push_str(&format!("  - {}\n", memory));
+            }
+        }
+
         // Existing files
         if !self.existing_files.is_empty() {
             output.push_str("\nExisting files:\n");
@@ -109,7 +245,50 @@ impl fmt::Display for IterationContext {
                 self.pending_issues.len()
             ));
             for issue in &self.pending_issues {
-                output.push_str(&format!("  - {}: {}\n", issue.severity, issue.description));
+                match &issue.location {
+                    Some(location) => output.push_str(&format!(
+                        "  - {} ({}): {}\n",
+                        issue.severity, location, issue.description
+                    )),
+                    None => output.push_str(&format!(
+                        "  - {}: {}\n",
+                        issue.severity, issue.description
+                    )),
+                }
+            }
+        }
+
+        // Steps done, and those the next plan should redo
+        let (affected, unaffected): (Vec<_>, Vec<_>) = self
+            .completed_steps
+            .iter()
+            .partition(|step| self.is_affected(step));
+        if !unaffected.is_empty() {
+            output.push_str("\nCompleted steps (done; their files are kept):\n");
+            for step in unaffected {
+                output.push_str(&format!("  - {}\n", step.description));
+            }
+        }
+        if !self.failed_steps.is_empty() || !affected.is_empty() {
+            output.push_str("\nSteps to redo:\n");
+            for step in &self.failed_steps {
+                let error = step.result.error.as_deref().unwrap_or("failed");
+                output.push_str(&format!("  - {} (failed: {})\n", step.description, error));
+            }
+            for step in affected {
+                output.push_str(&format!(
+                    "  - {} (its files have issues: {})\n",
+                    step.description,
+                    step.files.join(", ")
+                ));
+            }
+        }
+
+        // Guidance from the user
+        if !self.user_guidance.is_empty() {
+            output.push_str("\nGuidance from the user:\n");
+            for guidance in &self.user_guidance {
+                output.push_str(&format!("  - {}\n", guidance));
             }
         }
 
@@ -121,3 +300,108 @@ impl fmt::Display for IterationContext {
         write!(f, "{}", output)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::{ComplexityLevel, Step, StepCategory};
+    use crate::reviewer::{IssueCategory, IssueSeverity, QualityLevel};
+
+    fn plan(steps: &[&str]) -> Plan {
+        Plan {
+            goal: "Add a CLI".to_string(),
+            steps: steps
+                .iter()
+                .enumerate()
+                .map(|(i, description)| Step {
+                    id: format!("step_{}", i + 1),
+                    description: description.to_string(),
+                    category: StepCategory::CodeGeneration,
+                    inputs: Vec::new(),
+                    expected_outputs: Vec::new(),
+                    success_criteria: Vec::new(),
+                    estimated_tokens: 0,
+                    high_risk: false,
+                })
+                .collect(),
+            dependencies: HashMap::new(),
+            estimated_complexity: ComplexityLevel::Simple,
+        }
+    }
+
+    fn result(step_id: &str, success: bool, artifact: &str) -> StepResult {
+        StepResult {
+            step_id: step_id.to_string(),
+            success,
+            output: format!("output of {}", step_id),
+            artifacts_created: vec![artifact.to_string()],
+            tokens_used: 0,
+            error: (!success).then(|| "Model error".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_carries_forward_completed_steps() {
+        let mut context = IterationContext::new(1);
+        for name in ["src/main.rs", "src/args.rs"] {
+            context.add_file(
+                name.to_string(),
+                FileInfo {
+                    path: name.to_string(),
+                    language: "source".to_string(),
+                    description: String::new(),
+                    has_issues: false,
+                    issues: Vec::new(),
+                },
+            );
+        }
+        let names = HashMap::from([
+            ("id1".to_string(), "src/main.rs".to_string()),
+            ("id2".to_string(), "src/args.rs".to_string()),
+        ]);
+        let first = plan(&["Write main", "Parse arguments", "Add tests"]);
+        let results = [
+            result("step_1", true, "id1"),
+            result("step_2", true, "id2"),
+            result("step_3", false, "id3"),
+        ];
+        context.record_steps(&first, &results, &names);
+        assert_eq!(context.completed_steps.len(), 2);
+        assert_eq!(context.completed_steps[1].files, ["src/args.rs"]);
+        assert_eq!(context.failed_steps[0].description, "Add tests");
+
+        // The review finds an issue in the file of the second step
+        context.update_from_review(ReviewResult {
+            overall_quality: QualityLevel::Fair,
+            issues: vec![Issue {
+                severity: IssueSeverity::Major,
+                category: IssueCategory::Logic,
+                description: "Flags are ignored".to_string(),
+                location: Some("src/args.rs:3".to_string()),
+                suggestion: None,
+                patch: None,
+            }],
+            suggestions: Vec::new(),
+            ready_to_deploy: false,
+            summary: String::new(),
+        });
+        let shown = context.to_string();
+        assert!(shown.contains("Completed steps (done; their files are kept):\n  - Write main\n"));
+        assert!(shown.contains("  - Add tests (failed: Model error)\n"));
+        assert!(shown.contains("  - Parse arguments (its files have issues: src/args.rs)\n"));
+
+        // Only the unaffected step is reused when the next plan repeats it
+        let next = plan(&["write  main", "Parse arguments", "Add tests"]);
+        let carried = context.carried_forward(&next);
+        assert_eq!(carried.len(), 1);
+        assert_eq!(carried[0].step_id, "step_1");
+        assert_eq!(carried[0].output, "output of step_1");
+
+        context.iteration = 2;
+        let results = [carried[0].clone(), result("step_3", true, "id3")];
+        context.record_steps(&next, &results, &names);
+        assert_eq!(context.completed_steps.len(), 2);
+        assert_eq!(context.completed_steps[0].files, ["src/main.rs"]);
+        assert_eq!(context.failed_steps[0].description, "Parse arguments");
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::executor::StepResult;
+use crate::iteration_context::IterationContext;
+use crate::planner::Plan;
+use crate::reviewer::ReviewResult;
+
+/// Account of a single iteration, written to `iterations/<n>.md` in the run
+/// directory so the agent's trajectory can be audited after the fact
+pub struct IterationReport<'a> {
+    pub iteration: usize,
+    pub plan: &'a Plan,
+    pub results: &'a [StepResult],
+    /// Names of the artifacts, by id
+    pub file_names: &'a HashMap<String, String>,
+    /// `None` when the iteration ended before its review
+    pub review: Option<&'a ReviewResult>,
+    /// What the following iteration will attempt, if any
+    pub next: String,
+}
+
+impl IterationReport<'_> {
+    /// Render the report as markdown
+    pub fn to_markdown(&self) -> String {
+        let succeeded = self.results.iter().filter(|r| r.success).count();
+        let mut out = format!("# Iteration {}\n\n", self.iteration);
+        out.push_str(&format!("- **Goal:** {}\n", self.plan.goal));
+        out.push_str(&format!(
+            "- **Steps:** {} of {} succeeded\n",
+            succeeded,
+            self.plan.steps.len()
+        ));
+        if let Some(review) = self.review {
+            out.push_str(&format!(
+                "- **Review:** {:?} quality, {}\n",
+                review.overall_quality,
+                if review.ready_to_deploy {
+                    "ready to deploy"
+                } else {
+                    "not ready to deploy"
+                }
+            ));
+        }
+
+        out.push_str("\n## Plan\n\n");
+        for (i, step) in self.plan.steps.iter().enumerate() {
+            out.push_str(&format!(
+                "{}. {} _({:?}{})_\n",
+                i + 1,
+                step.description,
+                step.category,
+                if step.high_risk { ", high-risk" } else { "" }
+            ));
+        }
+
+        out.push_str("\n## Step Outcomes\n\n");
+        for (i, step) in self.plan.steps.iter().enumerate() {
+            let result = self.results.iter().find(|r| r.step_id == step.id);
+            let outcome = match result {
+                Some(result) if result.success => "✓ done".to_string(),
+                Some(result) => format!(
+                    "✗ failed: {}",
+                    result.error.as_deref().unwrap_or("no details")
+                ),
+                None => "not run".to_string(),
+            };
+            out.push_str(&format!("{}. {}: {}\n", i + 1, step.id, outcome));
+            let files: Vec<String> = result
+                .iter()
+                .flat_map(|r| &r.artifacts_created)
+                .map(|id| {
+                    format!(
+                        "`{}`",
+                        self.file_names.get(id).map_or(id.as_str(), String::as_str)
+                    )
+                })
+                .collect();
+            if !files.is_empty() {
+                out.push_str(&format!("   Files: {}\n", files.join(", ")));
+            }
+        }
+
+        out.push_str("\n## Review Findings\n\n");
+        match self.review {
+            None => out.push_str("_The iteration was not reviewed._\n"),
+            Some(review) => {
+                if !review.summary.is_empty() {
+                    out.push_str(&format!("{}\n\n", review.summary.trim()));
+                }
+                if review.issues.is_empty() {
+                    out.push_str("_No issues._\n");
+                }
+                for issue in &review.issues {
+                    let location = issue
+                        .location
+                        .as_deref()
+                        .map(|location| format!(" `{}`", location))
+                        .unwrap_or_default();
+                    out.push_str(&format!(
+                        "- **{}** ({:?}){}: {}\n",
+                        issue.severity, issue.category, location, issue.description
+                    ));
+                    if let Some(suggestion) = &issue.suggestion {
+                        out.push_str(&format!("  Suggestion: {}\n", suggestion));
+                    }
+                }
+            }
+        }
+
+        out.push_str("\n## Next\n\n");
+        out.push_str(self.next.trim_end());
+        out.push('\n');
+        out
+    }
+
+    /// Write the report into the `iterations` directory of `run_dir`
+    pub fn write_to(&self, run_dir: &Path) -> Result<PathBuf> {
+        let dir = run_dir.join("iterations");
+        std::fs::create_dir_all(&dir).context("Failed to create iteration report directory")?;
+        let path = dir.join(format!("{}.md", self.iteration));
+        std::fs::write(&path, self.to_markdown()).context("Failed to write iteration report")?;
+        Ok(path)
+    }
+}
+
+/// What iteration `next` will attempt, from the context it will start with
+pub fn next_attempt(next: usize, context: &IterationContext) -> String {
+    let mut out = format!("Iteration {} will plan again", next);
+    let redo: Vec<&str> = context
+        .failed_steps
+        .iter()
+        .chain(
+            context
+                .completed_steps
+                .iter()
+                .filter(|step| context.is_affected(step)),
+        )
+        .map(|step| step.description.as_str())
+        .collect();
+    if context.pending_issues.is_empty() && redo.is_empty() {
+        out.push_str(" with the review's feedback.\n");
+        return out;
+    }
+    out.push_str(" to:\n\n");
+    for issue in &context.pending_issues {
+        out.push_str(&format!(
+            "- Fix the {} issue: {}\n",
+            issue.severity.to_string().to_lowercase(),
+            issue.description
+        ));
+    }
+    for description in redo {
+        out.push_str(&format!("- Redo the step: {}\n", description));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iteration_context::FileInfo;
+    use crate::planner::{ComplexityLevel, Step, StepCategory};
+    use crate::reviewer::{Issue, IssueCategory, IssueSeverity, QualityLevel};
+
+    #[test]
+    fn test_iteration_report() {
+        let step = |id: &str, description: &str| Step {
+            id: id.to_string(),
+            description: description.to_string(),
+            category: StepCategory::CodeGeneration,
+            inputs: Vec::new(),
+            expected_outputs: Vec::new(),
+            success_criteria: Vec::new(),
+            estimated_tokens: 0,
+            high_risk: id == "step_2",
+        };
+        let plan = Plan {
+            goal: "Add a CLI".to_string(),
+            steps: vec![
+                step("step_1", "Write main"),
+                step("step_2", "Parse arguments"),
+            ],
+            dependencies: HashMap::new(),
+            estimated_complexity: ComplexityLevel::Simple,
+        };
+        let results = [
+            StepResult {
+                step_id: "step_1".to_string(),
+                success: true,
+                output: String::new(),
+                artifacts_created: vec!["id1".to_string()],
+                tokens_used: 0,
+                error: None,
+            },
+            StepResult {
+                step_id: "step_2".to_string(),
+                success: false,
+                output: String::new(),
+                artifacts_created: Vec::new(),
+                tokens_used: 0,
+                error: Some("Model error".to_string()),
+            },
+        ];
+        let review = ReviewResult {
+            overall_quality: QualityLevel::Fair,
+            issues: vec![Issue {
+                severity: IssueSeverity::Major,
+                category: IssueCategory::Logic,
+                description: "Flags are ignored".to_string(),
+                location: Some("src/main.rs:3".to_string()),
+                suggestion: Some("Parse them".to_string()),
+                patch: None,
+            }],
+            suggestions: Vec::new(),
+            ready_to_deploy: false,
+            summary: "Half done.".to_string(),
+        };
+        let file_names = HashMap::from([("id1".to_string(), "src/main.rs".to_string())]);
+        let mut context = IterationContext::new(1);
+        context.add_file(
+            "src/main.rs".to_string(),
+            FileInfo {
+                path: "src/main.rs".to_string(),
+                language: "source".to_string(),
+                description: String::new(),
+                has_issues: false,
+                issues: Vec::new(),
+            },
+        );
+        context.record_steps(&plan, &results, &file_names);
+        context.update_from_review(review.clone());
+        let report = IterationReport {
+            iteration: 1,
+            plan: &plan,
+            results: &results,
+            file_names: &file_names,
+            review: Some(&review),
+            next: next_attempt(2, &context),
+        };
+
+        let markdown = report.to_markdown();
+        assert!(markdown.starts_with("# Iteration 1\n\n- **Goal:** Add a CLI\n"));
+        assert!(markdown.contains("- **Steps:** 1 of 2 succeeded\n"));
+        assert!(markdown.contains("2. Parse arguments _(CodeGeneration, high-risk)_\n"));
+        assert!(markdown.contains("1. step_1: ✓ done\n   Files: `src/main.rs`\n"));
+        assert!(markdown.contains("2. step_2: ✗ failed: Model error\n"));
+        assert!(markdown.contains("- **Major** (Logic) `src/main.rs:3`: Flags are ignored\n"));
+        assert!(markdown.contains(
+            "## Next\n\nIteration 2 will plan again to:\n\n\
+             - Fix the major issue: Flags are ignored\n\
+             - Redo the step: Parse arguments\n\
+             - Redo the step: Write main\n"
+        ));
+
+        let dir = std::env::temp_dir().join(format!(
+            "cli_engineer_iteration_report_{}",
+            uuid::Uuid::new_v4()
+        ));
+        let path = report.write_to(&dir).unwrap();
+        assert_eq!(path, dir.join("iterations").join("1.md"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
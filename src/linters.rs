@@ -0,0 +1,297 @@
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use tokio::process::Command;
+
+use crate::reviewer::{Issue, IssueCategory, IssueSeverity};
+use crate::sandbox::Sandbox;
+
+/// Maximum number of diagnostics taken from a single tool
+const MAX_ISSUES_PER_TOOL: usize = 50;
+
+/// Run the linters that apply to the project at `root` and convert their
+/// diagnostics into review issues. Tools that are not installed are skipped.
+/// With a sandbox they run in its container instead of on the host.
+pub async fn run_linters(root: &Path, timeout: Duration, sandbox: Option<&Sandbox>) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    if root.join("Cargo.toml").exists()
+        && let Some(output) = run_tool(
+            root,
+            "cargo",
+            &[
+                "clippy",
+                "--message-format=json",
+                "--quiet",
+                "--all-targets",
+            ],
+            timeout,
+            sandbox,
+        )
+        .await
+    {
+        let found = parse_clippy(&output);
+        info!("cargo clippy reported {} diagnostics", found.len());
+        issues.extend(found);
+    }
+
+    if has_python_project(root)
+        && let Some(output) = run_tool(
+            root,
+            "ruff",
+            &["check", "--output-format", "json", "--exit-zero", "."],
+            timeout,
+            sandbox,
+        )
+        .await
+    {
+        let found = parse_ruff(&output);
+        info!("ruff reported {} diagnostics", found.len());
+        issues.extend(found);
+    }
+
+    if root.join("package.json").exists()
+        && let Some(output) = run_tool(
+            root,
+            "npx",
+            &["--no-install", "eslint", "--format", "json", "."],
+            timeout,
+            sandbox,
+        )
+        .await
+    {
+        let found = parse_eslint(&output, root);
+        info!("eslint reported {} diagnostics", found.len());
+        issues.extend(found);
+    }
+
+    issues
+}
+
+fn has_python_project(root: &Path) -> bool {
+    [
+        "pyproject.toml",
+        "setup.py",
+        "requirements.txt",
+        "ruff.toml",
+    ]
+    .iter()
+    .any(|f| root.join(f).exists())
+}
+
+/// Run a tool and return its stdout, or None if it is unavailable or times out.
+/// Linters exit non-zero when they find problems, so the exit code is ignored.
+pub(crate) async fn run_tool(
+    root: &Path,
+    program: &str,
+    args: &[&str],
+    timeout: Duration,
+    sandbox: Option<&Sandbox>,
+) -> Option<String> {
+    if let Some(sandbox) = sandbox {
+        return match sandbox.output(root, program, args, timeout).await {
+            // The runtime answered, so this is the image's "command not found"
+            Ok(output) if output.status.code() == Some(127) => {
+                debug!("{} unavailable in the sandbox, skipping", program);
+                None
+            }
+            Ok(output) => Some(String::from_utf8_lossy(&output.stdout).to_string()),
+            Err(e) => {
+                warn!("Failed to run {} in the sandbox: {:#}", program, e);
+                None
+            }
+        };
+    }
+
+    let child = Command::new(program)
+        .args(args)
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn();
+
+    let child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("{} unavailable, skipping: {}", program, e);
+            return None;
+        }
+    };
+
+    match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) => Some(String::from_utf8_lossy(&output.stdout).to_string()),
+        Ok(Err(e)) => {
+            warn!("Failed to run {}: {}", program, e);
+            None
+        }
+        Err(_) => {
+            warn!("{} timed out after {}s", program, timeout.as_secs());
+            None
+        }
+    }
+}
+
+/// Parse `cargo clippy --message-format=json` output
+pub fn parse_clippy(output: &str) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    for line in output.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if value["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &value["message"];
+        let level = message["level"].as_str().unwrap_or("");
+        let (severity, category) = match level {
+            "error" => (IssueSeverity::Critical, IssueCategory::Logic),
+            "warning" => (IssueSeverity::Minor, IssueCategory::BestPractices),
+            _ => continue,
+        };
+
+        let Some(span) = message["spans"]
+            .as_array()
+            .and_then(|spans| spans.iter().find(|s| s["is_primary"] == true))
+        else {
+            continue;
+        };
+
+        let code = message["code"]["code"].as_str().unwrap_or(level);
+        let suggestion = message["children"].as_array().and_then(|children| {
+            children
+                .iter()
+                .find(|c| c["level"] == "help")
+                .and_then(|c| c["message"].as_str())
+                .map(|s| s.to_string())
+        });
+
+        issues.push(Issue {
+            severity,
+            category,
+            description: format!(
+                "[{}] {}",
+                code,
+                message["message"].as_str().unwrap_or_default()
+            ),
+            location: Some(format!(
+                "{}:{}",
+                span["file_name"].as_str().unwrap_or_default(),
+                span["line_start"].as_u64().unwrap_or(0)
+            )),
+            suggestion,
+            patch: None,
+        });
+
+        if issues.len() >= MAX_ISSUES_PER_TOOL {
+            break;
+        }
+    }
+
+    issues
+}
+
+/// Parse `ruff check --output-format json` output
+pub fn parse_ruff(output: &str) -> Vec<Issue> {
+    let Ok(diagnostics) = serde_json::from_str::<Vec<serde_json::Value>>(output) else {
+        return Vec::new();
+    };
+
+    diagnostics
+        .iter()
+        .take(MAX_ISSUES_PER_TOOL)
+        .map(|d| {
+            let code = d["code"].as_str().unwrap_or("ruff");
+            // Pyflakes (F) rules catch real bugs such as undefined names
+            let (severity, category) = if code.starts_with('F') {
+                (IssueSeverity::Major, IssueCategory::Logic)
+            } else {
+                (IssueSeverity::Minor, IssueCategory::CodeStyle)
+            };
+            Issue {
+                severity,
+                category,
+                description: format!("[{}] {}", code, d["message"].as_str().unwrap_or_default()),
+                location: Some(format!(
+                    "{}:{}",
+                    d["filename"].as_str().unwrap_or_default(),
+                    d["location"]["row"].as_u64().unwrap_or(0)
+                )),
+                suggestion: d["fix"]["message"].as_str().map(|s| s.to_string()),
+                patch: None,
+            }
+        })
+        .collect()
+}
+
+/// Parse `eslint --format json` output
+pub fn parse_eslint(output: &str, root: &Path) -> Vec<Issue> {
+    let Ok(files) = serde_json::from_str::<Vec<serde_json::Value>>(output) else {
+        return Vec::new();
+    };
+
+    let mut issues = Vec::new();
+    for file in &files {
+        let path = file["filePath"].as_str().unwrap_or_default();
+        let path = Path::new(path)
+            .strip_prefix(root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string());
+
+        for message in file["messages"].as_array().into_iter().flatten() {
+            let severity = if message["severity"].as_u64() == Some(2) {
+                IssueSeverity::Major
+            } else {
+                IssueSeverity::Minor
+            };
+            issues.push(Issue {
+                severity,
+                category: IssueCategory::BestPractices,
+                description: format!(
+                    "[{}] {}",
+                    message["ruleId"].as_str().unwrap_or("eslint"),
+                    message["message"].as_str().unwrap_or_default()
+                ),
+                location: Some(format!(
+                    "{}:{}",
+                    path,
+                    message["line"].as_u64().unwrap_or(0)
+                )),
+                suggestion: None,
+                patch: None,
+            });
+            if issues.len() >= MAX_ISSUES_PER_TOOL {
+                return issues;
+            }
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_clippy() {
+        let output = r#"{"reason":"compiler-artifact","target":{}}
+{"reason":"compiler-message","message":{"level":"warning","message":"this `if` statement can be collapsed","code":{"code":"clippy::collapsible_if"},"spans":[{"file_name":"src/main.rs","line_start":12,"is_primary":true}],"children":[{"level":"help","message":"collapse nested if block"}]}}
+{"reason":"compiler-message","message":{"level":"error","message":"mismatched types","code":{"code":"E0308"},"spans":[{"file_name":"src/lib.rs","line_start":3,"is_primary":true}],"children":[]}}"#;
+
+        let issues = parse_clippy(output);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].severity, IssueSeverity::Minor);
+        assert_eq!(issues[0].location.as_deref(), Some("src/main.rs:12"));
+        assert_eq!(
+            issues[0].suggestion.as_deref(),
+            Some("collapse nested if block")
+        );
+        assert_eq!(issues[1].severity, IssueSeverity::Critical);
+        assert!(issues[1].description.starts_with("[E0308]"));
+    }
+}
@@ -1,9 +1,12 @@
 use crate::config::Config;
 use crate::event_bus::{Event, EventBus, EventEmitter};
 use crate::impl_event_emitter;
+use crate::logger;
 use anyhow::Result;
 use async_trait::async_trait;
+use serde_json::Value;
 use std::sync::Arc;
+use std::time::Instant;
 
 /// Trait representing an LLM provider.
 #[async_trait]
@@ -28,6 +31,78 @@ pub trait LLMProvider: Send + Sync {
     fn handles_own_metrics(&self) -> bool {
         false
     }
+
+    /// Embed texts for retrieval, using `model` or the provider's default
+    /// embedding model. Providers without an embedding API return an error.
+    async fn embed(&self, _texts: &[String], _model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+        Err(anyhow::anyhow!("{} does not support embeddings", self.name()))
+    }
+
+    /// Whether the provider's API lets the model call tools, see `send_with_tools`
+    fn supports_tools(&self) -> bool {
+        false
+    }
+
+    /// Continue a conversation in which the model can call `tools`, through
+    /// the provider's tool calling API
+    async fn send_with_tools(
+        &self,
+        _messages: &[ToolMessage],
+        _tools: &[ToolSpec],
+    ) -> Result<ToolResponse> {
+        Err(anyhow::anyhow!("{} does not support tool calling", self.name()))
+    }
+}
+
+/// A tool offered to the model through a provider's tool calling API
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// JSON schema of the arguments
+    pub parameters: Value,
+}
+
+/// A call of a tool by the model
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    /// Id the provider pairs the call's result with
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+/// One message of a conversation with tools
+#[derive(Debug, Clone)]
+pub enum ToolMessage {
+    User(String),
+    /// A response of the model and the tools it called
+    Assistant { text: String, calls: Vec<ToolCall> },
+    /// The output of each call of the previous response, by call id
+    Results(Vec<(String, String)>),
+}
+
+impl ToolMessage {
+    /// Characters of text the message holds
+    pub fn size(&self) -> usize {
+        match self {
+            ToolMessage::User(text) => text.len(),
+            ToolMessage::Assistant { text, calls } => {
+                text.len()
+                    + calls
+                        .iter()
+                        .map(|call| call.arguments.to_string().len())
+                        .sum::<usize>()
+            }
+            ToolMessage::Results(results) => results.iter().map(|(_, output)| output.len()).sum(),
+        }
+    }
+}
+
+/// A response of the model in a conversation with tools
+#[derive(Debug, Clone, Default)]
+pub struct ToolResponse {
+    pub text: String,
+    pub calls: Vec<ToolCall>,
 }
 
 /// Dummy provider used when no remote LLM is available.
@@ -71,6 +146,57 @@ impl LLMProvider for LocalProvider {
     }
 }
 
+/// Provider prefixing prompts with a system prompt before passing them on
+struct SystemPromptProvider {
+    inner: Arc<LLMManager>,
+    system_prompt: String,
+}
+
+#[async_trait]
+impl LLMProvider for SystemPromptProvider {
+    fn name(&self) -> &str {
+        self.inner.provider().name()
+    }
+
+    fn context_size(&self) -> usize {
+        self.inner.get_context_size()
+    }
+
+    async fn send_prompt(&self, prompt: &str) -> Result<String> {
+        self.inner
+            .send_prompt(&format!("{}\n\n{}", self.system_prompt, prompt))
+            .await
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.provider().model_name()
+    }
+
+    fn handles_own_metrics(&self) -> bool {
+        true
+    }
+
+    async fn embed(&self, texts: &[String], model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed(texts, model).await
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.inner.supports_tools()
+    }
+
+    async fn send_with_tools(
+        &self,
+        messages: &[ToolMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse> {
+        let mut messages = messages.to_vec();
+        if let Some(ToolMessage::User(first)) = messages.first_mut() {
+            *first = format!("{}\n\n{}", self.system_prompt, first);
+        }
+        self.inner.send_with_tools(&messages, tools).await
+    }
+}
+
 /// Manager that keeps track of multiple providers and context limits.
 pub struct LLMManager {
     providers: Vec<Box<dyn LLMProvider>>,
@@ -92,8 +218,20 @@ impl LLMManager {
         }
     }
 
+    /// A manager sending `system_prompt` ahead of every prompt through `inner`,
+    /// which reports the calls, for the agents of the multi-agent mode
+    pub fn with_system_prompt(inner: Arc<LLMManager>, system_prompt: String) -> Self {
+        Self {
+            providers: vec![Box::new(SystemPromptProvider {
+                inner,
+                system_prompt,
+            })],
+            event_bus: None,
+            config: None,
+        }
+    }
+
     /// Get the active provider.
-    #[allow(dead_code)]
     pub fn provider(&self) -> &dyn LLMProvider {
         &*self.providers[0]
     }
@@ -107,13 +245,93 @@ impl LLMManager {
         }
     }
 
+    /// Embed texts with the active provider.
+    pub async fn embed(&self, texts: &[String], model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+        match self.providers.first() {
+            Some(provider) => provider.embed(texts, model).await,
+            None => Err(anyhow::anyhow!("No providers available")),
+        }
+    }
+
     /// Send a prompt to the first available provider.
+    #[tracing::instrument(
+        name = "llm.call",
+        skip_all,
+        fields(
+            llm.provider = tracing::field::Empty,
+            llm.model = tracing::field::Empty,
+            llm.tokens = tracing::field::Empty,
+            llm.cost = tracing::field::Empty,
+        )
+    )]
     pub async fn send_prompt(&self, prompt: &str) -> anyhow::Result<String> {
         if self.providers.is_empty() {
             return Err(anyhow::anyhow!("No providers available"));
         }
 
-        let provider = &self.providers[0];
+        let provider = &*self.providers[0];
+        let started = self.call_started(provider).await;
+        let result = logger::with_provider(provider.name(), provider.send_prompt(prompt)).await;
+        self.call_finished(
+            provider,
+            prompt.len(),
+            result.as_ref().map(|response| response.len()),
+            started,
+        )
+        .await;
+        result
+    }
+
+    /// Whether the active provider lets the model call tools through its API
+    pub fn supports_tools(&self) -> bool {
+        self.providers
+            .first()
+            .is_some_and(|provider| provider.supports_tools())
+    }
+
+    /// Continue a conversation with tools with the active provider
+    #[tracing::instrument(
+        name = "llm.call",
+        skip_all,
+        fields(
+            llm.provider = tracing::field::Empty,
+            llm.model = tracing::field::Empty,
+            llm.tokens = tracing::field::Empty,
+            llm.cost = tracing::field::Empty,
+        )
+    )]
+    pub async fn send_with_tools(
+        &self,
+        messages: &[ToolMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse> {
+        let Some(provider) = self.providers.first() else {
+            return Err(anyhow::anyhow!("No providers available"));
+        };
+        let provider = &**provider;
+        let started = self.call_started(provider).await;
+        let result = logger::with_provider(
+            provider.name(),
+            provider.send_with_tools(messages, tools),
+        )
+        .await;
+        let sent = messages.iter().map(ToolMessage::size).sum();
+        let received = result.as_ref().map(|response| {
+            ToolMessage::Assistant {
+                text: response.text.clone(),
+                calls: response.calls.clone(),
+            }
+            .size()
+        });
+        self.call_finished(provider, sent, received, started).await;
+        result
+    }
+
+    /// Record the provider on the call's span and announce the call
+    async fn call_started(&self, provider: &dyn LLMProvider) -> Instant {
+        let span = tracing::Span::current();
+        span.record("llm.provider", provider.name());
+        span.record("llm.model", provider.model_name());
 
         // Emit API call started event
         if let Some(bus) = &self.event_bus {
@@ -124,18 +342,26 @@ impl LLMManager {
                 })
                 .await;
         }
+        Instant::now()
+    }
 
-        // Send prompt
-        let result = provider.send_prompt(prompt).await;
-
+    /// Announce a call's completion, with its estimated usage unless the
+    /// provider reports its own, or its error
+    async fn call_finished(
+        &self,
+        provider: &dyn LLMProvider,
+        prompt_len: usize,
+        result: Result<usize, &anyhow::Error>,
+        started: Instant,
+    ) {
         // Emit completion or error event
         if let Some(bus) = &self.event_bus {
-            match &result {
-                Ok(response) => {
+            match result {
+                Ok(response_len) => {
                     if !provider.handles_own_metrics() {
                         // Calculate approximate token counts (rough estimate: 1 token ≈ 4 characters)
-                        let input_tokens = prompt.len() / 4;
-                        let output_tokens = response.len() / 4;
+                        let input_tokens = prompt_len / 4;
+                        let output_tokens = response_len / 4;
                         let total_tokens = input_tokens + output_tokens;
 
                         // Calculate cost based on model configuration
@@ -146,6 +372,9 @@ impl LLMManager {
                                 provider: provider.name().to_string(),
                                 tokens: total_tokens,
                                 cost,
+                                duration_ms: started.elapsed().as_millis() as u64,
+                                output_tokens,
+                                first_token_ms: None,
                             })
                             .await;
                     }
@@ -160,8 +389,6 @@ impl LLMManager {
                 }
             }
         }
-
-        result
     }
 
     /// Calculate cost for API call based on provider configuration
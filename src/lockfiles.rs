@@ -0,0 +1,249 @@
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use serde_json::Value;
+
+/// Lockfiles whose pinned dependencies can be audited, with their OSV ecosystem
+pub const LOCKFILES: [(&str, &str); 3] = [
+    ("Cargo.lock", "crates.io"),
+    ("package-lock.json", "npm"),
+    ("requirements.txt", "PyPI"),
+];
+
+/// A package version pinned by a lockfile
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependency {
+    /// Package ecosystem as named by OSV: `crates.io`, `npm` or `PyPI`
+    pub ecosystem: &'static str,
+    pub name: String,
+    pub version: String,
+    /// Lockfile the package was found in, relative to the project root
+    pub lockfile: &'static str,
+    /// Declared license, usually an SPDX expression, when the lockfile or
+    /// the local package cache records it
+    pub license: Option<String>,
+}
+
+impl Dependency {
+    /// Package URL identifying the package across tools, e.g. `pkg:cargo/time@0.1.43`
+    pub fn purl(&self) -> String {
+        let kind = match self.ecosystem {
+            "crates.io" => "cargo",
+            "npm" => "npm",
+            _ => "pypi",
+        };
+        format!(
+            "pkg:{}/{}@{}",
+            kind,
+            self.name.replace('@', "%40"),
+            self.version
+        )
+    }
+}
+
+/// A package and version pinned by a lockfile, with its license if recorded there
+type Pinned = (String, String, Option<String>);
+
+/// Dependencies pinned by the lockfiles found at `root`, without duplicates
+pub fn detect_dependencies(root: &Path) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    for (lockfile, ecosystem) in LOCKFILES {
+        let Ok(content) = std::fs::read_to_string(root.join(lockfile)) else {
+            continue;
+        };
+        let pinned = match lockfile {
+            "Cargo.lock" => parse_cargo_lock(&content),
+            "package-lock.json" => parse_package_lock(&content),
+            _ => parse_requirements(&content),
+        };
+        for (name, version, license) in pinned {
+            // Cargo.lock has no licenses, but the sources of fetched crates do
+            let license = license.or_else(|| {
+                (ecosystem == "crates.io")
+                    .then(|| crate_license(&name, &version))
+                    .flatten()
+            });
+            let dependency = Dependency {
+                ecosystem,
+                name,
+                version,
+                lockfile,
+                license,
+            };
+            if !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+    }
+    dependencies
+}
+
+/// Registry packages of a `Cargo.lock`; workspace members and git or path
+/// dependencies have no published advisories
+fn parse_cargo_lock(content: &str) -> Vec<Pinned> {
+    let lock: toml::Value = match toml::from_str(content) {
+        Ok(lock) => lock,
+        Err(e) => {
+            warn!("Cannot parse Cargo.lock: {}", e);
+            return Vec::new();
+        }
+    };
+    lock.get("package")
+        .and_then(|packages| packages.as_array())
+        .into_iter()
+        .flatten()
+        .filter(|package| {
+            package
+                .get("source")
+                .and_then(|source| source.as_str())
+                .is_some_and(|source| source.starts_with("registry+"))
+        })
+        .filter_map(|package| {
+            Some((
+                package.get("name")?.as_str()?.to_string(),
+                package.get("version")?.as_str()?.to_string(),
+                None,
+            ))
+        })
+        .collect()
+}
+
+/// Installed packages of a `package-lock.json`, from `packages` (lockfile
+/// version 2 and 3) or the nested `dependencies` of version 1
+fn parse_package_lock(content: &str) -> Vec<Pinned> {
+    let lock: Value = match serde_json::from_str(content) {
+        Ok(lock) => lock,
+        Err(e) => {
+            warn!("Cannot parse package-lock.json: {}", e);
+            return Vec::new();
+        }
+    };
+
+    if let Some(packages) = lock["packages"].as_object() {
+        return packages
+            .iter()
+            // "" is the project itself; linked packages are local
+            .filter(|(path, package)| !path.is_empty() && package["link"] != true)
+            .filter_map(|(path, package)| {
+                let name = package["name"].as_str().unwrap_or_else(|| {
+                    path.rsplit_once("node_modules/")
+                        .map_or(path, |(_, name)| name)
+                });
+                Some((
+                    name.to_string(),
+                    package["version"].as_str()?.to_string(),
+                    package["license"].as_str().map(str::to_string),
+                ))
+            })
+            .collect();
+    }
+
+    fn walk(dependencies: &Value, pinned: &mut Vec<Pinned>) {
+        for (name, dependency) in dependencies.as_object().into_iter().flatten() {
+            if let Some(version) = dependency["version"].as_str() {
+                pinned.push((name.clone(), version.to_string(), None));
+            }
+            walk(&dependency["dependencies"], pinned);
+        }
+    }
+    let mut pinned = Vec::new();
+    walk(&lock["dependencies"], &mut pinned);
+    pinned
+}
+
+/// Exactly pinned (`name==version`) requirements; ranges cannot be audited
+fn parse_requirements(content: &str) -> Vec<Pinned> {
+    content
+        .lines()
+        .filter_map(|line| {
+            // Drop comments and environment markers
+            let line = line.split('#').next()?.split(';').next()?.trim();
+            let (name, version) = line.split_once("==")?;
+            // `package[extra]==1.0` audits as `package`
+            let name = name.split('[').next()?.trim();
+            let version = version.split(',').next()?.trim();
+            (!name.is_empty() && !version.is_empty() && !version.contains('*'))
+                .then(|| (name.to_lowercase(), version.to_string(), None))
+        })
+        .collect()
+}
+
+/// The `license` of a crate's manifest in the Cargo registry cache, with the
+/// deprecated `/` separator written as `OR`
+fn crate_license(name: &str, version: &str) -> Option<String> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))?;
+    let registries = std::fs::read_dir(cargo_home.join("registry").join("src")).ok()?;
+    registries.flatten().find_map(|registry| {
+        let manifest = registry
+            .path()
+            .join(format!("{}-{}", name, version))
+            .join("Cargo.toml");
+        let manifest: toml::Value =
+            toml::from_str(&std::fs::read_to_string(manifest).ok()?).ok()?;
+        let license = manifest.get("package")?.get("license")?.as_str()?;
+        Some(license.replace('/', " OR "))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lockfiles() {
+        let cargo_lock = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "time"
+version = "0.1.43"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        assert_eq!(
+            parse_cargo_lock(cargo_lock),
+            vec![("time".to_string(), "0.1.43".to_string(), None)]
+        );
+
+        let package_lock = r#"{
+            "lockfileVersion": 3,
+            "packages": {
+                "": { "name": "app", "version": "1.0.0" },
+                "node_modules/lodash": { "version": "4.17.15", "license": "MIT" },
+                "node_modules/a/node_modules/@scope/b": { "version": "2.0.0" },
+                "node_modules/local": { "resolved": "packages/local", "link": true }
+            }
+        }"#;
+        let mut pinned = parse_package_lock(package_lock);
+        pinned.sort();
+        assert_eq!(
+            pinned,
+            vec![
+                ("@scope/b".to_string(), "2.0.0".to_string(), None),
+                (
+                    "lodash".to_string(),
+                    "4.17.15".to_string(),
+                    Some("MIT".to_string())
+                ),
+            ]
+        );
+        let version_1 = r#"{"dependencies": {"a": {"version": "1.0.0",
+            "dependencies": {"b": {"version": "2.0.0"}}}}}"#;
+        assert_eq!(parse_package_lock(version_1).len(), 2);
+
+        let requirements = "# pinned\nDjango[argon2]==3.2.0 ; python_version > '3'\n\
+            requests>=2.0\nflask == 2.0.1  # web\n-r other.txt\n";
+        assert_eq!(
+            parse_requirements(requirements),
+            vec![
+                ("django".to_string(), "3.2.0".to_string(), None),
+                ("flask".to_string(), "2.0.1".to_string(), None),
+            ]
+        );
+    }
+}
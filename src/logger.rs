@@ -1,39 +1,357 @@
-use std::fs::OpenOptions;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::future::Future;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 use chrono::Utc;
-use log::{LevelFilter, info};
+use log::{LevelFilter, Log, Metadata, Record};
 use simplelog::{SimpleLogger, Config};
 
-pub fn init(verbose: bool) {
-    let level = if verbose {
-        LevelFilter::Info
-    } else {
-        LevelFilter::Warn
+use crate::config::{LogFormat, LoggingConfig};
+use crate::redaction::redact_log_message;
+
+tokio::task_local! {
+    static LOG_CONTEXT: LogContext;
+}
+
+/// Step and provider the records logged by a task are about
+#[derive(Debug, Clone, Default)]
+struct LogContext {
+    step_id: Option<String>,
+    provider: Option<String>,
+}
+
+fn current_context() -> LogContext {
+    LOG_CONTEXT.try_with(LogContext::clone).unwrap_or_default()
+}
+
+/// Tag the records logged while `future` runs with the step `step_id`
+pub async fn in_step<F: Future>(step_id: &str, future: F) -> F::Output {
+    let mut context = current_context();
+    context.step_id = Some(step_id.to_string());
+    LOG_CONTEXT.scope(context, future).await
+}
+
+/// Tag the records logged while `future` runs with `provider`
+pub async fn with_provider<F: Future>(provider: &str, future: F) -> F::Output {
+    let mut context = current_context();
+    context.provider = Some(provider.to_string());
+    LOG_CONTEXT.scope(context, future).await
+}
+
+/// Level of each module: a default one, or that of the longest key of
+/// `logging.levels` the module's path starts with
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// Longest key first
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Levels that don't parse are reported by config validation, and ignored here
+    pub fn new(default: LevelFilter, levels: &HashMap<String, String>) -> Self {
+        let mut modules: Vec<(String, LevelFilter)> = levels
+            .iter()
+            .filter_map(|(module, level)| Some((module.clone(), level.parse().ok()?)))
+            .collect();
+        modules.sort_by_key(|(module, _)| std::cmp::Reverse(module.len()));
+        Self { default, modules }
+    }
+
+    /// Level of the records of `target`, a module path; this crate's modules
+    /// are named without the crate, as in `providers::openai`
+    pub fn level(&self, target: &str) -> LevelFilter {
+        let module = target.strip_prefix("cli_engineer::").unwrap_or(target);
+        self.modules
+            .iter()
+            .find(|(key, _)| module.starts_with(key.as_str()))
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most verbose level of any module
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+
+    pub fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+}
+
+/// Log file of a session, continued in a new file past a size limit
+#[derive(Clone)]
+pub struct LogFile {
+    dir: PathBuf,
+    /// Name of the first file without its extension: `cli_engineer_<timestamp>`
+    stem: String,
+    format: LogFormat,
+    /// 0 for no limit
+    max_bytes: u64,
+    state: Arc<Mutex<LogState>>,
+}
+
+/// The file records are appended to
+struct LogState {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    /// Number of the file, from 1
+    part: usize,
+}
+
+impl LogFile {
+    /// Create `cli_engineer_<timestamp>.log`, or `.jsonl` for JSON records, in
+    /// `dir` after deleting the log files `settings` no longer keeps
+    pub fn create(dir: &Path, settings: &LoggingConfig) -> Option<Self> {
+        let result = fs::create_dir_all(dir).and_then(|()| {
+            prune(dir, settings)?;
+            let stem = format!("cli_engineer_{}", Utc::now().format("%Y%m%d_%H%M%S"));
+            Self::open(dir, stem, settings.format, settings.max_file_mb * 1024 * 1024)
+        });
+        match result {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("Warning: Could not create log file: {}", e);
+                None
+            }
+        }
+    }
+
+    fn open(dir: &Path, stem: String, format: LogFormat, max_bytes: u64) -> std::io::Result<Self> {
+        let path = dir.join(file_name(&stem, 1, format));
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        // JSON logs hold records alone, for jq
+        if format == LogFormat::Text {
+            let started = Utc::now().format("%Y-%m-%d %H:%M:%S");
+            writeln!(file, "=== CLI Engineer Session Started: {} UTC ===\n", started)?;
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            stem,
+            format,
+            max_bytes,
+            state: Arc::new(Mutex::new(LogState {
+                path,
+                file,
+                written: 0,
+                part: 1,
+            })),
+        })
+    }
+
+    /// The file being written to
+    pub fn path(&self) -> PathBuf {
+        self.state.lock().map(|state| state.path.clone()).unwrap_or_default()
+    }
+
+    /// Append `record`, whose redacted message is `message`
+    pub fn write(&self, record: &Record, message: &str) {
+        let line = match self.format {
+            LogFormat::Text => {
+                format!("{} [{}] {}\n", Utc::now().format("%H:%M:%S"), record.level(), message)
+            }
+            LogFormat::Json => {
+                let context = current_context();
+                let record = serde_json::json!({
+                    "timestamp": Utc::now().to_rfc3339(),
+                    "level": record.level().to_string(),
+                    "module": record.module_path().unwrap_or(record.target()),
+                    "message": message,
+                    "step_id": context.step_id,
+                    "provider": context.provider,
+                });
+                format!("{}\n", record)
+            }
+        };
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let full = state.written > 0 && state.written + line.len() as u64 > self.max_bytes;
+        if self.max_bytes > 0 && full {
+            let part = state.part + 1;
+            let path = self.dir.join(file_name(&self.stem, part, self.format));
+            match OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => {
+                    *state = LogState {
+                        path,
+                        file,
+                        written: 0,
+                        part,
+                    }
+                }
+                // Keep writing to the full file rather than lose records
+                Err(e) => eprintln!("Warning: Could not rotate the log file: {}", e),
+            }
+        }
+        let _ = state.file.write_all(line.as_bytes());
+        let _ = state.file.flush();
+        state.written += line.len() as u64;
+    }
+}
+
+/// Prints the path of the log file being written when dropped, at the end of main
+pub struct LogNotice(pub LogFile);
+
+impl Drop for LogNotice {
+    fn drop(&mut self) {
+        println!("Log file: {}", self.0.path().display());
+    }
+}
+
+/// `<stem>.log`, and `<stem>.<part>.log` for the files it is continued in
+fn file_name(stem: &str, part: usize, format: LogFormat) -> String {
+    let extension = match format {
+        LogFormat::Text => "log",
+        LogFormat::Json => "jsonl",
     };
-    let _ = SimpleLogger::init(level, Config::default());
+    match part {
+        1 => format!("{}.{}", stem, extension),
+        _ => format!("{}.{}.{}", stem, part, extension),
+    }
+}
+
+/// Delete the log files in `dir` older than `settings.max_age_days`, then the
+/// oldest beyond `settings.max_files`
+fn prune(dir: &Path, settings: &LoggingConfig) -> std::io::Result<()> {
+    let mut logs: Vec<(SystemTime, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let is_log = name.starts_with("cli_engineer_")
+                && (name.ends_with(".log") || name.ends_with(".jsonl"));
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            is_log.then(|| (modified, entry.path()))
+        })
+        .collect();
+    // Newest first
+    logs.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    let max_age = Duration::from_secs(settings.max_age_days * 24 * 60 * 60);
+    for (index, (modified, path)) in logs.iter().enumerate() {
+        let expired = settings.max_age_days > 0
+            && modified.elapsed().is_ok_and(|age| age > max_age);
+        // Room is left for the file about to be created
+        let surplus = settings.max_files > 0 && index + 1 >= settings.max_files;
+        if expired || surplus {
+            let _ = fs::remove_file(path);
+        }
+    }
+    Ok(())
+}
+
+/// Console logger that strips secrets from messages before printing them,
+/// and writes them to the log file if there is one
+struct RedactingLogger {
+    /// None when nothing is logged to the terminal
+    inner: Option<Box<SimpleLogger>>,
+    filter: LogFilter,
+    file: Option<LogFile>,
 }
 
-pub fn init_with_file_logging(verbose: bool) {
-    let level = if verbose {
-        LevelFilter::Info
-    } else {
-        LevelFilter::Warn
+impl Log for RedactingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = redact_log_message(&record.args().to_string());
+        if let Some(file) = &self.file {
+            file.write(record, &message);
+        }
+        if let Some(inner) = &self.inner {
+            inner.log(
+                &Record::builder()
+                    .metadata(record.metadata().clone())
+                    .args(format_args!("{}", message))
+                    .module_path(record.module_path())
+                    .file(record.file())
+                    .line(record.line())
+                    .build(),
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(inner) = &self.inner {
+            inner.flush();
+        }
+    }
+}
+
+/// Log the records `filter` lets through to the terminal, if `console`, and to `file`
+pub fn init(filter: LogFilter, console: bool, file: Option<LogFile>) {
+    let level = filter.max_level();
+    let logger = RedactingLogger {
+        inner: console.then(|| SimpleLogger::new(level, Config::default())),
+        filter,
+        file,
     };
-    
-    // Create log filename with timestamp
-    let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
-    let log_filename = format!("cli_engineer_{}.log", timestamp);
-    
-    // Initialize console logger
-    let _ = SimpleLogger::init(level, Config::default());
-    
-    // Log the start of the session to file
-    if let Ok(mut file) = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_filename)
-    {
-        let _ = writeln!(file, "\n=== CLI Engineer Session Started: {} ===", Utc::now().format("%Y-%m-%d %H:%M:%S UTC"));
-        info!("Verbose logging enabled. Session details will be logged to: {}", log_filename);
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_module_levels_and_json_records() {
+        let levels = HashMap::from([
+            ("providers".to_string(), "debug".to_string()),
+            ("providers::ollama".to_string(), "error".to_string()),
+            ("ui".to_string(), "warn".to_string()),
+        ]);
+        let filter = LogFilter::new(LevelFilter::Info, &levels);
+        assert_eq!(filter.level("cli_engineer::providers::openai"), LevelFilter::Debug);
+        assert_eq!(filter.level("cli_engineer::providers::ollama"), LevelFilter::Error);
+        assert_eq!(filter.level("cli_engineer::ui_dashboard"), LevelFilter::Warn);
+        assert_eq!(filter.level("cli_engineer::executor"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+
+        let dir = std::env::temp_dir().join(format!("cli_engineer_log_{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        // Small enough for each record to go to its own file
+        let file =
+            LogFile::open(&dir, "cli_engineer_test".to_string(), LogFormat::Json, 100).unwrap();
+        let record = Record::builder()
+            .level(log::Level::Warn)
+            .target("cli_engineer::providers::openai")
+            .module_path(Some("cli_engineer::providers::openai"))
+            .args(format_args!("rate limited"))
+            .build();
+        let call = with_provider("openai", async { file.write(&record, "rate limited") });
+        in_step("step_2", call).await;
+        file.write(&record, "outside");
+        assert_eq!(file.path(), dir.join("cli_engineer_test.2.jsonl"));
+
+        let read = |name: &str| -> serde_json::Value {
+            serde_json::from_str(fs::read_to_string(dir.join(name)).unwrap().trim()).unwrap()
+        };
+        let first = read("cli_engineer_test.jsonl");
+        assert_eq!(first["module"], "cli_engineer::providers::openai");
+        assert_eq!(first["level"], "WARN");
+        assert_eq!(first["step_id"], "step_2");
+        assert_eq!(first["provider"], "openai");
+        assert!(read("cli_engineer_test.2.jsonl")["step_id"].is_null());
+
+        // Only the newest log is kept, with room for the next one
+        fs::write(dir.join("notes.txt"), "kept").unwrap();
+        let settings = LoggingConfig {
+            max_files: 2,
+            ..LoggingConfig::default()
+        };
+        prune(&dir, &settings).unwrap();
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        assert!(dir.join("notes.txt").exists());
+        let _ = fs::remove_dir_all(&dir);
     }
 }
@@ -0,0 +1,612 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use log::{debug, info, warn};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{Mutex, mpsc};
+use tokio::time::Instant;
+
+use crate::config::{LspConfig, LspServerConfig};
+use crate::reviewer::{Issue, IssueCategory, IssueSeverity};
+
+/// Quiet time after which pushed diagnostics are taken as complete
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+/// Maximum number of diagnostics taken from a single file
+const MAX_ISSUES_PER_FILE: usize = 50;
+
+type Reader = BufReader<Pin<Box<dyn AsyncRead + Send>>>;
+type Writer = Pin<Box<dyn AsyncWrite + Send>>;
+
+/// Read one `Content-Length` framed message
+async fn read_message(reader: &mut Reader) -> Result<Value> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            anyhow::bail!("The language server closed the connection");
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length.context("Message without a Content-Length header")?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    Ok(serde_json::from_slice(&body)?)
+}
+
+/// An initialized connection to a language server, speaking JSON-RPC with
+/// `Content-Length` framing
+struct Session {
+    _child: Option<Child>,
+    /// Messages read by a background task, so waiting for one can time out
+    /// without losing part of it
+    messages: mpsc::Receiver<Value>,
+    writer: Writer,
+    next_id: u64,
+    /// Whether the server answers `textDocument/diagnostic` requests
+    pull: bool,
+    /// Version last sent for each open document
+    versions: HashMap<String, i64>,
+    /// Diagnostics last pushed for each document
+    pushed: HashMap<String, Vec<Value>>,
+}
+
+impl Session {
+    /// Start the server or connect to it, then initialize it for `root`
+    async fn start(config: &LspServerConfig, root: &Path, timeout: Duration) -> Result<Self> {
+        let (reader, writer, child): (Pin<Box<dyn AsyncRead + Send>>, Writer, _) =
+            match (&config.command, &config.address) {
+                (_, Some(address)) => {
+                    let stream = tokio::net::TcpStream::connect(address)
+                        .await
+                        .with_context(|| format!("Failed to connect to {}", address))?;
+                    let (reader, writer) = stream.into_split();
+                    (Box::pin(reader), Box::pin(writer), None)
+                }
+                (Some(command), None) => {
+                    let mut child = Command::new(command)
+                        .args(&config.args)
+                        .current_dir(root)
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::null())
+                        .kill_on_drop(true)
+                        .spawn()
+                        .with_context(|| format!("Failed to start {}", command))?;
+                    let stdin = child.stdin.take().context("No stdin")?;
+                    let stdout = child.stdout.take().context("No stdout")?;
+                    (Box::pin(stdout), Box::pin(stdin), Some(child))
+                }
+                (None, None) => anyhow::bail!("Neither a command nor an address is configured"),
+            };
+        let mut session = Self::new(BufReader::new(reader), writer);
+        session._child = child;
+        session.initialize(root, timeout).await?;
+        Ok(session)
+    }
+
+    fn new(mut reader: Reader, writer: Writer) -> Self {
+        let (sender, messages) = mpsc::channel(100);
+        tokio::spawn(async move {
+            loop {
+                match read_message(&mut reader).await {
+                    Ok(message) => {
+                        if sender.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        debug!("Stopped reading from the language server: {:#}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        Self {
+            _child: None,
+            messages,
+            writer,
+            next_id: 1,
+            pull: false,
+            versions: HashMap::new(),
+            pushed: HashMap::new(),
+        }
+    }
+
+    async fn initialize(&mut self, root: &Path, timeout: Duration) -> Result<()> {
+        let root_uri = file_uri(root);
+        let result = self
+            .request(
+                "initialize",
+                json!({
+                    "processId": std::process::id(),
+                    "clientInfo": { "name": "cli_engineer", "version": env!("CARGO_PKG_VERSION") },
+                    "rootUri": root_uri,
+                    "workspaceFolders": [{ "uri": root_uri, "name": "workspace" }],
+                    "capabilities": {
+                        "textDocument": {
+                            "publishDiagnostics": { "versionSupport": true },
+                            "diagnostic": { "dynamicRegistration": false },
+                        },
+                    },
+                }),
+                timeout,
+            )
+            .await?;
+        self.pull = result
+            .pointer("/capabilities/diagnosticProvider")
+            .is_some_and(|provider| !provider.is_null());
+        self.notify("initialized", json!({})).await
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<()> {
+        let body = serde_json::to_string(message)?;
+        let framed = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        self.writer.write_all(framed.as_bytes()).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    async fn notify(&mut self, method: &str, params: Value) -> Result<()> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+            .await
+    }
+
+    /// Read one message, answering requests from the server and recording
+    /// pushed diagnostics on the way
+    async fn receive(&mut self) -> Result<Value> {
+        let message = self
+            .messages
+            .recv()
+            .await
+            .context("The language server closed the connection")?;
+
+        match (
+            message.get("id"),
+            message.get("method").and_then(Value::as_str),
+        ) {
+            // Servers ask for settings and progress tokens; defaults and acknowledgements do
+            (Some(id), Some(method)) => {
+                let result = match method {
+                    "workspace/configuration" => {
+                        let items = message
+                            .pointer("/params/items")
+                            .and_then(Value::as_array)
+                            .map_or(0, Vec::len);
+                        Value::Array(vec![Value::Null; items])
+                    }
+                    _ => Value::Null,
+                };
+                let reply = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                self.send(&reply).await?;
+            }
+            (None, Some("textDocument/publishDiagnostics")) => {
+                if let Some(uri) = message.pointer("/params/uri").and_then(Value::as_str) {
+                    let diagnostics = message
+                        .pointer("/params/diagnostics")
+                        .and_then(Value::as_array)
+                        .cloned()
+                        .unwrap_or_default();
+                    self.pushed.insert(uri.to_string(), diagnostics);
+                }
+            }
+            _ => {}
+        }
+        Ok(message)
+    }
+
+    async fn request(&mut self, method: &str, params: Value, timeout: Duration) -> Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await?;
+        let response = tokio::time::timeout(timeout, async {
+            loop {
+                let message = self.receive().await?;
+                if message.get("id").and_then(Value::as_u64) == Some(id)
+                    && message.get("method").is_none()
+                {
+                    return Ok::<_, anyhow::Error>(message);
+                }
+            }
+        })
+        .await
+        .map_err(|_| anyhow!("No answer to {} within {:?}", method, timeout))??;
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error");
+            anyhow::bail!("{} failed: {}", method, message);
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    /// Open or update `files` (path and content) and return the diagnostics of each
+    async fn diagnostics(
+        &mut self,
+        files: &[(PathBuf, String)],
+        timeout: Duration,
+    ) -> Result<Vec<(PathBuf, Vec<Value>)>> {
+        let mut uris = Vec::new();
+        for (path, content) in files {
+            let uri = file_uri(path);
+            self.pushed.remove(&uri);
+            match self.versions.get_mut(&uri) {
+                Some(version) => {
+                    *version += 1;
+                    let version = *version;
+                    self.notify(
+                        "textDocument/didChange",
+                        json!({
+                            "textDocument": { "uri": uri, "version": version },
+                            "contentChanges": [{ "text": content }],
+                        }),
+                    )
+                    .await?;
+                }
+                None => {
+                    self.versions.insert(uri.clone(), 1);
+                    self.notify(
+                        "textDocument/didOpen",
+                        json!({
+                            "textDocument": {
+                                "uri": uri,
+                                "languageId": language_id(path),
+                                "version": 1,
+                                "text": content,
+                            },
+                        }),
+                    )
+                    .await?;
+                }
+            }
+            uris.push((path.clone(), uri));
+        }
+
+        let mut found = Vec::new();
+        if self.pull {
+            for (path, uri) in uris {
+                let report = self
+                    .request(
+                        "textDocument/diagnostic",
+                        json!({ "textDocument": { "uri": uri } }),
+                        timeout,
+                    )
+                    .await?;
+                let items = report
+                    .get("items")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                found.push((path, items));
+            }
+            return Ok(found);
+        }
+
+        // Servers push diagnostics when their analysis is done, often more than
+        // once; wait until every file has some and the server has gone quiet
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let all_received = uris.iter().all(|(_, uri)| self.pushed.contains_key(uri));
+            let wait = if all_received {
+                SETTLE_TIME.min(remaining)
+            } else {
+                remaining
+            };
+            if wait.is_zero() || tokio::time::timeout(wait, self.receive()).await.is_err() {
+                break;
+            }
+        }
+        for (path, uri) in uris {
+            match self.pushed.get(&uri) {
+                Some(diagnostics) => found.push((path, diagnostics.clone())),
+                None => debug!("No diagnostics received for {}", path.display()),
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// A configured server, started on first use
+struct Server {
+    name: String,
+    config: LspServerConfig,
+    /// `None` until started, and again after it failed
+    session: Mutex<Option<Session>>,
+    /// Set once the server failed, so it is not retried every iteration
+    failed: std::sync::atomic::AtomicBool,
+}
+
+/// Language servers asked for diagnostics on the files a run changes
+pub struct LspDiagnostics {
+    root: PathBuf,
+    timeout: Duration,
+    servers: Vec<Server>,
+}
+
+impl LspDiagnostics {
+    pub fn new(root: PathBuf, config: &LspConfig) -> Self {
+        let mut names: Vec<&String> = config.servers.keys().collect();
+        names.sort();
+        Self {
+            root,
+            timeout: Duration::from_secs(config.timeout_secs),
+            servers: names
+                .into_iter()
+                .map(|name| Server {
+                    name: name.clone(),
+                    config: config.servers[name].clone(),
+                    session: Mutex::new(None),
+                    failed: Default::default(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Diagnostics for `files`, given by path relative to the project root and
+    /// content, as review issues. Servers that cannot be reached are skipped
+    /// with a warning.
+    pub async fn issues(&self, files: &[(String, String)]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        for server in &self.servers {
+            if server.failed.load(std::sync::atomic::Ordering::Relaxed) {
+                continue;
+            }
+            let handled: Vec<(PathBuf, String)> = files
+                .iter()
+                .filter(|(path, _)| {
+                    let extension = Path::new(path)
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .unwrap_or("");
+                    server.config.extensions.iter().any(|e| e == extension)
+                })
+                .map(|(path, content)| (self.root.join(path), content.clone()))
+                .collect();
+            if handled.is_empty() {
+                continue;
+            }
+
+            match self.server_diagnostics(server, &handled).await {
+                Ok(found) => {
+                    for (path, diagnostics) in found {
+                        let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+                        let found = to_issues(&relative.to_string_lossy(), &diagnostics);
+                        info!(
+                            "{} reported {} diagnostics for {}",
+                            server.name,
+                            found.len(),
+                            relative.display()
+                        );
+                        issues.extend(found);
+                    }
+                }
+                Err(e) => {
+                    warn!("Language server {} disabled: {:#}", server.name, e);
+                    server
+                        .failed
+                        .store(true, std::sync::atomic::Ordering::Relaxed);
+                    *server.session.lock().await = None;
+                }
+            }
+        }
+        issues
+    }
+
+    async fn server_diagnostics(
+        &self,
+        server: &Server,
+        files: &[(PathBuf, String)],
+    ) -> Result<Vec<(PathBuf, Vec<Value>)>> {
+        let mut session = server.session.lock().await;
+        if session.is_none() {
+            info!("Starting language server {}", server.name);
+            *session = Some(Session::start(&server.config, &self.root, self.timeout).await?);
+        }
+        let session = session.as_mut().expect("started above");
+        session.diagnostics(files, self.timeout).await
+    }
+}
+
+/// Review issues for the LSP `diagnostics` of the file at `path`. Hints are left out.
+fn to_issues(path: &str, diagnostics: &[Value]) -> Vec<Issue> {
+    diagnostics
+        .iter()
+        .filter_map(|diagnostic| {
+            // Servers may omit the severity; clients are to treat it as an error
+            let (severity, category) = match diagnostic.get("severity").and_then(Value::as_u64) {
+                None | Some(1) => (IssueSeverity::Critical, IssueCategory::Logic),
+                Some(2) => (IssueSeverity::Minor, IssueCategory::BestPractices),
+                Some(3) => (IssueSeverity::Info, IssueCategory::BestPractices),
+                _ => return None,
+            };
+            let line = |key: &str| {
+                diagnostic
+                    .pointer(&format!("/range/{}/line", key))
+                    .and_then(Value::as_u64)
+                    .map_or(1, |line| line as usize + 1)
+            };
+            let (start, end) = (line("start"), line("end"));
+            let location = if start == end {
+                format!("{}:{}", path, start)
+            } else {
+                format!("{}:{}-{}", path, start, end)
+            };
+            let source = diagnostic
+                .get("source")
+                .and_then(Value::as_str)
+                .unwrap_or("lsp");
+            let code = match diagnostic.get("code") {
+                Some(Value::String(code)) => format!(" {}", code),
+                Some(Value::Number(code)) => format!(" {}", code),
+                _ => String::new(),
+            };
+            Some(Issue {
+                severity,
+                category,
+                description: format!(
+                    "[{}{}] {}",
+                    source,
+                    code,
+                    diagnostic
+                        .get("message")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .trim()
+                ),
+                location: Some(location),
+                suggestion: None,
+                patch: None,
+            })
+        })
+        .take(MAX_ISSUES_PER_FILE)
+        .collect()
+}
+
+/// `file://` URI of an absolute path
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        // Windows drive letters: file:///C:/...
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// LSP language identifier of a file, from its extension
+fn language_id(path: &Path) -> &str {
+    match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "rs" => "rust",
+        "ts" => "typescript",
+        "tsx" => "typescriptreact",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "javascriptreact",
+        "py" => "python",
+        "go" => "go",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "hpp" => "cpp",
+        "java" => "java",
+        extension => extension,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lsp_diagnostics() {
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        let (client_read, client_write) = tokio::io::split(client);
+        let mut session = Session::new(
+            BufReader::new(Box::pin(client_read)),
+            Box::pin(client_write),
+        );
+
+        // Answers initialize, then asks for settings and pushes diagnostics
+        // for the file once it is opened
+        let fake_server = tokio::spawn(async move {
+            let (read, mut write) = tokio::io::split(server);
+            let mut read: Reader = BufReader::new(Box::pin(read));
+            let mut send = async |message: Value| {
+                let body = message.to_string();
+                let framed = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+                write.write_all(framed.as_bytes()).await.unwrap();
+            };
+            let initialize = read_message(&mut read).await.unwrap();
+            send(json!({ "jsonrpc": "2.0", "id": initialize["id"], "result": { "capabilities": {} } }))
+                .await;
+            let initialized = read_message(&mut read).await.unwrap();
+            assert_eq!(initialized["method"], "initialized");
+            let opened = read_message(&mut read).await.unwrap();
+            assert_eq!(opened["params"]["textDocument"]["languageId"], "rust");
+            let uri = opened["params"]["textDocument"]["uri"].clone();
+            send(json!({
+                "jsonrpc": "2.0",
+                "id": "cfg",
+                "method": "workspace/configuration",
+                "params": { "items": [{}] },
+            }))
+            .await;
+            let reply = read_message(&mut read).await.unwrap();
+            assert_eq!(reply["result"], json!([null]));
+            let diagnostic = |line: u64, severity: u64, message: &str| {
+                json!({
+                    "range": {
+                        "start": { "line": line, "character": 0 },
+                        "end": { "line": line, "character": 5 },
+                    },
+                    "severity": severity,
+                    "code": "E0425",
+                    "source": "rustc",
+                    "message": message,
+                })
+            };
+            send(json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": {
+                    "uri": uri,
+                    "diagnostics": [
+                        diagnostic(2, 1, "cannot find value `x`"),
+                        diagnostic(0, 4, "consider renaming"),
+                    ],
+                },
+            }))
+            .await;
+            // Keep the connection open while the client waits for more
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let root = Path::new("/work/my project");
+        session
+            .initialize(root, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(!session.pull);
+        let found = session
+            .diagnostics(
+                &[(root.join("src/main.rs"), "fn main() {}".to_string())],
+                Duration::from_secs(10),
+            )
+            .await
+            .unwrap();
+        fake_server.abort();
+
+        assert_eq!(found.len(), 1);
+        let issues = to_issues("src/main.rs", &found[0].1);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, IssueSeverity::Critical);
+        assert_eq!(issues[0].description, "[rustc E0425] cannot find value `x`");
+        assert_eq!(issues[0].location.as_deref(), Some("src/main.rs:3"));
+        assert_eq!(
+            file_uri(&root.join("src/main.rs")),
+            "file:///work/my%20project/src/main.rs"
+        );
+    }
+}
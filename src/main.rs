@@ -1,41 +1,131 @@
 use anyhow::Result;
 use clap::{Parser, ValueEnum};
 use log::{error, info, warn, debug};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::{Arc, Mutex};
 use tokio::sync::oneshot;
 use tokio::time::Duration;
 use uuid::Uuid;
-use walkdir::WalkDir;
+use ignore::WalkBuilder;
 
 use agentic_loop::AgenticLoop;
+use agents::{AgentRole, AgentTeam};
 use artifact::ArtifactManager;
-use config::Config;
-use context::{ContextConfig, ContextManager};
+use checkpoint::{Checkpoint, CheckpointStore};
+use code_index::CodeIndex;
+use config::{Config, ConflictStrategy, ExecutionMode, ScanConfig, SecondOpinionConfig, resolve_api_key};
+use context::{ContextConfig, ContextManager, ContextSource};
+use diagrams::ModuleGraph;
 use event_bus::{Event, EventBus, EventEmitter};
+use event_log::{JsonlEventLog, EVENTS_FILE};
+use hooks::HookRunner;
 use llm_manager::{LLMManager, LLMProvider, LocalProvider};
+use memory::MemoryStore;
+use notifications::Notifier;
+use outcome::{Outcome, RunFailed};
+use reasoning_log::ReasoningLog;
+use redaction::{RedactionReport, Redactor};
+use remote_repo::RepoSpec;
+use repo_lock::RepoLock;
+use retrieval::RetrievalIndex;
+use run_control::{ControlMessage, RunControl};
+use run_record::{ArtifactRecord, RunRecord};
+use sampling::Sampler;
+use scan_cache::{FileChange, ScanCache};
+use telemetry::TelemetryMetrics;
+use theme::Theme;
+use tool_loop::ToolLoop;
+use tracing::Instrument;
 use providers::{
     anthropic::AnthropicProvider, ollama::OllamaProvider, openai::OpenAIProvider, openrouter::OpenRouterProvider, gemini::GeminiProvider,
+    mock::{CassetteMode, MockProvider, RecordingProvider},
 };
 use ui_dashboard::DashboardUI;
 use ui_enhanced::EnhancedUI;
 mod logger_dashboard;
 
 mod agentic_loop;
+mod agents;
+mod apply;
 mod artifact;
+mod artifact_list;
+mod artifact_diff;
+mod artifacts_command;
+mod bench;
+mod bundle;
+mod changelog;
+mod checkpoint;
+mod chunking;
+mod ci_output;
+mod code_index;
 mod concurrency;
 mod config;
+mod config_doctor;
+mod config_validation;
 mod context;
+mod convergence;
+mod coverage_gaps;
+mod diagrams;
+mod doc_links;
 mod event_bus;
+mod event_log;
 mod executor;
+mod file_summary;
+mod formatter;
+mod git;
+mod github;
+mod hooks;
+mod html_report;
+mod http_server;
 mod interpreter;
+mod ipc;
+mod issue;
 mod iteration_context;
+mod iteration_report;
+mod linters;
 mod llm_manager;
+mod lockfiles;
 mod logger;
+mod lsp;
+mod mcp_client;
+mod mcp_server;
+mod memory;
+mod merge;
+mod notifications;
+mod outcome;
+mod plan_progress;
 mod planner;
 mod providers;
+mod reasoning_log;
+mod redaction;
+mod remote_repo;
+mod replay;
+mod repo_lock;
+mod retrieval;
 mod reviewer;
+mod rpc_server;
+mod run_control;
+mod run_record;
+mod sampling;
+mod rubrics;
+mod sandbox;
+mod scan_cache;
+mod sbom;
+mod security_scan;
+mod serve;
+mod session;
+mod syntax_check;
+mod telemetry;
+mod terminal_text;
+mod theme;
+mod throughput;
+mod tool_loop;
+mod tracker;
+mod transcript;
 mod ui_dashboard;
 mod ui_enhanced;
+mod web_dashboard;
 
 #[derive(ValueEnum, Debug, Clone)]
 enum CommandKind {
@@ -49,6 +139,42 @@ enum CommandKind {
     Docs,
     #[clap(help = "Security analysis")]
     Security,
+    #[clap(help = "Software bill of materials from the lockfiles, with a summary of notable components and licenses")]
+    Sbom,
+    #[clap(help = "Copy generated artifacts into the project")]
+    Apply,
+    #[clap(help = "Inspect artifacts: `-- diff [NAME]`, `-- history NAME`, `-- revert NAME VERSION`, `-- find GLOB|tag:TAG`, `-- search PATTERN`, `-- operations`, `-- undo [ID]` or `-- export FILE [RUN_ID]`")]
+    Artifacts,
+    #[clap(help = "List what the agent remembers about the project: `-- list`, or `-- forget ID` to forget a memory")]
+    Memory,
+    #[clap(help = "Replay a recorded run in the UI: `-- [RUN_ID]`, the latest run by default")]
+    Replay,
+    #[clap(help = "Check the configuration: `-- doctor` shows each setting's source, the providers and any problems")]
+    Config,
+    #[clap(help = "Write a release of CHANGELOG.md from the git history since `--since`, the latest tag by default: `-- [VERSION]`, unreleased changes by default")]
+    Changelog,
+    #[clap(help = "Report the public functions the tests never run, from lcov, llvm-cov or coverage.py data; `--write-tests` goes on to write tests for them")]
+    CoverageGaps,
+    #[clap(help = "Compare models on a small task suite: `-- [PROVIDER|PROVIDER/MODEL|ALIAS ...]`, each enabled provider by default")]
+    BenchProviders,
+    #[clap(help = "Serve cli_engineer to other tools: `--mcp` speaks the Model Context Protocol and `--stdio` the editor JSON-RPC protocol on stdin and stdout, `--http` a REST API with a job queue")]
+    Serve,
+}
+
+impl CommandKind {
+    /// Whether the command can call a model, so that `[command.<name>]` applies
+    /// to it; coverage-gaps calls one only with `--write-tests`
+    fn calls_model(&self) -> bool {
+        matches!(
+            self,
+            CommandKind::Code | CommandKind::Refactor | CommandKind::Review | CommandKind::Docs | CommandKind::Security | CommandKind::Sbom | CommandKind::Changelog | CommandKind::CoverageGaps | CommandKind::BenchProviders | CommandKind::Serve
+        )
+    }
+
+    /// The name given on the command line
+    fn name(&self) -> String {
+        self.to_possible_value().map_or(String::new(), |v| v.get_name().to_string())
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -66,6 +192,112 @@ struct Args {
     /// Configuration file path
     #[arg(short, long)]
     config: Option<String>,
+    /// Write the log file as text or JSON records, overriding `logging.format`;
+    /// a JSON log is written without --verbose too
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    log_format: Option<config::LogFormat>,
+    /// Apply the settings of `[profiles.NAME]` in the configuration file
+    #[arg(long, value_name = "NAME")]
+    profile: Option<String>,
+    /// Continue a named session, keeping the conversation from earlier runs
+    #[arg(long, value_name = "NAME")]
+    session: Option<String>,
+    /// Start from a context exported by an earlier run instead of an empty one
+    #[arg(long, value_name = "PATH", conflicts_with = "session")]
+    context_file: Option<PathBuf>,
+    /// Continue the run that was interrupted, from its last checkpoint, without asking
+    #[arg(long)]
+    resume: bool,
+    /// Ask for guidance before planning each iteration after the first
+    #[arg(long)]
+    ask_guidance: bool,
+    /// Analyze a remote git repository (URL[@branch]) instead of the current directory
+    #[arg(long, value_name = "URL[@BRANCH]")]
+    repo: Option<String>,
+    /// Use issue NUMBER of the repository's GitHub or GitLab remote as the task,
+    /// and comment on it when the run ends
+    #[arg(long, value_name = "NUMBER")]
+    issue: Option<u64>,
+    /// Use Jira or Linear ticket KEY (e.g. PROJ-123) as the task, and comment
+    /// on it when the run ends
+    #[arg(long, value_name = "KEY", conflicts_with = "issue")]
+    task: Option<String>,
+    /// Confirm each file before the apply command copies it
+    #[arg(long)]
+    interactive: bool,
+    /// Let the apply command change the project without confirming each file
+    #[arg(long, conflicts_with = "interactive")]
+    yes: bool,
+    /// Run even if another run holds the project's lock
+    #[arg(long)]
+    force: bool,
+    /// Playback speed of the replay command; 2 is twice as fast, 0 skips the pauses
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0)]
+    speed: f64,
+    /// Serve a live web dashboard on PORT (localhost only) or HOST:PORT, which needs a token
+    #[arg(long, value_name = "[HOST:]PORT", value_parser = web_dashboard::parse_address)]
+    serve: Option<std::net::SocketAddr>,
+    /// Append the model's reasoning to .cli_engineer/reasoning_<run>.md
+    #[arg(long)]
+    export_reasoning: bool,
+    /// With the changelog command, the tag or commit after which changes are listed
+    #[arg(long, value_name = "REF")]
+    since: Option<String>,
+    /// With the coverage-gaps command, the lcov, llvm-cov JSON or coverage.py JSON file to read
+    #[arg(long, value_name = "PATH")]
+    coverage: Option<PathBuf>,
+    /// With the bench-providers command, the model reviewing every model's results instead of
+    /// `review.second_opinion`; it is left out of the benchmark
+    #[arg(long, value_name = "PROVIDER|PROVIDER/MODEL|ALIAS")]
+    judge: Option<String>,
+    /// With the coverage-gaps command, start a code run writing tests for the highest priority gaps
+    #[arg(long)]
+    write_tests: bool,
+    /// The most plan-execute-review cycles; overrides `execution.max_iterations` and
+    /// the iteration budget of `execution.iteration_budget.adaptive`
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    max_iterations: Option<u32>,
+    /// Stop the run after SECONDS, exiting with status 5; overrides `execution.timeout_secs`
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: Option<u64>,
+    /// Write an HTML report of the run to .cli_engineer/runs/<run>/report.html
+    #[arg(long)]
+    html_report: bool,
+    /// Write the HTML report and open it in the browser
+    #[arg(long)]
+    open_report: bool,
+    /// With the docs command, write Mermaid diagrams of the modules and their imports to docs/architecture.md
+    #[arg(long)]
+    diagrams: bool,
+    /// Draw nothing and print only a one-line summary of the run, for cron and CI;
+    /// exits non-zero unless the task was completed
+    #[arg(long, visible_alias = "quiet")]
+    headless: bool,
+    /// Print the run summary as JSON (implies --headless)
+    #[arg(long)]
+    json: bool,
+    /// Print the review's issues as GitHub annotations, Checkstyle or JUnit XML
+    /// for CI (implies --headless)
+    #[arg(long, value_enum, value_name = "FORMAT", conflicts_with = "json")]
+    output: Option<ci_output::OutputFormat>,
+    /// With the serve command, expose the agent as MCP tools over stdio
+    #[arg(long)]
+    mcp: bool,
+    /// With the serve command, speak the editor JSON-RPC protocol over stdio
+    #[arg(long, conflicts_with = "mcp")]
+    stdio: bool,
+    /// With the serve command, serve a REST API queueing tasks on PORT (localhost only) or HOST:PORT
+    #[arg(long, value_name = "[HOST:]PORT", value_parser = web_dashboard::parse_address, conflicts_with_all = ["mcp", "stdio"])]
+    http: Option<std::net::SocketAddr>,
+    /// Record every prompt to the model and its response to a cassette at PATH
+    #[arg(long, value_name = "PATH")]
+    record_cassette: Option<PathBuf>,
+    /// Answer prompts from a cassette recorded with --record-cassette instead of a model
+    #[arg(long, value_name = "PATH", conflicts_with = "record_cassette")]
+    replay_cassette: Option<PathBuf>,
+    /// With the sbom command, write CycloneDX or SPDX instead of `security.sbom_format`
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    sbom_format: Option<config::SbomFormat>,
     /// Command to execute
     #[arg(value_enum)]
     command: CommandKind,
@@ -75,50 +307,313 @@ struct Args {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(outcome) => outcome.into(),
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            outcome::exit_code(&e)
+        }
+    }
+}
+
+/// Run the command, returning how it ended
+async fn run() -> Result<Outcome> {
     // Load environment variables
     dotenv::dotenv().ok();
 
     // Parse command line arguments
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let summary = match (args.headless, args.json, args.output) {
+        (_, _, Some(format)) => Some(SummaryFormat::Ci(format)),
+        (_, true, None) => Some(SummaryFormat::Json),
+        (true, false, None) => Some(SummaryFormat::Line),
+        (false, false, None) => None,
+    };
 
     // Create event bus
     let event_bus = Arc::new(EventBus::new(1000));
 
+    // Smaller terminals get the text UI rather than a garbled dashboard
+    let too_small = !args.no_dashboard && !ui_dashboard::fits_terminal();
+    args.no_dashboard |= too_small;
+
+    // Load configuration, with the settings of the command and the command line applied
+    let mut command_name = args.command.name();
+    let unattended = summary.is_some() || matches!(args.command, CommandKind::Serve);
+    let (config, mut template) = resolve_config(&args, &args.command, unattended)?;
+
     // Initialize logger
-    if !args.no_dashboard {
-        let level = if args.verbose {
-            log::LevelFilter::Info
-        } else {
-            log::LevelFilter::Warn
-        };
-        logger_dashboard::DashboardLogger::init_with_file(event_bus.clone(), level, args.verbose)
+    let level = if args.verbose {
+        log::LevelFilter::Info
+    } else {
+        log::LevelFilter::Warn
+    };
+    let filter = logger::LogFilter::new(level, &config.logging.levels);
+    // A JSON log is asked for to be analyzed, so it is written without --verbose too
+    let log_file = if args.verbose || config.logging.format == config::LogFormat::Json {
+        let log_dir = std::env::current_dir()?.join(".cli_engineer").join("logs");
+        logger::LogFile::create(&log_dir, &config.logging)
+    } else {
+        None
+    };
+    let log_path = log_file.as_ref().map(logger::LogFile::path);
+    // Named at the end of the run, however it ends, where someone reads the terminal
+    let _log_notice = log_file
+        .clone()
+        .filter(|_| summary.is_none() && !matches!(args.command, CommandKind::Serve))
+        .map(logger::LogNotice);
+    if summary.is_some() || matches!(args.command, CommandKind::Serve) {
+        // Headless runs log nothing to the terminal, and served ones keep stdout for the protocol
+        logger::init(filter, false, log_file);
+    } else if !args.no_dashboard {
+        logger_dashboard::DashboardLogger::init(event_bus.clone(), filter, log_file)
             .expect("Failed to init DashboardLogger");
     } else {
-        if args.verbose {
-            logger::init_with_file_logging(args.verbose);
-        } else {
-            logger::init(args.verbose);
+        logger::init(filter, true, log_file);
+    }
+    if let Some(path) = log_path {
+        info!("Session details will be logged to: {}", path.display());
+    }
+
+    if let Some(profile) = &args.profile {
+        info!("Using profile '{}'", profile);
+    }
+    // The doctor reports problems rather than stopping at them
+    if matches!(args.command, CommandKind::Config) {
+        return config_doctor::run(&config, &args.prompt).await.map(|()| Outcome::Success);
+    }
+    // Report every problem now rather than failing mid-run; only the agent
+    // commands call a model
+    let calls_model = args.command.calls_model() && (!matches!(args.command, CommandKind::CoverageGaps) || args.write_tests);
+    config.validate(calls_model)?;
+    if (args.issue.is_some() || args.task.is_some()) && (!calls_model || matches!(args.command, CommandKind::Sbom | CommandKind::Changelog | CommandKind::CoverageGaps | CommandKind::BenchProviders | CommandKind::Serve) || args.repo.is_some()) {
+        anyhow::bail!("--issue and --task can only be used with the code, refactor, review, docs and security commands, without --repo");
+    }
+    let mut config = Arc::new(config);
+
+    // Colors of both UIs; NO_COLOR and `ui.colorful = false` turn off all colors
+    let theme = Theme::from_config(&config.ui)?;
+    theme.apply_to_output();
+
+    // Exporters are flushed when this is dropped at the end of main
+    let _telemetry = match telemetry::init(&config.telemetry) {
+        Ok(telemetry) => telemetry,
+        Err(e) => {
+            warn!("Telemetry export disabled: {:#}", e);
+            None
         }
+    };
+
+    let mut prompt = args.prompt.join(" ");
+
+    // Two runs in one project would race on its artifacts, caches and files;
+    // only reading them needs no lock
+    let _lock = match args.command {
+        // Undoing a delete or rename or reverting a file changes the artifacts, so it takes the lock
+        CommandKind::Artifacts if args.prompt.first().is_some_and(|action| action == "undo" || action == "revert") => {
+            let state_dir = std::env::current_dir()?.join(".cli_engineer");
+            Some(RepoLock::acquire(&state_dir, &command_name, args.force)?)
+        }
+        // So does forgetting a memory
+        CommandKind::Memory if args.prompt.first().is_some_and(|action| action == "forget") => {
+            let state_dir = std::env::current_dir()?.join(".cli_engineer");
+            Some(RepoLock::acquire(&state_dir, &command_name, args.force)?)
+        }
+        CommandKind::Artifacts | CommandKind::Memory | CommandKind::BenchProviders | CommandKind::Replay => None,
+        // A run on a clone locks the clone, below
+        _ if args.repo.is_some() => None,
+        _ => {
+            let state_dir = std::env::current_dir()?.join(".cli_engineer");
+            Some(RepoLock::acquire(&state_dir, &command_name, args.force)?)
+        }
+    };
+
+    // Served runs are driven by the client, without a UI
+    if matches!(args.command, CommandKind::Serve) {
+        if !args.mcp && !args.stdio && args.http.is_none() {
+            anyhow::bail!("The serve command needs a protocol: cli_engineer serve --mcp, --stdio or --http <PORT>");
+        }
+        if args.repo.is_some() {
+            anyhow::bail!("--repo cannot be used with the serve command");
+        }
+        if args.stdio {
+            return rpc_server::serve(config).await.map(|()| Outcome::Success);
+        }
+        if let Some(address) = args.http {
+            return http_server::serve(config, address).await.map(|()| Outcome::Success);
+        }
+        return mcp_server::serve(config).await.map(|()| Outcome::Success);
+    }
+    if matches!(args.command, CommandKind::BenchProviders) {
+        return bench::run(config, args.judge.as_deref(), &args.prompt).await.map(|()| Outcome::Success);
+    }
+    if matches!(args.command, CommandKind::Changelog) {
+        if args.repo.is_some() {
+            anyhow::bail!("--repo cannot be used with the changelog command");
+        }
+        let root = std::env::current_dir()?;
+        return changelog::run(&config, &root, args.since.as_deref(), &args.prompt).await.map(|()| Outcome::Success);
+    }
+    if matches!(args.command, CommandKind::CoverageGaps) {
+        if args.repo.is_some() {
+            anyhow::bail!("--repo cannot be used with the coverage-gaps command");
+        }
+        let root = std::env::current_dir()?;
+        let gaps = coverage_gaps::run(&config, &root, args.coverage.as_deref()).await?;
+        if !args.write_tests || gaps.is_empty() {
+            return Ok(Outcome::Success);
+        }
+        // The gaps become the task of a code run, with the prompt as extra
+        // instructions, configured by `[command.code]` like any other
+        prompt = coverage_gaps::tests_prompt(&gaps, &prompt);
+        args.command = CommandKind::Code;
+        command_name = args.command.name();
+        let (code_config, code_template) = resolve_config(&args, &args.command, unattended)?;
+        code_config.validate(true)?;
+        config = Arc::new(code_config);
+        template = code_template;
     }
 
-    // Load configuration
-    let config = Arc::new(Config::load(&args.config)?);
+    // Memories are listed and forgotten without a model or the dashboard
+    if matches!(args.command, CommandKind::Memory) {
+        if args.repo.is_some() {
+            anyhow::bail!("--repo cannot be used with the memory command");
+        }
+        let state_dir = std::env::current_dir()?.join(".cli_engineer");
+        return memory::run(&state_dir, &args.prompt).map(|()| Outcome::Success);
+    }
+
+    // Working with existing artifacts needs neither a model nor the dashboard
+    if matches!(args.command, CommandKind::Apply | CommandKind::Artifacts) {
+        if args.repo.is_some() {
+            anyhow::bail!("--repo cannot be used with the apply and artifacts commands");
+        }
+        let root = std::env::current_dir()?;
+        let artifact_dir = root.join(&config.execution.artifact_dir);
+        return match args.command {
+            CommandKind::Apply => {
+                apply::run(&artifact_dir, &root, args.interactive, args.yes).await
+            }
+            _ => artifacts_command::run(&artifact_dir, &root, &args.prompt).await,
+        }
+        .map(|()| Outcome::Success);
+    }
+
+    // Browsers see the whole run, so serve before anything happens
+    let _web_dashboard = match args.serve {
+        Some(address) => {
+            let token = serve::configured_token(&config.serve);
+            let dashboard = web_dashboard::serve(address, token, &event_bus).await?;
+            info!("Web dashboard on {}", dashboard.url);
+            if args.no_dashboard && summary.is_none() {
+                println!("Web dashboard on {}", dashboard.url);
+            }
+            Some(dashboard)
+        }
+        None => None,
+    };
+
+    // The issue's discussion becomes the task, with the command line prompt as extra instructions
+    let issue = match args.issue {
+        Some(number) => {
+            let forge = issue::Forge::detect(&std::env::current_dir()?, &config.git).await?;
+            let issue = issue::Issue::fetch(forge, number, &config.git).await?;
+            info!("Working on issue #{}: {}", issue.number, issue.url);
+            prompt = issue.prompt(&prompt);
+            Some(issue)
+        }
+        None => None,
+    };
+    let ticket = match &args.task {
+        Some(key) => {
+            let ticket = tracker::Ticket::fetch(key, &config.tracker).await?;
+            info!("Working on {}: {}", ticket.key, ticket.url);
+            prompt = ticket.prompt(&prompt);
+            Some(ticket)
+        }
+        None => None,
+    };
+
+    // A remote repository is shallow-cloned for the duration of the run
+    let cloned_repo = match &args.repo {
+        Some(spec) => {
+            if matches!(args.command, CommandKind::Code | CommandKind::Refactor | CommandKind::Replay) {
+                anyhow::bail!("--repo can only be used with the review, docs, security and sbom commands");
+            }
+            Some(remote_repo::clone_repo(RepoSpec::parse(spec)?).await?)
+        }
+        None => None,
+    };
+    let _clone_lock = match &cloned_repo {
+        Some(repo) => {
+            let state_dir = repo.path().join(".cli_engineer");
+            Some(RepoLock::acquire(&state_dir, &command_name, args.force)?)
+        }
+        None => None,
+    };
+    let current_dir = std::env::current_dir()?;
+    let run_options = RunOptions {
+        control: Arc::new(RunControl::new()),
+        session: args.session.clone(),
+        context_file: args.context_file.clone(),
+        root: cloned_repo
+            .as_ref()
+            .map_or(current_dir.clone(), |repo| repo.path().to_path_buf()),
+        scan_cache_dir: match &cloned_repo {
+            Some(repo) => current_dir
+                .join(".cli_engineer")
+                .join("repos")
+                .join(repo.spec.slug()),
+            None => current_dir.join(".cli_engineer"),
+        },
+        summary,
+        issue,
+        ticket,
+        require_approval: false,
+        // A run that crashed or lost its connection left a checkpoint; offer to continue it
+        resume: if cloned_repo.is_none() && !matches!(args.command, CommandKind::Replay) {
+            checkpoint::offer_resume(&current_dir.join(".cli_engineer"), &command_name, args.resume, summary.is_none())
+        } else {
+            None
+        },
+        cassette: match (&args.record_cassette, &args.replay_cassette) {
+            (Some(path), _) => Some(CassetteMode::record(path)?),
+            (None, Some(path)) => Some(CassetteMode::replay(path)?),
+            (None, None) => None,
+        },
+    };
+
+    if !matches!(args.command, CommandKind::Replay) {
+        handle_interrupts(run_options.control.clone(), event_bus.clone());
+    }
 
-    let prompt = args.prompt.join(" ");
+    if too_small && summary.is_none() {
+        warn!(
+            "Terminal smaller than {}x{}, using the text UI instead of the dashboard",
+            ui_dashboard::MIN_WIDTH,
+            ui_dashboard::MIN_HEIGHT
+        );
+    }
 
-    if !args.no_dashboard {
+    let outcome = if !args.no_dashboard && summary.is_none() {
         // Use dashboard UI when --no-dashboard is not specified
-        let mut ui = DashboardUI::new(false);
+        let mut ui = DashboardUI::new(false).with_theme(theme).with_workspace(
+            current_dir.join(&config.execution.artifact_dir),
+            current_dir.clone(),
+        );
         ui.set_event_bus(event_bus.clone());
+        if !matches!(args.command, CommandKind::Replay) {
+            ui = ui.with_control(run_options.control.clone());
+        }
 
         // Start UI
         ui.start()?;
 
-        if matches!(args.command, CommandKind::Code) && prompt.is_empty() {
+        if matches!(args.command, CommandKind::Code) && prompt.is_empty() && run_options.resume.is_none() {
             ui.display_error("PROMPT required for code command")?;
             ui.finish()?;
-            return Ok(());
+            return Ok(Outcome::Failed);
         }
 
         let ui_ref = Arc::new(Mutex::new(ui));
@@ -132,6 +627,7 @@ async fn main() -> Result<()> {
                 tokio::select! {
                     _ = interval.tick() => {
                         if let Ok(mut ui_guard) = ui_clone.try_lock() {
+                            let _ = ui_guard.handle_input();
                             let _ = ui_guard.throttled_render();
                         }
                     }
@@ -141,55 +637,24 @@ async fn main() -> Result<()> {
         });
 
         let result = match args.command {
-            CommandKind::Code => run_with_ui(prompt.clone(), config.clone(), event_bus.clone(), false, args.command).await,
-            CommandKind::Refactor => {
-                let p = if prompt.is_empty() {
-                    "Analyze the current directory and perform recommended refactoring.".to_string()
-                } else {
-                    prompt.clone()
-                };
-                run_with_ui(
-                    format!("Refactor codebase. {}", p),
-                    config.clone(),
-                    event_bus.clone(),
-                    true,
-                    args.command,
-                )
+            CommandKind::Replay => replay::run(&current_dir.join(".cli_engineer"), args.prompt.first().map(String::as_str), args.speed, &event_bus)
                 .await
-            }
-            CommandKind::Review => {
-                let p = if prompt.is_empty() {
-                    "ANALYSIS ONLY: Review the codebase files and create a comprehensive code review report. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your findings, suggestions, and recommendations in code_review.md. Focus on code quality, best practices, potential issues, and improvement opportunities.".to_string()
-                } else {
-                    format!("ANALYSIS ONLY: Review the codebase with focus on: {}. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your findings in code_review.md", prompt)
-                };
-                run_with_ui(p, config.clone(), event_bus.clone(), true, args.command).await
-            }
-            CommandKind::Docs => {
-                let p = if prompt.is_empty() {
-                    "Generate comprehensive documentation for the codebase. Create documentation files in a docs/ directory.".to_string()
-                } else {
-                    format!("Generate documentation for the codebase with these instructions: {}. Create documentation files in a docs/ directory.", prompt)
-                };
-                run_with_ui(p, config.clone(), event_bus.clone(), true, args.command).await
-            }
-            CommandKind::Security => {
-                let p = if prompt.is_empty() {
-                    "SECURITY ANALYSIS ONLY: Perform a comprehensive security analysis of the codebase. DO NOT generate, modify, or create any source code files. ONLY analyze existing code for vulnerabilities, security issues, and best practice violations. Document your findings, risk assessments, and security recommendations in security_report.md.".to_string()
-                } else {
-                    format!("SECURITY ANALYSIS ONLY: Perform a security analysis of the codebase focusing on: {}. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your security findings in security_report.md", prompt)
-                };
-                run_with_ui(p, config.clone(), event_bus.clone(), true, args.command).await
+                .map(|_| Outcome::Success),
+            CommandKind::Apply | CommandKind::Artifacts | CommandKind::Memory | CommandKind::Config | CommandKind::Changelog | CommandKind::CoverageGaps | CommandKind::BenchProviders | CommandKind::Serve => unreachable!("handled before the UI starts"),
+            command => {
+                let task = task_prompt(&command, &prompt, template.as_deref());
+                run_until_quit(task, config.clone(), event_bus.clone(), command, &run_options).await
             }
         };
 
         match result {
-            Ok(_) => {
+            Ok(outcome) => {
                 let _ = stop_tx.send(());
                 let _ = handle.await;
                 if let Ok(mut ui_guard) = ui_ref.try_lock() {
                     ui_guard.finish()?;
                 }
+                outcome
             }
             Err(e) => {
                 let _ = stop_tx.send(());
@@ -203,8 +668,11 @@ async fn main() -> Result<()> {
         }
     } else {
         // Use simple text UI when --no-dashboard is specified
-        let mut ui = if config.ui.colorful && config.ui.progress_bars && args.verbose {
-            EnhancedUI::new(false)
+        if config.execution.ask_for_guidance {
+            answer_guidance_requests(run_options.control.clone(), event_bus.clone());
+        }
+        let mut ui = if config.ui.progress_bars && args.verbose && summary.is_none() {
+            EnhancedUI::new(false).with_theme(theme)
         } else {
             EnhancedUI::new(true) // headless mode
         };
@@ -213,73 +681,234 @@ async fn main() -> Result<()> {
         // Start UI
         ui.start()?;
 
-        if matches!(args.command, CommandKind::Code) && prompt.is_empty() {
+        if matches!(args.command, CommandKind::Code) && prompt.is_empty() && run_options.resume.is_none() {
+            if summary.is_some() {
+                anyhow::bail!("PROMPT required for code command");
+            }
             ui.display_error("PROMPT required for code command").await?;
             ui.finish();
-            return Ok(());
+            return Ok(Outcome::Failed);
         }
 
         let result = match args.command {
-            CommandKind::Code => run_with_ui(prompt.clone(), config.clone(), event_bus.clone(), false, args.command).await,
-            CommandKind::Refactor => {
-                let p = if prompt.is_empty() {
-                    "Analyze the current directory and perform recommended refactoring.".to_string()
-                } else {
-                    prompt.clone()
-                };
-                run_with_ui(
-                    format!("Refactor codebase. {}", p),
-                    config.clone(),
-                    event_bus.clone(),
-                    true,
-                    args.command,
-                )
+            CommandKind::Replay => replay::run(&current_dir.join(".cli_engineer"), args.prompt.first().map(String::as_str), args.speed, &event_bus)
                 .await
-            }
-            CommandKind::Review => {
-                let p = if prompt.is_empty() {
-                    "ANALYSIS ONLY: Review the codebase files and create a comprehensive code review report. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your findings, suggestions, and recommendations in code_review.md. Focus on code quality, best practices, potential issues, and improvement opportunities.".to_string()
-                } else {
-                    format!("ANALYSIS ONLY: Review the codebase with focus on: {}. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your findings in code_review.md", prompt)
-                };
-                run_with_ui(p, config.clone(), event_bus.clone(), true, args.command).await
-            }
-            CommandKind::Docs => {
-                let p = if prompt.is_empty() {
-                    "Generate comprehensive documentation for the codebase. Create documentation files in a docs/ directory.".to_string()
-                } else {
-                    format!("Generate documentation for the codebase with these instructions: {}. Create documentation files in a docs/ directory.", prompt)
-                };
-                run_with_ui(p, config.clone(), event_bus.clone(), true, args.command).await
-            }
-            CommandKind::Security => {
-                let p = if prompt.is_empty() {
-                    "SECURITY ANALYSIS ONLY: Perform a comprehensive security analysis of the codebase. DO NOT generate, modify, or create any source code files. ONLY analyze existing code for vulnerabilities, security issues, and best practice violations. Document your findings, risk assessments, and security recommendations in security_report.md.".to_string()
-                } else {
-                    format!("SECURITY ANALYSIS ONLY: Perform a security analysis of the codebase focusing on: {}. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your security findings in security_report.md", prompt)
-                };
-                run_with_ui(p, config.clone(), event_bus.clone(), true, args.command).await
+                .map(|_| Outcome::Success),
+            CommandKind::Apply | CommandKind::Artifacts | CommandKind::Memory | CommandKind::Config | CommandKind::Changelog | CommandKind::CoverageGaps | CommandKind::BenchProviders | CommandKind::Serve => unreachable!("handled before the UI starts"),
+            command => {
+                let task = task_prompt(&command, &prompt, template.as_deref());
+                run_until_quit(task, config.clone(), event_bus.clone(), command, &run_options).await
             }
         };
 
         match result {
-            Ok(_) => ui.finish(),
+            Ok(outcome) => {
+                ui.finish();
+                outcome
+            }
+            // Headless runs leave the error to the exit status and stderr
+            Err(e) if summary.is_some() => return Err(e),
             Err(e) => {
                 ui.display_error(&format!("{}", e)).await?;
                 ui.finish();
                 return Err(e);
             }
         }
+    };
+
+    Ok(outcome)
+}
+
+/// Cancel the run on the first Ctrl-C, which saves its checkpoint and summary,
+/// and quit on the second without waiting for that. The dashboard reads
+/// Ctrl-C as a key instead.
+fn handle_interrupts(control: Arc<RunControl>, event_bus: Arc<EventBus>) {
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("\nCancelling the run; continue it later with --resume. Press Ctrl-C again to quit at once.");
+        let _ = control.send(ControlMessage::Cancel, &event_bus, "signal").await;
+        if tokio::signal::ctrl_c().await.is_ok() {
+            control.quit();
+        }
+    });
+}
+
+/// Run the agent for `command`, ending as cancelled as soon as the user quits
+async fn run_until_quit(task: String, config: Arc<Config>, event_bus: Arc<EventBus>, command: CommandKind, options: &RunOptions) -> Result<Outcome> {
+    // The SBOM comes from the lockfiles, not the code
+    let scan_codebase = !matches!(command, CommandKind::Code | CommandKind::Sbom);
+    let run = run_with_ui(task, config, event_bus, scan_codebase, command, options);
+    match options.control.unless_quit(run).await {
+        Some(result) => result,
+        None => {
+            warn!("Quitting without saving the state of the run");
+            // Dropping the run leaves its containers and clone behind
+            sandbox::remove_running();
+            remote_repo::remove_clones();
+            Ok(Outcome::Cancelled)
+        }
+    }
+}
+
+/// Ask on the terminal for the guidance the loop waits for between iterations
+fn answer_guidance_requests(control: Arc<RunControl>, event_bus: Arc<EventBus>) {
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut events = event_bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            };
+            let Event::Custom { event_type, data } = event else {
+                continue;
+            };
+            if event_type != run_control::GUIDANCE_REQUESTED_EVENT {
+                continue;
+            }
+            let iteration = data["iteration"].as_u64().unwrap_or_default();
+            let text = tokio::task::spawn_blocking(move || {
+                use std::io::Write;
+                eprint!("Guidance for iteration {} (Enter to continue without): ", iteration);
+                let _ = std::io::stderr().flush();
+                let mut line = String::new();
+                let _ = std::io::stdin().read_line(&mut line);
+                line
+            })
+            .await
+            .unwrap_or_default();
+            let _ = control.send(ControlMessage::Guidance { text }, &event_bus, "terminal").await;
+        }
+    });
+}
+
+/// Configuration for running `command`: the configuration file with the
+/// command's `[command.<name>]` section and the command line applied, and the
+/// section's prompt template. `args.no_dashboard` must already say whether the
+/// dashboard will be shown.
+fn resolve_config(args: &Args, command: &CommandKind, unattended: bool) -> Result<(Config, Option<String>)> {
+    let command_name = command.name();
+    let mut config = Config::load(&args.config, args.profile.as_deref())?.for_command(&command_name);
+    if let Some(format) = args.log_format {
+        config.logging.format = format;
     }
+    config.execution.export_reasoning |= args.export_reasoning;
+    if args.timeout.is_some() {
+        config.execution.timeout_secs = args.timeout;
+    }
+    // A limit given for this run is not lowered by the task's complexity
+    if let Some(max_iterations) = args.max_iterations {
+        config.execution.max_iterations = max_iterations as usize;
+        config.execution.iteration_budget.adaptive = false;
+    }
+    config.execution.ask_for_guidance |= args.ask_guidance;
+    config.report.html |= args.html_report;
+    config.report.open |= args.open_report;
+    config.docs.diagrams |= args.diagrams;
+    if let Some(format) = args.sbom_format {
+        config.security.sbom_format = format;
+    }
+    // Nobody is there to answer, or the dashboard holds the terminal a
+    // conflict question would be asked on
+    if (unattended || !args.no_dashboard) && config.execution.conflict_strategy == ConflictStrategy::Prompt {
+        config.execution.conflict_strategy = ConflictStrategy::Merge;
+    }
+    if unattended {
+        config.execution.ask_for_guidance = false;
+    }
+    let template = config.command.get(&command_name).and_then(|settings| settings.prompt.clone());
+    Ok((config, template))
+}
 
-    Ok(())
+/// Task given to the agent for `command`: the `[command.<name>] prompt`
+/// template if there is one, else the built-in task, with `prompt` from the
+/// command line worked in
+fn task_prompt(command: &CommandKind, prompt: &str, template: Option<&str>) -> String {
+    if let Some(template) = template {
+        return if template.contains("{prompt}") {
+            template.replace("{prompt}", prompt).trim().to_string()
+        } else if prompt.is_empty() {
+            template.to_string()
+        } else {
+            format!("{} {}", template, prompt)
+        };
+    }
+    match command {
+        CommandKind::Refactor => {
+            let p = if prompt.is_empty() {
+                "Analyze the current directory and perform recommended refactoring.".to_string()
+            } else {
+                prompt.to_string()
+            };
+            format!("Refactor codebase. {}", p)
+        }
+        CommandKind::Review => {
+            if prompt.is_empty() {
+                "ANALYSIS ONLY: Review the codebase files and create a comprehensive code review report. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your findings, suggestions, and recommendations in code_review.md. Focus on code quality, best practices, potential issues, and improvement opportunities.".to_string()
+            } else {
+                format!("ANALYSIS ONLY: Review the codebase with focus on: {}. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your findings in code_review.md", prompt)
+            }
+        }
+        CommandKind::Docs => {
+            if prompt.is_empty() {
+                "Generate comprehensive documentation for the codebase. Create documentation files in a docs/ directory.".to_string()
+            } else {
+                format!("Generate documentation for the codebase with these instructions: {}. Create documentation files in a docs/ directory.", prompt)
+            }
+        }
+        CommandKind::Security => {
+            if prompt.is_empty() {
+                "SECURITY ANALYSIS ONLY: Perform a comprehensive security analysis of the codebase. DO NOT generate, modify, or create any source code files. ONLY analyze existing code for vulnerabilities, security issues, and best practice violations. Document your findings, risk assessments, and security recommendations in security_report.md.".to_string()
+            } else {
+                format!("SECURITY ANALYSIS ONLY: Perform a security analysis of the codebase focusing on: {}. DO NOT generate, modify, or create any source code files. ONLY analyze existing code and document your security findings in security_report.md", prompt)
+            }
+        }
+        CommandKind::Sbom => {
+            let focus = if prompt.is_empty() {
+                String::new()
+            } else {
+                format!(" Focus on: {}.", prompt)
+            };
+            format!("SBOM SUMMARY ONLY: A software bill of materials has been generated from the project's lockfiles and is listed in the context. DO NOT generate, modify, or create any source code files or another SBOM. Write an executive summary in sbom_report.md covering the notable components (frameworks, security-sensitive libraries such as cryptography, TLS, authentication and parsing, and packages that look outdated or unmaintained), the license mix, and licenses that may need legal review, such as copyleft (GPL, AGPL, LGPL, MPL) or unknown licenses.{}", focus)
+        }
+        _ => prompt.to_string(),
+    }
+}
+
+/// How the codebase is scanned into context for a run
+struct ScanOptions<'a> {
+    /// Project root to scan
+    root: &'a Path,
+    /// Directory holding the scan cache for `root`
+    cache_dir: &'a Path,
+    /// Index files for retrieval instead of adding them to context
+    retrieval: bool,
+    /// Task prompt, used to pick the relevant sections of large files
+    query: &'a str,
+    settings: &'a ScanConfig,
+    redactor: Option<&'a Redactor>,
 }
 
 async fn scan_and_populate_context(
     context_manager: &ContextManager,
+    artifact_manager: &ArtifactManager,
     context_id: &str,
     event_bus: Arc<EventBus>,
-) -> Result<(usize, String)> {
+    options: ScanOptions<'_>,
+    redactions: &mut RedactionReport,
+) -> Result<(usize, String, Vec<(String, String)>)> {
+    let ScanOptions {
+        root,
+        cache_dir,
+        retrieval,
+        query,
+        settings,
+        redactor,
+    } = options;
     let _ = event_bus
         .emit(Event::LogLine {
             level: "INFO".to_string(),
@@ -289,36 +918,34 @@ async fn scan_and_populate_context(
 
     let mut file_count = 0;
     let mut file_list = Vec::new();
-    let current_dir = std::env::current_dir()?;
-    
-    // Define extensions to scan
-    let code_extensions = vec![
-        "rs", "py", "js", "ts", "java", "c", "cpp", "h", "hpp", "go", 
-        "rb", "php", "swift", "kt", "scala", "sh", "bash", "yaml", "yml",
-        "json", "toml", "xml", "html", "css", "jsx", "tsx", "vue", "svelte"
-    ];
+    // Scanned files are returned for indexing; with retrieval enabled they are not added to context
+    let mut scanned_files = Vec::new();
+    // One-line summaries of the files left out because the codebase share of the
+    // context window is used up
+    let mut over_budget = Vec::new();
     
-    let config_files = vec![
-        "Cargo.toml", "package.json", "pom.xml", "build.gradle", 
-        "requirements.txt", "setup.py", "Gemfile", "composer.json",
-        "Makefile", "Dockerfile", ".gitignore", "README.md", "README"
-    ];
-
-    // Scan for code files
-    for entry in WalkDir::new(&current_dir)
-        .max_depth(5)
-        .into_iter()
+    // Changes are reported against the previous scan, whose summaries of unchanged files are reused
+    let mut scan_cache = ScanCache::load(cache_dir);
+    let first_scan = scan_cache.is_empty();
+    let mut changed_files = Vec::new();
+
+    // Scan for code files, honoring .gitignore, .git/info/exclude and .cli_engineerignore
+    let walker = WalkBuilder::new(root)
+        .max_depth(Some(settings.max_depth))
+        .hidden(true)
+        .require_git(false)
+        .add_custom_ignore_filename(".cli_engineerignore")
         .filter_entry(|e| {
+            // Dependency and output directories are skipped even without a .gitignore
             let name = e.file_name().to_string_lossy();
-            !name.starts_with('.') && 
-            name != "target" && 
-            name != "node_modules" && 
-            name != "venv" &&
-            name != "artifacts" &&
-            name != "dist" &&
-            name != "build"
+            !(e.file_type().is_some_and(|t| t.is_dir())
+                && matches!(
+                    name.as_ref(),
+                    "target" | "node_modules" | "venv" | "artifacts" | "dist" | "build"
+                ))
         })
-    {
+        .build();
+    for entry in walker {
         let entry = entry?;
         let path = entry.path();
         
@@ -329,36 +956,80 @@ async fn scan_and_populate_context(
                 .unwrap_or("");
             
             // Check if it's a code file or config file
-            let should_include = code_extensions.contains(&ext) || 
-                                config_files.iter().any(|&cf| file_name == cf);
+            let should_include = settings.extensions.iter().any(|e| e == ext) || 
+                                settings.file_names.iter().any(|f| file_name == f.as_str());
             
             if should_include {
-                // Skip very large files
+                // Skip files too large to be source code (generated data, bundles)
                 let metadata = std::fs::metadata(&path)?;
-                if metadata.len() > 100_000 {
+                if metadata.len() > settings.max_file_bytes {
                     info!("Skipping large file {:?} ({}KB)", path, metadata.len() / 1024);
                     continue;
                 }
                 
-                match std::fs::read_to_string(&path) {
-                    Ok(content) => {
-                        let relative_path = path.strip_prefix(&current_dir)
-                            .unwrap_or(path)
-                            .to_string_lossy();
+                let relative_path = path.strip_prefix(root)
+                    .unwrap_or(path)
+                    .to_string_lossy();
+                match scan_cache.read(path, &relative_path) {
+                    Ok((original, change)) => {
+                        if !first_scan && change != FileChange::Unchanged {
+                            changed_files.push(relative_path.to_string());
+                        }
+                        // Everything that can leave the machine uses the redacted content
+                        let content = match redactor {
+                            Some(redactor) => {
+                                let (redacted, counts) = redactor.redact_file(&relative_path, &original);
+                                redactions.record(&relative_path, counts);
+                                redacted
+                            }
+                            None => original.clone(),
+                        };
                         
+                        // Large files are reduced to their header and the sections most relevant to the task
+                        let elided = (content.len() > settings.large_file_bytes)
+                            .then(|| chunking::elide_large_file(&relative_path, &content, query));
                         let file_info = format!(
-                            "File: {}\n```{}\n{}\n```",
+                            "File: {}{}\n```{}\n{}\n```",
                             relative_path,
+                            if elided.is_some() { " (partially elided)" } else { "" },
                             ext.to_string(),
-                            content
+                            elided.as_ref().map_or(content.as_str(), |e| e.content.as_str())
                         );
                         
-                        context_manager
-                            .add_message(context_id, "system".to_string(), file_info)
-                            .await?;
+                        artifact_manager.record_snapshot(path, original).await;
+                        scanned_files.push((relative_path.to_string(), content.clone()));
+                        if !retrieval
+                            && !context_manager
+                                .add_message_from(
+                                    context_id,
+                                    "system".to_string(),
+                                    file_info,
+                                    ContextSource::Codebase,
+                                )
+                                .await?
+                        {
+                            over_budget.push(scan_cache.summary(&relative_path, &content));
+                            continue;
+                        }
                         
                         file_count += 1;
-                        file_list.push(relative_path.to_string());
+                        match &elided {
+                            Some(e) if !retrieval => {
+                                info!(
+                                    "Elided large file {}: kept {} of {} sections",
+                                    relative_path, e.sections_included, e.sections_total
+                                );
+                                file_list.push(format!(
+                                    "{} (large file, partially elided: {} of {} sections included)",
+                                    relative_path, e.sections_included, e.sections_total
+                                ));
+                            }
+                            // Indexed files are listed with a summary since their content is not in context
+                            _ if retrieval => {
+                                file_list.push(scan_cache.summary(&relative_path, &content))
+                            }
+                            _ => file_list.push(relative_path.to_string()),
+                        }
                         info!("Added {} to context ({} bytes)", relative_path, content.len());
                     }
                     Err(e) => {
@@ -369,6 +1040,18 @@ async fn scan_and_populate_context(
         }
     }
 
+    let removed_files = scan_cache.removed();
+    if let Err(e) = scan_cache.save() {
+        warn!("Failed to save scan cache: {}", e);
+    }
+    if !first_scan {
+        info!(
+            "{} files changed, {} removed since the last scan",
+            changed_files.len(),
+            removed_files.len()
+        );
+    }
+
     event_bus
         .emit(Event::LogLine {
             level: "INFO".to_string(),
@@ -378,23 +1061,203 @@ async fn scan_and_populate_context(
     
     info!("Scan complete: added {} files to context", file_count);
     
+    if !over_budget.is_empty() {
+        warn!(
+            "Codebase context budget exhausted; {} files were not loaded",
+            over_budget.len()
+        );
+    }
+
     // Create a summary of what was scanned
-    let file_summary = if file_count > 0 {
-        format!("\n\nThe following {} files from this codebase have been loaded into context:\n{}", 
-                file_count, 
+    let file_summary = if file_count == 0 {
+        String::new()
+    } else if retrieval {
+        format!("\n\nThe following {} files from this codebase have been indexed; relevant excerpts are provided with each step:\n{}",
+                file_count,
                 file_list.join("\n"))
     } else {
-        String::new()
+        let mut summary = format!("\n\nThe following {} files from this codebase have been loaded into context:\n{}", 
+                file_count, 
+                file_list.join("\n"));
+        if !changed_files.is_empty() || !removed_files.is_empty() {
+            summary.push_str("\n\nChanges since the last run:");
+            for path in &changed_files {
+                summary.push_str(&format!("\nmodified or added: {}", path));
+            }
+            for path in &removed_files {
+                summary.push_str(&format!("\nremoved: {}", path));
+            }
+        }
+        if !over_budget.is_empty() {
+            summary.push_str(&format!(
+                "\n\nThe context budget for the codebase is used up; the remaining files are summarized below.\n{}",
+                file_summary::repository_overview(&over_budget)
+            ));
+        }
+        summary
     };
     
-    Ok((file_count, file_summary))
+    Ok((file_count, file_summary, scanned_files))
 }
 
-async fn run_with_ui(prompt: String, config: Arc<Config>, event_bus: Arc<EventBus>, scan_codebase: bool, command: CommandKind) -> Result<()> {
+/// Add the files listed in `[context] pinned` to context as pinned messages,
+/// pinning the scanned copy when the file was already loaded
+async fn pin_configured_files(
+    context_manager: &ContextManager,
+    context_id: &str,
+    root: &Path,
+    pinned: &[String],
+    redactor: Option<&Redactor>,
+    redactions: &mut RedactionReport,
+) -> Result<()> {
+    for path in pinned {
+        let path = path.trim_start_matches("./");
+        // The scanned copy of a large file keeps its "(partially elided)"
+        // header, so the model still knows sections are missing
+        let whole = format!("File: {}\n", path);
+        let elided = format!("File: {} (partially elided)\n", path);
+        let scanned = context_manager
+            .pin_messages(context_id, |m| m.content.starts_with(&whole) || m.content.starts_with(&elided))
+            .await?;
+        if scanned > 0 {
+            continue;
+        }
+
+        match std::fs::read_to_string(root.join(path)) {
+            Ok(content) => {
+                let content = match redactor {
+                    Some(redactor) => {
+                        let (redacted, counts) = redactor.redact_file(path, &content);
+                        redactions.record(path, counts);
+                        redacted
+                    }
+                    None => content,
+                };
+                let ext = std::path::Path::new(path)
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or("");
+                context_manager
+                    .add_pinned_message(
+                        context_id,
+                        "system".to_string(),
+                        format!("File: {}\n```{}\n{}\n```", path, ext, content),
+                    )
+                    .await?;
+                info!("Pinned {} in context", path);
+            }
+            Err(e) => warn!("Failed to read pinned file {}: {}", path, e),
+        }
+    }
+    Ok(())
+}
+
+/// Where and how a command runs, from the command line
+struct RunOptions {
+    /// Pauses, cancels or skips steps of the run, from the dashboard or IPC
+    control: Arc<RunControl>,
+    /// Continue this named session
+    session: Option<String>,
+    /// Start from this exported context
+    context_file: Option<PathBuf>,
+    /// Project analyzed: the current directory or a clone of `--repo`
+    root: PathBuf,
+    /// Where the scan cache for `root` is kept
+    scan_cache_dir: PathBuf,
+    /// Print a summary of the run when it ends, for headless runs
+    summary: Option<SummaryFormat>,
+    /// Issue the run works on, commented on when it ends
+    issue: Option<issue::Issue>,
+    /// Jira or Linear ticket the run works on, commented on when it ends
+    ticket: Option<tracker::Ticket>,
+    /// Wait for an `approve` control message after each plan, as with
+    /// `ipc.require_approval`
+    require_approval: bool,
+    /// Checkpoint of an interrupted run to continue instead of starting over
+    resume: Option<checkpoint::Checkpoint>,
+    /// Record the model's exchanges, or replay recorded ones
+    cassette: Option<CassetteMode>,
+}
+
+/// How a headless run reports its outcome on stdout
+#[derive(Debug, Clone, Copy)]
+enum SummaryFormat {
+    /// `RunRecord::summary_line`
+    Line,
+    /// The run record as one line of JSON
+    Json,
+    /// The final review's issues for a CI system
+    Ci(ci_output::OutputFormat),
+}
+
+async fn run_with_ui(prompt: String, config: Arc<Config>, event_bus: Arc<EventBus>, scan_codebase: bool, command: CommandKind, options: &RunOptions) -> Result<Outcome> {
+    let RunOptions { control, session, context_file, root, scan_cache_dir, summary, issue, ticket, require_approval, resume, cassette } = options;
+    let cassette = cassette.as_ref();
+    // The timeout covers the whole run, scanning included
+    let deadline = config.execution.timeout_secs.map(|secs| tokio::time::Instant::now() + Duration::from_secs(secs));
+    // A continued run works on the interrupted run's task
+    let prompt = resume.as_ref().map_or(prompt, |checkpoint| checkpoint.prompt.clone());
     let (llm_manager, artifact_manager, context_manager) =
-        setup_managers(&*config, event_bus.clone()).await?;
+        setup_managers(&config, event_bus.clone(), cassette).await?;
 
     let task_id = Uuid::new_v4().to_string();
+    let started_at = chrono::Utc::now();
+    let run_id = format!(
+        "{}_{}",
+        chrono::Utc::now().format("%Y%m%d_%H%M%S"),
+        &task_id[..8]
+    );
+    let state_dir = std::env::current_dir()?.join(".cli_engineer");
+    let events_path = RunRecord::runs_dir(&state_dir).join(&run_id).join(EVENTS_FILE);
+    match JsonlEventLog::create(&events_path) {
+        Ok(event_log) => event_bus.add_sink(Arc::new(event_log)),
+        Err(e) => warn!("Event log disabled: {}", e),
+    }
+    if config.execution.export_reasoning {
+        let path = reasoning_log::reasoning_path(&state_dir, &run_id);
+        match ReasoningLog::create(&path, &run_id) {
+            Ok(reasoning_log) => {
+                info!("Exporting reasoning to {}", path.display());
+                event_bus.add_sink(Arc::new(reasoning_log));
+            }
+            Err(e) => warn!("Reasoning export disabled: {}", e),
+        }
+    }
+    let notifier = config
+        .notifications
+        .enabled()
+        .then(|| Arc::new(Notifier::new(config.notifications.clone())));
+    if let Some(notifier) = &notifier {
+        event_bus.add_sink(notifier.clone());
+    }
+    let hooks = (!config.hooks.commands.is_empty())
+        .then(|| Arc::new(HookRunner::new(config.hooks.clone(), &event_bus)));
+    if let Some(hooks) = &hooks {
+        event_bus.add_sink(hooks.clone());
+    }
+    let ipc_bridge = if config.ipc.enabled {
+        let endpoint = config
+            .ipc
+            .path
+            .clone()
+            .unwrap_or_else(|| ipc::default_endpoint(&state_dir));
+        match ipc::start(&endpoint, event_bus.clone(), control.clone()) {
+            Ok(bridge) => {
+                info!("Streaming events on {}", endpoint);
+                Some(bridge)
+            }
+            Err(e) => {
+                warn!("IPC bridge disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    if config.telemetry.enabled {
+        event_bus.add_sink(Arc::new(TelemetryMetrics::new()));
+    }
+    event_bus.set_budget(config.budget.clone());
     event_bus
         .emit(Event::TaskStarted {
             task_id: task_id.clone(),
@@ -403,8 +1266,34 @@ async fn run_with_ui(prompt: String, config: Arc<Config>, event_bus: Arc<EventBu
         .await?;
     info!("Emitting TaskStarted event for task: {}", prompt);
 
+    // Linters and scanners can run project code, so they go in a container if asked
+    let sandbox = config
+        .execution
+        .isolated_execution
+        .then(|| sandbox::Sandbox::new(config.execution.sandbox.clone()));
+
+    // Run security scanners so the report is grounded in real findings
+    let security_findings = if matches!(command, CommandKind::Security) {
+        security_scan::run_security_scanners(root, &config.security, sandbox.as_ref()).await
+    } else {
+        Vec::new()
+    };
+
+    // The SBOM is built from the lockfiles; the model only summarizes it
+    let dependencies = if matches!(command, CommandKind::Sbom) {
+        let dependencies = lockfiles::detect_dependencies(root);
+        if dependencies.is_empty() {
+            anyhow::bail!("No dependencies found: the sbom command reads Cargo.lock, package-lock.json and requirements.txt");
+        }
+        let project = root.file_name().map_or("project".to_string(), |name| name.to_string_lossy().to_string());
+        sbom::write_sbom(&artifact_manager, config.security.sbom_format, &project, &dependencies).await?;
+        dependencies
+    } else {
+        Vec::new()
+    };
+
     // Create and run agentic loop
-    let agentic_loop = AgenticLoop::new(
+    let mut agentic_loop = AgenticLoop::new(
         llm_manager.clone(),
         config.execution.max_iterations,
         event_bus.clone(),
@@ -412,11 +1301,142 @@ async fn run_with_ui(prompt: String, config: Arc<Config>, event_bus: Arc<EventBu
     .with_context_manager(context_manager.clone())
     .with_config(config.clone())
     .with_artifact_manager(artifact_manager.clone())
-    .with_command(command);
+    .with_command(command.clone());
+    // Plans can only be approved over IPC or by the client of a served run
+    agentic_loop = agentic_loop.with_control(
+        control.clone(),
+        *require_approval || (ipc_bridge.is_some() && config.ipc.require_approval),
+    );
+    if config.review.run_linters
+        && matches!(command, CommandKind::Review | CommandKind::Refactor)
+    {
+        agentic_loop = agentic_loop.with_linters(
+            root.clone(),
+            std::time::Duration::from_secs(config.review.linter_timeout_secs),
+            sandbox,
+        );
+    }
+    if let Some(second_opinion) = &config.review.second_opinion {
+        match create_second_opinion_manager(second_opinion, &config, event_bus.clone(), cassette) {
+            Ok(manager) => agentic_loop = agentic_loop.with_second_opinion(manager),
+            Err(e) => warn!("Second-opinion review disabled: {}", e),
+        }
+    }
+    if !security_findings.is_empty() {
+        agentic_loop = agentic_loop.with_known_issues(security_findings.clone());
+    }
+    if config.lsp.enabled {
+        agentic_loop = agentic_loop
+            .with_lsp(Arc::new(lsp::LspDiagnostics::new(root.clone(), &config.lsp)));
+    }
+    if config.git.enabled {
+        agentic_loop = agentic_loop.with_git(root.clone());
+    }
+    if let Some(issue) = issue
+        && matches!(issue.forge, issue::Forge::GitHub { .. })
+    {
+        agentic_loop = agentic_loop.with_closing_issue(issue.number);
+    }
+    if config.execution.save_transcripts {
+        agentic_loop =
+            agentic_loop.with_transcript_dir(state_dir.join("transcripts").join(&run_id));
+    }
+    if config.execution.save_iteration_reports {
+        agentic_loop =
+            agentic_loop.with_iteration_reports(RunRecord::runs_dir(&state_dir).join(&run_id));
+    }
+    if config.memory.enabled {
+        let embedding_model = config.context.embedding_model.clone();
+        match MemoryStore::open(&state_dir, &run_id, llm_manager.clone(), embedding_model).await {
+            Ok(store) => agentic_loop = agentic_loop.with_memory(Arc::new(store)),
+            Err(e) => warn!("Project memory unavailable: {:#}", e),
+        }
+    }
+    let sampling = &config.execution.sampling;
+    if sampling.candidates > 1 && config.execution.mode == ExecutionMode::Plan {
+        let mut managers = vec![llm_manager.clone()];
+        for provider in &sampling.providers {
+            match create_second_opinion_manager(provider, &config, event_bus.clone(), cassette) {
+                Ok(manager) => managers.push(manager),
+                Err(e) => warn!("Sampling provider {} skipped: {}", provider.provider, e),
+            }
+        }
+        let sampler = Sampler::new(sampling.candidates, managers, sampling.judge);
+        agentic_loop = agentic_loop.with_sampler(Arc::new(sampler));
+    }
+    if config.execution.mode == ExecutionMode::Agents {
+        let mut models = std::collections::HashMap::new();
+        for role in AgentRole::ALL {
+            let agent = role.config(&config.agents);
+            if agent.provider.is_none() && agent.model.is_none() {
+                continue;
+            }
+            let provider = agent.provider.clone();
+            let choice = SecondOpinionConfig {
+                provider: provider.unwrap_or_else(|| llm_manager.provider().name().to_lowercase()),
+                model: agent.model.clone(),
+            };
+            match create_second_opinion_manager(&choice, &config, event_bus.clone(), cassette) {
+                Ok(manager) => {
+                    models.insert(role, manager);
+                }
+                Err(e) => warn!("The {} agent uses the main model: {}", role, e),
+            }
+        }
+        let team = AgentTeam::new(llm_manager.clone(), models, &config.agents, event_bus.clone())
+            .with_context_manager(context_manager.clone());
+        agentic_loop = agentic_loop.with_agents(Arc::new(team));
+    }
+    if config.execution.mode == ExecutionMode::Tools {
+        let tools = ToolLoop::new(llm_manager.clone(), artifact_manager.clone(), config.clone(), root.clone())
+            .with_event_bus(event_bus.clone())
+            .with_docs_only(matches!(command, CommandKind::Docs));
+        agentic_loop = agentic_loop.with_tools(Arc::new(tools));
+    }
+    let command_name = command.to_possible_value().as_ref().map_or("", |v| v.get_name()).to_string();
+    let mut checkpoints = CheckpointStore::new(&state_dir, Checkpoint::new(&run_id, &command_name, &prompt));
+    if let Some(previous) = &resume {
+        checkpoints = checkpoints.replacing(&state_dir, previous);
+    }
+    let checkpoints = Arc::new(checkpoints);
+    agentic_loop = agentic_loop.with_checkpoints(checkpoints.clone(), resume.clone());
     info!("AgenticLoop instance created.");
-    let ctx_id = context_manager
-        .create_context(std::collections::HashMap::new())
-        .await;
+    if session.is_some() && !config.context.cache_enabled {
+        warn!("Sessions are stored in the context cache; enable [context] cache_enabled to keep them");
+    }
+    let ctx_id = match (session, context_file) {
+        (Some(name), _) => session::open_session(&context_manager, name, event_bus.clone()).await?,
+        (None, Some(path)) => {
+            let id = context_manager.import(path).await?;
+            info!("Loaded context {} from {}", id, path.display());
+            id
+        }
+        (None, None) => {
+            context_manager
+                .create_context(std::collections::HashMap::new())
+                .await
+        }
+    };
+    if !security_findings.is_empty() {
+        context_manager
+            .add_message_from(
+                &ctx_id,
+                "system".to_string(),
+                security_scan::findings_context(&security_findings),
+                ContextSource::Codebase,
+            )
+            .await?;
+    }
+    if !dependencies.is_empty() {
+        context_manager
+            .add_message_from(
+                &ctx_id,
+                "system".to_string(),
+                sbom::components_context(config.security.sbom_format, &dependencies),
+                ContextSource::Codebase,
+            )
+            .await?;
+    }
     info!("Context created. Running agentic loop...");
 
     // Emit execution started event
@@ -427,18 +1447,179 @@ async fn run_with_ui(prompt: String, config: Arc<Config>, event_bus: Arc<EventBu
         })
         .await?;
 
+    // Secrets are redacted from files before they are sent to the provider
+    let redactor = config
+        .redaction
+        .enabled
+        .then(|| Redactor::new(&config.redaction));
+    let mut redactions = RedactionReport::default();
+
     // Scan and populate context if requested
-    let mut enhanced_prompt = prompt;
+    let mut enhanced_prompt = prompt.clone();
+    let mut architecture = None;
     if scan_codebase {
-        let (file_count, file_summary) = scan_and_populate_context(&context_manager, &ctx_id, event_bus.clone()).await?;
+        let (file_count, file_summary, scanned_files) = scan_and_populate_context(
+            &context_manager,
+            &artifact_manager,
+            &ctx_id,
+            event_bus.clone(),
+            ScanOptions {
+                root,
+                cache_dir: scan_cache_dir,
+                retrieval: config.context.retrieval,
+                query: &enhanced_prompt,
+                settings: &config.scan.for_command(
+                    command
+                        .to_possible_value()
+                        .as_ref()
+                        .map_or("", |v| v.get_name()),
+                ),
+                redactor: redactor.as_ref(),
+            },
+            &mut redactions,
+        )
+        .await?;
+        let draw_diagrams = config.docs.diagrams && matches!(command, CommandKind::Docs);
+        if (config.context.symbol_index || draw_diagrams) && !scanned_files.is_empty() {
+            let index = CodeIndex::build(&scanned_files);
+            let graph = draw_diagrams.then(|| ModuleGraph::build(&index));
+            if let Some(graph) = graph.filter(|graph| !graph.is_empty()) {
+                // Drawn from the code so the documentation does not guess at the architecture
+                let section = graph.render();
+                context_manager
+                    .add_message_from(
+                        &ctx_id,
+                        "system".to_string(),
+                        diagrams::diagrams_context(&section),
+                        ContextSource::Codebase,
+                    )
+                    .await?;
+                architecture = Some(section);
+            }
+            if config.context.symbol_index {
+                if config.context.retrieval {
+                    // Files are not in context with retrieval, so give the model a map of the codebase
+                    context_manager
+                        .add_message_from(
+                            &ctx_id,
+                            "system".to_string(),
+                            index.outline(),
+                            ContextSource::Codebase,
+                        )
+                        .await?;
+                }
+                agentic_loop = agentic_loop.with_code_index(Arc::new(index));
+            }
+        }
+        if config.context.retrieval && !scanned_files.is_empty() {
+            let index = RetrievalIndex::build(
+                &scanned_files,
+                llm_manager.clone(),
+                config.context.embedding_model.clone(),
+            )
+            .await;
+            agentic_loop =
+                agentic_loop.with_retrieval(Arc::new(index), config.context.retrieval_top_k);
+        }
         if file_count > 0 {
             // Append file summary to the prompt so the planner knows what files exist
             enhanced_prompt = format!("{}{}", enhanced_prompt, file_summary);
         }
     }
 
-    let result = agentic_loop.run(&enhanced_prompt, &ctx_id).await;
+    if !config.mcp.servers.is_empty() {
+        let mut tools = mcp_client::McpTools::start(&config.mcp).await;
+        if config.redaction.enabled {
+            tools = tools.with_redactor(Redactor::new(&config.redaction));
+        }
+        if !tools.is_empty() {
+            agentic_loop = agentic_loop.with_mcp_tools(Arc::new(tools));
+        }
+    }
+
+    if !config.context.pinned.is_empty() {
+        pin_configured_files(
+            &context_manager,
+            &ctx_id,
+            root,
+            &config.context.pinned,
+            redactor.as_ref(),
+            &mut redactions,
+        )
+        .await?;
+    }
+    if !redactions.is_empty() {
+        let report_path = state_dir
+            .join("redaction_reports")
+            .join(format!("{}.json", run_id));
+        warn!(
+            "Redacted secrets before sending context to the provider:\n{}",
+            redactions.summary()
+        );
+        if let Err(e) = redactions.save(&report_path) {
+            warn!("Failed to save redaction report: {}", e);
+        }
+        let _ = event_bus
+            .emit(Event::Custom {
+                event_type: "secrets_redacted".to_string(),
+                data: serde_json::json!({
+                    "report": report_path.display().to_string(),
+                    "summary": redactions.summary(),
+                }),
+            })
+            .await;
+    }
+
+    // Cancelling or timing out drops the loop, and with it any LLM call in flight
+    let mut timed_out = false;
+    let result = tokio::select! {
+        result = agentic_loop
+            .run(&enhanced_prompt, &ctx_id)
+            .instrument(tracing::info_span!("run", run_id = %run_id, command = %command_name)) => result,
+        () = control.cancelled() => Err(anyhow::anyhow!("Run cancelled")),
+        () = until(deadline) => {
+            timed_out = true;
+            Err(anyhow::anyhow!("Run timed out after {}s", config.execution.timeout_secs.unwrap_or_default()))
+        }
+    };
     info!("Agentic loop completed");
+    // A step cut short may have saved artifacts the manifest does not list yet
+    if let Err(e) = artifact_manager.flush().await {
+        warn!("Failed to save the artifact manifest: {}", e);
+    }
+    // After an error the checkpoint is kept, so the run can be continued
+    if result.is_ok() {
+        checkpoints.clear();
+    }
+
+    // Scanner results go into the report as they are, next to the model's analysis
+    if let Err(e) = security_scan::append_dependency_report(&artifact_manager, &security_findings).await {
+        warn!("Failed to add the dependency audit to security_report.md: {}", e);
+    }
+    if !dependencies.is_empty()
+        && let Err(e) = sbom::write_report(&artifact_manager, config.security.sbom_format, &dependencies).await
+    {
+        warn!("Failed to write {}: {}", sbom::REPORT_NAME, e);
+    }
+    if let Some(section) = &architecture
+        && let Err(e) = diagrams::write_diagrams(&artifact_manager, section).await
+    {
+        warn!("Failed to write {}: {}", diagrams::DIAGRAMS_NAME, e);
+    }
+
+    // Keep the final context so it can be shared or reused with --context-file
+    match context_manager.export(&ctx_id, &state_dir.join("contexts")).await {
+        Ok(path) => info!("Context exported to {}", path.display()),
+        Err(e) => warn!("Failed to export context: {}", e),
+    }
+
+    if let Some(name) = session {
+        if let Err(e) = context_manager.save_to_cache(&ctx_id).await {
+            warn!("Failed to save session '{}': {}", name, e);
+        } else {
+            info!("Saved session '{}'", name);
+        }
+    }
 
     match result {
         Ok(_) => {
@@ -460,6 +1641,83 @@ async fn run_with_ui(prompt: String, config: Arc<Config>, event_bus: Arc<EventBu
                 .await?;
         }
     }
+    if let Some(notifier) = &notifier {
+        notifier.flush().await;
+    }
+    if let Some(hooks) = &hooks {
+        hooks.flush().await;
+    }
+
+    // Record what the run did so it can be reviewed later and exported with `artifacts -- export`
+    let metrics = event_bus.get_metrics().await;
+    let mut record = RunRecord {
+        run_id: run_id.clone(),
+        command: command_name,
+        prompt: prompt.clone(),
+        started_at,
+        finished_at: chrono::Utc::now(),
+        success: matches!(&result, Ok(Some(review)) if review.ready_to_deploy),
+        error: result.as_ref().err().map(|e| e.to_string()),
+        artifacts: artifact_manager
+            .list_artifacts()
+            .await
+            .into_iter()
+            .map(|a| ArtifactRecord { name: a.name, path: a.path.display().to_string() })
+            .collect(),
+        review: result.as_ref().ok().cloned().flatten(),
+        pull_request: agentic_loop.pull_request(),
+        iterations: agentic_loop.history(),
+        stalled: agentic_loop.stalled(),
+        api_calls: metrics.total_api_calls,
+        tokens: metrics.total_tokens,
+        cost: metrics.total_cost,
+        outcome: None,
+    };
+    let stopped = if timed_out {
+        Some(Outcome::TimedOut)
+    } else if control.is_cancelled() {
+        Some(Outcome::Cancelled)
+    } else {
+        None
+    };
+    let outcome = Outcome::of(&record, &config.budget, stopped, metrics.last_api_error.is_some());
+    record.outcome = Some(outcome);
+    match record.save(&state_dir) {
+        Ok(path) => info!("Run summary written to {}", path.display()),
+        Err(e) => warn!("Failed to save run summary: {}", e),
+    }
+    if config.report.html || config.report.open {
+        match html_report::write(&record, &state_dir) {
+            Ok(path) => {
+                info!("HTML report written to {}", path.display());
+                if config.report.open
+                    && let Err(e) = html_report::open_in_browser(&path)
+                {
+                    warn!("Failed to open the HTML report: {}", e);
+                }
+            }
+            Err(e) => warn!("Failed to write HTML report: {}", e),
+        }
+    }
+    if let Some(issue) = issue
+        && config.git.issue_comment
+    {
+        match issue.comment(&issue::run_comment(&record), &config.git).await {
+            Ok(()) => info!("Commented on issue #{}", issue.number),
+            Err(e) => warn!("{:#}", e),
+        }
+    }
+    if let Some(ticket) = ticket
+        && config.git.issue_comment
+    {
+        match ticket.comment(&issue::run_comment(&record), &config.tracker).await {
+            Ok(()) => info!("Commented on {}", ticket.key),
+            Err(e) => warn!("{:#}", e),
+        }
+    }
+    if let Err(e) = artifact_manager.enforce_retention(&state_dir).await {
+        warn!("Failed to prune old runs: {}", e);
+    }
 
     // Cleanup artifacts if configured
     if config.execution.cleanup_on_exit {
@@ -467,17 +1725,158 @@ async fn run_with_ui(prompt: String, config: Arc<Config>, event_bus: Arc<EventBu
         artifact_manager.cleanup().await?;
     }
 
-    result.map(|_| ())
+    match summary {
+        Some(SummaryFormat::Line) => println!("{}", record.summary_line()),
+        Some(SummaryFormat::Json) => println!("{}", serde_json::to_string(&record)?),
+        Some(SummaryFormat::Ci(format)) => println!("{}", ci_output::render(*format, &record)),
+        None => {}
+    }
+    if let Err(error) = result {
+        return Err(RunFailed { outcome, error }.into());
+    }
+    // The loop gives up without an error when the task is not done; headless
+    // runs still have to fail for cron and CI to notice
+    if summary.is_some() && outcome != Outcome::Success {
+        return Err(RunFailed { outcome, error: anyhow::anyhow!("{}", outcome) }.into());
+    }
+    Ok(outcome)
+}
+
+/// Resolves at `deadline`, or never without one
+async fn until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Build a single-provider LLM manager for second-opinion reviews. The provider's
+/// own config section supplies temperature and pricing; it does not need to be enabled.
+/// With a cassette its exchanges are recorded, or replayed without building the provider.
+fn create_second_opinion_manager(
+    second_opinion: &SecondOpinionConfig,
+    config: &Config,
+    event_bus: Arc<EventBus>,
+    cassette: Option<&CassetteMode>,
+) -> Result<Arc<LLMManager>> {
+    let build = || second_opinion_provider(second_opinion, config, event_bus.clone());
+    let provider = match cassette {
+        Some(cassette) => {
+            let model = second_opinion.model.clone().unwrap_or_default();
+            cassette.provider(&second_opinion.provider, &model, build)?
+        }
+        None => build()?,
+    };
+
+    info!(
+        "Second-opinion reviewer: {} ({})",
+        provider.name(),
+        provider.model_name()
+    );
+    Ok(Arc::new(LLMManager::new(
+        vec![provider],
+        event_bus,
+        Arc::new(config.clone()),
+    )))
+}
+
+/// The provider a second opinion, sampling provider or agent names
+fn second_opinion_provider(
+    second_opinion: &SecondOpinionConfig,
+    config: &Config,
+    event_bus: Arc<EventBus>,
+) -> Result<Box<dyn LLMProvider>> {
+    let providers = &config.ai_providers;
+    let model_for = |configured: Option<&String>| {
+        second_opinion
+            .model
+            .clone()
+            .or_else(|| configured.cloned())
+    };
+
+    let provider: Box<dyn LLMProvider> = match second_opinion.provider.to_lowercase().as_str() {
+        "openai" => {
+            let section = providers.openai.as_ref();
+            let provider = OpenAIProvider::new(
+                resolve_api_key("OPENAI_API_KEY", section)?,
+                model_for(section.map(|c| &c.model)),
+                section.and_then(|c| c.temperature),
+            )
+            .with_event_bus(event_bus.clone())
+            .with_cost_per_1m_input_tokens(
+                section.and_then(|c| c.cost_per_1m_input_tokens).unwrap_or(0.0),
+            )
+            .with_cost_per_1m_output_tokens(
+                section.and_then(|c| c.cost_per_1m_output_tokens).unwrap_or(0.0),
+            );
+            Box::new(provider)
+        }
+        "anthropic" => {
+            let section = providers.anthropic.as_ref();
+            Box::new(AnthropicProvider::new(
+                resolve_api_key("ANTHROPIC_API_KEY", section)?,
+                model_for(section.map(|c| &c.model))
+                    .unwrap_or_else(|| "claude-sonnet-4-0".to_string()),
+                section.and_then(|c| c.temperature).unwrap_or(0.7),
+                section.and_then(|c| c.cost_per_1m_input_tokens).unwrap_or(3.0),
+                section.and_then(|c| c.cost_per_1m_output_tokens).unwrap_or(15.0),
+                Some(event_bus.clone()),
+            ))
+        }
+        "openrouter" => {
+            let section = providers.openrouter.as_ref();
+            let provider = OpenRouterProvider::new(
+                resolve_api_key("OPENROUTER_API_KEY", section)?,
+                model_for(section.map(|c| &c.model)),
+                section.and_then(|c| c.temperature),
+                section.and_then(|c| c.max_tokens),
+            )
+            .with_event_bus(event_bus.clone())
+            .with_cost_per_1m_input_tokens(
+                section.and_then(|c| c.cost_per_1m_input_tokens).unwrap_or(0.0),
+            )
+            .with_cost_per_1m_output_tokens(
+                section.and_then(|c| c.cost_per_1m_output_tokens).unwrap_or(0.0),
+            );
+            Box::new(provider)
+        }
+        "gemini" => {
+            let section = providers.gemini.as_ref();
+            Box::new(GeminiProvider::new(
+                resolve_api_key("GEMINI_API_KEY", section)?,
+                model_for(section.map(|c| &c.model)),
+                section.and_then(|c| c.temperature),
+                section.and_then(|c| c.cost_per_1m_input_tokens),
+                section.and_then(|c| c.cost_per_1m_output_tokens),
+                Some(event_bus.clone()),
+            ))
+        }
+        "ollama" => {
+            let section = providers.ollama.as_ref();
+            Box::new(OllamaProvider::new(
+                model_for(section.map(|c| &c.model)),
+                section.and_then(|c| c.temperature),
+                section.and_then(|c| c.max_tokens),
+                Some(event_bus.clone()),
+            )?)
+        }
+        other => anyhow::bail!("unknown provider '{}'", other),
+    };
+    Ok(provider)
 }
 
 async fn setup_managers(
     config: &Config,
     event_bus: Arc<EventBus>,
+    cassette: Option<&CassetteMode>,
 ) -> Result<(Arc<LLMManager>, Arc<ArtifactManager>, Arc<ContextManager>)> {
     // Initialize artifact manager
     let mut artifact_manager =
         ArtifactManager::new(std::env::current_dir()?.join(&config.execution.artifact_dir))?;
     artifact_manager.set_event_bus(event_bus.clone());
+    artifact_manager.set_conflict_strategy(config.execution.conflict_strategy);
+    artifact_manager.set_limits(config.artifacts.clone());
+    artifact_manager.set_project_root(std::env::current_dir()?);
     let artifact_manager = Arc::new(artifact_manager);
 
     // Initialize context manager
@@ -488,6 +1887,7 @@ async fn setup_managers(
         cache_dir: std::env::current_dir()?
             .join(".cli_engineer")
             .join("context_cache"),
+        budgets: config.context.budgets,
     };
 
     let mut context_manager = ContextManager::new(context_config)?;
@@ -498,11 +1898,21 @@ async fn setup_managers(
 
     if let Some(openrouter_config) = &config.ai_providers.openrouter {
         if openrouter_config.enabled {
-            match OpenRouterProvider::new(
-                Some(openrouter_config.model.clone()),
-                openrouter_config.temperature,
-                openrouter_config.max_tokens,
-            ) {
+            match resolve_api_key("OPENROUTER_API_KEY", Some(openrouter_config)).map(|api_key| {
+                OpenRouterProvider::new(
+                    api_key,
+                    Some(openrouter_config.model.clone()),
+                    openrouter_config.temperature,
+                    openrouter_config.max_tokens,
+                )
+                .with_event_bus(event_bus.clone())
+                .with_cost_per_1m_input_tokens(
+                    openrouter_config.cost_per_1m_input_tokens.unwrap_or(0.0),
+                )
+                .with_cost_per_1m_output_tokens(
+                    openrouter_config.cost_per_1m_output_tokens.unwrap_or(0.0),
+                )
+            }) {
                 Ok(provider) => {
                     info!("OpenRouter provider initialized successfully");
                     providers.push(Box::new(provider));
@@ -516,13 +1926,16 @@ async fn setup_managers(
 
     if let Some(gemini_config) = &config.ai_providers.gemini {
         if gemini_config.enabled {
-            match GeminiProvider::new(
-                Some(gemini_config.model.clone()),
-                gemini_config.temperature,
-                gemini_config.cost_per_1m_input_tokens,
-                gemini_config.cost_per_1m_output_tokens,
-                Some(event_bus.clone()),
-            ) {
+            match resolve_api_key("GEMINI_API_KEY", Some(gemini_config)).map(|api_key| {
+                GeminiProvider::new(
+                    api_key,
+                    Some(gemini_config.model.clone()),
+                    gemini_config.temperature,
+                    gemini_config.cost_per_1m_input_tokens,
+                    gemini_config.cost_per_1m_output_tokens,
+                    Some(event_bus.clone()),
+                )
+            }) {
                 Ok(provider) => {
                     info!("Gemini provider initialized successfully");
                     providers.push(Box::new(provider));
@@ -538,10 +1951,13 @@ async fn setup_managers(
         debug!("Found OpenAI config: enabled={}, model={}", openai_config.enabled, openai_config.model);
         if openai_config.enabled {
             debug!("OpenAI provider is enabled, initializing...");
-            match OpenAIProvider::new(
-                Some(openai_config.model.clone()),
-                openai_config.temperature,
-            ) {
+            match resolve_api_key("OPENAI_API_KEY", Some(openai_config)).map(|api_key| {
+                OpenAIProvider::new(
+                    api_key,
+                    Some(openai_config.model.clone()),
+                    openai_config.temperature,
+                )
+            }) {
                 Ok(provider) => {
                     info!("OpenAI provider initialized successfully");
                     providers.push(Box::new(provider
@@ -564,20 +1980,23 @@ async fn setup_managers(
         debug!("Found Anthropic config: enabled={}, model={}", anthropic_config.enabled, anthropic_config.model);
         if anthropic_config.enabled {
             debug!("Anthropic provider is enabled, checking API key...");
-            if let Ok(api_key) = std::env::var("ANTHROPIC_API_KEY") {
-                debug!("API key found, initializing Anthropic provider");
-                let provider = AnthropicProvider::new(
-                    api_key,
-                    anthropic_config.model.clone(),
-                    anthropic_config.temperature.unwrap_or(0.7),
-                    anthropic_config.cost_per_1m_input_tokens.unwrap_or(3.0),
-                    anthropic_config.cost_per_1m_output_tokens.unwrap_or(15.0),
-                    Some(event_bus.clone()),
-                );
-                info!("Anthropic provider initialized successfully");
-                providers.push(Box::new(provider));
-            } else {
-                warn!("ANTHROPIC_API_KEY environment variable not set. Skipping Anthropic provider.");
+            match resolve_api_key("ANTHROPIC_API_KEY", Some(anthropic_config)) {
+                Ok(api_key) => {
+                    debug!("API key found, initializing Anthropic provider");
+                    let provider = AnthropicProvider::new(
+                        api_key,
+                        anthropic_config.model.clone(),
+                        anthropic_config.temperature.unwrap_or(0.7),
+                        anthropic_config.cost_per_1m_input_tokens.unwrap_or(3.0),
+                        anthropic_config.cost_per_1m_output_tokens.unwrap_or(15.0),
+                        Some(event_bus.clone()),
+                    );
+                    info!("Anthropic provider initialized successfully");
+                    providers.push(Box::new(provider));
+                }
+                Err(e) => {
+                    warn!("{}. Skipping Anthropic provider.", e);
+                }
             }
         } else {
             debug!("Anthropic provider is disabled in config");
@@ -605,11 +2024,31 @@ async fn setup_managers(
         }
     }
 
+    if let Some(CassetteMode::Replay(script)) = cassette {
+        // The recorded responses stand in for the configured providers
+        let (name, model) = script
+            .lock()
+            .unwrap()
+            .front()
+            .map(|first| (first.provider.clone(), first.model.clone()))
+            .unwrap_or_default();
+        providers = vec![Box::new(MockProvider::replaying(script.clone(), &name, &model))];
+    }
+
     if providers.is_empty() {
         error!("No AI providers configured, using LocalProvider");
         providers.push(Box::new(LocalProvider));
     }
 
+    if let Some(CassetteMode::Record(path)) = cassette {
+        providers = providers
+            .into_iter()
+            .map(|provider| {
+                Box::new(RecordingProvider::new(provider, path.clone())) as Box<dyn LLMProvider>
+            })
+            .collect();
+    }
+
     let llm_manager = Arc::new(LLMManager::new(
         providers,
         event_bus.clone(),
@@ -620,3 +2059,75 @@ async fn setup_managers(
 
     Ok((llm_manager, artifact_manager, context_manager))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tests_for_coverage_gaps_are_written_with_the_code_template() {
+        let path = std::env::temp_dir().join(format!("cli_engineer_config_{}.toml", Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+[ai_providers.ollama]
+enabled = true
+model = "qwen3:8b"
+
+[execution]
+max_iterations = 3
+
+[ui]
+[context]
+
+[command.coverage-gaps]
+prompt = "Gaps: {prompt}"
+
+[command.code]
+prompt = "Code: {prompt}"
+max_iterations = 7
+"#,
+        )
+        .unwrap();
+        let config_path = path.to_string_lossy().to_string();
+        let args = Args::parse_from(["cli_engineer", "--config", &config_path, "--write-tests", "coverage-gaps"]);
+
+        let (_, template) = resolve_config(&args, &args.command, true).unwrap();
+        assert_eq!(template.as_deref(), Some("Gaps: {prompt}"));
+        let (config, template) = resolve_config(&args, &CommandKind::Code, true).unwrap();
+        assert_eq!(template.as_deref(), Some("Code: {prompt}"));
+        assert_eq!(config.execution.max_iterations, 7);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_conflicts_are_not_asked_about_under_the_dashboard() {
+        let path = std::env::temp_dir().join(format!("cli_engineer_config_{}.toml", Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+[ai_providers.ollama]
+enabled = true
+model = "qwen3:8b"
+
+[execution]
+conflict_strategy = "prompt"
+
+[ui]
+[context]
+"#,
+        )
+        .unwrap();
+        let config_path = path.to_string_lossy().to_string();
+        let strategy = |extra: &[&str]| {
+            let mut argv = vec!["cli_engineer", "--config", &config_path];
+            argv.extend(extra);
+            argv.push("code");
+            let args = Args::parse_from(argv);
+            resolve_config(&args, &args.command, false).unwrap().0.execution.conflict_strategy
+        };
+        assert_eq!(strategy(&[]), ConflictStrategy::Merge);
+        assert_eq!(strategy(&["--no-dashboard"]), ConflictStrategy::Prompt);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
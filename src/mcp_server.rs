@@ -0,0 +1,375 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use ignore::WalkBuilder;
+use log::{info, warn};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+
+use crate::code_index::CodeIndex;
+use crate::config::Config;
+use crate::event_bus::EventBus;
+use crate::interpreter::Interpreter;
+use crate::mcp_client::PROTOCOL_VERSION;
+use crate::planner::Planner;
+use crate::run_control::RunControl;
+use crate::run_record::RunRecord;
+use crate::serve::rpc_error;
+use crate::{CommandKind, RunOptions, run_with_ui, setup_managers, task_prompt};
+
+/// Commands served clients can run
+pub(crate) const TASK_COMMANDS: [&str; 5] = ["code", "refactor", "review", "docs", "security"];
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Serve the agent as MCP tools on stdin and stdout until stdin closes.
+/// Requests are answered concurrently, but only one task runs at a time
+/// since runs share the working directory.
+pub async fn serve(config: Arc<Config>) -> Result<()> {
+    let server = Arc::new(McpServer {
+        config,
+        running: Mutex::new(()),
+        stdout: Mutex::new(tokio::io::stdout()),
+    });
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut handles = Vec::new();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let server = server.clone();
+        handles.push(tokio::spawn(async move {
+            if let Some(response) = server.handle(&line).await
+                && let Err(e) = server.send(&response).await
+            {
+                warn!("Failed to answer MCP request: {}", e);
+            }
+        }));
+    }
+    // Let requests in flight finish before exiting
+    for handle in handles {
+        let _ = handle.await;
+    }
+    Ok(())
+}
+
+struct McpServer {
+    config: Arc<Config>,
+    /// Held while a task runs
+    running: Mutex<()>,
+    stdout: Mutex<tokio::io::Stdout>,
+}
+
+impl McpServer {
+    async fn send(&self, message: &Value) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        let mut stdout = self.stdout.lock().await;
+        stdout.write_all(line.as_bytes()).await?;
+        stdout.flush().await?;
+        Ok(())
+    }
+
+    /// The response to one message, or `None` for notifications
+    async fn handle(&self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => return Some(rpc_error(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let result = match method {
+            "initialize" => json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "cli_engineer", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "ping" => json!({}),
+            "tools/list" => json!({ "tools": tool_definitions() }),
+            "tools/call" => {
+                let Some(name) = params.get("name").and_then(Value::as_str) else {
+                    return Some(rpc_error(id, INVALID_PARAMS, "Missing tool name"));
+                };
+                let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+                info!("MCP tool call: {}", name);
+                let (text, is_error) = match self.call_tool(name, &arguments).await {
+                    Ok(text) => (text, false),
+                    Err(e) => (format!("{:#}", e), true),
+                };
+                json!({
+                    "content": [{ "type": "text", "text": text }],
+                    "isError": is_error,
+                })
+            }
+            _ => {
+                return Some(rpc_error(
+                    id,
+                    METHOD_NOT_FOUND,
+                    &format!("Unknown method {}", method),
+                ));
+            }
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+
+    async fn call_tool(&self, name: &str, arguments: &Value) -> Result<String> {
+        let argument = |key: &str| arguments.get(key).and_then(Value::as_str).unwrap_or("");
+        match name {
+            "run_task" => self.run_task(argument("command"), argument("prompt")).await,
+            "plan_task" => self.plan_task(argument("prompt")).await,
+            "codebase_outline" => Ok(self.code_index()?.outline()),
+            "find_symbol" => {
+                let index = self.code_index()?;
+                let symbols = index.find(argument("name"));
+                if symbols.is_empty() {
+                    anyhow::bail!("No definition of {} found", argument("name"));
+                }
+                Ok(symbols
+                    .iter()
+                    .map(|s| {
+                        format!(
+                            "{} {} ({}:{}-{}): {}",
+                            s.kind, s.qualified_name, s.path, s.start_line, s.end_line, s.signature
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            _ => anyhow::bail!("Unknown tool {}", name),
+        }
+    }
+
+    /// Run the whole agent for `command` and describe what the run did
+    async fn run_task(&self, command_name: &str, prompt: &str) -> Result<String> {
+        let command = task_command(command_name, prompt)?;
+        let _running = self.running.lock().await;
+        // Each run counts its own calls, tokens and cost
+        let record = run_task(
+            &self.config,
+            command,
+            command_name,
+            prompt,
+            Arc::new(EventBus::new(1000)),
+            Arc::new(RunControl::new()),
+            false,
+        )
+        .await?;
+        Ok(record.to_markdown())
+    }
+
+    /// Interpret and plan a task without executing it
+    async fn plan_task(&self, prompt: &str) -> Result<String> {
+        if prompt.is_empty() {
+            anyhow::bail!("A prompt is required");
+        }
+        let (llm_manager, _, _) =
+            setup_managers(&self.config, Arc::new(EventBus::new(1000)), None).await?;
+        let task = Interpreter::new().interpret(prompt)?;
+        let plan = Planner::new()
+            .plan(&task, &llm_manager, Some(&self.config), None)
+            .await?;
+        let mut text = format!("Goal: {}\n", plan.goal);
+        for (i, step) in plan.steps.iter().enumerate() {
+            text.push_str(&format!(
+                "{}. [{:?}] {}\n",
+                i + 1,
+                step.category,
+                step.description
+            ));
+        }
+        Ok(text)
+    }
+
+    /// Symbol index of the working directory, built from the files the scan would read
+    fn code_index(&self) -> Result<CodeIndex> {
+        Ok(CodeIndex::build(&project_files(
+            &std::env::current_dir()?,
+            &self.config,
+        )))
+    }
+}
+
+/// The command named `command_name`, if served clients may run it with `prompt`
+pub(crate) fn task_command(command_name: &str, prompt: &str) -> Result<CommandKind> {
+    let command = CommandKind::from_str(command_name, true)
+        .ok()
+        .filter(|_| TASK_COMMANDS.contains(&command_name))
+        .with_context(|| {
+            format!(
+                "Unknown command {:?}; expected one of {}",
+                command_name,
+                TASK_COMMANDS.join(", ")
+            )
+        })?;
+    if matches!(command, CommandKind::Code) && prompt.is_empty() {
+        anyhow::bail!("A prompt is required for the code command");
+    }
+    Ok(command)
+}
+
+/// Run the whole agent on the working directory without a UI and return the
+/// record of the run
+pub(crate) async fn run_task(
+    config: &Config,
+    command: CommandKind,
+    command_name: &str,
+    prompt: &str,
+    event_bus: Arc<EventBus>,
+    control: Arc<RunControl>,
+    require_approval: bool,
+) -> Result<RunRecord> {
+    let config = config.clone().for_command(command_name);
+    let template = config
+        .command
+        .get(command_name)
+        .and_then(|settings| settings.prompt.clone());
+    let task = task_prompt(&command, prompt, template.as_deref());
+    let state_dir = std::env::current_dir()?.join(".cli_engineer");
+    let options = RunOptions {
+        control,
+        session: None,
+        context_file: None,
+        root: std::env::current_dir()?,
+        scan_cache_dir: state_dir.clone(),
+        summary: None,
+        issue: None,
+        ticket: None,
+        require_approval,
+        resume: None,
+        cassette: None,
+    };
+    let scan_codebase = !matches!(command, CommandKind::Code);
+    run_with_ui(
+        task,
+        Arc::new(config),
+        event_bus,
+        scan_codebase,
+        command,
+        &options,
+    )
+    .await?;
+    RunRecord::load(&state_dir, None)
+}
+
+/// Source files under `root` picked as the codebase scan picks them
+fn project_files(root: &Path, config: &Config) -> Vec<(String, String)> {
+    let settings = &config.scan;
+    let walker = WalkBuilder::new(root)
+        .max_depth(Some(settings.max_depth))
+        .hidden(true)
+        .require_git(false)
+        .add_custom_ignore_filename(".cli_engineerignore")
+        .build();
+    walker
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter(|entry| {
+            let path = entry.path();
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let file_name = entry.file_name().to_string_lossy();
+            settings.extensions.iter().any(|e| e == extension)
+                || settings.file_names.iter().any(|f| file_name == f.as_str())
+        })
+        .filter(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.len() <= settings.max_file_bytes)
+        })
+        .filter_map(|entry| {
+            let content = std::fs::read_to_string(entry.path()).ok()?;
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            Some((relative.to_string_lossy().to_string(), content))
+        })
+        .collect()
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "run_task",
+            "description": "Run cli_engineer on the working directory: interpret the task, plan it, execute the steps and review the result, iterating until it is done. Generated files are saved as artifacts. Returns a summary of the run.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "command": { "type": "string", "enum": TASK_COMMANDS, "description": "Kind of task" },
+                    "prompt": { "type": "string", "description": "What to do; optional except for code" },
+                },
+                "required": ["command"],
+            },
+        },
+        {
+            "name": "plan_task",
+            "description": "Plan a task without executing it. Returns the goal and the categorized steps.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "prompt": { "type": "string", "description": "The task" } },
+                "required": ["prompt"],
+            },
+        },
+        {
+            "name": "codebase_outline",
+            "description": "Outline of the working directory's source files: their imports and definition signatures.",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "find_symbol",
+            "description": "Where a function, type or method is defined, by bare or qualified name (`Config::load`).",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "name": { "type": "string" } },
+                "required": ["name"],
+            },
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mcp_server() {
+        let server = McpServer {
+            config: Arc::new(Config::default()),
+            running: Mutex::new(()),
+            stdout: Mutex::new(tokio::io::stdout()),
+        };
+        let initialize = server
+            .handle(r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#)
+            .await
+            .unwrap();
+        assert_eq!(initialize["result"]["serverInfo"]["name"], "cli_engineer");
+        assert!(
+            server
+                .handle(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+                .await
+                .is_none()
+        );
+        let tools = server
+            .handle(r#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#)
+            .await
+            .unwrap();
+        assert_eq!(tools["result"]["tools"][0]["name"], "run_task");
+
+        let call = server
+            .handle(r#"{"jsonrpc":"2.0","id":"a","method":"tools/call","params":{"name":"run_task","arguments":{"command":"apply"}}}"#)
+            .await
+            .unwrap();
+        assert_eq!(call["id"], "a");
+        assert_eq!(call["result"]["isError"], true);
+        let unknown = server
+            .handle(r#"{"jsonrpc":"2.0","id":3,"method":"resources/list"}"#)
+            .await
+            .unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        let garbage = server.handle("{").await.unwrap();
+        assert_eq!(garbage["error"]["code"], PARSE_ERROR);
+    }
+}
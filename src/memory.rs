@@ -0,0 +1,563 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::llm_manager::LLMManager;
+use crate::planner::Plan;
+use crate::retrieval::{Embedder, dot};
+use crate::reviewer::ReviewResult;
+
+/// Memories of the project, one JSON object per line, in the state directory
+pub const MEMORY_FILE: &str = "memory.jsonl";
+/// Embeddings of the memories, rebuilt when missing or made by another embedder
+pub const MEMORY_INDEX_FILE: &str = "memory_index.json";
+/// Event announcing a memory recorded for later runs
+pub const MEMORY_RECORDED_EVENT: &str = "memory_recorded";
+/// Event announcing the memories recalled for a task
+pub const MEMORIES_RECALLED_EVENT: &str = "memories_recalled";
+
+/// Similarity above which a new memory repeats an existing one
+const DUPLICATE_SIMILARITY: f32 = 0.95;
+/// Most memories the model may suggest at the end of a run
+const MAX_LEARNED: usize = 5;
+
+/// What a memory records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryKind {
+    /// A design choice made for the project, e.g. a library or data format
+    Decision,
+    /// How the project does things, e.g. naming, layout or tools
+    Convention,
+    /// Guidance the user gave to correct the agent. No longer recorded, as
+    /// guidance is meant for one run; kept so older memory files still load.
+    Correction,
+}
+
+impl fmt::Display for MemoryKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MemoryKind::Decision => "decision",
+            MemoryKind::Convention => "convention",
+            MemoryKind::Correction => "correction",
+        })
+    }
+}
+
+/// Something learned about the project, kept across runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Memory {
+    pub id: String,
+    pub kind: MemoryKind,
+    pub content: String,
+    /// Run the memory was recorded in
+    pub run_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind, self.content)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct MemoryIndex {
+    /// Embedder the vectors were made with
+    embedder: String,
+    /// Vectors by memory id
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+struct State {
+    memories: Vec<Memory>,
+    index: MemoryIndex,
+    /// Chosen once there is something to embed
+    embedder: Option<Embedder>,
+}
+
+/// Project-scoped store of decisions and conventions, so later runs don't
+/// have to learn them again
+pub struct MemoryStore {
+    dir: PathBuf,
+    run_id: String,
+    llm_manager: Arc<LLMManager>,
+    model: Option<String>,
+    state: Mutex<State>,
+}
+
+impl MemoryStore {
+    /// Load the memories in `state_dir`, embedding those the index lacks
+    pub async fn open(
+        state_dir: &Path,
+        run_id: &str,
+        llm_manager: Arc<LLMManager>,
+        model: Option<String>,
+    ) -> Result<Self> {
+        let memories = list(state_dir)?;
+        let index = read_index(state_dir).unwrap_or_default();
+
+        let store = Self {
+            dir: state_dir.to_path_buf(),
+            run_id: run_id.to_string(),
+            llm_manager,
+            model,
+            state: Mutex::new(State {
+                memories,
+                index,
+                embedder: None,
+            }),
+        };
+        store.sync_index().await;
+        Ok(store)
+    }
+
+    /// Embed the memories missing from the index, re-embedding them all when
+    /// the index was made by an embedder no longer in use
+    async fn sync_index(&self) {
+        let mut state = self.state.lock().await;
+        if state.memories.is_empty() {
+            return;
+        }
+        let provider = Embedder::Provider {
+            llm_manager: self.llm_manager.clone(),
+            model: self.model.clone(),
+        };
+        let embedder = if state.index.embedder == provider.name() {
+            provider
+        } else if state.index.embedder == Embedder::Local.name() {
+            Embedder::Local
+        } else {
+            let texts: Vec<String> = state.memories.iter().map(Memory::to_string).collect();
+            let (embedder, vectors) =
+                Embedder::choose(&texts, self.llm_manager.clone(), self.model.clone()).await;
+            let ids = state.memories.iter().map(|memory| memory.id.clone());
+            state.index = MemoryIndex {
+                embedder: embedder.name(),
+                vectors: ids.zip(vectors).collect(),
+            };
+            state.embedder = Some(embedder);
+            self.save_index(&state.index);
+            return;
+        };
+
+        let missing: Vec<&Memory> = state
+            .memories
+            .iter()
+            .filter(|memory| !state.index.vectors.contains_key(&memory.id))
+            .collect();
+        if !missing.is_empty() {
+            let texts: Vec<String> = missing.iter().map(|memory| memory.to_string()).collect();
+            match embedder.embed(&texts).await {
+                Ok(vectors) => {
+                    let ids: Vec<String> = missing.iter().map(|memory| memory.id.clone()).collect();
+                    state.index.vectors.extend(ids.into_iter().zip(vectors));
+                    self.save_index(&state.index);
+                }
+                Err(e) => warn!("Failed to embed {} memories: {}", texts.len(), e),
+            }
+        }
+        state.embedder = Some(embedder);
+    }
+
+    /// The `k` memories most relevant to `query`, best first
+    pub async fn recall(&self, query: &str, k: usize) -> Vec<Memory> {
+        let state = self.state.lock().await;
+        let Some(embedder) = &state.embedder else {
+            return Vec::new();
+        };
+        if k == 0 {
+            return Vec::new();
+        }
+        let query_vector = match embedder.embed(&[query.to_string()]).await {
+            Ok(mut vectors) if !vectors.is_empty() => vectors.remove(0),
+            Ok(_) => return Vec::new(),
+            Err(e) => {
+                warn!("Failed to embed memory query: {}", e);
+                return Vec::new();
+            }
+        };
+        let mut scored: Vec<(f32, &Memory)> = state
+            .memories
+            .iter()
+            .filter_map(|memory| {
+                let vector = state.index.vectors.get(&memory.id)?;
+                Some((dot(&query_vector, vector), memory))
+            })
+            .filter(|(score, _)| *score > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(_, memory)| memory.clone())
+            .collect()
+    }
+
+    /// Record `content` for later runs. Returns `None` when it is empty or
+    /// repeats a memory already recorded.
+    pub async fn record(&self, kind: MemoryKind, content: &str) -> Result<Option<Memory>> {
+        let content = content.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut state = self.state.lock().await;
+        let repeated = state
+            .memories
+            .iter()
+            .any(|memory| memory.content.eq_ignore_ascii_case(&content));
+        if content.is_empty() || repeated {
+            return Ok(None);
+        }
+
+        let memory = Memory {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            content,
+            run_id: self.run_id.clone(),
+            created_at: Utc::now(),
+        };
+        let texts = [memory.to_string()];
+        let vector = match &state.embedder {
+            Some(embedder) => match embedder.embed(&texts).await {
+                Ok(mut vectors) => vectors.pop(),
+                Err(e) => {
+                    // Embedded when the store is next opened
+                    warn!("Failed to embed memory: {}", e);
+                    None
+                }
+            },
+            None => {
+                let (embedder, mut vectors) =
+                    Embedder::choose(&texts, self.llm_manager.clone(), self.model.clone()).await;
+                state.index = MemoryIndex {
+                    embedder: embedder.name(),
+                    vectors: HashMap::new(),
+                };
+                state.embedder = Some(embedder);
+                vectors.pop()
+            }
+        };
+        if let Some(vector) = &vector
+            && state
+                .index
+                .vectors
+                .values()
+                .any(|other| dot(vector, other) > DUPLICATE_SIMILARITY)
+        {
+            return Ok(None);
+        }
+
+        let path = self.dir.join(MEMORY_FILE);
+        std::fs::create_dir_all(&self.dir).context("Failed to create state directory")?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&memory)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+
+        info!("Remembering {}", memory);
+        if let Some(vector) = vector {
+            state.index.vectors.insert(memory.id.clone(), vector);
+            self.save_index(&state.index);
+        }
+        state.memories.push(memory.clone());
+        Ok(Some(memory))
+    }
+
+    fn save_index(&self, index: &MemoryIndex) {
+        let path = self.dir.join(MEMORY_INDEX_FILE);
+        let written = serde_json::to_string(index)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| std::fs::write(&path, json).map_err(anyhow::Error::from));
+        if let Err(e) = written {
+            warn!("Failed to save memory index {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// The memories recorded in `state_dir`, oldest first
+pub fn list(state_dir: &Path) -> Result<Vec<Memory>> {
+    let path = state_dir.join(MEMORY_FILE);
+    let mut memories = Vec::new();
+    if !path.exists() {
+        return Ok(memories);
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    for (number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(memory) => memories.push(memory),
+            Err(e) => warn!("Skipping line {} of {}: {}", number + 1, path.display(), e),
+        }
+    }
+    Ok(memories)
+}
+
+fn read_index(state_dir: &Path) -> Option<MemoryIndex> {
+    let content = std::fs::read_to_string(state_dir.join(MEMORY_INDEX_FILE)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Forget the memory whose id is or starts with `id`, removing it from the
+/// memory file and its embedding from the index
+pub fn forget(state_dir: &Path, id: &str) -> Result<Memory> {
+    let mut matching = list(state_dir)?
+        .into_iter()
+        .filter(|memory| !id.is_empty() && memory.id.starts_with(id));
+    let memory = match (matching.next(), matching.next()) {
+        (Some(memory), None) => memory,
+        (None, _) => anyhow::bail!("No memory has the id {}", id),
+        (Some(_), Some(_)) => anyhow::bail!("Several memories have ids starting with {}", id),
+    };
+
+    // Lines that don't parse are kept, so a hand edit gone wrong isn't lost
+    let path = state_dir.join(MEMORY_FILE);
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let kept: String = content
+        .lines()
+        .filter(|line| {
+            serde_json::from_str::<Memory>(line).map_or(true, |other| other.id != memory.id)
+        })
+        .map(|line| format!("{}\n", line))
+        .collect();
+    std::fs::write(&path, kept).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    if let Some(mut index) = read_index(state_dir)
+        && index.vectors.remove(&memory.id).is_some()
+    {
+        let path = state_dir.join(MEMORY_INDEX_FILE);
+        std::fs::write(&path, serde_json::to_string(&index)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(memory)
+}
+
+const USAGE: &str = "Usage: cli_engineer memory -- <list | forget ID>";
+
+/// The `memory` command: list the memories of the project, or forget one
+pub fn run(state_dir: &Path, args: &[String]) -> Result<()> {
+    match args {
+        [] => print_memories(state_dir),
+        [action, rest @ ..] => match (action.as_str(), rest) {
+            ("list", []) => print_memories(state_dir),
+            ("forget", [id]) => {
+                let memory = forget(state_dir, id)?;
+                println!("Forgot {}", memory);
+                Ok(())
+            }
+            _ => anyhow::bail!(USAGE),
+        },
+    }
+}
+
+fn print_memories(state_dir: &Path) -> Result<()> {
+    let memories = list(state_dir)?;
+    if memories.is_empty() {
+        println!("No memories recorded for this project");
+    }
+    for memory in memories {
+        println!(
+            "{}  {}  {}",
+            &memory.id[..memory.id.len().min(8)],
+            memory.created_at.format("%Y-%m-%d"),
+            memory
+        );
+    }
+    Ok(())
+}
+
+/// Prompt asking what a finished run taught about the project that later
+/// runs should know, given the memories already recorded
+pub fn learning_prompt(
+    task: &str,
+    plan: &Plan,
+    review: Option<&ReviewResult>,
+    known: &[Memory],
+) -> String {
+    let mut prompt = format!(
+        "A run of a coding agent just finished.\n\nTask: {}\n\nPlan:\n",
+        task
+    );
+    for (i, step) in plan.steps.iter().enumerate() {
+        prompt.push_str(&format!("{}. {}\n", i + 1, step.description));
+    }
+    if let Some(review) = review {
+        prompt.push_str(&format!("\nReview: {}\n", review.summary.trim()));
+        for issue in &review.issues {
+            prompt.push_str(&format!("- {}\n", issue.description));
+        }
+    }
+    if !known.is_empty() {
+        prompt.push_str("\nAlready remembered:\n");
+        for memory in known {
+            prompt.push_str(&format!("- {}\n", memory));
+        }
+    }
+    prompt.push_str(&format!(
+        "\nList what a developer starting a new task in this project should know and could \
+         not easily tell from the code: design choices made for the project (libraries, \
+         architecture, data formats) on lines starting with `DECISION:`, and the project's \
+         conventions (naming, layout, tools, style) on lines starting with `CONVENTION:`. \
+         One sentence each, at most {}. Leave out details of this task alone and anything \
+         already remembered. Reply with NONE if there is nothing worth remembering.",
+        MAX_LEARNED
+    ));
+    prompt
+}
+
+/// The memories suggested in a reply to the learning prompt
+pub fn parse_learned(reply: &str) -> Vec<(MemoryKind, String)> {
+    reply
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim().trim_start_matches(['-', '*']).trim_start();
+            let (kind, content) = if let Some(content) = line.strip_prefix("DECISION:") {
+                (MemoryKind::Decision, content)
+            } else {
+                (MemoryKind::Convention, line.strip_prefix("CONVENTION:")?)
+            };
+            let content = content.trim();
+            (!content.is_empty()).then(|| (kind, content.to_string()))
+        })
+        .take(MAX_LEARNED)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::event_bus::EventBus;
+    use crate::llm_manager::LocalProvider;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("cli_engineer_memory_{}", uuid::Uuid::new_v4()))
+    }
+
+    /// The local provider has no embeddings, so the local embedding is used
+    fn llm() -> Arc<LLMManager> {
+        Arc::new(LLMManager::new(
+            vec![Box::new(LocalProvider)],
+            Arc::new(EventBus::new(10)),
+            Arc::new(Config::default()),
+        ))
+    }
+
+    #[test]
+    fn test_parse_learned() {
+        let reply = "DECISION: The project uses sqlx for database access, not diesel.\n\
+                     - CONVENTION: Tests live in tests/ with one file per module.\n\
+                     Other text\nCONVENTION:\n";
+        assert_eq!(
+            parse_learned(reply),
+            vec![
+                (
+                    MemoryKind::Decision,
+                    "The project uses sqlx for database access, not diesel.".to_string()
+                ),
+                (
+                    MemoryKind::Convention,
+                    "Tests live in tests/ with one file per module.".to_string()
+                ),
+            ]
+        );
+        assert!(parse_learned("NONE").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_later_runs_recall_memories() {
+        let dir = temp_dir();
+        let store = MemoryStore::open(&dir, "run1", llm(), None).await.unwrap();
+        assert!(store.recall("database access", 3).await.is_empty());
+        let decision = "The project uses sqlx for database access, not diesel.";
+        store.record(MemoryKind::Decision, decision).await.unwrap();
+        let convention = "Tests live in tests/ with one file per module.";
+        store
+            .record(MemoryKind::Convention, convention)
+            .await
+            .unwrap();
+
+        let store = MemoryStore::open(&dir, "run2", llm(), None).await.unwrap();
+        let recalled = store
+            .recall("Add a table for users to the sqlx database", 1)
+            .await;
+        assert_eq!(recalled[0].to_string(), format!("decision: {}", decision));
+        assert_eq!(recalled[0].run_id, "run1");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_memories_are_not_recorded() {
+        let dir = temp_dir();
+        let store = MemoryStore::open(&dir, "run1", llm(), None).await.unwrap();
+        let decision = "The project uses sqlx for database access, not diesel.";
+        assert!(
+            store
+                .record(MemoryKind::Decision, decision)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        let repeated = "the project uses sqlx for database  access, not diesel.";
+        assert!(
+            store
+                .record(MemoryKind::Decision, repeated)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            store
+                .record(MemoryKind::Convention, "  ")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(list(&dir).unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_forget_removes_the_memory_and_its_embedding() {
+        let dir = temp_dir();
+        let store = MemoryStore::open(&dir, "run1", llm(), None).await.unwrap();
+        let kept = "Tests live in tests/ with one file per module.";
+        store.record(MemoryKind::Convention, kept).await.unwrap();
+        let forgotten = store
+            .record(MemoryKind::Decision, "The parser is generated by lalrpop.")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let memory = forget(&dir, &forgotten.id[..8]).unwrap();
+        assert_eq!(memory.id, forgotten.id);
+        let memories = list(&dir).unwrap();
+        assert_eq!(memories.len(), 1);
+        assert_eq!(memories[0].content, kept);
+        let index = read_index(&dir).unwrap();
+        assert!(!index.vectors.contains_key(&forgotten.id));
+        assert!(index.vectors.contains_key(&memories[0].id));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_forgetting_an_unknown_memory_is_an_error() {
+        let dir = temp_dir();
+        let error = forget(&dir, "1234").unwrap_err();
+        assert!(error.to_string().contains("No memory"), "{}", error);
+        assert!(forget(&dir, "").is_err());
+        assert!(run(&dir, &["forget".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use similar::{Algorithm, DiffOp, capture_diff_slices};
+
+/// Result of a line-based three-way merge
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeResult {
+    pub content: String,
+    pub conflicts: usize,
+}
+
+/// A contiguous change to the base: lines `start..end` replaced by `lines`
+#[derive(Debug, Clone)]
+struct Change<'a> {
+    start: usize,
+    end: usize,
+    lines: Vec<&'a str>,
+}
+
+fn changes<'a>(base: &[&'a str], other: &[&'a str]) -> Vec<Change<'a>> {
+    capture_diff_slices(Algorithm::Myers, base, other)
+        .into_iter()
+        .filter_map(|op| match op {
+            DiffOp::Equal { .. } => None,
+            DiffOp::Delete {
+                old_index, old_len, ..
+            } => Some(Change {
+                start: old_index,
+                end: old_index + old_len,
+                lines: Vec::new(),
+            }),
+            DiffOp::Insert {
+                old_index,
+                new_index,
+                new_len,
+            } => Some(Change {
+                start: old_index,
+                end: old_index,
+                lines: other[new_index..new_index + new_len].to_vec(),
+            }),
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => Some(Change {
+                start: old_index,
+                end: old_index + old_len,
+                lines: other[new_index..new_index + new_len].to_vec(),
+            }),
+        })
+        .collect()
+}
+
+fn overlaps(a: &Change, start: usize, end: usize) -> bool {
+    // Pure insertions at the same point count as overlapping
+    (a.start < end && start < a.end) || a.start == start || (a.start == a.end && a.start == end)
+}
+
+/// Apply one side's changes to the base range `start..end`
+fn apply_side<'a>(
+    base: &[&'a str],
+    start: usize,
+    end: usize,
+    changes: &[&Change<'a>],
+) -> Vec<&'a str> {
+    let mut out = Vec::new();
+    let mut pos = start;
+    for change in changes {
+        out.extend_from_slice(&base[pos..change.start]);
+        out.extend_from_slice(&change.lines);
+        pos = change.end;
+    }
+    out.extend_from_slice(&base[pos..end]);
+    out
+}
+
+/// Three-way merge of `ours` and `theirs`, both derived from `base`.
+///
+/// Non-overlapping edits from both sides are combined; overlapping edits that differ
+/// are written with git-style conflict markers using the given labels.
+pub fn three_way_merge(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    ours_label: &str,
+    theirs_label: &str,
+) -> MergeResult {
+    let base_lines: Vec<&str> = base.lines().collect();
+    let our_lines: Vec<&str> = ours.lines().collect();
+    let their_lines: Vec<&str> = theirs.lines().collect();
+
+    let our_changes = changes(&base_lines, &our_lines);
+    let their_changes = changes(&base_lines, &their_lines);
+
+    let mut output: Vec<String> = Vec::new();
+    let mut conflicts = 0;
+    let mut pos = 0;
+    let (mut i, mut j) = (0, 0);
+
+    while i < our_changes.len() || j < their_changes.len() {
+        // Start a cluster with whichever change comes first
+        let (mut start, mut end) = match (our_changes.get(i), their_changes.get(j)) {
+            (Some(a), Some(b)) if b.start < a.start => (b.start, b.end),
+            (Some(a), _) => (a.start, a.end),
+            (None, Some(b)) => (b.start, b.end),
+            (None, None) => break,
+        };
+        let (mut ours_in, mut theirs_in): (Vec<&Change>, Vec<&Change>) = (Vec::new(), Vec::new());
+
+        // Grow the cluster until no more changes from either side overlap it
+        loop {
+            let mut grew = false;
+            while let Some(change) = our_changes.get(i).filter(|c| overlaps(c, start, end)) {
+                start = start.min(change.start);
+                end = end.max(change.end);
+                ours_in.push(change);
+                i += 1;
+                grew = true;
+            }
+            while let Some(change) = their_changes.get(j).filter(|c| overlaps(c, start, end)) {
+                start = start.min(change.start);
+                end = end.max(change.end);
+                theirs_in.push(change);
+                j += 1;
+                grew = true;
+            }
+            if !grew {
+                break;
+            }
+        }
+
+        output.extend(base_lines[pos..start].iter().map(|l| l.to_string()));
+        pos = end;
+
+        let our_version = apply_side(&base_lines, start, end, &ours_in);
+        let their_version = apply_side(&base_lines, start, end, &theirs_in);
+
+        let merged = if theirs_in.is_empty() || our_version == their_version {
+            our_version
+        } else if ours_in.is_empty() {
+            their_version
+        } else {
+            conflicts += 1;
+            output.push(format!("<<<<<<< {}", ours_label));
+            output.extend(our_version.iter().map(|l| l.to_string()));
+            output.push("=======".to_string());
+            output.extend(their_version.iter().map(|l| l.to_string()));
+            output.push(format!(">>>>>>> {}", theirs_label));
+            continue;
+        };
+        output.extend(merged.iter().map(|l| l.to_string()));
+    }
+
+    output.extend(base_lines[pos..].iter().map(|l| l.to_string()));
+
+    let mut content = output.join("\n");
+    if theirs.ends_with('\n') && !content.is_empty() {
+        content.push('\n');
+    }
+    MergeResult { content, conflicts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_overlapping_edits_merge_cleanly() {
+        let base = "a\nb\nc\nd\ne\n";
+        let ours = "a\nB\nc\nd\ne\n";
+        let theirs = "a\nb\nc\nd\nE\n";
+        let result = three_way_merge(base, ours, theirs, "workspace", "generated");
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.content, "a\nB\nc\nd\nE\n");
+    }
+
+    #[test]
+    fn test_overlapping_edits_conflict() {
+        let base = "a\nb\nc\n";
+        let ours = "a\nuser\nc\n";
+        let theirs = "a\nmodel\nc\n";
+        let result = three_way_merge(base, ours, theirs, "workspace", "generated");
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(
+            result.content,
+            "a\n<<<<<<< workspace\nuser\n=======\nmodel\n>>>>>>> generated\nc\n"
+        );
+    }
+
+    #[test]
+    fn test_identical_edits() {
+        let base = "x\ny\n";
+        let both = "x\nz\n";
+        let result = three_way_merge(base, both, both, "a", "b");
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.content, both);
+    }
+}
@@ -0,0 +1,356 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+
+use crate::config::{NotificationKind, NotificationsConfig, WebhookConfig, WebhookFormat};
+use crate::event_bus::{Event, EventSink};
+
+/// Longest time `flush` waits for deliveries still in flight
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(10);
+/// Discord rejects messages longer than this
+const DISCORD_MAX_CHARS: usize = 2000;
+/// Desktop notifications are truncated by the OS well before this
+const DESKTOP_MAX_CHARS: usize = 300;
+
+/// A message about the run, sent to every webhook subscribed to its kind
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub task: String,
+    pub summary: String,
+    pub cost: f32,
+    pub artifacts: Vec<String>,
+}
+
+impl Notification {
+    fn title(&self) -> &'static str {
+        match self.kind {
+            NotificationKind::TaskCompleted => "✅ cli_engineer task completed",
+            NotificationKind::TaskFailed => "❌ cli_engineer task failed",
+            NotificationKind::BudgetThreshold => "⚠️ cli_engineer budget threshold reached",
+        }
+    }
+
+    fn task_line(&self) -> &str {
+        self.task.lines().next().unwrap_or_default()
+    }
+
+    fn text(&self) -> String {
+        let mut text = format!(
+            "{}: {}\n{}\nCost: ${:.4}",
+            self.title(),
+            self.task_line(),
+            self.summary,
+            self.cost
+        );
+        if !self.artifacts.is_empty() {
+            text.push_str(&format!("\nArtifacts: {}", self.artifacts.join(", ")));
+        }
+        text
+    }
+
+    /// Request body for a webhook of the given format
+    pub fn payload(&self, format: WebhookFormat) -> serde_json::Value {
+        match format {
+            WebhookFormat::Slack => serde_json::json!({ "text": self.text() }),
+            WebhookFormat::Discord => serde_json::json!({
+                "content": self.text().chars().take(DISCORD_MAX_CHARS).collect::<String>()
+            }),
+            WebhookFormat::Json => serde_json::to_value(self).unwrap_or_default(),
+        }
+    }
+
+    /// Body of a desktop notification: the task and its result
+    fn desktop_body(&self) -> String {
+        format!("{}\n{}", self.task_line(), self.summary)
+            .chars()
+            .take(DESKTOP_MAX_CHARS)
+            .collect()
+    }
+}
+
+/// What the notifier has seen of the run so far
+#[derive(Default)]
+struct RunState {
+    task: String,
+    cost: f32,
+    artifacts: Vec<String>,
+    finished: bool,
+}
+
+/// Event sink posting notifications to the configured webhooks and, when
+/// enabled, showing desktop notifications when the run finishes. Deliveries
+/// run in the background; `flush` waits for them before the process exits.
+pub struct Notifier {
+    config: NotificationsConfig,
+    client: reqwest::Client,
+    state: Mutex<RunState>,
+    pending: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Notifier {
+    pub fn new(config: NotificationsConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::builder()
+                .timeout(FLUSH_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            state: Mutex::new(RunState::default()),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Notification triggered by `event`, if any. Only the first of the
+    /// completion and failure events of a run is reported.
+    fn notification_for(&self, event: &Event) -> Option<Notification> {
+        let mut state = self.state.lock().unwrap();
+        let (kind, summary) = match event {
+            Event::TaskStarted { description, .. } => {
+                if state.task.is_empty() {
+                    state.task = description.clone();
+                }
+                return None;
+            }
+            Event::ArtifactCreated { name, .. } => {
+                state.artifacts.push(name.clone());
+                return None;
+            }
+            Event::APICallCompleted { cost, .. } => {
+                state.cost += cost;
+                return None;
+            }
+            Event::BudgetThreshold {
+                pct,
+                kind,
+                used,
+                limit,
+            } => (
+                NotificationKind::BudgetThreshold,
+                format!(
+                    "The run has used {}% of its {} budget ({} of {})",
+                    pct,
+                    kind,
+                    kind.format_amount(*used),
+                    kind.format_amount(*limit)
+                ),
+            ),
+            Event::TaskCompleted { result, .. } if !state.finished => {
+                state.finished = true;
+                (NotificationKind::TaskCompleted, result.clone())
+            }
+            Event::TaskFailed { error, .. } if !state.finished => {
+                state.finished = true;
+                (NotificationKind::TaskFailed, error.clone())
+            }
+            _ => return None,
+        };
+        Some(Notification {
+            kind,
+            task: state.task.clone(),
+            summary,
+            cost: state.cost,
+            artifacts: state.artifacts.clone(),
+        })
+    }
+
+    fn subscribed(webhook: &WebhookConfig, kind: NotificationKind) -> bool {
+        webhook.events.is_empty() || webhook.events.contains(&kind)
+    }
+
+    /// Wait for deliveries still in flight, up to a timeout
+    pub async fn flush(&self) {
+        let pending: Vec<_> = self.pending.lock().unwrap().drain(..).collect();
+        let all = futures::future::join_all(pending);
+        if tokio::time::timeout(FLUSH_TIMEOUT, all).await.is_err() {
+            log::warn!("Gave up waiting for webhook notifications to be delivered");
+        }
+    }
+}
+
+impl EventSink for Notifier {
+    fn handle(&self, event: &Event) {
+        let Some(notification) = self.notification_for(event) else {
+            return;
+        };
+        if self.config.desktop && notification.kind != NotificationKind::BudgetThreshold {
+            let handle = tokio::spawn(notify_desktop(notification.clone()));
+            self.pending.lock().unwrap().push(handle);
+        }
+        for webhook in &self.config.webhooks {
+            if !Self::subscribed(webhook, notification.kind) {
+                continue;
+            }
+            let request = self
+                .client
+                .post(&webhook.url)
+                .json(&notification.payload(webhook.format));
+            let url = webhook.url.clone();
+            let handle = tokio::spawn(async move {
+                match request.send().await.and_then(|r| r.error_for_status()) {
+                    Ok(_) => log::info!("Sent notification to {}", url),
+                    Err(e) => log::warn!("Failed to send notification to {}: {}", url, e),
+                }
+            });
+            self.pending.lock().unwrap().push(handle);
+        }
+    }
+}
+
+/// Show `notification` with the platform's notifier
+async fn notify_desktop(notification: Notification) {
+    let urgent = notification.kind == NotificationKind::TaskFailed;
+    let mut command = desktop_command(notification.title(), &notification.desktop_body(), urgent);
+    match command.output().await {
+        Ok(output) if output.status.success() => log::info!("Sent desktop notification"),
+        Ok(output) => log::warn!(
+            "Desktop notification failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => log::warn!(
+            "Failed to run {} for desktop notifications: {}",
+            command.as_std().get_program().to_string_lossy(),
+            e
+        ),
+    }
+}
+
+/// The text is passed in the environment so it never needs quoting
+#[cfg(target_os = "macos")]
+fn desktop_command(title: &str, body: &str, _urgent: bool) -> Command {
+    let mut command = Command::new("osascript");
+    command
+        .args([
+            "-e",
+            "display notification (system attribute \"CLI_ENGINEER_BODY\") \
+             with title (system attribute \"CLI_ENGINEER_TITLE\")",
+        ])
+        .env("CLI_ENGINEER_TITLE", title)
+        .env("CLI_ENGINEER_BODY", body);
+    command
+}
+
+/// The text is passed in the environment so it never needs quoting
+#[cfg(windows)]
+fn desktop_command(title: &str, body: &str, _urgent: bool) -> Command {
+    const TOAST_SCRIPT: &str = r#"
+[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] | Out-Null
+$template = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02)
+$text = $template.GetElementsByTagName('text')
+$text.Item(0).AppendChild($template.CreateTextNode($env:CLI_ENGINEER_TITLE)) | Out-Null
+$text.Item(1).AppendChild($template.CreateTextNode($env:CLI_ENGINEER_BODY)) | Out-Null
+$toast = [Windows.UI.Notifications.ToastNotification]::new($template)
+[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('cli_engineer').Show($toast)
+"#;
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", TOAST_SCRIPT])
+        .env("CLI_ENGINEER_TITLE", title)
+        .env("CLI_ENGINEER_BODY", body);
+    command
+}
+
+/// `notify-send` from libnotify, available on most Linux and BSD desktops
+#[cfg(not(any(target_os = "macos", windows)))]
+fn desktop_command(title: &str, body: &str, urgent: bool) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args([
+        "--app-name=cli_engineer",
+        if urgent {
+            "--urgency=critical"
+        } else {
+            "--urgency=normal"
+        },
+        title,
+        body,
+    ]);
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::BudgetKind;
+
+    #[test]
+    fn test_notifications() {
+        let notifier = Notifier::new(NotificationsConfig::default());
+        let api_call = Event::APICallCompleted {
+            provider: "openai".to_string(),
+            tokens: 1000,
+            cost: 0.6,
+            duration_ms: 0,
+            output_tokens: 0,
+            first_token_ms: None,
+        };
+        assert!(
+            notifier
+                .notification_for(&Event::TaskStarted {
+                    task_id: "t1".to_string(),
+                    description: "Refactor the parser\nin detail".to_string(),
+                })
+                .is_none()
+        );
+        notifier.notification_for(&Event::ArtifactCreated {
+            name: "src/parser.rs".to_string(),
+            path: "artifacts/src/parser.rs".to_string(),
+            artifact_type: "SourceCode".to_string(),
+        });
+        for _ in 0..3 {
+            assert!(notifier.notification_for(&api_call).is_none());
+        }
+        let threshold = notifier
+            .notification_for(&Event::BudgetThreshold {
+                pct: 80,
+                kind: BudgetKind::Cost,
+                used: 1.8,
+                limit: 2.0,
+            })
+            .unwrap();
+        assert_eq!(threshold.kind, NotificationKind::BudgetThreshold);
+        assert_eq!(
+            threshold.summary,
+            "The run has used 80% of its cost budget ($1.80 of $2.00)"
+        );
+
+        let failed = Event::TaskFailed {
+            task_id: "main".to_string(),
+            error: "Planning failed".to_string(),
+        };
+        let notification = notifier.notification_for(&failed).unwrap();
+        assert!(notifier.notification_for(&failed).is_none());
+        assert_eq!(
+            notification.payload(WebhookFormat::Slack)["text"],
+            "❌ cli_engineer task failed: Refactor the parser\nPlanning failed\nCost: $1.8000\nArtifacts: src/parser.rs"
+        );
+        let json = notification.payload(WebhookFormat::Json);
+        assert_eq!(json["kind"], "task_failed");
+        assert_eq!(json["artifacts"][0], "src/parser.rs");
+        assert_eq!(
+            notification.desktop_body(),
+            "Refactor the parser\nPlanning failed"
+        );
+        #[cfg(target_os = "linux")]
+        {
+            let command = desktop_command(notification.title(), "body", true);
+            let args: Vec<_> = command.as_std().get_args().collect();
+            assert_eq!(command.as_std().get_program(), "notify-send");
+            assert_eq!(args[1], "--urgency=critical");
+            assert_eq!(args[3], "body");
+        }
+
+        let webhook = WebhookConfig {
+            url: "http://localhost".to_string(),
+            format: WebhookFormat::Discord,
+            events: vec![NotificationKind::TaskFailed],
+        };
+        assert!(Notifier::subscribed(&webhook, NotificationKind::TaskFailed));
+        assert!(!Notifier::subscribed(
+            &webhook,
+            NotificationKind::BudgetThreshold
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+use std::process::ExitCode;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::BudgetConfig;
+use crate::reviewer::IssueSeverity;
+use crate::run_record::RunRecord;
+
+/// How a run ended, each with its own exit status so scripts and CI can tell
+/// them apart. Status 2 is left to usage errors, which clap reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The task was completed and the final review found no major issue
+    Success,
+    /// The run failed, or ended without completing the task
+    Failed,
+    /// The final review lists critical or major issues
+    MajorIssues,
+    /// The run spent more than `budget.max_cost` or `budget.max_tokens`
+    BudgetExceeded,
+    /// The run was stopped by `--timeout` or `execution.timeout_secs`
+    TimedOut,
+    /// The run failed with the model provider failing its last call
+    ProviderFailure,
+    /// The run was cancelled, as with Ctrl-C
+    Cancelled,
+}
+
+impl Outcome {
+    /// How the run `record` describes ended. `stopped` is the outcome of a run
+    /// cut short by a timeout or cancellation; `provider_failed` tells whether
+    /// the last model call failed.
+    pub fn of(
+        record: &RunRecord,
+        budget: &BudgetConfig,
+        stopped: Option<Outcome>,
+        provider_failed: bool,
+    ) -> Self {
+        if let Some(outcome) = stopped {
+            return outcome;
+        }
+        let completed = record.error.is_none() && record.success;
+        if !completed && provider_failed {
+            return Outcome::ProviderFailure;
+        }
+        if record.error.is_some() {
+            return Outcome::Failed;
+        }
+        let over_cost = budget
+            .max_cost
+            .is_some_and(|max| max > 0.0 && record.cost > max);
+        let over_tokens = budget
+            .max_tokens
+            .is_some_and(|max| max > 0 && record.tokens > max);
+        if over_cost || over_tokens {
+            return Outcome::BudgetExceeded;
+        }
+        let major_issues = record.review.as_ref().is_some_and(|review| {
+            review.issues.iter().any(|issue| {
+                matches!(
+                    issue.severity,
+                    IssueSeverity::Critical | IssueSeverity::Major
+                )
+            })
+        });
+        match (major_issues, record.success) {
+            (true, _) => Outcome::MajorIssues,
+            (false, true) => Outcome::Success,
+            (false, false) => Outcome::Failed,
+        }
+    }
+
+    pub fn code(self) -> u8 {
+        match self {
+            Outcome::Success => 0,
+            Outcome::Failed => 1,
+            Outcome::MajorIssues => 3,
+            Outcome::BudgetExceeded => 4,
+            Outcome::TimedOut => 5,
+            Outcome::ProviderFailure => 6,
+            Outcome::Cancelled => 130,
+        }
+    }
+}
+
+impl From<Outcome> for ExitCode {
+    fn from(outcome: Outcome) -> Self {
+        ExitCode::from(outcome.code())
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Outcome::Success => "The task was completed",
+            Outcome::Failed => "The task was not completed",
+            Outcome::MajorIssues => "The final review found critical or major issues",
+            Outcome::BudgetExceeded => "The run went over its budget",
+            Outcome::TimedOut => "The run timed out",
+            Outcome::ProviderFailure => "The model provider failed",
+            Outcome::Cancelled => "The run was cancelled",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Error ending a run, with the outcome that sets the exit status. Displayed
+/// as the error it wraps.
+#[derive(Debug)]
+pub struct RunFailed {
+    pub outcome: Outcome,
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for RunFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for RunFailed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Exit status of a run that ended with `error`
+pub fn exit_code(error: &anyhow::Error) -> ExitCode {
+    error
+        .downcast_ref::<RunFailed>()
+        .map_or(ExitCode::FAILURE, |failed| failed.outcome.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reviewer::{Issue, IssueCategory, QualityLevel, ReviewResult};
+
+    #[test]
+    fn test_outcomes_of_runs() {
+        let now = chrono::Utc::now();
+        let mut record = RunRecord {
+            run_id: "20250101_090000_aaaa".to_string(),
+            command: "code".to_string(),
+            prompt: "Write a calculator".to_string(),
+            started_at: now,
+            finished_at: now,
+            success: true,
+            error: None,
+            artifacts: Vec::new(),
+            review: None,
+            pull_request: None,
+            iterations: Vec::new(),
+            stalled: None,
+            api_calls: 3,
+            tokens: 5000,
+            cost: 0.5,
+            outcome: None,
+        };
+        let budget = BudgetConfig::default();
+        let of =
+            |record: &RunRecord, budget: &BudgetConfig| Outcome::of(record, budget, None, false);
+        assert_eq!(of(&record, &budget), Outcome::Success);
+        let cancelled = Outcome::of(&record, &budget, Some(Outcome::Cancelled), true);
+        assert_eq!(cancelled.code(), 130);
+
+        let tight = BudgetConfig {
+            max_tokens: Some(4000),
+            ..BudgetConfig::default()
+        };
+        assert_eq!(of(&record, &tight), Outcome::BudgetExceeded);
+
+        record.review = Some(ReviewResult {
+            overall_quality: QualityLevel::Fair,
+            issues: vec![Issue {
+                severity: IssueSeverity::Major,
+                category: IssueCategory::Security,
+                description: "SQL built from user input".to_string(),
+                location: None,
+                suggestion: None,
+                patch: None,
+            }],
+            suggestions: Vec::new(),
+            ready_to_deploy: false,
+            summary: "Injection".to_string(),
+        });
+        record.success = false;
+        assert_eq!(of(&record, &budget).code(), 3);
+        // The model going away is told from the task not getting done
+        assert_eq!(
+            Outcome::of(&record, &budget, None, true),
+            Outcome::ProviderFailure
+        );
+        record.review = None;
+        assert_eq!(of(&record, &budget), Outcome::Failed);
+
+        let failed = anyhow::Error::new(RunFailed {
+            outcome: Outcome::TimedOut,
+            error: anyhow::anyhow!("Run timed out after 60s"),
+        });
+        assert_eq!(failed.to_string(), "Run timed out after 60s");
+        assert_eq!(exit_code(&failed), ExitCode::from(5));
+        assert_eq!(exit_code(&anyhow::anyhow!("No API key")), ExitCode::FAILURE);
+    }
+}
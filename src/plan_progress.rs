@@ -0,0 +1,287 @@
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+
+use crate::event_bus::{Event, StepStatus};
+
+/// Custom event announcing a plan and its steps before they run
+pub const PLAN_EXECUTION_STARTED_EVENT: &str = "plan_execution_started";
+
+/// Where a plan step stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Skipped,
+}
+
+impl StepState {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            StepState::Pending => "○",
+            StepState::Running => "▶",
+            StepState::Done => "✔",
+            StepState::Failed => "✘",
+            StepState::Skipped => "↷",
+        }
+    }
+}
+
+impl From<StepStatus> for StepState {
+    fn from(status: StepStatus) -> Self {
+        match status {
+            StepStatus::Done => StepState::Done,
+            StepStatus::Failed => StepState::Failed,
+            StepStatus::Skipped => StepState::Skipped,
+        }
+    }
+}
+
+/// A step as listed in the plan announcement
+#[derive(Deserialize)]
+struct PlannedStep {
+    id: String,
+    description: String,
+    #[serde(default)]
+    category: String,
+}
+
+/// One line of the plan checklist
+#[derive(Debug, Clone)]
+pub struct PlanStep {
+    pub id: String,
+    pub description: String,
+    /// `StepCategory` name, e.g. "CodeGeneration"
+    pub category: String,
+    pub state: StepState,
+    started: Option<Instant>,
+    duration: Option<Duration>,
+}
+
+impl PlanStep {
+    /// Time the step took, or has taken so far while it runs
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.duration
+            .or_else(|| self.started.map(|started| started.elapsed()))
+    }
+
+    pub fn category_icon(&self) -> &'static str {
+        match self.category.as_str() {
+            "Analysis" => "🔍",
+            "FileOperation" => "📁",
+            "CodeGeneration" => "✨",
+            "CodeModification" => "🔧",
+            "Testing" => "🧪",
+            "Documentation" => "📝",
+            "Research" => "📚",
+            "Review" => "👀",
+            _ => "•",
+        }
+    }
+}
+
+/// The current plan as a checklist, kept up to date from the plan announcement,
+/// step events and task progress
+#[derive(Debug, Default)]
+pub struct PlanProgress {
+    pub iteration: usize,
+    pub goal: String,
+    pub steps: Vec<PlanStep>,
+}
+
+impl PlanProgress {
+    /// Update the checklist from `event`, returning whether it changed
+    pub fn apply(&mut self, event: &Event) -> bool {
+        match event {
+            Event::Custom { event_type, data } if event_type == PLAN_EXECUTION_STARTED_EVENT => {
+                let steps: Vec<PlannedStep> = data
+                    .get("steps")
+                    .and_then(|steps| serde_json::from_value(steps.clone()).ok())
+                    .unwrap_or_default();
+                self.iteration = data
+                    .get("iteration")
+                    .and_then(|iteration| iteration.as_u64())
+                    .unwrap_or(self.iteration as u64 + 1) as usize;
+                self.goal = data
+                    .get("plan_goal")
+                    .and_then(|goal| goal.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                self.steps = steps
+                    .into_iter()
+                    .map(|step| PlanStep {
+                        id: step.id,
+                        description: step.description,
+                        category: step.category,
+                        state: StepState::Pending,
+                        started: None,
+                        duration: None,
+                    })
+                    .collect();
+                true
+            }
+            Event::StepStarted {
+                step_id,
+                description,
+                ..
+            } => {
+                let index = match self.steps.iter().position(|step| &step.id == step_id) {
+                    Some(index) => index,
+                    // Plans announced without their steps still get a checklist
+                    None => {
+                        self.steps.push(PlanStep {
+                            id: step_id.clone(),
+                            description: description.clone(),
+                            category: String::new(),
+                            state: StepState::Pending,
+                            started: None,
+                            duration: None,
+                        });
+                        self.steps.len() - 1
+                    }
+                };
+                let step = &mut self.steps[index];
+                step.state = StepState::Running;
+                step.started = Some(Instant::now());
+                step.duration = None;
+                true
+            }
+            Event::StepFinished {
+                step_id,
+                status,
+                duration_ms,
+                ..
+            } => match self.step_mut(step_id) {
+                Some(step) => {
+                    step.state = (*status).into();
+                    step.duration = Some(Duration::from_millis(*duration_ms));
+                    true
+                }
+                None => false,
+            },
+            // Runs recorded before step events only report completed steps
+            Event::TaskProgress { task_id, .. } => match self.step_mut(task_id) {
+                Some(step) if matches!(step.state, StepState::Pending | StepState::Running) => {
+                    step.state = StepState::Done;
+                    step.duration = step.elapsed();
+                    true
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn step_mut(&mut self, id: &str) -> Option<&mut PlanStep> {
+        self.steps.iter_mut().find(|step| step.id == id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Steps that have ended, whether done, failed or skipped
+    pub fn finished(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|step| !matches!(step.state, StepState::Pending | StepState::Running))
+            .count()
+    }
+
+    /// Fraction of the plan's steps that have ended
+    pub fn ratio(&self) -> f64 {
+        if self.steps.is_empty() {
+            0.0
+        } else {
+            self.finished() as f64 / self.steps.len() as f64
+        }
+    }
+
+    /// Index of the running step, or else of the last one that ended
+    pub fn current(&self) -> Option<usize> {
+        self.steps
+            .iter()
+            .position(|step| step.state == StepState::Running)
+            .or_else(|| {
+                self.steps
+                    .iter()
+                    .rposition(|step| step.state != StepState::Pending)
+            })
+    }
+}
+
+/// Step time as `4.2s` or `1m05s`
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 10 {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    } else if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_progress_from_events() {
+        let mut plan = PlanProgress::default();
+        assert!(plan.apply(&Event::Custom {
+            event_type: PLAN_EXECUTION_STARTED_EVENT.to_string(),
+            data: serde_json::json!({
+                "iteration": 2,
+                "plan_goal": "Add tests",
+                "steps": [
+                    {"id": "step_1", "description": "Read code", "category": "Analysis"},
+                    {"id": "step_2", "description": "Write tests", "category": "Testing"},
+                    {"id": "step_3", "description": "Document", "category": "Documentation"},
+                ],
+            }),
+        }));
+        assert_eq!((plan.iteration, plan.goal.as_str()), (2, "Add tests"));
+        assert_eq!(plan.steps[1].category_icon(), "🧪");
+        assert_eq!(plan.current(), None);
+
+        plan.apply(&Event::StepStarted {
+            step_id: "step_1".to_string(),
+            number: 1,
+            total: 3,
+            description: "Read code".to_string(),
+        });
+        assert_eq!(plan.steps[0].state, StepState::Running);
+        assert!(plan.steps[0].elapsed().is_some());
+        assert_eq!(plan.current(), Some(0));
+
+        plan.apply(&Event::StepFinished {
+            step_id: "step_1".to_string(),
+            status: StepStatus::Done,
+            duration_ms: 65_000,
+            error: None,
+        });
+        plan.apply(&Event::StepFinished {
+            step_id: "step_2".to_string(),
+            status: StepStatus::Skipped,
+            duration_ms: 1_500,
+            error: Some("Skipped by the user".to_string()),
+        });
+        // Older runs only report progress
+        plan.apply(&Event::TaskProgress {
+            task_id: "step_3".to_string(),
+            progress: 100.0,
+            message: String::new(),
+        });
+        let states: Vec<StepState> = plan.steps.iter().map(|step| step.state).collect();
+        assert_eq!(
+            states,
+            [StepState::Done, StepState::Skipped, StepState::Done]
+        );
+        assert_eq!(plan.ratio(), 1.0);
+        assert_eq!(format_elapsed(plan.steps[0].elapsed().unwrap()), "1m05s");
+        assert_eq!(format_elapsed(plan.steps[1].elapsed().unwrap()), "1.5s");
+        assert_eq!(format_elapsed(Duration::from_secs(42)), "42s");
+    }
+}
@@ -5,6 +5,9 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// Ends the description of a step the planner flags as high-risk
+const HIGH_RISK_MARKER: &str = "[high-risk]";
+
 /// Represents a structured plan with categorized steps
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Plan {
@@ -23,6 +26,9 @@ pub struct Step {
     pub expected_outputs: Vec<String>,
     pub success_criteria: Vec<String>,
     pub estimated_tokens: usize,
+    /// Easy to get subtly wrong, so worth several candidate responses
+    #[serde(default)]
+    pub high_risk: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -52,6 +58,7 @@ impl Planner {
     }
 
     /// Create a structured plan for the given task using the provided LLM
+    #[tracing::instrument(name = "plan", skip_all)]
     pub async fn plan(
         &self,
         task: &Task,
@@ -111,6 +118,9 @@ Provide the plan as a numbered list. Be concise and specific.",
             if cfg.execution.disable_auto_git {
                 prompt.push_str("\n\nIMPORTANT: Do NOT include git repository initialization (git init) or git-related setup steps unless explicitly requested in the task description. Focus only on the core functionality requested.");
             }
+            if cfg.execution.sampling.candidates > 1 {
+                prompt.push_str(&format!("\n\nEnd a step with {} when it is easy to get subtly wrong: concurrency, parsing, security-sensitive code, data migrations or changes to public APIs. Mark only the steps that truly are.", HIGH_RISK_MARKER));
+            }
         }
 
         // Add iteration context if provided
@@ -129,6 +139,18 @@ Provide the plan as a numbered list. Be concise and specific.",
                 prompt.push_str("\n3. If a file needs changes, describe what needs to be modified, not recreated");
                 prompt.push_str("\n4. Only create new files if they don't already exist");
             }
+
+            if !ctx.completed_steps.is_empty() || !ctx.failed_steps.is_empty() {
+                prompt.push_str("\n\nIMPORTANT: The completed steps listed above are done and their files are kept. Do NOT plan them again. Plan only the steps listed under \"Steps to redo\" and those needed to fix the pending issues.");
+            }
+
+            if !ctx.memories.is_empty() {
+                prompt.push_str("\n\nIMPORTANT: The project memory above records decisions, conventions and user corrections from earlier runs. Follow it unless the task says otherwise; do not plan work that contradicts it.");
+            }
+
+            if !ctx.user_guidance.is_empty() {
+                prompt.push_str("\n\nIMPORTANT: The user gave guidance during the run. Follow it in every step of the plan, even where it overrides the review's suggestions.");
+            }
         }
 
         prompt
@@ -198,26 +220,34 @@ Provide the plan as a numbered list. Be concise and specific.",
     }
 
     fn create_step_from_lines(&self, text: &str, index: usize) -> Step {
-        // Categorize the step based on keywords
-        let category = if text.contains("create") || text.contains("new file") {
+        let high_risk = text.contains(HIGH_RISK_MARKER);
+        let text = &text.replace(HIGH_RISK_MARKER, "");
+        let text = text.trim();
+
+        // Categorize the step based on keywords, whatever their case
+        let lower = text.to_lowercase();
+        let category = if lower.contains("create") || lower.contains("new file") {
             StepCategory::FileOperation
-        } else if text.contains("write") || text.contains("implement") || text.contains("generate")
+        } else if lower.contains("write")
+            || lower.contains("implement")
+            || lower.contains("generate")
         {
             StepCategory::CodeGeneration
-        } else if text.contains("modify") || text.contains("update") || text.contains("change") {
+        } else if lower.contains("modify") || lower.contains("update") || lower.contains("change") {
             StepCategory::CodeModification
-        } else if text.contains("test") || text.contains("verify") || text.contains("validate") {
+        } else if lower.contains("test") || lower.contains("verify") || lower.contains("validate") {
             StepCategory::Testing
-        } else if text.contains("document") || text.contains("comment") {
+        } else if lower.contains("document") || lower.contains("comment") {
             StepCategory::Documentation
-        } else if text.contains("analyze")
-            || text.contains("understand")
-            || text.contains("examine")
+        } else if lower.contains("analyze")
+            || lower.contains("understand")
+            || lower.contains("examine")
         {
             StepCategory::Analysis
-        } else if text.contains("research") || text.contains("look up") || text.contains("find") {
+        } else if lower.contains("research") || lower.contains("look up") || lower.contains("find")
+        {
             StepCategory::Research
-        } else if text.contains("review") || text.contains("check") {
+        } else if lower.contains("review") || lower.contains("check") {
             StepCategory::Review
         } else {
             StepCategory::Analysis // Default
@@ -231,6 +261,7 @@ Provide the plan as a numbered list. Be concise and specific.",
             expected_outputs: Vec::new(),
             success_criteria: vec![format!("Successfully complete: {}", text)],
             estimated_tokens: text.len() / 4, // Rough estimate
+            high_risk,
         }
     }
 }
@@ -240,3 +271,27 @@ impl Default for Planner {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_keywords_match_in_any_case() {
+        let planner = Planner::new();
+        for (text, category) in [
+            ("Create hello.py", StepCategory::FileOperation),
+            ("Implement the parser", StepCategory::CodeGeneration),
+            ("UPDATE the README links", StepCategory::CodeModification),
+            ("Verify the output", StepCategory::Testing),
+            ("Say hello", StepCategory::Analysis),
+        ] {
+            assert_eq!(
+                planner.create_step_from_lines(text, 1).category,
+                category,
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -2,14 +2,17 @@ use async_trait::async_trait;
 use anyhow::{anyhow, Context, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::sync::Arc;
+use std::time::Instant;
 use log::{debug, error};
 use futures::stream::StreamExt;
 use tokio_util::codec::{FramedRead, LinesCodec};
 use tokio_util::io::StreamReader;
 
-use crate::llm_manager::LLMProvider;
+use crate::llm_manager::{LLMProvider, ToolCall, ToolMessage, ToolResponse, ToolSpec};
 use crate::event_bus::{Event, EventBus};
+use crate::throughput::StreamMeter;
 
 #[derive(Debug, Serialize)]
 struct AnthropicRequest {
@@ -218,6 +221,7 @@ impl LLMProvider for AnthropicProvider {
 
     async fn send_prompt(&self, prompt: &str) -> Result<String> {
         let supports_thinking = self.supports_extended_thinking();
+        let mut meter = StreamMeter::start("anthropic", self.event_bus.clone());
         
         let request = AnthropicRequest {
             model: self.model.clone(),
@@ -307,6 +311,7 @@ impl LLMProvider for AnthropicProvider {
                                     ContentBlock::Thinking { thinking } => {
                                         debug!("Thinking block started: {}", thinking);
                                         thinking_buffer.push_str(&thinking);
+                                        meter.record(&thinking).await;
                                         
                                         // Handle initial thinking content with buffering
                                         if let Some(bus) = &self.event_bus {
@@ -326,6 +331,7 @@ impl LLMProvider for AnthropicProvider {
                                     ContentBlock::Text { text } => {
                                         debug!("Text block started: {}", text);
                                         final_text.push_str(&text);
+                                        meter.record(&text).await;
                                     }
                                 }
                             }
@@ -334,6 +340,7 @@ impl LLMProvider for AnthropicProvider {
                                     ContentDelta::ThinkingDelta { thinking } => {
                                         debug!("Thinking delta: {}", thinking);
                                         thinking_buffer.push_str(&thinking);
+                                        meter.record(&thinking).await;
                                         
                                         // Send chunks when buffer grows significantly OR at sentence boundaries
                                         if let Some(bus) = &self.event_bus {
@@ -363,6 +370,7 @@ impl LLMProvider for AnthropicProvider {
                                     ContentDelta::TextDelta { text } => {
                                         debug!("Text delta: {}", text);
                                         final_text.push_str(&text);
+                                        meter.record(&text).await;
                                     }
                                     ContentDelta::SignatureDelta { signature: _ } => {
                                         // Signature deltas are for cryptographic verification, we don't need to display them
@@ -437,6 +445,9 @@ impl LLMProvider for AnthropicProvider {
                 provider: "anthropic".to_string(),
                 tokens: total_input_tokens + total_output_tokens,
                 cost,
+                duration_ms: meter.duration_ms(),
+                output_tokens: total_output_tokens,
+                first_token_ms: meter.first_token_ms(),
             }).await;
         }
 
@@ -446,4 +457,136 @@ impl LLMProvider for AnthropicProvider {
 
         Ok(final_text)
     }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    async fn send_with_tools(
+        &self,
+        messages: &[ToolMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse> {
+        let started = Instant::now();
+        let tools: Vec<Value> = tools
+            .iter()
+            .map(|tool| json!({
+                "name": tool.name,
+                "description": tool.description,
+                "input_schema": tool.parameters,
+            }))
+            .collect();
+        let request = json!({
+            "model": self.model,
+            "messages": tool_messages(messages),
+            "tools": tools,
+            "max_tokens": 16000,
+            "temperature": self.temperature,
+        });
+
+        let response = self
+            .client
+            .post(format!("{}/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send request to Anthropic API")?;
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("Anthropic API error: {}", error_text));
+        }
+        let body: Value = response.json().await.context("Failed to parse Anthropic response")?;
+
+        let mut result = ToolResponse::default();
+        for block in body["content"].as_array().into_iter().flatten() {
+            match block["type"].as_str() {
+                Some("text") => result.text.push_str(block["text"].as_str().unwrap_or_default()),
+                Some("tool_use") => result.calls.push(ToolCall {
+                    id: block["id"].as_str().unwrap_or_default().to_string(),
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: block["input"].clone(),
+                }),
+                _ => {}
+            }
+        }
+
+        let input_tokens = body["usage"]["input_tokens"].as_u64().unwrap_or(0) as usize;
+        let output_tokens = body["usage"]["output_tokens"].as_u64().unwrap_or(0) as usize;
+        let cost = self.calculate_cost(input_tokens, output_tokens);
+        self.log_usage(input_tokens, output_tokens, cost);
+        if let Some(event_bus) = &self.event_bus {
+            let _ = event_bus.emit(Event::APICallCompleted {
+                provider: "anthropic".to_string(),
+                tokens: input_tokens + output_tokens,
+                cost,
+                duration_ms: started.elapsed().as_millis() as u64,
+                output_tokens,
+                first_token_ms: None,
+            }).await;
+        }
+        Ok(result)
+    }
+}
+
+/// A conversation with tools as Messages API messages: calls are `tool_use`
+/// blocks of the assistant, their results `tool_result` blocks of the user
+fn tool_messages(messages: &[ToolMessage]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|message| match message {
+            ToolMessage::User(text) => json!({"role": "user", "content": text}),
+            ToolMessage::Assistant { text, calls } => {
+                let mut content = Vec::new();
+                if !text.is_empty() {
+                    content.push(json!({"type": "text", "text": text}));
+                }
+                for call in calls {
+                    content.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.name,
+                        "input": call.arguments,
+                    }));
+                }
+                json!({"role": "assistant", "content": content})
+            }
+            ToolMessage::Results(results) => {
+                let content: Vec<Value> = results
+                    .iter()
+                    .map(|(id, output)| {
+                        json!({"type": "tool_result", "tool_use_id": id, "content": output})
+                    })
+                    .collect();
+                json!({"role": "user", "content": content})
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_messages() {
+        let messages = tool_messages(&[
+            ToolMessage::User("Fix the bug".to_string()),
+            ToolMessage::Assistant {
+                text: String::new(),
+                calls: vec![ToolCall {
+                    id: "toolu_1".to_string(),
+                    name: "read_file".to_string(),
+                    arguments: json!({"path": "src/lib.rs"}),
+                }],
+            },
+            ToolMessage::Results(vec![("toolu_1".to_string(), "fn main() {}".to_string())]),
+        ]);
+        assert_eq!(messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(messages[1]["content"][0]["input"]["path"], "src/lib.rs");
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][0]["tool_use_id"], "toolu_1");
+    }
 }
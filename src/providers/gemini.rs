@@ -1,5 +1,4 @@
 use serde::{Deserialize, Serialize};
-use std::env;
 use std::str;
 use anyhow::{anyhow, Context, Result};
 use futures_util::StreamExt;
@@ -8,6 +7,7 @@ use std::sync::Arc;
 
 use crate::llm_manager::LLMProvider;
 use crate::event_bus::{Event, EventBus};
+use crate::throughput::StreamMeter;
 
 /// Gemini API provider implementation
 pub struct GeminiProvider {
@@ -94,10 +94,8 @@ struct ResponsePart {
 
 impl GeminiProvider {
     /// Create a new Gemini provider with default settings
-    pub fn new(model: Option<String>, temperature: Option<f32>, cost_per_1m_input_tokens: Option<f32>, cost_per_1m_output_tokens: Option<f32>, event_bus: Option<Arc<EventBus>>) -> Result<Self> {
-        let api_key =
-            env::var("GEMINI_API_KEY").context("GEMINI_API_KEY environment variable not set")?;
-        Ok(Self {
+    pub fn new(api_key: String, model: Option<String>, temperature: Option<f32>, cost_per_1m_input_tokens: Option<f32>, cost_per_1m_output_tokens: Option<f32>, event_bus: Option<Arc<EventBus>>) -> Self {
+        Self {
             api_key,
             model: model.unwrap_or_else(|| "gemini-1.5-flash-latest".to_string()),
             base_url: "https://generativelanguage.googleapis.com/v1beta".to_string(),
@@ -106,7 +104,7 @@ impl GeminiProvider {
             event_bus,
             cost_per_1m_input_tokens: cost_per_1m_input_tokens.unwrap_or(0.0),
             cost_per_1m_output_tokens: cost_per_1m_output_tokens.unwrap_or(0.0),
-        })
+        }
     }
 }
 
@@ -134,6 +132,7 @@ impl LLMProvider for GeminiProvider {
     
     async fn send_prompt(&self, prompt: &str) -> Result<String> {
         let client = reqwest::Client::new();
+        let mut meter = StreamMeter::start("gemini", self.event_bus.clone());
 
         let request = GeminiRequest {
             contents: vec![
@@ -224,6 +223,7 @@ impl LLMProvider for GeminiProvider {
                                 if let Some(content) = &candidate.content {
                                     for part in &content.parts {
                                         if let Some(text) = &part.text {
+                                            meter.record(text).await;
                                             if part.thought {
                                                 // This is thinking content - buffer it and emit reasoning traces
                                                 thinking_buffer.push_str(text);
@@ -288,6 +288,9 @@ impl LLMProvider for GeminiProvider {
                 provider: "gemini".to_string(),
                 tokens: total_tokens,
                 cost: total_cost,
+                duration_ms: meter.duration_ms(),
+                output_tokens: total_candidates_tokens,
+                first_token_ms: meter.first_token_ms(),
             }).await;
         }
 
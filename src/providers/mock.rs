@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::llm_manager::LLMProvider;
+
+/// A prompt and what the provider answered, as kept in a cassette
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Provider and model that answered
+    #[serde(default)]
+    pub provider: String,
+    #[serde(default)]
+    pub model: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub response: String,
+    /// Set instead of `response` when the request failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Exchanges recorded from real providers, replayed to rerun a task
+/// deterministically and without API keys. Stored as JSON Lines, one
+/// interaction per line, so recording a prompt only appends to the file.
+#[derive(Debug, Clone, Default)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+        let interactions = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| {
+                serde_json::from_str(line).with_context(|| {
+                    format!("Invalid cassette {} at line {}", path.display(), number + 1)
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { interactions })
+    }
+
+    /// Add `interaction` to the end of the cassette at `path`
+    pub fn append(path: &Path, interaction: &Interaction) -> Result<()> {
+        let mut line = serde_json::to_string(interaction)?;
+        line.push('\n');
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open cassette {}", path.display()))?;
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("Failed to write cassette {}", path.display()))
+    }
+}
+
+/// Record the model's exchanges to a cassette, or replay them from one. Every
+/// provider of a run shares it: the main model, second opinions, sampling
+/// providers and agents with their own model.
+#[derive(Debug, Clone)]
+pub enum CassetteMode {
+    Record(PathBuf),
+    /// Interactions not replayed yet
+    Replay(Arc<Mutex<VecDeque<Interaction>>>),
+}
+
+impl CassetteMode {
+    /// Start a new recording at `path`, replacing an earlier one
+    pub fn record(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(path, "")
+            .with_context(|| format!("Failed to write cassette {}", path.display()))?;
+        Ok(Self::Record(path.to_path_buf()))
+    }
+
+    pub fn replay(path: &Path) -> Result<Self> {
+        let cassette = Cassette::load(path)?;
+        Ok(Self::Replay(Arc::new(Mutex::new(
+            cassette.interactions.into(),
+        ))))
+    }
+
+    /// Provider standing in for a `name` provider running `model`: `make` builds the
+    /// real one, whose exchanges are recorded, unless they are replayed
+    pub fn provider(
+        &self,
+        name: &str,
+        model: &str,
+        make: impl FnOnce() -> Result<Box<dyn LLMProvider>>,
+    ) -> Result<Box<dyn LLMProvider>> {
+        match self {
+            Self::Record(path) => Ok(Box::new(RecordingProvider::new(make()?, path.clone()))),
+            Self::Replay(script) => {
+                // Answer under the name and model the provider was recorded with
+                let recorded = script
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .find(|interaction| interaction.provider.eq_ignore_ascii_case(name))
+                    .map(|interaction| (interaction.provider.clone(), interaction.model.clone()));
+                let (name, model) = recorded.as_ref().map_or((name, model), |(name, model)| {
+                    (name.as_str(), model.as_str())
+                });
+                Ok(Box::new(MockProvider::replaying(
+                    script.clone(),
+                    name,
+                    model,
+                )))
+            }
+        }
+    }
+}
+
+/// Provider answering from a script instead of a model, for tests and for
+/// replaying cassettes
+pub struct MockProvider {
+    name: String,
+    model: String,
+    /// Responses to prompts containing a pattern; checked first, and reusable
+    rules: Vec<(String, String)>,
+    /// Answers to the prompts no rule matches, each used once. A recorded
+    /// prompt is answered by its own interaction, the others in order.
+    script: Arc<Mutex<VecDeque<Interaction>>>,
+    prompts: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self {
+            name: "mock".to_string(),
+            model: "mock".to_string(),
+            rules: Vec::new(),
+            script: Arc::new(Mutex::new(VecDeque::new())),
+            prompts: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Replay interactions from `script`, which other providers of the run may share
+    pub fn replaying(script: Arc<Mutex<VecDeque<Interaction>>>, name: &str, model: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            model: model.to_string(),
+            script,
+            ..Self::new()
+        }
+    }
+}
+
+/// Scripting, for tests driving the agentic loop
+#[cfg(test)]
+impl MockProvider {
+    /// Answer every prompt containing `pattern` with `response`
+    pub fn respond_to(mut self, pattern: &str, response: &str) -> Self {
+        self.rules.push((pattern.to_string(), response.to_string()));
+        self
+    }
+
+    /// Answer the next prompt no rule matches with `response`
+    pub fn then_respond(self, response: &str) -> Self {
+        self.push(response, None)
+    }
+
+    /// Fail the next prompt no rule matches with `error`
+    pub fn then_fail(self, error: &str) -> Self {
+        self.push("", Some(error.to_string()))
+    }
+
+    fn push(self, response: &str, error: Option<String>) -> Self {
+        self.script.lock().unwrap().push_back(Interaction {
+            provider: String::new(),
+            model: String::new(),
+            prompt: String::new(),
+            response: response.to_string(),
+            error,
+        });
+        self
+    }
+
+    /// The prompts received so far, oldest first, shared so they can be read
+    /// after the provider is handed to a manager
+    pub fn prompts(&self) -> Arc<Mutex<Vec<String>>> {
+        self.prompts.clone()
+    }
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl LLMProvider for MockProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn context_size(&self) -> usize {
+        128_000
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn send_prompt(&self, prompt: &str) -> Result<String> {
+        self.prompts.lock().unwrap().push(prompt.to_string());
+        if let Some((_, response)) = self
+            .rules
+            .iter()
+            .find(|(pattern, _)| prompt.contains(pattern.as_str()))
+        {
+            return Ok(response.clone());
+        }
+
+        let mut script = self.script.lock().unwrap();
+        // Steps run in parallel may ask in another order than they were recorded in,
+        // and a shared script also holds the answers of other providers
+        let position = script
+            .iter()
+            .position(|interaction| {
+                interaction.prompt == prompt && interaction.provider == self.name
+            })
+            .or_else(|| {
+                script
+                    .iter()
+                    .position(|interaction| interaction.prompt == prompt)
+            })
+            .or_else(|| {
+                script
+                    .iter()
+                    .position(|interaction| interaction.provider == self.name)
+            })
+            .unwrap_or(0);
+        let interaction = script.remove(position).ok_or_else(|| {
+            let start: String = prompt.chars().take(80).collect();
+            anyhow!("No scripted response left for prompt: {}", start)
+        })?;
+        if !interaction.prompt.is_empty() && interaction.prompt != prompt {
+            debug!("Replaying a response recorded for a different prompt");
+        }
+        match interaction.error {
+            Some(error) => Err(anyhow!(error)),
+            None => Ok(interaction.response),
+        }
+    }
+}
+
+/// Provider passing prompts on to `inner` and appending every exchange to a
+/// cassette as it happens, so a crashed run still leaves a usable cassette
+pub struct RecordingProvider {
+    inner: Box<dyn LLMProvider>,
+    path: PathBuf,
+}
+
+impl RecordingProvider {
+    pub fn new(inner: Box<dyn LLMProvider>, path: PathBuf) -> Self {
+        Self { inner, path }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for RecordingProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn context_size(&self) -> usize {
+        self.inner.context_size()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn handles_own_metrics(&self) -> bool {
+        self.inner.handles_own_metrics()
+    }
+
+    async fn send_prompt(&self, prompt: &str) -> Result<String> {
+        let result = self.inner.send_prompt(prompt).await;
+        let (response, error) = match &result {
+            Ok(response) => (response.clone(), None),
+            Err(e) => (String::new(), Some(format!("{:#}", e))),
+        };
+        let interaction = Interaction {
+            provider: self.inner.name().to_string(),
+            model: self.inner.model_name().to_string(),
+            prompt: prompt.to_string(),
+            response,
+            error,
+        };
+        if let Err(e) = Cassette::append(&self.path, &interaction) {
+            warn!("Failed to save cassette: {:#}", e);
+        }
+        result
+    }
+
+    async fn embed(&self, texts: &[String], model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+        self.inner.embed(texts, model).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cassette_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("cli_engineer_cassette_{}", uuid::Uuid::new_v4()))
+            .join("run.jsonl")
+    }
+
+    #[tokio::test]
+    async fn test_scripted_responses() {
+        let mock = MockProvider::new()
+            .respond_to("Review", "QUALITY: good")
+            .then_respond("1. Write main.rs")
+            .then_fail("rate limited");
+        let prompts = mock.prompts();
+        assert_eq!(
+            mock.send_prompt("Review this").await.unwrap(),
+            "QUALITY: good"
+        );
+        assert_eq!(mock.send_prompt("Plan").await.unwrap(), "1. Write main.rs");
+        assert!(mock.send_prompt("Retry").await.is_err());
+        assert!(mock.send_prompt("Again").await.is_err());
+        assert_eq!(prompts.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_recording_appends_each_exchange() {
+        let path = cassette_path();
+        let mode = CassetteMode::record(&path).unwrap();
+        let recorder = mode
+            .provider("mock", "mock", || {
+                Ok(Box::new(
+                    MockProvider::new()
+                        .then_respond("first")
+                        .then_fail("timeout"),
+                ))
+            })
+            .unwrap();
+        let _ = recorder.send_prompt("a").await;
+        assert_eq!(Cassette::load(&path).unwrap().interactions.len(), 1);
+        let _ = recorder.send_prompt("b").await;
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        let cassette = Cassette::load(&path).unwrap();
+        assert_eq!(cassette.interactions[0].provider, "mock");
+        assert_eq!(cassette.interactions[1].error.as_deref(), Some("timeout"));
+        // A new recording starts empty
+        CassetteMode::record(&path).unwrap();
+        assert!(Cassette::load(&path).unwrap().interactions.is_empty());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_replay_shared_by_providers() {
+        let path = cassette_path();
+        let mode = CassetteMode::record(&path).unwrap();
+        let main = mode
+            .provider("mock", "mock", || {
+                Ok(Box::new(
+                    MockProvider::new()
+                        .then_respond("first")
+                        .then_respond("second")
+                        .then_fail("timeout"),
+                ))
+            })
+            .unwrap();
+        for prompt in ["a", "b", "c"] {
+            let _ = main.send_prompt(prompt).await;
+        }
+        let judge = MockProvider::replaying(Arc::default(), "judge", "large")
+            .respond_to("judge", "verdict");
+        let judge = RecordingProvider::new(Box::new(judge), path.clone());
+        judge.send_prompt("judge this").await.unwrap();
+
+        // Recorded prompts get their own responses, whatever the order; the
+        // real providers are never built
+        let mode = CassetteMode::replay(&path).unwrap();
+        let unbuilt = || -> Result<Box<dyn LLMProvider>> { anyhow::bail!("no API key") };
+        let main = mode.provider("mock", "mock", unbuilt).unwrap();
+        let judge = mode.provider("judge", "large", unbuilt).unwrap();
+        assert_eq!(judge.model_name(), "large");
+        assert_eq!(main.send_prompt("b").await.unwrap(), "second");
+        assert_eq!(judge.send_prompt("judge that").await.unwrap(), "verdict");
+        assert_eq!(main.send_prompt("changed").await.unwrap(), "first");
+        assert_eq!(
+            main.send_prompt("c").await.unwrap_err().to_string(),
+            "timeout"
+        );
+        assert!(main.send_prompt("d").await.is_err());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod anthropic;
 pub mod ollama;
 pub mod gemini;
+pub mod mock;
 pub mod openai;
 pub mod openrouter;
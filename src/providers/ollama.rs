@@ -1,6 +1,7 @@
 use anyhow::{anyhow, Result};
 use crate::llm_manager::LLMProvider;
 use crate::event_bus::{Event, EventBus};
+use crate::throughput::StreamMeter;
 use log::{info};
 use std::sync::Arc;
 use tokio;
@@ -106,6 +107,20 @@ impl LLMProvider for OllamaProvider {
         &self.model
     }
 
+    async fn embed(&self, texts: &[String], model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+        let model = model.unwrap_or("nomic-embed-text").to_string();
+        let mut vectors = Vec::with_capacity(texts.len());
+        for text in texts {
+            let response = self
+                .client
+                .generate_embeddings(model.clone(), text.clone(), None)
+                .await
+                .map_err(|e| anyhow!("Ollama embeddings failed: {}", e))?;
+            vectors.push(response.embeddings.into_iter().map(|x| x as f32).collect());
+        }
+        Ok(vectors)
+    }
+
     async fn send_prompt(&self, prompt: &str) -> Result<String> {
         info!("Sending prompt to Ollama model '{}': {} characters", self.model, prompt.len());
         
@@ -118,6 +133,7 @@ impl LLMProvider for OllamaProvider {
         
         request = request.options(options);
         
+        let mut meter = StreamMeter::start("Ollama", self.event_bus.clone());
         let mut stream = self.client.generate_stream(request).await
             .map_err(|e| anyhow!("Failed to start Ollama stream: {}", e))?;
         
@@ -134,6 +150,7 @@ impl LLMProvider for OllamaProvider {
                 let content = &chunk_response.response;
                 
                 full_response.push_str(content);
+                meter.record(content).await;
                 
                 // Handle thinking tags (no direct printing - only send events)
                 for part in content.split("<think>") {
@@ -1,11 +1,12 @@
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
-use std::env;
+use serde_json::{json, Value};
 use std::sync::Arc;
+use std::time::Instant;
 use log::{debug, error};
 
-use crate::llm_manager::LLMProvider;
+use crate::llm_manager::{LLMProvider, ToolCall, ToolMessage, ToolResponse, ToolSpec};
 use crate::event_bus::{Event, EventBus};
 
 /// OpenAI API provider implementation
@@ -150,10 +151,8 @@ struct Usage {
 
 impl OpenAIProvider {
     /// Create a new OpenAI provider with default settings
-    pub fn new(model: Option<String>, temperature: Option<f32>) -> Result<Self> {
-        let api_key =
-            env::var("OPENAI_API_KEY").context("OPENAI_API_KEY environment variable not set")?;
-        Ok(Self {
+    pub fn new(api_key: String, model: Option<String>, temperature: Option<f32>) -> Self {
+        Self {
             api_key,
             model: model.unwrap_or_else(|| "gpt-4.1".to_string()),
             base_url: "https://api.openai.com/v1".to_string(),
@@ -161,7 +160,7 @@ impl OpenAIProvider {
             event_bus: None,
             cost_per_1m_input_tokens: 0.0,
             cost_per_1m_output_tokens: 0.0,
-        })
+        }
     }
 
     /// Create a new OpenAI provider with custom configuration
@@ -281,8 +280,45 @@ impl LLMProvider for OpenAIProvider {
         true
     }
 
+    async fn embed(&self, texts: &[String], model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+        #[derive(Deserialize)]
+        struct EmbeddingData {
+            index: usize,
+            embedding: Vec<f32>,
+        }
+        #[derive(Deserialize)]
+        struct EmbeddingResponse {
+            data: Vec<EmbeddingData>,
+        }
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/embeddings", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&serde_json::json!({
+                "model": model.unwrap_or("text-embedding-3-small"),
+                "input": texts,
+            }))
+            .send()
+            .await
+            .context("Failed to send embeddings request to OpenAI")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("OpenAI embeddings API error ({}): {}", status, body));
+        }
+
+        let mut parsed: EmbeddingResponse = response
+            .json()
+            .await
+            .context("Failed to parse OpenAI embeddings response")?;
+        parsed.data.sort_by_key(|d| d.index);
+        Ok(parsed.data.into_iter().map(|d| d.embedding).collect())
+    }
+
     async fn send_prompt(&self, prompt: &str) -> Result<String> {
         let client = reqwest::Client::new();
+        let started = Instant::now();
 
         // Check if this is a reasoning model that supports reasoning summaries
         let is_reasoning_model = Self::is_reasoning_model(&self.model);
@@ -385,18 +421,156 @@ impl LLMProvider for OpenAIProvider {
                     provider: "openai".to_string(),
                     tokens: usage.total_tokens,
                     cost: total_cost,
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    output_tokens: usage.output_tokens,
+                    first_token_ms: None,
                 }).await;
             }
         }
 
         Ok(content)
     }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    async fn send_with_tools(
+        &self,
+        messages: &[ToolMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse> {
+        let started = Instant::now();
+        let tools: Vec<Value> = tools
+            .iter()
+            .map(|tool| json!({
+                "type": "function",
+                "name": tool.name,
+                "description": tool.description,
+                "parameters": tool.parameters,
+            }))
+            .collect();
+        let request = json!({
+            "model": self.model,
+            "input": tool_input(messages),
+            "tools": tools,
+        });
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/responses", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .context("Failed to send request to OpenAI API")?;
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow!("OpenAI API error: {}", error_text));
+        }
+        let body: Value = response.json().await.context("Failed to parse OpenAI response")?;
+
+        let mut result = ToolResponse::default();
+        for item in body["output"].as_array().into_iter().flatten() {
+            match item["type"].as_str() {
+                Some("message") => {
+                    for content in item["content"].as_array().into_iter().flatten() {
+                        if let Some(text) = content["text"].as_str() {
+                            result.text.push_str(text);
+                        }
+                    }
+                }
+                Some("function_call") => {
+                    let arguments = item["arguments"].as_str().unwrap_or("{}");
+                    result.calls.push(ToolCall {
+                        id: item["call_id"].as_str().unwrap_or_default().to_string(),
+                        name: item["name"].as_str().unwrap_or_default().to_string(),
+                        // Malformed arguments reach the tool as a string, which it refuses
+                        arguments: serde_json::from_str(arguments)
+                            .unwrap_or_else(|_| Value::String(arguments.to_string())),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let input_tokens = body["usage"]["input_tokens"].as_u64().unwrap_or(0) as usize;
+        let output_tokens = body["usage"]["output_tokens"].as_u64().unwrap_or(0) as usize;
+        if let Some(event_bus) = &self.event_bus {
+            let cost = (input_tokens as f32 * self.cost_per_1m_input_tokens
+                + output_tokens as f32 * self.cost_per_1m_output_tokens)
+                / 1_000_000.0;
+            let _ = event_bus.emit(Event::APICallCompleted {
+                provider: "openai".to_string(),
+                tokens: input_tokens + output_tokens,
+                cost,
+                duration_ms: started.elapsed().as_millis() as u64,
+                output_tokens,
+                first_token_ms: None,
+            }).await;
+        }
+        Ok(result)
+    }
+}
+
+/// A conversation with tools as Responses API input items: the model's calls
+/// are `function_call` items, their results `function_call_output` items
+fn tool_input(messages: &[ToolMessage]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for message in messages {
+        match message {
+            ToolMessage::User(text) => input.push(json!({"role": "user", "content": text})),
+            ToolMessage::Assistant { text, calls } => {
+                if !text.is_empty() {
+                    input.push(json!({"role": "assistant", "content": text}));
+                }
+                for call in calls {
+                    input.push(json!({
+                        "type": "function_call",
+                        "call_id": call.id,
+                        "name": call.name,
+                        "arguments": call.arguments.to_string(),
+                    }));
+                }
+            }
+            ToolMessage::Results(results) => {
+                for (id, output) in results {
+                    input.push(json!({
+                        "type": "function_call_output",
+                        "call_id": id,
+                        "output": output,
+                    }));
+                }
+            }
+        }
+    }
+    input
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tool_input() {
+        let input = tool_input(&[
+            ToolMessage::User("Fix the bug".to_string()),
+            ToolMessage::Assistant {
+                text: "Looking first".to_string(),
+                calls: vec![ToolCall {
+                    id: "call_1".to_string(),
+                    name: "search".to_string(),
+                    arguments: json!({"pattern": "fn add"}),
+                }],
+            },
+            ToolMessage::Results(vec![("call_1".to_string(), "No matches".to_string())]),
+        ]);
+        assert_eq!(input.len(), 4);
+        assert_eq!(input[2]["type"], "function_call");
+        assert_eq!(input[2]["arguments"], r#"{"pattern":"fn add"}"#);
+        assert_eq!(input[3]["call_id"], "call_1");
+    }
+
     #[test]
     fn test_context_sizes() {
         let provider =
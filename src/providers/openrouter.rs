@@ -1,32 +1,79 @@
+use std::sync::Arc;
+use std::time::Instant;
+
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
 use log;
 use reqwest;
-use serde_json;
-use std::env;
+use serde_json::{self, json, Value};
 
-use crate::llm_manager::LLMProvider;
+use crate::event_bus::{Event, EventBus};
+use crate::llm_manager::{LLMProvider, ToolCall, ToolMessage, ToolResponse, ToolSpec};
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct OpenRouterProvider {
     pub model: String,
     pub temperature: f32,
     pub max_tokens: usize,
     api_key: String,
     client: reqwest::Client,
+    event_bus: Option<Arc<EventBus>>,
+    cost_per_1m_input_tokens: f32,
+    cost_per_1m_output_tokens: f32,
 }
 
 impl OpenRouterProvider {
-    pub fn new(model: Option<String>, temperature: Option<f32>, max_tokens: Option<usize>) -> Result<Self> {
-        let api_key = env::var("OPENROUTER_API_KEY")
-            .context("OPENROUTER_API_KEY environment variable not set")?;
-        Ok(Self {
+    pub fn new(api_key: String, model: Option<String>, temperature: Option<f32>, max_tokens: Option<usize>) -> Self {
+        Self {
             model: model.unwrap_or_else(|| "deepseek/deepseek-r1-0528-qwen3-8b".to_string()),
             temperature: temperature.unwrap_or(0.2),
             max_tokens: max_tokens.unwrap_or(8192),
             api_key,
             client: reqwest::Client::new(),
-        })
+            event_bus: None,
+            cost_per_1m_input_tokens: 0.0,
+            cost_per_1m_output_tokens: 0.0,
+        }
+    }
+
+    /// Set event bus for reporting API calls
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    /// Set cost per 1 million input tokens
+    pub fn with_cost_per_1m_input_tokens(mut self, cost: f32) -> Self {
+        self.cost_per_1m_input_tokens = cost;
+        self
+    }
+
+    /// Set cost per 1 million output tokens
+    pub fn with_cost_per_1m_output_tokens(mut self, cost: f32) -> Self {
+        self.cost_per_1m_output_tokens = cost;
+        self
+    }
+
+    /// Report a completed call with the token usage of its response
+    async fn emit_usage(&self, json: &Value, started: Instant) {
+        let Some(event_bus) = &self.event_bus else {
+            return;
+        };
+        let input_tokens = json["usage"]["prompt_tokens"].as_u64().unwrap_or(0) as usize;
+        let output_tokens = json["usage"]["completion_tokens"].as_u64().unwrap_or(0) as usize;
+        let cost = (input_tokens as f32 * self.cost_per_1m_input_tokens
+            + output_tokens as f32 * self.cost_per_1m_output_tokens)
+            / 1_000_000.0;
+        let _ = event_bus
+            .emit(Event::APICallCompleted {
+                provider: "openrouter".to_string(),
+                tokens: input_tokens + output_tokens,
+                cost,
+                duration_ms: started.elapsed().as_millis() as u64,
+                output_tokens,
+                first_token_ms: None,
+            })
+            .await;
     }
 }
 
@@ -44,6 +91,7 @@ impl LLMProvider for OpenRouterProvider {
 
     async fn send_prompt(&self, prompt: &str) -> Result<String> {
         let url = "https://openrouter.ai/api/v1/chat/completions";
+        let started = Instant::now();
         let req_body = serde_json::json!({
             "model": self.model,
             "messages": [{"role": "user", "content": prompt}],
@@ -70,6 +118,7 @@ impl LLMProvider for OpenRouterProvider {
             .json()
             .await
             .context("Failed to parse OpenRouter response")?;
+        self.emit_usage(&json, started).await;
             
         // Check if response was truncated
         if let Some(finish_reason) = json["choices"][0]["finish_reason"].as_str() {
@@ -91,4 +140,104 @@ impl LLMProvider for OpenRouterProvider {
             .ok_or_else(|| anyhow!("No content in OpenRouter response"))?;
         Ok(content.to_string())
     }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    async fn send_with_tools(
+        &self,
+        messages: &[ToolMessage],
+        tools: &[ToolSpec],
+    ) -> Result<ToolResponse> {
+        let url = "https://openrouter.ai/api/v1/chat/completions";
+        let started = Instant::now();
+        let tools: Vec<Value> = tools
+            .iter()
+            .map(|tool| json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters,
+                },
+            }))
+            .collect();
+        let req_body = json!({
+            "model": self.model,
+            "messages": tool_messages(messages),
+            "tools": tools,
+            "temperature": self.temperature,
+            "max_tokens": self.max_tokens,
+        });
+        let resp = self
+            .client
+            .post(url)
+            .bearer_auth(&self.api_key)
+            .header(
+                "HTTP-Referer",
+                "https://github.com/trilogy-group/cli_engineer",
+            )
+            .header("X-Title", "cli_engineer")
+            .json(&req_body)
+            .send()
+            .await
+            .context("Failed to send request to OpenRouter")?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("OpenRouter API error: {}", resp.status()));
+        }
+        let json: Value = resp
+            .json()
+            .await
+            .context("Failed to parse OpenRouter response")?;
+        self.emit_usage(&json, started).await;
+
+        let message = &json["choices"][0]["message"];
+        let calls = message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|call| {
+                let arguments = call["function"]["arguments"].as_str().unwrap_or("{}");
+                ToolCall {
+                    id: call["id"].as_str().unwrap_or_default().to_string(),
+                    name: call["function"]["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: serde_json::from_str(arguments)
+                        .unwrap_or_else(|_| Value::String(arguments.to_string())),
+                }
+            })
+            .collect();
+        Ok(ToolResponse {
+            text: message["content"].as_str().unwrap_or_default().to_string(),
+            calls,
+        })
+    }
+}
+
+/// A conversation with tools as chat completion messages: the model's calls
+/// are the `tool_calls` of its message, each result a `tool` message
+fn tool_messages(messages: &[ToolMessage]) -> Vec<Value> {
+    let mut chat = Vec::new();
+    for message in messages {
+        match message {
+            ToolMessage::User(text) => chat.push(json!({"role": "user", "content": text})),
+            ToolMessage::Assistant { text, calls } => {
+                let calls: Vec<Value> = calls
+                    .iter()
+                    .map(|call| json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": {"name": call.name, "arguments": call.arguments.to_string()},
+                    }))
+                    .collect();
+                chat.push(json!({"role": "assistant", "content": text, "tool_calls": calls}));
+            }
+            ToolMessage::Results(results) => {
+                for (id, output) in results {
+                    chat.push(json!({"role": "tool", "tool_call_id": id, "content": output}));
+                }
+            }
+        }
+    }
+    chat
 }
@@ -0,0 +1,172 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+
+use crate::event_bus::{Event, EventSink};
+
+/// Path of the reasoning export for `run_id` in the state directory
+pub fn reasoning_path(state_dir: &Path, run_id: &str) -> PathBuf {
+    state_dir.join(format!("reasoning_{}.md", run_id))
+}
+
+/// Where the run is, for the headings traces are filed under
+#[derive(Default)]
+struct Position {
+    iteration: usize,
+    /// Heading of the current part of the iteration, e.g. a step
+    section: String,
+    /// Headings already written, so quiet steps leave no empty sections
+    written_iteration: usize,
+    written_section: String,
+}
+
+/// Event sink appending every reasoning trace to
+/// `.cli_engineer/reasoning_<run_id>.md`, under the iteration and step it
+/// belongs to
+pub struct ReasoningLog {
+    path: PathBuf,
+    writer: Mutex<(BufWriter<File>, Position)>,
+}
+
+impl ReasoningLog {
+    pub fn create(path: &Path, run_id: &str) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("Failed to create state directory")?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open reasoning log {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(
+            writer,
+            "# Reasoning of run {}\n\nStarted {}\n",
+            run_id,
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S %:z")
+        )
+        .and_then(|_| writer.flush())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new((writer, Position::default())),
+        })
+    }
+}
+
+impl Position {
+    /// Move to the part of the run `event` starts, if any
+    fn apply(&mut self, event: &Event) {
+        match event {
+            Event::Custom { event_type, data } => match event_type.as_str() {
+                "iteration_started" => {
+                    self.iteration =
+                        data.get("iteration")
+                            .and_then(|iteration| iteration.as_u64())
+                            .unwrap_or(self.iteration as u64 + 1) as usize;
+                    self.section = "Planning".to_string();
+                }
+                "review_started" => self.section = "Review".to_string(),
+                _ => {}
+            },
+            Event::StepStarted {
+                step_id,
+                number,
+                total,
+                description,
+            } => {
+                self.section = format!("Step {}/{} `{}`: {}", number, total, step_id, description);
+            }
+            _ => {}
+        }
+    }
+
+    /// Markdown for `message`, preceded by any headings not written yet
+    fn entry(&mut self, message: &str, time: &str) -> String {
+        let mut entry = String::new();
+        if self.iteration != self.written_iteration {
+            entry.push_str(&format!("## Iteration {}\n\n", self.iteration));
+            self.written_iteration = self.iteration;
+            self.written_section.clear();
+        }
+        if !self.section.is_empty() && self.section != self.written_section {
+            entry.push_str(&format!("### {}\n\n", self.section));
+            self.written_section = self.section.clone();
+        }
+        entry.push_str(&format!("**{}** {}\n", time, message.trim()));
+        entry
+    }
+}
+
+impl EventSink for ReasoningLog {
+    fn handle(&self, event: &Event) {
+        let mut guard = self.writer.lock().unwrap();
+        let (writer, position) = &mut *guard;
+        let Event::ReasoningTrace { message } = event else {
+            position.apply(event);
+            return;
+        };
+        if message.trim().is_empty() {
+            return;
+        }
+        let time = chrono::Local::now().format("%H:%M:%S").to_string();
+        let entry = position.entry(message, &time);
+        // Flushed per trace so the export is complete up to a crash
+        if let Err(e) = writeln!(writer, "{}", entry).and_then(|_| writer.flush()) {
+            // Logging would emit another event; report on stderr instead
+            eprintln!("Failed to write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reasoning_log_sections() {
+        let dir =
+            std::env::temp_dir().join(format!("cli_engineer_reasoning_{}", uuid::Uuid::new_v4()));
+        let path = reasoning_path(&dir, "run1");
+        let log = ReasoningLog::create(&path, "run1").unwrap();
+        let trace = |message: &str| Event::ReasoningTrace {
+            message: message.to_string(),
+        };
+
+        log.handle(&Event::Custom {
+            event_type: "iteration_started".to_string(),
+            data: serde_json::json!({"iteration": 1}),
+        });
+        log.handle(&trace("Which files matter?"));
+        log.handle(&trace("   "));
+        for number in 1..=2 {
+            log.handle(&Event::StepStarted {
+                step_id: format!("step_{}", number),
+                number,
+                total: 2,
+                description: format!("Do part {}", number),
+            });
+        }
+        log.handle(&trace("Writing the second part"));
+        log.handle(&trace("Done with it"));
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# Reasoning of run run1\n"));
+        let body: Vec<&str> = content
+            .lines()
+            .filter(|line| line.starts_with('#') || line.starts_with("**"))
+            .skip(1)
+            .collect();
+        assert_eq!(body.len(), 6, "{}", content);
+        assert_eq!(body[0], "## Iteration 1");
+        assert_eq!(body[1], "### Planning");
+        assert!(body[2].ends_with("** Which files matter?"));
+        // The first step had no reasoning and gets no heading
+        assert_eq!(body[3], "### Step 2/2 `step_2`: Do part 2");
+        assert!(body[5].ends_with("** Done with it"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
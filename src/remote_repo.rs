@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use tokio::process::Command;
+
+/// Clones that exist right now, removed on interrupt
+static CLONES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Remove the clones this process made, e.g. before it exits on Ctrl-C,
+/// which would otherwise leave them in the temporary directory
+pub fn remove_clones() {
+    let clones = std::mem::take(&mut *CLONES.lock().unwrap_or_else(|e| e.into_inner()));
+    for path in clones {
+        remove(&path);
+    }
+}
+
+fn remove(path: &Path) {
+    if path.exists()
+        && let Err(e) = std::fs::remove_dir_all(path)
+    {
+        warn!("Failed to remove clone {}: {}", path.display(), e);
+    }
+}
+
+/// A repository given with `--repo URL[@branch]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoSpec {
+    pub url: String,
+    pub branch: Option<String>,
+}
+
+impl RepoSpec {
+    /// Parse `URL[@branch]`. The `@` of scp-style URLs (`git@github.com:org/repo.git`)
+    /// and of credentials (`https://user@host/repo`) is not taken as a branch separator:
+    /// a branch only follows a URL that already has a path.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            anyhow::bail!("--repo needs a repository URL");
+        }
+
+        if let Some((url, branch)) = spec.rsplit_once('@') {
+            let path_part = url.split_once("://").map_or(url, |(_, rest)| rest);
+            if path_part.contains('/') && !branch.is_empty() && !branch.contains(':') {
+                return Ok(Self {
+                    url: url.to_string(),
+                    branch: Some(branch.to_string()),
+                });
+            }
+        }
+        Ok(Self {
+            url: spec.to_string(),
+            branch: None,
+        })
+    }
+
+    /// Directory-safe name for the repository and branch, e.g. `org_repo_main`
+    pub fn slug(&self) -> String {
+        let name = self
+            .url
+            .trim_end_matches('/')
+            .trim_end_matches(".git")
+            .rsplit(['/', ':'])
+            .take(2)
+            .collect::<Vec<_>>()
+            .into_iter()
+            .rev()
+            .collect::<Vec<_>>()
+            .join("_");
+        let name = match &self.branch {
+            Some(branch) => format!("{}_{}", name, branch),
+            None => name,
+        };
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
+
+/// A shallow clone in a temporary directory, removed when dropped
+pub struct ClonedRepo {
+    pub spec: RepoSpec,
+    path: PathBuf,
+}
+
+impl ClonedRepo {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ClonedRepo {
+    fn drop(&mut self) {
+        CLONES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|clone| clone != &self.path);
+        remove(&self.path);
+    }
+}
+
+/// Shallow-clone `spec` into a new temporary directory
+pub async fn clone_repo(spec: RepoSpec) -> Result<ClonedRepo> {
+    let path = std::env::temp_dir().join(format!(
+        "cli_engineer_repo_{}_{}",
+        spec.slug(),
+        &uuid::Uuid::new_v4().to_string()[..8]
+    ));
+    info!("Cloning {} into {}", spec.url, path.display());
+    CLONES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(path.clone());
+    // From here, dropping the guard cleans up a partial clone
+    let cloned = ClonedRepo { spec, path };
+
+    let mut command = Command::new("git");
+    command.args(["clone", "--depth", "1", "--single-branch", "--quiet"]);
+    if let Some(branch) = &cloned.spec.branch {
+        command.args(["--branch", branch]);
+    }
+    // A URL starting with a dash is not taken for an option
+    let output = command
+        .arg("--")
+        .arg(&cloned.spec.url)
+        .arg(&cloned.path)
+        // Fail instead of waiting for credentials on a terminal that may be a dashboard
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .context("Failed to run git; it is required for --repo")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to clone {}: {}",
+            cloned.spec.url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(cloned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repo_spec() {
+        let spec = RepoSpec::parse("https://github.com/org/repo.git@feature/login").unwrap();
+        assert_eq!(spec.url, "https://github.com/org/repo.git");
+        assert_eq!(spec.branch.as_deref(), Some("feature/login"));
+        assert_eq!(spec.slug(), "org_repo_feature_login");
+
+        let spec = RepoSpec::parse("git@github.com:org/repo.git").unwrap();
+        assert_eq!(spec.url, "git@github.com:org/repo.git");
+        assert_eq!(spec.branch, None);
+
+        let spec = RepoSpec::parse("git@github.com:org/repo.git@v1.2").unwrap();
+        assert_eq!(spec.url, "git@github.com:org/repo.git");
+        assert_eq!(spec.branch.as_deref(), Some("v1.2"));
+
+        let spec = RepoSpec::parse("https://user@host.com/org/repo").unwrap();
+        assert_eq!(spec.branch, None);
+    }
+
+    #[tokio::test]
+    async fn test_a_url_is_never_taken_for_an_option() {
+        let marker = std::env::temp_dir().join(format!("cli_engineer_{}", uuid::Uuid::new_v4()));
+        let spec = RepoSpec {
+            url: format!("--upload-pack=touch {}", marker.display()),
+            branch: None,
+        };
+        let error = clone_repo(spec).await.err().unwrap();
+        assert!(error.to_string().contains("Failed to clone"), "{}", error);
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_clones_are_removed_on_interrupt() {
+        let origin = std::env::temp_dir().join(format!("cli_engineer_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&origin).unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(&origin)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "--quiet"]);
+        std::fs::write(origin.join("README.md"), "# Origin\n").unwrap();
+        git(&["add", "."]);
+        git(&[
+            "-c",
+            "user.name=Test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "--quiet",
+            "-m",
+            "Initial commit",
+        ]);
+
+        let spec = RepoSpec::parse(&format!("file://{}", origin.display())).unwrap();
+        let cloned = clone_repo(spec).await.unwrap();
+        assert!(cloned.path().join("README.md").is_file());
+        remove_clones();
+        assert!(!cloned.path().exists());
+        drop(cloned);
+        let _ = std::fs::remove_dir_all(&origin);
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+use crate::event_bus::EventBus;
+use crate::event_log::{EVENTS_FILE, EventRecord, read_events};
+use crate::run_record::RunRecord;
+
+/// Event log of `run_id`, or of the most recent run that has one. Runs that
+/// crashed have an event log but no summary, so this does not rely on summaries.
+pub fn event_log_path(state_dir: &Path, run_id: Option<&str>) -> Result<PathBuf> {
+    let runs_dir = RunRecord::runs_dir(state_dir);
+    let run_id = match run_id {
+        Some(id) => id.to_string(),
+        // Run IDs start with their timestamp, so the last one sorts last
+        None => fs::read_dir(&runs_dir)
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().join(EVENTS_FILE).is_file())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .max()
+            .with_context(|| format!("No event logs in {}", runs_dir.display()))?,
+    };
+    Ok(runs_dir.join(run_id).join(EVENTS_FILE))
+}
+
+/// How long to wait before replaying `next`: the recorded gap divided by
+/// `speed`. A speed of 0 replays without waiting.
+fn replay_delay(previous: &EventRecord, next: &EventRecord, speed: f64) -> Duration {
+    if speed <= 0.0 {
+        return Duration::ZERO;
+    }
+    let gap = (next.timestamp - previous.timestamp)
+        .to_std()
+        .unwrap_or_default();
+    gap.div_f64(speed)
+}
+
+/// The `replay` command: emit the recorded events of a run on `bus` in their
+/// original order and pacing, so the UI renders the run as it happened.
+/// Returns the number of events replayed.
+pub async fn run(
+    state_dir: &Path,
+    run_id: Option<&str>,
+    speed: f64,
+    bus: &EventBus,
+) -> Result<usize> {
+    if !speed.is_finite() || speed < 0.0 {
+        anyhow::bail!("--speed must be a non-negative number");
+    }
+    let records = read_events(&event_log_path(state_dir, run_id)?)?;
+    let mut previous: Option<&EventRecord> = None;
+    for record in &records {
+        match previous {
+            Some(previous) if speed > 0.0 => {
+                tokio::time::sleep(replay_delay(previous, record, speed)).await
+            }
+            // Give subscribers a chance to keep up with the broadcast channel
+            _ => tokio::task::yield_now().await,
+        }
+        bus.emit(record.event.clone()).await?;
+        previous = Some(record);
+    }
+    Ok(records.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::Event;
+
+    #[tokio::test]
+    async fn test_replay() {
+        let state_dir =
+            std::env::temp_dir().join(format!("cli_engineer_replay_{}", uuid::Uuid::new_v4()));
+        assert!(event_log_path(&state_dir, None).is_err());
+
+        let start = chrono::Utc::now();
+        let records: Vec<EventRecord> = (0..3)
+            .map(|i| EventRecord {
+                timestamp: start + chrono::Duration::seconds(i * 2),
+                event: Event::TaskProgress {
+                    task_id: "t1".to_string(),
+                    progress: i as f32,
+                    message: format!("step {}", i),
+                },
+            })
+            .collect();
+        assert_eq!(
+            replay_delay(&records[0], &records[1], 4.0),
+            Duration::from_millis(500)
+        );
+        assert_eq!(replay_delay(&records[0], &records[1], 0.0), Duration::ZERO);
+
+        let path = RunRecord::runs_dir(&state_dir)
+            .join("20250101_090000_aaaa")
+            .join(EVENTS_FILE);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let lines: Vec<String> = records
+            .iter()
+            .map(|r| serde_json::to_string(r).unwrap())
+            .collect();
+        fs::write(&path, lines.join("\n")).unwrap();
+        assert_eq!(event_log_path(&state_dir, None).unwrap(), path);
+
+        let bus = EventBus::new(10);
+        let mut receiver = bus.subscribe();
+        assert_eq!(run(&state_dir, None, 0.0, &bus).await.unwrap(), 3);
+        for i in 0..3 {
+            match receiver.recv().await.unwrap() {
+                Event::TaskProgress { message, .. } => assert_eq!(message, format!("step {}", i)),
+                _ => panic!("Wrong event type"),
+            }
+        }
+        assert!(run(&state_dir, None, -1.0, &bus).await.is_err());
+
+        fs::remove_dir_all(&state_dir).unwrap();
+    }
+}
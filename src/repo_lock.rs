@@ -0,0 +1,159 @@
+use std::fs::{self, File, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+const LOCK_FILE: &str = "run.lock";
+
+/// Who holds the lock, as written to the lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LockOwner {
+    pid: u32,
+    command: String,
+    started_at: DateTime<Utc>,
+}
+
+impl LockOwner {
+    /// The holder written to `file`, unless the operating system keeps a
+    /// locked file from being read, as Windows does
+    fn read(mut file: &File) -> Option<Self> {
+        let mut json = String::new();
+        file.read_to_string(&mut json).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    fn describe(holder: Option<&Self>) -> String {
+        match holder {
+            Some(holder) => format!(
+                "{} command, process {}, started {}",
+                holder.command,
+                holder.pid,
+                holder.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+            ),
+            None => "unknown command".to_string(),
+        }
+    }
+}
+
+/// Advisory lock on a project, so two runs don't race on its artifact
+/// manifest, caches and files. It is an operating system lock on the lock
+/// file, so it is released when dropped or when the process dies, and a run
+/// that was killed leaves no stale lock behind.
+pub struct RepoLock {
+    /// The locked file; None for a forced run that found the lock held
+    file: Option<File>,
+}
+
+impl RepoLock {
+    /// Lock the project whose state is in `state_dir` for `command`. Fails if
+    /// another run holds the lock, unless `force`.
+    pub fn acquire(state_dir: &Path, command: &str, force: bool) -> Result<Self> {
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("Failed to create {}", state_dir.display()))?;
+        let path = state_dir.join(LOCK_FILE);
+        let mut file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open the lock {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = LockOwner::read(&file);
+                let holder = LockOwner::describe(holder.as_ref());
+                if !force {
+                    anyhow::bail!(
+                        "Another run ({}) is working in this project; wait for it to finish, \
+                         or pass --force to run anyway",
+                        holder
+                    );
+                }
+                warn!("Running without the lock held by another run ({})", holder);
+                return Ok(Self { file: None });
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()));
+            }
+        }
+
+        let owner = LockOwner {
+            pid: std::process::id(),
+            command: command.to_string(),
+            started_at: Utc::now(),
+        };
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(serde_json::to_string(&owner)?.as_bytes())
+            .with_context(|| format!("Failed to write the lock {}", path.display()))?;
+        Ok(Self { file: Some(file) })
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        // The file stays: removing it would let a run that opened it before
+        // lock a file no other run can see
+        if let Some(file) = &self.file {
+            let _ = file.set_len(0);
+            let _ = file.unlock();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_dir() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("cli_engineer_lock_{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_a_held_lock_names_its_holder() {
+        let dir = state_dir();
+        let lock = RepoLock::acquire(&dir, "code", false).unwrap();
+        let error = RepoLock::acquire(&dir, "review", false).err().unwrap();
+        // Windows keeps the holder's file from being read
+        if cfg!(unix) {
+            assert!(error.to_string().contains("code command"), "{}", error);
+        }
+        assert!(error.to_string().contains("--force"), "{}", error);
+        drop(lock);
+        RepoLock::acquire(&dir, "review", false).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_the_lock_file_of_a_killed_run_is_not_a_lock() {
+        let dir = state_dir();
+        fs::create_dir_all(&dir).unwrap();
+        let killed = LockOwner {
+            pid: u32::MAX,
+            command: "docs".to_string(),
+            started_at: Utc::now(),
+        };
+        fs::write(dir.join(LOCK_FILE), serde_json::to_string(&killed).unwrap()).unwrap();
+        let _lock = RepoLock::acquire(&dir, "code", false).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_force_runs_without_taking_the_lock() {
+        let dir = state_dir();
+        let lock = RepoLock::acquire(&dir, "code", false).unwrap();
+        let forced = RepoLock::acquire(&dir, "review", true).unwrap();
+        assert!(forced.file.is_none());
+        drop(forced);
+        // The forced run leaves the holder's lock alone
+        assert!(RepoLock::acquire(&dir, "docs", false).is_err());
+        drop(lock);
+        RepoLock::acquire(&dir, "docs", false).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
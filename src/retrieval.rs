@@ -0,0 +1,271 @@
+use std::sync::Arc;
+
+use log::{info, warn};
+
+use crate::llm_manager::LLMManager;
+
+/// Lines per chunk when splitting files for the index
+const CHUNK_LINES: usize = 60;
+/// Lines shared between consecutive chunks so definitions aren't cut in half
+const CHUNK_OVERLAP: usize = 10;
+/// Dimensions of the local hashed embedding
+const LOCAL_DIMENSIONS: usize = 512;
+/// Texts sent per provider embedding request
+const EMBED_BATCH_SIZE: usize = 64;
+
+/// A contiguous range of lines from a scanned file
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// How texts and the queries against them are embedded; the same embedder is used for both
+pub(crate) enum Embedder {
+    Provider {
+        llm_manager: Arc<LLMManager>,
+        model: Option<String>,
+    },
+    Local,
+}
+
+impl Embedder {
+    /// Embed `texts` with the provider's embedding API when available, and with
+    /// the local hashed embedding otherwise
+    pub(crate) async fn choose(
+        texts: &[String],
+        llm_manager: Arc<LLMManager>,
+        model: Option<String>,
+    ) -> (Self, Vec<Vec<f32>>) {
+        let provider = Embedder::Provider { llm_manager, model };
+        match provider.embed(texts).await {
+            Ok(vectors) if vectors.len() == texts.len() => (provider, vectors),
+            Ok(_) => {
+                warn!("Provider returned an incomplete set of embeddings; using local embeddings");
+                (
+                    Embedder::Local,
+                    Embedder::Local.embed(texts).await.unwrap_or_default(),
+                )
+            }
+            Err(e) => {
+                info!(
+                    "Provider embeddings unavailable ({}); using local embeddings",
+                    e
+                );
+                (
+                    Embedder::Local,
+                    Embedder::Local.embed(texts).await.unwrap_or_default(),
+                )
+            }
+        }
+    }
+
+    /// Identifies the embedding space, so vectors from different embedders are never compared
+    pub(crate) fn name(&self) -> String {
+        match self {
+            Embedder::Provider { llm_manager, model } => format!(
+                "{}/{}",
+                llm_manager.provider().name(),
+                model.as_deref().unwrap_or("default")
+            ),
+            Embedder::Local => "local".to_string(),
+        }
+    }
+
+    pub(crate) async fn embed(&self, texts: &[String]) -> anyhow::Result<Vec<Vec<f32>>> {
+        match self {
+            Embedder::Provider { llm_manager, model } => {
+                let mut vectors = Vec::with_capacity(texts.len());
+                for batch in texts.chunks(EMBED_BATCH_SIZE) {
+                    vectors.extend(llm_manager.embed(batch, model.as_deref()).await?);
+                }
+                Ok(vectors.into_iter().map(normalize).collect())
+            }
+            Embedder::Local => Ok(texts.iter().map(|t| local_embedding(t)).collect()),
+        }
+    }
+}
+
+/// Embedding index over chunks of the scanned codebase, used to retrieve only
+/// the code relevant to each step instead of loading every file into context
+pub struct RetrievalIndex {
+    chunks: Vec<Chunk>,
+    vectors: Vec<Vec<f32>>,
+    embedder: Embedder,
+}
+
+impl RetrievalIndex {
+    /// Chunk and embed `files` (path, content). Uses the provider's embedding API
+    /// when available and falls back to a local hashed embedding otherwise.
+    pub async fn build(
+        files: &[(String, String)],
+        llm_manager: Arc<LLMManager>,
+        model: Option<String>,
+    ) -> Self {
+        let chunks: Vec<Chunk> = files
+            .iter()
+            .flat_map(|(path, content)| chunk_file(path, content))
+            .collect();
+        let texts: Vec<String> = chunks.iter().map(embedding_text).collect();
+
+        let (embedder, vectors) = Embedder::choose(&texts, llm_manager, model).await;
+
+        info!(
+            "Built retrieval index with {} chunks from {} files",
+            chunks.len(),
+            files.len()
+        );
+        Self {
+            chunks,
+            vectors,
+            embedder,
+        }
+    }
+
+    /// The `k` chunks most similar to `query`, best first
+    pub async fn retrieve(&self, query: &str, k: usize) -> Vec<&Chunk> {
+        let query_vector = match self.embedder.embed(&[query.to_string()]).await {
+            Ok(mut vectors) if !vectors.is_empty() => vectors.remove(0),
+            Ok(_) => return Vec::new(),
+            Err(e) => {
+                warn!("Failed to embed retrieval query: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut scored: Vec<(f32, usize)> = self
+            .vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (dot(&query_vector, v), i))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(_, i)| &self.chunks[i])
+            .collect()
+    }
+
+    /// Render the chunks relevant to `query` for inclusion in a prompt
+    pub async fn context_for(&self, query: &str, k: usize) -> String {
+        let chunks = self.retrieve(query, k).await;
+        if chunks.is_empty() {
+            return String::new();
+        }
+
+        let mut out = String::from("Relevant code retrieved from the codebase:\n\n");
+        for chunk in chunks {
+            let extension = chunk.path.rsplit('.').next().unwrap_or("");
+            out.push_str(&format!(
+                "File: {} (lines {}-{})\n```{}\n{}\n```\n\n",
+                chunk.path, chunk.start_line, chunk.end_line, extension, chunk.text
+            ));
+        }
+        out
+    }
+}
+
+/// Split a file into overlapping line windows
+pub fn chunk_file(path: &str, content: &str) -> Vec<Chunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = (start + CHUNK_LINES).min(lines.len());
+        chunks.push(Chunk {
+            path: path.to_string(),
+            start_line: start + 1,
+            end_line: end,
+            text: lines[start..end].join("\n"),
+        });
+        if end == lines.len() {
+            break;
+        }
+        start = end - CHUNK_OVERLAP;
+    }
+    chunks
+}
+
+/// Chunk text with its path, so file names contribute to similarity
+fn embedding_text(chunk: &Chunk) -> String {
+    format!("{}\n{}", chunk.path, chunk.text)
+}
+
+/// Deterministic bag-of-words embedding using feature hashing. Identifiers are
+/// split on camelCase and snake_case so `compress_context` matches "compress".
+pub fn local_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; LOCAL_DIMENSIONS];
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        for token in split_identifier(word) {
+            if token.len() < 2 {
+                continue;
+            }
+            let hash = fnv1a(token.as_bytes());
+            let sign = if hash & 1 == 0 { 1.0 } else { -1.0 };
+            vector[(hash >> 1) as usize % LOCAL_DIMENSIONS] += sign;
+        }
+    }
+    normalize(vector)
+}
+
+fn split_identifier(word: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in word.chars() {
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_file_overlaps() {
+        let content: String = (1..=130).map(|i| format!("line {}\n", i)).collect();
+        let chunks = chunk_file("a.rs", &content);
+        let ranges: Vec<(usize, usize)> =
+            chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(ranges, vec![(1, 60), (51, 110), (101, 130)]);
+    }
+
+    #[test]
+    fn test_local_embedding_similarity() {
+        let query = local_embedding("how is the context compressed");
+        let relevant =
+            local_embedding("fn compress_context(&self) { /* summarize old messages */ }");
+        let unrelated =
+            local_embedding("fn render_dashboard(frame: &mut Frame) { draw_cost_panel() }");
+        assert!(dot(&query, &relevant) > dot(&query, &unrelated));
+    }
+}
@@ -1,12 +1,22 @@
-use crate::context::ContextManager;
+use crate::artifact::ArtifactManager;
+use crate::context::{ContextManager, ContextSource};
+use crate::doc_links;
 use crate::event_bus::{Event, EventBus};
 use crate::executor::StepResult;
+use crate::linters;
+use crate::rubrics;
 use crate::llm_manager::LLMManager;
+use crate::lsp::LspDiagnostics;
 use crate::planner::Plan;
+use crate::sandbox::Sandbox;
 use anyhow::{Context, Result};
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::OnceCell;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReviewResult {
@@ -32,6 +42,55 @@ pub struct Issue {
     pub description: String,
     pub location: Option<String>,
     pub suggestion: Option<String>,
+    /// Mechanical fix for trivial issues, applied without a full modification step
+    #[serde(default)]
+    pub patch: Option<FixPatch>,
+}
+
+/// A search/replace edit to a single file proposed by the reviewer
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FixPatch {
+    pub file: String,
+    pub search: String,
+    pub replace: String,
+}
+
+impl FixPatch {
+    /// Apply the patch, returning None unless the search text occurs exactly once
+    pub fn apply(&self, content: &str) -> Option<String> {
+        if self.search.is_empty() || content.matches(&self.search).count() != 1 {
+            return None;
+        }
+        Some(content.replacen(&self.search, &self.replace, 1))
+    }
+}
+
+impl Issue {
+    /// File path part of the location, without any `:line` suffix
+    pub fn location_path(&self) -> Option<&str> {
+        let location = self.location.as_deref()?;
+        Some(match location.rsplit_once(':') {
+            Some((path, lines)) if parse_line_range(lines).is_some() => path,
+            _ => location,
+        })
+    }
+
+    /// Line range of the location, e.g. `(10, 25)` for `src/lib.rs:10-25`
+    pub fn location_lines(&self) -> Option<(usize, usize)> {
+        let (_, lines) = self.location.as_deref()?.rsplit_once(':')?;
+        parse_line_range(lines)
+    }
+}
+
+fn parse_line_range(lines: &str) -> Option<(usize, usize)> {
+    let (start, end) = match lines.split_once('-') {
+        Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+        None => {
+            let line = lines.trim().parse().ok()?;
+            (line, line)
+        }
+    };
+    Some((start, end))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -83,6 +142,29 @@ pub struct Reviewer {
     context_manager: Option<Arc<ContextManager>>,
     event_bus: Option<Arc<EventBus>>,
     review_prompt_template: String,
+    /// Project root to run linters against, if linting is enabled
+    lint_root: Option<PathBuf>,
+    lint_timeout: Duration,
+    /// Container the linters run in, if isolated
+    lint_sandbox: Option<Sandbox>,
+    /// Linter diagnostics, collected once per run since the workspace is not modified
+    lint_issues: OnceCell<Vec<Issue>>,
+    /// Findings from tools run before the loop (e.g. security scanners)
+    known_issues: Vec<Issue>,
+    /// Severities that prevent `ready_to_deploy`; when unset the model decides
+    block_on: Option<Vec<IssueSeverity>>,
+    /// Independent reviewer on a different provider/model
+    second_opinion: Option<Arc<LLMManager>>,
+    /// Language servers checking the files each iteration changed
+    lsp: Option<Arc<LspDiagnostics>>,
+    artifact_manager: Option<Arc<ArtifactManager>>,
+    /// Review the diff of changed artifacts (with this many context lines)
+    /// instead of the step outputs
+    diff_context: Option<usize>,
+    /// Rubric file paths keyed by command or language name
+    rubric_paths: std::collections::HashMap<String, String>,
+    /// Name of the command being run, used to select its rubric
+    command: Option<String>,
 }
 
 impl Reviewer {
@@ -91,6 +173,18 @@ impl Reviewer {
             context_manager: None,
             event_bus: None,
             review_prompt_template: Self::default_review_prompt(),
+            lint_root: None,
+            lint_timeout: Duration::from_secs(300),
+            lint_sandbox: None,
+            lint_issues: OnceCell::new(),
+            known_issues: Vec::new(),
+            block_on: None,
+            second_opinion: None,
+            lsp: None,
+            artifact_manager: None,
+            diff_context: None,
+            rubric_paths: std::collections::HashMap::new(),
+            command: None,
         }
     }
 
@@ -105,7 +199,231 @@ impl Reviewer {
         self
     }
 
+    /// Run project linters against `root`, in `sandbox` if given, and merge
+    /// their diagnostics into reviews
+    pub fn with_linters(
+        mut self,
+        root: PathBuf,
+        timeout: Duration,
+        sandbox: Option<Sandbox>,
+    ) -> Self {
+        self.lint_root = Some(root);
+        self.lint_timeout = timeout;
+        self.lint_sandbox = sandbox;
+        self
+    }
+
+    /// Merge tool findings gathered outside the reviewer into every review
+    pub fn with_known_issues(mut self, issues: Vec<Issue>) -> Self {
+        self.known_issues = issues;
+        self
+    }
+
+    /// Decide `ready_to_deploy` from issue severities instead of the model's verdict.
+    /// Unknown severity names are ignored.
+    pub fn with_block_on(mut self, severities: &[String]) -> Self {
+        let block_on = severities
+            .iter()
+            .filter_map(|name| match name.to_lowercase().as_str() {
+                "critical" => Some(IssueSeverity::Critical),
+                "major" => Some(IssueSeverity::Major),
+                "minor" => Some(IssueSeverity::Minor),
+                "info" => Some(IssueSeverity::Info),
+                other => {
+                    warn!("Ignoring unknown severity '{}' in review.block_on", other);
+                    None
+                }
+            })
+            .collect();
+        self.block_on = Some(block_on);
+        self
+    }
+
+    /// Check links in generated markdown artifacts against the artifacts and workspace
+    pub fn with_artifact_manager(mut self, manager: Arc<ArtifactManager>) -> Self {
+        self.artifact_manager = Some(manager);
+        self
+    }
+
+    /// Review only what changed in this run: unified diffs of the artifacts the
+    /// steps produced against their content before the run, with
+    /// `context_lines` of surrounding context
+    pub fn with_diff_only(mut self, context_lines: usize) -> Self {
+        self.diff_context = Some(context_lines);
+        self
+    }
+
+    /// Append rubric files, keyed by command or language name, to the review prompt
+    pub fn with_rubrics(mut self, rubric_paths: std::collections::HashMap<String, String>) -> Self {
+        self.rubric_paths = rubric_paths;
+        self
+    }
+
+    pub fn with_command(mut self, command: &str) -> Self {
+        self.command = Some(command.to_string());
+        self
+    }
+
+    /// Rubrics for this review, chosen by command and the languages of the produced files
+    async fn select_rubrics(&self, plan: &Plan, results: &[StepResult]) -> Vec<(String, String)> {
+        let mut files = Vec::new();
+        if let Some(artifact_mgr) = &self.artifact_manager {
+            for id in results.iter().flat_map(|r| &r.artifacts_created) {
+                if let Some(artifact) = artifact_mgr.get_artifact(id).await {
+                    files.push(artifact.path.to_string_lossy().to_string());
+                }
+            }
+        }
+        let root = std::env::current_dir().unwrap_or_default();
+        let languages = rubrics::detect_languages(files.iter().map(|f| f.as_str()), &root);
+
+        // Without an explicit command, documentation tasks are recognized from the goal
+        let goal = plan.goal.to_lowercase();
+        let command = self.command.clone().or_else(|| {
+            (goal.contains("documentation") || goal.contains("docs")).then(|| "docs".to_string())
+        });
+
+        rubrics::select_rubrics(&self.rubric_paths, command.as_deref(), &languages, &root)
+    }
+
+    /// Have a second model review the same results and merge both opinions
+    pub fn with_second_opinion(mut self, llm_manager: Arc<LLMManager>) -> Self {
+        self.second_opinion = Some(llm_manager);
+        self
+    }
+
+    /// Add language server diagnostics on the changed files to reviews
+    pub fn with_lsp(mut self, lsp: Arc<LspDiagnostics>) -> Self {
+        self.lsp = Some(lsp);
+        self
+    }
+
+    /// Language server diagnostics for the artifacts created by `results`
+    async fn lsp_issues(&self, results: &[StepResult]) -> Vec<Issue> {
+        let (Some(lsp), Some(artifact_mgr)) = (&self.lsp, &self.artifact_manager) else {
+            return Vec::new();
+        };
+
+        // Artifacts are checked as the project files they would replace
+        let mut seen = std::collections::HashSet::new();
+        let mut files = Vec::new();
+        for id in results.iter().flat_map(|r| &r.artifacts_created) {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(artifact) = artifact_mgr.get_artifact(id).await else {
+                continue;
+            };
+            let content = match &artifact.content {
+                Some(content) => content.clone(),
+                None => match tokio::fs::read_to_string(&artifact.path).await {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                },
+            };
+            let relative = artifact
+                .path
+                .strip_prefix(artifact_mgr.artifact_dir())
+                .unwrap_or(&artifact.path);
+            files.push((relative.to_string_lossy().to_string(), content));
+        }
+        if files.is_empty() {
+            return Vec::new();
+        }
+        lsp.issues(&files).await
+    }
+
+    /// Linter diagnostics for the project, running the linters on first use
+    async fn lint_issues(&self) -> &[Issue] {
+        let Some(root) = &self.lint_root else {
+            return &[];
+        };
+        self.lint_issues
+            .get_or_init(|| {
+                linters::run_linters(root, self.lint_timeout, self.lint_sandbox.as_ref())
+            })
+            .await
+    }
+
+    /// Deterministically check relative links in all markdown artifacts
+    async fn broken_link_issues(&self) -> Vec<Issue> {
+        let Some(artifact_mgr) = &self.artifact_manager else {
+            return Vec::new();
+        };
+        let workspace_root = std::env::current_dir().unwrap_or_default();
+
+        let mut issues = Vec::new();
+        for artifact in artifact_mgr.list_artifacts().await {
+            let is_markdown = artifact
+                .path
+                .extension()
+                .is_some_and(|ext| ext == "md" || ext == "markdown");
+            if !is_markdown {
+                continue;
+            }
+            let content = match &artifact.content {
+                Some(content) => content.clone(),
+                None => match tokio::fs::read_to_string(&artifact.path).await {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                },
+            };
+            issues.extend(doc_links::check_markdown_links(
+                &artifact.path,
+                &content,
+                artifact_mgr.artifact_dir(),
+                &workspace_root,
+            ));
+        }
+        issues
+    }
+
+    /// Unified diffs of the artifacts created by `results` against their baseline
+    async fn artifact_diffs(&self, results: &[StepResult], context_lines: usize) -> String {
+        let Some(artifact_mgr) = &self.artifact_manager else {
+            return String::new();
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        let mut diffs = String::new();
+        for id in results.iter().flat_map(|r| &r.artifacts_created) {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(artifact) = artifact_mgr.get_artifact(id).await else {
+                continue;
+            };
+            let current = match &artifact.content {
+                Some(content) => content.clone(),
+                None => tokio::fs::read_to_string(&artifact.path)
+                    .await
+                    .unwrap_or_default(),
+            };
+            let baseline = artifact_mgr.baseline_content(&artifact).await;
+            if baseline == current {
+                continue;
+            }
+
+            let name = artifact
+                .path
+                .strip_prefix(artifact_mgr.artifact_dir())
+                .unwrap_or(&artifact.path)
+                .to_string_lossy()
+                .to_string();
+            let diff = similar::TextDiff::from_lines(&baseline, &current);
+            diffs.push_str(
+                &diff
+                    .unified_diff()
+                    .context_radius(context_lines)
+                    .header(&format!("a/{}", name), &format!("b/{}", name))
+                    .to_string(),
+            );
+        }
+        diffs
+    }
+
     /// Review the execution results for correctness and quality
+    #[tracing::instrument(name = "review", skip_all, fields(review.steps = results.len()))]
     pub async fn review(
         &self,
         plan: &Plan,
@@ -126,13 +444,89 @@ impl Reviewer {
                 .await;
         }
 
-        // Build review prompt
-        let prompt = self.build_review_prompt(plan, results);
+        // Build review prompt, grounded in real linter diagnostics when available
+        let tool_issues: Vec<Issue> = self
+            .lint_issues()
+            .await
+            .iter()
+            .chain(&self.known_issues)
+            .cloned()
+            .collect();
+        let rubrics = self.select_rubrics(plan, results).await;
+        let mut prompt = self.build_review_prompt(plan, results, &rubrics);
+        if let Some(context_lines) = self.diff_context {
+            let diffs = self.artifact_diffs(results, context_lines).await;
+            if diffs.is_empty() {
+                prompt.push_str(
+                    "\n\nThe files written in this iteration are unchanged since the run started.\n",
+                );
+            } else {
+                prompt.push_str(&format!(
+                    "\n\nChanges since the run started to the files written in this iteration \
+                     (unified diff, line numbers in hunk headers refer to the new file):\n```diff\n{}```\n",
+                    diffs
+                ));
+            }
+        }
+        if !tool_issues.is_empty() {
+            prompt.push_str("\n\nDiagnostics reported by the project's linters and scanners:\n");
+            for issue in &tool_issues {
+                prompt.push_str(&format!(
+                    "- [{}] {} ({})\n",
+                    issue.severity,
+                    issue.description,
+                    issue.location.as_deref().unwrap_or("unknown location")
+                ));
+            }
+            prompt.push_str(
+                "These diagnostics are already recorded; do not repeat them as issues, \
+                 but take them into account in your assessment.\n",
+            );
+        }
+
+        let lsp_issues = self.lsp_issues(results).await;
+        if !lsp_issues.is_empty() {
+            prompt.push_str(
+                "\n\nDiagnostics reported by the language server for the files changed in this \
+                 iteration:\n",
+            );
+            for issue in &lsp_issues {
+                prompt.push_str(&format!(
+                    "- [{}] {} ({})\n",
+                    issue.severity,
+                    issue.description,
+                    issue.location.as_deref().unwrap_or_default()
+                ));
+            }
+            prompt.push_str(
+                "These diagnostics are already recorded as issues; do not repeat them.\n",
+            );
+        }
+
+        let link_issues = self.broken_link_issues().await;
+        if !link_issues.is_empty() {
+            prompt.push_str("\n\nBroken links found in the generated documentation:\n");
+            for issue in &link_issues {
+                prompt.push_str(&format!(
+                    "- {} ({})\n",
+                    issue.description,
+                    issue.location.as_deref().unwrap_or_default()
+                ));
+            }
+            prompt.push_str(
+                "These broken links are already recorded as issues; do not repeat them.\n",
+            );
+        }
 
         // Add to context if available
         if let Some(ctx_mgr) = &self.context_manager {
             ctx_mgr
-                .add_message(context_id, "user".to_string(), prompt.clone())
+                .add_message_from(
+                    context_id,
+                    "user".to_string(),
+                    prompt.clone(),
+                    ContextSource::PlanReview,
+                )
                 .await?;
         }
 
@@ -145,15 +539,84 @@ impl Reviewer {
         // Add response to context
         if let Some(ctx_mgr) = &self.context_manager {
             ctx_mgr
-                .add_message(context_id, "assistant".to_string(), response.clone())
+                .add_message_from(
+                    context_id,
+                    "assistant".to_string(),
+                    response.clone(),
+                    ContextSource::PlanReview,
+                )
                 .await?;
         }
 
         // Parse review response
-        let review_result = self
+        let mut review_result = self
             .parse_review_response(&response, results)
             .context("Failed to parse review response")?;
 
+        if let Some(second) = &self.second_opinion {
+            let second_result = match second.send_prompt(&prompt).await {
+                Ok(second_response) => self
+                    .parse_second_opinion(&second_response, results)
+                    .context("Failed to parse the second-opinion review"),
+                Err(e) => Err(e),
+            };
+            match second_result {
+                Ok(second_result) => {
+                    let stats = self.merge_second_opinion(&mut review_result, second_result);
+                    review_result.summary.push_str(&format!(
+                        " Second opinion ({}): {} agreed, {} primary only, {} second opinion only.",
+                        second.provider().model_name(),
+                        stats.agreed,
+                        stats.primary_only,
+                        stats.second_only
+                    ));
+
+                    if let Some(bus) = &self.event_bus {
+                        let _ = bus
+                            .emit(Event::Custom {
+                                event_type: "second_opinion_review".to_string(),
+                                data: serde_json::json!({
+                                    "model": second.provider().model_name(),
+                                    "agreed": stats.agreed,
+                                    "primary_only": stats.primary_only,
+                                    "second_only": stats.second_only,
+                                    "ready_to_deploy": review_result.ready_to_deploy,
+                                }),
+                            })
+                            .await;
+                    }
+                }
+                Err(e) => warn!(
+                    "Second-opinion review failed, using primary review only: {:#}",
+                    e
+                ),
+            }
+        }
+
+        // Language server errors mean the generated code does not build; warnings
+        // only block under a severity policy
+        if !lsp_issues.is_empty() {
+            let has_errors = lsp_issues
+                .iter()
+                .any(|issue| issue.severity == IssueSeverity::Critical);
+            review_result.issues.extend(lsp_issues);
+            review_result.ready_to_deploy = self.apply_block_on(
+                &review_result.issues,
+                review_result.ready_to_deploy && !has_errors,
+            );
+        }
+
+        // Broken links are facts about the generated artifacts and count toward the verdict
+        if !link_issues.is_empty() {
+            review_result.issues.extend(link_issues);
+            review_result.ready_to_deploy =
+                self.apply_block_on(&review_result.issues, false);
+        }
+
+        // Tool findings describe the existing workspace rather than generated
+        // artifacts, so they are reported without affecting ready_to_deploy
+        review_result.issues.extend(tool_issues);
+
         // Emit review completed event
         if let Some(bus) = &self.event_bus {
             let _ = bus
@@ -171,14 +634,15 @@ impl Reviewer {
         Ok(review_result)
     }
 
-    fn build_review_prompt(&self, plan: &Plan, results: &[StepResult]) -> String {
+    fn build_review_prompt(
+        &self,
+        plan: &Plan,
+        results: &[StepResult],
+        rubrics: &[(String, String)],
+    ) -> String {
         let mut outputs_summary = String::new();
 
-        // Check if this is a documentation task
-        let is_documentation_task = plan.goal.to_lowercase().contains("documentation") || 
-                                   plan.goal.to_lowercase().contains("docs");
-
-        // Collect all created artifacts for documentation-specific checks
+        // Collect all created artifacts for rubric checks
         let mut all_artifacts = Vec::new();
 
         for (i, result) in results.iter().enumerate() {
@@ -203,7 +667,8 @@ impl Reviewer {
 
             if let Some(error) = &result.error {
                 outputs_summary.push_str(&format!("Error: {}\n", error));
-            } else {
+            } else if self.diff_context.is_none() {
+                // In diff mode the changed code is reviewed from the diffs instead
                 // Truncate very long outputs
                 let output = if result.output.len() > 1000 {
                     format!("{}... (truncated)", &result.output[..1000])
@@ -223,37 +688,20 @@ impl Reviewer {
             outputs_summary
         );
 
-        // Add documentation-specific review criteria if applicable
-        if is_documentation_task {
-            prompt.push_str("\n### DOCUMENTATION-SPECIFIC REVIEW CRITERIA ###\n");
-            prompt.push_str("Please pay special attention to these documentation-specific issues:\n\n");
-            
-            prompt.push_str("1. **File Organization**:\n");
-            prompt.push_str("   - Check that ALL documentation files are in the artifacts/docs/ directory\n");
-            prompt.push_str("   - Flag any files created outside of artifacts/docs/ as CRITICAL issues\n");
-            prompt.push_str("   - Non-documentation files (code, configs, etc.) should NOT be in artifacts\n\n");
-            
-            prompt.push_str("2. **File Completeness**:\n");
-            prompt.push_str("   - Check each file for incomplete content (e.g., sections that say 'TODO' or appear cut off)\n");
-            prompt.push_str("   - Check for files that end abruptly without proper conclusion\n");
-            prompt.push_str("   - Look for files with fewer than 30 lines that seem to be just stubs or introductions\n");
-            prompt.push_str("   - Check for headings without content (e.g., '## Section' followed by nothing)\n");
-            prompt.push_str("   - Check for incomplete sentences or paragraphs that seem cut off mid-thought\n");
-            prompt.push_str("   - Flag incomplete files as MAJOR issues\n\n");
-            
-            prompt.push_str("3. **Link Integrity**:\n");
-            prompt.push_str("   - Check all internal links (e.g., [text](filename.md)) reference files that actually exist\n");
-            prompt.push_str("   - Flag broken links as MAJOR issues and list the missing files\n");
-            prompt.push_str("   - Suggest creating the missing files in the next iteration\n\n");
-            
-            prompt.push_str("4. **Content Quality**:\n");
-            prompt.push_str("   - Ensure documentation is specific to the actual codebase, not generic\n");
-            prompt.push_str("   - Check that API documentation matches actual code structure\n\n");
-            
-            if !all_artifacts.is_empty() {
-                prompt.push_str(&format!("\nFiles created in this execution:\n{}\n", 
-                    all_artifacts.join("\n")));
-            }
+        // Add the command and language rubrics
+        for (name, rubric) in rubrics {
+            prompt.push_str(&format!(
+                "\n### {} REVIEW CRITERIA ###\n",
+                name.to_uppercase()
+            ));
+            prompt.push_str(rubric.trim_end());
+            prompt.push('\n');
+        }
+        if !rubrics.is_empty() && !all_artifacts.is_empty() {
+            prompt.push_str(&format!(
+                "\nFiles created in this execution:\n{}\n",
+                all_artifacts.join("\n")
+            ));
         }
 
         prompt.push_str("\nProvide a comprehensive review following the format specified above.");
@@ -272,11 +720,11 @@ impl Reviewer {
         let mut issues = Vec::new();
 
         // Parse structured response
-        let lines: Vec<&str> = response.lines().collect();
+        let mut lines = response.lines();
         let mut in_issues_section = false;
 
-        for line in lines {
-            let line = line.trim();
+        while let Some(raw_line) = lines.next() {
+            let line = raw_line.trim();
 
             if line.starts_with("QUALITY:") {
                 let quality_str = line.replace("QUALITY:", "").trim().to_lowercase();
@@ -298,9 +746,20 @@ impl Reviewer {
                 if let Some(issue) = self.parse_issue_line(line) {
                     issues.push(issue);
                 }
+            } else if let Some(file) = line.strip_prefix("PATCH:") {
+                // Patches are only accepted for trivial issues
+                let patch = parse_patch_block(file.trim(), &mut lines);
+                if let Some(issue) = issues.last_mut()
+                    && matches!(issue.severity, IssueSeverity::Minor | IssueSeverity::Info)
+                {
+                    issue.patch = patch;
+                }
             }
         }
 
+        // Apply the severity gating policy, overriding the model's verdict
+        ready_to_deploy = self.apply_block_on(&issues, ready_to_deploy);
+
         // Fallback summary if not found
         if summary.is_empty() {
             let issue_count = issues.len();
@@ -311,7 +770,7 @@ impl Reviewer {
             
             // Auto-determine ready_to_deploy if not explicitly set by LLM
             // Ready to deploy if quality is good/excellent AND no critical issues
-            if !ready_to_deploy {
+            if !ready_to_deploy && self.block_on.is_none() {
                 ready_to_deploy = matches!(overall_quality, QualityLevel::Good | QualityLevel::Excellent) 
                     && critical_count == 0;
             }
@@ -338,6 +797,77 @@ impl Reviewer {
         })
     }
 
+    /// Decide `ready_to_deploy` from the severity policy, if one is configured
+    fn apply_block_on(&self, issues: &[Issue], verdict: bool) -> bool {
+        let Some(block_on) = &self.block_on else {
+            return verdict;
+        };
+        let blocking = issues
+            .iter()
+            .filter(|i| block_on.contains(&i.severity))
+            .count();
+        let gated = blocking == 0;
+        if gated != verdict {
+            info!(
+                "Severity policy overrides reviewer verdict: ready_to_deploy = {} ({} blocking issues)",
+                gated, blocking
+            );
+        }
+        gated
+    }
+
+    /// Merge a second reviewer's result into the primary one. Issues both reviewers
+    /// found are kept as-is; issues only one found are marked and downgraded one
+    /// severity level, except critical issues which are never waved through.
+    /// Parse the second reviewer's response, which only counts if it gives a
+    /// verdict: without one it would be read as not ready to deploy
+    fn parse_second_opinion(&self, response: &str, results: &[StepResult]) -> Result<ReviewResult> {
+        if !response
+            .lines()
+            .any(|line| line.trim().starts_with("READY_TO_DEPLOY:"))
+        {
+            anyhow::bail!(
+                "no READY_TO_DEPLOY verdict in the response starting {:?}",
+                response.trim().chars().take(80).collect::<String>()
+            );
+        }
+        self.parse_review_response(response, results)
+    }
+
+    fn merge_second_opinion(&self, primary: &mut ReviewResult, second: ReviewResult) -> SecondOpinionStats {
+        let mut stats = SecondOpinionStats::default();
+        let mut unmatched_second: Vec<Issue> = second.issues;
+        let mut merged = Vec::new();
+
+        for mut issue in primary.issues.drain(..) {
+            if let Some(pos) = unmatched_second.iter().position(|other| same_issue(&issue, other)) {
+                let other = unmatched_second.remove(pos);
+                // Keep the more severe rating when both reviewers agree on the issue
+                if severity_rank(&other.severity) > severity_rank(&issue.severity) {
+                    issue.severity = other.severity;
+                }
+                stats.agreed += 1;
+            } else {
+                issue.description = format!("[primary reviewer only] {}", issue.description);
+                issue.severity = downgrade(issue.severity);
+                stats.primary_only += 1;
+            }
+            merged.push(issue);
+        }
+
+        for mut issue in unmatched_second {
+            issue.description = format!("[second opinion only] {}", issue.description);
+            issue.severity = downgrade(issue.severity);
+            stats.second_only += 1;
+            merged.push(issue);
+        }
+
+        primary.issues = merged;
+        let verdict = primary.ready_to_deploy && second.ready_to_deploy;
+        primary.ready_to_deploy = self.apply_block_on(&primary.issues, verdict);
+        stats
+    }
+
     fn parse_issue_line(&self, line: &str) -> Option<Issue> {
         // Remove the leading "- SEVERITY: " part
         let content = line.strip_prefix("- SEVERITY:")?.trim();
@@ -345,7 +875,7 @@ impl Reviewer {
         // Split by "|" to get parts
         let parts: Vec<&str> = content.split("|").collect();
 
-        if parts.len() < 4 {
+        if parts.len() < 3 {
             return None;
         }
 
@@ -359,12 +889,15 @@ impl Reviewer {
             _ => return None,
         };
 
-        // Extract category from "CATEGORY: xxx" format
-        let category_part = parts[1].trim();
-        let category_str = category_part
-            .strip_prefix("CATEGORY:")?
-            .trim()
-            .to_lowercase();
+        // Remaining fields are "KEY: value"; LOCATION is optional
+        let field = |key: &str| {
+            parts[1..]
+                .iter()
+                .find_map(|p| p.trim().strip_prefix(key))
+                .map(|v| v.trim().to_string())
+        };
+
+        let category_str = field("CATEGORY:")?.to_lowercase();
         let category = match category_str.as_str() {
             "logic" => IssueCategory::Logic,
             "performance" => IssueCategory::Performance,
@@ -377,26 +910,20 @@ impl Reviewer {
             _ => return None,
         };
 
-        // Extract description
-        let desc_part = parts[2].trim();
-        let description = desc_part.strip_prefix("DESCRIPTION:")?.trim().to_string();
-
-        // Extract suggestion
-        let suggestion = if parts.len() > 3 {
-            let sug_part = parts[3].trim();
-            sug_part
-                .strip_prefix("SUGGESTION:")
-                .map(|s| s.trim().to_string())
-        } else {
-            None
-        };
+        let description = field("DESCRIPTION:")?;
+        let location = field("LOCATION:").filter(|l| {
+            let l = l.to_lowercase();
+            !l.is_empty() && l != "n/a" && l != "none"
+        });
+        let suggestion = field("SUGGESTION:");
 
         Some(Issue {
             severity,
             category,
             description,
-            location: None,
+            location,
             suggestion,
+            patch: None,
         })
     }
 
@@ -411,7 +938,7 @@ For each ACTUAL issue found, specify:
 - Severity: Critical (blocks functionality), Major (significant problem), Minor (small issue), Suggestion (improvement)
 - Category: Logic, Security, Performance, CodeStyle, BestPractices, Documentation, Testing
 - Description: Specific description of the actual issue
-- Location: Where the issue is (if applicable)
+- Location: File path and line or line range, e.g. src/main.rs:42 or src/lib.rs:10-25 (N/A if not tied to a file)
 - Suggestion: How to fix it
 
 Format your response as:
@@ -422,14 +949,268 @@ SUMMARY: [One line summary]
 ISSUES:
 [If no issues exist, write "No issues found"]
 [Otherwise list each issue as:]
-- SEVERITY: [severity] | CATEGORY: [category] | DESCRIPTION: [description] | SUGGESTION: [suggestion]
+- SEVERITY: [severity] | CATEGORY: [category] | LOCATION: [path:line] | DESCRIPTION: [description] | SUGGESTION: [suggestion]
+
+For Minor or Suggestion issues with a small, exact fix, you may add a patch directly
+below the issue line. SEARCH must match the file content exactly and occur only once:
+  PATCH: [path]
+  <<<<<<< SEARCH
+  [exact existing lines]
+  =======
+  [replacement lines]
+  >>>>>>> REPLACE
 
 Be honest and accurate. For simple scripts like "Hello World", there are usually NO actual issues."#.to_string()
     }
 }
 
+/// Read a SEARCH/REPLACE block following a `PATCH:` line
+fn parse_patch_block<'a>(file: &str, lines: &mut impl Iterator<Item = &'a str>) -> Option<FixPatch> {
+    if file.is_empty() || !lines.next()?.trim().starts_with("<<<<<<< SEARCH") {
+        return None;
+    }
+
+    let mut search = Vec::new();
+    let mut replace = Vec::new();
+    let mut in_replace = false;
+    for line in lines {
+        match line.trim() {
+            "=======" if !in_replace => in_replace = true,
+            marker if marker.starts_with(">>>>>>> REPLACE") => {
+                return Some(FixPatch {
+                    file: file.to_string(),
+                    search: search.join("\n"),
+                    replace: replace.join("\n"),
+                });
+            }
+            _ if in_replace => replace.push(line),
+            _ => search.push(line),
+        }
+    }
+    None
+}
+
+#[derive(Debug, Default)]
+struct SecondOpinionStats {
+    agreed: usize,
+    primary_only: usize,
+    second_only: usize,
+}
+
+fn severity_rank(severity: &IssueSeverity) -> u8 {
+    match severity {
+        IssueSeverity::Critical => 3,
+        IssueSeverity::Major => 2,
+        IssueSeverity::Minor => 1,
+        IssueSeverity::Info => 0,
+    }
+}
+
+fn downgrade(severity: IssueSeverity) -> IssueSeverity {
+    match severity {
+        IssueSeverity::Critical => IssueSeverity::Critical,
+        IssueSeverity::Major => IssueSeverity::Minor,
+        IssueSeverity::Minor | IssueSeverity::Info => IssueSeverity::Info,
+    }
+}
+
+/// Heuristically decide whether two reviewers reported the same issue: the same
+/// location, or descriptions sharing most of their significant words
+fn same_issue(a: &Issue, b: &Issue) -> bool {
+    if let (Some(la), Some(lb)) = (&a.location, &b.location)
+        && !la.is_empty()
+        && la == lb
+    {
+        return true;
+    }
+
+    let words = |text: &str| -> std::collections::HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| w.len() > 3)
+            .map(|w| w.to_lowercase())
+            .collect()
+    };
+    let (wa, wb) = (words(&a.description), words(&b.description));
+    if wa.is_empty() || wb.is_empty() {
+        return false;
+    }
+    let shared = wa.intersection(&wb).count() as f32;
+    let total = wa.union(&wb).count() as f32;
+    shared / total >= 0.4
+}
+
 impl Default for Reviewer {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_on_policy_overrides_verdict() {
+        let response = "QUALITY: Good\nREADY_TO_DEPLOY: Yes\nSUMMARY: Looks fine\nISSUES:\n\
+                        - SEVERITY: Major | CATEGORY: Logic | DESCRIPTION: Off-by-one | SUGGESTION: Fix bound";
+        let reviewer = Reviewer::new().with_block_on(&["critical".to_string(), "major".to_string()]);
+        assert!(!reviewer.parse_review_response(response, &[]).unwrap().ready_to_deploy);
+
+        let response = "QUALITY: Fair\nREADY_TO_DEPLOY: No\nSUMMARY: Nits\nISSUES:\n\
+                        - SEVERITY: Minor | CATEGORY: CodeStyle | DESCRIPTION: Naming | SUGGESTION: Rename";
+        assert!(reviewer.parse_review_response(response, &[]).unwrap().ready_to_deploy);
+    }
+
+    #[test]
+    fn test_merge_second_opinion() {
+        let reviewer = Reviewer::new().with_block_on(&["critical".to_string(), "major".to_string()]);
+        let mut primary = reviewer
+            .parse_review_response(
+                "READY_TO_DEPLOY: No\nISSUES:\n\
+                 - SEVERITY: Major | CATEGORY: Logic | DESCRIPTION: Loop bound skips the last element | SUGGESTION: Use <=\n\
+                 - SEVERITY: Major | CATEGORY: CodeStyle | DESCRIPTION: Inconsistent naming | SUGGESTION: Rename",
+                &[],
+            )
+            .unwrap();
+        let second = reviewer
+            .parse_review_response(
+                "READY_TO_DEPLOY: No\nISSUES:\n\
+                 - SEVERITY: Critical | CATEGORY: Logic | DESCRIPTION: The loop bound skips the last element | SUGGESTION: Fix",
+                &[],
+            )
+            .unwrap();
+
+        let stats = reviewer.merge_second_opinion(&mut primary, second);
+        assert_eq!((stats.agreed, stats.primary_only, stats.second_only), (1, 1, 0));
+        assert_eq!(primary.issues[0].severity, IssueSeverity::Critical);
+        assert_eq!(primary.issues[1].severity, IssueSeverity::Minor);
+        assert!(primary.issues[1].description.starts_with("[primary reviewer only]"));
+        assert!(!primary.ready_to_deploy);
+    }
+
+    #[tokio::test]
+    async fn test_unparseable_second_opinion_falls_back_to_the_primary_review() {
+        use crate::providers::mock::MockProvider;
+
+        let manager = |response: &str| {
+            Arc::new(LLMManager::new(
+                vec![Box::new(MockProvider::new().respond_to("", response))],
+                Arc::new(EventBus::new(10)),
+                Arc::new(crate::config::Config::default()),
+            ))
+        };
+        let primary = manager("QUALITY: Good\nREADY_TO_DEPLOY: Yes\nSUMMARY: Fine\nISSUES:\n");
+        let reviewer = Reviewer::new().with_second_opinion(manager("I'd rather not say."));
+        let plan = Plan {
+            goal: "Add a parser".to_string(),
+            steps: Vec::new(),
+            dependencies: std::collections::HashMap::new(),
+            estimated_complexity: crate::planner::ComplexityLevel::Simple,
+        };
+        let review = reviewer
+            .review(&plan, &[], &primary, "review")
+            .await
+            .unwrap();
+        assert!(review.ready_to_deploy);
+        assert!(!review.summary.contains("Second opinion"));
+
+        let unparseable = reviewer.parse_second_opinion("Looks fine to me", &[]);
+        assert!(unparseable.is_err());
+        let verdict = reviewer.parse_second_opinion("READY_TO_DEPLOY: No", &[]);
+        assert!(verdict.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_diff_only_review_shows_the_diff_instead_of_the_output() {
+        use crate::artifact::ArtifactType;
+        use crate::providers::mock::MockProvider;
+
+        let dir = std::env::temp_dir().join(format!("cli_engineer_review_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("parser.rs"), "fn a() {}\nfn keep() {}\n").unwrap();
+        let artifacts = Arc::new(ArtifactManager::new(dir.clone()).unwrap());
+        let artifact = artifacts
+            .create_artifact(
+                "parser.rs".to_string(),
+                ArtifactType::SourceCode,
+                "fn b() {}\nfn keep() {}\n".to_string(),
+                std::collections::HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        let mock = MockProvider::new().then_respond("QUALITY: Good\nREADY_TO_DEPLOY: Yes\nSUMMARY: Fine");
+        let prompts = mock.prompts();
+        let llm = LLMManager::new(
+            vec![Box::new(mock)],
+            Arc::new(EventBus::new(10)),
+            Arc::new(crate::config::Config::default()),
+        );
+        let reviewer = Reviewer::new()
+            .with_artifact_manager(artifacts)
+            .with_diff_only(0);
+        let plan = Plan {
+            goal: "Rename a to b".to_string(),
+            steps: Vec::new(),
+            dependencies: std::collections::HashMap::new(),
+            estimated_complexity: crate::planner::ComplexityLevel::Simple,
+        };
+        let results = [StepResult {
+            step_id: "1".to_string(),
+            success: true,
+            output: "The whole file as generated".to_string(),
+            artifacts_created: vec![artifact.id],
+            tokens_used: 0,
+            error: None,
+        }];
+        reviewer.review(&plan, &results, &llm, "review").await.unwrap();
+
+        let prompt = prompts.lock().unwrap()[0].clone();
+        assert!(prompt.contains("Changes since the run started"), "{}", prompt);
+        assert!(prompt.contains("--- a/parser.rs\n+++ b/parser.rs\n"), "{}", prompt);
+        assert!(prompt.contains("-fn a() {}\n+fn b() {}\n"), "{}", prompt);
+        assert!(!prompt.contains("fn keep"), "{}", prompt);
+        assert!(!prompt.contains("The whole file as generated"), "{}", prompt);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_issue_location() {
+        let reviewer = Reviewer::new();
+        let issue = reviewer
+            .parse_issue_line(
+                "- SEVERITY: Major | CATEGORY: Logic | LOCATION: src/lib.rs:10-25 | DESCRIPTION: Bad bound | SUGGESTION: Fix",
+            )
+            .unwrap();
+        assert_eq!(issue.location_path(), Some("src/lib.rs"));
+        assert_eq!(issue.location_lines(), Some((10, 25)));
+        assert_eq!(issue.suggestion.as_deref(), Some("Fix"));
+
+        // The older format without LOCATION still parses
+        let issue = reviewer
+            .parse_issue_line("- SEVERITY: Minor | CATEGORY: CodeStyle | DESCRIPTION: Naming | SUGGESTION: Rename")
+            .unwrap();
+        assert!(issue.location.is_none());
+        assert_eq!(issue.description, "Naming");
+    }
+
+    #[test]
+    fn test_parse_fix_patch() {
+        let response = "READY_TO_DEPLOY: No\nISSUES:\n\
+                        - SEVERITY: Minor | CATEGORY: CodeStyle | LOCATION: main.py:2 | DESCRIPTION: Typo | SUGGESTION: Fix it\n\
+                        PATCH: main.py\n\
+                        <<<<<<< SEARCH\n\
+                        print(\"helo\")\n\
+                        =======\n\
+                        print(\"hello\")\n\
+                        >>>>>>> REPLACE\n";
+        let review = Reviewer::new().parse_review_response(response, &[]).unwrap();
+        let patch = review.issues[0].patch.as_ref().expect("patch parsed");
+        assert_eq!(patch.file, "main.py");
+        assert_eq!(
+            patch.apply("import sys\nprint(\"helo\")\n").as_deref(),
+            Some("import sys\nprint(\"hello\")\n")
+        );
+        assert!(patch.apply("print(\"hi\")\n").is_none());
+    }
+}
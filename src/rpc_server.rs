@@ -0,0 +1,297 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::config::Config;
+use crate::event_bus::{Event, EventBus};
+use crate::event_log::EventRecord;
+use crate::mcp_server::{run_task, task_command};
+use crate::run_control::{ControlMessage, RunControl};
+
+/// Version of the protocol described in docs/editor_protocol.md
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The request does not fit the server's state, e.g. cancelling with no task running
+const INVALID_STATE: i64 = -32000;
+
+/// Speak the editor protocol on stdin and stdout until stdin closes: clients
+/// start one task at a time, receive its events as notifications and control
+/// it with the messages of the IPC bridge
+pub async fn serve(config: Arc<Config>) -> Result<()> {
+    let server = Arc::new(RpcServer {
+        config,
+        stdout: Mutex::new(tokio::io::stdout()),
+        task: Mutex::new(None),
+    });
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.clone().handle(&line).await
+            && let Err(e) = server.send(&response).await
+        {
+            warn!("Failed to answer request: {}", e);
+        }
+    }
+
+    // The client is gone; stop the task so its record is still saved
+    let task = server.task.lock().await.take();
+    if let Some(task) = task {
+        let _ = task
+            .control
+            .send(ControlMessage::Cancel, &task.bus, "rpc")
+            .await;
+        let _ = task.handle.await;
+    }
+    Ok(())
+}
+
+/// The task being run for the client
+struct RunningTask {
+    bus: Arc<EventBus>,
+    control: Arc<RunControl>,
+    handle: JoinHandle<()>,
+}
+
+struct RpcServer {
+    config: Arc<Config>,
+    stdout: Mutex<tokio::io::Stdout>,
+    task: Mutex<Option<RunningTask>>,
+}
+
+impl RpcServer {
+    async fn send(&self, message: &Value) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        let mut stdout = self.stdout.lock().await;
+        stdout.write_all(line.as_bytes()).await?;
+        stdout.flush().await?;
+        Ok(())
+    }
+
+    async fn notify(&self, method: &str, params: Value) {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        if let Err(e) = self.send(&message).await {
+            warn!("Failed to send {} notification: {}", method, e);
+        }
+    }
+
+    /// The response to one message, or `None` for notifications
+    async fn handle(self: Arc<Self>, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let control = match method {
+            "pause" => Some(ControlMessage::Pause),
+            "resume" => Some(ControlMessage::Resume),
+            "cancel" => Some(ControlMessage::Cancel),
+            "skip" => Some(ControlMessage::Skip),
+            "approve" => Some(ControlMessage::Approve),
+            "guidance" => Some(ControlMessage::Guidance {
+                text: params["text"].as_str().unwrap_or_default().to_string(),
+            }),
+            _ => None,
+        };
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "name": "cli_engineer",
+                "version": env!("CARGO_PKG_VERSION"),
+                "protocolVersion": PROTOCOL_VERSION,
+                "commands": crate::mcp_server::TASK_COMMANDS,
+            })),
+            "start" => self.start(&params).await,
+            "status" => Ok(self.status().await),
+            _ => match control {
+                Some(control) => self.control(control).await,
+                None => {
+                    return Some(error_response(
+                        id,
+                        METHOD_NOT_FOUND,
+                        &format!("Unknown method {}", method),
+                    ));
+                }
+            },
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    /// Start a task in the background; its events and outcome follow as notifications
+    async fn start(self: &Arc<Self>, params: &Value) -> Result<Value, (i64, String)> {
+        let argument = |key: &str| params.get(key).and_then(Value::as_str).unwrap_or("");
+        let command_name = argument("command").to_string();
+        let prompt = argument("prompt").to_string();
+        let require_approval = params
+            .get("requireApproval")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let command =
+            task_command(&command_name, &prompt).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+
+        let mut task = self.task.lock().await;
+        if task.as_ref().is_some_and(|task| !task.handle.is_finished()) {
+            return Err((INVALID_STATE, "A task is already running".to_string()));
+        }
+        info!("Starting {} task for the client", command_name);
+        // Each run counts its own calls, tokens and cost
+        let bus = Arc::new(EventBus::new(1000));
+        let control = Arc::new(RunControl::new());
+        let server = self.clone();
+        let handle = tokio::spawn({
+            let bus = bus.clone();
+            let control = control.clone();
+            async move {
+                let mut events = bus.subscribe();
+                let run = run_task(
+                    &server.config,
+                    command,
+                    &command_name,
+                    &prompt,
+                    bus.clone(),
+                    control,
+                    require_approval,
+                );
+                tokio::pin!(run);
+                let outcome = loop {
+                    tokio::select! {
+                        biased;
+                        event = events.recv() => server.forward(event).await,
+                        outcome = &mut run => break outcome,
+                    }
+                };
+                // Events emitted as the run ended
+                while let Ok(event) = events.try_recv() {
+                    server.forward(Ok(event)).await;
+                }
+                let params = match outcome {
+                    Ok(record) => json!({
+                        "success": record.success,
+                        "error": record.error,
+                        "run": record,
+                    }),
+                    Err(e) => json!({ "success": false, "error": format!("{:#}", e) }),
+                };
+                server.notify("finished", params).await;
+            }
+        });
+        *task = Some(RunningTask {
+            bus,
+            control,
+            handle,
+        });
+        Ok(json!({}))
+    }
+
+    /// Send an event of the running task to the client
+    async fn forward(&self, event: Result<Event, RecvError>) {
+        match event {
+            Ok(event) => {
+                let record = EventRecord {
+                    timestamp: chrono::Utc::now(),
+                    event,
+                };
+                self.notify("event", json!(record)).await;
+            }
+            Err(RecvError::Lagged(missed)) => {
+                warn!(
+                    "{} events were dropped because the client fell behind",
+                    missed
+                );
+            }
+            // The run holds the bus until it ends
+            Err(RecvError::Closed) => {}
+        }
+    }
+
+    async fn status(&self) -> Value {
+        match self.task.lock().await.as_ref() {
+            Some(task) if !task.handle.is_finished() => json!({
+                "running": true,
+                "paused": task.control.is_paused(),
+                "approvalRequest": task.control.approval_request(),
+            }),
+            _ => json!({ "running": false }),
+        }
+    }
+
+    async fn control(&self, message: ControlMessage) -> Result<Value, (i64, String)> {
+        let task = self.task.lock().await;
+        let task = task
+            .as_ref()
+            .filter(|task| !task.handle.is_finished())
+            .context("No task is running")
+            .map_err(|e| (INVALID_STATE, e.to_string()))?;
+        task.control
+            .send(message, &task.bus, "rpc")
+            .await
+            .map_err(|e| (INVALID_STATE, e.to_string()))?;
+        Ok(json!({}))
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rpc_server() {
+        let server = Arc::new(RpcServer {
+            config: Arc::new(Config::default()),
+            stdout: Mutex::new(tokio::io::stdout()),
+            task: Mutex::new(None),
+        });
+        let request = |line: &'static str| {
+            let server = server.clone();
+            async move { server.handle(line).await }
+        };
+
+        let initialize = request(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#)
+            .await
+            .unwrap();
+        assert_eq!(initialize["result"]["protocolVersion"], PROTOCOL_VERSION);
+        let status = request(r#"{"jsonrpc":"2.0","id":2,"method":"status"}"#)
+            .await
+            .unwrap();
+        assert_eq!(status["result"]["running"], false);
+        let cancel = request(r#"{"jsonrpc":"2.0","id":3,"method":"cancel"}"#)
+            .await
+            .unwrap();
+        assert_eq!(cancel["error"]["code"], INVALID_STATE);
+        let start =
+            request(r#"{"jsonrpc":"2.0","id":4,"method":"start","params":{"command":"code"}}"#)
+                .await
+                .unwrap();
+        assert_eq!(start["error"]["code"], INVALID_PARAMS);
+        assert!(
+            request(r#"{"jsonrpc":"2.0","method":"exit"}"#)
+                .await
+                .is_none()
+        );
+        let unknown = request(r#"{"jsonrpc":"2.0","id":5,"method":"tools/list"}"#)
+            .await
+            .unwrap();
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
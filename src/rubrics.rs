@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use log::warn;
+
+/// Built-in rubric for documentation tasks, used unless `docs` is overridden in config
+const DOCUMENTATION_RUBRIC: &str = "\
+Please pay special attention to these documentation-specific issues:
+
+1. **File Organization**:
+   - Check that ALL documentation files are in the artifacts/docs/ directory
+   - Flag any files created outside of artifacts/docs/ as CRITICAL issues
+   - Non-documentation files (code, configs, etc.) should NOT be in artifacts
+
+2. **File Completeness**:
+   - Check each file for incomplete content (e.g., sections that say 'TODO' or appear cut off)
+   - Check for files that end abruptly without proper conclusion
+   - Look for files with fewer than 30 lines that seem to be just stubs or introductions
+   - Check for headings without content (e.g., '## Section' followed by nothing)
+   - Check for incomplete sentences or paragraphs that seem cut off mid-thought
+   - Flag incomplete files as MAJOR issues
+
+3. **Link Integrity**:
+   - Internal links are checked automatically; any broken ones are listed below
+   - Check that links point to the most relevant page, not just an existing one
+
+4. **Content Quality**:
+   - Ensure documentation is specific to the actual codebase, not generic
+   - Check that API documentation matches actual code structure
+";
+
+/// Language names keyed by file extension, used to select language rubrics
+const LANGUAGE_EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("py", "python"),
+    ("js", "javascript"),
+    ("jsx", "javascript"),
+    ("mjs", "javascript"),
+    ("ts", "typescript"),
+    ("tsx", "typescript"),
+    ("go", "go"),
+    ("java", "java"),
+    ("rb", "ruby"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cpp", "cpp"),
+    ("hpp", "cpp"),
+    ("cs", "csharp"),
+    ("sh", "shell"),
+];
+
+/// Languages of the given files, plus those implied by project manifests in `root`
+pub fn detect_languages<'a>(files: impl IntoIterator<Item = &'a str>, root: &Path) -> Vec<String> {
+    let mut languages: Vec<String> = Vec::new();
+    let mut add = |language: &str| {
+        if !languages.iter().any(|l| l == language) {
+            languages.push(language.to_string());
+        }
+    };
+
+    for file in files {
+        let extension = file.rsplit('.').next().unwrap_or("").to_lowercase();
+        if let Some((_, language)) = LANGUAGE_EXTENSIONS
+            .iter()
+            .find(|(ext, _)| *ext == extension)
+        {
+            add(language);
+        }
+    }
+
+    for (manifest, language) in [
+        ("Cargo.toml", "rust"),
+        ("pyproject.toml", "python"),
+        ("package.json", "javascript"),
+        ("go.mod", "go"),
+    ] {
+        if root.join(manifest).exists() {
+            add(language);
+        }
+    }
+
+    languages
+}
+
+/// Resolve the rubrics for a review: the command's rubric first, then one per
+/// detected language. Keys in `configured` are command or language names and
+/// values are rubric file paths relative to `root`. Returns (name, text) pairs.
+pub fn select_rubrics(
+    configured: &HashMap<String, String>,
+    command: Option<&str>,
+    languages: &[String],
+    root: &Path,
+) -> Vec<(String, String)> {
+    let mut rubrics = Vec::new();
+
+    let load = |key: &str| -> Option<String> {
+        let path = configured.get(key)?;
+        match std::fs::read_to_string(root.join(path)) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                warn!(
+                    "Failed to read review rubric '{}' from {}: {}",
+                    key, path, e
+                );
+                None
+            }
+        }
+    };
+
+    if let Some(command) = command {
+        match load(command) {
+            Some(text) => rubrics.push((command.to_string(), text)),
+            None if command == "docs" => {
+                rubrics.push(("docs".to_string(), DOCUMENTATION_RUBRIC.to_string()))
+            }
+            None => {}
+        }
+    }
+
+    for language in languages {
+        if let Some(text) = load(language) {
+            rubrics.push((language.clone(), text));
+        }
+    }
+
+    rubrics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_rubrics() {
+        let root =
+            std::env::temp_dir().join(format!("cli_engineer_rubrics_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("rubrics")).unwrap();
+        std::fs::write(
+            root.join("rubrics/rust.md"),
+            "- No unwrap in library code\n",
+        )
+        .unwrap();
+
+        let configured = HashMap::from([("rust".to_string(), "rubrics/rust.md".to_string())]);
+        let languages = detect_languages(["src/lib.rs", "README.md"], &root);
+        assert_eq!(languages, vec!["rust".to_string()]);
+
+        let rubrics = select_rubrics(&configured, Some("docs"), &languages, &root);
+        let names: Vec<&str> = rubrics.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["docs", "rust"]);
+        assert!(rubrics[0].1.contains("File Organization"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
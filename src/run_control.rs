@@ -0,0 +1,355 @@
+use std::future::Future;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::event_bus::{Event, EventBus};
+
+/// Custom event announcing a plan that waits for an `approve` message
+pub const APPROVAL_REQUESTED_EVENT: &str = "approval_requested";
+
+/// Custom event announcing that the loop waits for a `guidance` message
+/// before planning its next iteration
+pub const GUIDANCE_REQUESTED_EVENT: &str = "guidance_requested";
+
+/// Control message sent to a running process, from the dashboard or over the
+/// IPC bridge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlMessage {
+    /// Stop before the next step until resumed
+    Pause,
+    Resume,
+    /// Stop the run, abandoning the step in progress
+    Cancel,
+    /// Abandon the step in progress and continue with the next one
+    Skip,
+    /// Execute the plan awaiting approval
+    Approve,
+    /// Guidance for the planner from the next iteration on, e.g. "keep the
+    /// public API stable". Empty text only ends a wait for guidance.
+    Guidance {
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ControlState {
+    paused: bool,
+    cancelled: bool,
+    skip_requested: bool,
+    awaiting_approval: bool,
+    awaiting_guidance: bool,
+    /// No longer waiting for the cancelled run to save its state
+    quitting: bool,
+}
+
+/// Pause, cancel and approval state of a run, checked by the agentic loop
+/// between steps
+pub struct RunControl {
+    state: watch::Sender<ControlState>,
+    /// Details of the plan awaiting approval, for clients that connect later
+    approval_request: Mutex<Option<serde_json::Value>>,
+    /// Guidance received since the loop last took it
+    guidance: Mutex<Vec<String>>,
+}
+
+impl Default for RunControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunControl {
+    pub fn new() -> Self {
+        Self {
+            state: watch::Sender::new(ControlState::default()),
+            approval_request: Mutex::new(None),
+            guidance: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Apply a control message, returning the name of the event announcing it
+    pub fn apply(&self, message: ControlMessage) -> Result<&'static str> {
+        if let ControlMessage::Guidance { text } = &message
+            && !text.trim().is_empty()
+        {
+            self.guidance.lock().unwrap().push(text.trim().to_string());
+        }
+        let mut result = Ok("");
+        self.state.send_modify(|state| {
+            result = match message {
+                ControlMessage::Pause => {
+                    state.paused = true;
+                    Ok("run_paused")
+                }
+                ControlMessage::Resume => {
+                    state.paused = false;
+                    Ok("run_resumed")
+                }
+                ControlMessage::Cancel => {
+                    state.cancelled = true;
+                    Ok("run_cancelled")
+                }
+                ControlMessage::Skip => {
+                    state.skip_requested = true;
+                    Ok("step_skip_requested")
+                }
+                ControlMessage::Approve if state.awaiting_approval => {
+                    state.awaiting_approval = false;
+                    Ok("plan_approved")
+                }
+                ControlMessage::Approve => Err(anyhow::anyhow!("No plan is awaiting approval")),
+                ControlMessage::Guidance { .. } => {
+                    state.awaiting_guidance = false;
+                    Ok("guidance_received")
+                }
+            };
+        });
+        result
+    }
+
+    /// Apply a control message and announce it on `bus`, naming where it came from
+    pub async fn send(&self, message: ControlMessage, bus: &EventBus, source: &str) -> Result<()> {
+        let mut data = serde_json::json!({ "source": source });
+        if let ControlMessage::Guidance { text } = &message {
+            data["text"] = text.trim().into();
+        }
+        let event_type = self.apply(message)?;
+        let _ = bus
+            .emit(Event::Custom {
+                event_type: event_type.to_string(),
+                data,
+            })
+            .await;
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.borrow().paused
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.borrow().cancelled
+    }
+
+    /// Wait while the run is paused. Fails once it is cancelled.
+    pub async fn checkpoint(&self) -> Result<()> {
+        self.wait_until(|state| !state.paused).await
+    }
+
+    /// Wait for an `approve` message for the plan described by `request`.
+    /// Fails if the run is cancelled instead.
+    pub async fn wait_for_approval(&self, request: serde_json::Value) -> Result<()> {
+        *self.approval_request.lock().unwrap() = Some(request);
+        self.state
+            .send_modify(|state| state.awaiting_approval = true);
+        let approved = self.wait_until(|state| !state.awaiting_approval).await;
+        *self.approval_request.lock().unwrap() = None;
+        approved?;
+        self.checkpoint().await
+    }
+
+    /// Wait until the step starting now should be abandoned: resolves when it
+    /// is skipped and fails when the run is cancelled. Earlier skips are
+    /// forgotten, so a skip only ever applies to the step in progress.
+    pub async fn interrupted(&self) -> Result<()> {
+        self.state.send_modify(|state| state.skip_requested = false);
+        self.wait_until(|state| state.skip_requested).await?;
+        self.state.send_modify(|state| state.skip_requested = false);
+        Ok(())
+    }
+
+    /// Wait for a `guidance` message, which may be empty. Guidance received
+    /// before the wait and not yet taken ends it at once. Fails if the run is
+    /// cancelled instead.
+    pub async fn wait_for_guidance(&self) -> Result<()> {
+        self.state
+            .send_modify(|state| state.awaiting_guidance = !self.has_guidance());
+        self.wait_until(|state| !state.awaiting_guidance).await
+    }
+
+    /// Whether guidance was received since it was last taken
+    pub fn has_guidance(&self) -> bool {
+        !self.guidance.lock().unwrap().is_empty()
+    }
+
+    /// Guidance received since the last call, oldest first
+    pub fn take_guidance(&self) -> Vec<String> {
+        std::mem::take(&mut *self.guidance.lock().unwrap())
+    }
+
+    /// Resolves once the run is cancelled
+    pub async fn cancelled(&self) {
+        let _ = self.wait_until(|_| false).await;
+    }
+
+    /// Cancel the run and stop waiting for it to save its state, as on a
+    /// second Ctrl-C
+    pub fn quit(&self) {
+        self.state.send_modify(|state| {
+            state.cancelled = true;
+            state.quitting = true;
+        });
+    }
+
+    /// Run `work` to the end, unless the user quits first. Returns None then,
+    /// having dropped `work`.
+    pub async fn unless_quit<T>(&self, work: impl Future<Output = T>) -> Option<T> {
+        let mut receiver = self.state.subscribe();
+        let quitting = async move {
+            // The sender lives as long as `self`, so this cannot fail
+            let _ = receiver.wait_for(|state| state.quitting).await;
+        };
+        tokio::select! {
+            result = work => Some(result),
+            () = quitting => None,
+        }
+    }
+
+    /// Details of the plan awaiting approval, if any
+    pub fn approval_request(&self) -> Option<serde_json::Value> {
+        self.approval_request.lock().unwrap().clone()
+    }
+
+    async fn wait_until(&self, ready: impl Fn(&ControlState) -> bool) -> Result<()> {
+        let mut receiver = self.state.subscribe();
+        loop {
+            let state = *receiver.borrow_and_update();
+            if state.cancelled {
+                anyhow::bail!("Run cancelled");
+            }
+            if ready(&state) {
+                return Ok(());
+            }
+            // The sender lives as long as `self`, so this cannot fail
+            receiver.changed().await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_run_control() {
+        let control = Arc::new(RunControl::new());
+        control.checkpoint().await.unwrap();
+        assert!(control.apply(ControlMessage::Approve).is_err());
+
+        control.apply(ControlMessage::Pause).unwrap();
+        let waiting = tokio::spawn({
+            let control = control.clone();
+            async move { control.checkpoint().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        assert_eq!(
+            control.apply(ControlMessage::Resume).unwrap(),
+            "run_resumed"
+        );
+        waiting.await.unwrap().unwrap();
+
+        let approval = tokio::spawn({
+            let control = control.clone();
+            async move {
+                control
+                    .wait_for_approval(serde_json::json!({ "iteration": 1 }))
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(control.approval_request().unwrap()["iteration"], 1);
+        assert_eq!(
+            control.apply(ControlMessage::Approve).unwrap(),
+            "plan_approved"
+        );
+        approval.await.unwrap().unwrap();
+        assert!(control.approval_request().is_none());
+
+        control.apply(ControlMessage::Skip).unwrap();
+        let step = tokio::spawn({
+            let control = control.clone();
+            async move { control.interrupted().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        // A skip sent before the step started does not apply to it
+        assert!(!step.is_finished());
+        control.apply(ControlMessage::Skip).unwrap();
+        step.await.unwrap().unwrap();
+
+        let guidance = tokio::spawn({
+            let control = control.clone();
+            async move { control.wait_for_guidance().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!guidance.is_finished());
+        let message: ControlMessage =
+            serde_json::from_str(r#"{"command": "guidance", "text": " Keep the API "}"#).unwrap();
+        assert_eq!(control.apply(message).unwrap(), "guidance_received");
+        guidance.await.unwrap().unwrap();
+        control
+            .apply(ControlMessage::Guidance {
+                text: String::new(),
+            })
+            .unwrap();
+        assert_eq!(control.take_guidance(), ["Keep the API"]);
+        assert!(control.take_guidance().is_empty());
+
+        let message: ControlMessage = serde_json::from_str(r#"{"command": "cancel"}"#).unwrap();
+        control.apply(message).unwrap();
+        assert!(control.checkpoint().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_guidance_sent_before_the_wait_is_used() {
+        let control = RunControl::new();
+        control
+            .apply(ControlMessage::Guidance {
+                text: "Keep the API".to_string(),
+            })
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(1), control.wait_for_guidance())
+            .await
+            .expect("the wait should end at once")
+            .unwrap();
+        assert_eq!(control.take_guidance(), ["Keep the API"]);
+    }
+
+    #[tokio::test]
+    async fn test_a_cancelled_run_is_waited_for() {
+        let control = Arc::new(RunControl::new());
+        control.apply(ControlMessage::Cancel).unwrap();
+        let saved = control
+            .unless_quit(async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                "saved"
+            })
+            .await;
+        assert_eq!(saved, Some("saved"));
+    }
+
+    #[tokio::test]
+    async fn test_quitting_stops_waiting_for_the_run() {
+        let control = Arc::new(RunControl::new());
+        let run = tokio::spawn({
+            let control = control.clone();
+            async move { control.unless_quit(std::future::pending::<()>()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!run.is_finished());
+        control.quit();
+        assert_eq!(run.await.unwrap(), None);
+        assert!(control.is_cancelled());
+        // Work started after quitting is not waited for either
+        assert_eq!(
+            control.unless_quit(std::future::pending::<()>()).await,
+            None
+        );
+    }
+}
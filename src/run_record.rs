@@ -0,0 +1,357 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::outcome::Outcome;
+use crate::reviewer::ReviewResult;
+
+const SUMMARY_FILE: &str = "run_summary.json";
+const SUMMARY_MARKDOWN_FILE: &str = "run_summary.md";
+/// Name of the summary file before it was paired with the Markdown summary
+const LEGACY_SUMMARY_FILE: &str = "summary.json";
+
+/// What a run did, written to `.cli_engineer/runs/<run_id>/run_summary.json`
+/// and `run_summary.md` when it ends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub run_id: String,
+    pub command: String,
+    pub prompt: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: chrono::DateTime<chrono::Utc>,
+    pub success: bool,
+    pub error: Option<String>,
+    /// Artifacts in the manifest at the end of the run
+    pub artifacts: Vec<ArtifactRecord>,
+    /// The last review of the run
+    pub review: Option<ReviewResult>,
+    /// URL of the pull request opened for the run's branch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pull_request: Option<String>,
+    /// Plan and step outcomes of each iteration
+    #[serde(default)]
+    pub iterations: Vec<IterationRecord>,
+    /// Why the loop stopped before its iteration limit, when it stopped making progress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stalled: Option<String>,
+    #[serde(default)]
+    pub api_calls: usize,
+    #[serde(default)]
+    pub tokens: usize,
+    /// Cost in USD
+    #[serde(default)]
+    pub cost: f32,
+    /// How the run ended, which sets the exit status
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<Outcome>,
+}
+
+/// An artifact of a run and where it was written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ArtifactEntry")]
+pub struct ArtifactRecord {
+    pub name: String,
+    pub path: String,
+}
+
+/// Older summaries recorded artifacts by name only
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ArtifactEntry {
+    Name(String),
+    Record { name: String, path: String },
+}
+
+impl From<ArtifactEntry> for ArtifactRecord {
+    fn from(entry: ArtifactEntry) -> Self {
+        match entry {
+            ArtifactEntry::Name(name) => Self {
+                path: name.clone(),
+                name,
+            },
+            ArtifactEntry::Record { name, path } => Self { name, path },
+        }
+    }
+}
+
+/// One pass of the planning-action-review cycle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IterationRecord {
+    pub iteration: usize,
+    /// Goal of the iteration's plan
+    pub goal: String,
+    pub steps: Vec<StepRecord>,
+    /// Summary of the iteration's review, if it got that far
+    pub review: Option<String>,
+}
+
+/// A planned step and how its execution went
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub description: String,
+    pub category: String,
+    /// `None` when the step was not run
+    pub success: Option<bool>,
+    pub error: Option<String>,
+    pub artifacts: Vec<String>,
+}
+
+impl RunRecord {
+    /// Directory holding the records of all runs
+    pub fn runs_dir(state_dir: &Path) -> PathBuf {
+        state_dir.join("runs")
+    }
+
+    /// Write the JSON and Markdown summaries, returning the path of the Markdown one
+    pub fn save(&self, state_dir: &Path) -> Result<PathBuf> {
+        let dir = Self::runs_dir(state_dir).join(&self.run_id);
+        fs::create_dir_all(&dir).context("Failed to create run directory")?;
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize run summary")?;
+        fs::write(dir.join(SUMMARY_FILE), json).context("Failed to write run summary")?;
+        let path = dir.join(SUMMARY_MARKDOWN_FILE);
+        fs::write(&path, self.to_markdown()).context("Failed to write run summary")?;
+        Ok(path)
+    }
+
+    /// The summary file in a run directory, if the run has one
+    fn summary_file(run_dir: &Path) -> Option<PathBuf> {
+        [SUMMARY_FILE, LEGACY_SUMMARY_FILE]
+            .iter()
+            .map(|name| run_dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Load the record of `run_id`, or of the most recent run
+    pub fn load(state_dir: &Path, run_id: Option<&str>) -> Result<Self> {
+        let runs_dir = Self::runs_dir(state_dir);
+        let run_id = match run_id {
+            Some(id) => id.to_string(),
+            // Run IDs start with their timestamp, so the last one sorts last
+            None => fs::read_dir(&runs_dir)
+                .ok()
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.ok())
+                .filter(|e| Self::summary_file(&e.path()).is_some())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .max()
+                .with_context(|| format!("No runs recorded in {}", runs_dir.display()))?,
+        };
+        let run_dir = runs_dir.join(&run_id);
+        let path = Self::summary_file(&run_dir).unwrap_or_else(|| run_dir.join(SUMMARY_FILE));
+        let json = fs::read_to_string(&path)
+            .with_context(|| format!("No run summary at {}", path.display()))?;
+        serde_json::from_str(&json).context("Failed to parse run summary")
+    }
+
+    /// One line for headless runs: `review completed in 83s · 2 iterations ·
+    /// 3 artifacts · 12 API calls · 4800 tokens · $0.0210 · run <id>`, with the
+    /// error at the end of a failed run
+    pub fn summary_line(&self) -> String {
+        let outcome = match &self.error {
+            Some(_) => "failed",
+            None if self.success => "completed",
+            None if self.stalled.is_some() => "stalled",
+            None => "not completed",
+        };
+        let mut line = format!(
+            "{} {} in {}s · {} iterations · {} artifacts · {} API calls · {} tokens · ${:.4} · run {}",
+            self.command,
+            outcome,
+            (self.finished_at - self.started_at).num_seconds(),
+            self.iterations.len(),
+            self.artifacts.len(),
+            self.api_calls,
+            self.tokens,
+            self.cost,
+            self.run_id
+        );
+        if let Some(reason) = self.error.as_ref().or(self.stalled.as_ref()) {
+            line.push_str(&format!(": {}", reason.replace('\n', " ")));
+        }
+        line
+    }
+
+    /// Human-readable summary of the run and its final review
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Run {}\n\n", self.run_id);
+        out.push_str(&format!("- Command: `{}`\n", self.command));
+        out.push_str(&format!(
+            "- Started: {}\n- Finished: {} ({}s)\n",
+            self.started_at.to_rfc3339(),
+            self.finished_at.to_rfc3339(),
+            (self.finished_at - self.started_at).num_seconds()
+        ));
+        match &self.error {
+            Some(error) => out.push_str(&format!("- Result: failed: {}\n", error)),
+            None if self.success => out.push_str("- Result: completed\n"),
+            None => match &self.stalled {
+                Some(reason) => out.push_str(&format!("- Result: stalled: {}\n", reason)),
+                None => out.push_str("- Result: not completed\n"),
+            },
+        }
+        if let Some(url) = &self.pull_request {
+            out.push_str(&format!("- Pull request: {}\n", url));
+        }
+        out.push_str(&format!(
+            "- Iterations: {}\n- API calls: {}\n- Tokens: {}\n- Cost: ${:.4}\n",
+            self.iterations.len(),
+            self.api_calls,
+            self.tokens,
+            self.cost
+        ));
+        out.push_str(&format!("\n## Prompt\n\n{}\n", self.prompt));
+
+        for iteration in &self.iterations {
+            out.push_str(&format!(
+                "\n## Iteration {}\n\nPlan: {}\n\n",
+                iteration.iteration, iteration.goal
+            ));
+            for (i, step) in iteration.steps.iter().enumerate() {
+                let outcome = match step.success {
+                    Some(true) => "done",
+                    Some(false) => "failed",
+                    None => "not run",
+                };
+                out.push_str(&format!(
+                    "{}. [{}] {} ({})\n",
+                    i + 1,
+                    outcome,
+                    step.description,
+                    step.category
+                ));
+                if let Some(error) = &step.error {
+                    out.push_str(&format!("   - Error: {}\n", error));
+                }
+                if !step.artifacts.is_empty() {
+                    out.push_str(&format!("   - Artifacts: {}\n", step.artifacts.join(", ")));
+                }
+            }
+            if let Some(review) = &iteration.review {
+                out.push_str(&format!("\nReview: {}\n", review));
+            }
+        }
+
+        out.push_str("\n## Artifacts\n\n");
+        if self.artifacts.is_empty() {
+            out.push_str("None\n");
+        }
+        for artifact in &self.artifacts {
+            out.push_str(&format!("- {} (`{}`)\n", artifact.name, artifact.path));
+        }
+
+        if let Some(review) = &self.review {
+            out.push_str(&format!(
+                "\n## Final Review\n\nQuality: {:?}. Ready to deploy: {}.\n\n{}\n",
+                review.overall_quality,
+                if review.ready_to_deploy { "yes" } else { "no" },
+                review.summary
+            ));
+            if !review.issues.is_empty() {
+                out.push_str("\n### Issues\n\n");
+                for issue in &review.issues {
+                    match &issue.location {
+                        Some(location) => out.push_str(&format!(
+                            "- [{}] {}: {}\n",
+                            issue.severity, location, issue.description
+                        )),
+                        None => {
+                            out.push_str(&format!("- [{}] {}\n", issue.severity, issue.description))
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_run() {
+        let state_dir =
+            std::env::temp_dir().join(format!("cli_engineer_runs_{}", uuid::Uuid::new_v4()));
+        assert!(RunRecord::load(&state_dir, None).is_err());
+
+        // Summaries written before `run_summary.json` are still found
+        let legacy_dir = RunRecord::runs_dir(&state_dir).join("20241231_090000_cccc");
+        fs::create_dir_all(&legacy_dir).unwrap();
+        fs::write(
+            legacy_dir.join(LEGACY_SUMMARY_FILE),
+            r#"{"run_id": "20241231_090000_cccc", "command": "docs", "prompt": "",
+                "started_at": "2024-12-31T09:00:00Z", "finished_at": "2024-12-31T09:01:00Z",
+                "success": true, "error": null, "artifacts": ["README.md"], "review": null}"#,
+        )
+        .unwrap();
+        let legacy = RunRecord::load(&state_dir, None).unwrap();
+        assert_eq!(legacy.artifacts[0].path, "README.md");
+        assert!(legacy.iterations.is_empty());
+
+        let now = chrono::Utc::now();
+        for run_id in ["20250101_090000_aaaa", "20250102_090000_bbbb"] {
+            RunRecord {
+                run_id: run_id.to_string(),
+                command: "review".to_string(),
+                prompt: "Review the code".to_string(),
+                started_at: now,
+                finished_at: now,
+                success: true,
+                error: None,
+                artifacts: vec![ArtifactRecord {
+                    name: "code_review.md".to_string(),
+                    path: "artifacts/code_review.md".to_string(),
+                }],
+                review: None,
+                pull_request: None,
+                stalled: None,
+                iterations: vec![IterationRecord {
+                    iteration: 1,
+                    goal: "Review the code".to_string(),
+                    steps: vec![StepRecord {
+                        description: "Write the report".to_string(),
+                        category: "Documentation".to_string(),
+                        success: Some(false),
+                        error: Some("Timed out".to_string()),
+                        artifacts: Vec::new(),
+                    }],
+                    review: Some("Report missing".to_string()),
+                }],
+                api_calls: 2,
+                tokens: 1500,
+                cost: 0.02,
+                outcome: None,
+            }
+            .save(&state_dir)
+            .unwrap();
+        }
+
+        let latest = RunRecord::load(&state_dir, None).unwrap();
+        assert_eq!(latest.run_id, "20250102_090000_bbbb");
+        let markdown = fs::read_to_string(
+            RunRecord::runs_dir(&state_dir)
+                .join(&latest.run_id)
+                .join(SUMMARY_MARKDOWN_FILE),
+        )
+        .unwrap();
+        assert_eq!(markdown, latest.to_markdown());
+        assert!(markdown.contains("- code_review.md (`artifacts/code_review.md`)\n"));
+        assert!(
+            markdown
+                .contains("1. [failed] Write the report (Documentation)\n   - Error: Timed out\n")
+        );
+        assert_eq!(
+            latest.summary_line(),
+            "review completed in 0s · 1 iterations · 1 artifacts · 2 API calls · 1500 tokens · \
+             $0.0200 · run 20250102_090000_bbbb"
+        );
+        let first = RunRecord::load(&state_dir, Some("20250101_090000_aaaa")).unwrap();
+        assert_eq!(first.run_id, "20250101_090000_aaaa");
+
+        fs::remove_dir_all(&state_dir).unwrap();
+    }
+}
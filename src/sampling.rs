@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use futures::future::join_all;
+use log::{info, warn};
+
+use crate::config::SamplingJudge;
+use crate::llm_manager::LLMManager;
+
+/// Event announcing the candidates generated for a step and the one chosen
+pub const STEP_SAMPLED_EVENT: &str = "step_sampled";
+
+/// A response generated for a step, with the provider that gave it
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub provider: String,
+    pub response: String,
+}
+
+/// The candidates generated for a step and the response made of them
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub response: String,
+    pub candidates: Vec<Candidate>,
+    /// How the response was chosen, e.g. `candidate 2 (openai), picked by the judge`
+    pub selection: String,
+}
+
+/// What the judge made of the candidates
+#[derive(Debug, PartialEq)]
+enum Judgement {
+    /// Index of the best candidate
+    Choice(usize),
+    /// A response combining the candidates
+    Merged(String),
+}
+
+/// Generates several responses to a step prompt, across providers, and keeps
+/// the best of them: self-consistency for the steps that are easy to get wrong
+pub struct Sampler {
+    count: usize,
+    /// The main manager, then those of the further providers, used in turn
+    managers: Vec<Arc<LLMManager>>,
+    judge: SamplingJudge,
+}
+
+impl Sampler {
+    pub fn new(count: usize, managers: Vec<Arc<LLMManager>>, judge: SamplingJudge) -> Self {
+        Self {
+            count,
+            managers,
+            judge,
+        }
+    }
+
+    /// Generate the candidates for `prompt` concurrently and choose among them.
+    /// Fails only when every candidate request failed.
+    pub async fn sample(&self, prompt: &str, step: &str) -> Result<Sample> {
+        let requests = (0..self.count).map(|index| {
+            let manager = &self.managers[index % self.managers.len()];
+            async move {
+                let provider = manager.provider().name().to_string();
+                (provider, manager.send_prompt(prompt).await)
+            }
+        });
+        let mut candidates = Vec::new();
+        let mut last_error = None;
+        for (provider, result) in join_all(requests).await {
+            match result {
+                Ok(response) => candidates.push(Candidate { provider, response }),
+                Err(e) => {
+                    warn!("Candidate response from {} failed: {:#}", provider, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return Err(last_error.unwrap_or_else(|| anyhow!("No candidate responses")));
+        }
+        if candidates.len() == 1 {
+            return Ok(Sample {
+                response: candidates[0].response.clone(),
+                selection: "the only candidate generated".to_string(),
+                candidates,
+            });
+        }
+
+        let judged = match self.judge {
+            SamplingJudge::Model => match self.ask_judge(step, &candidates).await {
+                Ok(judgement) => Some(judgement),
+                Err(e) => {
+                    warn!(
+                        "Judging candidates failed, falling back to agreement: {:#}",
+                        e
+                    );
+                    None
+                }
+            },
+            SamplingJudge::Heuristic => None,
+        };
+        let (response, selection) = match judged {
+            Some(Judgement::Merged(response)) => (
+                response,
+                format!("merged from {} candidates by the judge", candidates.len()),
+            ),
+            Some(Judgement::Choice(index)) => (
+                candidates[index].response.clone(),
+                format!(
+                    "candidate {} ({}), picked by the judge",
+                    index + 1,
+                    candidates[index].provider
+                ),
+            ),
+            None => {
+                let index = most_agreed(&candidates);
+                (
+                    candidates[index].response.clone(),
+                    format!(
+                        "candidate {} ({}), agreeing most with the others",
+                        index + 1,
+                        candidates[index].provider
+                    ),
+                )
+            }
+        };
+        info!("Chose {} for step: {}", selection, step);
+        Ok(Sample {
+            response,
+            candidates,
+            selection,
+        })
+    }
+
+    /// Ask the main model which candidate is best, or for a merge of them
+    async fn ask_judge(&self, step: &str, candidates: &[Candidate]) -> Result<Judgement> {
+        // About three characters per token, leaving room for the reply
+        let budget = self.managers[0].get_context_size() * 3;
+        let prompt = judge_prompt(step, candidates, budget);
+        let reply = self.managers[0].send_prompt(&prompt).await?;
+        parse_judgement(&reply, candidates.len()).ok_or_else(|| {
+            anyhow!(
+                "Unrecognized judgement: {}",
+                reply.lines().next().unwrap_or("")
+            )
+        })
+    }
+}
+
+/// The prompt asking the judge to choose among `candidates`, within `budget`
+/// characters. Candidates are shortened to an equal share when they do not
+/// fit, and the judge can then only choose, as it cannot merge what it did
+/// not see in full.
+fn judge_prompt(step: &str, candidates: &[Candidate], budget: usize) -> String {
+    let mut prompt = format!(
+        "You are judging {} candidate responses to the same software engineering step.\n\n\
+         Step: {}\n\n",
+        candidates.len(),
+        step
+    );
+    let instructions = "Pick the candidate that completes the step most correctly and \
+         completely. Reply with `CHOICE: <number>` on the first line, optionally followed by \
+         your reasons.";
+    let merge = " Only if combining candidates is clearly better than any one of them, \
+         reply with `MERGED:` on the first line followed by the complete merged response, \
+         in the same format as the candidates, with every file in full.";
+    let share = budget.saturating_sub(prompt.len() + instructions.len() + merge.len())
+        / candidates.len().max(1);
+    let mut shortened = false;
+    for (index, candidate) in candidates.iter().enumerate() {
+        let response = candidate.response.trim();
+        let response = match response.char_indices().nth(share) {
+            Some((end, _)) => {
+                shortened = true;
+                format!("{}\n[... shortened to fit]", &response[..end])
+            }
+            None => response.to_string(),
+        };
+        prompt.push_str(&format!(
+            "<candidate number=\"{}\">\n{}\n</candidate>\n\n",
+            index + 1,
+            response
+        ));
+    }
+    prompt.push_str(instructions);
+    if !shortened {
+        prompt.push_str(merge);
+    }
+    prompt
+}
+
+/// The judge's reply as a choice among `count` candidates or a merged response
+fn parse_judgement(reply: &str, count: usize) -> Option<Judgement> {
+    let reply = reply.trim_start();
+    if let Some(merged) = reply.strip_prefix("MERGED:") {
+        let merged = merged.trim();
+        return (!merged.is_empty()).then(|| Judgement::Merged(merged.to_string()));
+    }
+    let line = reply.lines().find(|line| line.contains("CHOICE:"))?;
+    let number: usize = line
+        .split("CHOICE:")
+        .nth(1)?
+        .trim_start()
+        .trim_start_matches('#')
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    (1..=count)
+        .contains(&number)
+        .then(|| Judgement::Choice(number - 1))
+}
+
+/// Index of the candidate sharing the most lines with the others, preferring
+/// candidates with code blocks and, on ties, the earliest
+fn most_agreed(candidates: &[Candidate]) -> usize {
+    let lines: Vec<HashSet<&str>> = candidates
+        .iter()
+        .map(|candidate| {
+            candidate
+                .response
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .collect();
+    let score = |index: usize| {
+        let agreement: f64 = (0..lines.len())
+            .filter(|&other| other != index)
+            .map(|other| {
+                let shared = lines[index].intersection(&lines[other]).count();
+                let all = lines[index].union(&lines[other]).count();
+                if all == 0 {
+                    0.0
+                } else {
+                    shared as f64 / all as f64
+                }
+            })
+            .sum();
+        let has_code = candidates[index].response.contains("```");
+        agreement + if has_code { 1.0 } else { 0.0 }
+    };
+    let mut best = 0;
+    let mut best_score = f64::MIN;
+    for index in 0..candidates.len() {
+        let score = score(index);
+        if score > best_score {
+            best = index;
+            best_score = score;
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(response: &str) -> Candidate {
+        Candidate {
+            provider: "openai".to_string(),
+            response: response.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_judgement_and_agreement() {
+        assert_eq!(
+            parse_judgement("CHOICE: 2\nIt handles errors", 3),
+            Some(Judgement::Choice(1))
+        );
+        assert_eq!(
+            parse_judgement("Best is... CHOICE: #1", 3),
+            Some(Judgement::Choice(0))
+        );
+        assert_eq!(parse_judgement("CHOICE: 4", 3), None);
+        assert_eq!(
+            parse_judgement("MERGED:\n```rust\nfn main() {}\n```", 2),
+            Some(Judgement::Merged("```rust\nfn main() {}\n```".to_string()))
+        );
+        assert_eq!(parse_judgement("I cannot decide", 2), None);
+
+        let candidates = [
+            candidate("The function is correct."),
+            candidate("```rust\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n```"),
+            candidate("```rust\nfn add(a: i32, b: i32) -> i32 {\n    a - b\n}\n```"),
+            candidate("```rust\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n```\nDone."),
+        ];
+        assert_eq!(most_agreed(&candidates), 1);
+        assert_eq!(most_agreed(&candidates[..1]), 0);
+    }
+
+    #[test]
+    fn test_judge_prompt_fits_the_budget() {
+        let candidates = [candidate(&"a".repeat(100)), candidate(&"b".repeat(10_000))];
+        let prompt = judge_prompt("Add a parser", &candidates, 100_000);
+        assert!(prompt.contains(&"b".repeat(10_000)));
+        assert!(prompt.contains("MERGED:"));
+
+        let prompt = judge_prompt("Add a parser", &candidates, 3_000);
+        assert!(prompt.len() < 3_100, "{}", prompt.len());
+        assert!(prompt.contains(&"a".repeat(100)));
+        assert_eq!(prompt.matches("[... shortened to fit]").count(), 1);
+        // A merge of responses the judge saw only in part would lose code
+        assert!(!prompt.contains("MERGED:"));
+        assert!(prompt.contains("CHOICE:"));
+    }
+}
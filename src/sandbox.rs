@@ -0,0 +1,256 @@
+use std::path::Path;
+use std::process::{Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use log::{debug, warn};
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+
+use crate::config::{SandboxConfig, SandboxMount};
+
+/// Where the project is found inside the container
+const WORKDIR: &str = "/workspace";
+/// How long creating, copying into and removing a container may take
+const SETUP_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Containers started by this process, numbered to give each a unique name
+static CONTAINERS: AtomicUsize = AtomicUsize::new(0);
+/// Runtime and name of the containers that exist right now, removed on interrupt
+static RUNNING: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Force-remove the containers this process has running, e.g. before it
+/// exits on Ctrl-C, which would otherwise leave them behind
+pub fn remove_running() {
+    let running = std::mem::take(&mut *RUNNING.lock().unwrap_or_else(|e| e.into_inner()));
+    for (runtime, name) in running {
+        let removed = std::process::Command::new(&runtime)
+            .args(["rm", "--force", &name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if !removed.is_ok_and(|status| status.success()) {
+            warn!("Failed to remove sandbox container {}", name);
+        }
+    }
+}
+
+fn track(runtime: &str, name: &str) {
+    RUNNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((runtime.to_string(), name.to_string()));
+}
+
+fn untrack(name: &str) {
+    RUNNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|(_, running)| running != name);
+}
+
+/// Runs commands in a throwaway container instead of on the host, so code
+/// they execute (build scripts, test suites) cannot touch the machine.
+/// Only the review linters, the security scanners and `run_command` tool
+/// commands go through it; hooks, MCP servers, language servers and
+/// formatters run on the host.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    config: SandboxConfig,
+    /// Whether the runtime answered `<runtime> version`, checked once
+    available: Arc<OnceCell<Result<(), String>>>,
+}
+
+impl Sandbox {
+    pub fn new(config: SandboxConfig) -> Self {
+        Self {
+            config,
+            available: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Fail unless the container runtime is installed and reachable, so that
+    /// a failure of the command in the container is never mistaken for it
+    async fn check_runtime(&self) -> Result<()> {
+        self.available
+            .get_or_init(|| async {
+                self.runtime(&["version"]).await.map_err(|e| {
+                    format!(
+                        "container runtime {} is not available: {:#}",
+                        self.config.runtime, e
+                    )
+                })
+            })
+            .await
+            .clone()
+            .map_err(|e| anyhow!(e))
+    }
+
+    /// Run `program` with `args` on the project at `root` in a new container
+    /// and return its output. The container is removed afterwards, also when
+    /// `timeout` runs out; fails if the container runtime is unavailable.
+    pub async fn output(
+        &self,
+        root: &Path,
+        program: &str,
+        args: &[&str],
+        timeout: Duration,
+    ) -> Result<Output> {
+        self.check_runtime().await?;
+        let name = format!(
+            "cli_engineer-{}-{}",
+            std::process::id(),
+            CONTAINERS.fetch_add(1, Ordering::Relaxed)
+        );
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("Cannot find {}", root.display()))?;
+
+        track(&self.config.runtime, &name);
+        let created = self
+            .runtime(&self.create_args(&name, &root, program, args))
+            .await
+            .context("Failed to create the sandbox container");
+        if let Err(e) = created {
+            untrack(&name);
+            return Err(e);
+        }
+        let output = self.run_in(&name, &root, timeout).await;
+        // `--rm` removes a container that ran to the end; one that was never
+        // started or timed out is still there
+        if output.is_err()
+            && let Err(e) = self.runtime(&["rm", "--force", &name]).await
+        {
+            warn!("Failed to remove sandbox container {}: {:#}", name, e);
+        }
+        untrack(&name);
+        output
+    }
+
+    /// Arguments of `<runtime> create` for a container running `program`
+    fn create_args(&self, name: &str, root: &Path, program: &str, args: &[&str]) -> Vec<String> {
+        let mut create = vec![
+            "create".to_string(),
+            "--rm".to_string(),
+            "--name".to_string(),
+            name.to_string(),
+            "--workdir".to_string(),
+            WORKDIR.to_string(),
+        ];
+        if !self.config.network {
+            create.extend(["--network".to_string(), "none".to_string()]);
+        }
+        if self.config.mount == SandboxMount::Mount {
+            create.extend([
+                "--volume".to_string(),
+                format!("{}:{}", root.display(), WORKDIR),
+            ]);
+        }
+        create.extend(self.config.extra_args.iter().cloned());
+        create.push(self.config.image.clone());
+        create.push(program.to_string());
+        create.extend(args.iter().map(|arg| arg.to_string()));
+        create
+    }
+
+    /// Copy the project in if configured, then start the container and wait for it
+    async fn run_in(&self, name: &str, root: &Path, timeout: Duration) -> Result<Output> {
+        if self.config.mount == SandboxMount::Copy {
+            let source = format!("{}/.", root.display());
+            self.runtime(&["cp", &source, &format!("{}:{}", name, WORKDIR)])
+                .await
+                .context("Failed to copy the project into the sandbox")?;
+        }
+        debug!("Starting sandbox container {}", name);
+        let child = Command::new(&self.config.runtime)
+            .args(["start", "--attach", name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.config.runtime))?;
+        tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .map_err(|_| anyhow!("timed out after {}s", timeout.as_secs()))?
+            .map_err(Into::into)
+    }
+
+    /// Run the container runtime with `args`, failing on a non-zero exit
+    async fn runtime<S: AsRef<str>>(&self, args: &[S]) -> Result<()> {
+        let output = tokio::time::timeout(
+            SETUP_TIMEOUT,
+            Command::new(&self.config.runtime)
+                .args(args.iter().map(AsRef::as_ref))
+                .stdin(Stdio::null())
+                .kill_on_drop(true)
+                .output(),
+        )
+        .await
+        .map_err(|_| anyhow!("{} timed out", self.config.runtime))?
+        .with_context(|| format!("Failed to run {}", self.config.runtime))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{} exited with {}: {}",
+                self.config.runtime,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_args() {
+        let mut config = SandboxConfig {
+            image: "rust:1".to_string(),
+            extra_args: vec!["--memory".to_string(), "2g".to_string()],
+            ..SandboxConfig::default()
+        };
+        let root = Path::new("/src/project");
+        assert_eq!(
+            Sandbox::new(config.clone())
+                .create_args("box", root, "cargo", &["clippy"])
+                .join(" "),
+            "create --rm --name box --workdir /workspace --network none --memory 2g rust:1 \
+cargo clippy"
+        );
+
+        config.mount = SandboxMount::Mount;
+        config.network = true;
+        assert_eq!(
+            Sandbox::new(config)
+                .create_args("box", root, "cargo", &["clippy"])
+                .join(" "),
+            "create --rm --name box --workdir /workspace --volume /src/project:/workspace \
+--memory 2g rust:1 cargo clippy"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_runtime_is_an_error() {
+        let sandbox = Sandbox::new(SandboxConfig {
+            runtime: "cli_engineer-no-such-runtime".to_string(),
+            image: "rust:1".to_string(),
+            ..SandboxConfig::default()
+        });
+        let error = sandbox
+            .output(Path::new("."), "cargo", &["clippy"], Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("cli_engineer-no-such-runtime is not available"),
+            "{:#}",
+            error
+        );
+        assert!(RUNNING.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,534 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde_json::{Value, json};
+
+use crate::artifact::{ArtifactManager, ArtifactType, add_tags};
+use crate::config::SbomFormat;
+use crate::lockfiles::Dependency;
+
+/// Report the model writes its executive summary to
+pub const REPORT_NAME: &str = "sbom_report.md";
+const REPORT_TITLE: &str = "# Software Bill of Materials";
+/// Most components listed to the model; the SBOM itself has them all
+const MAX_CONTEXT_COMPONENTS: usize = 500;
+
+/// SPDX license identifiers common in package registries. An expression
+/// naming any other license is written as a license name, which SBOM
+/// validators accept, rather than as an expression they would reject.
+const SPDX_LICENSES: &[&str] = &[
+    "0BSD",
+    "AFL-3.0",
+    "AGPL-3.0",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "Apache-1.1",
+    "Apache-2.0",
+    "Artistic-2.0",
+    "BlueOak-1.0.0",
+    "BSD-1-Clause",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSD-3-Clause-Clear",
+    "BSD-4-Clause",
+    "BSL-1.0",
+    "CC-BY-3.0",
+    "CC-BY-4.0",
+    "CC-BY-SA-4.0",
+    "CC0-1.0",
+    "CDDL-1.0",
+    "CDDL-1.1",
+    "EPL-1.0",
+    "EPL-2.0",
+    "EUPL-1.2",
+    "GPL-2.0",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "HPND",
+    "ISC",
+    "LGPL-2.0",
+    "LGPL-2.1",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "MIT",
+    "MIT-0",
+    "MPL-1.1",
+    "MPL-2.0",
+    "MS-PL",
+    "NCSA",
+    "OFL-1.1",
+    "OpenSSL",
+    "PostgreSQL",
+    "PSF-2.0",
+    "Python-2.0",
+    "Unicode-3.0",
+    "Unicode-DFS-2016",
+    "Unlicense",
+    "WTFPL",
+    "X11",
+    "Zlib",
+    "ZPL-2.1",
+];
+
+/// SPDX license exceptions, which follow `WITH`
+const SPDX_EXCEPTIONS: &[&str] = &[
+    "Classpath-exception-2.0",
+    "GCC-exception-3.1",
+    "LLVM-exception",
+    "OpenSSL-exception",
+];
+
+/// Whether `license` is an SPDX license expression such as
+/// `(MIT OR Apache-2.0) AND Unicode-3.0`, rather than a name like
+/// `Apache License 2.0` or a `SEE LICENSE IN` note
+fn is_spdx_expression(license: &str) -> bool {
+    let spaced = license.replace('(', " ( ").replace(')', " ) ");
+    let mut depth = 0usize;
+    // Operands and operators alternate, starting and ending with an operand
+    let mut expect_operand = true;
+    let mut after_with = false;
+    for token in spaced.split_whitespace() {
+        match token {
+            "(" if expect_operand => depth += 1,
+            ")" if !expect_operand && depth > 0 => depth -= 1,
+            "AND" | "OR" | "WITH" if !expect_operand => {
+                after_with = token == "WITH";
+                expect_operand = true;
+            }
+            _ if expect_operand && after_with => {
+                if !SPDX_EXCEPTIONS.contains(&token) {
+                    return false;
+                }
+                after_with = false;
+                expect_operand = false;
+            }
+            _ if expect_operand => {
+                let id = token.strip_suffix('+').unwrap_or(token);
+                let reference = id.strip_prefix("LicenseRef-").is_some_and(|name| {
+                    !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+                });
+                if !reference && !SPDX_LICENSES.contains(&id) {
+                    return false;
+                }
+                expect_operand = false;
+            }
+            _ => return false,
+        }
+    }
+    !expect_operand && depth == 0
+}
+
+/// SPDX identifier of a license given by a name that is not an SPDX expression
+fn license_ref(license: &str) -> String {
+    let name: String = license
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("LicenseRef-{}", name.trim_matches('-'))
+}
+
+/// Artifact name of the SBOM in `format`
+pub fn file_name(format: SbomFormat) -> &'static str {
+    match format {
+        SbomFormat::Cyclonedx => "sbom.cdx.json",
+        SbomFormat::Spdx => "sbom.spdx.json",
+    }
+}
+
+/// The SBOM of `project` listing `dependencies`
+pub fn document(format: SbomFormat, project: &str, dependencies: &[Dependency]) -> Value {
+    let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    match format {
+        SbomFormat::Cyclonedx => cyclonedx(project, dependencies, &timestamp),
+        SbomFormat::Spdx => spdx(project, dependencies, &timestamp),
+    }
+}
+
+fn cyclonedx(project: &str, dependencies: &[Dependency], timestamp: &str) -> Value {
+    let components: Vec<Value> = dependencies
+        .iter()
+        .map(|dependency| {
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": dependency.purl(),
+                "name": dependency.name,
+                "version": dependency.version,
+                "purl": dependency.purl(),
+            });
+            match &dependency.license {
+                Some(license) if is_spdx_expression(license) => {
+                    component["licenses"] = json!([{ "expression": license }]);
+                }
+                Some(license) => {
+                    component["licenses"] = json!([{ "license": { "name": license } }]);
+                }
+                None => {}
+            }
+            component
+        })
+        .collect();
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": "1.5",
+        "serialNumber": format!("urn:uuid:{}", uuid::Uuid::new_v4()),
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "cli_engineer",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }]
+            },
+            "component": { "type": "application", "bom-ref": project, "name": project },
+        },
+        "components": components,
+        "dependencies": [{
+            "ref": project,
+            "dependsOn": dependencies.iter().map(Dependency::purl).collect::<Vec<_>>(),
+        }],
+    })
+}
+
+fn spdx(project: &str, dependencies: &[Dependency], timestamp: &str) -> Value {
+    let mut packages = vec![json!({
+        "name": project,
+        "SPDXID": "SPDXRef-Project",
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+    })];
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": "SPDXRef-Project",
+    })];
+    // Licenses that are not SPDX expressions are declared as references
+    // to their name, described once each
+    let mut extracted: Vec<Value> = Vec::new();
+    for (index, dependency) in dependencies.iter().enumerate() {
+        let id = format!("SPDXRef-Package-{}", index + 1);
+        let declared = match &dependency.license {
+            Some(license) if is_spdx_expression(license) => license.clone(),
+            Some(license) => {
+                let reference = license_ref(license);
+                if !extracted
+                    .iter()
+                    .any(|info| info["licenseId"] == reference.as_str())
+                {
+                    extracted.push(json!({
+                        "licenseId": reference,
+                        "name": license,
+                        "extractedText": license,
+                    }));
+                }
+                reference
+            }
+            None => "NOASSERTION".to_string(),
+        };
+        packages.push(json!({
+            "name": dependency.name,
+            "SPDXID": id,
+            "versionInfo": dependency.version,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "licenseConcluded": "NOASSERTION",
+            "licenseDeclared": declared,
+            "externalRefs": [{
+                "referenceCategory": "PACKAGE-MANAGER",
+                "referenceType": "purl",
+                "referenceLocator": dependency.purl(),
+            }],
+        }));
+        relationships.push(json!({
+            "spdxElementId": "SPDXRef-Project",
+            "relationshipType": "DEPENDS_ON",
+            "relatedSpdxElement": id,
+        }));
+    }
+    let mut document = json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": project,
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}",
+            project.replace(|c: char| !c.is_ascii_alphanumeric(), "-"),
+            uuid::Uuid::new_v4()
+        ),
+        "creationInfo": {
+            "created": timestamp,
+            "creators": [format!("Tool: cli_engineer-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    });
+    if !extracted.is_empty() {
+        document["hasExtractedLicensingInfos"] = json!(extracted);
+    }
+    document
+}
+
+/// Save the SBOM as an artifact
+pub async fn write_sbom(
+    artifact_manager: &ArtifactManager,
+    format: SbomFormat,
+    project: &str,
+    dependencies: &[Dependency],
+) -> Result<()> {
+    let mut metadata = HashMap::new();
+    add_tags(&mut metadata, &["sbom"]);
+    artifact_manager
+        .create_artifact(
+            file_name(format).to_string(),
+            ArtifactType::Data,
+            serde_json::to_string_pretty(&document(format, project, dependencies))?,
+            metadata,
+        )
+        .await?;
+    Ok(())
+}
+
+/// The components as a context message for the model to summarize
+pub fn components_context(format: SbomFormat, dependencies: &[Dependency]) -> String {
+    let mut out = format!(
+        "Software bill of materials generated from the project's lockfiles and saved as {} \
+         ({} components). Licenses are as declared by the packages; \"unknown\" means none was \
+         recorded. Base the executive summary on these components only.\n\nLicenses:\n",
+        file_name(format),
+        dependencies.len()
+    );
+    for (license, count) in license_counts(dependencies) {
+        out.push_str(&format!("- {}: {}\n", license, count));
+    }
+    out.push_str("\nComponents:\n");
+    for dependency in dependencies.iter().take(MAX_CONTEXT_COMPONENTS) {
+        out.push_str(&format!(
+            "- {} {} ({}, {})\n",
+            dependency.name,
+            dependency.version,
+            dependency.ecosystem,
+            dependency.license.as_deref().unwrap_or("unknown")
+        ));
+    }
+    if dependencies.len() > MAX_CONTEXT_COMPONENTS {
+        out.push_str(&format!(
+            "- ... and {} more\n",
+            dependencies.len() - MAX_CONTEXT_COMPONENTS
+        ));
+    }
+    out
+}
+
+/// Write `sbom_report.md`: the component inventory, followed by the
+/// executive summary the model wrote there
+pub async fn write_report(
+    artifact_manager: &ArtifactManager,
+    format: SbomFormat,
+    dependencies: &[Dependency],
+) -> Result<()> {
+    let report = artifact_manager
+        .list_artifacts()
+        .await
+        .into_iter()
+        .find(|artifact| {
+            artifact
+                .path
+                .file_name()
+                .is_some_and(|name| name == REPORT_NAME)
+        });
+    let inventory = inventory(format, dependencies);
+    match report {
+        Some(report) => {
+            let content = std::fs::read_to_string(&report.path)
+                .ok()
+                .or(report.content)
+                .unwrap_or_default();
+            // The inventory of an earlier run is replaced
+            let summary = content
+                .strip_prefix(REPORT_TITLE)
+                .and_then(|rest| rest.split_once("\n---\n"))
+                .map_or(content.as_str(), |(_, summary)| summary);
+            artifact_manager
+                .update_artifact(&report.id, format!("{}\n{}\n", inventory, summary.trim()))
+                .await
+        }
+        None => artifact_manager
+            .create_artifact(
+                REPORT_NAME.to_string(),
+                ArtifactType::Documentation,
+                inventory,
+                HashMap::new(),
+            )
+            .await
+            .map(|_| ()),
+    }
+}
+
+/// Markdown tables of the licenses and components, ending in a `---` rule
+fn inventory(format: SbomFormat, dependencies: &[Dependency]) -> String {
+    let mut lockfiles: Vec<&str> = dependencies.iter().map(|d| d.lockfile).collect();
+    lockfiles.dedup();
+    let mut out = format!(
+        "{}\n\n{} components pinned by {}. The full SBOM is in `{}`.\n\n\
+         | License | Components |\n|---------|------------|\n",
+        REPORT_TITLE,
+        dependencies.len(),
+        lockfiles.join(", "),
+        file_name(format)
+    );
+    for (license, count) in license_counts(dependencies) {
+        out.push_str(&format!("| {} | {} |\n", license, count));
+    }
+    out.push_str(
+        "\n| Component | Version | Ecosystem | License |\n\
+         |-----------|---------|-----------|---------|\n",
+    );
+    for dependency in dependencies {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            dependency.name,
+            dependency.version,
+            dependency.ecosystem,
+            dependency.license.as_deref().unwrap_or("unknown")
+        ));
+    }
+    out.push_str("\n---\n");
+    out
+}
+
+/// Number of components per license, most common first
+fn license_counts(dependencies: &[Dependency]) -> Vec<(&str, usize)> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for dependency in dependencies {
+        let license = dependency.license.as_deref().unwrap_or("unknown");
+        match counts.iter_mut().find(|(l, _)| *l == license) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((license, 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sbom_documents() {
+        let dependencies = vec![
+            Dependency {
+                ecosystem: "npm",
+                name: "@scope/b".to_string(),
+                version: "2.0.0".to_string(),
+                lockfile: "package-lock.json",
+                license: Some("MIT".to_string()),
+            },
+            Dependency {
+                ecosystem: "PyPI",
+                name: "flask".to_string(),
+                version: "2.0.1".to_string(),
+                lockfile: "requirements.txt",
+                license: None,
+            },
+        ];
+
+        let bom = document(SbomFormat::Cyclonedx, "app", &dependencies);
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["components"][0]["purl"], "pkg:npm/%40scope/b@2.0.0");
+        assert_eq!(bom["components"][0]["licenses"][0]["expression"], "MIT");
+        assert!(bom["components"][1].get("licenses").is_none());
+        assert_eq!(
+            bom["dependencies"][0]["dependsOn"][1],
+            "pkg:pypi/flask@2.0.1"
+        );
+
+        let spdx = document(SbomFormat::Spdx, "app", &dependencies);
+        assert_eq!(spdx["spdxVersion"], "SPDX-2.3");
+        assert_eq!(spdx["packages"][2]["licenseDeclared"], "NOASSERTION");
+        assert_eq!(
+            spdx["relationships"][1]["relatedSpdxElement"],
+            "SPDXRef-Package-1"
+        );
+
+        let inventory = inventory(SbomFormat::Spdx, &dependencies);
+        assert!(inventory.contains("2 components pinned by package-lock.json, requirements.txt"));
+        assert!(inventory.contains("| flask | 2.0.1 | PyPI | unknown |"));
+        assert!(inventory.ends_with("\n---\n"));
+    }
+
+    #[test]
+    fn test_spdx_expressions() {
+        for license in [
+            "MIT",
+            "MIT OR Apache-2.0",
+            "(MIT OR Apache-2.0) AND Unicode-3.0",
+            "Apache-2.0 WITH LLVM-exception",
+            "GPL-2.0+",
+            "LicenseRef-Proprietary",
+        ] {
+            assert!(is_spdx_expression(license), "{}", license);
+        }
+        for license in [
+            "",
+            "Apache License 2.0",
+            "MIT/Apache-2.0",
+            "SEE LICENSE IN LICENSE.md",
+            "BSD",
+            "MIT OR",
+            "(MIT",
+            "MIT WITH Apache-2.0",
+            "mit or apache-2.0",
+        ] {
+            assert!(!is_spdx_expression(license), "{}", license);
+        }
+    }
+
+    #[test]
+    fn test_other_licenses_are_written_as_names() {
+        let dependency = |name: &str, license: &str| Dependency {
+            ecosystem: "npm",
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            lockfile: "package-lock.json",
+            license: Some(license.to_string()),
+        };
+        let dependencies = vec![
+            dependency("a", "Apache License 2.0"),
+            dependency("b", "Apache License 2.0"),
+            dependency("c", "MIT"),
+        ];
+
+        let bom = document(SbomFormat::Cyclonedx, "app", &dependencies);
+        let licenses = &bom["components"][0]["licenses"][0];
+        assert_eq!(licenses["license"]["name"], "Apache License 2.0");
+        assert!(licenses.get("expression").is_none());
+        assert_eq!(bom["components"][2]["licenses"][0]["expression"], "MIT");
+
+        let spdx = document(SbomFormat::Spdx, "app", &dependencies);
+        let reference = "LicenseRef-Apache-License-2.0";
+        assert_eq!(spdx["packages"][1]["licenseDeclared"], reference);
+        assert_eq!(spdx["packages"][2]["licenseDeclared"], reference);
+        assert_eq!(spdx["packages"][3]["licenseDeclared"], "MIT");
+        let extracted = spdx["hasExtractedLicensingInfos"].as_array().unwrap();
+        assert_eq!(extracted.len(), 1);
+        assert_eq!(extracted[0]["licenseId"], reference);
+        assert_eq!(extracted[0]["extractedText"], "Apache License 2.0");
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Context, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::file_summary;
+
+/// How a scanned file compares with the previous run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileChange {
+    New,
+    Changed,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    size: u64,
+    modified_secs: u64,
+    modified_nanos: u32,
+    sha256: String,
+    /// One-line summary of the file, made when one was first needed
+    #[serde(default)]
+    summary: Option<String>,
+}
+
+/// Fingerprints and summaries of the files of the previous scan, stored in
+/// `.cli_engineer/scan_cache.json`. File contents are never stored. Files
+/// whose size and modification time are unchanged are not hashed again, and
+/// unchanged files keep their summary.
+pub struct ScanCache {
+    path: PathBuf,
+    files: HashMap<String, CachedFile>,
+    seen: HashSet<String>,
+}
+
+impl ScanCache {
+    /// Load the cache from `state_dir`; a missing or unreadable cache starts empty
+    pub fn load(state_dir: &Path) -> Self {
+        let path = state_dir.join("scan_cache.json");
+        let files = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!("Ignoring unreadable scan cache {}: {}", path.display(), e);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+        Self {
+            path,
+            files,
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Whether there was no previous scan to compare against
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Content of `path` (keyed by `relative_path`) and how it changed since the last scan
+    pub fn read(&mut self, path: &Path, relative_path: &str) -> Result<(String, FileChange)> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        self.seen.insert(relative_path.to_string());
+        let content = std::fs::read_to_string(path)?;
+
+        if let Some(cached) = self.files.get(relative_path)
+            && cached.size == metadata.len()
+            && cached.modified_secs == modified.as_secs()
+            && cached.modified_nanos == modified.subsec_nanos()
+        {
+            debug!("Scan cache hit for {}", relative_path);
+            return Ok((content, FileChange::Unchanged));
+        }
+
+        let sha256 = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let (change, summary) = match self.files.remove(relative_path) {
+            None => (FileChange::New, None),
+            // Touched but identical, e.g. after a branch switch
+            Some(cached) if cached.sha256 == sha256 => (FileChange::Unchanged, cached.summary),
+            Some(_) => (FileChange::Changed, None),
+        };
+        self.files.insert(
+            relative_path.to_string(),
+            CachedFile {
+                size: metadata.len(),
+                modified_secs: modified.as_secs(),
+                modified_nanos: modified.subsec_nanos(),
+                sha256,
+                summary,
+            },
+        );
+        Ok((content, change))
+    }
+
+    /// One-line summary of the file read as `relative_path`, whose content is
+    /// `content`: the one stored if the file is unchanged, else a new one,
+    /// which is stored
+    pub fn summary(&mut self, relative_path: &str, content: &str) -> String {
+        let Some(cached) = self.files.get_mut(relative_path) else {
+            return file_summary::summarize_file(relative_path, content);
+        };
+        cached
+            .summary
+            .get_or_insert_with(|| file_summary::summarize_file(relative_path, content))
+            .clone()
+    }
+
+    /// Files cached by the previous scan that no longer exist or were not scanned
+    pub fn removed(&self) -> Vec<String> {
+        let mut removed: Vec<String> = self
+            .files
+            .keys()
+            .filter(|path| !self.seen.contains(*path))
+            .cloned()
+            .collect();
+        removed.sort();
+        removed
+    }
+
+    /// Persist the files seen in this scan, dropping the rest
+    pub fn save(mut self) -> Result<()> {
+        let seen = std::mem::take(&mut self.seen);
+        self.files.retain(|path, _| seen.contains(path));
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string(&self.files).context("Failed to serialize scan cache")?;
+        std::fs::write(&self.path, json).context("Failed to write scan cache")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cli_engineer_scan_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_contents_are_not_stored() {
+        let dir = temp_dir();
+        let file = dir.join("lib.rs");
+        std::fs::write(&file, "const SECRET_MARKER: u8 = 1;\n").unwrap();
+        let mut cache = ScanCache::load(&dir);
+        cache.read(&file, "lib.rs").unwrap();
+        cache.save().unwrap();
+        let stored = std::fs::read_to_string(dir.join("scan_cache.json")).unwrap();
+        assert!(stored.contains("sha256"));
+        assert!(!stored.contains("SECRET_MARKER"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_summaries_are_kept_until_the_file_changes() {
+        let dir = temp_dir();
+        let file = dir.join("lib.rs");
+        std::fs::write(&file, "fn a() {}\n").unwrap();
+        let mut cache = ScanCache::load(&dir);
+        let (content, _) = cache.read(&file, "lib.rs").unwrap();
+        let summary = cache.summary("lib.rs", &content);
+        assert!(summary.contains("a"), "{}", summary);
+        cache.save().unwrap();
+
+        // The stored summary is used, not one of the content given
+        let mut cache = ScanCache::load(&dir);
+        cache.read(&file, "lib.rs").unwrap();
+        assert_eq!(cache.summary("lib.rs", "fn other() {}\n"), summary);
+        cache.save().unwrap();
+
+        std::fs::write(&file, "fn b() {}\nfn c() {}\n").unwrap();
+        let mut cache = ScanCache::load(&dir);
+        let (content, change) = cache.read(&file, "lib.rs").unwrap();
+        assert_eq!(change, FileChange::Changed);
+        assert_ne!(cache.summary("lib.rs", &content), summary);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_cache_starts_empty() {
+        let dir = temp_dir();
+        std::fs::write(dir.join("scan_cache.json"), "{not json").unwrap();
+        assert!(ScanCache::load(&dir).is_empty());
+        assert!(
+            ScanCache::load(&dir)
+                .read(&dir.join("missing.rs"), "missing.rs")
+                .is_err()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scan_cache_detects_changes() {
+        let dir = temp_dir();
+        let file = dir.join("lib.rs");
+        std::fs::write(&file, "fn a() {}\n").unwrap();
+        std::fs::write(dir.join("old.rs"), "fn old() {}\n").unwrap();
+
+        let mut cache = ScanCache::load(&dir);
+        assert_eq!(cache.read(&file, "lib.rs").unwrap().1, FileChange::New);
+        cache.read(&dir.join("old.rs"), "old.rs").unwrap();
+        cache.save().unwrap();
+
+        let mut cache = ScanCache::load(&dir);
+        let (content, change) = cache.read(&file, "lib.rs").unwrap();
+        assert_eq!(
+            (content.as_str(), change),
+            ("fn a() {}\n", FileChange::Unchanged)
+        );
+        assert_eq!(cache.removed(), vec!["old.rs".to_string()]);
+
+        std::fs::write(&file, "fn a() { changed() }\n").unwrap();
+        let mut cache = ScanCache::load(&dir);
+        assert_eq!(cache.read(&file, "lib.rs").unwrap().1, FileChange::Changed);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,676 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::StreamExt;
+use log::{info, warn};
+use serde_json::{Value, json};
+
+use crate::artifact::{ArtifactManager, ArtifactType};
+use crate::config::SecurityConfig;
+use crate::linters::run_tool;
+use crate::lockfiles::{self, Dependency, LOCKFILES};
+use crate::reviewer::{Issue, IssueCategory, IssueSeverity};
+use crate::sandbox::Sandbox;
+
+const OSV_API: &str = "https://api.osv.dev/v1";
+/// Most advisories whose details are fetched from OSV in one scan
+const MAX_OSV_ADVISORIES: usize = 200;
+/// Heading of the scanner results appended to `security_report.md`
+const DEPENDENCY_AUDIT_HEADING: &str = "## Dependency Audit";
+
+/// Run the configured security scanners that apply to the project at `root`
+/// and convert their findings into review issues. Scanners that are not
+/// installed are skipped. With a sandbox they run in its container; OSV
+/// queries are made from the host.
+pub async fn run_security_scanners(
+    root: &Path,
+    config: &SecurityConfig,
+    sandbox: Option<&Sandbox>,
+) -> Vec<Issue> {
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let enabled = |name: &str| config.scanners.iter().any(|s| s == name);
+    let mut findings = Vec::new();
+
+    if enabled("cargo-audit")
+        && root.join("Cargo.lock").exists()
+        && let Some(output) = run_tool(root, "cargo", &["audit", "--json"], timeout, sandbox).await
+    {
+        let found = parse_cargo_audit(&output);
+        info!("cargo audit reported {} findings", found.len());
+        findings.extend(found);
+    }
+
+    if enabled("npm-audit")
+        && root.join("package-lock.json").exists()
+        && let Some(output) = run_tool(root, "npm", &["audit", "--json"], timeout, sandbox).await
+    {
+        let found = parse_npm_audit(&output);
+        info!("npm audit reported {} findings", found.len());
+        findings.extend(found);
+    }
+
+    // OSV covers lockfiles whose audit tool is not installed, and requirements.txt
+    let dependencies = if enabled("osv") {
+        lockfiles::detect_dependencies(root)
+    } else {
+        Vec::new()
+    };
+    if !dependencies.is_empty() {
+        match query_osv(&dependencies, timeout).await {
+            Ok(found) => {
+                // cargo-audit and npm audit report the same advisories
+                let found: Vec<Issue> = found
+                    .into_iter()
+                    .filter(|finding| {
+                        !advisory_ids(finding).iter().any(|id| {
+                            findings
+                                .iter()
+                                .any(|known| advisory_ids(known).contains(id))
+                        })
+                    })
+                    .collect();
+                info!("OSV reported {} new findings", found.len());
+                findings.extend(found);
+            }
+            Err(e) => warn!("OSV query failed: {:#}", e),
+        }
+    }
+
+    if enabled("semgrep")
+        && let Some(output) = run_tool(
+            root,
+            "semgrep",
+            &[
+                "scan",
+                "--json",
+                "--quiet",
+                "--config",
+                &config.semgrep_config,
+            ],
+            timeout,
+            sandbox,
+        )
+        .await
+    {
+        let found = parse_semgrep(&output);
+        info!("semgrep reported {} findings", found.len());
+        findings.extend(found);
+    }
+
+    findings
+}
+
+/// Format scanner findings as a context message for the model to explain and prioritize
+pub fn findings_context(findings: &[Issue]) -> String {
+    let mut out = String::from(
+        "Security scanner findings (cargo-audit, npm audit, OSV, semgrep). These are verified tool \
+         results: explain each one, assess its real impact on this codebase, and prioritize them \
+         in the report, citing the advisory or rule ID. Do not report vulnerabilities that are \
+         not supported by these findings or by code you can point to.\n",
+    );
+    for finding in findings {
+        out.push_str(&format!("- [{}] {}", finding.severity, finding.description));
+        if let Some(location) = &finding.location {
+            out.push_str(&format!(" ({})", location));
+        }
+        if let Some(fix) = &finding.suggestion {
+            out.push_str(&format!(" — fix: {}", fix));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// The advisory and dependency findings as a Markdown section for the report
+pub fn dependency_report(findings: &[Issue]) -> Option<String> {
+    let rows: Vec<String> = findings
+        .iter()
+        .filter(|finding| {
+            finding
+                .location
+                .as_deref()
+                .is_some_and(|location| LOCKFILES.iter().any(|(lockfile, _)| *lockfile == location))
+        })
+        .map(|finding| {
+            let ids = advisory_ids(finding).join(", ");
+            let package = finding
+                .description
+                .split_once("] ")
+                .map_or(finding.description.as_str(), |(_, rest)| rest);
+            let cell = |text: &str| text.replace('|', "\\|").replace('\n', " ");
+            format!(
+                "| {} | {} | {} | {} | {} |",
+                finding.severity,
+                cell(&ids),
+                cell(package),
+                finding.location.as_deref().unwrap_or_default(),
+                cell(finding.suggestion.as_deref().unwrap_or("-"))
+            )
+        })
+        .collect();
+    if rows.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}\n\nAdvisories affecting the versions pinned by the project's lockfiles, as reported \
+         by cargo-audit, npm audit and the OSV database.\n\n\
+         | Severity | Advisory | Package | Lockfile | Fix |\n\
+         |----------|----------|---------|----------|-----|\n{}\n",
+        DEPENDENCY_AUDIT_HEADING,
+        rows.join("\n")
+    ))
+}
+
+/// Append the dependency audit to `security_report.md`, replacing the section
+/// of an earlier run, or create the report if the model did not write one
+pub async fn append_dependency_report(
+    artifact_manager: &ArtifactManager,
+    findings: &[Issue],
+) -> Result<()> {
+    let Some(section) = dependency_report(findings) else {
+        return Ok(());
+    };
+    let report = artifact_manager
+        .list_artifacts()
+        .await
+        .into_iter()
+        .find(|artifact| {
+            artifact
+                .path
+                .file_name()
+                .is_some_and(|name| name == "security_report.md")
+        });
+    match report {
+        Some(report) => {
+            let content = std::fs::read_to_string(&report.path)
+                .ok()
+                .or(report.content)
+                .unwrap_or_default();
+            let analysis = content
+                .find(DEPENDENCY_AUDIT_HEADING)
+                .map_or(content.as_str(), |start| &content[..start]);
+            artifact_manager
+                .update_artifact(
+                    &report.id,
+                    format!("{}\n\n{}", analysis.trim_end(), section),
+                )
+                .await
+        }
+        None => artifact_manager
+            .create_artifact(
+                "security_report.md".to_string(),
+                ArtifactType::Documentation,
+                format!("# Security Report\n\n{}", section),
+                HashMap::new(),
+            )
+            .await
+            .map(|_| ()),
+    }
+}
+
+/// The advisory IDs a finding's description starts with, e.g. `[RUSTSEC-..., CVE-...]`
+fn advisory_ids(finding: &Issue) -> Vec<&str> {
+    finding
+        .description
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map(|(ids, _)| ids.split(", ").collect())
+        .unwrap_or_default()
+}
+
+/// Look the dependencies up in the OSV database
+async fn query_osv(dependencies: &[Dependency], timeout: Duration) -> Result<Vec<Issue>> {
+    let client = reqwest::Client::builder().timeout(timeout).build()?;
+
+    // The batch endpoint only returns the IDs of the advisories affecting each package
+    let mut affected: Vec<(&Dependency, String)> = Vec::new();
+    for chunk in dependencies.chunks(1000) {
+        let queries: Vec<Value> = chunk
+            .iter()
+            .map(|dependency| {
+                json!({
+                    "package": { "name": dependency.name, "ecosystem": dependency.ecosystem },
+                    "version": dependency.version,
+                })
+            })
+            .collect();
+        let response: Value = client
+            .post(format!("{}/querybatch", OSV_API))
+            .json(&json!({ "queries": queries }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let results = response["results"].as_array().into_iter().flatten();
+        for (dependency, result) in chunk.iter().zip(results) {
+            for vuln in result["vulns"].as_array().into_iter().flatten() {
+                if let Some(id) = vuln["id"].as_str() {
+                    affected.push((dependency, id.to_string()));
+                }
+            }
+        }
+    }
+
+    let mut ids: Vec<String> = affected.iter().map(|(_, id)| id.clone()).collect();
+    ids.sort();
+    ids.dedup();
+    if ids.len() > MAX_OSV_ADVISORIES {
+        warn!(
+            "Fetching details of {} of {} OSV advisories",
+            MAX_OSV_ADVISORIES,
+            ids.len()
+        );
+        ids.truncate(MAX_OSV_ADVISORIES);
+    }
+    let details: HashMap<String, Value> = futures::stream::iter(ids)
+        .map(|id| {
+            let client = &client;
+            async move {
+                let vuln = async {
+                    client
+                        .get(format!("{}/vulns/{}", OSV_API, id))
+                        .send()
+                        .await?
+                        .error_for_status()?
+                        .json::<Value>()
+                        .await
+                }
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Failed to fetch OSV advisory {}: {}", id, e);
+                    json!({ "id": id })
+                });
+                (id, vuln)
+            }
+        })
+        .buffer_unordered(8)
+        .collect()
+        .await;
+
+    Ok(affected
+        .into_iter()
+        .filter_map(|(dependency, id)| Some(osv_issue(dependency, details.get(&id)?)))
+        .collect())
+}
+
+/// Convert an OSV advisory affecting `dependency` into a finding
+fn osv_issue(dependency: &Dependency, vuln: &Value) -> Issue {
+    let mut ids = vec![vuln["id"].as_str().unwrap_or("OSV").to_string()];
+    ids.extend(
+        vuln["aliases"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|alias| alias.as_str().map(|s| s.to_string())),
+    );
+    let affected: Vec<&Value> = vuln["affected"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|affected| {
+            affected["package"]["ecosystem"] == dependency.ecosystem
+                && affected["package"]["name"]
+                    .as_str()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(&dependency.name))
+        })
+        .collect();
+
+    // Version ranges are sequences of introduced/fixed events
+    let mut ranges = Vec::new();
+    let mut fixed = Vec::new();
+    for range in affected
+        .iter()
+        .flat_map(|affected| affected["ranges"].as_array().into_iter().flatten())
+        .filter(|range| range["type"] != "GIT")
+    {
+        let mut introduced = None;
+        let lower = |introduced: Option<&str>| match introduced {
+            Some(version) if version != "0" => format!(">={}, ", version),
+            _ => String::new(),
+        };
+        for event in range["events"].as_array().into_iter().flatten() {
+            if let Some(version) = event["introduced"].as_str() {
+                introduced = Some(version);
+            } else if let Some(version) = event["fixed"].as_str() {
+                ranges.push(format!("{}<{}", lower(introduced.take()), version));
+                if !fixed.contains(&version) {
+                    fixed.push(version);
+                }
+            } else if let Some(version) = event["last_affected"].as_str() {
+                ranges.push(format!("{}<={}", lower(introduced.take()), version));
+            }
+        }
+        match introduced {
+            Some("0") => ranges.push("all versions".to_string()),
+            Some(version) => ranges.push(format!(">={}", version)),
+            None => {}
+        }
+    }
+
+    // RUSTSEC marks unmaintained and unsound crates as informational
+    let informational = vuln["database_specific"]["informational"].is_string()
+        || affected
+            .iter()
+            .any(|affected| affected["database_specific"]["informational"].is_string());
+    let (severity, category) = if informational {
+        (IssueSeverity::Minor, IssueCategory::Dependencies)
+    } else {
+        (
+            vuln["database_specific"]["severity"]
+                .as_str()
+                .map_or(IssueSeverity::Major, severity_from_label),
+            IssueCategory::Security,
+        )
+    };
+
+    let summary = vuln["summary"]
+        .as_str()
+        .filter(|summary| !summary.trim().is_empty())
+        .or_else(|| {
+            vuln["details"]
+                .as_str()
+                .and_then(|details| details.lines().next())
+        })
+        .unwrap_or("advisory");
+    let mut description = format!(
+        "[{}] {} {}: {}",
+        ids.join(", "),
+        dependency.name,
+        dependency.version,
+        summary.trim()
+    );
+    if !ranges.is_empty() {
+        description.push_str(&format!(" (affected: {})", ranges.join(" or ")));
+    }
+    Issue {
+        severity,
+        category,
+        description,
+        location: Some(dependency.lockfile.to_string()),
+        suggestion: (!fixed.is_empty()).then(|| format!("Upgrade to {}", fixed.join(" or "))),
+        patch: None,
+    }
+}
+
+fn severity_from_label(label: &str) -> IssueSeverity {
+    match label.to_lowercase().as_str() {
+        "critical" => IssueSeverity::Critical,
+        "high" | "error" => IssueSeverity::Major,
+        "moderate" | "medium" | "warning" => IssueSeverity::Minor,
+        _ => IssueSeverity::Info,
+    }
+}
+
+/// Parse `cargo audit --json` output
+pub fn parse_cargo_audit(output: &str) -> Vec<Issue> {
+    let Ok(report) = serde_json::from_str::<serde_json::Value>(output) else {
+        return Vec::new();
+    };
+
+    let describe = |entry: &serde_json::Value| {
+        let advisory = &entry["advisory"];
+        let mut ids = vec![advisory["id"].as_str().unwrap_or("RUSTSEC").to_string()];
+        ids.extend(
+            advisory["aliases"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|a| a.as_str().map(|s| s.to_string())),
+        );
+        let package = format!(
+            "{} {}",
+            entry["package"]["name"].as_str().unwrap_or_default(),
+            entry["package"]["version"].as_str().unwrap_or_default()
+        );
+        let description = format!(
+            "[{}] {}: {}",
+            ids.join(", "),
+            package.trim(),
+            advisory["title"].as_str().unwrap_or("advisory")
+        );
+        let patched: Vec<&str> = entry["versions"]["patched"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str())
+            .collect();
+        let suggestion = if patched.is_empty() {
+            None
+        } else {
+            Some(format!("Upgrade to {}", patched.join(" or ")))
+        };
+        (description, suggestion)
+    };
+
+    let mut findings = Vec::new();
+    for entry in report["vulnerabilities"]["list"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        let (description, suggestion) = describe(entry);
+        findings.push(Issue {
+            severity: IssueSeverity::Major,
+            category: IssueCategory::Security,
+            description,
+            location: Some("Cargo.lock".to_string()),
+            suggestion,
+            patch: None,
+        });
+    }
+
+    // Unmaintained and yanked crates are reported as warnings
+    if let Some(warnings) = report["warnings"].as_object() {
+        for entry in warnings.values().filter_map(|w| w.as_array()).flatten() {
+            let (description, suggestion) = describe(entry);
+            findings.push(Issue {
+                severity: IssueSeverity::Minor,
+                category: IssueCategory::Dependencies,
+                description,
+                location: Some("Cargo.lock".to_string()),
+                suggestion,
+                patch: None,
+            });
+        }
+    }
+
+    findings
+}
+
+/// Parse `npm audit --json` output (npm 7+ format)
+pub fn parse_npm_audit(output: &str) -> Vec<Issue> {
+    let Ok(report) = serde_json::from_str::<serde_json::Value>(output) else {
+        return Vec::new();
+    };
+    let Some(vulnerabilities) = report["vulnerabilities"].as_object() else {
+        return Vec::new();
+    };
+
+    let mut findings = Vec::new();
+    for (name, vuln) in vulnerabilities {
+        // `via` lists advisories directly, or names of vulnerable dependencies
+        let advisories: Vec<&serde_json::Value> = vuln["via"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|v| v.is_object())
+            .collect();
+        if advisories.is_empty() {
+            continue;
+        }
+
+        for advisory in advisories {
+            let id = advisory["url"]
+                .as_str()
+                .and_then(|url| url.rsplit('/').next())
+                .unwrap_or("npm-advisory");
+            let cwes: Vec<&str> = advisory["cwe"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|c| c.as_str())
+                .collect();
+            let ids = if cwes.is_empty() {
+                id.to_string()
+            } else {
+                format!("{}, {}", id, cwes.join(", "))
+            };
+            findings.push(Issue {
+                severity: severity_from_label(
+                    advisory["severity"]
+                        .as_str()
+                        .or(vuln["severity"].as_str())
+                        .unwrap_or("info"),
+                ),
+                category: IssueCategory::Security,
+                description: format!(
+                    "[{}] {} {}: {}",
+                    ids,
+                    name,
+                    advisory["range"].as_str().unwrap_or_default(),
+                    advisory["title"].as_str().unwrap_or("advisory")
+                ),
+                location: Some("package-lock.json".to_string()),
+                suggestion: match &vuln["fixAvailable"] {
+                    serde_json::Value::Bool(true) => Some("Run `npm audit fix`".to_string()),
+                    serde_json::Value::Object(fix) => Some(format!(
+                        "Upgrade {} to {}",
+                        fix.get("name").and_then(|n| n.as_str()).unwrap_or(name),
+                        fix.get("version")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("a patched version")
+                    )),
+                    _ => None,
+                },
+                patch: None,
+            });
+        }
+    }
+
+    findings
+}
+
+/// Parse `semgrep scan --json` output
+pub fn parse_semgrep(output: &str) -> Vec<Issue> {
+    let Ok(report) = serde_json::from_str::<serde_json::Value>(output) else {
+        return Vec::new();
+    };
+
+    report["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|result| {
+            let extra = &result["extra"];
+            Issue {
+                severity: severity_from_label(extra["severity"].as_str().unwrap_or("info")),
+                category: IssueCategory::Security,
+                description: format!(
+                    "[{}] {}",
+                    result["check_id"].as_str().unwrap_or("semgrep"),
+                    extra["message"].as_str().unwrap_or_default().trim()
+                ),
+                location: Some(format!(
+                    "{}:{}",
+                    result["path"].as_str().unwrap_or_default(),
+                    result["start"]["line"].as_u64().unwrap_or(0)
+                )),
+                suggestion: extra["fix"]
+                    .as_str()
+                    .map(|fix| format!("Replace with `{}`", fix)),
+                patch: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_npm_audit() {
+        let output = r#"{
+            "auditReportVersion": 2,
+            "vulnerabilities": {
+                "lodash": {
+                    "name": "lodash",
+                    "severity": "high",
+                    "via": [{
+                        "title": "Prototype Pollution in lodash",
+                        "url": "https://github.com/advisories/GHSA-p6mc-m468-83gw",
+                        "severity": "high",
+                        "cwe": ["CWE-1321"],
+                        "range": "<4.17.19"
+                    }],
+                    "fixAvailable": true
+                },
+                "app-dep": {
+                    "name": "app-dep",
+                    "severity": "high",
+                    "via": ["lodash"],
+                    "fixAvailable": true
+                }
+            }
+        }"#;
+
+        let findings = parse_npm_audit(output);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, IssueSeverity::Major);
+        assert!(
+            findings[0]
+                .description
+                .starts_with("[GHSA-p6mc-m468-83gw, CWE-1321] lodash")
+        );
+        assert_eq!(
+            findings[0].suggestion.as_deref(),
+            Some("Run `npm audit fix`")
+        );
+    }
+
+    #[test]
+    fn test_osv_issue() {
+        let vuln = serde_json::json!({
+            "id": "GHSA-p6mc-m468-83gw",
+            "aliases": ["CVE-2020-8203"],
+            "summary": "Prototype Pollution in lodash",
+            "database_specific": { "severity": "HIGH" },
+            "affected": [{
+                "package": { "ecosystem": "npm", "name": "lodash" },
+                "ranges": [{
+                    "type": "SEMVER",
+                    "events": [{ "introduced": "3.7.0" }, { "fixed": "4.17.19" }]
+                }]
+            }]
+        });
+        let dependency = Dependency {
+            ecosystem: "npm",
+            name: "lodash".to_string(),
+            version: "4.17.15".to_string(),
+            lockfile: "package-lock.json",
+            license: None,
+        };
+
+        let finding = osv_issue(&dependency, &vuln);
+        assert_eq!(finding.severity, IssueSeverity::Major);
+        assert_eq!(
+            finding.description,
+            "[GHSA-p6mc-m468-83gw, CVE-2020-8203] lodash 4.17.15: Prototype Pollution in lodash \
+             (affected: >=3.7.0, <4.17.19)"
+        );
+        assert_eq!(finding.suggestion.as_deref(), Some("Upgrade to 4.17.19"));
+        assert_eq!(
+            advisory_ids(&finding),
+            vec!["GHSA-p6mc-m468-83gw", "CVE-2020-8203"]
+        );
+
+        let report = dependency_report(&[finding]).unwrap();
+        assert!(report.contains(
+            "| Major | GHSA-p6mc-m468-83gw, CVE-2020-8203 | lodash 4.17.15: Prototype Pollution \
+             in lodash (affected: >=3.7.0, <4.17.19) | package-lock.json | Upgrade to 4.17.19 |"
+        ));
+    }
+}
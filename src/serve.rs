@@ -0,0 +1,134 @@
+use std::net::SocketAddr;
+
+use axum::http::{HeaderMap, header};
+use serde_json::{Value, json};
+use subtle::ConstantTimeEq;
+
+use crate::config::ServeConfig;
+
+/// Error object of a response: the `error` of a JSON-RPC response and the
+/// body of an HTTP API error
+pub fn error_object(code: i64, message: &str) -> Value {
+    json!({ "code": code, "message": message })
+}
+
+/// JSON-RPC error response to the request `id`
+pub fn rpc_error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": error_object(code, message) })
+}
+
+/// Token set by `CLI_ENGINEER_API_TOKEN`, or else `serve.api_token`
+pub fn configured_token(config: &ServeConfig) -> Option<String> {
+    match std::env::var("CLI_ENGINEER_API_TOKEN") {
+        Ok(token) if !token.is_empty() => Some(token),
+        _ => config.api_token.clone().filter(|token| !token.is_empty()),
+    }
+}
+
+/// Random token for a server started without one
+pub fn generate_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Whether `given` is `token`, compared in constant time
+pub fn token_matches(given: &str, token: &str) -> bool {
+    given.as_bytes().ct_eq(token.as_bytes()).into()
+}
+
+/// The bearer token of a request, if it sent one
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Whether a request was sent to the server listening on `address`, by its
+/// `Host` header and, from a browser, its `Origin`. Pages of other sites
+/// send their own origin, and DNS rebinding sends a foreign host.
+pub fn same_origin(address: SocketAddr, headers: &HeaderMap) -> bool {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let Some(host) = header(header::HOST) else {
+        return false;
+    };
+    if !host_allowed(address, host) {
+        return false;
+    }
+    match header(header::ORIGIN) {
+        Some(origin) => origin
+            .strip_prefix("http://")
+            .is_some_and(|origin_host| host_allowed(address, origin_host)),
+        None => true,
+    }
+}
+
+/// Whether `host`, a `Host` header or the host of an origin, names `address`.
+/// Any name is accepted with the right port when listening on every interface.
+fn host_allowed(address: SocketAddr, host: &str) -> bool {
+    let Some((name, port)) = host.rsplit_once(':') else {
+        return false;
+    };
+    if port.parse() != Ok(address.port()) {
+        return false;
+    }
+    let ip = address.ip();
+    if ip.is_unspecified() {
+        return true;
+    }
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    name.parse() == Ok(ip) || (ip.is_loopback() && name.eq_ignore_ascii_case("localhost"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(host: &str, origin: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::HOST, HeaderValue::from_str(host).unwrap());
+        if let Some(origin) = origin {
+            headers.insert(header::ORIGIN, HeaderValue::from_str(origin).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_same_origin_accepts_the_bound_address() {
+        let address: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        assert!(same_origin(address, &headers("127.0.0.1:8080", None)));
+        assert!(same_origin(
+            address,
+            &headers("localhost:8080", Some("http://localhost:8080"))
+        ));
+        let any: SocketAddr = "0.0.0.0:8080".parse().unwrap();
+        assert!(same_origin(any, &headers("agent.internal:8080", None)));
+    }
+
+    #[test]
+    fn test_same_origin_rejects_other_sites_and_rebinding() {
+        let address: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        // DNS rebinding: the attacker's name resolves to the loopback address
+        assert!(!same_origin(address, &headers("evil.example:8080", None)));
+        assert!(!same_origin(address, &headers("localhost:9090", None)));
+        assert!(!same_origin(address, &headers("localhost", None)));
+        assert!(!same_origin(
+            address,
+            &headers("localhost:8080", Some("https://evil.example"))
+        ));
+        assert!(!same_origin(
+            address,
+            &headers("localhost:8080", Some("null"))
+        ));
+        assert!(!same_origin(address, &HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert!(token_matches("secret", "secret"));
+        assert!(!token_matches("secreT", "secret"));
+        assert!(!token_matches("secret2", "secret"));
+        assert_ne!(generate_token(), generate_token());
+        assert_eq!(generate_token().len(), 32);
+    }
+}
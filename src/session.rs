@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use log::info;
+
+use crate::context::{ContextManager, Message};
+use crate::event_bus::{Event, EventBus};
+
+/// Prefix of the summaries written by context compression
+const SUMMARY_PREFIX: &str = "=== Context Summary ===";
+/// Prefix of the task interpretation the agentic loop records for every run
+const TASK_PREFIX: &str = "Task interpreted as:";
+
+/// Context ID a named session is cached under. The cache lives in the project's
+/// `.cli_engineer` directory, so sessions are keyed by project and name.
+pub fn session_context_id(name: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("session_{}", slug)
+}
+
+/// Whether a message is carried into the next run of a session. Scanned files and
+/// tool findings are system messages rebuilt on every run, so only the
+/// conversation, compression summaries and earlier task interpretations are kept.
+pub fn is_session_history(message: &Message) -> bool {
+    message.role != "system"
+        || message.content.starts_with(SUMMARY_PREFIX)
+        || message.content.starts_with(TASK_PREFIX)
+}
+
+/// Resume the named session from the context cache, or start it if it doesn't exist.
+/// Returns the context ID to run with and save afterwards.
+pub async fn open_session(
+    context_manager: &ContextManager,
+    name: &str,
+    event_bus: Arc<EventBus>,
+) -> Result<String> {
+    let id = session_context_id(name);
+
+    let message = match context_manager.load_from_cache(&id).await {
+        Ok(()) => {
+            context_manager
+                .retain_messages(&id, is_session_history)
+                .await?;
+            let count = context_manager.get_messages(&id, None).await?.len();
+            format!(
+                "Resuming session '{}' with {} earlier messages",
+                name, count
+            )
+        }
+        Err(_) => {
+            let metadata = HashMap::from([
+                ("session".to_string(), name.to_string()),
+                (
+                    "project".to_string(),
+                    std::env::current_dir()?.display().to_string(),
+                ),
+            ]);
+            context_manager
+                .create_context_with_id(id.clone(), metadata)
+                .await;
+            format!("Starting new session '{}'", name)
+        }
+    };
+
+    info!("{}", message);
+    let _ = event_bus
+        .emit(Event::LogLine {
+            level: "INFO".to_string(),
+            message,
+        })
+        .await;
+
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_history_filter() {
+        assert_eq!(session_context_id("auth/login v2"), "session_auth_login_v2");
+
+        let message = |role: &str, content: &str| Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            timestamp: chrono::Utc::now(),
+            token_count: None,
+            pinned: false,
+            source: Default::default(),
+            content_hash: 0,
+        };
+        assert!(is_session_history(&message("user", "add a login form")));
+        assert!(is_session_history(&message(
+            "system",
+            "=== Context Summary ===\n- chose JWT\n=== End Summary ==="
+        )));
+        assert!(!is_session_history(&message(
+            "system",
+            "File: src/main.rs\n```rs\nfn main() {}\n```"
+        )));
+    }
+}
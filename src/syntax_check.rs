@@ -0,0 +1,242 @@
+use std::fmt;
+use std::process::Stdio;
+use std::time::Duration;
+
+use log::debug;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tree_sitter::{Language, Node, Parser};
+
+/// How long `python3` or `node` may take to check a file before it is treated
+/// as valid
+const INTERPRETER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A syntax problem found in a generated file
+#[derive(Debug, Clone)]
+pub struct SyntaxError {
+    pub filename: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.filename, line, self.message),
+            None => write!(f, "{}: {}", self.filename, self.message),
+        }
+    }
+}
+
+/// Check generated file content for syntax errors based on its extension.
+///
+/// Rust, JSON, TOML, TypeScript and JSX are parsed in-process. Python and
+/// JavaScript are checked with the local `python3` / `node` interpreters when
+/// they are installed; if the interpreter is missing or does not answer within
+/// `INTERPRETER_TIMEOUT` the file is treated as valid. Unknown extensions are
+/// never rejected.
+pub async fn check_syntax(filename: &str, content: &str) -> Option<SyntaxError> {
+    let extension = filename.rsplit('.').next().unwrap_or("").to_lowercase();
+    let error = |line: Option<usize>, message: String| SyntaxError {
+        filename: filename.to_string(),
+        line,
+        message,
+    };
+
+    match extension.as_str() {
+        "rs" => syn::parse_file(content).err().map(|e| {
+            let line = e.span().start().line;
+            error(if line > 0 { Some(line) } else { None }, e.to_string())
+        }),
+        "json" => serde_json::from_str::<serde_json::Value>(content)
+            .err()
+            .map(|e| error(Some(e.line()), e.to_string())),
+        "toml" => content.parse::<toml::Table>().err().map(|e| {
+            let line = e
+                .span()
+                .map(|span| content[..span.start.min(content.len())].lines().count().max(1));
+            error(line, e.message().to_string())
+        }),
+        "py" => check_python(filename, content)
+            .await
+            .map(|(line, message)| error(line, message)),
+        "js" | "mjs" | "cjs" => check_javascript(&extension, content)
+            .await
+            .map(|(line, message)| error(line, message)),
+        "ts" => check_tree_sitter(tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(), content)
+            .map(|(line, message)| error(Some(line), message)),
+        "tsx" => check_tree_sitter(tree_sitter_typescript::LANGUAGE_TSX.into(), content)
+            .map(|(line, message)| error(Some(line), message)),
+        "jsx" => check_tree_sitter(tree_sitter_javascript::LANGUAGE.into(), content)
+            .map(|(line, message)| error(Some(line), message)),
+        _ => None,
+    }
+}
+
+/// Parse source with a tree-sitter grammar, returning the line and a description
+/// of the first error or missing node
+fn check_tree_sitter(language: Language, content: &str) -> Option<(usize, String)> {
+    let mut parser = Parser::new();
+    parser.set_language(&language).ok()?;
+    let tree = parser.parse(content, None)?;
+    let root = tree.root_node();
+    if !root.has_error() {
+        return None;
+    }
+    let node = first_error(root)?;
+    let line = node.start_position().row + 1;
+    let message = if node.is_missing() {
+        format!("Missing `{}`", node.kind())
+    } else {
+        let text = node.utf8_text(content.as_bytes()).unwrap_or_default();
+        match text.lines().next().map(str::trim).filter(|t| !t.is_empty()) {
+            Some(text) => format!("Unexpected `{}`", text),
+            None => "Syntax error".to_string(),
+        }
+    };
+    Some((line, message))
+}
+
+/// The first error or missing node in source order
+fn first_error(node: Node) -> Option<Node> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+    let mut cursor = node.walk();
+    node.children(&mut cursor)
+        .filter(|child| child.has_error())
+        .find_map(first_error)
+}
+
+/// Parse Python source with `ast.parse` via the local interpreter
+async fn check_python(filename: &str, content: &str) -> Option<(Option<usize>, String)> {
+    let script = "import ast, sys\n\
+                  try:\n    ast.parse(sys.stdin.read(), filename=sys.argv[1])\n\
+                  except SyntaxError as e:\n    print(f\"{e.lineno}|{e.msg}\")\n    sys.exit(1)";
+
+    let mut child = match Command::new("python3")
+        .arg("-c")
+        .arg(script)
+        .arg(filename)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            debug!("python3 unavailable, skipping syntax check for {}: {}", filename, e);
+            return None;
+        }
+    };
+
+    // Dropping the child on a timeout kills it
+    let checked = async {
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(content.as_bytes()).await;
+        }
+        child.wait_with_output().await
+    };
+    let output = match tokio::time::timeout(INTERPRETER_TIMEOUT, checked).await {
+        Ok(output) => output.ok()?,
+        Err(_) => {
+            debug!("python3 timed out, skipping syntax check for {}", filename);
+            return None;
+        }
+    };
+    if output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (line, message) = stdout.trim().split_once('|')?;
+    Some((line.parse().ok(), message.to_string()))
+}
+
+/// Check JavaScript source with `node --check`
+async fn check_javascript(extension: &str, content: &str) -> Option<(Option<usize>, String)> {
+    let temp_path = std::env::temp_dir().join(format!(
+        "cli_engineer_check_{}.{}",
+        uuid::Uuid::new_v4(),
+        extension
+    ));
+    tokio::fs::write(&temp_path, content).await.ok()?;
+
+    let output = Command::new("node")
+        .arg("--check")
+        .arg(&temp_path)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(INTERPRETER_TIMEOUT, output).await;
+    let _ = tokio::fs::remove_file(&temp_path).await;
+
+    let output = match output {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            debug!("node unavailable, skipping JavaScript syntax check: {}", e);
+            return None;
+        }
+        Err(_) => {
+            debug!("node timed out, skipping JavaScript syntax check");
+            return None;
+        }
+    };
+    if output.status.success() {
+        return None;
+    }
+
+    // node prints "<path>:<line>" followed by the offending source and the error
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let temp_str = temp_path.to_string_lossy();
+    let line = stderr
+        .lines()
+        .find_map(|l| l.strip_prefix(temp_str.as_ref()))
+        .and_then(|rest| rest.trim_start_matches(':').parse().ok());
+    let message = stderr
+        .lines()
+        .find(|l| l.contains("Error:"))
+        .unwrap_or("Syntax error")
+        .trim()
+        .to_string();
+    Some((line, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rust_syntax() {
+        assert!(check_syntax("main.rs", "fn main() { println!(\"hi\"); }").await.is_none());
+
+        let err = check_syntax("main.rs", "fn main() {\n    let x = ;\n}")
+            .await
+            .expect("expected a syntax error");
+        assert_eq!(err.line, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_data_formats() {
+        assert!(check_syntax("a.json", "{\"a\": 1}").await.is_none());
+        assert!(check_syntax("a.json", "{\"a\": }").await.is_some());
+        assert!(check_syntax("Cargo.toml", "[package]\nname = \"x\"").await.is_none());
+        assert!(check_syntax("Cargo.toml", "[package\nname = ").await.is_some());
+        assert!(check_syntax("notes.md", "# anything {").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_typescript_syntax() {
+        let valid = "interface User {\n    name: string;\n}\n\nexport function greet(user: User): string {\n    return `Hi ${user.name}`;\n}\n";
+        assert!(check_syntax("greet.ts", valid).await.is_none());
+        assert!(check_syntax("App.tsx", "export const App = () => <div>{1}</div>;\n").await.is_none());
+
+        let broken = "export function greet(name: string): string {\n    return name +;\n}\n";
+        let err = check_syntax("greet.ts", broken)
+            .await
+            .expect("expected a syntax error");
+        assert_eq!(err.line, Some(2));
+        assert!(check_syntax("App.jsx", "const App = () => <div>;\n").await.is_some());
+    }
+}
@@ -0,0 +1,176 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Context, Result};
+use opentelemetry::KeyValue;
+use opentelemetry::metrics::Counter;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::config::TelemetryConfig;
+use crate::event_bus::{Event, EventSink};
+
+/// Exporters installed for the process. Dropping it flushes and shuts them down.
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider,
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            log::warn!("Failed to flush trace export: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            log::warn!("Failed to flush metrics export: {}", e);
+        }
+    }
+}
+
+/// URL of one OTLP/HTTP signal under a collector base URL
+fn signal_endpoint(base: &str, signal: &str) -> String {
+    format!("{}/v1/{}", base.trim_end_matches('/'), signal)
+}
+
+/// Install the OTLP exporters and route `tracing` spans to them. Returns None
+/// when telemetry is disabled.
+pub fn init(config: &TelemetryConfig) -> Result<Option<Telemetry>> {
+    if !config.enabled {
+        return Ok(None);
+    }
+    let resource = Resource::builder()
+        .with_service_name(config.service_name.clone())
+        .build();
+
+    let mut spans = SpanExporter::builder().with_http();
+    if let Some(base) = &config.endpoint {
+        spans = spans.with_endpoint(signal_endpoint(base, "traces"));
+    }
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_batch_exporter(
+            spans
+                .build()
+                .context("Failed to create OTLP span exporter")?,
+        )
+        .with_resource(resource.clone())
+        .build();
+
+    let mut metrics = MetricExporter::builder().with_http();
+    if let Some(base) = &config.endpoint {
+        metrics = metrics.with_endpoint(signal_endpoint(base, "metrics"));
+    }
+    let meter_provider = SdkMeterProvider::builder()
+        .with_periodic_exporter(
+            metrics
+                .build()
+                .context("Failed to create OTLP metric exporter")?,
+        )
+        .with_resource(resource)
+        .build();
+    opentelemetry::global::set_meter_provider(meter_provider.clone());
+
+    let tracer = tracer_provider.tracer("cli_engineer");
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+        .context("Failed to install the tracing subscriber")?;
+
+    Ok(Some(Telemetry {
+        tracer_provider,
+        meter_provider,
+    }))
+}
+
+/// Event sink turning API calls, artifacts and the run's outcome into OTel
+/// counters. Token counts and cost are also recorded on the `llm.call` span
+/// the event is emitted from.
+pub struct TelemetryMetrics {
+    api_calls: Counter<u64>,
+    api_errors: Counter<u64>,
+    tokens: Counter<u64>,
+    cost: Counter<f64>,
+    artifacts: Counter<u64>,
+    runs: Counter<u64>,
+    /// Set once the run's outcome is counted; later outcome events repeat it
+    finished: AtomicBool,
+}
+
+impl TelemetryMetrics {
+    pub fn new() -> Self {
+        let meter = opentelemetry::global::meter("cli_engineer");
+        Self {
+            api_calls: meter.u64_counter("cli_engineer.api_calls").build(),
+            api_errors: meter.u64_counter("cli_engineer.api_errors").build(),
+            tokens: meter.u64_counter("cli_engineer.tokens").build(),
+            cost: meter
+                .f64_counter("cli_engineer.cost")
+                .with_unit("USD")
+                .build(),
+            artifacts: meter.u64_counter("cli_engineer.artifacts").build(),
+            runs: meter.u64_counter("cli_engineer.runs").build(),
+            finished: AtomicBool::new(false),
+        }
+    }
+}
+
+impl EventSink for TelemetryMetrics {
+    fn handle(&self, event: &Event) {
+        match event {
+            Event::APICallCompleted {
+                provider,
+                tokens,
+                cost,
+                ..
+            } => {
+                let attributes = [KeyValue::new("provider", provider.clone())];
+                self.api_calls.add(1, &attributes);
+                self.tokens.add(*tokens as u64, &attributes);
+                self.cost.add(*cost as f64, &attributes);
+                let span = tracing::Span::current();
+                span.record("llm.tokens", *tokens as u64);
+                span.record("llm.cost", *cost as f64);
+            }
+            Event::APIError { provider, .. } => {
+                self.api_errors
+                    .add(1, &[KeyValue::new("provider", provider.clone())]);
+            }
+            Event::ArtifactCreated { artifact_type, .. } => {
+                self.artifacts
+                    .add(1, &[KeyValue::new("type", artifact_type.clone())]);
+            }
+            Event::TaskCompleted { .. } | Event::TaskFailed { .. }
+                if !self.finished.swap(true, Ordering::Relaxed) =>
+            {
+                let status = if matches!(event, Event::TaskCompleted { .. }) {
+                    "completed"
+                } else {
+                    "failed"
+                };
+                self.runs.add(1, &[KeyValue::new("status", status)]);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_endpoint() {
+        assert_eq!(
+            signal_endpoint("http://collector:4318/", "traces"),
+            "http://collector:4318/v1/traces"
+        );
+        assert_eq!(
+            signal_endpoint("https://otlp.example.com/otlp", "metrics"),
+            "https://otlp.example.com/otlp/v1/metrics"
+        );
+        assert!(init(&TelemetryConfig::default()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,263 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Resets all colors and styles
+const RESET: &str = "\u{1b}[0m";
+
+/// A piece of terminal text: an escape sequence or a single character
+enum Segment<'a> {
+    Escape(&'a str),
+    Char(char),
+}
+
+/// Split `text` into escape sequences and the characters between them
+fn segments(text: &str) -> impl Iterator<Item = Segment<'_>> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let mut chars = rest.char_indices();
+        let (_, first) = chars.next()?;
+        let end = if first == '\u{1b}' {
+            match chars.next() {
+                // CSI: parameters and intermediates up to a final byte
+                Some((_, '[')) => chars
+                    .find(|(_, c)| ('\u{40}'..='\u{7e}').contains(c))
+                    .map_or(rest.len(), |(i, c)| i + c.len_utf8()),
+                // OSC, e.g. hyperlinks: up to BEL or ESC \
+                Some((_, ']')) => {
+                    let body = &rest[2..];
+                    match (body.find('\u{7}'), body.find("\u{1b}\\")) {
+                        (Some(bel), Some(st)) if st < bel => 2 + st + 2,
+                        (Some(bel), _) => 2 + bel + 1,
+                        (None, Some(st)) => 2 + st + 2,
+                        (None, None) => rest.len(),
+                    }
+                }
+                Some((i, c)) => i + c.len_utf8(),
+                None => rest.len(),
+            }
+        } else {
+            first.len_utf8()
+        };
+        let (segment, remaining) = rest.split_at(end);
+        rest = remaining;
+        Some(if first == '\u{1b}' {
+            Segment::Escape(segment)
+        } else {
+            Segment::Char(first)
+        })
+    })
+}
+
+fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// Whether `escape` sets colors or styles, as opposed to moving the cursor
+fn is_style(escape: &str) -> bool {
+    escape.starts_with("\u{1b}[") && escape.ends_with('m')
+}
+
+fn is_reset(escape: &str) -> bool {
+    escape == "\u{1b}[m" || escape == RESET
+}
+
+/// `text` without its escape sequences
+pub fn strip_ansi(text: &str) -> String {
+    segments(text)
+        .filter_map(|segment| match segment {
+            Segment::Char(c) => Some(c),
+            Segment::Escape(_) => None,
+        })
+        .collect()
+}
+
+/// Columns `text` takes on a terminal: wide characters such as CJK and most
+/// emoji take two, escape sequences and combining marks none
+pub fn display_width(text: &str) -> usize {
+    strip_ansi(text).width()
+}
+
+/// `text` cut to `max_width` columns, ending in `...` when cut. Colors are
+/// kept and reset after the cut.
+pub fn truncate(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_string();
+    }
+    let ellipsis = if max_width >= 3 { "..." } else { "" };
+    let budget = max_width - ellipsis.len();
+
+    let mut result = String::new();
+    let mut width = 0;
+    let mut styled = false;
+    for segment in segments(text) {
+        match segment {
+            Segment::Escape(escape) => {
+                if is_style(escape) {
+                    styled = !is_reset(escape);
+                    result.push_str(escape);
+                }
+            }
+            Segment::Char(c) => {
+                let w = char_width(c);
+                if width + w > budget {
+                    break;
+                }
+                width += w;
+                result.push(c);
+            }
+        }
+    }
+    if styled {
+        result.push_str(RESET);
+    }
+    result.push_str(ellipsis);
+    result
+}
+
+/// Lines of wrapped text, carrying colors over each line break so every line
+/// can be drawn on its own
+struct LineBuilder {
+    lines: Vec<String>,
+    line: String,
+    width: usize,
+    /// Style sequences in effect since the last reset
+    active: Vec<String>,
+}
+
+impl LineBuilder {
+    fn push_escape(&mut self, escape: &str) {
+        if !is_style(escape) {
+            return;
+        }
+        if is_reset(escape) {
+            self.active.clear();
+        } else {
+            self.active.push(escape.to_string());
+        }
+        self.line.push_str(escape);
+    }
+
+    fn push_char(&mut self, c: char) {
+        self.line.push(c);
+        self.width += char_width(c);
+    }
+
+    fn break_line(&mut self) {
+        let mut line = std::mem::take(&mut self.line);
+        if !self.active.is_empty() {
+            line.push_str(RESET);
+        }
+        self.lines.push(line);
+        self.line = self.active.concat();
+        self.width = 0;
+    }
+}
+
+/// Wrap `text` at word boundaries to lines of at most `max_width` columns,
+/// breaking words too long for a line of their own. Runs of whitespace become
+/// single spaces; escape sequences other than colors and styles are dropped.
+pub fn wrap(text: &str, max_width: usize) -> Vec<String> {
+    let max_width = max_width.max(1);
+    let mut builder = LineBuilder {
+        lines: Vec::new(),
+        line: String::new(),
+        width: 0,
+        active: Vec::new(),
+    };
+
+    for word in split_words(text) {
+        let word_width = display_width(word);
+        if builder.width > 0 {
+            if builder.width + 1 + word_width <= max_width {
+                builder.push_char(' ');
+            } else {
+                builder.break_line();
+            }
+        }
+        for segment in segments(word) {
+            match segment {
+                Segment::Escape(escape) => builder.push_escape(escape),
+                Segment::Char(c) => {
+                    if builder.width > 0 && builder.width + char_width(c) > max_width {
+                        builder.break_line();
+                    }
+                    builder.push_char(c);
+                }
+            }
+        }
+    }
+
+    if builder.width > 0 || builder.lines.is_empty() {
+        builder.break_line();
+    }
+    builder.lines
+}
+
+/// Words of `text` with the escape sequences before and inside them
+fn split_words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut offset = 0;
+    for segment in segments(text) {
+        let len = match segment {
+            Segment::Escape(escape) => escape.len(),
+            Segment::Char(c) => c.len_utf8(),
+        };
+        match segment {
+            Segment::Char(c) if c.is_whitespace() => {
+                if let Some(word_start) = start.take() {
+                    words.push(&text[word_start..offset]);
+                }
+            }
+            _ => {
+                start.get_or_insert(offset);
+            }
+        }
+        offset += len;
+    }
+    if let Some(word_start) = start {
+        words.push(&text[word_start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width_aware_wrapping() {
+        let red = "\u{1b}[31m";
+        assert_eq!(
+            strip_ansi(&format!("{}error{} \u{1b}]8;;x\u{7}ok", red, RESET)),
+            "error ok"
+        );
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("🤔 🚀 ✅"), 8);
+        assert_eq!(display_width(&format!("{}abc{}", red, RESET)), 3);
+
+        assert_eq!(truncate("日本語のテキスト", 9), "日本語...");
+        assert_eq!(truncate("short", 9), "short");
+        assert_eq!(
+            truncate(&format!("{}abcdefgh", red), 6),
+            format!("{}abc{}...", red, RESET)
+        );
+
+        assert_eq!(wrap("one two three", 7), ["one two", "three"]);
+        assert_eq!(wrap("", 10), [""]);
+        // CJK has no spaces to break at
+        assert_eq!(wrap("日本語のテキスト", 6), ["日本語", "のテキ", "スト"]);
+        for line in wrap("🚀 launch 🎯 the 日本語 rocket into orbit", 9) {
+            assert!(display_width(&line) <= 9, "{}", line);
+        }
+
+        // Colors carry over line breaks
+        let lines = wrap(&format!("{}red words here{} plain", red, RESET), 10);
+        assert_eq!(
+            lines,
+            [
+                format!("{}red words{}", red, RESET),
+                format!("{}here{} plain", red, RESET)
+            ]
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::{Result, anyhow, bail};
+use colored::{ColoredString, Colorize};
+use ratatui::style::{Color, Modifier, Style};
+
+use crate::config::UIConfig;
+
+/// Presets `[ui.theme] preset` can name
+const PRESETS: &[&str] = &["dark", "light", "mono"];
+
+/// Elements `[ui.theme.colors]` can set
+const ELEMENTS: &[&str] = &[
+    "border",
+    "title",
+    "text",
+    "muted",
+    "accent",
+    "highlight",
+    "success",
+    "warning",
+    "error",
+];
+
+/// Colors of what the dashboard and text UI draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    /// Off under `NO_COLOR`, `ui.colorful = false` or the mono preset, leaving
+    /// only bold and reversed text
+    pub colored: bool,
+    /// Box borders and rules
+    pub border: Color,
+    pub title: Color,
+    pub text: Color,
+    /// Hints, pending steps, reasoning and other secondary text
+    pub muted: Color,
+    /// Phase, info logs and context usage
+    pub accent: Color,
+    /// The current task and running step
+    pub highlight: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub error: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// Light text on dark terminal backgrounds
+    pub fn dark() -> Self {
+        Self {
+            colored: true,
+            border: Color::LightBlue,
+            title: Color::White,
+            text: Color::White,
+            muted: Color::DarkGray,
+            accent: Color::Cyan,
+            highlight: Color::Yellow,
+            success: Color::Green,
+            warning: Color::Yellow,
+            error: Color::Red,
+        }
+    }
+
+    /// Dark text on light terminal backgrounds
+    pub fn light() -> Self {
+        Self {
+            colored: true,
+            border: Color::Blue,
+            title: Color::Black,
+            text: Color::Black,
+            muted: Color::DarkGray,
+            accent: Color::Blue,
+            highlight: Color::Magenta,
+            success: Color::Green,
+            warning: Color::Rgb(175, 95, 0),
+            error: Color::Red,
+        }
+    }
+
+    /// The terminal's own colors only
+    pub fn mono() -> Self {
+        Self {
+            colored: false,
+            border: Color::Reset,
+            title: Color::Reset,
+            text: Color::Reset,
+            muted: Color::Reset,
+            accent: Color::Reset,
+            highlight: Color::Reset,
+            success: Color::Reset,
+            warning: Color::Reset,
+            error: Color::Reset,
+        }
+    }
+
+    fn preset(name: &str) -> Result<Self> {
+        match name {
+            "dark" => Ok(Self::dark()),
+            "light" => Ok(Self::light()),
+            "mono" => Ok(Self::mono()),
+            _ => bail!(
+                "Unknown theme preset '{}'; expected one of {}",
+                name,
+                PRESETS.join(", ")
+            ),
+        }
+    }
+
+    /// Theme from `[ui.theme]`, or no colors at all when `NO_COLOR` is set or
+    /// `ui.colorful` is off
+    pub fn from_config(config: &UIConfig) -> Result<Self> {
+        let mut theme = Self::preset(&config.theme.preset)?;
+        for (element, value) in &config.theme.colors {
+            let color = value.parse().map_err(|_| {
+                anyhow!("Invalid color '{}' for ui.theme.colors.{}", value, element)
+            })?;
+            let slot = theme.element_mut(element).ok_or_else(|| {
+                anyhow!(
+                    "Unknown theme element '{}'; expected one of {}",
+                    element,
+                    ELEMENTS.join(", ")
+                )
+            })?;
+            *slot = color;
+        }
+
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        if no_color || !config.colorful {
+            theme = Self::mono();
+        }
+        Ok(theme)
+    }
+
+    fn element_mut(&mut self, element: &str) -> Option<&mut Color> {
+        Some(match element {
+            "border" => &mut self.border,
+            "title" => &mut self.title,
+            "text" => &mut self.text,
+            "muted" => &mut self.muted,
+            "accent" => &mut self.accent,
+            "highlight" => &mut self.highlight,
+            "success" => &mut self.success,
+            "warning" => &mut self.warning,
+            "error" => &mut self.error,
+            _ => return None,
+        })
+    }
+
+    /// Turn colors off in everything else printed, progress bars included,
+    /// when the theme has none
+    pub fn apply_to_output(&self) {
+        if !self.colored {
+            colored::control::set_override(false);
+            console::set_colors_enabled(false);
+            console::set_colors_enabled_stderr(false);
+        }
+    }
+
+    /// Text in `color`
+    pub fn fg(&self, color: Color) -> Style {
+        if self.colored {
+            Style::default().fg(color)
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Badges such as PAUSED: dark text on `color`, or reversed text
+    pub fn badge(&self, color: Color) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        if self.colored {
+            style.fg(Color::Black).bg(color)
+        } else {
+            style.add_modifier(Modifier::REVERSED)
+        }
+    }
+
+    /// `text` in `color` for line-based output
+    pub fn paint(&self, text: &str, color: Color) -> ColoredString {
+        match terminal_color(color).filter(|_| self.colored) {
+            Some(color) => text.color(color),
+            None => text.normal(),
+        }
+    }
+
+    /// Progress bar template placeholder such as `{spinner:.green}`, with `alt`
+    /// for the unfilled part of bars
+    pub fn placeholder(&self, key: &str, color: Color, alt: Option<Color>) -> String {
+        let style = match (dotted_style(color), alt.map(dotted_style)) {
+            _ if !self.colored => None,
+            (Some(style), Some(Some(alt))) => Some(format!("{}/{}", style, alt)),
+            (style, _) => style,
+        };
+        match style {
+            Some(style) => format!("{{{}:.{}}}", key, style),
+            None => format!("{{{}}}", key),
+        }
+    }
+}
+
+/// `color` for the `colored` crate, which has no 256-color palette beyond
+/// the 16 basic colors
+fn terminal_color(color: Color) -> Option<colored::Color> {
+    use colored::Color as C;
+    Some(match color {
+        Color::Reset => return None,
+        Color::Black | Color::Indexed(0) => C::Black,
+        Color::Red | Color::Indexed(1) => C::Red,
+        Color::Green | Color::Indexed(2) => C::Green,
+        Color::Yellow | Color::Indexed(3) => C::Yellow,
+        Color::Blue | Color::Indexed(4) => C::Blue,
+        Color::Magenta | Color::Indexed(5) => C::Magenta,
+        Color::Cyan | Color::Indexed(6) => C::Cyan,
+        Color::Gray | Color::Indexed(7) => C::White,
+        Color::DarkGray | Color::Indexed(8) => C::BrightBlack,
+        Color::LightRed | Color::Indexed(9) => C::BrightRed,
+        Color::LightGreen | Color::Indexed(10) => C::BrightGreen,
+        Color::LightYellow | Color::Indexed(11) => C::BrightYellow,
+        Color::LightBlue | Color::Indexed(12) => C::BrightBlue,
+        Color::LightMagenta | Color::Indexed(13) => C::BrightMagenta,
+        Color::LightCyan | Color::Indexed(14) => C::BrightCyan,
+        Color::White | Color::Indexed(15) => C::BrightWhite,
+        Color::Rgb(r, g, b) => C::TrueColor { r, g, b },
+        Color::Indexed(_) => return None,
+    })
+}
+
+/// `color` as a progress bar template style; progress bars take the 16 basic
+/// colors and the 256-color palette
+fn dotted_style(color: Color) -> Option<String> {
+    let name = match color {
+        Color::Black => "black",
+        Color::Red => "red",
+        Color::Green => "green",
+        Color::Yellow => "yellow",
+        Color::Blue => "blue",
+        Color::Magenta => "magenta",
+        Color::Cyan => "cyan",
+        Color::Gray => "white",
+        Color::DarkGray => "black.bright",
+        Color::LightRed => "red.bright",
+        Color::LightGreen => "green.bright",
+        Color::LightYellow => "yellow.bright",
+        Color::LightBlue => "blue.bright",
+        Color::LightMagenta => "magenta.bright",
+        Color::LightCyan => "cyan.bright",
+        Color::White => "white.bright",
+        Color::Indexed(index) => return Some(index.to_string()),
+        Color::Reset | Color::Rgb(..) => return None,
+    };
+    Some(name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_theme_from_config() {
+        let mut ui = Config::default().ui;
+        ui.theme.preset = "light".to_string();
+        ui.theme
+            .colors
+            .insert("border".to_string(), "#102030".to_string());
+        ui.theme.colors.insert("error".to_string(), "9".to_string());
+        // Without NO_COLOR in the environment the colors apply
+        if std::env::var_os("NO_COLOR").is_none() {
+            let theme = Theme::from_config(&ui).unwrap();
+            assert_eq!(theme.border, Color::Rgb(16, 32, 48));
+            assert_eq!(theme.error, Color::Indexed(9));
+            assert_eq!(theme.text, Theme::light().text);
+            assert_eq!(theme.fg(theme.success), Style::default().fg(Color::Green));
+        }
+
+        ui.colorful = false;
+        let theme = Theme::from_config(&ui).unwrap();
+        assert_eq!(theme, Theme::mono());
+        assert_eq!(theme.fg(Color::Red), Style::default());
+        assert!(
+            theme
+                .badge(Color::Yellow)
+                .add_modifier
+                .contains(Modifier::REVERSED)
+        );
+        assert_eq!(theme.paint("plain", Color::Red).fgcolor, None);
+
+        ui.theme
+            .colors
+            .insert("frame".to_string(), "red".to_string());
+        assert!(Theme::from_config(&ui).is_err());
+        ui.theme.colors.clear();
+        ui.theme
+            .colors
+            .insert("text".to_string(), "not-a-color".to_string());
+        assert!(Theme::from_config(&ui).is_err());
+        ui.theme.colors.clear();
+        ui.theme.preset = "neon".to_string();
+        assert!(Theme::from_config(&ui).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::event_bus::{Event, EventBus};
+
+/// How often a stream reports its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Completed calls the rolling average covers
+const ROLLING_CALLS: usize = 10;
+
+/// A call without new output for this long may be hung
+pub const STALL_AFTER: Duration = Duration::from_secs(15);
+
+/// Rough token count of `bytes` of text, as estimated for cost
+fn estimate_tokens(bytes: usize) -> usize {
+    bytes / 4
+}
+
+/// Times a provider's streamed response and reports its progress as
+/// `APICallProgress` events
+pub struct StreamMeter {
+    provider: String,
+    event_bus: Option<Arc<EventBus>>,
+    started: Instant,
+    first_output: Option<Duration>,
+    output_bytes: usize,
+    last_progress: Option<Instant>,
+}
+
+impl StreamMeter {
+    /// Start timing a request to `provider`
+    pub fn start(provider: &str, event_bus: Option<Arc<EventBus>>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            event_bus,
+            started: Instant::now(),
+            first_output: None,
+            output_bytes: 0,
+            last_progress: None,
+        }
+    }
+
+    /// Count streamed output, reporting the progress at most every
+    /// `PROGRESS_INTERVAL`
+    pub async fn record(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let elapsed = self.started.elapsed();
+        self.first_output.get_or_insert(elapsed);
+        self.output_bytes += text.len();
+
+        let due = self
+            .last_progress
+            .is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL);
+        if let Some(bus) = &self.event_bus
+            && due
+        {
+            self.last_progress = Some(Instant::now());
+            let _ = bus
+                .emit(Event::APICallProgress {
+                    provider: self.provider.clone(),
+                    output_tokens: estimate_tokens(self.output_bytes),
+                    elapsed_ms: elapsed.as_millis() as u64,
+                })
+                .await;
+        }
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    pub fn first_token_ms(&self) -> Option<u64> {
+        self.first_output.map(|first| first.as_millis() as u64)
+    }
+}
+
+/// Tokens per second generated, not counting the wait for the first token
+fn rate(output_tokens: usize, duration: Duration, first_token: Option<Duration>) -> Option<f64> {
+    let generating = duration.saturating_sub(first_token.unwrap_or_default());
+    // Too short to measure, e.g. a response that arrived in one chunk
+    let generating = if generating < Duration::from_millis(200) {
+        duration
+    } else {
+        generating
+    };
+    (output_tokens > 0 && !generating.is_zero())
+        .then(|| output_tokens as f64 / generating.as_secs_f64())
+}
+
+/// How the call in progress is doing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallState {
+    /// No output yet, for this long
+    Waiting(Duration),
+    /// Output arriving at `rate` tokens per second
+    Streaming { rate: f64, elapsed: Duration },
+    /// No new output for `STALL_AFTER` or longer, after `elapsed` in total
+    Stalled { elapsed: Duration },
+}
+
+/// The call in progress
+struct ActiveCall {
+    started: Instant,
+    output_tokens: usize,
+    /// Time into the call of its first and latest progress
+    first_output: Option<Duration>,
+    latest_output: Option<Duration>,
+}
+
+/// Duration and output of a completed call
+struct CallTiming {
+    duration: Duration,
+    output_tokens: usize,
+    first_token: Option<Duration>,
+}
+
+/// Throughput and latency of API calls, from their start, progress and
+/// completion events
+#[derive(Default)]
+pub struct Throughput {
+    active: Option<ActiveCall>,
+    recent: VecDeque<CallTiming>,
+}
+
+impl Throughput {
+    pub fn apply(&mut self, event: &Event) {
+        match event {
+            Event::APICallStarted { .. } => {
+                self.active = Some(ActiveCall {
+                    started: Instant::now(),
+                    output_tokens: 0,
+                    first_output: None,
+                    latest_output: None,
+                });
+            }
+            Event::APICallProgress {
+                output_tokens,
+                elapsed_ms,
+                ..
+            } => {
+                let elapsed = Duration::from_millis(*elapsed_ms);
+                let active = self.active.get_or_insert_with(|| ActiveCall {
+                    started: Instant::now() - elapsed,
+                    output_tokens: 0,
+                    first_output: None,
+                    latest_output: None,
+                });
+                active.output_tokens = *output_tokens;
+                active.first_output.get_or_insert(elapsed);
+                active.latest_output = Some(elapsed);
+            }
+            Event::APICallCompleted {
+                duration_ms,
+                output_tokens,
+                first_token_ms,
+                ..
+            } => {
+                self.active = None;
+                // Older event logs have no timings
+                if *duration_ms > 0 {
+                    if self.recent.len() == ROLLING_CALLS {
+                        self.recent.pop_front();
+                    }
+                    self.recent.push_back(CallTiming {
+                        duration: Duration::from_millis(*duration_ms),
+                        output_tokens: *output_tokens,
+                        first_token: first_token_ms.map(Duration::from_millis),
+                    });
+                }
+            }
+            Event::APIError { .. } => self.active = None,
+            _ => {}
+        }
+    }
+
+    /// State of the call in progress, if any
+    pub fn active(&self) -> Option<CallState> {
+        let active = self.active.as_ref()?;
+        let elapsed = active.started.elapsed();
+        Some(match active.latest_output {
+            None => CallState::Waiting(elapsed),
+            Some(latest) if elapsed.saturating_sub(latest) >= STALL_AFTER => {
+                CallState::Stalled { elapsed }
+            }
+            Some(latest) => CallState::Streaming {
+                rate: rate(active.output_tokens, latest, active.first_output).unwrap_or(0.0),
+                elapsed,
+            },
+        })
+    }
+
+    /// Duration of the last completed call
+    pub fn last_latency(&self) -> Option<Duration> {
+        self.recent.back().map(|call| call.duration)
+    }
+
+    /// Tokens per second over the last `ROLLING_CALLS` calls
+    pub fn average_rate(&self) -> Option<f64> {
+        let rates: Vec<f64> = self
+            .recent
+            .iter()
+            .filter_map(|call| rate(call.output_tokens, call.duration, call.first_token))
+            .collect();
+        (!rates.is_empty()).then(|| rates.iter().sum::<f64>() / rates.len() as f64)
+    }
+
+    /// Average duration of the last `ROLLING_CALLS` calls
+    pub fn average_latency(&self) -> Option<Duration> {
+        let total: Duration = self.recent.iter().map(|call| call.duration).sum();
+        (!self.recent.is_empty()).then(|| total / self.recent.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn completed(duration_ms: u64, output_tokens: usize, first_token_ms: Option<u64>) -> Event {
+        Event::APICallCompleted {
+            provider: "anthropic".to_string(),
+            tokens: output_tokens * 2,
+            cost: 0.0,
+            duration_ms,
+            output_tokens,
+            first_token_ms,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_throughput() {
+        let bus = Arc::new(EventBus::new(10));
+        let mut receiver = bus.subscribe();
+        let mut meter = StreamMeter::start("anthropic", Some(bus.clone()));
+        meter.record("").await;
+        meter.record(&"x".repeat(400)).await;
+        // Within the progress interval only the count changes
+        meter.record(&"x".repeat(400)).await;
+        assert!(meter.first_token_ms().is_some());
+        let progress = receiver.recv().await.unwrap();
+        assert!(matches!(
+            progress,
+            Event::APICallProgress {
+                output_tokens: 100,
+                ..
+            }
+        ));
+        assert!(receiver.try_recv().is_err());
+
+        let mut throughput = Throughput::default();
+        assert_eq!(throughput.active(), None);
+        throughput.apply(&Event::APICallStarted {
+            provider: "anthropic".to_string(),
+            model: "claude".to_string(),
+        });
+        assert!(matches!(throughput.active(), Some(CallState::Waiting(_))));
+        throughput.apply(&progress);
+        throughput.apply(&Event::APICallProgress {
+            provider: "anthropic".to_string(),
+            output_tokens: 300,
+            elapsed_ms: 3_000,
+        });
+        match throughput.active() {
+            Some(CallState::Streaming { rate, .. }) => {
+                assert!((rate - 100.0).abs() < 1.0, "{}", rate)
+            }
+            state => panic!("{:?}", state),
+        }
+
+        // 400 tokens in 4s after a 1s wait, then 100 tokens in 1s
+        throughput.apply(&completed(5_000, 400, Some(1_000)));
+        throughput.apply(&completed(1_000, 100, None));
+        throughput.apply(&completed(0, 100, None));
+        assert_eq!(throughput.active(), None);
+        assert_eq!(throughput.last_latency(), Some(Duration::from_secs(1)));
+        assert_eq!(throughput.average_rate(), Some(100.0));
+        assert_eq!(throughput.average_latency(), Some(Duration::from_secs(3)));
+    }
+}
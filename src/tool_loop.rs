@@ -0,0 +1,919 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use ignore::WalkBuilder;
+use log::{info, warn};
+use serde_json::{Value, json};
+use tokio::process::Command;
+
+use crate::artifact::{ArtifactManager, ArtifactType, add_tags};
+use crate::config::Config;
+use crate::event_bus::{Event, EventBus};
+use crate::executor::extract_tool_call_tags;
+use crate::formatter::format_content;
+use crate::interpreter::Task;
+use crate::iteration_context::IterationContext;
+use crate::llm_manager::{LLMManager, ToolCall, ToolMessage, ToolResponse, ToolSpec};
+use crate::planner::{ComplexityLevel, Plan, Step, StepCategory};
+use crate::redaction::Redactor;
+use crate::run_control::RunControl;
+use crate::sandbox::Sandbox;
+
+/// Id of the single step of an iteration in the tools mode
+pub const TOOLS_STEP_ID: &str = "tools";
+/// Most characters of a tool result passed back to the model
+const MAX_RESULT_CHARS: usize = 20_000;
+/// Most lines a search returns
+const MAX_SEARCH_MATCHES: usize = 100;
+/// Most project files listed in the prompt
+const MAX_LISTED_FILES: usize = 200;
+/// Files larger than this are not searched
+const MAX_SEARCH_FILE_BYTES: u64 = 1_000_000;
+
+const TOOLS_PROMPT: &str =
+    "You are an expert software engineer working on a task in a project with tools.";
+
+/// How to call tools without a tool calling API, followed by the list of tools
+const TOOL_CALL_SYNTAX: &str = r#"Call a tool with a tag holding its arguments as a JSON object:
+<tool_call tool="read_file">{"path": "src/main.rs"}</tool_call>
+
+Tools:"#;
+
+const TOOLS_GUIDANCE: &str = "Paths are relative to the project root. Make several calls in one \
+response when they do not depend on each other. Look before you change: read the code involved \
+and search for its uses. Call finish once the task is done.";
+
+/// The tools offered to the model; run_command only when it is enabled
+fn tool_specs(run_commands: bool) -> Vec<ToolSpec> {
+    let string = |description: &str| json!({"type": "string", "description": description});
+    let object = |properties: Value, required: &[&str]| {
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    };
+    let mut specs = vec![
+        ToolSpec {
+            name: "read_file",
+            description: "The content of a file, as you wrote it if you did, otherwise as it is \
+                in the project",
+            parameters: object(json!({"path": string("File to read")}), &["path"]),
+        },
+        ToolSpec {
+            name: "write_file",
+            description: "Write the complete new content of a file",
+            parameters: object(
+                json!({"path": string("File to write"), "content": string("Its whole content")}),
+                &["path", "content"],
+            ),
+        },
+        ToolSpec {
+            name: "search",
+            description: "Lines matching a regular expression, in the whole project or under \
+                `path`",
+            parameters: object(
+                json!({
+                    "pattern": string("Regular expression"),
+                    "path": string("Directory or file to search in"),
+                }),
+                &["pattern"],
+            ),
+        },
+    ];
+    if run_commands {
+        specs.push(ToolSpec {
+            name: "run_command",
+            description: "Run a shell command and get its exit status and output. Commands run \
+                in the directory the files you write are saved to",
+            parameters: object(json!({"command": string("Shell command")}), &["command"]),
+        });
+    }
+    specs.push(ToolSpec {
+        name: "finish",
+        description: "End your work on the task, with a summary of what you did",
+        parameters: object(json!({"summary": string("What you did")}), &["summary"]),
+    });
+    specs
+}
+
+/// What a tool session did
+pub struct ToolOutcome {
+    /// The model's summary when it finished, else its last response
+    pub summary: String,
+    /// Whether the model called `finish` before running out of turns
+    pub finished: bool,
+    /// Ids of the artifacts written
+    pub artifacts: Vec<String>,
+    /// Prompt and response of every turn
+    pub turns: Vec<(String, String)>,
+}
+
+/// One response of the model and the results of the tools it called
+struct Turn {
+    response: String,
+    results: String,
+}
+
+/// Lets the model work on a task with tools, turn by turn, instead of
+/// executing a plan made up front: `execution.mode = "tools"`
+pub struct ToolLoop {
+    llm_manager: Arc<LLMManager>,
+    artifact_manager: Arc<ArtifactManager>,
+    config: Arc<Config>,
+    /// Project the tools read and search in; commands run where the
+    /// artifacts are written
+    root: PathBuf,
+    sandbox: Option<Sandbox>,
+    redactor: Option<Redactor>,
+    event_bus: Option<Arc<EventBus>>,
+    /// Only write files under docs/, for the docs command
+    docs_only: bool,
+}
+
+impl ToolLoop {
+    pub fn new(
+        llm_manager: Arc<LLMManager>,
+        artifact_manager: Arc<ArtifactManager>,
+        config: Arc<Config>,
+        root: PathBuf,
+    ) -> Self {
+        let execution = &config.execution;
+        Self {
+            sandbox: execution
+                .isolated_execution
+                .then(|| Sandbox::new(execution.sandbox.clone())),
+            redactor: config
+                .redaction
+                .enabled
+                .then(|| Redactor::new(&config.redaction)),
+            llm_manager,
+            artifact_manager,
+            config,
+            root,
+            event_bus: None,
+            docs_only: false,
+        }
+    }
+
+    pub fn with_event_bus(mut self, event_bus: Arc<EventBus>) -> Self {
+        self.event_bus = Some(event_bus);
+        self
+    }
+
+    pub fn with_docs_only(mut self, docs_only: bool) -> Self {
+        self.docs_only = docs_only;
+        self
+    }
+
+    /// The plan of an iteration in the tools mode: a single step working on
+    /// the whole task, with the iteration's context as its input
+    pub fn plan(task: &Task, context: &IterationContext) -> Plan {
+        Plan {
+            goal: task.goal.clone(),
+            steps: vec![Step {
+                id: TOOLS_STEP_ID.to_string(),
+                description: task.description.clone(),
+                category: StepCategory::CodeModification,
+                inputs: vec![context.to_string()],
+                expected_outputs: Vec::new(),
+                success_criteria: vec![task.goal.clone()],
+                estimated_tokens: 0,
+                high_risk: false,
+            }],
+            dependencies: HashMap::new(),
+            estimated_complexity: ComplexityLevel::Medium,
+        }
+    }
+
+    /// Work on `step` until the model calls `finish`, answers without calling
+    /// a tool or runs out of turns. Waits between turns while the run is paused.
+    /// Tools are offered through the provider's tool calling API where it has
+    /// one, else described in the prompt and called with tags.
+    pub async fn run(
+        &self,
+        step: &Step,
+        iteration: usize,
+        control: Option<&RunControl>,
+    ) -> Result<ToolOutcome> {
+        let native = self.llm_manager.supports_tools();
+        let specs = tool_specs(self.config.execution.tools.run_commands);
+        let header = self.header(step, (!native).then_some(specs.as_slice()));
+        let max_turns = self.config.execution.tools.max_turns;
+        // About three characters per token, leaving room for the response
+        let budget = self.llm_manager.get_context_size() * 3;
+        // The conversation so far, as turns of the tag protocol or messages
+        let mut turns: Vec<Turn> = Vec::new();
+        let mut messages = vec![ToolMessage::User(format!(
+            "{}\nStart working on the task.",
+            header
+        ))];
+        // What the model was last sent, for the transcript
+        let mut last_results = String::new();
+        let mut outcome = ToolOutcome {
+            summary: String::new(),
+            finished: false,
+            artifacts: Vec::new(),
+            turns: Vec::new(),
+        };
+
+        for turn in 1..=max_turns {
+            if let Some(control) = control {
+                control.checkpoint().await?;
+            }
+            let (prompt, response) = if native {
+                let sent = elide_results(&messages, budget);
+                let response = self.llm_manager.send_with_tools(&sent, &specs).await?;
+                let prompt = match &messages[..] {
+                    [ToolMessage::User(first)] => first.clone(),
+                    _ => last_results.clone(),
+                };
+                (prompt, response)
+            } else {
+                let prompt = transcript_prompt(&header, &turns, budget);
+                let text = self.llm_manager.send_prompt(&prompt).await?;
+                let calls = text_tool_calls(&text, turn);
+                (prompt, ToolResponse { text, calls })
+            };
+            outcome.turns.push((prompt, render_response(&response)));
+
+            if response.calls.is_empty() {
+                outcome.summary = response.text;
+                outcome.finished = true;
+                return Ok(outcome);
+            }
+            let mut results = Vec::new();
+            let mut rendered = String::new();
+            let mut summary = None;
+            for call in &response.calls {
+                let result = if call.name == "finish" {
+                    summary = Some(
+                        call.arguments["summary"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                    );
+                    Ok("Finished".to_string())
+                } else {
+                    let started = Instant::now();
+                    let result = self.call(call, iteration, &mut outcome).await;
+                    self.announce(&call.name, turn, result.is_ok(), started)
+                        .await;
+                    result
+                };
+                let (status, output) = result_output(result);
+                rendered.push_str(&tool_result(&call.name, status, &output));
+                results.push((call.id.clone(), format!("{}: {}", status, output)));
+            }
+            if let Some(summary) = summary {
+                info!("Tool session finished after {} turns", turn);
+                outcome.summary = summary;
+                outcome.finished = true;
+                return Ok(outcome);
+            }
+            if native {
+                messages.push(ToolMessage::Assistant {
+                    text: response.text,
+                    calls: response.calls,
+                });
+                messages.push(ToolMessage::Results(results));
+                last_results = rendered;
+            } else {
+                turns.push(Turn {
+                    response: response.text,
+                    results: rendered,
+                });
+            }
+        }
+
+        warn!(
+            "Tool session stopped after {} turns without finishing",
+            max_turns
+        );
+        outcome.summary = outcome
+            .turns
+            .last()
+            .map(|(_, response)| response.clone())
+            .unwrap_or_default();
+        Ok(outcome)
+    }
+
+    /// Instructions, task and project layout, sent at the start of every turn.
+    /// `tags` lists the tools to call with tags, without a tool calling API.
+    fn header(&self, step: &Step, tags: Option<&[ToolSpec]>) -> String {
+        let mut header = TOOLS_PROMPT.to_string();
+        if let Some(specs) = tags {
+            header.push_str(&format!(" {}\n", TOOL_CALL_SYNTAX));
+            for spec in specs {
+                header.push_str(&format!("- {}\n", describe_tool(spec)));
+            }
+        }
+        header.push_str(&format!("\n{}", TOOLS_GUIDANCE));
+        if self.docs_only {
+            header.push_str("\nOnly write files under docs/.");
+        }
+        header.push_str(&format!("\n\nTask: {}\n", step.description));
+        for input in &step.inputs {
+            header.push_str(&format!("\n{}\n", input));
+        }
+
+        let files: Vec<String> = WalkBuilder::new(&self.root)
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+            .filter_map(|entry| {
+                let path = entry.path().strip_prefix(&self.root).ok()?;
+                Some(path.to_string_lossy().to_string())
+            })
+            .take(MAX_LISTED_FILES + 1)
+            .collect();
+        header.push_str("\nProject files:\n");
+        for file in files.iter().take(MAX_LISTED_FILES) {
+            header.push_str(&format!("  {}\n", file));
+        }
+        if files.len() > MAX_LISTED_FILES {
+            header.push_str("  ... (use search to find more)\n");
+        }
+        header
+    }
+
+    async fn call(
+        &self,
+        call: &ToolCall,
+        iteration: usize,
+        outcome: &mut ToolOutcome,
+    ) -> Result<String> {
+        let (tool, arguments) = (call.name.as_str(), &call.arguments);
+        if !arguments.is_object() {
+            anyhow::bail!("Arguments must be a JSON object");
+        }
+        let argument = |name: &str| {
+            arguments[name]
+                .as_str()
+                .with_context(|| format!("{} needs a `{}` string", tool, name))
+        };
+        match tool {
+            "read_file" => self.read_file(argument("path")?).await,
+            "write_file" => {
+                let path = argument("path")?;
+                let id = self
+                    .write_file(path, argument("content")?, iteration)
+                    .await?;
+                outcome.artifacts.push(id);
+                Ok(format!("Wrote {}", path))
+            }
+            "search" => self.search(argument("pattern")?, arguments["path"].as_str()),
+            "run_command" => self.run_command(argument("command")?).await,
+            _ => Err(anyhow!(
+                "Unknown tool {}; use read_file, write_file, search, run_command or finish",
+                tool
+            )),
+        }
+    }
+
+    async fn read_file(&self, path: &str) -> Result<String> {
+        let project_path = self.project_path(path)?;
+        let written = self
+            .artifact_manager
+            .list_artifacts()
+            .await
+            .into_iter()
+            .find(|artifact| artifact.name == path);
+        let file = written.map_or(project_path, |artifact| artifact.path);
+        let content = tokio::fs::read_to_string(&file)
+            .await
+            .with_context(|| format!("Cannot read {}", path))?;
+        Ok(self.redact(path, &content))
+    }
+
+    /// Save `content` as the artifact `path`, returning its id
+    async fn write_file(&self, path: &str, content: &str, iteration: usize) -> Result<String> {
+        self.project_path(path)?;
+        if self.docs_only && !path.starts_with("docs/") {
+            anyhow::bail!("Only files under docs/ can be written by the docs command");
+        }
+        let content = format_content(path, content, &self.config.formatting).await;
+        let mut metadata = HashMap::new();
+        metadata.insert("step_id".to_string(), TOOLS_STEP_ID.to_string());
+        add_tags(
+            &mut metadata,
+            &[&format!("iteration:{}", iteration), "tools"],
+        );
+        let artifact = self
+            .artifact_manager
+            .create_artifact(
+                path.to_string(),
+                ArtifactType::from_filename(path),
+                content,
+                metadata,
+            )
+            .await?;
+        Ok(artifact.id)
+    }
+
+    fn search(&self, pattern: &str, path: Option<&str>) -> Result<String> {
+        let regex = regex::Regex::new(pattern).context("Invalid pattern")?;
+        let base = match path {
+            Some(path) => self.project_path(path)?,
+            None => self.root.clone(),
+        };
+        let mut matches = Vec::new();
+        for entry in WalkBuilder::new(&base).build().flatten() {
+            let too_large = entry
+                .metadata()
+                .map_or(true, |metadata| metadata.len() > MAX_SEARCH_FILE_BYTES);
+            if !entry.file_type().is_some_and(|kind| kind.is_file()) || too_large {
+                continue;
+            }
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let name = entry
+                .path()
+                .strip_prefix(&self.root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .to_string();
+            for (number, line) in content.lines().enumerate() {
+                if regex.is_match(line) {
+                    let line: String = line.trim().chars().take(200).collect();
+                    matches.push(format!("{}:{}: {}", name, number + 1, line));
+                    if matches.len() == MAX_SEARCH_MATCHES {
+                        matches.push("... (more matches; narrow the search)".to_string());
+                        return Ok(self.redact(&name, &matches.join("\n")));
+                    }
+                }
+            }
+        }
+        if matches.is_empty() {
+            return Ok("No matches".to_string());
+        }
+        Ok(self.redact("search", &matches.join("\n")))
+    }
+
+    async fn run_command(&self, command: &str) -> Result<String> {
+        let tools = &self.config.execution.tools;
+        if !tools.run_commands {
+            anyhow::bail!("run_command is disabled by `execution.tools.run_commands`");
+        }
+        info!("Running tool command: {}", command);
+        // Commands see the files the model wrote, where they are saved
+        let workdir = self.artifact_manager.artifact_dir();
+        std::fs::create_dir_all(workdir)
+            .with_context(|| format!("Cannot create {}", workdir.display()))?;
+        let timeout = Duration::from_secs(tools.command_timeout_secs);
+        let output = match &self.sandbox {
+            Some(sandbox) => {
+                sandbox
+                    .output(workdir, "sh", &["-c", command], timeout)
+                    .await?
+            }
+            None => {
+                let child = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .current_dir(workdir)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .context("Failed to start the shell")?;
+                tokio::time::timeout(timeout, child.wait_with_output())
+                    .await
+                    .map_err(|_| anyhow!("Timed out after {}s", timeout.as_secs()))??
+            }
+        };
+        let text = format!(
+            "exit status: {}\n{}{}",
+            output
+                .status
+                .code()
+                .map_or("killed".to_string(), |code| code.to_string()),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Ok(self.redact(command, &text))
+    }
+
+    /// `path` inside the project; absolute paths and `..` are refused
+    fn project_path(&self, path: &str) -> Result<PathBuf> {
+        let relative = Path::new(path);
+        if relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            anyhow::bail!("{} is outside the project", path);
+        }
+        Ok(self.root.join(relative))
+    }
+
+    fn redact(&self, path: &str, content: &str) -> String {
+        match &self.redactor {
+            Some(redactor) => redactor.redact_file(path, content).0,
+            None => content.to_string(),
+        }
+    }
+
+    async fn announce(&self, tool: &str, turn: usize, ok: bool, started: Instant) {
+        if let Some(bus) = &self.event_bus {
+            let _ = bus
+                .emit(Event::Custom {
+                    event_type: "tool_called".to_string(),
+                    data: serde_json::json!({
+                        "tool": tool,
+                        "turn": turn,
+                        "status": if ok { "ok" } else { "error" },
+                        "duration_ms": started.elapsed().as_millis() as u64,
+                    }),
+                })
+                .await;
+        }
+    }
+}
+
+/// The prompt of the next turn: the header and the turns so far. The
+/// results of the oldest turns are left out when it would exceed `budget`
+/// characters.
+fn transcript_prompt(header: &str, turns: &[Turn], budget: usize) -> String {
+    let size = |turn: &Turn| turn.response.len() + turn.results.len();
+    let mut total = header.len() + turns.iter().map(size).sum::<usize>();
+    let mut elided = 0;
+    while total > budget && elided < turns.len().saturating_sub(1) {
+        total -= turns[elided].results.len();
+        elided += 1;
+    }
+
+    let mut prompt = header.to_string();
+    for (index, turn) in turns.iter().enumerate() {
+        prompt.push_str(&format!(
+            "\nYOUR RESPONSE {}:\n{}\n\nTOOL RESULTS:\n",
+            index + 1,
+            turn.response
+        ));
+        if index < elided {
+            prompt.push_str("(left out to save space)\n");
+        } else {
+            prompt.push_str(&turn.results);
+        }
+    }
+    prompt.push_str(if turns.is_empty() {
+        "\nStart working on the task."
+    } else {
+        "\nContinue working on the task."
+    });
+    prompt
+}
+
+/// The model's part of the conversation with tools, as `<tool_call>` tags
+/// where the provider's API made the calls
+fn render_response(response: &ToolResponse) -> String {
+    let mut rendered = response.text.clone();
+    for call in &response.calls {
+        if !rendered.is_empty() {
+            rendered.push('\n');
+        }
+        rendered.push_str(&format!(
+            "<tool_call tool=\"{}\">{}</tool_call>",
+            call.name, call.arguments
+        ));
+    }
+    rendered
+}
+
+/// Status and output of a tool, its error if it failed
+fn result_output(result: Result<String>) -> (&'static str, String) {
+    let (status, output) = match result {
+        Ok(output) => ("ok", output),
+        Err(e) => ("error", format!("{:#}", e)),
+    };
+    (status, output.chars().take(MAX_RESULT_CHARS).collect())
+}
+
+fn tool_result(tool: &str, status: &str, output: &str) -> String {
+    format!(
+        "<tool_result tool=\"{}\" status=\"{}\">\n{}\n</tool_result>\n",
+        tool, status, output
+    )
+}
+
+/// A tool as listed in the prompt: its name, arguments and description
+fn describe_tool(spec: &ToolSpec) -> String {
+    let required: Vec<&str> = spec.parameters["required"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    let arguments: Vec<String> = spec.parameters["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, _)| match required.contains(&name.as_str()) {
+            true => format!("\"{}\"", name),
+            false => format!("\"{}\"?", name),
+        })
+        .collect();
+    format!(
+        "{} {{{}}}: {}",
+        spec.name,
+        arguments.join(", "),
+        spec.description
+    )
+}
+
+/// The `<tool_call tool="...">{arguments}</tool_call>` tags of a response,
+/// numbered within `turn`. Arguments that are not valid JSON are passed on
+/// as a string, which the tools refuse.
+fn text_tool_calls(response: &str, turn: usize) -> Vec<ToolCall> {
+    extract_tool_call_tags(response)
+        .into_iter()
+        .filter_map(|(mut attributes, arguments)| {
+            let Some(tool) = attributes.remove("tool") else {
+                warn!("Ignoring tool call without a tool: {:?}", attributes);
+                return None;
+            };
+            let arguments = match arguments.trim() {
+                "" => Value::Object(Default::default()),
+                arguments => serde_json::from_str(arguments)
+                    .unwrap_or_else(|_| Value::String(arguments.to_string())),
+            };
+            Some((tool, arguments))
+        })
+        .enumerate()
+        .map(|(index, (name, arguments))| ToolCall {
+            id: format!("call_{}_{}", turn, index + 1),
+            name,
+            arguments,
+        })
+        .collect()
+}
+
+/// The conversation with the outputs of the oldest tool calls left out
+/// while it exceeds `budget` characters; the latest results are always kept
+fn elide_results(messages: &[ToolMessage], budget: usize) -> Vec<ToolMessage> {
+    let mut messages = messages.to_vec();
+    let mut total: usize = messages.iter().map(ToolMessage::size).sum();
+    let last = messages.len().saturating_sub(1);
+    for message in &mut messages[..last] {
+        if total <= budget {
+            break;
+        }
+        if let ToolMessage::Results(results) = message {
+            for (_, output) in results {
+                total -= output.len();
+                *output = "(left out to save space)".to_string();
+            }
+        }
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_manager::LLMProvider;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Answers with the scripted responses in turn, with tags
+    struct ScriptedProvider(Mutex<Vec<&'static str>>);
+
+    #[async_trait]
+    impl LLMProvider for ScriptedProvider {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn context_size(&self) -> usize {
+            100_000
+        }
+
+        async fn send_prompt(&self, _prompt: &str) -> Result<String> {
+            Ok(self.0.lock().unwrap().remove(0).to_string())
+        }
+    }
+
+    /// Calls the scripted tools through the tool calling API, keeping the
+    /// conversations it was sent
+    struct NativeProvider {
+        responses: Mutex<Vec<ToolResponse>>,
+        sent: Arc<Mutex<Vec<Vec<ToolMessage>>>>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for NativeProvider {
+        fn name(&self) -> &str {
+            "native"
+        }
+
+        fn context_size(&self) -> usize {
+            100_000
+        }
+
+        async fn send_prompt(&self, _prompt: &str) -> Result<String> {
+            anyhow::bail!("tools are called through the API")
+        }
+
+        fn supports_tools(&self) -> bool {
+            true
+        }
+
+        async fn send_with_tools(
+            &self,
+            messages: &[ToolMessage],
+            _tools: &[ToolSpec],
+        ) -> Result<ToolResponse> {
+            self.sent.lock().unwrap().push(messages.to_vec());
+            Ok(self.responses.lock().unwrap().remove(0))
+        }
+    }
+
+    /// A project with src/lib.rs, in a directory removed when dropped
+    struct Project {
+        dir: PathBuf,
+        root: PathBuf,
+    }
+
+    impl Project {
+        fn new() -> Self {
+            let dir = std::env::temp_dir()
+                .join(format!("cli_engineer_tool_loop_{}", uuid::Uuid::new_v4()));
+            let root = dir.join("project");
+            std::fs::create_dir_all(root.join("src")).unwrap();
+            std::fs::write(
+                root.join("src/lib.rs"),
+                "pub fn add(a: i32) -> i32 {\n    a\n}\n",
+            )
+            .unwrap();
+            Self { dir, root }
+        }
+
+        fn tools(&self, provider: impl LLMProvider + 'static, config: Config) -> ToolLoop {
+            let config = Arc::new(config);
+            let llm_manager = Arc::new(LLMManager::new(
+                vec![Box::new(provider)],
+                Arc::new(EventBus::new(100)),
+                config.clone(),
+            ));
+            let artifact_manager =
+                Arc::new(ArtifactManager::new(self.dir.join("artifacts")).unwrap());
+            ToolLoop::new(llm_manager, artifact_manager, config, self.root.clone())
+        }
+    }
+
+    impl Drop for Project {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn step() -> Step {
+        let task = Task {
+            description: "Make add take two numbers".to_string(),
+            goal: "add(a, b)".to_string(),
+        };
+        ToolLoop::plan(&task, &IterationContext::new(1))
+            .steps
+            .remove(0)
+    }
+
+    fn call(id: &str, name: &str, arguments: Value) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_with_tags() {
+        let project = Project::new();
+        let provider = ScriptedProvider(Mutex::new(vec![
+            r#"<tool_call tool="search">{"pattern": "fn add"}</tool_call>
+<tool_call tool="read_file">{"path": "../secret"}</tool_call>"#,
+            r#"<tool_call tool="write_file">{"path": "src/lib.rs", "content": "pub fn add(a: i32, b: i32) -> i32 { a + b }\n"}</tool_call>
+<tool_call tool="read_file">{"path": "src/lib.rs"}</tool_call>"#,
+            r#"<tool_call tool="finish">{"summary": "Added b"}</tool_call>"#,
+        ]));
+        let tools = project.tools(provider, Config::default());
+
+        let outcome = tools.run(&step(), 1, None).await.unwrap();
+        assert!(outcome.finished);
+        assert_eq!(outcome.summary, "Added b");
+        assert_eq!(outcome.artifacts.len(), 1);
+
+        let (first_prompt, _) = &outcome.turns[0];
+        assert!(first_prompt.contains(r#"- read_file {"path"}: The content of a file"#));
+        assert!(first_prompt.contains(r#"- search {"path"?, "pattern"}"#));
+        let (second_prompt, _) = &outcome.turns[1];
+        assert!(second_prompt.contains("src/lib.rs:1: pub fn add(a: i32) -> i32 {"));
+        assert!(second_prompt.contains("../secret is outside the project"));
+        let (third_prompt, _) = &outcome.turns[2];
+        assert!(third_prompt.contains("Wrote src/lib.rs"));
+        assert!(third_prompt.contains("a + b"));
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_through_the_api() {
+        let project = Project::new();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let provider = NativeProvider {
+            responses: Mutex::new(vec![
+                ToolResponse {
+                    text: "Looking first".to_string(),
+                    calls: vec![call("toolu_1", "search", json!({"pattern": "fn add"}))],
+                },
+                ToolResponse {
+                    text: String::new(),
+                    calls: vec![call(
+                        "toolu_2",
+                        "finish",
+                        json!({"summary": "Nothing to do"}),
+                    )],
+                },
+            ]),
+            sent: sent.clone(),
+        };
+        let tools = project.tools(provider, Config::default());
+
+        let outcome = tools.run(&step(), 1, None).await.unwrap();
+        assert!(outcome.finished);
+        assert_eq!(outcome.summary, "Nothing to do");
+
+        let sent = sent.lock().unwrap();
+        let ToolMessage::User(first) = &sent[0][0] else {
+            panic!("the conversation starts with the task");
+        };
+        // The API describes the tools, so the prompt doesn't
+        assert!(!first.contains("<tool_call"));
+        assert!(first.contains("Task: Make add take two numbers"));
+        let ToolMessage::Results(results) = &sent[1][2] else {
+            panic!("the second request carries the results");
+        };
+        assert_eq!(results[0].0, "toolu_1");
+        assert!(results[0].1.starts_with("ok: src/lib.rs:1: pub fn add"));
+        assert!(outcome.turns[0].1.contains(r#"<tool_call tool="search">"#));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments_are_refused() {
+        let project = Project::new();
+        let provider = ScriptedProvider(Mutex::new(vec![
+            r#"<tool_call tool="read_file">{"path": </tool_call>"#,
+            "Done",
+        ]));
+        let outcome = project
+            .tools(provider, Config::default())
+            .run(&step(), 1, None)
+            .await
+            .unwrap();
+        let (second_prompt, _) = &outcome.turns[1];
+        assert!(second_prompt.contains("Arguments must be a JSON object"));
+        assert_eq!(outcome.summary, "Done");
+    }
+
+    #[tokio::test]
+    async fn test_run_command_is_off_by_default() {
+        assert!(!Config::default().execution.tools.run_commands);
+        let project = Project::new();
+        let provider = ScriptedProvider(Mutex::new(vec![
+            r#"<tool_call tool="run_command">{"command": "rm -rf ."}</tool_call>"#,
+            "Done",
+        ]));
+        let outcome = project
+            .tools(provider, Config::default())
+            .run(&step(), 1, None)
+            .await
+            .unwrap();
+        let (first_prompt, _) = &outcome.turns[0];
+        assert!(!first_prompt.contains("- run_command"));
+        let (second_prompt, _) = &outcome.turns[1];
+        assert!(second_prompt.contains("run_command is disabled"));
+        assert!(project.root.join("src/lib.rs").exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_commands_see_the_written_files() {
+        let project = Project::new();
+        let provider = ScriptedProvider(Mutex::new(vec![
+            r#"<tool_call tool="write_file">{"path": "notes.txt", "content": "written"}</tool_call>
+<tool_call tool="run_command">{"command": "cat notes.txt"}</tool_call>"#,
+            "Done",
+        ]));
+        let mut config = Config::default();
+        config.execution.tools.run_commands = true;
+        let outcome = project
+            .tools(provider, config)
+            .run(&step(), 1, None)
+            .await
+            .unwrap();
+        let (second_prompt, _) = &outcome.turns[1];
+        assert!(second_prompt.contains("exit status: 0\nwritten"));
+    }
+}
@@ -0,0 +1,481 @@
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+
+use crate::config::TrackerConfig;
+use crate::issue::{client, send, untrusted};
+
+const LINEAR_API: &str = "https://api.linear.app/graphql";
+/// Comments longer than this are rejected by Jira
+const MAX_COMMENT_CHARS: usize = 32_000;
+
+/// Where a ticket is kept
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tracker {
+    Jira,
+    Linear,
+}
+
+impl Tracker {
+    /// The tracker of ticket `key`: Linear for the teams of `linear_teams`,
+    /// else Jira if `jira_url` is set, else Linear if an API key is found
+    fn detect(key: &str, config: &TrackerConfig) -> Result<Self> {
+        let team = key.split('-').next().unwrap_or_default();
+        if config
+            .linear_teams
+            .iter()
+            .any(|linear| linear.eq_ignore_ascii_case(team))
+        {
+            Ok(Tracker::Linear)
+        } else if config.jira_url.is_some() {
+            Ok(Tracker::Jira)
+        } else if linear_token(config).is_ok() {
+            Ok(Tracker::Linear)
+        } else {
+            anyhow::bail!(
+                "Cannot tell where {} is kept: set tracker.jira_url for Jira, or LINEAR_API_KEY \
+                 and tracker.linear_teams for Linear",
+                key
+            )
+        }
+    }
+}
+
+/// A Jira or Linear ticket used as the task of a run
+#[derive(Debug, Clone)]
+pub struct Ticket {
+    pub tracker: Tracker,
+    /// Key such as `PROJ-123`
+    pub key: String,
+    /// ID comments are posted to: Linear's internal issue ID, or the key for Jira
+    id: String,
+    pub title: String,
+    pub description: String,
+    /// From the configured Jira field, or else the description's
+    /// "Acceptance criteria" section
+    pub acceptance_criteria: Option<String>,
+    pub url: String,
+    /// Author and text of each comment, oldest first
+    pub comments: Vec<(String, String)>,
+}
+
+impl Ticket {
+    /// Fetch the ticket and its comments
+    pub async fn fetch(key: &str, config: &TrackerConfig) -> Result<Self> {
+        let key = key.trim().to_uppercase();
+        let valid = key.split_once('-').is_some_and(|(team, number)| {
+            team.starts_with(|c: char| c.is_ascii_alphabetic())
+                && team.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                && !number.is_empty()
+                && number.chars().all(|c| c.is_ascii_digit())
+        });
+        if !valid {
+            anyhow::bail!("{} is not a ticket key such as PROJ-123", key);
+        }
+        let ticket = match Tracker::detect(&key, config)? {
+            Tracker::Jira => Self::fetch_jira(&key, config).await,
+            Tracker::Linear => Self::fetch_linear(&key, config).await,
+        };
+        ticket.with_context(|| format!("Failed to fetch {}", key))
+    }
+
+    async fn fetch_jira(key: &str, config: &TrackerConfig) -> Result<Self> {
+        let base = jira_base(config)?;
+        let mut fields = "summary,description,comment".to_string();
+        if let Some(field) = &config.jira_acceptance_criteria_field {
+            fields.push_str(&format!(",{}", field));
+        }
+        // Version 2 of the API returns descriptions as text rather than documents
+        let issue = send(jira_auth(
+            client()?.get(format!(
+                "{}/rest/api/2/issue/{}?fields={}",
+                base, key, fields
+            )),
+            config,
+            false,
+        )?)
+        .await?;
+        let fields = &issue["fields"];
+
+        let (description, mut acceptance_criteria) =
+            split_acceptance_criteria(fields["description"].as_str().unwrap_or_default());
+        if let Some(field) = &config.jira_acceptance_criteria_field {
+            let criteria = match &fields[field] {
+                Value::Null => None,
+                Value::String(text) => Some(text.clone()),
+                other => Some(other.to_string()),
+            };
+            acceptance_criteria = criteria
+                .filter(|criteria| !criteria.trim().is_empty())
+                .or(acceptance_criteria);
+        }
+        let comments = fields["comment"]["comments"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|comment| {
+                (
+                    text(&comment["author"], "displayName"),
+                    text(comment, "body"),
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            tracker: Tracker::Jira,
+            key: key.to_string(),
+            id: key.to_string(),
+            title: text(fields, "summary"),
+            description,
+            acceptance_criteria,
+            url: format!("{}/browse/{}", base, key),
+            comments,
+        })
+    }
+
+    async fn fetch_linear(key: &str, config: &TrackerConfig) -> Result<Self> {
+        let data = linear_request(
+            "query Ticket($id: String!) { issue(id: $id) { id identifier title description url \
+             comments { nodes { body createdAt user { name } } } } }",
+            json!({ "id": key }),
+            config,
+        )
+        .await?;
+        let issue = &data["issue"];
+        if issue.is_null() {
+            anyhow::bail!("Linear has no issue {}", key);
+        }
+        let mut comments: Vec<&Value> = issue["comments"]["nodes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .collect();
+        comments.sort_by_key(|comment| comment["createdAt"].as_str().unwrap_or_default());
+        let (description, acceptance_criteria) =
+            split_acceptance_criteria(issue["description"].as_str().unwrap_or_default());
+
+        Ok(Self {
+            tracker: Tracker::Linear,
+            key: text(issue, "identifier"),
+            id: text(issue, "id"),
+            title: text(issue, "title"),
+            description,
+            acceptance_criteria,
+            url: text(issue, "url"),
+            comments: comments
+                .into_iter()
+                .map(|comment| (text(&comment["user"], "name"), text(comment, "body")))
+                .collect(),
+        })
+    }
+
+    /// The task: the ticket's title, description, acceptance criteria and
+    /// discussion, fenced as untrusted, followed by `instructions` from the
+    /// command line
+    pub fn prompt(&self, instructions: &str) -> String {
+        let mut ticket = self.title.trim().to_string();
+        if !self.description.trim().is_empty() {
+            ticket.push_str(&format!("\n\n{}", self.description.trim()));
+        }
+        if let Some(criteria) = &self.acceptance_criteria {
+            ticket.push_str(&format!(
+                "\n\nAcceptance criteria, all of which must be met:\n{}",
+                criteria.trim()
+            ));
+        }
+        if !self.comments.is_empty() {
+            ticket.push_str("\n\nDiscussion:");
+            for (author, body) in &self.comments {
+                ticket.push_str(&format!("\n\n{} wrote:\n{}", author, body.trim()));
+            }
+        }
+        let mut prompt = format!("Resolve {}.\n\n{}", self.key, untrusted("ticket", &ticket));
+        if !instructions.trim().is_empty() {
+            prompt.push_str(&format!(
+                "\n\nAdditional instructions: {}",
+                instructions.trim()
+            ));
+        }
+        prompt
+    }
+
+    /// Post `text`, in Markdown, as a comment on the ticket
+    pub async fn comment(&self, text: &str, config: &TrackerConfig) -> Result<()> {
+        let text: String = text.chars().take(MAX_COMMENT_CHARS).collect();
+        let posted = match self.tracker {
+            Tracker::Jira => {
+                // Version 3 takes comments as documents rather than wiki markup
+                let url = format!(
+                    "{}/rest/api/3/issue/{}/comment",
+                    jira_base(config)?,
+                    self.id
+                );
+                let request = jira_auth(client()?.post(url), config, true)?;
+                send(request.json(&json!({ "body": adf_document(&text) })))
+                    .await
+                    .map(|_| ())
+            }
+            Tracker::Linear => linear_request(
+                "mutation Comment($issueId: String!, $body: String!) { \
+                 commentCreate(input: { issueId: $issueId, body: $body }) { success } }",
+                json!({ "issueId": self.id, "body": text }),
+                config,
+            )
+            .await
+            .map(|_| ()),
+        };
+        posted.with_context(|| format!("Failed to comment on {}", self.key))
+    }
+}
+
+/// Markdown as an Atlassian Document Format document: paragraphs, `- `
+/// lists, `code` and links
+fn adf_document(markdown: &str) -> Value {
+    let mut content = Vec::new();
+    for block in markdown.split("\n\n") {
+        // Runs of list items and of other lines within the block
+        let mut lines = block
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .peekable();
+        while let Some(first) = lines.peek().copied() {
+            if first.starts_with("- ") {
+                let mut items = Vec::new();
+                while let Some(item) = lines.next_if(|line| line.starts_with("- ")) {
+                    items.push(json!({
+                        "type": "listItem",
+                        "content": [{ "type": "paragraph", "content": adf_inline(&item[2..]) }],
+                    }));
+                }
+                content.push(json!({ "type": "bulletList", "content": items }));
+            } else {
+                let mut nodes = Vec::new();
+                while let Some(line) = lines.next_if(|line| !line.starts_with("- ")) {
+                    if !nodes.is_empty() {
+                        nodes.push(json!({ "type": "hardBreak" }));
+                    }
+                    nodes.extend(adf_inline(line));
+                }
+                content.push(json!({ "type": "paragraph", "content": nodes }));
+            }
+        }
+    }
+    json!({ "type": "doc", "version": 1, "content": content })
+}
+
+/// Text nodes of a line, with `code` spans and URLs marked
+fn adf_inline(line: &str) -> Vec<Value> {
+    let mut nodes = Vec::new();
+    for (index, part) in line.split('`').enumerate() {
+        if part.is_empty() {
+            continue;
+        }
+        if index % 2 == 1 {
+            nodes.push(json!({ "type": "text", "text": part, "marks": [{ "type": "code" }] }));
+            continue;
+        }
+        let mut plain = String::new();
+        for word in part.split_inclusive(' ') {
+            let url = word.trim_end();
+            if url.starts_with("https://") || url.starts_with("http://") {
+                if !plain.is_empty() {
+                    nodes.push(json!({ "type": "text", "text": std::mem::take(&mut plain) }));
+                }
+                nodes.push(json!({
+                    "type": "text",
+                    "text": url,
+                    "marks": [{ "type": "link", "attrs": { "href": url } }],
+                }));
+                plain.push_str(&word[url.len()..]);
+            } else {
+                plain.push_str(word);
+            }
+        }
+        if !plain.is_empty() {
+            nodes.push(json!({ "type": "text", "text": plain }));
+        }
+    }
+    nodes
+}
+
+fn jira_base(config: &TrackerConfig) -> Result<&str> {
+    config
+        .jira_url
+        .as_deref()
+        .map(|url| url.trim_end_matches('/'))
+        .context("No tracker.jira_url is configured")
+}
+
+/// Authenticate a Jira request: basic auth with `jira_email` on Jira Cloud, a
+/// bearer personal access token otherwise. Without a token the request is sent
+/// anonymously unless `required`.
+fn jira_auth(
+    request: reqwest::RequestBuilder,
+    config: &TrackerConfig,
+    required: bool,
+) -> Result<reqwest::RequestBuilder> {
+    let token = match std::env::var("JIRA_API_TOKEN") {
+        Ok(token) if !token.is_empty() => Some(token),
+        _ => config.jira_token.clone(),
+    };
+    let email = match std::env::var("JIRA_EMAIL") {
+        Ok(email) if !email.is_empty() => Some(email),
+        _ => config.jira_email.clone(),
+    };
+    Ok(match (token, email) {
+        (Some(token), Some(email)) => request.basic_auth(email, Some(token)),
+        (Some(token), None) => request.bearer_auth(token),
+        (None, _) if required => anyhow::bail!(
+            "JIRA_API_TOKEN environment variable not set, and no tracker.jira_token is configured"
+        ),
+        (None, _) => request,
+    })
+}
+
+fn linear_token(config: &TrackerConfig) -> Result<String> {
+    match std::env::var("LINEAR_API_KEY") {
+        Ok(token) if !token.is_empty() => Ok(token),
+        _ => config.linear_token.clone().context(
+            "LINEAR_API_KEY environment variable not set, and no tracker.linear_token is configured",
+        ),
+    }
+}
+
+/// Run a GraphQL request against Linear and return its `data`
+async fn linear_request(query: &str, variables: Value, config: &TrackerConfig) -> Result<Value> {
+    let reply = send(
+        client()?
+            .post(LINEAR_API)
+            .header("Authorization", linear_token(config)?)
+            .json(&json!({ "query": query, "variables": variables })),
+    )
+    .await?;
+    // GraphQL errors come with a success status
+    if let Some(error) = reply["errors"].as_array().and_then(|errors| errors.first()) {
+        anyhow::bail!(
+            "{}",
+            error["message"].as_str().unwrap_or("Linear request failed")
+        );
+    }
+    Ok(reply["data"].clone())
+}
+
+fn text(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Split a description into its text and the section under a heading such as
+/// `## Acceptance Criteria`, `h3. Acceptance criteria` or `Acceptance criteria:`,
+/// which ends at the next heading
+fn split_acceptance_criteria(description: &str) -> (String, Option<String>) {
+    let heading_text = |line: &str| -> Option<String> {
+        let line = line.trim();
+        let text = if let Some(text) = line.strip_prefix('#') {
+            text.trim_start_matches('#')
+        } else if let [b'h', level, b'.', ..] = line.as_bytes()
+            && level.is_ascii_digit()
+        {
+            &line[3..]
+        } else {
+            return None;
+        };
+        Some(text.trim().to_lowercase())
+    };
+    let is_criteria = |line: &str| {
+        let text = heading_text(line).unwrap_or_else(|| line.trim().to_lowercase());
+        text.trim_matches(|c: char| c == '*' || c == '_' || c == ':' || c.is_whitespace())
+            == "acceptance criteria"
+    };
+
+    let lines: Vec<&str> = description.lines().collect();
+    let Some(start) = lines.iter().position(|line| is_criteria(line)) else {
+        return (description.trim().to_string(), None);
+    };
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| heading_text(line).is_some())
+        .map_or(lines.len(), |offset| start + 1 + offset);
+    let criteria = lines[start + 1..end].join("\n").trim().to_string();
+    let rest = [&lines[..start], &lines[end..]].concat().join("\n");
+    (
+        rest.trim().to_string(),
+        (!criteria.is_empty()).then_some(criteria),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_and_acceptance_criteria() {
+        let config = TrackerConfig {
+            jira_url: Some("https://example.atlassian.net/".to_string()),
+            linear_teams: vec!["LIN".to_string()],
+            ..TrackerConfig::default()
+        };
+        assert_eq!(
+            Tracker::detect("LIN-456", &config).unwrap(),
+            Tracker::Linear
+        );
+        assert_eq!(Tracker::detect("PROJ-123", &config).unwrap(), Tracker::Jira);
+
+        let (description, criteria) = split_acceptance_criteria(
+            "Exports time out.\n\nh3. Acceptance criteria\n* Exports of 1M rows finish\n\
+             * Progress is shown\nh3. Notes\nSee INC-9.",
+        );
+        assert_eq!(description, "Exports time out.\n\nh3. Notes\nSee INC-9.");
+        assert_eq!(
+            criteria.as_deref(),
+            Some("* Exports of 1M rows finish\n* Progress is shown")
+        );
+        let (_, criteria) =
+            split_acceptance_criteria("Text\n\n**Acceptance Criteria:**\n- [ ] Works");
+        assert_eq!(criteria.as_deref(), Some("- [ ] Works"));
+    }
+
+    #[test]
+    fn test_ticket_prompt() {
+        let ticket = Ticket {
+            tracker: Tracker::Jira,
+            key: "PROJ-123".to_string(),
+            id: "PROJ-123".to_string(),
+            title: "Slow exports".to_string(),
+            description: "Exports time out.".to_string(),
+            acceptance_criteria: Some("* Exports of 1M rows finish".to_string()),
+            url: "https://example.atlassian.net/browse/PROJ-123".to_string(),
+            comments: vec![("Alice".to_string(), "Stream the rows".to_string())],
+        };
+        let prompt = ticket.prompt("");
+        assert!(prompt.starts_with("Resolve PROJ-123.\n\nThe ticket below was written by others."));
+        assert!(prompt.ends_with(
+            "\n```\nSlow exports\n\nExports time out.\n\n\
+Acceptance criteria, all of which must be met:\n* Exports of 1M rows finish\n\n\
+Discussion:\n\nAlice wrote:\nStream the rows\n```"
+        ));
+    }
+
+    #[test]
+    fn test_comments_are_documents_for_jira() {
+        let document = adf_document(
+            "cli_engineer ran `code` for this issue.\n\n\
+             Pull request: https://github.com/o/r/pull/1\n\nFiles:\n- `src/a.rs`",
+        );
+        assert_eq!(document["type"], "doc");
+        let content = document["content"].as_array().unwrap();
+        assert_eq!(content[0]["content"][1]["text"], "code");
+        assert_eq!(content[0]["content"][1]["marks"][0]["type"], "code");
+        assert_eq!(
+            content[1]["content"][1]["marks"][0]["attrs"]["href"],
+            "https://github.com/o/r/pull/1"
+        );
+        assert_eq!(content[2]["content"][0]["text"], "Files:");
+        assert_eq!(content[3]["type"], "bulletList");
+        let item = &content[3]["content"][0]["content"][0]["content"][0];
+        assert_eq!(item["text"], "src/a.rs");
+        assert_eq!(item["marks"][0]["type"], "code");
+    }
+}
@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+/// Full record of a single executed step, written to disk for debugging
+#[derive(Debug, Clone)]
+pub struct StepTranscript {
+    pub iteration: usize,
+    pub step_id: String,
+    pub step_number: usize,
+    pub total_steps: usize,
+    pub description: String,
+    pub category: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub duration: Duration,
+    pub prompt: String,
+    pub response: String,
+    /// Additional prompt/response rounds within the step (e.g. syntax fixes)
+    pub follow_ups: Vec<(String, String)>,
+    /// Candidate responses as (provider, response), when the step was sampled
+    pub candidates: Vec<(String, String)>,
+    /// How the response was chosen among the candidates
+    pub selection: Option<String>,
+    /// Extracted artifacts as (filename, size in bytes)
+    pub artifacts: Vec<(String, usize)>,
+    pub error: Option<String>,
+}
+
+impl StepTranscript {
+    pub fn new(
+        iteration: usize,
+        step_id: &str,
+        step_number: usize,
+        total_steps: usize,
+        description: &str,
+        category: String,
+    ) -> Self {
+        Self {
+            iteration,
+            step_id: step_id.to_string(),
+            step_number,
+            total_steps,
+            description: description.to_string(),
+            category,
+            started_at: chrono::Utc::now(),
+            duration: Duration::default(),
+            prompt: String::new(),
+            response: String::new(),
+            follow_ups: Vec::new(),
+            candidates: Vec::new(),
+            selection: None,
+            artifacts: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// File name of this transcript within the run directory
+    pub fn file_name(&self) -> String {
+        format!("iter{}_{}.md", self.iteration, self.step_id)
+    }
+
+    /// Render the transcript as markdown
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!(
+            "# Step {}/{}: {}\n\n",
+            self.step_number, self.total_steps, self.description
+        );
+        out.push_str(&format!("- **Iteration:** {}\n", self.iteration));
+        out.push_str(&format!("- **Step ID:** {}\n", self.step_id));
+        out.push_str(&format!("- **Category:** {}\n", self.category));
+        out.push_str(&format!(
+            "- **Started:** {}\n",
+            self.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+        ));
+        out.push_str(&format!(
+            "- **Duration:** {:.2}s\n",
+            self.duration.as_secs_f32()
+        ));
+        if let Some(selection) = &self.selection {
+            out.push_str(&format!("- **Response:** {}\n", selection));
+        }
+        if let Some(error) = &self.error {
+            out.push_str(&format!("- **Error:** {}\n", error));
+        }
+
+        out.push_str("\n## Extracted Artifacts\n\n");
+        if self.artifacts.is_empty() {
+            out.push_str("_None_\n");
+        }
+        for (name, size) in &self.artifacts {
+            out.push_str(&format!("- `{}` ({} bytes)\n", name, size));
+        }
+
+        out.push_str("\n## Prompt\n\n");
+        out.push_str(&fenced(&self.prompt));
+        out.push_str("\n## Response\n\n");
+        out.push_str(&fenced(&self.response));
+
+        for (i, (provider, response)) in self.candidates.iter().enumerate() {
+            out.push_str(&format!("\n## Candidate {} ({})\n\n", i + 1, provider));
+            out.push_str(&fenced(response));
+        }
+
+        for (i, (prompt, response)) in self.follow_ups.iter().enumerate() {
+            out.push_str(&format!("\n## Follow-up {} Prompt\n\n", i + 1));
+            out.push_str(&fenced(prompt));
+            out.push_str(&format!("\n## Follow-up {} Response\n\n", i + 1));
+            out.push_str(&fenced(response));
+        }
+
+        out
+    }
+
+    /// Write the transcript into `dir`, creating it if needed
+    pub fn write_to(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir).context("Failed to create transcript directory")?;
+        let path = dir.join(self.file_name());
+        std::fs::write(&path, self.to_markdown()).context("Failed to write step transcript")?;
+        Ok(path)
+    }
+}
+
+/// Wrap text in a code fence longer than any backtick run it contains
+fn fenced(text: &str) -> String {
+    let mut longest = 0;
+    let mut current = 0;
+    for c in text.chars() {
+        if c == '`' {
+            current += 1;
+            longest = longest.max(current);
+        } else {
+            current = 0;
+        }
+    }
+    let fence = "`".repeat((longest + 1).max(3));
+    format!("{}text\n{}\n{}\n", fence, text.trim_end(), fence)
+}
@@ -1,45 +1,324 @@
-use crate::event_bus::{Event, EventBus, EventEmitter};
+use crate::artifact_list::{ApplyStatus, ArtifactList, format_size};
+use crate::event_bus::{BudgetKind, ContextBreakdown, Event, EventBus, EventEmitter};
 use crate::impl_event_emitter;
+use crate::plan_progress::{PlanProgress, StepState, format_elapsed};
+use crate::run_control::{ControlMessage, RunControl};
+use crate::terminal_text::{display_width, strip_ansi, truncate, wrap};
+use crate::theme::Theme;
+use crate::throughput::{CallState, STALL_AFTER, Throughput};
 use anyhow::Result;
 use colored::*;
-use crossterm::{
-    cursor::{MoveTo, Show},
-    execute,
-    terminal::{Clear, ClearType, size},
-};
-use std::io::{self, Write};
+use crossterm::event::{self as terminal_event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use log::warn;
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, BorderType, LineGauge, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::io::{self, Stdout};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tokio;
 
-/// Dashboard UI that updates in-place without scrolling
-use std::collections::VecDeque;
+/// Log lines and reasoning traces kept for scrolling back
+const MAX_PANE_ENTRIES: usize = 500;
+
+/// Diff lines shown in the latest change panel
+const DIFF_PANEL_LINES: usize = 4;
+
+/// Steps of the plan checklist shown at once, around the running step
+const PLAN_PANEL_LINES: usize = 6;
+
+/// Artifacts shown at once, around the selected one
+const ARTIFACT_PANEL_LINES: usize = 5;
+
+/// Smallest terminal the dashboard is drawn in; smaller ones get the text UI
+/// at startup, or a notice when resized below it
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 10;
 
-// Static mutex to prevent concurrent renders
-static RENDER_MUTEX: std::sync::Mutex<()> = std::sync::Mutex::new(());
+/// Rows the logs and reasoning need at least; with fewer left by the panels,
+/// the compact layout is drawn instead
+const MIN_PANE_HEIGHT: u16 = 5;
 
+/// Title and diff lines of the latest change panel
+type DiffPanel = (String, Vec<String>);
+
+/// Everything the dashboard shows, updated from the event bus
+#[derive(Default)]
+struct DashboardState {
+    phase: String,
+    task: String,
+    status: String,
+    progress: f32,
+    api_calls: usize,
+    artifacts_created: usize,
+    /// Artifact versions written, counting the first version of each artifact
+    artifact_versions: usize,
+    tasks_completed: usize,
+    tasks_total: usize,
+    total_cost: f64,
+    context_usage: f32,
+    context_breakdown: ContextBreakdown,
+    /// Speed and latency of API calls
+    throughput: Throughput,
+    /// Whether the run is paused before its next step
+    paused: bool,
+    /// Whether the loop waits for guidance before its next plan
+    guidance_requested: bool,
+    /// Steps of the current plan and where each stands
+    plan: PlanProgress,
+    /// Artifacts written during the run
+    artifacts: ArtifactList,
+    /// Title and diff lines of the most recent artifact change
+    latest_diff: Option<DiffPanel>,
+    /// Percentage and message of the most recent budget threshold reached
+    budget_alert: Option<(u8, String)>,
+    /// Level and message of each log line
+    log_lines: VecDeque<(String, String)>,
+    /// Reasoning traces from LLM models
+    reasoning_traces: VecDeque<String>,
+}
+
+impl DashboardState {
+    fn apply(&mut self, event: Event) {
+        self.plan.apply(&event);
+        self.throughput.apply(&event);
+        self.artifacts.apply(&event);
+        match event {
+            Event::LogLine { level, message } => {
+                push_bounded(&mut self.log_lines, (level, message));
+            }
+            Event::TaskStarted { description, .. } => {
+                self.task = description;
+                self.status = "Running".to_string();
+            }
+            Event::TaskCompleted { .. } => {
+                self.status = "Completed".to_string();
+                self.progress = 1.0;
+                self.tasks_completed += 1;
+            }
+            Event::ExecutionStarted { .. } => {
+                self.tasks_total += 1;
+                self.phase = format!("Iteration {}", self.tasks_total);
+                self.progress = 0.0;
+            }
+            Event::APICallStarted { provider, model } => {
+                self.api_calls += 1;
+                self.status = format!("Calling {}/{}", provider, model);
+            }
+            Event::APICallCompleted { cost, .. } => {
+                self.total_cost += cost as f64;
+                self.status = "API response received".to_string();
+            }
+            Event::ArtifactCreated { .. } => {
+                self.artifacts_created += 1;
+                self.artifact_versions += 1;
+            }
+            Event::ArtifactUpdated { .. } | Event::ArtifactModifiedExternally { .. } => {
+                self.artifact_versions += 1;
+            }
+            Event::ArtifactDiff {
+                name,
+                against,
+                diff,
+            } => {
+                self.latest_diff = Some(diff_panel(&name, &against, &diff));
+            }
+            Event::BudgetThreshold {
+                pct,
+                kind,
+                used,
+                limit,
+            } => {
+                self.budget_alert = Some((pct, budget_alert_message(pct, kind, used, limit)));
+            }
+            Event::ContextUsageChanged {
+                usage_percentage,
+                breakdown,
+                ..
+            } => {
+                self.context_usage = usage_percentage;
+                self.context_breakdown = breakdown;
+            }
+            Event::ReasoningTrace { message } if !message.trim().is_empty() => {
+                push_bounded(&mut self.reasoning_traces, message);
+            }
+            // Sent by the run controls, from the dashboard or over IPC
+            Event::Custom { event_type, .. } => match event_type.as_str() {
+                "run_paused" => {
+                    self.paused = true;
+                    self.status = "Paused before the next step".to_string();
+                }
+                "run_resumed" => {
+                    self.paused = false;
+                    self.status = "Resumed".to_string();
+                }
+                "run_cancelled" => self.status = "Cancelling...".to_string(),
+                "guidance_requested" => {
+                    self.guidance_requested = true;
+                    self.status =
+                        "Waiting for guidance: press f to give it, Enter to go on without"
+                            .to_string();
+                }
+                "guidance_received" => {
+                    self.guidance_requested = false;
+                    self.status = "Guidance received".to_string();
+                }
+                "step_skip_requested" => self.status = "Skipping the current step".to_string(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Pane of the dashboard that can take the keyboard focus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Pane {
+    #[default]
+    Logs,
+    Reasoning,
+    Artifacts,
+}
+
+/// Which panes are shown, which has focus and how far each is scrolled back,
+/// in lines from the bottom. A pane scrolled to the bottom follows new lines.
+#[derive(Debug, Default)]
+struct ViewState {
+    focus: Pane,
+    log_scroll: usize,
+    reasoning_scroll: usize,
+    logs_hidden: bool,
+    reasoning_hidden: bool,
+    artifacts_hidden: bool,
+    /// Selected line of the artifact pane
+    artifact_selected: usize,
+    /// Artifacts listed, as of the last frame drawn
+    artifact_count: usize,
+    /// Whether the run can be paused, skipped and cancelled from the keyboard
+    has_controls: bool,
+}
+
+impl ViewState {
+    fn is_hidden(&self, pane: Pane) -> bool {
+        match pane {
+            Pane::Logs => self.logs_hidden,
+            Pane::Reasoning => self.reasoning_hidden,
+            // The artifact pane only appears once there are artifacts
+            Pane::Artifacts => self.artifacts_hidden || self.artifact_count == 0,
+        }
+    }
+
+    /// Show or hide `pane`, moving focus to a pane that is shown
+    fn toggle(&mut self, pane: Pane) {
+        match pane {
+            Pane::Logs => self.logs_hidden = !self.logs_hidden,
+            Pane::Reasoning => self.reasoning_hidden = !self.reasoning_hidden,
+            Pane::Artifacts => self.artifacts_hidden = !self.artifacts_hidden,
+        }
+        if self.is_hidden(self.focus) {
+            self.focus = self.next_shown(self.focus);
+        } else if !self.is_hidden(pane) {
+            self.focus = pane;
+        }
+    }
+
+    /// The next pane after `pane` that is shown, or `pane` itself
+    fn next_shown(&self, pane: Pane) -> Pane {
+        let mut next = next_pane(pane);
+        while next != pane && self.is_hidden(next) {
+            next = next_pane(next);
+        }
+        next
+    }
+
+    /// Scroll position of the focused pane, unless it is the artifact list
+    fn scroll_mut(&mut self) -> Option<&mut usize> {
+        match self.focus {
+            Pane::Logs => Some(&mut self.log_scroll),
+            Pane::Reasoning => Some(&mut self.reasoning_scroll),
+            Pane::Artifacts => None,
+        }
+    }
+
+    /// Scroll the focused pane, returning whether `code` scrolled it
+    fn scroll(&mut self, code: KeyCode) -> bool {
+        let Some(scroll) = self.scroll_mut() else {
+            return false;
+        };
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_add(1),
+            KeyCode::Down | KeyCode::Char('j') => *scroll = scroll.saturating_sub(1),
+            KeyCode::PageUp => *scroll = scroll.saturating_add(10),
+            KeyCode::PageDown => *scroll = scroll.saturating_sub(10),
+            // Clamped to the oldest line when drawn
+            KeyCode::Home | KeyCode::Char('g') => *scroll = usize::MAX,
+            KeyCode::End | KeyCode::Char('G') => *scroll = 0,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Move the artifact selection, returning whether `code` moved it
+    fn select_artifact(&mut self, code: KeyCode) -> bool {
+        let last = self.artifact_count.saturating_sub(1);
+        let selected = &mut self.artifact_selected;
+        match code {
+            KeyCode::Up | KeyCode::Char('k') => *selected = selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => *selected = (*selected + 1).min(last),
+            KeyCode::PageUp => *selected = selected.saturating_sub(10),
+            KeyCode::PageDown => *selected = (*selected + 10).min(last),
+            KeyCode::Home | KeyCode::Char('g') => *selected = 0,
+            KeyCode::End | KeyCode::Char('G') => *selected = last,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Apply a navigation key, returning whether it was one
+    fn handle_key(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Tab | KeyCode::BackTab => self.focus = self.next_shown(self.focus),
+            KeyCode::Char('l') => self.toggle(Pane::Logs),
+            KeyCode::Char('r') => self.toggle(Pane::Reasoning),
+            KeyCode::Char('a') => self.toggle(Pane::Artifacts),
+            code if self.focus == Pane::Artifacts => return self.select_artifact(code),
+            code => return self.scroll(code),
+        }
+        true
+    }
+}
+
+fn next_pane(pane: Pane) -> Pane {
+    match pane {
+        Pane::Logs => Pane::Reasoning,
+        Pane::Reasoning => Pane::Artifacts,
+        Pane::Artifacts => Pane::Logs,
+    }
+}
+
+/// Full-screen dashboard drawn with ratatui, laid out to fit the terminal and
+/// redrawn when it is resized
 pub struct DashboardUI {
     headless: bool,
     event_bus: Option<Arc<EventBus>>,
     start_time: Instant,
-    // Log buffer
-    log_lines: Arc<Mutex<VecDeque<String>>>,
-    // Reasoning traces from LLM models
-    reasoning_traces: Arc<Mutex<VecDeque<String>>>,
-    // Current status
-    current_phase: Arc<Mutex<String>>,
-    current_task: Arc<Mutex<String>>,
-    current_status: Arc<Mutex<String>>,
-    progress: Arc<Mutex<f32>>,
-
-    // Metrics
-    api_calls: Arc<Mutex<usize>>,
-    artifacts_created: Arc<Mutex<usize>>,
-    tasks_completed: Arc<Mutex<usize>>,
-    tasks_total: Arc<Mutex<usize>>,
-    total_cost: Arc<Mutex<f64>>,
-    context_usage: Arc<Mutex<f32>>,
+    state: Arc<Mutex<DashboardState>>,
+    view: ViewState,
+    control: Option<Arc<RunControl>>,
+    theme: Theme,
+    terminal: Option<Terminal<CrosstermBackend<Stdout>>>,
     last_update: Instant,
+    /// Whether Ctrl-C was pressed once, cancelling the run
+    interrupted: bool,
+    /// Guidance being typed after pressing `f`
+    guidance_input: Option<String>,
+    /// Error the run ended with, shown instead of the completion banner
+    error: Option<String>,
 }
 
 impl DashboardUI {
@@ -48,107 +327,69 @@ impl DashboardUI {
             headless,
             event_bus: None,
             start_time: Instant::now(),
-            current_phase: Arc::new(Mutex::new("Initializing".to_string())),
-            current_task: Arc::new(Mutex::new(String::new())),
-            current_status: Arc::new(Mutex::new(String::new())),
-            progress: Arc::new(Mutex::new(0.0)),
-            api_calls: Arc::new(Mutex::new(0)),
-            artifacts_created: Arc::new(Mutex::new(0)),
-            tasks_completed: Arc::new(Mutex::new(0)),
-            tasks_total: Arc::new(Mutex::new(0)),
-            total_cost: Arc::new(Mutex::new(0.0)),
-            context_usage: Arc::new(Mutex::new(0.0)),
+            state: Arc::new(Mutex::new(DashboardState {
+                phase: "Initializing".to_string(),
+                ..DashboardState::default()
+            })),
+            view: ViewState::default(),
+            control: None,
+            theme: Theme::default(),
+            terminal: None,
             last_update: Instant::now(),
-            log_lines: Arc::new(Mutex::new(VecDeque::with_capacity(30))),
-            reasoning_traces: Arc::new(Mutex::new(VecDeque::with_capacity(30))),
+            interrupted: false,
+            guidance_input: None,
+            error: None,
         }
     }
 
+    /// Let the run be paused with `p`, have its current step skipped with `s`
+    /// and be cancelled with `c`
+    pub fn with_control(mut self, control: Arc<RunControl>) -> Self {
+        self.control = Some(control);
+        self.view.has_controls = true;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Show whether each artifact is still to be copied into the project at
+    /// `root` by `apply`
+    pub fn with_workspace(self, artifact_dir: PathBuf, root: PathBuf) -> Self {
+        self.state.lock().unwrap().artifacts = ArtifactList::with_workspace(artifact_dir, root);
+        self
+    }
+
     pub fn start(&mut self) -> Result<()> {
         if self.headless {
             return Ok(());
         }
 
-        // Clear entire screen and move to top
-        execute!(io::stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
+        // A panic message printed in raw mode is garbled and leaves the shell
+        // unusable, so restore the terminal first
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = disable_raw_mode();
+            let _ = crossterm::execute!(io::stdout(), crossterm::cursor::Show);
+            previous_hook(info);
+        }));
+
+        // Raw mode lets the panes be scrolled without echoing keys
+        enable_raw_mode()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+        terminal.clear()?;
+        terminal.hide_cursor()?;
+        self.terminal = Some(terminal);
 
         // Start background event listener if event bus is available
         if let Some(event_bus) = &self.event_bus {
-            let receiver = event_bus.subscribe();
-            let log_lines = self.log_lines.clone();
-            let current_phase = self.current_phase.clone();
-            let current_task = self.current_task.clone();
-            let current_status = self.current_status.clone();
-            let progress = self.progress.clone();
-            let api_calls = self.api_calls.clone();
-            let artifacts_created = self.artifacts_created.clone();
-            let tasks_completed = self.tasks_completed.clone();
-            let tasks_total = self.tasks_total.clone();
-            let total_cost = self.total_cost.clone();
-            let context_usage = self.context_usage.clone();
-            let reasoning_traces = self.reasoning_traces.clone();
-
+            let mut receiver = event_bus.subscribe();
+            let state = self.state.clone();
             tokio::spawn(async move {
-                let mut event_receiver = receiver;
-                while let Ok(event) = event_receiver.recv().await {
-                    match event {
-                        Event::LogLine { level, message } => {
-                            let colored = match level.as_str() {
-                                "ERROR" => format!("[ERROR] {}", message).red().to_string(),
-                                "WARN" => format!("[WARN ] {}", message).yellow().to_string(),
-                                "INFO" => format!("[INFO ] {}", message).cyan().to_string(),
-                                "DEBUG" => format!("[DEBUG] {}", message).white().to_string(),
-                                "TRACE" => format!("[TRACE] {}", message).dimmed().to_string(),
-                                _ => format!("[{}] {}", level, message),
-                            };
-                            let mut logs = log_lines.lock().unwrap();
-                            if logs.len() >= 30 {
-                                logs.pop_front();
-                            }
-                            logs.push_back(colored.clone());
-                        }
-                        Event::TaskStarted { description, .. } => {
-                            *current_task.lock().unwrap() = description;
-                            *current_status.lock().unwrap() = "Running".to_string();
-                        }
-                        Event::TaskCompleted { .. } => {
-                            *current_status.lock().unwrap() = "Completed".to_string();
-                            *progress.lock().unwrap() = 1.0;
-                            *tasks_completed.lock().unwrap() += 1;
-                        }
-                        Event::ExecutionStarted { .. } => {
-                            *tasks_total.lock().unwrap() += 1;
-                            let iter_count = *tasks_total.lock().unwrap();
-                            *current_phase.lock().unwrap() = format!("Iteration {}", iter_count);
-                        }
-                        Event::APICallStarted { provider, model } => {
-                            *api_calls.lock().unwrap() += 1;
-                            *current_status.lock().unwrap() =
-                                format!("Calling {}/{}", provider, model);
-                        }
-                        Event::APICallCompleted { cost, .. } => {
-                            *total_cost.lock().unwrap() += cost as f64;
-                            *current_status.lock().unwrap() = "API response received".to_string();
-                        }
-                        Event::ArtifactCreated { .. } => {
-                            *artifacts_created.lock().unwrap() += 1;
-                        }
-                        Event::ContextUsageChanged {
-                            usage_percentage, ..
-                        } => {
-                            *context_usage.lock().unwrap() = usage_percentage;
-                        }
-                        Event::ReasoningTrace { message } => {
-                            if !message.trim().is_empty() {
-                                let mut traces = reasoning_traces.lock().unwrap();
-                                if traces.len() >= 30 {
-                                    traces.pop_front();
-                                }
-                                traces.push_back(message);
-                            }
-                        }
-                        _ => {}
-                    }
+                while let Ok(event) = receiver.recv().await {
+                    state.lock().unwrap().apply(event);
                 }
             });
         }
@@ -160,401 +401,257 @@ impl DashboardUI {
         if self.headless {
             return Ok(());
         }
+        self.render()?;
+        self.restore_terminal()?;
 
-        // Show cursor again
-        execute!(io::stdout(), Show)?;
-
-        // Move to bottom and print summary
-        let (_, height) = size()?;
-        execute!(io::stdout(), MoveTo(0, height - 2))?;
-
+        let state = self.state.lock().unwrap();
         let elapsed = self.start_time.elapsed();
-        println!("\n{}", "=".repeat(120).bright_blue());
+        let width = crossterm::terminal::size().map_or(120, |(w, _)| w as usize);
+        let theme = &self.theme;
         println!(
-            "{} {} in {:.1}s",
-            "✓".green().bold(),
-            "Task completed".bright_white().bold(),
-            elapsed.as_secs_f32()
+            "\n{}",
+            theme.paint(&"=".repeat(width.min(120)), theme.border)
         );
+        match &self.error {
+            Some(error) => println!(
+                "{} {} after {:.1}s: {}",
+                theme.paint("✗", theme.error).bold(),
+                theme.paint("Task failed", theme.title).bold(),
+                elapsed.as_secs_f32(),
+                error
+            ),
+            None => println!(
+                "{} {} in {:.1}s",
+                theme.paint("✓", theme.success).bold(),
+                theme.paint("Task completed", theme.title).bold(),
+                elapsed.as_secs_f32()
+            ),
+        }
         println!(
-            "  {} iterations | {} API calls | {} artifacts | ${:.3} cost",
-            self.tasks_total.lock().unwrap().to_string().cyan(),
-            self.api_calls.lock().unwrap().to_string().yellow(),
-            self.artifacts_created.lock().unwrap().to_string().green(),
-            format!("{:.3}", self.total_cost.lock().unwrap()).magenta()
+            "  {} iterations | {} API calls | {} artifacts | ${} cost",
+            theme.paint(&state.tasks_total.to_string(), theme.accent),
+            theme.paint(&state.api_calls.to_string(), theme.highlight),
+            theme.paint(&state.artifacts_created.to_string(), theme.success),
+            theme.paint(&format!("{:.3}", state.total_cost), theme.success)
         );
 
         Ok(())
     }
 
-    fn render(&self) -> Result<()> {
-        if self.headless {
-            return Ok(());
+    /// Leave raw mode and show the cursor below the last frame
+    fn restore_terminal(&mut self) -> Result<()> {
+        if let Some(mut terminal) = self.terminal.take() {
+            disable_raw_mode()?;
+            terminal.show_cursor()?;
+            let height = terminal.size()?.height;
+            terminal.set_cursor_position((0, height.saturating_sub(1)))?;
         }
+        Ok(())
+    }
 
-        // Acquire the render mutex
-        let _lock = RENDER_MUTEX.lock().unwrap();
-
-        // Clear entire screen and move to top
-        execute!(io::stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
-
-        // Box width constants
-        const _BOX_WIDTH: usize = 120;
-        const CONTENT_WIDTH: usize = 118; // BOX_WIDTH - 2 (for borders)
-
-        // Calculate elapsed time
+    fn render(&mut self) -> Result<()> {
+        let Some(terminal) = self.terminal.as_mut() else {
+            return Ok(());
+        };
+        let state = self.state.lock().unwrap();
         let elapsed = self.start_time.elapsed();
-        let minutes = elapsed.as_secs() / 60;
-        let seconds = elapsed.as_secs() % 60;
-
-        // Header
-        println!("{}", "╔══════════════════════════════════════════════════════════════════════════════════════════════════════════════════════╗".bright_blue());
-
-        // Title line with time
-        let title = "CLI Engineer";
-        let time_str = format!("{}:{:02}", minutes, seconds);
-        let padding = CONTENT_WIDTH.saturating_sub(title.len() + time_str.len() + 3);
-        println!(
-            "{} {}{}{} {}{}",
-            "║".bright_blue(),
-            title.bright_white().bold(),
-            " ".repeat(padding),
-            time_str,
-            " ", // add 1 space after time
-            "║".bright_blue()
-        );
-
-        println!("{}", "╠══════════════════════════════════════════════════════════════════════════════════════════════════════════════════════╣".bright_blue());
+        let theme = self.theme;
+        terminal.draw(|frame| draw(frame, &state, &mut self.view, &theme, elapsed))?;
+        Ok(())
+    }
 
-        // Phase and Progress
-        let phase_label = "Phase: ";
-        let phase_text = if let Ok(guard) = self.current_phase.try_lock() {
-            guard.clone()
-        } else {
-            "Loading...".to_string()
-        };
-        let progress_bar_str = self.render_progress_bar(60);
-        let progress_bar_visible = strip_ansi_codes(&progress_bar_str);
-        let progress_bar_width = progress_bar_visible.len();
-
-        // Calculate padding: distribute space before and after progress bar
-        let used_width = phase_label.len() + phase_text.len() + 1 + progress_bar_width; // 1 space after phase_text
-        let total_padding = CONTENT_WIDTH.saturating_sub(used_width);
-        let right_padding = 32; // Fixed right padding to ensure proper alignment
-        let left_padding = total_padding.saturating_sub(right_padding);
-
-        print!(
-            "{}{}{}",
-            "║".bright_blue(),
-            phase_label.bright_white(),
-            phase_text.cyan()
-        );
-        print!(" {}", " ".repeat(left_padding));
-        print!("{}", progress_bar_str);
-        print!("{}", " ".repeat(right_padding));
-        println!("{}", " ║".bright_blue());
-        io::stdout().flush()?;
-
-        // Current Task
-        let task_label = "Task: ";
-        let max_task_len = CONTENT_WIDTH.saturating_sub(task_label.len() + 1);
-        let task_text = if let Ok(guard) = self.current_task.try_lock() {
-            if guard.len() > max_task_len {
-                // Use char-safe truncation to avoid broken UTF-8
-                let truncated_len = max_task_len.saturating_sub(3);
-                let mut char_count = 0;
-                let mut end_idx = 0;
-                for (i, _) in guard.char_indices() {
-                    if char_count >= truncated_len {
-                        break;
+    /// Handle keys and resizes waiting on the terminal, without blocking
+    pub fn handle_input(&mut self) -> Result<()> {
+        if self.terminal.is_none() {
+            return Ok(());
+        }
+        while terminal_event::poll(Duration::ZERO)? {
+            match terminal_event::read()? {
+                terminal_event::Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    // Raw mode swallows SIGINT, so Ctrl-C still has to stop the
+                    // run: the first cancels it, which saves its state and
+                    // summary, and a second quits at once
+                    if key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL)
+                    {
+                        if self.control.is_some() && !self.interrupted {
+                            self.interrupted = true;
+                            self.send_control(ControlMessage::Cancel);
+                            self.state.lock().unwrap().status =
+                                "Cancelling; press Ctrl-C again to quit at once".to_string();
+                            self.render()?;
+                            continue;
+                        }
+                        if let Some(control) = &self.control {
+                            control.quit();
+                            continue;
+                        }
+                        // A replay has nothing to save
+                        self.restore_terminal()?;
+                        println!();
+                        std::process::exit(130);
+                    }
+                    if self.handle_guidance_key(key.code) {
+                        self.render()?;
+                        continue;
+                    }
+                    let open = key.code == KeyCode::Char('e')
+                        || (key.code == KeyCode::Enter && self.view.focus == Pane::Artifacts);
+                    if let Some(message) = self.control_message(key.code) {
+                        self.send_control(message);
+                    } else if open {
+                        self.open_selected_artifact()?;
+                    } else if self.view.handle_key(key) {
+                        self.render()?;
                     }
-                    end_idx = i;
-                    char_count += 1;
-                }
-                if char_count < guard.chars().count() {
-                    format!("{}...", &guard[..end_idx])
-                } else {
-                    guard.clone()
                 }
-            } else {
-                guard.clone()
+                // `draw` picks up the new size and clears the screen
+                terminal_event::Event::Resize(..) => self.render()?,
+                _ => {}
             }
-        } else {
-            "Loading...".to_string()
-        };
-        let task_padding = CONTENT_WIDTH.saturating_sub(task_label.len() + strip_ansi_codes(&task_text).len() + 1);
-
-        print!(
-            "{} {}{}",
-            "║".bright_blue(),
-            task_label.bright_white(),
-            task_text.yellow()
-        );
-        print!("{}", " ".repeat(task_padding));
-        println!("{}", "║".bright_blue());
-        io::stdout().flush()?;
-
-        // Status - only render if there's actual status content
-        let status_text = if let Ok(guard) = self.current_status.try_lock() {
-            guard.clone()
-        } else {
-            String::new()
-        };
-        
-        if !status_text.is_empty() {
-            let status_label = "Status: ";
-            let max_status_len = CONTENT_WIDTH.saturating_sub(status_label.len() + 1);
-            let status_text = if status_text.len() > max_status_len {
-                // Use char_indices to find safe character boundaries for truncation
-                let truncate_at = status_text
-                    .char_indices()
-                    .nth(max_status_len.saturating_sub(3))
-                    .map(|(i, _)| i)
-                    .unwrap_or(status_text.len());
-                format!("{}...", &status_text[..truncate_at])
-            } else {
-                status_text
-            };
-            let status_color = if status_text.starts_with("✅") {
-                status_text.green()
-            } else if status_text.starts_with("❌") {
-                status_text.red()
-            } else {
-                status_text.white()
-            };
-            let status_padding =
-                CONTENT_WIDTH.saturating_sub(status_label.len() + status_text.len() + 1);
-
-            print!(
-                "{} {}{}",
-                "║".bright_blue(),
-                status_label.bright_white(),
-                status_color
-            );
-            print!("{}", " ".repeat(status_padding));
-            println!("{}", "║".bright_blue());
-            io::stdout().flush()?;
         }
+        Ok(())
+    }
 
-        println!("{}", "╠══════════════════════════════════════════════════════════════════════════════════════════════════════════════════════╣".bright_blue());
-
-        // Metrics - build the complete metrics line first
-        let api_calls = if let Ok(guard) = self.api_calls.try_lock() {
-            *guard
-        } else {
-            0
-        };
-        let artifacts = if let Ok(guard) = self.artifacts_created.try_lock() {
-            *guard
-        } else {
-            0
-        };
-        let tasks_completed = if let Ok(guard) = self.tasks_completed.try_lock() {
-            *guard
-        } else {
-            0
-        };
-        let tasks_total = if let Ok(guard) = self.tasks_total.try_lock() {
-            *guard
-        } else {
-            0
-        };
-        let total_cost = if let Ok(guard) = self.total_cost.try_lock() {
-            *guard
-        } else {
-            0.0
+    /// Open the selected artifact in `$VISUAL` or `$EDITOR`, handing it the
+    /// terminal until it exits. The run goes on meanwhile.
+    fn open_selected_artifact(&mut self) -> Result<()> {
+        let path = {
+            let state = self.state.lock().unwrap();
+            match state.artifacts.entries.get(self.view.artifact_selected) {
+                Some(entry) => entry.path.clone(),
+                None => return Ok(()),
+            }
         };
-        let context_usage = if let Ok(guard) = self.context_usage.try_lock() {
-            *guard
-        } else {
-            0.0
+        let editor = std::env::var("VISUAL")
+            .or_else(|_| std::env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_string());
+        let mut words = editor.split_whitespace();
+        let Some(program) = words.next() else {
+            return Ok(());
         };
 
-        let formatted_cost = format!("{:.3}", total_cost);
-        let formatted_tasks = format!("{}/{}", tasks_completed, tasks_total);
-        let formatted_api_calls = api_calls.to_string();
-        let formatted_artifacts = artifacts.to_string();
-        let formatted_context = format!("{:.1}", context_usage);
-
-        // Calculate padding for metrics line
-        let content = format!(
-            "📊 Tasks: {} | 🤖 API Calls: {} | 💰 Cost: ${} | 📝 Artifacts: {} | 💾 Context: {}%",
-            formatted_tasks,
-            formatted_api_calls,
-            formatted_cost,
-            formatted_artifacts,
-            formatted_context
-        );
-        let emoji_adjustment = 10; // Account for emoji display width
-        let metrics_padding = CONTENT_WIDTH.saturating_sub(content.len() + 1 - emoji_adjustment);
-
-        print!("{} ", "║".bright_blue());
-        print!(
-            "📊 Tasks: {} | 🤖 API Calls: {} | 💰 Cost: ${} | 📝 Artifacts: {} | 💾 Context: {}%",
-            formatted_tasks.cyan(),
-            formatted_api_calls.yellow(),
-            formatted_cost.green(),
-            formatted_artifacts.green(),
-            formatted_context
-        );
-        print!("{}", " ".repeat(metrics_padding));
-        println!("{}", "║".bright_blue());
-        println!("{}", "╠══════════════════════════════════════════════════════════════════════════════════════════════════════════════════════╣".bright_blue());
-        io::stdout().flush()?;
-
-        // Split log area into two sections: upper for logs, lower for reasoning traces
-        let log_lines = if let Ok(guard) = self.log_lines.try_lock() {
-            guard.clone()
-        } else {
-            std::collections::VecDeque::new()
-        };
+        disable_raw_mode()?;
+        if let Some(terminal) = self.terminal.as_mut() {
+            terminal.clear()?;
+            terminal.show_cursor()?;
+        }
+        let status = std::process::Command::new(program)
+            .args(words)
+            .arg(&path)
+            .status();
+        enable_raw_mode()?;
+        if let Some(terminal) = self.terminal.as_mut() {
+            terminal.clear()?;
+            terminal.hide_cursor()?;
+        }
 
-        let reasoning_traces = if let Ok(guard) = self.reasoning_traces.try_lock() {
-            guard.clone()
-        } else {
-            std::collections::VecDeque::new()
+        let message = match status {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(format!("{} exited with {}", program, status)),
+            Err(e) => Some(format!("Failed to start {}: {}", program, e)),
         };
+        if let Some(message) = message {
+            self.state.lock().unwrap().status = message;
+        }
+        self.render()
+    }
 
-        // Upper section: Regular logs (15 lines)
-        let log_section_lines = 15;
-        for (i, log_line) in log_lines.iter().enumerate() {
-            if i >= log_section_lines { break; }
-            let max_log_len = CONTENT_WIDTH.saturating_sub(1); // Leave 1 space for right border
-            let visible_log = strip_ansi_codes(log_line);
-            let truncated_log = if visible_log.len() > max_log_len {
-                // Use char_indices to find safe character boundaries
-                let truncate_at = visible_log
-                    .char_indices()
-                    .nth(max_log_len.saturating_sub(3))
-                    .map(|(i, _)| i)
-                    .unwrap_or(visible_log.len());
-                format!("{}...", &visible_log[..truncate_at])
-            } else {
-                log_line.clone()
-            };
-            let visible_truncated = strip_ansi_codes(&truncated_log);
-            let log_padding = CONTENT_WIDTH.saturating_sub(visible_truncated.len() + 1); // +1 for the space after ║
-            print!(
-                "{} {}{}",
-                "║".bright_blue(),
-                truncated_log,
-                " ".repeat(log_padding)
-            );
-            println!("{}", "║".bright_blue());
-            io::stdout().flush()?;
-        }
-
-        // Fill remaining log lines
-        let used_log_lines = std::cmp::min(log_lines.len(), log_section_lines);
-        for _ in used_log_lines..log_section_lines {
-            let log_padding = CONTENT_WIDTH - 1;
-            print!("{} {}", "║".bright_blue(), " ".repeat(log_padding));
-            println!("{}", "║".bright_blue());
-            io::stdout().flush()?;
-        }
-
-        println!("{}", "╠═══════════════════════════════════════════════ 🤔 Model Reasoning ═══════════════════════════════════════════════════╣".bright_blue());
-
-        // Lower section: Reasoning traces (15 lines)
-        let trace_section_lines = 15;
-        
-        // Calculate which traces to show (most recent ones)
-        let traces_to_show: Vec<_> = if reasoning_traces.len() > trace_section_lines {
-            reasoning_traces.iter()
-                .skip(reasoning_traces.len() - trace_section_lines)
-                .collect()
-        } else {
-            reasoning_traces.iter().collect()
-        };
-        
-        // Render the traces
-        let mut lines_rendered = 0;
-        for trace in traces_to_show.iter() {
-            if lines_rendered >= trace_section_lines { break; }
-            
-            // Split trace into lines and render each line
-            for line in trace.split('\n') {
-                if lines_rendered >= trace_section_lines { break; }
-                
-                //let max_trace_len = 110; // Wrap reasoning traces at 110 characters
-                let max_trace_len = CONTENT_WIDTH - 2; // +1 for the space after ║
-                let visible_line = strip_ansi_codes(line);
-                
-                // Wrap the line instead of truncating
-                let wrapped_lines = wrap_text(&visible_line, max_trace_len);
-                
-                for wrapped_line in wrapped_lines {
-                    if lines_rendered >= trace_section_lines { break; }
-                    
-                    let visual_width_wrapped = visual_width(&wrapped_line);
-                    let trace_padding = CONTENT_WIDTH.saturating_sub(visual_width_wrapped + 1); // +1 for the space after ║
-                    print!(
-                        "{} {}{}",
-                        "║".bright_blue(),
-                        wrapped_line.bright_black(), // Show reasoning traces in gray
-                        " ".repeat(trace_padding)
-                    );
-                    println!("{}", "║".bright_blue());
-                    io::stdout().flush()?;
-                    lines_rendered += 1;
+    /// Type guidance for the planner: `f` starts, `Enter` sends and `Esc`
+    /// drops it. `Enter` alone goes on when the loop waits for guidance.
+    /// Returns whether the key was taken.
+    fn handle_guidance_key(&mut self, code: KeyCode) -> bool {
+        if self.control.is_none() {
+            return false;
+        }
+        let Some(input) = self.guidance_input.as_mut() else {
+            let waiting = self.state.lock().unwrap().guidance_requested;
+            match code {
+                KeyCode::Char('f') => self.guidance_input = Some(String::new()),
+                KeyCode::Enter if waiting => {
+                    self.send_control(ControlMessage::Guidance {
+                        text: String::new(),
+                    });
+                    return true;
                 }
+                _ => return false,
+            }
+            self.state.lock().unwrap().status = "Guidance: ▏".to_string();
+            return true;
+        };
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Enter => {
+                let text = self.guidance_input.take().unwrap_or_default();
+                self.send_control(ControlMessage::Guidance { text });
+                return true;
+            }
+            KeyCode::Esc => {
+                self.guidance_input = None;
+                self.state.lock().unwrap().status = "Guidance dropped".to_string();
+                return true;
             }
+            _ => return true,
         }
+        self.state.lock().unwrap().status = format!("Guidance: {}▏", input);
+        true
+    }
 
-        // Fill remaining trace lines if we have fewer lines than allocated space
-        for _ in lines_rendered..trace_section_lines {
-            let trace_padding = CONTENT_WIDTH - 1;
-            print!("{} {}", "║".bright_blue(), " ".repeat(trace_padding));
-            println!("{}", "║".bright_blue());
-            io::stdout().flush()?;
+    /// Control message bound to `code`, if the run can be controlled
+    fn control_message(&self, code: KeyCode) -> Option<ControlMessage> {
+        let control = self.control.as_ref()?;
+        match code {
+            KeyCode::Char('p') if control.is_paused() => Some(ControlMessage::Resume),
+            KeyCode::Char('p') => Some(ControlMessage::Pause),
+            KeyCode::Char('s') => Some(ControlMessage::Skip),
+            KeyCode::Char('c') => Some(ControlMessage::Cancel),
+            _ => None,
         }
-
-        println!("{}", "╚══════════════════════════════════════════════════════════════════════════════════════════════════════════════════════╝".bright_blue());
-
-        // Flush output
-        io::stdout().flush()?;
-
-        Ok(())
     }
 
-    fn render_progress_bar(&self, width: usize) -> String {
-        let progress_val = if let Ok(guard) = self.progress.try_lock() {
-            *guard
-        } else {
-            0.0
+    /// Apply `message`, announcing it so the dashboard and other clients show it
+    fn send_control(&self, message: ControlMessage) {
+        let Some(control) = self.control.clone() else {
+            return;
         };
-        let filled = ((progress_val * width as f32) as usize).min(width);
-        let empty = width - filled;
-
-        format!(
-            "[{}{}] {:.0}%",
-            "█".repeat(filled).green(),
-            "─".repeat(empty).bright_black(),
-            progress_val * 100.0
-        )
+        let Some(bus) = self.event_bus.clone() else {
+            let _ = control.apply(message);
+            return;
+        };
+        tokio::spawn(async move {
+            if let Err(e) = control.send(message, &bus, "dashboard").await {
+                warn!("{}", e);
+            }
+        });
     }
 
     #[allow(dead_code)]
     pub fn update_phase(&mut self, phase: &str) -> Result<()> {
-        *self.current_phase.lock().unwrap() = phase.to_string();
-        *self.progress.lock().unwrap() = 0.0;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.phase = phase.to_string();
+            state.progress = 0.0;
+        }
         self.throttled_render()
     }
 
     #[allow(dead_code)]
     pub fn update_task(&mut self, task: &str) -> Result<()> {
-        *self.current_task.lock().unwrap() = task.to_string();
+        self.state.lock().unwrap().task = task.to_string();
         self.throttled_render()
     }
 
     pub fn update_status(&mut self, status: &str) -> Result<()> {
-        *self.current_status.lock().unwrap() = status.to_string();
+        self.state.lock().unwrap().status = status.to_string();
         self.throttled_render()
     }
 
     #[allow(dead_code)]
     pub fn update_progress(&mut self, progress: f32) -> Result<()> {
-        *self.progress.lock().unwrap() = progress.clamp(0.0, 1.0);
+        self.state.lock().unwrap().progress = progress.clamp(0.0, 1.0);
         self.throttled_render()
     }
 
@@ -567,11 +664,14 @@ impl DashboardUI {
         tasks_total: usize,
         total_cost: f64,
     ) -> Result<()> {
-        *self.api_calls.lock().unwrap() = api_calls;
-        *self.artifacts_created.lock().unwrap() = artifacts;
-        *self.tasks_completed.lock().unwrap() = tasks_completed;
-        *self.tasks_total.lock().unwrap() = tasks_total;
-        *self.total_cost.lock().unwrap() = total_cost;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.api_calls = api_calls;
+            state.artifacts_created = artifacts;
+            state.tasks_completed = tasks_completed;
+            state.tasks_total = tasks_total;
+            state.total_cost = total_cost;
+        }
         self.throttled_render()
     }
 
@@ -586,67 +686,12 @@ impl DashboardUI {
 
     #[allow(dead_code)]
     pub fn handle_event(&mut self, event: Event) -> Result<()> {
-        match event {
-            Event::LogLine { level, message } => {
-                let colored = match level.as_str() {
-                    "ERROR" => format!("[ERROR] {}", message).red().to_string(),
-                    "WARN" => format!("[WARN ] {}", message).yellow().to_string(),
-                    "INFO" => format!("[INFO ] {}", message).cyan().to_string(),
-                    "DEBUG" => format!("[DEBUG] {}", message).white().to_string(),
-                    "TRACE" => format!("[TRACE] {}", message).dimmed().to_string(),
-                    _ => format!("[{}] {}", level, message),
-                };
-                let mut logs = self.log_lines.lock().unwrap();
-                if logs.len() >= 30 {
-                    logs.pop_front();
-                }
-                logs.push_back(colored.clone());
-            }
-            Event::TaskStarted { description, .. } => {
-                self.update_task(&description)?;
-                self.update_status("Running")?;
-            }
-            Event::TaskCompleted { .. } => {
-                self.update_status("Completed")?;
-                self.update_progress(1.0)?;
-                *self.tasks_completed.lock().unwrap() += 1;
-            }
-            Event::ExecutionStarted { .. } => {
-                *self.tasks_total.lock().unwrap() += 1;
-                let iter_count = *self.tasks_total.lock().unwrap();
-                self.update_phase(&format!("Iteration {}", iter_count))?;
-            }
-            Event::APICallStarted { provider, model } => {
-                *self.api_calls.lock().unwrap() += 1;
-                self.update_status(&format!("Calling {}/{}", provider, model))?;
-            }
-            Event::APICallCompleted { cost, .. } => {
-                *self.total_cost.lock().unwrap() += cost as f64;
-                self.update_status("API response received")?;
-            }
-            Event::ArtifactCreated { .. } => {
-                *self.artifacts_created.lock().unwrap() += 1;
-            }
-            Event::ContextUsageChanged {
-                usage_percentage, ..
-            } => {
-                *self.context_usage.lock().unwrap() = usage_percentage;
-            }
-            Event::ReasoningTrace { message } => {
-                if !message.trim().is_empty() {
-                    let mut traces = self.reasoning_traces.lock().unwrap();
-                    if traces.len() >= 30 {
-                        traces.pop_front();
-                    }
-                    traces.push_back(message);
-                }
-            }
-            _ => {}
-        }
-        Ok(())
+        self.state.lock().unwrap().apply(event);
+        self.throttled_render()
     }
 
     pub fn display_error(&mut self, error: &str) -> Result<()> {
+        self.error = Some(error.to_string());
         self.update_status(&format!("❌ {}", error))
     }
 
@@ -656,74 +701,948 @@ impl DashboardUI {
     }
 }
 
+impl Drop for DashboardUI {
+    fn drop(&mut self) {
+        // Never leave the shell in raw mode, even when the run errors out
+        let _ = self.restore_terminal();
+    }
+}
+
 // Implement EventEmitter trait
 impl_event_emitter!(DashboardUI);
 
-// Helper to strip ANSI escape codes
-fn strip_ansi_codes(s: &str) -> String {
-    let mut result = String::with_capacity(s.len());
-    let mut chars = s.chars().peekable();
-    while let Some(c) = chars.next() {
-        if c == '\u{1b}' {
-            // Skip until 'm'
-            while let Some(nc) = chars.next() {
-                if nc == 'm' {
-                    break;
-                }
+/// Whether the terminal is large enough for the dashboard; terminals whose
+/// size is unknown are assumed to be
+pub fn fits_terminal() -> bool {
+    crossterm::terminal::size().map_or(true, |(width, height)| {
+        width >= MIN_WIDTH && height >= MIN_HEIGHT
+    })
+}
+
+/// Draw the dashboard: status and metrics at the top, then the latest change,
+/// logs and model reasoning sharing the remaining height. Terminals too small
+/// for that get a compact single column.
+fn draw(
+    frame: &mut Frame,
+    state: &DashboardState,
+    view: &mut ViewState,
+    theme: &Theme,
+    elapsed: Duration,
+) {
+    let border = theme.fg(theme.border);
+    let area = frame.area();
+    let inner_width = area.width.saturating_sub(2);
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        draw_too_small(frame, theme);
+        return;
+    }
+
+    let metrics = metrics_lines(state, theme, inner_width);
+    view.artifact_count = state.artifacts.len();
+    view.artifact_selected = view
+        .artifact_selected
+        .min(view.artifact_count.saturating_sub(1));
+    if view.is_hidden(view.focus) {
+        view.focus = view.next_shown(view.focus);
+    }
+    let artifacts_height = if view.is_hidden(Pane::Artifacts) {
+        0
+    } else {
+        view.artifact_count.min(ARTIFACT_PANEL_LINES) as u16 + 2
+    };
+    let plan_height = if state.plan.is_empty() {
+        0
+    } else {
+        state.plan.steps.len().min(PLAN_PANEL_LINES) as u16 + 2
+    };
+    let diff_height = if state.latest_diff.is_some() {
+        DIFF_PANEL_LINES as u16 + 3
+    } else {
+        0
+    };
+    let panels_height =
+        5 + metrics.len() as u16 + 2 + plan_height + artifacts_height + diff_height + 1;
+    if area.height < panels_height + MIN_PANE_HEIGHT {
+        draw_compact(frame, state, view, theme, elapsed);
+        return;
+    }
+    let [
+        header_area,
+        metrics_area,
+        plan_area,
+        artifacts_area,
+        diff_area,
+        logs_area,
+        reasoning_area,
+        footer_area,
+    ] = Layout::vertical([
+        Constraint::Length(5),
+        Constraint::Length(metrics.len() as u16 + 2),
+        Constraint::Length(plan_height),
+        Constraint::Length(artifacts_height),
+        Constraint::Length(diff_height),
+        pane_constraint(view.logs_hidden),
+        pane_constraint(view.reasoning_hidden),
+        Constraint::Length(1),
+    ])
+    .areas(area);
+
+    draw_header(frame, header_area, state, theme, elapsed);
+    frame.render_widget(
+        Paragraph::new(metrics).block(Block::bordered().border_style(border)),
+        metrics_area,
+    );
+    if !state.plan.is_empty() {
+        draw_plan(frame, plan_area, &state.plan, theme);
+    }
+    if !view.is_hidden(Pane::Artifacts) {
+        draw_artifacts(
+            frame,
+            artifacts_area,
+            &state.artifacts,
+            view.artifact_selected,
+            view.focus == Pane::Artifacts,
+            theme,
+        );
+    }
+
+    if let Some((title, lines)) = &state.latest_diff {
+        let lines: Vec<Line> = lines
+            .iter()
+            .map(|line| {
+                let color = if line.starts_with('+') {
+                    theme.success
+                } else if line.starts_with('-') {
+                    theme.error
+                } else if line.starts_with("@@") {
+                    theme.accent
+                } else {
+                    theme.muted
+                };
+                Line::styled(line.clone(), theme.fg(color))
+            })
+            .collect();
+        let block = Block::bordered()
+            .border_style(border)
+            .title(Line::from(vec![
+                Span::raw(" 📄 Latest change: "),
+                Span::styled(
+                    format!("{} ", title),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+            ]));
+        frame.render_widget(Paragraph::new(lines).block(block), diff_area);
+    }
+
+    let focus = view.focus;
+    if !view.logs_hidden {
+        draw_pane(
+            frame,
+            logs_area,
+            " Logs ",
+            log_lines(state, theme, inner_width),
+            &mut view.log_scroll,
+            focus == Pane::Logs,
+            theme,
+        );
+    }
+    if !view.reasoning_hidden {
+        draw_pane(
+            frame,
+            reasoning_area,
+            " 🤔 Model Reasoning ",
+            reasoning_lines(state, theme, inner_width),
+            &mut view.reasoning_scroll,
+            focus == Pane::Reasoning,
+            theme,
+        );
+    }
+    frame.render_widget(footer(state, view, theme), footer_area);
+}
+
+/// One column without panels: phase and progress, task, key metrics, the
+/// running step, then the logs or the model reasoning, whichever has focus
+fn draw_compact(
+    frame: &mut Frame,
+    state: &DashboardState,
+    view: &mut ViewState,
+    theme: &Theme,
+    elapsed: Duration,
+) {
+    let label = theme.fg(theme.text);
+    let [
+        phase_row,
+        task_row,
+        metrics_row,
+        step_row,
+        pane_area,
+        footer_area,
+    ] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Fill(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let width = frame.area().width;
+
+    let [phase_area, gauge_area] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Length(width / 3)]).areas(phase_row);
+    frame.render_widget(
+        Line::from(vec![
+            Span::styled("Phase: ", label),
+            Span::styled(state.phase.as_str(), theme.fg(theme.accent)),
+        ]),
+        phase_area,
+    );
+    draw_gauge(frame, gauge_area, state, theme);
+    frame.render_widget(
+        Line::from(vec![
+            Span::styled("Task: ", label),
+            Span::styled(
+                truncate(&state.task, width.saturating_sub(6) as usize),
+                theme.fg(theme.highlight),
+            ),
+        ]),
+        task_row,
+    );
+    let metrics = format!(
+        "{}:{:02} · ${:.3} · {} calls · {} artifacts · context {:.0}%",
+        elapsed.as_secs() / 60,
+        elapsed.as_secs() % 60,
+        state.total_cost,
+        state.api_calls,
+        state.artifacts_created,
+        state.context_usage
+    );
+    frame.render_widget(
+        Line::styled(truncate(&metrics, width as usize), theme.fg(theme.muted)),
+        metrics_row,
+    );
+    let step = state.plan.current().map(|index| {
+        let step = &state.plan.steps[index];
+        format!(
+            "{} {}/{} {}",
+            step.state.icon(),
+            index + 1,
+            state.plan.steps.len(),
+            step.description
+        )
+    });
+    let step = step.unwrap_or_else(|| state.status.clone());
+    frame.render_widget(
+        Line::styled(truncate(&step, width as usize), theme.fg(theme.text)),
+        step_row,
+    );
+
+    let inner_width = width.saturating_sub(2);
+    let show_reasoning =
+        view.logs_hidden || (view.focus == Pane::Reasoning && !view.reasoning_hidden);
+    if show_reasoning && !view.reasoning_hidden {
+        draw_pane(
+            frame,
+            pane_area,
+            " 🤔 Model Reasoning ",
+            reasoning_lines(state, theme, inner_width),
+            &mut view.reasoning_scroll,
+            true,
+            theme,
+        );
+    } else if !view.logs_hidden {
+        draw_pane(
+            frame,
+            pane_area,
+            " Logs ",
+            log_lines(state, theme, inner_width),
+            &mut view.log_scroll,
+            view.focus == Pane::Logs,
+            theme,
+        );
+    }
+    frame.render_widget(footer(state, view, theme), footer_area);
+}
+
+/// What is drawn in a terminal smaller than `MIN_WIDTH` by `MIN_HEIGHT`
+fn draw_too_small(frame: &mut Frame, theme: &Theme) {
+    let area = frame.area();
+    let lines = vec![
+        Line::styled(
+            format!("Terminal too small: {}x{}", area.width, area.height),
+            theme.fg(theme.warning),
+        ),
+        Line::styled(
+            format!("The dashboard needs {}x{}", MIN_WIDTH, MIN_HEIGHT),
+            theme.fg(theme.muted),
+        ),
+    ];
+    frame.render_widget(Paragraph::new(lines), area);
+}
+
+/// Log lines wrapped to `width`, colored by level
+fn log_lines(state: &DashboardState, theme: &Theme, width: u16) -> Vec<Line<'static>> {
+    state
+        .log_lines
+        .iter()
+        .flat_map(|(level, message)| {
+            let style = match level.as_str() {
+                "ERROR" => theme.fg(theme.error),
+                "WARN" => theme.fg(theme.warning),
+                "INFO" => theme.fg(theme.accent),
+                "DEBUG" => theme.fg(theme.text),
+                "TRACE" => theme.fg(theme.muted),
+                _ => Style::default(),
+            };
+            wrap_lines(&format!("[{:<5}] {}", level, message), width, style)
+        })
+        .collect()
+}
+
+fn reasoning_lines(state: &DashboardState, theme: &Theme, width: u16) -> Vec<Line<'static>> {
+    state
+        .reasoning_traces
+        .iter()
+        .flat_map(|trace| wrap_lines(trace, width, theme.fg(theme.muted)))
+        .collect()
+}
+
+/// The plan as a checklist, scrolled to keep the running step in view
+fn draw_plan(frame: &mut Frame, area: Rect, plan: &PlanProgress, theme: &Theme) {
+    let visible = PLAN_PANEL_LINES.min(plan.steps.len());
+    let first = plan
+        .current()
+        .unwrap_or(0)
+        .saturating_sub(visible / 2)
+        .min(plan.steps.len() - visible);
+    let width = area.width.saturating_sub(2);
+
+    let lines: Vec<Line> = plan.steps[first..first + visible]
+        .iter()
+        .map(|step| {
+            let (color, text) = match step.state {
+                StepState::Pending => (theme.muted, theme.muted),
+                StepState::Running => (theme.highlight, theme.text),
+                StepState::Done => (theme.success, theme.text),
+                StepState::Failed => (theme.error, theme.error),
+                StepState::Skipped => (theme.muted, theme.muted),
+            };
+            let elapsed = step
+                .elapsed()
+                .map(|elapsed| format!(" {}", format_elapsed(elapsed)))
+                .unwrap_or_default();
+            let icons = format!("{} {} ", step.state.icon(), step.category_icon());
+            let description_width =
+                (width as usize).saturating_sub(display_width(&icons) + elapsed.len());
+            Line::from(vec![
+                Span::styled(format!("{} ", step.state.icon()), theme.fg(color)),
+                Span::raw(format!("{} ", step.category_icon())),
+                Span::styled(
+                    truncate(&step.description, description_width),
+                    theme.fg(text),
+                ),
+                Span::styled(elapsed, theme.fg(theme.muted)),
+            ])
+        })
+        .collect();
+    let block = Block::bordered()
+        .border_style(theme.fg(theme.border))
+        .title(format!(
+            " 📋 Plan · iteration {} · {}/{} steps ",
+            plan.iteration,
+            plan.finished(),
+            plan.steps.len()
+        ));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Artifacts of the run with their type, size, change and apply status,
+/// scrolled to keep the selected one in view
+fn draw_artifacts(
+    frame: &mut Frame,
+    area: Rect,
+    artifacts: &ArtifactList,
+    selected: usize,
+    focused: bool,
+    theme: &Theme,
+) {
+    let visible = ARTIFACT_PANEL_LINES.min(artifacts.len());
+    let first = selected
+        .saturating_sub(visible / 2)
+        .min(artifacts.len() - visible);
+    let width = area.width.saturating_sub(2) as usize;
+
+    let lines: Vec<Line> = artifacts.entries[first..first + visible]
+        .iter()
+        .enumerate()
+        .map(|(offset, entry)| {
+            let mut details = Vec::new();
+            if !entry.artifact_type.is_empty() {
+                details.push(entry.artifact_type.clone());
             }
-        } else {
-            result.push(c);
+            if let Some(size) = entry.size {
+                details.push(format_size(size));
+            }
+            details.push(entry.change());
+            let details = format!(" {} ", details.join(" · "));
+            let (status, color) = match entry.status {
+                Some(ApplyStatus::New) => (ApplyStatus::New.label(), theme.accent),
+                Some(ApplyStatus::Pending) => (ApplyStatus::Pending.label(), theme.warning),
+                Some(ApplyStatus::Applied) => (ApplyStatus::Applied.label(), theme.success),
+                None => ("", theme.muted),
+            };
+            let name_width =
+                width.saturating_sub(2 + display_width(&details) + display_width(status));
+            let name = truncate(&entry.name, name_width);
+            let padding = " ".repeat(name_width.saturating_sub(display_width(&name)));
+
+            let is_selected = first + offset == selected;
+            let marker = if is_selected && focused { "▶ " } else { "  " };
+            let mut name_style = theme.fg(theme.text);
+            if is_selected && focused {
+                name_style = name_style.add_modifier(Modifier::BOLD);
+            }
+            Line::from(vec![
+                Span::styled(marker, theme.fg(theme.highlight)),
+                Span::styled(format!("{}{}", name, padding), name_style),
+                Span::styled(details, theme.fg(theme.muted)),
+                Span::styled(status, theme.fg(color)),
+            ])
+        })
+        .collect();
+
+    let mut block = Block::bordered()
+        .border_style(theme.fg(if focused { theme.accent } else { theme.border }))
+        .title(format!(" 📦 Artifacts · {} ", artifacts.len()));
+    if focused {
+        if !theme.colored {
+            block = block.border_type(BorderType::Thick);
         }
+        block = block.title(Line::from(" e: open in $EDITOR ").right_aligned());
     }
-    result
+    frame.render_widget(Paragraph::new(lines).block(block), area);
 }
 
-// Helper function to calculate visual width (accounting for emoji width)
-fn visual_width(s: &str) -> usize {
-    s.chars().map(|c| {
-        match c {
-            // Common emojis used in reasoning traces
-            '🤔' | '✨' | '🔍' | '💭' | '🧠' | '⚡' | '🎯' | '💡' => 2,
-            // Regular characters
-            _ => 1,
-        }
-    }).sum()
+/// Hidden panes take no space; shown ones share what is left
+fn pane_constraint(hidden: bool) -> Constraint {
+    if hidden {
+        Constraint::Length(0)
+    } else {
+        Constraint::Fill(1)
+    }
+}
+
+/// Key bindings, led by the run controls when the run can be controlled
+fn footer(state: &DashboardState, view: &ViewState, theme: &Theme) -> Line<'static> {
+    let key = theme.fg(theme.title).add_modifier(Modifier::BOLD);
+    let hint = theme.fg(theme.muted);
+    let mut bindings = Vec::new();
+    if view.has_controls {
+        bindings.push(("p", if state.paused { "resume" } else { "pause" }));
+        bindings.extend([("s", "skip step"), ("c", "cancel"), ("f", "guidance")]);
+    }
+    if !state.artifacts.is_empty() {
+        bindings.extend([("e", "edit"), ("a", "artifacts")]);
+    }
+    bindings.extend([
+        ("r", "reasoning"),
+        ("l", "logs"),
+        ("↑↓", "scroll"),
+        ("End", "follow"),
+        ("Tab", "switch pane"),
+    ]);
+
+    let mut spans = Vec::new();
+    if state.paused {
+        spans.push(Span::styled(" PAUSED ", theme.badge(theme.warning)));
+    }
+    for (keys, action) in bindings {
+        spans.push(Span::styled(format!(" {}", keys), key));
+        spans.push(Span::styled(format!(" {} ", action), hint));
+    }
+    Line::from(spans)
+}
+
+/// Title with the elapsed time, then phase and progress, task and status
+fn draw_header(
+    frame: &mut Frame,
+    area: Rect,
+    state: &DashboardState,
+    theme: &Theme,
+    elapsed: Duration,
+) {
+    let label = theme.fg(theme.text);
+    let block = Block::bordered()
+        .border_style(theme.fg(theme.border))
+        .title(Span::styled(
+            " CLI Engineer ",
+            theme.fg(theme.title).add_modifier(Modifier::BOLD),
+        ))
+        .title(
+            Line::from(format!(
+                " {}:{:02} ",
+                elapsed.as_secs() / 60,
+                elapsed.as_secs() % 60
+            ))
+            .right_aligned(),
+        );
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let [phase_row, task_row, status_row] =
+        Layout::vertical([Constraint::Length(1); 3]).areas(inner);
+    let gauge_width = (inner.width / 2).min(60);
+    let [phase_area, gauge_area] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Length(gauge_width)]).areas(phase_row);
+    frame.render_widget(
+        Line::from(vec![
+            Span::styled("Phase: ", label),
+            Span::styled(state.phase.as_str(), theme.fg(theme.accent)),
+        ]),
+        phase_area,
+    );
+    draw_gauge(frame, gauge_area, state, theme);
+    frame.render_widget(
+        Line::from(vec![
+            Span::styled("Task: ", label),
+            Span::styled(
+                truncate(&state.task, task_row.width.saturating_sub(6) as usize),
+                theme.fg(theme.highlight),
+            ),
+        ]),
+        task_row,
+    );
+    if !state.status.is_empty() {
+        let color = if state.status.starts_with('✅') {
+            theme.success
+        } else if state.status.starts_with('❌') {
+            theme.error
+        } else {
+            theme.text
+        };
+        frame.render_widget(
+            Line::from(vec![
+                Span::styled("Status: ", label),
+                Span::styled(
+                    truncate(&state.status, status_row.width.saturating_sub(8) as usize),
+                    theme.fg(color),
+                ),
+            ]),
+            status_row,
+        );
+    }
+}
+
+/// Progress of the run; plans show steps done rather than an opaque percentage
+fn draw_gauge(frame: &mut Frame, area: Rect, state: &DashboardState, theme: &Theme) {
+    let (ratio, label) = if state.plan.is_empty() || state.progress >= 1.0 {
+        (
+            state.progress.clamp(0.0, 1.0) as f64,
+            format!("{:>3.0}%", state.progress * 100.0),
+        )
+    } else {
+        (
+            state.plan.ratio(),
+            format!("{}/{}", state.plan.finished(), state.plan.steps.len()),
+        )
+    };
+    frame.render_widget(
+        LineGauge::default()
+            .ratio(ratio)
+            .label(label)
+            .filled_style(theme.fg(theme.success))
+            .unfilled_style(theme.fg(theme.muted)),
+        area,
+    );
 }
 
-// Helper function to wrap text at word boundaries
-fn wrap_text(text: &str, max_width: usize) -> Vec<String> {
+/// Metrics, context window usage and budget alert, with the metrics spread
+/// over as many lines as `width` requires
+fn metrics_lines(state: &DashboardState, theme: &Theme, width: u16) -> Vec<Line<'static>> {
+    let artifacts = if state.artifact_versions > state.artifacts_created {
+        format!(
+            "{} ({} versions)",
+            state.artifacts_created, state.artifact_versions
+        )
+    } else {
+        state.artifacts_created.to_string()
+    };
+    let metrics = [
+        (
+            "📊 Tasks: ",
+            format!("{}/{}", state.tasks_completed, state.tasks_total),
+            theme.accent,
+        ),
+        (
+            "🤖 API Calls: ",
+            state.api_calls.to_string(),
+            theme.highlight,
+        ),
+        (
+            "💰 Cost: $",
+            format!("{:.3}", state.total_cost),
+            theme.success,
+        ),
+        ("📝 Artifacts: ", artifacts, theme.success),
+        (
+            "💾 Context: ",
+            format!("{:.1}%", state.context_usage),
+            theme.text,
+        ),
+        speed_metric(&state.throughput, theme),
+        latency_metric(&state.throughput, theme),
+    ];
+
     let mut lines = Vec::new();
-    let mut current_line = String::new();
+    let mut current: Vec<Span<'static>> = Vec::new();
     let mut current_width = 0;
+    for (label, value, color) in metrics {
+        let item = [Span::raw(label), Span::styled(value, theme.fg(color))];
+        let item_width: usize = item.iter().map(Span::width).sum();
+        if !current.is_empty() && current_width + 3 + item_width > width as usize {
+            lines.push(Line::from(std::mem::take(&mut current)));
+            current_width = 0;
+        }
+        if !current.is_empty() {
+            current.push(Span::styled(" | ", theme.fg(theme.muted)));
+            current_width += 3;
+        }
+        current_width += item_width;
+        current.extend(item);
+    }
+    lines.push(Line::from(current));
 
-    for word in text.split_whitespace() {
-        let word_visual_width = visual_width(word);
-        
-        // Check if adding this word would exceed the limit
-        if current_width + word_visual_width + (if current_line.is_empty() { 0 } else { 1 }) <= max_width {
-            if !current_line.is_empty() {
-                current_line.push(' ');
-                current_width += 1;
-            }
-            current_line.push_str(word);
-            current_width += word_visual_width;
+    lines.push(Line::from(vec![
+        Span::raw("🧠 Context window: "),
+        Span::styled(state.context_breakdown.to_string(), theme.fg(theme.muted)),
+    ]));
+    if let Some((pct, message)) = &state.budget_alert {
+        let color = if *pct >= 100 {
+            theme.error
         } else {
-            // Start a new line
-            if !current_line.is_empty() {
-                lines.push(current_line);
+            theme.warning
+        };
+        lines.push(Line::from(vec![
+            Span::styled("Budget: ", theme.fg(theme.text)),
+            Span::styled(
+                message.clone(),
+                theme.fg(color).add_modifier(Modifier::BOLD),
+            ),
+        ]));
+    }
+    lines
+}
+
+/// Output rate of the call in progress, or else the rolling average, colored
+/// to tell a slow model from a hung connection
+fn speed_metric(throughput: &Throughput, theme: &Theme) -> (&'static str, String, Color) {
+    let average = throughput
+        .average_rate()
+        .map(|rate| format!("avg {:.0} tok/s", rate));
+    let (value, color) = match throughput.active() {
+        Some(CallState::Streaming { rate, .. }) => (
+            match &average {
+                Some(average) => format!("{:.0} tok/s ({})", rate, average),
+                None => format!("{:.0} tok/s", rate),
+            },
+            theme.success,
+        ),
+        Some(CallState::Waiting(waited)) => (
+            format!("waiting {}", format_elapsed(waited)),
+            if waited >= STALL_AFTER {
+                theme.warning
+            } else {
+                theme.muted
+            },
+        ),
+        Some(CallState::Stalled { .. }) => ("stalled".to_string(), theme.error),
+        None => (average.unwrap_or_else(|| "-".to_string()), theme.text),
+    };
+    ("⚡ Speed: ", value, color)
+}
+
+/// Time taken by the call in progress, or else by the last call
+fn latency_metric(throughput: &Throughput, theme: &Theme) -> (&'static str, String, Color) {
+    let average = throughput
+        .average_latency()
+        .map(|latency| format!("avg {}", format_elapsed(latency)));
+    let elapsed = match throughput.active() {
+        Some(CallState::Waiting(elapsed))
+        | Some(CallState::Streaming { elapsed, .. })
+        | Some(CallState::Stalled { elapsed }) => Some(elapsed),
+        None => throughput.last_latency(),
+    };
+    let value = match (elapsed, average) {
+        (Some(elapsed), Some(average)) => format!("{} ({})", format_elapsed(elapsed), average),
+        (Some(elapsed), None) => format_elapsed(elapsed),
+        (None, _) => "-".to_string(),
+    };
+    ("⏱ Latency: ", value, theme.accent)
+}
+
+/// Bordered pane showing the lines that fit, `scroll` lines up from the
+/// bottom. `scroll` is clamped so it never passes the oldest line.
+fn draw_pane(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    lines: Vec<Line<'static>>,
+    scroll: &mut usize,
+    focused: bool,
+    theme: &Theme,
+) {
+    let mut block = Block::bordered()
+        .border_style(theme.fg(if focused { theme.accent } else { theme.border }))
+        .title(title);
+    // Without colors the focused pane stands out by its heavier border
+    if focused && !theme.colored {
+        block = block.border_type(BorderType::Thick);
+    }
+    let height = block.inner(area).height as usize;
+    let max_scroll = lines.len().saturating_sub(height);
+    *scroll = (*scroll).min(max_scroll);
+    if *scroll > 0 {
+        block = block.title(Line::from(format!(" ↑ {} more below ", *scroll)).right_aligned());
+    }
+    let end = lines.len() - *scroll;
+    let visible: Vec<Line> = lines
+        .into_iter()
+        .skip(end.saturating_sub(height))
+        .take(height)
+        .collect();
+    frame.render_widget(Paragraph::new(visible).block(block), area);
+}
+
+/// Wrap each line of `text` to `width` columns, dropping escape codes
+fn wrap_lines(text: &str, width: u16, style: Style) -> Vec<Line<'static>> {
+    text.split('\n')
+        .flat_map(|line| wrap(&strip_ansi(line), width as usize))
+        .map(|line| Line::styled(line, style))
+        .collect()
+}
+
+fn push_bounded<T>(entries: &mut VecDeque<T>, entry: T) {
+    if entries.len() >= MAX_PANE_ENTRIES {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
+}
+
+/// Title and body lines of the latest change panel, without the file headers
+fn diff_panel(name: &str, against: &str, diff: &str) -> DiffPanel {
+    let lines = diff
+        .lines()
+        .filter(|l| !l.starts_with("---") && !l.starts_with("+++"))
+        .map(|l| l.replace('\t', "    "))
+        .take(DIFF_PANEL_LINES)
+        .collect();
+    (format!("{} (against {})", name, against), lines)
+}
+
+/// Dashboard line for a budget threshold: `80% of cost budget used ($4.00 of $5.00)`
+fn budget_alert_message(pct: u8, kind: BudgetKind, used: f64, limit: f64) -> String {
+    format!(
+        "{}% of {} budget used ({} of {})",
+        pct,
+        kind,
+        kind.format_amount(used),
+        kind.format_amount(limit)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::StepStatus;
+    use ratatui::backend::TestBackend;
+
+    fn render_to_text(state: &DashboardState, view: &mut ViewState, width: u16) -> String {
+        render_sized(state, view, width, 30)
+    }
+
+    fn render_sized(
+        state: &DashboardState,
+        view: &mut ViewState,
+        width: u16,
+        height: u16,
+    ) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal
+            .draw(|frame| {
+                draw(
+                    frame,
+                    state,
+                    view,
+                    &Theme::default(),
+                    Duration::from_secs(75),
+                )
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_dashboard_layout_and_scrolling() {
+        let mut state = DashboardState::default();
+        state.apply(Event::ExecutionStarted {
+            environment: "test".to_string(),
+        });
+        for i in 0..40 {
+            state.apply(Event::LogLine {
+                level: "INFO".to_string(),
+                message: format!("log line {}", i),
+            });
+        }
+        state.apply(Event::ReasoningTrace {
+            message: "🤔 日本語のテキストは幅が二倍です 🚀 and wide emoji too 🎯".repeat(2),
+        });
+        let mut view = ViewState::default();
+
+        // Narrow terminals wrap the metrics and wide text instead of breaking
+        // the borders
+        let text = render_to_text(&state, &mut view, 50);
+        assert!(text.contains("Iteration 1") && text.contains("1:15"));
+        assert!(text.contains("Cost: $0.000") && text.contains("Context: 0.0%"));
+        assert!(text.contains("log line 39") && !text.contains("log line 0 "));
+        for line in text.lines().take(29) {
+            assert!(line.ends_with(['│', '┐', '┘']), "{}", line);
+        }
+
+        // Scrolling back past the oldest line stops at it
+        view.handle_key(KeyEvent::from(KeyCode::Home));
+        let text = render_to_text(&state, &mut view, 50);
+        assert!(text.contains("log line 0 ") && !text.contains("log line 39"));
+        assert!(view.log_scroll > 0 && view.log_scroll < 40);
+
+        view.handle_key(KeyEvent::from(KeyCode::End));
+        view.handle_key(KeyEvent::from(KeyCode::Tab));
+        assert_eq!((view.focus, view.log_scroll), (Pane::Reasoning, 0));
+
+        // Hiding the focused pane gives its space and the focus to the other
+        view.handle_key(KeyEvent::from(KeyCode::Char('r')));
+        assert_eq!(view.focus, Pane::Logs);
+        view.has_controls = true;
+        state.apply(Event::Custom {
+            event_type: "run_paused".to_string(),
+            data: serde_json::json!({ "source": "ipc" }),
+        });
+        let text = render_to_text(&state, &mut view, 80);
+        assert!(!text.contains("Model Reasoning") && text.contains("log line 25"));
+        assert!(!text.contains("Plan ·"));
+        assert!(
+            text.contains("PAUSED") && text.contains("p resume") && text.contains("s skip step")
+        );
+
+        // The plan checklist follows the running step
+        state.apply(Event::Custom {
+            event_type: "plan_execution_started".to_string(),
+            data: serde_json::json!({
+                "iteration": 1,
+                "plan_goal": "Review",
+                "steps": (1..=8).map(|i| serde_json::json!({
+                    "id": format!("step_{}", i),
+                    "description": format!("Step number {}", i),
+                    "category": "Review",
+                })).collect::<Vec<_>>(),
+            }),
+        });
+        for i in 1..=7 {
+            state.apply(Event::StepStarted {
+                step_id: format!("step_{}", i),
+                number: i,
+                total: 8,
+                description: String::new(),
+            });
+            if i < 7 {
+                state.apply(Event::StepFinished {
+                    step_id: format!("step_{}", i),
+                    status: StepStatus::Done,
+                    duration_ms: 12_000,
+                    error: None,
+                });
             }
-            current_line = word.to_string();
-            current_width = word_visual_width;
         }
+        let text = render_to_text(&state, &mut view, 80);
+        assert!(text.contains("Plan · iteration 1 · 6/8 steps"));
+        let plan_line = |number: usize| {
+            let description = format!("Step number {} ", number);
+            text.lines()
+                .find(|line| line.contains(&description))
+                .map(str::to_string)
+        };
+        assert!(plan_line(6).unwrap().contains("✔ 👀") && plan_line(6).unwrap().contains("12s"));
+        assert!(plan_line(7).unwrap().contains("▶ 👀"));
+        assert!(plan_line(8).unwrap().contains("○ 👀"));
+        assert!(plan_line(1).is_none());
+
+        // Artifacts get a pane of their own, reachable with Tab
+        for name in ["main.rs", "lib.rs"] {
+            state.apply(Event::ArtifactCreated {
+                name: name.to_string(),
+                path: format!("/nonexistent/{}", name),
+                artifact_type: "SourceCode".to_string(),
+            });
+        }
+        state.apply(Event::ArtifactUpdated {
+            name: "lib.rs".to_string(),
+            path: "/nonexistent/lib.rs".to_string(),
+            version: 2,
+        });
+        let text = render_to_text(&state, &mut view, 80);
+        assert!(text.contains("Artifacts · 2") && text.contains("e edit"));
+        assert!(text.contains("SourceCode · updated v2"));
+        view.handle_key(KeyEvent::from(KeyCode::Tab));
+        view.handle_key(KeyEvent::from(KeyCode::Down));
+        let text = render_to_text(&state, &mut view, 80);
+        assert_eq!((view.focus, view.artifact_selected), (Pane::Artifacts, 1));
+        assert!(text.contains("▶ lib.rs") && text.contains("open in $EDITOR"));
+        view.handle_key(KeyEvent::from(KeyCode::Char('a')));
+        let text = render_to_text(&state, &mut view, 80);
+        assert_eq!(view.focus, Pane::Logs);
+        assert!(!text.contains("Artifacts ·"));
     }
-    
-    if !current_line.is_empty() {
-        lines.push(current_line);
-    }
-    
-    if lines.is_empty() {
-        lines.push(String::new());
+
+    #[test]
+    fn test_small_terminals_get_a_compact_layout() {
+        let mut state = DashboardState::default();
+        state.apply(Event::TaskStarted {
+            task_id: "task".to_string(),
+            description: "Write a calculator".to_string(),
+        });
+        for i in 0..20 {
+            state.apply(Event::LogLine {
+                level: "INFO".to_string(),
+                message: format!("log line {}", i),
+            });
+        }
+        state.apply(Event::ReasoningTrace {
+            message: "Thinking about it".to_string(),
+        });
+        let mut view = ViewState::default();
+
+        // Too short for the panels: one column with the logs under the status
+        let text = render_sized(&state, &mut view, 60, 16);
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(lines[1].contains("Task: Write a calculator"));
+        assert!(lines[2].contains("1:15") && lines[2].contains("$0.000"));
+        assert!(text.contains("log line 19") && !text.contains("Model Reasoning"));
+        assert!(!text.contains("Cost:"));
+
+        // The focused pane is the one shown
+        view.handle_key(KeyEvent::from(KeyCode::Tab));
+        let text = render_sized(&state, &mut view, 60, 16);
+        assert!(text.contains("Model Reasoning") && text.contains("Thinking about it"));
+        assert!(!text.contains("log line 19"));
+
+        let text = render_sized(&state, &mut view, 30, 6);
+        assert!(text.contains("Terminal too small: 30x6"));
     }
-    
-    lines
 }
@@ -15,6 +15,11 @@ use tokio::sync::RwLock;
 
 use crate::event_bus::{Event, EventBus, EventEmitter, Metrics};
 use crate::impl_event_emitter;
+use crate::plan_progress::{
+    PLAN_EXECUTION_STARTED_EVENT, PlanProgress, PlanStep, StepState, format_elapsed,
+};
+use crate::terminal_text::{display_width, truncate, wrap};
+use crate::theme::Theme;
 
 /// Enhanced terminal UI with colors, progress bars, and metrics
 pub struct EnhancedUI {
@@ -25,6 +30,7 @@ pub struct EnhancedUI {
     event_bus: Option<Arc<EventBus>>,
     start_time: Instant,
     last_metrics: Arc<RwLock<Metrics>>,
+    theme: Theme,
 }
 
 impl EnhancedUI {
@@ -37,31 +43,42 @@ impl EnhancedUI {
             event_bus: None,
             start_time: Instant::now(),
             last_metrics: Arc::new(RwLock::new(Metrics::default())),
+            theme: Theme::default(),
         }
     }
 
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
     pub fn start(&mut self) -> Result<()> {
         if self.headless {
             return Ok(());
         }
 
         // Clear screen and print header
+        let theme = self.theme;
         execute!(io::stdout(), Clear(ClearType::All), MoveTo(0, 0))?;
-        println!("{}", "=".repeat(80).bright_blue());
+        println!("{}", theme.paint(&rule(), theme.border));
         println!(
             "{}",
-            "CLI Engineer - Autonomous Coding Agent"
-                .bright_white()
+            theme
+                .paint("CLI Engineer - Autonomous Coding Agent", theme.title)
                 .bold()
         );
-        println!("{}", "=".repeat(80).bright_blue());
+        println!("{}", theme.paint(&rule(), theme.border));
         println!();
 
         // Create main progress bar
         let main_progress = self.multi_progress.add(ProgressBar::new(100));
         main_progress.set_style(
             ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] {wide_bar:.cyan/blue} {pos}% {msg}")
+                .template(&format!(
+                    "{} [{{elapsed_precise}}] {} {{pos}}% {{msg}}",
+                    theme.placeholder("spinner", theme.success, None),
+                    theme.placeholder("wide_bar", theme.accent, Some(theme.border))
+                ))
                 .unwrap()
                 .progress_chars("█▓▒░"),
         );
@@ -82,15 +99,18 @@ impl EnhancedUI {
             let mut receiver = bus.subscribe();
 
             tokio::spawn(async move {
+                let mut checklist = PlanChecklist::new(theme);
                 loop {
                     match receiver.recv().await {
                         Ok(event) => {
+                            checklist.apply(&event, &multi_progress, &main_progress);
                             Self::handle_event(
                                 event,
                                 &multi_progress,
                                 &main_progress,
                                 &metrics_bar,
                                 &last_metrics,
+                                &theme,
                             )
                             .await;
                         }
@@ -116,18 +136,37 @@ impl EnhancedUI {
                         let elapsed = start_time.elapsed().as_secs();
                         let status = format!(
                             "{} | {} | {} | {} | {} | {}",
-                            format!("⏱️  {:02}:{:02}", elapsed / 60, elapsed % 60).bright_white(),
-                            format!(
-                                "📊 Tasks: {}/{}",
-                                metrics.tasks_completed,
-                                metrics.tasks_completed + metrics.tasks_failed
-                            )
-                            .bright_green(),
-                            format!("🤖 API Calls: {}", metrics.total_api_calls).bright_cyan(),
-                            format!("💰 Cost: ${:.4}", metrics.total_cost).bright_yellow(),
-                            format!("📝 Artifacts: {}", metrics.artifacts_created).bright_magenta(),
-                            format!("💾 Context: {:.0}%", metrics.current_context_usage)
-                                .bright_blue(),
+                            theme.paint(
+                                &format!("⏱️  {:02}:{:02}", elapsed / 60, elapsed % 60),
+                                theme.text
+                            ),
+                            theme.paint(
+                                &format!(
+                                    "📊 Tasks: {}/{}",
+                                    metrics.tasks_completed,
+                                    metrics.tasks_completed + metrics.tasks_failed
+                                ),
+                                theme.accent
+                            ),
+                            theme.paint(
+                                &format!("🤖 API Calls: {}", metrics.total_api_calls),
+                                theme.highlight
+                            ),
+                            theme.paint(
+                                &format!("💰 Cost: ${:.4}", metrics.total_cost),
+                                theme.success
+                            ),
+                            theme.paint(
+                                &format!("📝 Artifacts: {}", metrics.artifacts_created),
+                                theme.success
+                            ),
+                            theme.paint(
+                                &format!(
+                                    "💾 Context: {:.0}% ({})",
+                                    metrics.current_context_usage, metrics.context_breakdown
+                                ),
+                                theme.text
+                            ),
                         );
                         bar.set_message(status);
                     }
@@ -147,9 +186,10 @@ impl EnhancedUI {
         let metrics = executor::block_on(async { self.last_metrics.read().await.clone() });
 
         println!();
-        println!("{}", "=".repeat(80).bright_blue());
-        println!("{}", "Session Summary".bright_white().bold());
-        println!("{}", "=".repeat(80).bright_blue());
+        let theme = self.theme;
+        println!("{}", theme.paint(&rule(), theme.border));
+        println!("{}", theme.paint("Session Summary", theme.title).bold());
+        println!("{}", theme.paint(&rule(), theme.border));
 
         let elapsed = self.start_time.elapsed();
         println!(
@@ -159,27 +199,27 @@ impl EnhancedUI {
         );
         println!(
             "✅ Tasks Completed: {}",
-            metrics.tasks_completed.to_string().bright_green()
+            theme.paint(&metrics.tasks_completed.to_string(), theme.success)
         );
         println!(
             "❌ Tasks Failed: {}",
-            metrics.tasks_failed.to_string().bright_red()
+            theme.paint(&metrics.tasks_failed.to_string(), theme.error)
         );
         println!(
             "🤖 Total API Calls: {}",
-            metrics.total_api_calls.to_string().bright_cyan()
+            theme.paint(&metrics.total_api_calls.to_string(), theme.accent)
         );
         println!(
             "🪙  Total Tokens: {}",
-            metrics.total_tokens.to_string().bright_cyan()
+            theme.paint(&metrics.total_tokens.to_string(), theme.accent)
         );
         println!(
             "💰 Total Cost: ${:.4}",
-            metrics.total_cost.to_string().bright_yellow()
+            theme.paint(&metrics.total_cost.to_string(), theme.success)
         );
         println!(
             "📝 Artifacts Created: {}",
-            metrics.artifacts_created.to_string().bright_magenta()
+            theme.paint(&metrics.artifacts_created.to_string(), theme.success)
         );
         println!();
 
@@ -196,26 +236,41 @@ impl EnhancedUI {
 
     #[allow(dead_code)]
     pub async fn display_task(&mut self, task: &str) -> Result<()> {
-        println!("{} {}", "▶ Task:".cyan().bold(), task.white());
+        let theme = &self.theme;
+        println!(
+            "{}",
+            labelled(
+                theme.paint("▶ Task:", theme.accent).bold(),
+                theme.paint(task, theme.text)
+            )
+        );
         Ok(())
     }
 
     pub async fn display_error(&mut self, error: &str) -> Result<()> {
-        println!("{} {}", "✗ Error:".red().bold(), error.white());
+        let theme = &self.theme;
+        println!(
+            "{}",
+            labelled(
+                theme.paint("✗ Error:", theme.error).bold(),
+                theme.paint(error, theme.text)
+            )
+        );
         Ok(())
     }
 
     async fn handle_event(
         event: Event,
-        _multi_progress: &MultiProgress,
+        multi_progress: &MultiProgress,
         main_progress: &Option<ProgressBar>,
         _metrics_bar: &Option<ProgressBar>,
         _last_metrics: &Arc<RwLock<Metrics>>,
+        theme: &Theme,
     ) {
         match event {
             Event::TaskStarted { description, .. } => {
                 if let Some(pb) = main_progress {
-                    pb.set_message(format!("🚀 {}", description));
+                    pb.set_message(fit_status(format!("🚀 {}", description)));
                     pb.set_position(0);
                 }
             }
@@ -224,34 +279,37 @@ impl EnhancedUI {
             } => {
                 if let Some(pb) = main_progress {
                     pb.set_position(progress as u64);
-                    pb.set_message(format!("⚡ {}", message));
+                    pb.set_message(fit_status(format!("⚡ {}", message)));
                 }
             }
             Event::TaskCompleted { result, .. } => {
                 if let Some(pb) = main_progress {
                     pb.set_position(100);
-                    pb.set_message(format!("✅ {}", result));
+                    pb.set_message(fit_status(format!("✅ {}", result)));
                 }
             }
             Event::TaskFailed { error, .. } => {
                 if let Some(pb) = main_progress {
-                    pb.set_message(format!("❌ {}", error.bright_red()));
+                    pb.set_message(fit_status(format!("❌ {}", theme.paint(&error, theme.error))));
                 }
             }
             Event::ExecutionStarted { environment } => {
                 if let Some(pb) = main_progress {
-                    pb.set_message(format!("🔧 Executing in {}", environment));
+                    pb.set_message(fit_status(format!("🔧 Executing in {}", environment)));
                 }
             }
             Event::ExecutionProgress { step, progress } => {
                 if let Some(pb) = main_progress {
                     pb.set_position(progress as u64);
-                    pb.set_message(format!("🔨 {}", step));
+                    pb.set_message(fit_status(format!("🔨 {}", step)));
                 }
             }
             Event::DependencyInstalling { package } => {
                 if let Some(pb) = main_progress {
-                    pb.set_message(format!("📦 Installing {}", package.bright_cyan()));
+                    pb.set_message(fit_status(format!(
+                        "📦 Installing {}",
+                        theme.paint(&package, theme.accent)
+                    )));
                 }
             }
             Event::ArtifactCreated {
@@ -260,22 +318,213 @@ impl EnhancedUI {
                 ..
             } => {
                 if let Some(pb) = main_progress {
-                    pb.set_message(format!(
+                    pb.set_message(fit_status(format!(
                         "📄 Created {} ({})",
-                        name.bright_green(),
+                        theme.paint(&name, theme.success),
                         artifact_type
-                    ));
+                    )));
+                }
+            }
+            Event::ArtifactUpdated { name, version, .. } => {
+                if let Some(pb) = main_progress {
+                    pb.set_message(fit_status(format!(
+                        "📄 Updated {} (v{})",
+                        theme.paint(&name, theme.success),
+                        version
+                    )));
+                }
+            }
+            Event::ArtifactModifiedExternally { name, version, .. } => {
+                if let Some(pb) = main_progress {
+                    pb.set_message(fit_status(format!(
+                        "✏️  {} edited on disk (v{})",
+                        theme.paint(&name, theme.warning),
+                        version
+                    )));
                 }
             }
             Event::APICallStarted { provider, model } => {
                 if let Some(pb) = main_progress {
-                    pb.set_message(format!("🤖 Calling {} ({})", provider.bright_cyan(), model));
+                    pb.set_message(fit_status(format!(
+                        "🤖 Calling {} ({})",
+                        theme.paint(&provider, theme.accent),
+                        model
+                    )));
                 }
             }
+            Event::BudgetThreshold {
+                pct,
+                kind,
+                used,
+                limit,
+            } => {
+                let message = format!(
+                    "⚠️  Budget: {}% of {} budget used ({} of {})",
+                    pct,
+                    kind,
+                    kind.format_amount(used),
+                    kind.format_amount(limit)
+                );
+                let color = if pct >= 100 {
+                    theme.error
+                } else {
+                    theme.warning
+                };
+                let message = theme.paint(&message, color).bold();
+                // Printed above the progress bars so it stays on screen
+                let _ = multi_progress.println(message.to_string());
+            }
             _ => {}
         }
     }
 }
 
+fn terminal_width() -> usize {
+    crossterm::terminal::size()
+        .map(|(width, _)| width as usize)
+        .unwrap_or(80)
+}
+
+/// Horizontal rule across the terminal, up to 80 columns
+fn rule() -> String {
+    "=".repeat(terminal_width().min(80))
+}
+
+/// Status message of the main progress bar, cut to leave the bar room on the
+/// line; longer messages would wrap and break the redraw
+fn fit_status(message: String) -> String {
+    truncate(&message, terminal_width() / 2)
+}
+
+/// `label` followed by `text` wrapped to the terminal, continuation lines
+/// lined up under the first
+fn labelled(label: ColoredString, text: ColoredString) -> String {
+    let label = label.to_string();
+    let indent = display_width(&label) + 1;
+    let width = terminal_width().saturating_sub(indent).max(20);
+    let separator = format!("\n{}", " ".repeat(indent));
+    format!(
+        "{} {}",
+        label,
+        wrap(&text.to_string(), width).join(&separator)
+    )
+}
+
+/// The current plan shown above the main progress bar, one line per step
+struct PlanChecklist {
+    theme: Theme,
+    plan: PlanProgress,
+    title: Option<ProgressBar>,
+    /// Each step's line and the state it was last drawn in
+    lines: Vec<(ProgressBar, Option<StepState>)>,
+}
+
+impl PlanChecklist {
+    fn new(theme: Theme) -> Self {
+        Self {
+            theme,
+            plan: PlanProgress::default(),
+            title: None,
+            lines: Vec::new(),
+        }
+    }
+
+    fn apply(
+        &mut self,
+        event: &Event,
+        multi_progress: &MultiProgress,
+        main_progress: &Option<ProgressBar>,
+    ) {
+        if !self.plan.apply(event) {
+            return;
+        }
+
+        let new_plan = matches!(
+            event,
+            Event::Custom { event_type, .. } if event_type == PLAN_EXECUTION_STARTED_EVENT
+        );
+        if new_plan || self.lines.len() != self.plan.steps.len() {
+            for (line, _) in self.lines.drain(..) {
+                line.finish_and_clear();
+            }
+            let add = |bar: ProgressBar| match main_progress {
+                Some(main_progress) => multi_progress.insert_before(main_progress, bar),
+                None => multi_progress.add(bar),
+            };
+            self.title.get_or_insert_with(|| {
+                let title = add(ProgressBar::new_spinner());
+                title.set_style(ProgressStyle::default_spinner().template("{msg}").unwrap());
+                title
+            });
+            for _ in &self.plan.steps {
+                self.lines.push((add(ProgressBar::new_spinner()), None));
+            }
+        }
+
+        let theme = &self.theme;
+        if let Some(title) = &self.title {
+            title.set_message(format!(
+                "{} {}",
+                theme
+                    .paint(
+                        &format!("📋 Plan (iteration {})", self.plan.iteration),
+                        theme.title
+                    )
+                    .bold(),
+                theme.paint(
+                    &format!("{}/{} steps", self.plan.finished(), self.plan.steps.len()),
+                    theme.muted
+                )
+            ));
+        }
+        for (step, (line, drawn)) in self.plan.steps.iter().zip(self.lines.iter_mut()) {
+            if *drawn != Some(step.state) {
+                draw_step(step, line, theme);
+                *drawn = Some(step.state);
+            }
+        }
+    }
+}
+
+/// A running step spins and counts its time; the others show how they ended
+fn draw_step(step: &PlanStep, line: &ProgressBar, theme: &Theme) {
+    let description = format!("{} {}", step.category_icon(), step.description);
+    if step.state == StepState::Running {
+        line.set_style(
+            ProgressStyle::default_spinner()
+                .template(&format!(
+                    "  {} {{wide_msg}} {}",
+                    theme.placeholder("spinner", theme.highlight, None),
+                    theme.placeholder("elapsed", theme.muted, None)
+                ))
+                .unwrap(),
+        );
+        line.reset_elapsed();
+        line.set_message(theme.paint(&description, theme.text).to_string());
+        line.enable_steady_tick(Duration::from_millis(100));
+        return;
+    }
+
+    line.disable_steady_tick();
+    line.set_style(
+        ProgressStyle::default_spinner()
+            .template("  {wide_msg} {prefix}")
+            .unwrap(),
+    );
+    let (icon, text) = match step.state {
+        StepState::Done => (theme.success, theme.text),
+        StepState::Failed => (theme.error, theme.error),
+        _ => (theme.muted, theme.muted),
+    };
+    let icon = theme.paint(step.state.icon(), icon);
+    let description = theme.paint(&description, text);
+    let elapsed = step
+        .elapsed()
+        .map(|elapsed| theme.paint(&format_elapsed(elapsed), theme.muted).to_string())
+        .unwrap_or_default();
+    line.set_prefix(elapsed);
+    line.set_message(format!("{} {}", icon, description));
+}
+
 // Implement EventEmitter trait for EnhancedUI
 impl_event_emitter!(EnhancedUI);
@@ -0,0 +1,304 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result, bail};
+use axum::Router;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use log::warn;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::event_bus::{Event, EventBus, EventSink};
+use crate::event_log::EventRecord;
+use crate::serve;
+
+/// The whole web UI: plan, logs, reasoning, artifact diffs and cost
+const PAGE: &str = include_str!("web_dashboard.html");
+/// Events kept so browsers that connect mid-run can catch up
+const MAX_HISTORY: usize = 10_000;
+
+/// Address given to `--serve`: a port on localhost, or `host:port` to listen
+/// on another interface
+pub fn parse_address(value: &str) -> Result<SocketAddr> {
+    if let Ok(port) = value.parse::<u16>() {
+        return Ok(SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+    value
+        .parse()
+        .with_context(|| format!("Invalid address '{}'; expected PORT or HOST:PORT", value))
+}
+
+/// Events of the run so far, as JSON, and the channel new ones go out on
+pub(crate) struct EventFeed {
+    history: Mutex<VecDeque<String>>,
+    live: broadcast::Sender<String>,
+}
+
+impl EventFeed {
+    pub(crate) fn new() -> Self {
+        Self {
+            history: Mutex::new(VecDeque::new()),
+            live: broadcast::channel(1000).0,
+        }
+    }
+
+    /// Events so far and a receiver for every later one
+    pub(crate) fn subscribe(&self) -> (Vec<String>, broadcast::Receiver<String>) {
+        // Subscribing under the lock means no event is missed or sent twice
+        let history = self.history.lock().unwrap();
+        (history.iter().cloned().collect(), self.live.subscribe())
+    }
+}
+
+impl EventSink for EventFeed {
+    fn handle(&self, event: &Event) {
+        let record = EventRecord {
+            timestamp: chrono::Utc::now(),
+            event: event.clone(),
+        };
+        let Ok(json) = serde_json::to_string(&record) else {
+            return;
+        };
+        let mut history = self.history.lock().unwrap();
+        if history.len() >= MAX_HISTORY {
+            history.pop_front();
+        }
+        history.push_back(json.clone());
+        let _ = self.live.send(json);
+    }
+}
+
+/// Web dashboard streaming the run's events to browsers over a WebSocket.
+/// Stops serving when dropped.
+pub struct WebDashboard {
+    /// Address of the page, with the token the event stream asks for
+    pub url: String,
+    task: JoinHandle<()>,
+}
+
+/// What the event stream checks of each browser connecting to it
+struct Dashboard {
+    feed: Arc<EventFeed>,
+    address: SocketAddr,
+    token: String,
+}
+
+#[derive(serde::Deserialize)]
+struct EventsQuery {
+    token: Option<String>,
+}
+
+impl Drop for WebDashboard {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Serve the web dashboard on `address`, recording events from `bus` from now on.
+/// Browsers need `token` to follow the run, or one made up for this run; other
+/// machines can only be let in with a configured token.
+pub async fn serve(
+    address: SocketAddr,
+    token: Option<String>,
+    bus: &EventBus,
+) -> Result<WebDashboard> {
+    if !address.ip().is_loopback() && token.is_none() {
+        bail!(
+            "Refusing to serve the web dashboard on {} without a token; set CLI_ENGINEER_API_TOKEN \
+             or serve.api_token, or listen on localhost",
+            address
+        );
+    }
+    let token = token.unwrap_or_else(serve::generate_token);
+    let listener = tokio::net::TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to listen on {}", address))?;
+    let address = listener.local_addr()?;
+
+    let feed = Arc::new(EventFeed::new());
+    bus.add_sink(feed.clone());
+    let url = format!("http://{}/?token={}", address, token);
+    let dashboard = Arc::new(Dashboard {
+        feed,
+        address,
+        token,
+    });
+    let app = Router::new()
+        .route("/", get(|| async { Html(PAGE) }))
+        .route("/events", get(events))
+        .with_state(dashboard);
+
+    let task = tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            warn!("Web dashboard stopped: {}", e);
+        }
+    });
+    Ok(WebDashboard { url, task })
+}
+
+/// `GET /events?token=...`: only the dashboard's own page, opened with the
+/// run's token, may follow the run
+async fn events(
+    socket: WebSocketUpgrade,
+    State(dashboard): State<Arc<Dashboard>>,
+    headers: HeaderMap,
+    Query(query): Query<EventsQuery>,
+) -> Response {
+    if !serve::same_origin(dashboard.address, &headers) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let authorized = query
+        .token
+        .is_some_and(|token| serve::token_matches(&token, &dashboard.token));
+    if !authorized {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let feed = dashboard.feed.clone();
+    socket.on_upgrade(move |socket| stream_events(socket, feed))
+}
+
+/// Send the run so far, then each event as it happens, as `EventRecord` JSON
+async fn stream_events(mut socket: WebSocket, feed: Arc<EventFeed>) {
+    let (history, mut live) = feed.subscribe();
+    for json in history {
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        let json = tokio::select! {
+            event = live.recv() => match event {
+                Ok(json) => json,
+                Err(RecvError::Lagged(missed)) => serde_json::json!({
+                    "error": format!("{} events were dropped because the browser fell behind", missed)
+                })
+                .to_string(),
+                Err(RecvError::Closed) => break,
+            },
+            // Browsers only ever send pings and the close handshake
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn start(token: &str) -> (WebDashboard, String) {
+        let bus = EventBus::new(100);
+        let dashboard = serve(parse_address("0").unwrap(), Some(token.to_string()), &bus)
+            .await
+            .unwrap();
+        let host = dashboard.url["http://".len()..]
+            .split('/')
+            .next()
+            .unwrap()
+            .to_string();
+        (dashboard, host)
+    }
+
+    /// Status line of a request for `path` with `headers`
+    async fn status(host: &str, path: &str, headers: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(host).await.unwrap();
+        let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n{}\r\n", path, host, headers);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = vec![0; 1024];
+        let read = stream.read(&mut response).await.unwrap();
+        let response = String::from_utf8_lossy(&response[..read]).to_string();
+        response.lines().next().unwrap_or_default().to_string()
+    }
+
+    const UPGRADE: &str = "Connection: Upgrade\r\nUpgrade: websocket\r\n\
+        Sec-WebSocket-Version: 13\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n";
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address("8080").unwrap(),
+            "127.0.0.1:8080".parse().unwrap()
+        );
+        assert_eq!(
+            parse_address("0.0.0.0:9000").unwrap(),
+            "0.0.0.0:9000".parse().unwrap()
+        );
+        assert!(parse_address("localhost").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_page_is_served() {
+        let (dashboard, host) = start("secret").await;
+        assert!(dashboard.url.ends_with("/?token=secret"));
+        let mut stream = tokio::net::TcpStream::connect(&host).await.unwrap();
+        let request = format!(
+            "GET / HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n",
+            host
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut page = String::new();
+        stream.read_to_string(&mut page).await.unwrap();
+        assert!(page.starts_with("HTTP/1.1 200") && page.contains("new WebSocket"));
+    }
+
+    #[tokio::test]
+    async fn test_events_need_the_token() {
+        let (_dashboard, host) = start("secret").await;
+        let status_of = |path| status(&host, path, UPGRADE);
+        assert!(status_of("/events?token=secret").await.contains("101"));
+        assert!(status_of("/events").await.contains("401"));
+        assert!(status_of("/events?token=guess").await.contains("401"));
+    }
+
+    #[tokio::test]
+    async fn test_events_refuse_other_origins() {
+        let (_dashboard, host) = start("secret").await;
+        let own = format!("{}Origin: http://{}\r\n", UPGRADE, host);
+        assert!(
+            status(&host, "/events?token=secret", &own)
+                .await
+                .contains("101")
+        );
+        let foreign = format!("{}Origin: https://evil.example\r\n", UPGRADE);
+        let refused = status(&host, "/events?token=secret", &foreign).await;
+        assert!(refused.contains("403"));
+    }
+
+    #[tokio::test]
+    async fn test_other_machines_need_a_configured_token() {
+        let bus = EventBus::new(100);
+        let error = serve(parse_address("0.0.0.0:0").unwrap(), None, &bus)
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("without a token"));
+        // On localhost a token is made up for the run
+        let dashboard = serve(parse_address("0").unwrap(), None, &bus)
+            .await
+            .unwrap();
+        assert!(dashboard.url.contains("?token="));
+    }
+
+    #[tokio::test]
+    async fn test_browsers_connecting_mid_run_catch_up() {
+        let feed = EventFeed::new();
+        feed.handle(&Event::SystemReady);
+        let (history, mut live) = feed.subscribe();
+        feed.handle(&Event::ShutdownRequested);
+        assert_eq!(history.len(), 1);
+        assert!(history[0].contains("SystemReady"));
+        assert!(live.recv().await.unwrap().contains("ShutdownRequested"));
+    }
+}